@@ -0,0 +1,60 @@
+//! Records build metadata for `build_info()`
+//!
+//! Only inputs that are fixed for a given source tree and toolchain are
+//! recorded (no timestamps or host paths), so reproducible builds embed
+//! identical metadata.
+
+use std::process::Command;
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-env-changed=COLDSTAR_GIT_COMMIT");
+
+    // Source tarballs have no .git; release tooling passes the commit instead
+    let commit = std::env::var("COLDSTAR_GIT_COMMIT")
+        .ok()
+        .filter(|c| !c.is_empty())
+        .or_else(git_commit)
+        .unwrap_or_else(|| "unknown".to_string());
+
+    let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    let rustc_version = Command::new(rustc)
+        .arg("--version")
+        .output()
+        .ok()
+        .and_then(|out| String::from_utf8(out.stdout).ok())
+        .map(|v| v.trim().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+
+    let mut features: Vec<String> = std::env::vars()
+        .filter_map(|(key, _)| key.strip_prefix("CARGO_FEATURE_").map(|f| f.to_lowercase().replace('_', "-")))
+        .collect();
+    features.sort();
+
+    println!("cargo:rustc-env=COLDSTAR_BUILD_GIT_COMMIT={}", commit);
+    println!("cargo:rustc-env=COLDSTAR_BUILD_RUSTC={}", rustc_version);
+    println!("cargo:rustc-env=COLDSTAR_BUILD_TARGET={}", std::env::var("TARGET").unwrap_or_default());
+    println!("cargo:rustc-env=COLDSTAR_BUILD_PROFILE={}", std::env::var("PROFILE").unwrap_or_default());
+    println!("cargo:rustc-env=COLDSTAR_BUILD_FEATURES={}", features.join(","));
+}
+
+fn git_commit() -> Option<String> {
+    let git = |args: &[&str]| {
+        Command::new("git")
+            .args(args)
+            .output()
+            .ok()
+            .filter(|out| out.status.success())
+            .and_then(|out| String::from_utf8(out.stdout).ok())
+            .map(|s| s.trim().to_string())
+    };
+
+    // Rebuild when HEAD moves (checkout, commit)
+    if let Some(git_dir) = git(&["rev-parse", "--absolute-git-dir"]) {
+        println!("cargo:rerun-if-changed={}/HEAD", git_dir);
+        println!("cargo:rerun-if-changed={}/refs", git_dir);
+        println!("cargo:rerun-if-changed={}/packed-refs", git_dir);
+    }
+
+    git(&["rev-parse", "HEAD"])
+}
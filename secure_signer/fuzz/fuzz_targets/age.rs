@@ -0,0 +1,18 @@
+#![no_main]
+
+use std::sync::OnceLock;
+
+use coldstar_secure_signer::age::{generate_age_identity, AgeIdentity};
+use coldstar_secure_signer::crypto::set_locking_mode;
+use coldstar_secure_signer::{EncryptedKeyContainer, LockingMode};
+use libfuzzer_sys::fuzz_target;
+
+static IDENTITY: OnceLock<String> = OnceLock::new();
+
+fuzz_target!(|data: &[u8]| {
+    set_locking_mode(LockingMode::Permissive);
+    let identity = IDENTITY.get_or_init(|| generate_age_identity().expect("identity").0.to_string());
+    // Not a passphrase: an scrypt stanza may ask for up to 4 GiB, past
+    // libFuzzer's RSS limit
+    let _ = EncryptedKeyContainer::from_age(data, &AgeIdentity::X25519(identity), "fuzz");
+});
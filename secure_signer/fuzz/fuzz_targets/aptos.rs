@@ -0,0 +1,11 @@
+#![no_main]
+
+use coldstar_secure_signer::aptos::sign_aptos;
+use coldstar_secure_signer::crypto::set_locking_mode;
+use coldstar_secure_signer::LockingMode;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    set_locking_mode(LockingMode::Permissive);
+    let _ = sign_aptos(&[7u8; 32], data);
+});
@@ -0,0 +1,10 @@
+#![no_main]
+
+use coldstar_secure_signer::bitcoin::{Psbt, Transaction, TxOut};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = Transaction::parse(data);
+    let _ = TxOut::parse(data);
+    let _ = Psbt::parse(data);
+});
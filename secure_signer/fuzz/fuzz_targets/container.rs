@@ -0,0 +1,11 @@
+#![no_main]
+
+use coldstar_secure_signer::EncryptedKeyContainer;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(container) = EncryptedKeyContainer::from_bytes(data) {
+        // Whatever parses must encode again
+        let _ = container.to_bytes();
+    }
+});
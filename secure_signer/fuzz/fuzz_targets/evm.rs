@@ -0,0 +1,15 @@
+#![no_main]
+
+use coldstar_secure_signer::evm::{decode_nft_call, ChainProfile, EvmTransaction, TokenCall};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = decode_nft_call(data);
+    if let Some(call) = TokenCall::decode(data) {
+        assert_eq!(TokenCall::decode(&call.calldata()), Some(call));
+    }
+    if let Ok(transaction) = serde_json::from_slice::<EvmTransaction>(data) {
+        let _ = ChainProfile::for_chain_id(transaction.chain_id).check(&transaction);
+        let _ = transaction.unsigned_bytes();
+    }
+});
@@ -0,0 +1,12 @@
+#![no_main]
+
+use coldstar_secure_signer::crypto::set_locking_mode;
+use coldstar_secure_signer::{EncryptedKeyContainer, LockingMode};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    set_locking_mode(LockingMode::Permissive);
+    if let Ok(json) = std::str::from_utf8(data) {
+        let _ = EncryptedKeyContainer::from_keystore_v3(json, "fuzz");
+    }
+});
@@ -0,0 +1,17 @@
+#![no_main]
+
+use coldstar_secure_signer::musig::{AggregateNonce, PartialSignature, PublicNonce};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(nonce) = PublicNonce::from_bytes(data) {
+        assert_eq!(nonce.to_bytes()[..], data[..]);
+        let _ = AggregateNonce::aggregate(&[nonce]);
+    }
+    if let Ok(nonce) = AggregateNonce::from_bytes(data) {
+        assert_eq!(nonce.to_bytes()[..], data[..]);
+    }
+    if let Ok(signature) = PartialSignature::from_bytes(data) {
+        assert_eq!(signature.to_bytes()[..], data[..]);
+    }
+});
@@ -0,0 +1,13 @@
+#![no_main]
+
+use coldstar_secure_signer::solana::{decode_transaction, SolanaMessage, SolanaTransaction};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(message) = SolanaMessage::parse(data) {
+        // Whatever parses must round-trip
+        assert_eq!(SolanaMessage::parse(&message.serialize()).ok(), Some(message));
+    }
+    let _ = SolanaTransaction::parse(data);
+    let _ = decode_transaction(data);
+});
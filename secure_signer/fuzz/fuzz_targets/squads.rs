@@ -0,0 +1,9 @@
+#![no_main]
+
+use coldstar_secure_signer::solana::squads::VaultTransactionMessage;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = VaultTransactionMessage::parse(data);
+    let _ = VaultTransactionMessage::from_account_data(data);
+});
@@ -0,0 +1,10 @@
+#![no_main]
+
+use coldstar_secure_signer::stellar::StellarEnvelope;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(envelope) = StellarEnvelope::from_xdr(data) {
+        let _ = envelope.hash("Test SDF Network ; September 2015");
+    }
+});
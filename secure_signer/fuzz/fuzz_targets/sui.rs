@@ -0,0 +1,12 @@
+#![no_main]
+
+use coldstar_secure_signer::sui::{sign_sui, transaction_digest};
+use coldstar_secure_signer::crypto::set_locking_mode;
+use coldstar_secure_signer::LockingMode;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    set_locking_mode(LockingMode::Permissive);
+    let _ = transaction_digest(data);
+    let _ = sign_sui(&[7u8; 32], data);
+});
@@ -0,0 +1,10 @@
+#![no_main]
+
+use coldstar_secure_signer::ton::Cell;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(cell) = Cell::from_boc(data) {
+        let _ = cell.to_boc();
+    }
+});
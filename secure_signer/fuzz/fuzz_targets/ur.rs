@@ -0,0 +1,18 @@
+#![no_main]
+
+use coldstar_secure_signer::ur::UrDecoder;
+use coldstar_secure_signer::EncryptedKeyContainer;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    // One scanned frame per line
+    let mut decoder = UrDecoder::new();
+    for frame in String::from_utf8_lossy(data).lines() {
+        let _ = decoder.receive(frame);
+        let _ = decoder.progress();
+    }
+    if let Some(ur) = decoder.result() {
+        let _ = ur.decode::<Vec<u8>>();
+        let _ = ur.decode::<EncryptedKeyContainer>();
+    }
+});
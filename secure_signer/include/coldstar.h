@@ -0,0 +1,418 @@
+/* coldstar secure signer: versioned C ABI. See src/ffi/mod.rs. */
+
+#ifndef COLDSTAR_H
+#define COLDSTAR_H
+
+/* Generated by cbindgen from src/ffi/mod.rs - do not edit. */
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+// Version of the `coldstar_*` ABI described by `include/coldstar.h`
+#define COLDSTAR_ABI_VERSION 1
+
+// Size of an Ed25519 signature
+#define COLDSTAR_ED25519_SIGNATURE_SIZE 64
+
+// Size of an Ed25519 public key
+#define COLDSTAR_ED25519_PUBLIC_KEY_SIZE 32
+
+// Size of an EVM signature (r || s || v)
+#define COLDSTAR_EVM_SIGNATURE_SIZE 65
+
+// Size of an EVM address
+#define COLDSTAR_EVM_ADDRESS_SIZE 20
+
+// Size of the hash signed by `coldstar_sign_evm_hash`
+#define COLDSTAR_EVM_HASH_SIZE 32
+
+// Size of an Ed25519 signature written by the raw functions
+#define SIGNER_SIGNATURE_SIZE 64
+
+// Size of an Ed25519 public key written by the raw functions
+#define SIGNER_PUBLIC_KEY_SIZE 32
+
+// Size of an EVM signature (r || s || v) written by the raw functions
+#define SIGNER_EVM_SIGNATURE_SIZE 65
+
+// Status returned by every fallible `coldstar_*` function
+//
+// Values below 100 are argument errors detected at the boundary; values
+// from 100 mirror the variants of [`SignerError`] one to one. New values
+// may be added without an ABI version change, so treat unknown values as
+// generic failures.
+enum ColdstarStatus
+#if defined(__cplusplus) || __STDC_VERSION__ >= 202311L
+  : int32_t
+#endif // defined(__cplusplus) || __STDC_VERSION__ >= 202311L
+ {
+  COLDSTAR_STATUS_OK = 0,
+  // A required pointer argument was null
+  COLDSTAR_STATUS_NULL_POINTER = 1,
+  // A string argument was not valid UTF-8
+  COLDSTAR_STATUS_INVALID_UTF8 = 2,
+  // The library panicked; the call was abandoned and wrote no output
+  COLDSTAR_STATUS_PANIC = 3,
+  COLDSTAR_STATUS_MEMORY_LOCK_FAILED = 100,
+  COLDSTAR_STATUS_KEY_DERIVATION_FAILED = 101,
+  // Wrong passphrase or corrupted container
+  COLDSTAR_STATUS_DECRYPTION_FAILED = 102,
+  COLDSTAR_STATUS_INVALID_KEY_FORMAT = 103,
+  COLDSTAR_STATUS_SIGNING_FAILED = 104,
+  COLDSTAR_STATUS_INVALID_TRANSACTION = 105,
+  COLDSTAR_STATUS_PARSE_ERROR = 106,
+  COLDSTAR_STATUS_SERIALIZATION_ERROR = 107,
+  COLDSTAR_STATUS_BASE58_ERROR = 108,
+  COLDSTAR_STATUS_BASE64_ERROR = 109,
+  COLDSTAR_STATUS_CONTAINER_ERROR = 110,
+  COLDSTAR_STATUS_IO_ERROR = 111,
+  COLDSTAR_STATUS_BROADCAST_ERROR = 112,
+  COLDSTAR_STATUS_POLICY_VIOLATION = 113,
+  COLDSTAR_STATUS_BACKEND_ERROR = 114,
+  COLDSTAR_STATUS_DERIVATION_ERROR = 115,
+  COLDSTAR_STATUS_CEREMONY_ERROR = 116,
+  COLDSTAR_STATUS_FEE_ESTIMATION_ERROR = 117,
+  COLDSTAR_STATUS_PLATFORM_ERROR = 118,
+  COLDSTAR_STATUS_INTEGRITY_ERROR = 119,
+  COLDSTAR_STATUS_KMS_ERROR = 120,
+  COLDSTAR_STATUS_AUDIT_ERROR = 121,
+  COLDSTAR_STATUS_ENTROPY_ERROR = 122,
+  COLDSTAR_STATUS_SESSION_LOCKED = 123,
+  COLDSTAR_STATUS_IDEMPOTENCY_CONFLICT = 124,
+  COLDSTAR_STATUS_CONTAINER_NOT_VALID = 125,
+  COLDSTAR_STATUS_ROTATION_ERROR = 126,
+  COLDSTAR_STATUS_ATTESTATION_ERROR = 127,
+};
+#ifndef __cplusplus
+#if __STDC_VERSION__ >= 202311L
+typedef enum ColdstarStatus ColdstarStatus;
+#else
+typedef int32_t ColdstarStatus;
+#endif // __STDC_VERSION__ >= 202311L
+#endif // __cplusplus
+
+// Bytes allocated by the library; release with `coldstar_free_buffer`
+typedef struct ColdstarBuffer {
+  uint8_t *data;
+  uintptr_t len;
+} ColdstarBuffer;
+
+// Output of `coldstar_sign_solana`; release with
+// `coldstar_free_solana_signature`
+typedef struct ColdstarSolanaSignature {
+  uint8_t signature[COLDSTAR_ED25519_SIGNATURE_SIZE];
+  uint8_t public_key[COLDSTAR_ED25519_PUBLIC_KEY_SIZE];
+  // The transaction with the signature inserted
+  struct ColdstarBuffer signed_transaction;
+} ColdstarSolanaSignature;
+
+// Output of `coldstar_sign_evm_hash`; owns no memory
+typedef struct ColdstarEvmSignature {
+  // r || s || v, with v = 27 or 28
+  uint8_t signature[COLDSTAR_EVM_SIGNATURE_SIZE];
+  uint8_t address[COLDSTAR_EVM_ADDRESS_SIZE];
+} ColdstarEvmSignature;
+
+// Result code for FFI operations
+typedef struct SignerResult {
+  // 0 for success, non-zero for error
+  int32_t error_code;
+  // Result string (JSON for success, error message for failure)
+  // Must be freed with free_string()
+  char *result;
+} SignerResult;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// The ABI version this library implements ([`COLDSTAR_ABI_VERSION`])
+uint32_t coldstar_abi_version(void);
+
+// The crate version, as a static string that must not be freed
+const char *coldstar_version(void);
+
+// Message for the last failed call on this thread, or null
+//
+// The pointer stays valid until the next `coldstar_*` call on the same
+// thread; copy the string to keep it. Do not free it.
+const char *coldstar_last_error_message(void);
+
+// Whether secure buffers can be locked in memory on this system
+bool coldstar_mlock_supported(void);
+
+// Build metadata as a JSON object
+//
+// # Safety
+// `json_out` must be writable. The string written to it must be released
+// with `coldstar_free_string`.
+ColdstarStatus coldstar_build_info(char **json_out);
+
+// Encrypt a private key into a new container
+//
+// # Arguments
+// * `private_key` / `private_key_len` - 32-byte seed, or 64-byte keypair
+//   (seed || public key) whose public key must match the seed
+// * `passphrase` - Null-terminated UTF-8 passphrase
+// * `container_json_out` - Receives the container JSON
+//
+// # Safety
+// `private_key` must point to `private_key_len` readable bytes,
+// `passphrase` must be a valid C string and `container_json_out` must be
+// writable. The string written to it must be released with
+// `coldstar_free_string`.
+ColdstarStatus coldstar_create_container(const uint8_t *private_key,
+                                         uintptr_t private_key_len,
+                                         const char *passphrase,
+                                         char **container_json_out);
+
+// Decrypt a container and sign a Solana transaction
+//
+// # Arguments
+// * `container_json` - Null-terminated container JSON
+// * `passphrase` - Null-terminated UTF-8 passphrase
+// * `message` / `message_len` - Serialized unsigned transaction message;
+//   anything else, or a message this key is not a required signer of,
+//   fails with `COLDSTAR_STATUS_INVALID_TRANSACTION`
+// * `signature_out` - Receives the signature, public key and signed
+//   transaction
+//
+// # Safety
+// The strings must be valid C strings, `message` must point to
+// `message_len` readable bytes and `signature_out` must be writable.
+// On success, `signature_out` must be released with
+// `coldstar_free_solana_signature`.
+ColdstarStatus coldstar_sign_solana(const char *container_json,
+                                    const char *passphrase,
+                                    const uint8_t *message,
+                                    uintptr_t message_len,
+                                    struct ColdstarSolanaSignature *signature_out);
+
+// Decrypt a container and sign a 32-byte EVM hash (secp256k1)
+//
+// # Arguments
+// * `container_json` - Null-terminated container JSON
+// * `passphrase` - Null-terminated UTF-8 passphrase
+// * `hash` - `COLDSTAR_EVM_HASH_SIZE` bytes, normally a keccak256 digest
+// * `signature_out` - Receives the signature and signing address
+//
+// # Safety
+// The strings must be valid C strings, `hash` must point to
+// `COLDSTAR_EVM_HASH_SIZE` readable bytes and `signature_out` must be
+// writable.
+ColdstarStatus coldstar_sign_evm_hash(const char *container_json,
+                                      const char *passphrase,
+                                      const uint8_t *hash,
+                                      struct ColdstarEvmSignature *signature_out);
+
+// Release a string returned through an out-parameter
+//
+// # Safety
+// `string` must be null or a string from a `coldstar_*` function that has
+// not been freed yet.
+void coldstar_free_string(char *string);
+
+// Release a buffer and reset it to empty, so freeing twice is harmless
+//
+// # Safety
+// `buffer` must be null or point to a buffer filled in by a `coldstar_*`
+// function.
+void coldstar_free_buffer(struct ColdstarBuffer *buffer);
+
+// Release the memory owned by a `ColdstarSolanaSignature`
+//
+// # Safety
+// `signature` must be null or point to a value filled in by
+// `coldstar_sign_solana`.
+void coldstar_free_solana_signature(struct ColdstarSolanaSignature *signature);
+
+// Create an encrypted key container from a private key
+//
+// # Arguments
+// * `private_key_b58` - Base58-encoded private key (32 or 64 bytes)
+// * `passphrase` - Null-terminated passphrase string
+//
+// # Returns
+// SignerResult with JSON container on success
+//
+// # Safety
+// All pointers must be valid, null-terminated C strings.
+struct SignerResult signer_create_container(const char *private_key_b58, const char *passphrase);
+
+// Decrypt a key container and sign a transaction
+//
+// # Arguments
+// * `container_json` - Null-terminated JSON string of the encrypted container
+// * `passphrase` - Null-terminated passphrase string
+// * `transaction_b64` - Base64-encoded unsigned transaction bytes
+//
+// # Returns
+// SignerResult with JSON signing result on success
+//
+// # Safety
+// All pointers must be valid, null-terminated C strings.
+struct SignerResult signer_sign_transaction(const char *container_json,
+                                            const char *passphrase,
+                                            const char *transaction_b64);
+
+// Decrypt a key container and sign a transaction, choosing the output
+// encodings
+//
+// # Arguments
+// * `container_json` - Null-terminated JSON string of the encrypted container
+// * `passphrase` - Null-terminated passphrase string
+// * `transaction_b64` - Base64-encoded unsigned transaction bytes
+// * `encoding_json` - JSON object such as `{"signature":"hex","public_key":"hex"}`
+//   (fields: `signature`, `public_key`, `transaction`; values: `base58`,
+//   `base64`, `hex`); missing fields and a null pointer use the defaults
+//
+// # Returns
+// SignerResult with JSON signing result on success
+//
+// # Safety
+// All non-null pointers must be valid, null-terminated C strings.
+struct SignerResult signer_sign_transaction_encoded(const char *container_json,
+                                                    const char *passphrase,
+                                                    const char *transaction_b64,
+                                                    const char *encoding_json);
+
+// Sign a Solana transaction directly with a base58-encoded private key
+//
+// # Security Warning
+// This function accepts a plaintext private key. Prefer using
+// signer_sign_transaction with an encrypted container for better security.
+//
+// # Arguments
+// * `private_key_b58` - Base58-encoded private key
+// * `message_b64` - Base64-encoded unsigned transaction message; anything
+//   else fails with error code 4
+//
+// # Returns
+// SignerResult with JSON signing result on success
+//
+// # Safety
+// All pointers must be valid, null-terminated C strings.
+struct SignerResult signer_sign_direct(const char *private_key_b58, const char *message_b64);
+
+// Decrypt a key container and sign an EVM transaction hash (secp256k1)
+//
+// # Arguments
+// * `container_json` - Null-terminated JSON string of the encrypted container
+// * `passphrase` - Null-terminated passphrase string
+// * `message_hash_hex` - Hex-encoded 32-byte keccak256 hash (with or without 0x prefix)
+//
+// # Returns
+// SignerResult with JSON EVMSigningResult on success
+//
+// # Safety
+// All pointers must be valid, null-terminated C strings.
+struct SignerResult signer_sign_evm_transaction(const char *container_json,
+                                                const char *passphrase,
+                                                const char *message_hash_hex);
+
+// Decrypt a key container and sign raw transaction message bytes
+//
+// # Arguments
+// * `container_json` - Null-terminated JSON string of the encrypted container
+// * `passphrase` - Null-terminated passphrase string
+// * `message` / `message_len` - Unsigned transaction message bytes
+// * `signature_out` - Buffer of `SIGNER_SIGNATURE_SIZE` bytes
+// * `public_key_out` - Buffer of `SIGNER_PUBLIC_KEY_SIZE` bytes, or null
+//
+// # Returns
+// 0 on success, otherwise a `SignerResult` error code
+//
+// # Safety
+// The strings must be valid and null-terminated, `message` must point to
+// `message_len` readable bytes, and the output buffers must be writable
+// for their documented sizes.
+int32_t signer_sign_transaction_raw(const char *container_json,
+                                    const char *passphrase,
+                                    const uint8_t *message,
+                                    uintptr_t message_len,
+                                    uint8_t *signature_out,
+                                    uint8_t *public_key_out);
+
+// Sign raw transaction message bytes directly with a raw private key
+//
+// # Security Warning
+// This function accepts a plaintext private key. Prefer
+// `signer_sign_transaction_raw` with an encrypted container.
+//
+// # Arguments
+// * `private_key` / `private_key_len` - 32-byte seed or 64-byte keypair
+// * `message` / `message_len` - Unsigned transaction message bytes;
+//   anything else fails with error code 4
+// * `signature_out` - Buffer of `SIGNER_SIGNATURE_SIZE` bytes
+//
+// # Returns
+// 0 on success, otherwise a `SignerResult` error code
+//
+// # Safety
+// Input pointers must point to the given number of readable bytes and
+// `signature_out` must be writable for `SIGNER_SIGNATURE_SIZE` bytes.
+int32_t signer_sign_direct_raw(const uint8_t *private_key,
+                               uintptr_t private_key_len,
+                               const uint8_t *message,
+                               uintptr_t message_len,
+                               uint8_t *signature_out);
+
+// Decrypt a key container and sign a raw 32-byte EVM hash (secp256k1)
+//
+// # Arguments
+// * `container_json` - Null-terminated JSON string of the encrypted container
+// * `passphrase` - Null-terminated passphrase string
+// * `message_hash` - 32-byte keccak256 hash
+// * `signature_out` - Buffer of `SIGNER_EVM_SIGNATURE_SIZE` bytes, receives
+//   r || s || v (v is 27 or 28)
+//
+// # Returns
+// 0 on success, otherwise a `SignerResult` error code
+//
+// # Safety
+// The strings must be valid and null-terminated, `message_hash` must point
+// to 32 readable bytes, and `signature_out` must be writable for
+// `SIGNER_EVM_SIGNATURE_SIZE` bytes.
+int32_t signer_sign_evm_raw(const char *container_json,
+                            const char *passphrase,
+                            const uint8_t *message_hash,
+                            uint8_t *signature_out);
+
+// Free a string allocated by Rust
+//
+// # Safety
+// The pointer must have been returned by a signer_* function.
+// After calling this, the pointer is invalid.
+void signer_free_string(char *ptr);
+
+// Free a SignerResult
+//
+// # Safety
+// The result must have been returned by a signer_* function.
+void signer_free_result(struct SignerResult result);
+
+// Get the library version
+//
+// # Returns
+// Null-terminated version string. Do NOT free this pointer.
+const char *signer_version(void);
+
+// Get the build metadata as JSON
+//
+// # Returns
+// Null-terminated JSON ([`crate::build_info::BuildInfo`]). Free with
+// `signer_free_string`.
+char *signer_build_info(void);
+
+// Check if memory locking is supported on this platform
+//
+// # Returns
+// 1 if memory locking is supported, 0 otherwise
+int32_t signer_check_mlock_support(void);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* COLDSTAR_H */
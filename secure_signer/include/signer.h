@@ -3,6 +3,9 @@
  * 
  * This header defines the C-compatible interface for the Rust signing library.
  * 
+ * Legacy: new bindings should use the versioned coldstar_* ABI in
+ * coldstar.h, which reports a distinct status code for every error.
+ * 
  * Memory Management:
  * - All strings returned by signer_* functions are allocated by Rust
  * - Use signer_free_result() to free SignerResult structs
@@ -20,6 +23,7 @@
 #ifndef SOLANA_SECURE_SIGNER_H
 #define SOLANA_SECURE_SIGNER_H
 
+#include <stddef.h>
 #include <stdint.h>
 
 #ifdef __cplusplus
@@ -42,6 +46,7 @@ extern "C" {
  *   3 - Base58/Base64 decode error
  *   4 - Crypto operation failed
  *   5 - Serialization error
+ *   6 - Internal error (the library panicked; no result was produced)
  */
 typedef struct {
     int32_t error_code;
@@ -91,14 +96,34 @@ SignerResult signer_sign_transaction(
 );
 
 /**
- * Sign a message directly with a private key.
+ * Decrypt a key container and sign a transaction, choosing the output
+ * encodings.
+ * 
+ * @param container_json  JSON string of the encrypted container
+ * @param passphrase      Null-terminated passphrase for decryption
+ * @param transaction_b64 Base64-encoded unsigned transaction bytes
+ * @param encoding_json   JSON object, e.g. {"signature":"hex","public_key":"hex"};
+ *                        fields signature, public_key, transaction; values
+ *                        base58, base64, hex. NULL selects the defaults.
+ * @return SignerResult with signing result on success
+ */
+SignerResult signer_sign_transaction_encoded(
+    const char* container_json,
+    const char* passphrase,
+    const char* transaction_b64,
+    const char* encoding_json
+);
+
+/**
+ * Sign a Solana transaction directly with a private key.
  * 
  * WARNING: This is less secure than using an encrypted container.
  * The private key is still processed in secure memory, but it must
  * be passed as a parameter.
  * 
  * @param private_key_b58 Base58-encoded private key
- * @param message_b64     Base64-encoded message to sign
+ * @param message_b64     Base64-encoded unsigned transaction message
+ *                        (anything else fails with error code 4)
  * @return SignerResult with signing result on success
  */
 SignerResult signer_sign_direct(
@@ -106,6 +131,75 @@ SignerResult signer_sign_direct(
     const char* message_b64
 );
 
+/**
+ * Raw-bytes variants.
+ * 
+ * These take byte buffers with explicit lengths, write fixed-size results
+ * into caller-provided buffers, and return an error code from the table
+ * above (0 on success). Nothing is allocated, so nothing needs freeing.
+ */
+#define SIGNER_SIGNATURE_SIZE 64
+#define SIGNER_PUBLIC_KEY_SIZE 32
+#define SIGNER_EVM_SIGNATURE_SIZE 65
+
+/**
+ * Sign raw transaction message bytes using an encrypted key container.
+ * 
+ * @param container_json JSON string of the encrypted container
+ * @param passphrase     Null-terminated passphrase for decryption
+ * @param message        Unsigned transaction message bytes
+ * @param message_len    Length of message
+ * @param signature_out  Receives the SIGNER_SIGNATURE_SIZE-byte signature
+ * @param public_key_out Receives the SIGNER_PUBLIC_KEY_SIZE-byte public key (may be NULL)
+ * @return 0 on success, error code otherwise
+ */
+int32_t signer_sign_transaction_raw(
+    const char* container_json,
+    const char* passphrase,
+    const uint8_t* message,
+    size_t message_len,
+    uint8_t* signature_out,
+    uint8_t* public_key_out
+);
+
+/**
+ * Sign raw transaction message bytes directly with a raw private key.
+ * 
+ * WARNING: This is less secure than using an encrypted container.
+ * 
+ * @param private_key     32-byte seed, or 64-byte keypair (seed || public
+ *                        key) whose public key must match the seed
+ * @param private_key_len Length of private_key
+ * @param message         Unsigned transaction message bytes (anything
+ *                        else fails with error code 4)
+ * @param message_len     Length of message
+ * @param signature_out   Receives the SIGNER_SIGNATURE_SIZE-byte signature
+ * @return 0 on success, error code otherwise
+ */
+int32_t signer_sign_direct_raw(
+    const uint8_t* private_key,
+    size_t private_key_len,
+    const uint8_t* message,
+    size_t message_len,
+    uint8_t* signature_out
+);
+
+/**
+ * Sign a raw 32-byte EVM hash (secp256k1) using an encrypted key container.
+ * 
+ * @param container_json JSON string of the encrypted container
+ * @param passphrase     Null-terminated passphrase for decryption
+ * @param message_hash   32-byte keccak256 hash
+ * @param signature_out  Receives r || s || v (SIGNER_EVM_SIGNATURE_SIZE bytes, v = 27 or 28)
+ * @return 0 on success, error code otherwise
+ */
+int32_t signer_sign_evm_raw(
+    const char* container_json,
+    const char* passphrase,
+    const uint8_t* message_hash,
+    uint8_t* signature_out
+);
+
 /**
  * Free a SignerResult structure.
  * 
@@ -130,6 +224,14 @@ void signer_free_string(char* ptr);
  */
 const char* signer_version(void);
 
+/**
+ * Get the build metadata (git commit, rustc version, target, profile,
+ * features) as a JSON object.
+ * 
+ * @return JSON string - free with signer_free_string()
+ */
+char* signer_build_info(void);
+
 /**
  * Check if memory locking (mlock) is supported.
  * 
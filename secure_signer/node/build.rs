@@ -0,0 +1,4 @@
+fn main() {
+    // Node resolves the addon's napi_* symbols at load time
+    napi_build::setup();
+}
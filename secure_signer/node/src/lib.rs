@@ -0,0 +1,269 @@
+//! Node.js bindings (napi-rs)
+//!
+//! The `cdylib` is a Node addon (rename `libcoldstar_node.so`/`.dylib` or
+//! `coldstar_node.dll` to `coldstar.node`).
+//! Everything that runs the KDF returns a `Promise` and does the work on
+//! the libuv threadpool, so Argon2 never blocks the event loop; signing
+//! with an unlocked session is fast and synchronous:
+//!
+//! ```js
+//! const { createContainer, decryptAndSign, SigningSession, Policy } = require("./coldstar.node");
+//! const container = await createContainer(seed, passphrase);
+//! const { signature } = await decryptAndSign(container, passphrase, message);
+//!
+//! const session = await SigningSession.unlock(container, passphrase, { idleTimeoutMs: 60000 });
+//! session.sign(message);
+//! session.close();
+//!
+//! new Policy(policyJson).checkEvmCall(calldata); // throws on a violation
+//! ```
+//!
+//! Errors are thrown as `Error`s carrying the [`SignerError`] message.
+
+use std::time::Duration;
+
+use napi::bindgen_prelude::{AsyncTask, Buffer, ToNapiValue, TypeName};
+use napi::{Env, Task};
+use napi_derive::napi;
+use zeroize::Zeroizing;
+
+use coldstar_secure_signer::backend::SignerBackend;
+use coldstar_secure_signer::crypto::{self, EVMSigningResult, EncryptedKeyContainer, SigningResult};
+use coldstar_secure_signer::error::SignerError;
+use coldstar_secure_signer::evm;
+use coldstar_secure_signer::policy;
+use coldstar_secure_signer::session::{self, SessionConfig};
+
+fn js_error(error: SignerError) -> napi::Error {
+    napi::Error::from_reason(error.to_string())
+}
+
+/// Result of a Solana signature
+#[napi(object)]
+pub struct SolanaSignature {
+    /// Base58 signature
+    pub signature: String,
+    /// Base64 signed transaction, when the message was a transaction
+    pub signed_transaction: Option<String>,
+    /// Base58 public key
+    pub public_key: String,
+}
+
+impl From<SigningResult> for SolanaSignature {
+    fn from(result: SigningResult) -> Self {
+        Self {
+            signature: result.signature,
+            signed_transaction: result.signed_transaction,
+            public_key: result.public_key,
+        }
+    }
+}
+
+/// Result of an EVM signature
+#[napi(object)]
+pub struct EvmSignature {
+    /// 0x-prefixed r || s || v
+    pub signature: String,
+    /// Checksummed signing address
+    pub address: String,
+    /// 27 or 28
+    pub v: u32,
+}
+
+impl From<EVMSigningResult> for EvmSignature {
+    fn from(result: EVMSigningResult) -> Self {
+        Self {
+            signature: result.signature,
+            address: result.address,
+            v: result.v.into(),
+        }
+    }
+}
+
+/// Session lifetime limits; omitted fields use the defaults (5 idle
+/// minutes, 1 hour TTL)
+#[napi(object)]
+pub struct SessionOptions {
+    pub idle_timeout_ms: Option<u32>,
+    pub ttl_ms: Option<u32>,
+}
+
+fn session_config(options: Option<SessionOptions>) -> SessionConfig {
+    let mut config = SessionConfig::default();
+    if let Some(options) = options {
+        if let Some(ms) = options.idle_timeout_ms {
+            config.idle_timeout = Duration::from_millis(ms.into());
+        }
+        if let Some(ms) = options.ttl_ms {
+            config.ttl = Some(Duration::from_millis(ms.into()));
+        }
+    }
+    config
+}
+
+/// Work that runs on the libuv threadpool and resolves a `Promise`
+pub struct Blocking<T>(Option<Box<dyn FnOnce() -> Result<T, SignerError> + Send>>);
+
+impl<T: ToNapiValue + TypeName + Send + 'static> Task for Blocking<T> {
+    type Output = T;
+    type JsValue = T;
+
+    fn compute(&mut self) -> napi::Result<T> {
+        let work = self.0.take().expect("task computed once");
+        work().map_err(js_error)
+    }
+
+    fn resolve(&mut self, _env: Env, output: T) -> napi::Result<T> {
+        Ok(output)
+    }
+}
+
+fn blocking<T>(work: impl FnOnce() -> Result<T, SignerError> + Send + 'static) -> AsyncTask<Blocking<T>>
+where
+    T: ToNapiValue + TypeName + Send + 'static,
+{
+    AsyncTask::new(Blocking(Some(Box::new(work))))
+}
+
+/// Encrypt a 32-byte seed (or 64-byte keypair) into container JSON
+#[napi(ts_return_type = "Promise<string>")]
+pub fn create_container(private_key: Buffer, passphrase: String) -> AsyncTask<Blocking<String>> {
+    let private_key = Zeroizing::new(private_key.to_vec());
+    let passphrase = Zeroizing::new(passphrase);
+    blocking(move || crypto::create_encrypted_key_container(&private_key, &passphrase))
+}
+
+/// Decrypt a container and sign a Solana transaction or message
+#[napi(ts_return_type = "Promise<SolanaSignature>")]
+pub fn decrypt_and_sign(
+    container_json: String,
+    passphrase: String,
+    message: Buffer,
+) -> AsyncTask<Blocking<SolanaSignature>> {
+    let passphrase = Zeroizing::new(passphrase);
+    let message = message.to_vec();
+    blocking(move || crypto::decrypt_and_sign(&container_json, &passphrase, &message).map(SolanaSignature::from))
+}
+
+/// Decrypt a container and sign an off-chain message (no transaction checks)
+#[napi(ts_return_type = "Promise<SolanaSignature>")]
+pub fn decrypt_and_sign_message(
+    container_json: String,
+    passphrase: String,
+    message: Buffer,
+) -> AsyncTask<Blocking<SolanaSignature>> {
+    let passphrase = Zeroizing::new(passphrase);
+    let message = message.to_vec();
+    blocking(move || {
+        crypto::decrypt_and_sign_message(&container_json, &passphrase, &message).map(SolanaSignature::from)
+    })
+}
+
+/// Decrypt a container and sign a 32-byte EVM hash
+#[napi(ts_return_type = "Promise<EvmSignature>")]
+pub fn decrypt_and_sign_evm(
+    container_json: String,
+    passphrase: String,
+    message_hash: Buffer,
+) -> AsyncTask<Blocking<EvmSignature>> {
+    let passphrase = Zeroizing::new(passphrase);
+    let message_hash = message_hash.to_vec();
+    blocking(move || {
+        crypto::decrypt_and_sign_evm(&container_json, &passphrase, &message_hash).map(EvmSignature::from)
+    })
+}
+
+/// Address (0x-prefixed lowercase hex) that signed a 32-byte EVM hash,
+/// from a 65-byte `r || s || v` signature
+#[napi]
+pub fn recover_evm_address(message_hash: Buffer, signature: Buffer) -> napi::Result<String> {
+    let signature: [u8; 65] = signature.as_ref().try_into().map_err(|_| {
+        js_error(SignerError::InvalidTransaction(format!(
+            "EVM signature must be 65 bytes, got {}",
+            signature.len()
+        )))
+    })?;
+    evm::recover_evm_address(&message_hash, &signature).map_err(js_error)
+}
+
+/// An unlocked container; see [`session::SigningSession`]
+#[napi]
+pub struct SigningSession {
+    inner: session::SigningSession,
+}
+
+#[napi]
+impl SigningSession {
+    /// Decrypt the container on the threadpool and start a session
+    #[napi(ts_return_type = "Promise<SigningSession>")]
+    pub fn unlock(
+        container_json: String,
+        passphrase: String,
+        options: Option<SessionOptions>,
+    ) -> AsyncTask<Blocking<SigningSession>> {
+        let passphrase = Zeroizing::new(passphrase);
+        let config = session_config(options);
+        blocking(move || {
+            let container = EncryptedKeyContainer::from_json(&container_json)?;
+            let inner = session::SigningSession::unlock(&container, &passphrase, config)?;
+            Ok(SigningSession { inner })
+        })
+    }
+
+    /// Sign a Solana transaction or message
+    #[napi]
+    pub fn sign(&self, message: Buffer) -> napi::Result<SolanaSignature> {
+        self.inner.sign(&message).map(SolanaSignature::from).map_err(js_error)
+    }
+
+    /// Sign a 32-byte EVM hash
+    #[napi]
+    pub fn sign_evm_hash(&self, message_hash: Buffer) -> napi::Result<EvmSignature> {
+        self.inner.sign_evm_hash(&message_hash).map(EvmSignature::from).map_err(js_error)
+    }
+
+    /// The container's base58 public key, if it records one
+    #[napi(getter)]
+    pub fn public_key(&self) -> Option<String> {
+        self.inner.public_key().map(str::to_string)
+    }
+
+    /// Whether the key has been zeroized (closed, idle, or expired)
+    #[napi(getter)]
+    pub fn is_locked(&self) -> bool {
+        self.inner.is_locked()
+    }
+
+    /// Zeroize the key now
+    #[napi]
+    pub fn close(&self) {
+        self.inner.close();
+    }
+}
+
+/// A signing policy; see [`policy::Policy`]
+#[napi]
+pub struct Policy {
+    inner: policy::Policy,
+}
+
+#[napi]
+impl Policy {
+    /// Parse a policy from its JSON form
+    #[napi(constructor)]
+    pub fn new(json: String) -> napi::Result<Self> {
+        policy::Policy::from_json(&json).map(|inner| Self { inner }).map_err(js_error)
+    }
+
+    /// Whether the policy governs the container with this ID
+    #[napi]
+    pub fn applies_to(&self, container_id: String) -> bool {
+        self.inner.applies_to(&container_id)
+    }
+
+    /// Throw if the EVM calldata violates a rule
+    #[napi]
+    pub fn check_evm_call(&self, calldata: Buffer) -> napi::Result<()> {
+        self.inner.check_evm_call(&calldata).map_err(js_error)
+    }
+}
@@ -290,7 +290,7 @@ class FFISigner:
         return self._process_result(result)
     
     def sign_direct(self, private_key_b58: str, message: bytes) -> dict:
-        """Sign a message directly (less secure than using container)."""
+        """Sign a transaction message directly (less secure than using container)."""
         message_b64 = base64.b64encode(message).decode('ascii')
         
         result = self.lib.signer_sign_direct(
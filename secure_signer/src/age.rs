@@ -0,0 +1,497 @@
+//! age file format import and export
+//!
+//! Converts containers to and from [age](https://age-encryption.org/v1)
+//! files, so keys at rest can be handled with `age`, `rage`, and whatever
+//! already manages age identities. An exported file holds the raw key (a
+//! 32-byte seed or scalar), encrypted to one or more recipients:
+//!
+//! - [`AgeRecipient::X25519`]: an `age1...` public key (an `X25519` stanza)
+//! - [`AgeRecipient::Passphrase`]: a passphrase (an `scrypt` stanza, which
+//!   must be the only one)
+//!
+//! ```ignore
+//! let file = container.to_age(passphrase, &[AgeRecipient::X25519("age1...")])?;
+//! // age -d -i key.txt exported.age  (prints the raw key)
+//! let container = EncryptedKeyContainer::from_age(&file, &AgeIdentity::X25519(&identity), new_passphrase)?;
+//! ```
+//!
+//! On import the payload is decrypted in place inside a [`SecureBuffer`],
+//! and the key then only moves through this crate's locked-memory path.
+//! Only the binary format is read and written (not `age -a` armor), and
+//! plugin recipients such as `age-plugin-yubikey` run out of process and
+//! are not supported.
+
+use base64::engine::general_purpose::STANDARD_NO_PAD;
+use base64::Engine;
+use bech32::{Bech32, Hrp};
+use chacha20poly1305::aead::{AeadInPlace, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Nonce, Tag};
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use x25519_dalek::{PublicKey, StaticSecret};
+use zeroize::Zeroizing;
+
+use crate::crypto::{get_locking_mode, Cipher, EncryptedKeyContainer};
+use crate::entropy::{fill_random, EntropyRng};
+use crate::error::SignerError;
+use crate::kdf::{Kdf, KdfParams, ScryptParams};
+use crate::secure_buffer::SecureBuffer;
+
+/// First line of every age file
+const INTRO: &str = "age-encryption.org/v1";
+const X25519_INFO: &[u8] = b"age-encryption.org/v1/X25519";
+const SCRYPT_SALT_LABEL: &[u8] = b"age-encryption.org/v1/scrypt";
+const RECIPIENT_HRP: &str = "age";
+const IDENTITY_HRP: &str = "age-secret-key-";
+
+const FILE_KEY_SIZE: usize = 16;
+const PAYLOAD_NONCE_SIZE: usize = 16;
+const TAG_SIZE: usize = 16;
+const CHUNK_SIZE: usize = 64 * 1024;
+/// Stanza bodies are wrapped at this many base64 characters
+const COLUMNS: usize = 64;
+
+/// scrypt work factor (log2 N) for exports, as `age` uses
+pub const AGE_SCRYPT_LOG_N: u8 = 18;
+/// Highest scrypt work factor accepted on import, as `age` accepts
+const MAX_SCRYPT_LOG_N: u8 = 22;
+
+type FileKey = Zeroizing<[u8; FILE_KEY_SIZE]>;
+
+/// Who an exported file is encrypted to
+#[derive(Clone, Copy, Debug)]
+pub enum AgeRecipient<'a> {
+    /// An X25519 recipient (`age1...`)
+    X25519(&'a str),
+    /// A passphrase; must be the only recipient
+    Passphrase(&'a str),
+}
+
+/// What opens an age file
+#[derive(Clone, Copy)]
+pub enum AgeIdentity<'a> {
+    /// An X25519 identity (`AGE-SECRET-KEY-1...`), or the contents of an
+    /// `age-keygen` identity file
+    X25519(&'a str),
+    /// The file's passphrase
+    Passphrase(&'a str),
+}
+
+impl core::fmt::Debug for AgeIdentity<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(match self {
+            AgeIdentity::X25519(_) => "AgeIdentity::X25519([REDACTED])",
+            AgeIdentity::Passphrase(_) => "AgeIdentity::Passphrase([REDACTED])",
+        })
+    }
+}
+
+/// Generate an X25519 identity, returning it (`AGE-SECRET-KEY-1...`) and
+/// its recipient (`age1...`)
+pub fn generate_age_identity() -> Result<(Zeroizing<String>, String), SignerError> {
+    crate::entropy::health_check()?;
+    let secret = StaticSecret::random_from_rng(EntropyRng);
+    let identity = bech32::encode_upper::<Bech32>(hrp(IDENTITY_HRP), secret.as_bytes())
+        .map_err(|e| SignerError::SerializationError(e.to_string()))?;
+    Ok((Zeroizing::new(identity), recipient_string(&PublicKey::from(&secret))?))
+}
+
+/// Encrypt `plaintext` to `recipients` as an age file
+pub fn encrypt_age(plaintext: &[u8], recipients: &[AgeRecipient]) -> Result<Vec<u8>, SignerError> {
+    encrypt(plaintext, recipients, AGE_SCRYPT_LOG_N)
+}
+
+fn encrypt(plaintext: &[u8], recipients: &[AgeRecipient], scrypt_log_n: u8) -> Result<Vec<u8>, SignerError> {
+    if recipients.is_empty() {
+        return Err(SignerError::ContainerError("age: no recipients".to_string()));
+    }
+    if recipients.len() > 1 && recipients.iter().any(|r| matches!(r, AgeRecipient::Passphrase(_))) {
+        return Err(SignerError::ContainerError(
+            "age: a passphrase must be the only recipient".to_string(),
+        ));
+    }
+
+    let mut file_key = FileKey::default();
+    fill_random(file_key.as_mut())?;
+    let mut header = format!("{}\n", INTRO);
+    for recipient in recipients {
+        let (kind, args, body) = match recipient {
+            AgeRecipient::X25519(recipient) => wrap_x25519(&file_key, recipient)?,
+            AgeRecipient::Passphrase(passphrase) => wrap_scrypt(&file_key, passphrase, scrypt_log_n)?,
+        };
+        write_stanza(&mut header, kind, &args, &body);
+    }
+    header.push_str("---");
+    let mac = STANDARD_NO_PAD.encode(header_mac(&file_key, header.as_bytes()).finalize().into_bytes());
+
+    let mut nonce = [0u8; PAYLOAD_NONCE_SIZE];
+    fill_random(&mut nonce)?;
+    let mut file = format!("{} {}\n", header, mac).into_bytes();
+    file.extend_from_slice(&nonce);
+    let payload = payload_cipher(&file_key, &nonce);
+    let chunks = plaintext.len().div_ceil(CHUNK_SIZE).max(1);
+    for (index, chunk) in (0..chunks).map(|i| (i, &plaintext[i * CHUNK_SIZE..plaintext.len().min((i + 1) * CHUNK_SIZE)])) {
+        let start = file.len();
+        file.extend_from_slice(chunk);
+        let tag = payload
+            .encrypt_in_place_detached(&chunk_nonce(index as u128, index + 1 == chunks)?, b"", &mut file[start..])
+            .map_err(|_| SignerError::SigningFailed("age payload encryption failed".to_string()))?;
+        file.extend_from_slice(&tag);
+    }
+    Ok(file)
+}
+
+/// Decrypt an age file into locked memory
+pub fn decrypt_age(file: &[u8], identity: &AgeIdentity) -> Result<SecureBuffer, SignerError> {
+    let header = parse_header(file)?;
+    let file_key = unwrap_file_key(&header.stanzas, identity)?;
+    header_mac(&file_key, &file[..header.mac_input_len])
+        .verify_slice(&header.mac)
+        .map_err(|_| SignerError::DecryptionFailed)?;
+
+    let payload = &file[header.len..];
+    if payload.len() < PAYLOAD_NONCE_SIZE + TAG_SIZE {
+        return Err(malformed("payload is truncated"));
+    }
+    let (nonce, chunks) = payload.split_at(PAYLOAD_NONCE_SIZE);
+    let chunks: Vec<&[u8]> = chunks.chunks(CHUNK_SIZE + TAG_SIZE).collect();
+    if chunks.len() > 1 && chunks[chunks.len() - 1].len() == TAG_SIZE {
+        return Err(malformed("payload ends with an empty chunk"));
+    }
+    if chunks.iter().any(|chunk| chunk.len() < TAG_SIZE) {
+        return Err(malformed("payload is truncated"));
+    }
+
+    let cipher = payload_cipher(&file_key, nonce.try_into().expect("split at the nonce size"));
+    let mut plaintext =
+        SecureBuffer::with_mode(chunks.iter().map(|c| c.len() - TAG_SIZE).sum(), get_locking_mode())?;
+    let mut offset = 0;
+    for (index, chunk) in chunks.iter().enumerate() {
+        let (ciphertext, tag) = chunk.split_at(chunk.len() - TAG_SIZE);
+        let out = &mut plaintext.as_mut_slice()[offset..offset + ciphertext.len()];
+        out.copy_from_slice(ciphertext);
+        cipher
+            .decrypt_in_place_detached(&chunk_nonce(index as u128, index + 1 == chunks.len())?, b"", out, Tag::from_slice(tag))
+            .map_err(|_| SignerError::DecryptionFailed)?;
+        offset += ciphertext.len();
+    }
+    Ok(plaintext)
+}
+
+impl EncryptedKeyContainer {
+    /// Import the key from an age file, re-encrypting it with `passphrase`
+    /// under the native format (Argon2id, AES-256-GCM)
+    pub fn from_age(file: &[u8], identity: &AgeIdentity, passphrase: &str) -> Result<Self, SignerError> {
+        let mut key = decrypt_age(file, identity)?;
+        let container = Self::encrypt_with_kdf(key.as_slice(), passphrase, Cipher::default(), KdfParams::default());
+        key.zeroize();
+        container
+    }
+
+    /// Export the raw key as an age file encrypted to `recipients`
+    pub fn to_age(&self, passphrase: &str, recipients: &[AgeRecipient]) -> Result<Vec<u8>, SignerError> {
+        let mut key = self.decrypt_key(passphrase)?;
+        let file = encrypt_age(key.as_slice(), recipients);
+        key.zeroize();
+        file
+    }
+}
+
+fn hrp(value: &str) -> Hrp {
+    Hrp::parse(value).expect("valid HRP")
+}
+
+fn malformed(reason: &str) -> SignerError {
+    SignerError::ContainerError(format!("age: {}", reason))
+}
+
+fn recipient_string(public_key: &PublicKey) -> Result<String, SignerError> {
+    bech32::encode::<Bech32>(hrp(RECIPIENT_HRP), public_key.as_bytes())
+        .map_err(|e| SignerError::SerializationError(e.to_string()))
+}
+
+/// Decode 32 bytes of bech32 data with the given HRP
+fn decode_bech32(value: &str, expected_hrp: &str, what: &str) -> Result<Zeroizing<[u8; 32]>, SignerError> {
+    let invalid = || SignerError::ContainerError(format!("age: invalid {}", what));
+    let (hrp, data) = bech32::decode(value).map_err(|_| invalid())?;
+    let data = Zeroizing::new(data);
+    if !hrp.as_str().eq_ignore_ascii_case(expected_hrp) || data.len() != 32 {
+        return Err(invalid());
+    }
+    let mut bytes = Zeroizing::new([0u8; 32]);
+    bytes.copy_from_slice(&data);
+    Ok(bytes)
+}
+
+fn chacha(key: &[u8; 32]) -> ChaCha20Poly1305 {
+    ChaCha20Poly1305::new(key.into())
+}
+
+/// HKDF-SHA-256 to 32 bytes
+fn hkdf(salt: &[u8], ikm: &[u8], info: &[u8]) -> Zeroizing<[u8; 32]> {
+    let mut key = Zeroizing::new([0u8; 32]);
+    Hkdf::<Sha256>::new(Some(salt), ikm)
+        .expand(info, key.as_mut())
+        .expect("32 bytes is a valid HKDF-SHA-256 length");
+    key
+}
+
+/// Encrypt the file key under a wrapping key (zero nonce; each wrapping
+/// key is used once)
+fn wrap(key: &[u8; 32], file_key: &FileKey) -> Result<Vec<u8>, SignerError> {
+    let mut body = file_key.to_vec();
+    let tag = chacha(key)
+        .encrypt_in_place_detached(&Nonce::default(), b"", &mut body)
+        .map_err(|_| SignerError::SigningFailed("age key wrapping failed".to_string()))?;
+    body.extend_from_slice(&tag);
+    Ok(body)
+}
+
+fn unwrap(key: &[u8; 32], body: &[u8]) -> Option<FileKey> {
+    if body.len() != FILE_KEY_SIZE + TAG_SIZE {
+        return None;
+    }
+    let mut file_key = FileKey::default();
+    file_key.copy_from_slice(&body[..FILE_KEY_SIZE]);
+    chacha(key)
+        .decrypt_in_place_detached(&Nonce::default(), b"", file_key.as_mut(), Tag::from_slice(&body[FILE_KEY_SIZE..]))
+        .ok()?;
+    Some(file_key)
+}
+
+type Stanza = (&'static str, Vec<String>, Vec<u8>);
+
+fn wrap_x25519(file_key: &FileKey, recipient: &str) -> Result<Stanza, SignerError> {
+    let recipient = PublicKey::from(*decode_bech32(recipient, RECIPIENT_HRP, "recipient")?);
+    let ephemeral = StaticSecret::random_from_rng(EntropyRng);
+    let share = PublicKey::from(&ephemeral);
+    let shared = ephemeral.diffie_hellman(&recipient);
+    if !shared.was_contributory() {
+        return Err(malformed("recipient is a low-order point"));
+    }
+    let salt = [share.as_bytes().as_slice(), recipient.as_bytes()].concat();
+    let key = hkdf(&salt, shared.as_bytes(), X25519_INFO);
+    Ok(("X25519", vec![STANDARD_NO_PAD.encode(share.as_bytes())], wrap(&key, file_key)?))
+}
+
+fn wrap_scrypt(file_key: &FileKey, passphrase: &str, log_n: u8) -> Result<Stanza, SignerError> {
+    let mut salt = [0u8; 16];
+    fill_random(&mut salt)?;
+    let key = scrypt_key(passphrase, &salt, log_n)?;
+    let args = vec![STANDARD_NO_PAD.encode(salt), log_n.to_string()];
+    Ok(("scrypt", args, wrap(&key, file_key)?))
+}
+
+fn scrypt_key(passphrase: &str, salt: &[u8], log_n: u8) -> Result<Zeroizing<[u8; 32]>, SignerError> {
+    let derived = Kdf::Scrypt(ScryptParams { log_n, r: 8, p: 1 })
+        .derive(passphrase.as_bytes(), &[SCRYPT_SALT_LABEL, salt].concat())?;
+    let mut key = Zeroizing::new([0u8; 32]);
+    key.copy_from_slice(derived.as_slice());
+    Ok(key)
+}
+
+fn write_stanza(header: &mut String, kind: &str, args: &[String], body: &[u8]) {
+    header.push_str("-> ");
+    header.push_str(kind);
+    for arg in args {
+        header.push(' ');
+        header.push_str(arg);
+    }
+    header.push('\n');
+    let encoded = STANDARD_NO_PAD.encode(body);
+    for line in encoded.as_bytes().chunks(COLUMNS) {
+        header.push_str(core::str::from_utf8(line).expect("base64 is ASCII"));
+        header.push('\n');
+    }
+    // The last line is always short, so a full one is followed by an empty one
+    if encoded.len().is_multiple_of(COLUMNS) {
+        header.push('\n');
+    }
+}
+
+fn header_mac(file_key: &FileKey, header: &[u8]) -> Hmac<Sha256> {
+    let key = hkdf(&[], file_key.as_ref(), b"header");
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(key.as_ref()).expect("HMAC takes any key length");
+    mac.update(header);
+    mac
+}
+
+fn payload_cipher(file_key: &FileKey, nonce: &[u8; PAYLOAD_NONCE_SIZE]) -> ChaCha20Poly1305 {
+    chacha(&hkdf(nonce, file_key.as_ref(), b"payload"))
+}
+
+/// STREAM nonce: an 11-byte big-endian counter and a last-chunk flag
+fn chunk_nonce(counter: u128, last: bool) -> Result<Nonce, SignerError> {
+    if counter >= 1 << 88 {
+        return Err(malformed("payload is too long"));
+    }
+    let mut nonce = Nonce::default();
+    nonce[..11].copy_from_slice(&counter.to_be_bytes()[5..]);
+    nonce[11] = u8::from(last);
+    Ok(nonce)
+}
+
+struct Header<'a> {
+    stanzas: Vec<(&'a str, Vec<&'a str>, Vec<u8>)>,
+    mac: Vec<u8>,
+    /// Bytes covered by the MAC, through `---`
+    mac_input_len: usize,
+    /// Bytes up to the payload
+    len: usize,
+}
+
+fn parse_header(file: &[u8]) -> Result<Header<'_>, SignerError> {
+    let mut position = 0;
+    let mut next_line = || -> Result<(usize, &str), SignerError> {
+        let rest = &file[position..];
+        let end = rest.iter().position(|&b| b == b'\n').ok_or_else(|| malformed("header is truncated"))?;
+        let line = core::str::from_utf8(&rest[..end]).map_err(|_| malformed("header is not ASCII"))?;
+        let start = position;
+        position += end + 1;
+        Ok((start, line))
+    };
+
+    if next_line()?.1 != INTRO {
+        return Err(malformed("not an age v1 file"));
+    }
+    let mut stanzas = Vec::new();
+    loop {
+        let (start, line) = next_line()?;
+        if let Some(mac) = line.strip_prefix("--- ") {
+            let mac = STANDARD_NO_PAD.decode(mac).map_err(|_| malformed("invalid header MAC"))?;
+            return Ok(Header {
+                stanzas,
+                mac,
+                mac_input_len: start + 3,
+                len: position,
+            });
+        }
+        let Some(stanza) = line.strip_prefix("-> ") else {
+            return Err(malformed("invalid header line"));
+        };
+        let mut args = stanza.split(' ');
+        let kind = args.next().unwrap_or_default();
+        let args: Vec<&str> = args.collect();
+        if kind.is_empty() || args.iter().any(|arg| arg.is_empty()) {
+            return Err(malformed("invalid stanza"));
+        }
+        let mut encoded = String::new();
+        loop {
+            let (_, line) = next_line()?;
+            if line.len() > COLUMNS {
+                return Err(malformed("stanza body line is too long"));
+            }
+            encoded.push_str(line);
+            if line.len() < COLUMNS {
+                break;
+            }
+        }
+        let body = STANDARD_NO_PAD.decode(&encoded).map_err(|_| malformed("invalid stanza body"))?;
+        stanzas.push((kind, args, body));
+    }
+}
+
+fn unwrap_file_key(stanzas: &[(&str, Vec<&str>, Vec<u8>)], identity: &AgeIdentity) -> Result<FileKey, SignerError> {
+    let has_scrypt = stanzas.iter().any(|(kind, _, _)| *kind == "scrypt");
+    if has_scrypt && stanzas.len() > 1 {
+        return Err(malformed("an scrypt stanza must be the only one"));
+    }
+    match identity {
+        AgeIdentity::X25519(identity) => {
+            let key = identity
+                .lines()
+                .map(str::trim)
+                .find(|line| line.to_ascii_uppercase().starts_with("AGE-SECRET-KEY-1"))
+                .ok_or_else(|| malformed("no AGE-SECRET-KEY-1 identity found"))?;
+            let secret = StaticSecret::from(*decode_bech32(key, IDENTITY_HRP, "identity")?);
+            let public = PublicKey::from(&secret);
+            for (_, args, body) in stanzas.iter().filter(|(kind, _, _)| *kind == "X25519") {
+                let share = match args.as_slice() {
+                    [share] => STANDARD_NO_PAD.decode(share).ok().and_then(|s| <[u8; 32]>::try_from(s).ok()),
+                    _ => None,
+                }
+                .ok_or_else(|| malformed("invalid X25519 stanza"))?;
+                let shared = secret.diffie_hellman(&PublicKey::from(share));
+                if !shared.was_contributory() {
+                    return Err(malformed("X25519 share is a low-order point"));
+                }
+                let salt = [share.as_slice(), public.as_bytes()].concat();
+                if let Some(file_key) = unwrap(&hkdf(&salt, shared.as_bytes(), X25519_INFO), body) {
+                    return Ok(file_key);
+                }
+            }
+            Err(SignerError::DecryptionFailed)
+        }
+        AgeIdentity::Passphrase(passphrase) => {
+            let Some((_, args, body)) = stanzas.first().filter(|_| has_scrypt) else {
+                return Err(malformed("the file is not passphrase-encrypted"));
+            };
+            let (salt, log_n) = match args.as_slice() {
+                [salt, log_n] if !log_n.starts_with('0') && log_n.bytes().all(|b| b.is_ascii_digit()) => {
+                    (STANDARD_NO_PAD.decode(salt).ok().filter(|s| s.len() == 16), log_n.parse::<u8>().ok())
+                }
+                _ => (None, None),
+            };
+            let (Some(salt), Some(log_n)) = (salt, log_n) else {
+                return Err(malformed("invalid scrypt stanza"));
+            };
+            if log_n > MAX_SCRYPT_LOG_N {
+                return Err(malformed("scrypt work factor is too high"));
+            }
+            let key = scrypt_key(passphrase, &salt, log_n)?;
+            unwrap(&key, body).ok_or(SignerError::DecryptionFailed)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_age_round_trips_with_both_stanzas() {
+        std::env::set_var("SIGNER_ALLOW_INSECURE_MEMORY", "1");
+        let container =
+            EncryptedKeyContainer::encrypt_with_kdf(&[6u8; 32], "pw", Cipher::Aes256Gcm, KdfParams::MINIMUM).unwrap();
+        let (identity, recipient) = generate_age_identity().unwrap();
+        let (_, other) = generate_age_identity().unwrap();
+        assert!(recipient.starts_with("age1") && identity.starts_with("AGE-SECRET-KEY-1"));
+
+        let file = container.to_age("pw", &[AgeRecipient::X25519(&other), AgeRecipient::X25519(&recipient)]).unwrap();
+        assert!(file.starts_with(b"age-encryption.org/v1\n-> X25519 "));
+        let keygen_file = format!("# created: 2026-01-01T00:00:00Z\n# public key: {}\n{}\n", recipient, *identity);
+        let imported = EncryptedKeyContainer::from_age(&file, &AgeIdentity::X25519(&keygen_file), "new").unwrap();
+        assert_eq!(imported.public_key, container.public_key);
+        let (stranger, _) = generate_age_identity().unwrap();
+        assert!(matches!(decrypt_age(&file, &AgeIdentity::X25519(&stranger)), Err(SignerError::DecryptionFailed)));
+
+        // Tampering with the header or payload is caught
+        let mut tampered = file.clone();
+        let last = tampered.len() - 1;
+        tampered[last] ^= 1;
+        assert!(decrypt_age(&tampered, &AgeIdentity::X25519(&identity)).is_err());
+
+        let file = encrypt(&[7u8; 32], &[AgeRecipient::Passphrase("secret")], 10).unwrap();
+        assert!(file.starts_with(b"age-encryption.org/v1\n-> scrypt "));
+        assert_eq!(decrypt_age(&file, &AgeIdentity::Passphrase("secret")).unwrap().as_slice(), &[7u8; 32]);
+        assert!(decrypt_age(&file, &AgeIdentity::Passphrase("wrong")).is_err());
+        assert!(encrypt_age(b"", &[AgeRecipient::Passphrase("a"), AgeRecipient::X25519(&recipient)]).is_err());
+    }
+
+    #[test]
+    fn test_age_payload_chunking() {
+        std::env::set_var("SIGNER_ALLOW_INSECURE_MEMORY", "1");
+        let (identity, recipient) = generate_age_identity().unwrap();
+        for len in [0, CHUNK_SIZE, CHUNK_SIZE + 1] {
+            let plaintext: Vec<u8> = (0..len).map(|i| i as u8).collect();
+            let file = encrypt_age(&plaintext, &[AgeRecipient::X25519(&recipient)]).unwrap();
+            assert_eq!(decrypt_age(&file, &AgeIdentity::X25519(&identity)).unwrap().as_slice(), plaintext.as_slice());
+            // Dropping the final chunk is detected
+            if len > CHUNK_SIZE {
+                let truncated = &file[..file.len() - (len - CHUNK_SIZE) - TAG_SIZE];
+                assert!(decrypt_age(truncated, &AgeIdentity::X25519(&identity)).is_err());
+            }
+        }
+    }
+}
@@ -0,0 +1,76 @@
+//! Application secrets derived from a wallet key
+//!
+//! Some applications need a deterministic symmetric key tied to the wallet
+//! (e.g. encrypted note storage that can be recovered from the same seed).
+//! [`derive_app_secret`] gives them one via HKDF-SHA256 over the container's
+//! seed, so the raw seed or signing key is never exported. Secrets for
+//! different labels are independent, and knowing one reveals nothing about
+//! the seed or other labels.
+
+use hkdf::Hkdf;
+use sha2::Sha256;
+
+use crate::crypto::{get_locking_mode, EncryptedKeyContainer};
+use crate::error::SignerError;
+use crate::secure_buffer::SecureBuffer;
+
+/// HKDF salt; separates application secrets from any other use of the seed
+const APP_SECRET_SALT: &[u8] = b"coldstar-app-secret-v1";
+
+/// Length of derived application secrets
+pub const APP_SECRET_SIZE: usize = 32;
+
+/// Derive a 32-byte application secret from a container's seed
+///
+/// `label` identifies the application and purpose (e.g.
+/// `"com.example.notes/encryption"`); the same container, passphrase and
+/// label always produce the same secret.
+pub fn derive_app_secret(
+    container: &EncryptedKeyContainer,
+    passphrase: &str,
+    label: &str,
+) -> Result<SecureBuffer, SignerError> {
+    if label.is_empty() {
+        return Err(SignerError::KeyDerivationFailed("application label must not be empty".to_string()));
+    }
+
+    let mut seed = container.decrypt_key(passphrase)?;
+    let result = derive_from_seed(seed.as_slice(), label);
+    seed.zeroize();
+    result
+}
+
+fn derive_from_seed(seed: &[u8], label: &str) -> Result<SecureBuffer, SignerError> {
+    let mut secret = SecureBuffer::with_mode(APP_SECRET_SIZE, get_locking_mode())?;
+    Hkdf::<Sha256>::new(Some(APP_SECRET_SALT), seed)
+        .expand(label.as_bytes(), secret.as_mut_slice())
+        .map_err(|e| SignerError::KeyDerivationFailed(e.to_string()))?;
+    Ok(secret)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::Cipher;
+    use crate::kdf::KdfParams;
+
+    #[test]
+    fn test_app_secrets_are_deterministic_and_label_bound() {
+        let seed = [9u8; 32];
+        let container =
+            EncryptedKeyContainer::encrypt_with_kdf(&seed, "pw", Cipher::Aes256Gcm, KdfParams::MINIMUM).unwrap();
+
+        let notes = derive_app_secret(&container, "pw", "notes").unwrap();
+        assert_eq!(notes.len(), APP_SECRET_SIZE);
+        assert_eq!(notes.as_slice(), derive_app_secret(&container, "pw", "notes").unwrap().as_slice());
+        assert_ne!(notes.as_slice(), derive_app_secret(&container, "pw", "backup").unwrap().as_slice());
+        assert_ne!(notes.as_slice(), &seed);
+
+        let other =
+            EncryptedKeyContainer::encrypt_with_kdf(&[8u8; 32], "pw", Cipher::Aes256Gcm, KdfParams::MINIMUM).unwrap();
+        assert_ne!(notes.as_slice(), derive_app_secret(&other, "pw", "notes").unwrap().as_slice());
+
+        assert!(derive_app_secret(&container, "pw", "").is_err());
+        assert!(derive_app_secret(&container, "wrong", "notes").is_err());
+    }
+}
@@ -0,0 +1,332 @@
+//! Approval of individual signing requests
+//!
+//! A [`Policy`](crate::policy::Policy) decides what may be signed at all; an
+//! [`ApprovalUi`] asks whether this particular request should be. The signer
+//! shows a summary, then waits a bounded time for a decision, and anything
+//! other than an explicit approval refuses the request:
+//!
+//! - [`TerminalApproval`]: prints the summary and reads `y`/`n` from a
+//!   terminal (stdin/stderr by default, so stdout stays machine-readable)
+//! - [`HeadlessApproval`]: decides with a closure, for automated approvers
+//!   and for bridges to push notifications or a TUI
+//!
+//! [`require_approval`] runs one request through a UI.
+
+use std::io::{self, BufRead, BufReader, Write};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::SignerError;
+use crate::evm::abi::TokenCall;
+use crate::evm::chain::ChainProfile;
+use crate::evm::nft::decode_nft_call;
+use crate::evm::transaction::{EvmTransaction, GasPricing};
+use crate::solana::decode::decode_transaction;
+
+/// What the approver is shown
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ApprovalRequest {
+    /// One-line description of the request
+    pub title: String,
+    /// Human-readable details, one per line
+    #[serde(default)]
+    pub details: Vec<String>,
+    /// ID of the container that would sign
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub container_id: Option<String>,
+}
+
+impl ApprovalRequest {
+    /// Create a request with a title and no details
+    pub fn new(title: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            details: Vec::new(),
+            container_id: None,
+        }
+    }
+
+    /// Add a line of detail
+    pub fn with_detail(mut self, detail: impl Into<String>) -> Self {
+        self.details.push(detail.into());
+        self
+    }
+
+    /// Name the container that would sign
+    pub fn for_container(mut self, container_id: impl Into<String>) -> Self {
+        self.container_id = Some(container_id.into());
+        self
+    }
+
+    /// Request for a Solana message, one detail per decoded instruction
+    pub fn solana(message_bytes: &[u8]) -> Result<Self, SignerError> {
+        Ok(decode_transaction(message_bytes)?
+            .iter()
+            .fold(Self::new("Sign Solana transaction"), |request, ix| request.with_detail(ix.summary())))
+    }
+
+    /// Request for an EVM transaction
+    pub fn evm(transaction: &EvmTransaction) -> Self {
+        let profile = ChainProfile::for_chain_id(transaction.chain_id);
+        let to = transaction
+            .to
+            .map_or("contract creation".to_string(), |to| format!("0x{}", hex::encode(to)));
+        let fee = match transaction.pricing {
+            GasPricing::Legacy { gas_price } => format!("gas price {} wei", gas_price),
+            GasPricing::Eip1559(fee) => format!(
+                "max fee {} wei, tip {} wei",
+                fee.max_fee_per_gas, fee.max_priority_fee_per_gas
+            ),
+        };
+
+        let mut request = Self::new(format!("Sign {} transaction (chain {})", profile.name, transaction.chain_id))
+            .with_detail(format!("To: {}", to))
+            .with_detail(format!("Value: {} wei", transaction.value))
+            .with_detail(format!("Gas: {} at {}", transaction.gas_limit, fee));
+        if let Some(call @ TokenCall::Erc20Transfer { .. }) = TokenCall::decode(&transaction.data) {
+            request = request.with_detail(call.summary());
+        } else if let Ok(Some(call)) = decode_nft_call(&transaction.data) {
+            request = request.with_detail(call.summary());
+        } else if !transaction.data.is_empty() {
+            request = request.with_detail(format!("Calldata: {} bytes", transaction.data.len()));
+        }
+        request
+    }
+
+    /// Multi-line text for display
+    pub fn render(&self) -> String {
+        let mut text = self.title.clone();
+        if let Some(id) = &self.container_id {
+            text.push_str(&format!("\n  Key: {}", id));
+        }
+        for detail in &self.details {
+            text.push_str(&format!("\n  {}", detail));
+        }
+        text
+    }
+}
+
+/// The approver's answer
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ApprovalDecision {
+    /// Sign the request
+    Approved,
+    /// Refuse the request
+    Denied,
+    /// No answer in time; treated as a denial
+    TimedOut,
+}
+
+/// A way of presenting requests and collecting decisions
+pub trait ApprovalUi {
+    /// Present the request to the approver
+    fn display(&self, request: &ApprovalRequest) -> Result<(), SignerError>;
+
+    /// Wait up to `timeout` for a decision on a displayed request
+    fn await_decision(&self, request: &ApprovalRequest, timeout: Duration) -> Result<ApprovalDecision, SignerError>;
+}
+
+/// Display a request and fail unless it is approved within `timeout`
+///
+/// Denials and timeouts return `SignerError::PolicyViolation`.
+pub fn require_approval(ui: &dyn ApprovalUi, request: &ApprovalRequest, timeout: Duration) -> Result<(), SignerError> {
+    ui.display(request)?;
+    match ui.await_decision(request, timeout)? {
+        ApprovalDecision::Approved => Ok(()),
+        ApprovalDecision::Denied => Err(SignerError::PolicyViolation(format!(
+            "'{}' was denied by the approver",
+            request.title
+        ))),
+        ApprovalDecision::TimedOut => Err(SignerError::PolicyViolation(format!(
+            "'{}' was not approved within {}s",
+            request.title,
+            timeout.as_secs()
+        ))),
+    }
+}
+
+/// Approval by typing `y` at a prompt
+///
+/// Input is read on a helper thread so the wait can time out. An answer
+/// typed after a timeout is consumed and discarded; it never approves the
+/// next request.
+pub struct TerminalApproval {
+    input: Arc<Mutex<Box<dyn BufRead + Send>>>,
+    output: Mutex<Box<dyn Write + Send>>,
+}
+
+impl TerminalApproval {
+    /// Prompt on stderr and read answers from stdin
+    pub fn stdio() -> Self {
+        Self::new(BufReader::new(io::stdin()), io::stderr())
+    }
+
+    /// Prompt on `output` and read answers from `input`
+    pub fn new(input: impl BufRead + Send + 'static, output: impl Write + Send + 'static) -> Self {
+        Self {
+            input: Arc::new(Mutex::new(Box::new(input))),
+            output: Mutex::new(Box::new(output)),
+        }
+    }
+}
+
+impl ApprovalUi for TerminalApproval {
+    fn display(&self, request: &ApprovalRequest) -> Result<(), SignerError> {
+        let mut output = self
+            .output
+            .lock()
+            .map_err(|_| SignerError::IoError("approval output lock poisoned".to_string()))?;
+        write!(output, "{}\nApprove? [y/N] ", request.render())?;
+        output.flush()?;
+        Ok(())
+    }
+
+    fn await_decision(&self, _request: &ApprovalRequest, timeout: Duration) -> Result<ApprovalDecision, SignerError> {
+        let (tx, rx) = mpsc::channel();
+        let input = Arc::clone(&self.input);
+        std::thread::spawn(move || {
+            let mut line = String::new();
+            let read = match input.lock() {
+                Ok(mut input) => input.read_line(&mut line).map(|_| line),
+                Err(_) => Err(io::Error::other("approval input lock poisoned")),
+            };
+            let _ = tx.send(read);
+        });
+
+        match rx.recv_timeout(timeout) {
+            Ok(Ok(line)) => match line.trim().to_ascii_lowercase().as_str() {
+                "y" | "yes" => Ok(ApprovalDecision::Approved),
+                _ => Ok(ApprovalDecision::Denied),
+            },
+            Ok(Err(e)) => Err(e.into()),
+            Err(mpsc::RecvTimeoutError::Timeout) => Ok(ApprovalDecision::TimedOut),
+            Err(mpsc::RecvTimeoutError::Disconnected) => Ok(ApprovalDecision::Denied),
+        }
+    }
+}
+
+/// Approval decided by code, without a person in the loop
+pub struct HeadlessApproval {
+    decide: Box<dyn Fn(&ApprovalRequest) -> ApprovalDecision + Send + Sync>,
+}
+
+impl HeadlessApproval {
+    /// Decide each request with `decide`
+    pub fn new(decide: impl Fn(&ApprovalRequest) -> ApprovalDecision + Send + Sync + 'static) -> Self {
+        Self {
+            decide: Box::new(decide),
+        }
+    }
+
+    /// Approve everything (the signer's policy remains the only check)
+    pub fn approve_all() -> Self {
+        Self::new(|_| ApprovalDecision::Approved)
+    }
+
+    /// Deny everything
+    pub fn deny_all() -> Self {
+        Self::new(|_| ApprovalDecision::Denied)
+    }
+}
+
+impl ApprovalUi for HeadlessApproval {
+    fn display(&self, _request: &ApprovalRequest) -> Result<(), SignerError> {
+        Ok(())
+    }
+
+    fn await_decision(&self, request: &ApprovalRequest, _timeout: Duration) -> Result<ApprovalDecision, SignerError> {
+        Ok((self.decide)(request))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Output sink the test can read back
+    #[derive(Clone, Default)]
+    struct SharedOutput(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedOutput {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_terminal_approval() {
+        let request = ApprovalRequest::new("Sign test").with_detail("Transfer 1 SOL");
+        let output = SharedOutput::default();
+        let ui = TerminalApproval::new(io::Cursor::new(b"y\nno\n".to_vec()), output.clone());
+
+        assert!(require_approval(&ui, &request, Duration::from_secs(5)).is_ok());
+        assert!(matches!(
+            require_approval(&ui, &request, Duration::from_secs(5)),
+            Err(SignerError::PolicyViolation(_))
+        ));
+        // End of input denies
+        assert_eq!(ui.await_decision(&request, Duration::from_secs(5)).unwrap(), ApprovalDecision::Denied);
+
+        let shown = String::from_utf8(output.0.lock().unwrap().clone()).unwrap();
+        assert!(shown.contains("Sign test\n  Transfer 1 SOL\nApprove? [y/N] "));
+    }
+
+    #[test]
+    fn test_terminal_approval_times_out() {
+        /// Input that never answers
+        struct Silent;
+        impl io::Read for Silent {
+            fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+                std::thread::sleep(Duration::from_secs(3600));
+                Ok(0)
+            }
+        }
+
+        let ui = TerminalApproval::new(BufReader::new(Silent), io::sink());
+        let request = ApprovalRequest::new("Sign test");
+        assert_eq!(
+            ui.await_decision(&request, Duration::from_millis(50)).unwrap(),
+            ApprovalDecision::TimedOut
+        );
+    }
+
+    #[test]
+    fn test_headless_approval() {
+        let ui = HeadlessApproval::new(|request| {
+            if request.details.iter().any(|d| d.contains("ALL tokens")) {
+                ApprovalDecision::Denied
+            } else {
+                ApprovalDecision::Approved
+            }
+        });
+        let mut transaction = EvmTransaction {
+            chain_id: 56,
+            nonce: 0,
+            gas_limit: 60_000,
+            to: Some([0x22; 20]),
+            value: 0,
+            data: Vec::new(),
+            pricing: GasPricing::Legacy { gas_price: 1_000_000_000 },
+        };
+        let request = ApprovalRequest::evm(&transaction);
+        assert!(request.title.contains("BNB Smart Chain"));
+        assert!(require_approval(&ui, &request, Duration::ZERO).is_ok());
+
+        // setApprovalForAll(operator, true)
+        transaction.data = hex::decode("a22cb465").unwrap();
+        transaction.data.extend_from_slice(&[0u8; 12]);
+        transaction.data.extend_from_slice(&[0x33; 20]);
+        transaction.data.extend_from_slice(&[0u8; 31]);
+        transaction.data.push(1);
+        assert!(require_approval(&ui, &ApprovalRequest::evm(&transaction), Duration::ZERO).is_err());
+        assert!(require_approval(&HeadlessApproval::deny_all(), &request, Duration::ZERO).is_err());
+    }
+}
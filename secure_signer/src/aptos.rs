@@ -0,0 +1,155 @@
+//! Aptos Ed25519 signing
+//!
+//! Aptos signs `SHA3-256("APTOS::RawTransaction") || BCS(RawTransaction)`:
+//! the domain-separating prefix keeps a transaction signature from being
+//! valid for any other BCS type. The container's 32-byte key is the
+//! Ed25519 seed, as for Solana.
+//!
+//! An account's address is `SHA3-256(public key || 0x00)`, the trailing
+//! byte being the Ed25519 authentication scheme.
+
+use ed25519_dalek::{Signer, SigningKey};
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Sha3_256};
+
+use crate::crypto::{get_locking_mode, EncryptedKeyContainer};
+use crate::entropy::health_check;
+use crate::error::SignerError;
+use crate::secure_buffer::SecureBuffer;
+
+/// Authentication scheme byte of single Ed25519 keys
+const ED25519_SCHEME: u8 = 0x00;
+
+/// `TransactionAuthenticator::Ed25519` variant index
+const ED25519_AUTHENTICATOR: u8 = 0x00;
+
+/// Result of an Aptos signing operation
+#[derive(Serialize, Deserialize)]
+pub struct AptosSigningResult {
+    /// The signature (0x-prefixed hex, 64 bytes)
+    pub signature: String,
+    /// The public key (0x-prefixed hex, 32 bytes)
+    pub public_key: String,
+    /// Account address (0x-prefixed hex, 32 bytes)
+    pub address: String,
+    /// BCS `SignedTransaction` (0x-prefixed hex), ready to submit
+    pub signed_transaction: String,
+}
+
+/// The bytes that are signed for a BCS-encoded `RawTransaction`
+pub fn signing_message(raw_transaction: &[u8]) -> Vec<u8> {
+    [&Sha3_256::digest(b"APTOS::RawTransaction")[..], raw_transaction].concat()
+}
+
+/// Address of the account whose authentication key is `public_key`
+pub fn aptos_address(public_key: &[u8; 32]) -> String {
+    format!("0x{}", hex::encode(Sha3_256::digest([&public_key[..], &[ED25519_SCHEME]].concat())))
+}
+
+/// Sign a raw transaction with a key in a secure buffer
+pub(crate) fn sign_aptos_with_secure_key(
+    secure_key: &mut SecureBuffer,
+    raw_transaction: &[u8],
+) -> Result<AptosSigningResult, SignerError> {
+    if secure_key.len() != 32 {
+        return Err(SignerError::InvalidKeyFormat(secure_key.len()));
+    }
+    health_check()?;
+
+    let signing_key = SigningKey::from_bytes(
+        secure_key
+            .as_slice()
+            .try_into()
+            .map_err(|_| SignerError::InvalidKeyFormat(secure_key.len()))?,
+    );
+    let public_key = signing_key.verifying_key().to_bytes();
+    let signature = signing_key.sign(&signing_message(raw_transaction)).to_bytes();
+
+    // SignedTransaction: raw transaction, then the authenticator with
+    // ULEB128-length-prefixed key and signature
+    let mut signed_transaction = raw_transaction.to_vec();
+    signed_transaction.push(ED25519_AUTHENTICATOR);
+    signed_transaction.push(32);
+    signed_transaction.extend_from_slice(&public_key);
+    signed_transaction.push(64);
+    signed_transaction.extend_from_slice(&signature);
+
+    Ok(AptosSigningResult {
+        signature: format!("0x{}", hex::encode(signature)),
+        public_key: format!("0x{}", hex::encode(public_key)),
+        address: aptos_address(&public_key),
+        signed_transaction: format!("0x{}", hex::encode(signed_transaction)),
+    })
+}
+
+/// Decrypt a key container and sign an Aptos transaction
+///
+/// Same security model as `decrypt_and_sign`, with the same Ed25519 key as
+/// Solana.
+///
+/// # Arguments
+/// * `container_json` - JSON-serialized EncryptedKeyContainer
+/// * `passphrase` - The passphrase for decryption
+/// * `raw_transaction` - BCS-encoded `RawTransaction`, without the prefix
+pub fn decrypt_and_sign_aptos(
+    container_json: &str,
+    passphrase: &str,
+    raw_transaction: &[u8],
+) -> Result<AptosSigningResult, SignerError> {
+    let container = EncryptedKeyContainer::from_json(container_json)?;
+
+    let mut secure_key = container.decrypt_key(passphrase)?;
+    let result = sign_aptos_with_secure_key(&mut secure_key, raw_transaction);
+    secure_key.zeroize();
+
+    result
+}
+
+/// Sign an Aptos transaction with a raw private key
+///
+/// # Security Warning
+/// Prefer using decrypt_and_sign_aptos() for the full secure workflow.
+pub fn sign_aptos(private_key: &[u8], raw_transaction: &[u8]) -> Result<AptosSigningResult, SignerError> {
+    let mut secure_key = SecureBuffer::from_slice_with_mode(private_key, get_locking_mode())?;
+    let result = sign_aptos_with_secure_key(&mut secure_key, raw_transaction);
+    secure_key.zeroize();
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::Cipher;
+    use crate::kdf::KdfParams;
+    use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+
+    #[test]
+    fn test_aptos_signature_and_address() {
+        std::env::set_var("SIGNER_ALLOW_INSECURE_MEMORY", "1");
+        let container =
+            EncryptedKeyContainer::encrypt_with_kdf(&[7u8; 32], "pw", Cipher::Aes256Gcm, KdfParams::MINIMUM).unwrap();
+        let json = container.to_json().unwrap();
+        let raw_transaction = [0x11u8; 40];
+
+        assert_eq!(
+            hex::encode(signing_message(&[])),
+            "b5e97db07fa0bd0e5598aa3643a9bc6f6693bddc1a9fec9e674a461eaa00b193"
+        );
+
+        let result = decrypt_and_sign_aptos(&json, "pw", &raw_transaction).unwrap();
+        assert_eq!(result.address, "0xcc405722b15c00a19d37e51d9a756de1e61b780ad0935f3363bc7fce64edbdad");
+
+        let decode = |s: &str| hex::decode(s.trim_start_matches("0x")).unwrap();
+        let public_key = VerifyingKey::from_bytes(&decode(&result.public_key).try_into().unwrap()).unwrap();
+        let signature = Signature::from_slice(&decode(&result.signature)).unwrap();
+        assert!(public_key.verify(&signing_message(&raw_transaction), &signature).is_ok());
+        // A signature over the bare transaction would be a different one
+        assert!(public_key.verify(&raw_transaction, &signature).is_err());
+
+        let signed = decode(&result.signed_transaction);
+        assert_eq!(signed.len(), raw_transaction.len() + 1 + 33 + 65);
+        assert_eq!(&signed[signed.len() - 64..], &signature.to_bytes());
+
+        assert!(decrypt_and_sign_aptos(&json, "wrong", &raw_transaction).is_err());
+    }
+}
@@ -0,0 +1,379 @@
+//! Append-only, hash-chained audit log
+//!
+//! Every entry commits to the hash of the previous entry, so deleting,
+//! reordering, or editing any entry breaks the chain from that point on.
+//! Entries are stored as JSON lines.
+//!
+//! # What Is Logged
+//!
+//! Only public information: public keys, signatures, transaction ids, and
+//! SHA-256 hashes of signed payloads. Key material and passphrases are never
+//! part of an audit event.
+//!
+//! # Hashing
+//!
+//! `hash = SHA-256(prev_hash || canonical JSON of {seq, timestamp, event, build})`,
+//! hex encoded (`build` is omitted when absent). The first entry uses 64
+//! zero characters as `prev_hash`.
+
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::build_info::build_info;
+use crate::error::SignerError;
+
+/// `prev_hash` of the first entry
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// An auditable event
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AuditEvent {
+    /// A payload was signed
+    Signed {
+        /// Chain family ("solana", "evm", ...)
+        chain: String,
+        /// Public key or address of the signer
+        public_key: String,
+        /// ID of the container that signed, if signed from a container
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        container_id: Option<String>,
+        /// SHA-256 of the signed payload (hex)
+        payload_hash: String,
+        /// The produced signature
+        signature: String,
+        /// Signature number from the signer's [`SignatureCounter`](crate::counter::SignatureCounter)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        counter: Option<u64>,
+    },
+    /// A signed transaction was submitted to the network
+    Broadcast {
+        /// Sequence number of the `Signed` entry
+        signed_entry: u64,
+        /// Chain family
+        chain: String,
+        /// Transaction id (Solana signature / EVM tx hash)
+        tx_id: String,
+    },
+    /// The network reported a status for a broadcast transaction
+    TxStatus {
+        /// Sequence number of the `Signed` entry
+        signed_entry: u64,
+        /// Chain family
+        chain: String,
+        /// Transaction id
+        tx_id: String,
+        /// Status label ("pending", "processed", "confirmed", "finalized", "failed")
+        status: String,
+        /// Slot (Solana) or block number (EVM) the transaction landed in
+        #[serde(skip_serializing_if = "Option::is_none")]
+        slot: Option<u64>,
+        /// Failure reason reported by the node
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+    },
+    /// An unlocked session was locked and its key material zeroized
+    SessionLocked {
+        /// Label of the session
+        session: String,
+        /// Why it was locked ("idle", "ttl", ...)
+        reason: String,
+        /// Seconds since the session was last used
+        idle_seconds: u64,
+    },
+}
+
+/// One entry in the audit log
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct AuditEntry {
+    /// Sequence number, starting at 0
+    pub seq: u64,
+    /// Unix timestamp (seconds)
+    pub timestamp: u64,
+    /// The recorded event
+    pub event: AuditEvent,
+    /// Fingerprint of the signer build that wrote the entry
+    /// ([`BuildInfo::fingerprint`](crate::build_info::BuildInfo::fingerprint));
+    /// absent in entries written before it was recorded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub build: Option<String>,
+    /// Hash of the previous entry (hex)
+    pub prev_hash: String,
+    /// Hash of this entry (hex)
+    pub hash: String,
+}
+
+impl AuditEntry {
+    /// Compute the hash this entry should have
+    pub fn compute_hash(&self) -> Result<String, SignerError> {
+        #[derive(Serialize)]
+        struct Body<'a> {
+            seq: u64,
+            timestamp: u64,
+            event: &'a AuditEvent,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            build: &'a Option<String>,
+        }
+
+        let body = serde_json::to_vec(&Body {
+            seq: self.seq,
+            timestamp: self.timestamp,
+            event: &self.event,
+            build: &self.build,
+        })?;
+
+        let mut hasher = Sha256::new();
+        hasher.update(self.prev_hash.as_bytes());
+        hasher.update(&body);
+        Ok(hex::encode(hasher.finalize()))
+    }
+}
+
+/// Append-only audit log, optionally backed by a JSON-lines file
+pub struct AuditLog {
+    path: Option<PathBuf>,
+    entries: Vec<AuditEntry>,
+}
+
+impl AuditLog {
+    /// Create a log that lives only in memory
+    pub fn in_memory() -> Self {
+        Self {
+            path: None,
+            entries: Vec::new(),
+        }
+    }
+
+    /// Open (or create) a log file, verifying the existing chain
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, SignerError> {
+        let path = path.as_ref().to_path_buf();
+        let mut entries = Vec::new();
+
+        if path.exists() {
+            for line in BufReader::new(File::open(&path)?).lines() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                entries.push(serde_json::from_str(&line)?);
+            }
+            verify_chain(&entries)?;
+        }
+
+        Ok(Self {
+            path: Some(path),
+            entries,
+        })
+    }
+
+    /// All entries, oldest first
+    pub fn entries(&self) -> &[AuditEntry] {
+        &self.entries
+    }
+
+    /// Look up an entry by sequence number
+    pub fn get(&self, seq: u64) -> Option<&AuditEntry> {
+        usize::try_from(seq).ok().and_then(|i| self.entries.get(i))
+    }
+
+    /// Append an event, returning the new entry
+    pub fn append(&mut self, event: AuditEvent) -> Result<&AuditEntry, SignerError> {
+        let (seq, prev_hash) = match self.entries.last() {
+            Some(last) => (last.seq + 1, last.hash.clone()),
+            None => (0, GENESIS_HASH.to_string()),
+        };
+
+        let mut entry = AuditEntry {
+            seq,
+            timestamp: unix_now(),
+            event,
+            build: Some(build_info().fingerprint()),
+            prev_hash,
+            hash: String::new(),
+        };
+        entry.hash = entry.compute_hash()?;
+
+        if let Some(path) = &self.path {
+            let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+            writeln!(file, "{}", serde_json::to_string(&entry)?)?;
+            file.sync_data()?;
+        }
+
+        self.entries.push(entry);
+        Ok(self.entries.last().expect("entry was just pushed"))
+    }
+}
+
+/// Verify that entries form an unbroken hash chain starting at genesis
+pub fn verify_chain(entries: &[AuditEntry]) -> Result<(), SignerError> {
+    verify_segment(entries, 0, GENESIS_HASH)
+}
+
+/// Verify a contiguous segment of the chain
+///
+/// The first entry must have sequence number `first_seq` and link to
+/// `anchor_hash` (the hash of the entry preceding the segment).
+pub fn verify_segment(entries: &[AuditEntry], first_seq: u64, anchor_hash: &str) -> Result<(), SignerError> {
+    let mut prev_hash = anchor_hash.to_string();
+
+    for (i, entry) in entries.iter().enumerate() {
+        let seq = first_seq + i as u64;
+        if entry.seq != seq || entry.prev_hash != prev_hash || entry.compute_hash()? != entry.hash {
+            return Err(SignerError::AuditError(format!(
+                "hash chain broken at entry {}",
+                seq
+            )));
+        }
+        prev_hash = entry.hash.clone();
+    }
+
+    Ok(())
+}
+
+/// Check that signature counters in `Signed` events have no gaps or repeats
+///
+/// Counters must increase by exactly one per public key; the first counter
+/// seen for a key may be any value, so log segments can be checked too.
+/// Events without a counter are skipped.
+pub fn verify_counters(entries: &[AuditEntry]) -> Result<(), SignerError> {
+    let mut last = std::collections::HashMap::new();
+
+    for entry in entries {
+        let AuditEvent::Signed {
+            public_key,
+            counter: Some(counter),
+            ..
+        } = &entry.event
+        else {
+            continue;
+        };
+
+        if let Some(previous) = last.insert(public_key.as_str(), *counter) {
+            if *counter <= previous {
+                return Err(SignerError::AuditError(format!(
+                    "signature counter for {} went from {} back to {} at entry {}",
+                    public_key, previous, counter, entry.seq
+                )));
+            }
+            if *counter != previous + 1 {
+                return Err(SignerError::AuditError(format!(
+                    "signatures {}..{} for {} are missing before entry {}",
+                    previous + 1,
+                    counter - 1,
+                    public_key,
+                    entry.seq
+                )));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// SHA-256 of a payload, hex encoded, for use in `AuditEvent::Signed`
+pub fn payload_hash(payload: &[u8]) -> String {
+    hex::encode(Sha256::digest(payload))
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signed_event(n: u8) -> AuditEvent {
+        AuditEvent::Signed {
+            chain: "solana".to_string(),
+            public_key: "pubkey".to_string(),
+            container_id: None,
+            payload_hash: payload_hash(&[n]),
+            signature: format!("sig{}", n),
+            counter: None,
+        }
+    }
+
+    #[test]
+    fn test_chain_links_entries() {
+        let mut log = AuditLog::in_memory();
+        log.append(signed_event(1)).unwrap();
+        log.append(signed_event(2)).unwrap();
+
+        let entries = log.entries();
+        assert_eq!(entries[0].prev_hash, GENESIS_HASH);
+        assert_eq!(entries[1].prev_hash, entries[0].hash);
+        assert!(verify_chain(entries).is_ok());
+    }
+
+    #[test]
+    fn test_tampering_detected() {
+        let mut log = AuditLog::in_memory();
+        log.append(signed_event(1)).unwrap();
+        log.append(signed_event(2)).unwrap();
+
+        let mut entries = log.entries().to_vec();
+        entries[0].event = signed_event(9);
+        assert!(verify_chain(&entries).is_err());
+
+        let mut entries = log.entries().to_vec();
+        entries.remove(0);
+        assert!(verify_chain(&entries).is_err());
+    }
+
+    #[test]
+    fn test_file_backed_log_reopens() {
+        let path = std::env::temp_dir().join(format!("coldstar-audit-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        {
+            let mut log = AuditLog::open(&path).unwrap();
+            log.append(signed_event(1)).unwrap();
+        }
+        {
+            let mut log = AuditLog::open(&path).unwrap();
+            let entry = log.append(signed_event(2)).unwrap();
+            assert_eq!(entry.seq, 1);
+        }
+
+        let log = AuditLog::open(&path).unwrap();
+        assert_eq!(log.entries().len(), 2);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_counter_gaps_and_rollbacks_detected() {
+        let counted = |key: &str, counter: u64| AuditEvent::Signed {
+            chain: "solana".to_string(),
+            public_key: key.to_string(),
+            container_id: None,
+            payload_hash: payload_hash(&counter.to_le_bytes()),
+            signature: format!("sig{}", counter),
+            counter: Some(counter),
+        };
+        let log_of = |events: Vec<AuditEvent>| {
+            let mut log = AuditLog::in_memory();
+            for event in events {
+                log.append(event).unwrap();
+            }
+            log.entries().to_vec()
+        };
+
+        let ok = log_of(vec![counted("a", 7), counted("b", 1), signed_event(0), counted("a", 8)]);
+        assert!(verify_counters(&ok).is_ok());
+
+        let gap = log_of(vec![counted("a", 1), counted("a", 3)]);
+        assert!(matches!(verify_counters(&gap), Err(SignerError::AuditError(_))));
+
+        let rollback = log_of(vec![counted("a", 4), counted("a", 5), counted("a", 5)]);
+        assert!(verify_counters(&rollback).is_err());
+    }
+}
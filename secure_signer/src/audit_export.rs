@@ -0,0 +1,352 @@
+//! Encrypted, signed audit log exports
+//!
+//! A segment of the audit log can be exported for a third-party auditor
+//! without shipping the raw log file:
+//!
+//! - Entries are encrypted to the auditor's X25519 public key (ephemeral
+//!   X25519, HKDF-SHA256, XChaCha20-Poly1305).
+//! - The export header and ciphertext are signed with the signer's Ed25519
+//!   key, so the auditor can tell who produced the export.
+//! - The header pins the segment: sequence range, the hash preceding the
+//!   first entry (`anchor_hash`) and the hash of the last entry
+//!   (`head_hash`).
+//!
+//! [`verify_audit_export`] checks the signature, decrypts, and verifies the
+//! hash chain from anchor to head, returning the entries.
+
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use hkdf::Hkdf;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use x25519_dalek::{EphemeralSecret, PublicKey, StaticSecret};
+
+use crate::audit::{verify_counters, verify_segment, AuditEntry, AuditLog, GENESIS_HASH};
+use crate::crypto::EncryptedKeyContainer;
+use crate::entropy::{fill_random, EntropyRng};
+use crate::error::SignerError;
+use crate::secure_buffer::SecureBuffer;
+
+/// Export format version
+const EXPORT_VERSION: u8 = 1;
+
+/// HKDF info string for the export encryption key
+const HKDF_INFO: &[u8] = b"coldstar-audit-export-v1";
+
+/// An encrypted, signed segment of the audit log
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct AuditExport {
+    /// Export format version
+    pub version: u8,
+    /// Sequence number of the first exported entry
+    pub first_seq: u64,
+    /// Sequence number of the last exported entry
+    pub last_seq: u64,
+    /// Hash of the entry preceding the segment (genesis hash for seq 0)
+    pub anchor_hash: String,
+    /// Hash of the last exported entry
+    pub head_hash: String,
+    /// Ed25519 key that signed the export (base58)
+    pub exporter_public_key: String,
+    /// Ephemeral X25519 public key (hex)
+    pub ephemeral_public_key: String,
+    /// XChaCha20-Poly1305 nonce (base64)
+    pub nonce: String,
+    /// Encrypted JSON array of entries (base64)
+    pub ciphertext: String,
+    /// Ed25519 signature over header and ciphertext (base58)
+    pub signature: String,
+}
+
+/// Signed and authenticated fields, in a fixed order
+#[derive(Serialize)]
+struct ExportHeader<'a> {
+    version: u8,
+    first_seq: u64,
+    last_seq: u64,
+    anchor_hash: &'a str,
+    head_hash: &'a str,
+    exporter_public_key: &'a str,
+    ephemeral_public_key: &'a str,
+    nonce: &'a str,
+}
+
+impl AuditExport {
+    fn header_bytes(&self) -> Result<Vec<u8>, SignerError> {
+        Ok(serde_json::to_vec(&ExportHeader {
+            version: self.version,
+            first_seq: self.first_seq,
+            last_seq: self.last_seq,
+            anchor_hash: &self.anchor_hash,
+            head_hash: &self.head_hash,
+            exporter_public_key: &self.exporter_public_key,
+            ephemeral_public_key: &self.ephemeral_public_key,
+            nonce: &self.nonce,
+        })?)
+    }
+
+    /// Message covered by the signature: header JSON followed by the ciphertext
+    fn signed_message(&self) -> Result<Vec<u8>, SignerError> {
+        let mut message = self.header_bytes()?;
+        message.extend_from_slice(self.ciphertext.as_bytes());
+        Ok(message)
+    }
+}
+
+impl AuditLog {
+    /// Export entries `first_seq..=last_seq`, encrypted to `auditor_public_key`
+    /// and signed with the Ed25519 key in `container`
+    pub fn export_segment(
+        &self,
+        first_seq: u64,
+        last_seq: u64,
+        container: &EncryptedKeyContainer,
+        passphrase: &str,
+        auditor_public_key: &[u8; 32],
+    ) -> Result<AuditExport, SignerError> {
+        if first_seq > last_seq {
+            return Err(SignerError::AuditError("empty export range".to_string()));
+        }
+        let entries: Vec<&AuditEntry> = (first_seq..=last_seq)
+            .map(|seq| {
+                self.get(seq)
+                    .ok_or_else(|| SignerError::AuditError(format!("no audit entry {}", seq)))
+            })
+            .collect::<Result<_, _>>()?;
+
+        let anchor_hash = entries[0].prev_hash.clone();
+        let head_hash = entries[entries.len() - 1].hash.clone();
+        let plaintext = serde_json::to_vec(&entries)?;
+
+        // Ephemeral-static X25519 agreement
+        let ephemeral = EphemeralSecret::random_from_rng(EntropyRng);
+        let ephemeral_public = PublicKey::from(&ephemeral);
+        let auditor = PublicKey::from(*auditor_public_key);
+        let shared = ephemeral.diffie_hellman(&auditor);
+        if !shared.was_contributory() {
+            return Err(SignerError::AuditError("auditor key is a low-order point".to_string()));
+        }
+        let mut key = derive_export_key(shared.as_bytes(), &ephemeral_public, &auditor)?;
+
+        let mut nonce = [0u8; 24];
+        fill_random(&mut nonce)?;
+
+        let mut signing_key = container.decrypt_key(passphrase)?;
+        let signer = SigningKey::from_bytes(
+            signing_key
+                .as_slice()
+                .try_into()
+                .map_err(|_| SignerError::InvalidKeyFormat(signing_key.len()))?,
+        );
+        signing_key.zeroize();
+
+        let mut export = AuditExport {
+            version: EXPORT_VERSION,
+            first_seq,
+            last_seq,
+            anchor_hash,
+            head_hash,
+            exporter_public_key: bs58::encode(signer.verifying_key().as_bytes()).into_string(),
+            ephemeral_public_key: hex::encode(ephemeral_public.as_bytes()),
+            nonce: base64::Engine::encode(&base64::engine::general_purpose::STANDARD, nonce),
+            ciphertext: String::new(),
+            signature: String::new(),
+        };
+
+        let header = export.header_bytes()?;
+        let ciphertext = XChaCha20Poly1305::new_from_slice(key.as_slice())
+            .map_err(|e| SignerError::KeyDerivationFailed(e.to_string()))?
+            .encrypt(
+                XNonce::from_slice(&nonce),
+                Payload { msg: &plaintext, aad: &header },
+            )
+            .map_err(|_| SignerError::AuditError("export encryption failed".to_string()))?;
+        key.zeroize();
+
+        export.ciphertext = base64::Engine::encode(&base64::engine::general_purpose::STANDARD, ciphertext);
+        let signature: Signature = signer.sign(&export.signed_message()?);
+        export.signature = bs58::encode(signature.to_bytes()).into_string();
+
+        Ok(export)
+    }
+}
+
+/// X25519 public key for an auditor's 32-byte secret
+pub fn auditor_public_key(auditor_secret: &SecureBuffer) -> Result<[u8; 32], SignerError> {
+    let secret = static_secret(auditor_secret)?;
+    Ok(PublicKey::from(&secret).to_bytes())
+}
+
+/// Verify and decrypt an audit export
+///
+/// Checks that the export was signed by `trusted_exporter` (base58 Ed25519
+/// public key), decrypts it with the auditor's X25519 secret, and verifies
+/// that the entries form an unbroken chain from `anchor_hash` to
+/// `head_hash` with no gaps in the signature counters.
+pub fn verify_audit_export(
+    export: &AuditExport,
+    auditor_secret: &SecureBuffer,
+    trusted_exporter: &str,
+) -> Result<Vec<AuditEntry>, SignerError> {
+    if export.version != EXPORT_VERSION {
+        return Err(SignerError::AuditError(format!(
+            "unsupported export version {}",
+            export.version
+        )));
+    }
+    if export.exporter_public_key != trusted_exporter {
+        return Err(SignerError::AuditError(
+            "export was not produced by the trusted key".to_string(),
+        ));
+    }
+
+    // Signature
+    let exporter_bytes: [u8; 32] = bs58::decode(&export.exporter_public_key)
+        .into_vec()?
+        .try_into()
+        .map_err(|_| SignerError::AuditError("invalid exporter public key".to_string()))?;
+    let exporter = VerifyingKey::from_bytes(&exporter_bytes)
+        .map_err(|e| SignerError::AuditError(e.to_string()))?;
+    let signature_bytes: [u8; 64] = bs58::decode(&export.signature)
+        .into_vec()?
+        .try_into()
+        .map_err(|_| SignerError::AuditError("invalid export signature".to_string()))?;
+    exporter
+        .verify(&export.signed_message()?, &Signature::from_bytes(&signature_bytes))
+        .map_err(|_| SignerError::AuditError("export signature is invalid".to_string()))?;
+
+    // Decryption
+    let ephemeral_bytes: [u8; 32] = hex::decode(&export.ephemeral_public_key)
+        .ok()
+        .and_then(|b| b.try_into().ok())
+        .ok_or_else(|| SignerError::AuditError("invalid ephemeral public key".to_string()))?;
+    let ephemeral_public = PublicKey::from(ephemeral_bytes);
+    let secret = static_secret(auditor_secret)?;
+    let auditor = PublicKey::from(&secret);
+    let shared = secret.diffie_hellman(&ephemeral_public);
+    if !shared.was_contributory() {
+        return Err(SignerError::DecryptionFailed);
+    }
+    let mut key = derive_export_key(shared.as_bytes(), &ephemeral_public, &auditor)?;
+
+    let nonce = base64::Engine::decode(&base64::engine::general_purpose::STANDARD, &export.nonce)?;
+    let ciphertext = base64::Engine::decode(&base64::engine::general_purpose::STANDARD, &export.ciphertext)?;
+    if nonce.len() != 24 {
+        return Err(SignerError::AuditError("invalid export nonce".to_string()));
+    }
+    let plaintext = XChaCha20Poly1305::new_from_slice(key.as_slice())
+        .map_err(|e| SignerError::KeyDerivationFailed(e.to_string()))?
+        .decrypt(
+            XNonce::from_slice(&nonce),
+            Payload { msg: &ciphertext, aad: &export.header_bytes()? },
+        )
+        .map_err(|_| SignerError::DecryptionFailed)?;
+    key.zeroize();
+
+    // Hash chain
+    let entries: Vec<AuditEntry> = serde_json::from_slice(&plaintext)?;
+    let expected_len = export.last_seq.checked_sub(export.first_seq).map(|n| n + 1);
+    if expected_len != Some(entries.len() as u64) {
+        return Err(SignerError::AuditError(
+            "export does not cover the declared range".to_string(),
+        ));
+    }
+    if export.first_seq == 0 && export.anchor_hash != GENESIS_HASH {
+        return Err(SignerError::AuditError(
+            "segment starting at 0 must anchor to genesis".to_string(),
+        ));
+    }
+    verify_segment(&entries, export.first_seq, &export.anchor_hash)?;
+    if entries.last().map(|e| e.hash.as_str()) != Some(export.head_hash.as_str()) {
+        return Err(SignerError::AuditError(
+            "export head hash does not match entries".to_string(),
+        ));
+    }
+    verify_counters(&entries)?;
+
+    Ok(entries)
+}
+
+fn static_secret(auditor_secret: &SecureBuffer) -> Result<StaticSecret, SignerError> {
+    let bytes: [u8; 32] = auditor_secret
+        .as_slice()
+        .try_into()
+        .map_err(|_| SignerError::InvalidKeyFormat(auditor_secret.len()))?;
+    Ok(StaticSecret::from(bytes))
+}
+
+/// HKDF-SHA256 over the shared secret, salted with both public keys
+fn derive_export_key(
+    shared: &[u8; 32],
+    ephemeral_public: &PublicKey,
+    auditor_public: &PublicKey,
+) -> Result<SecureBuffer, SignerError> {
+    let mut salt = [0u8; 64];
+    salt[..32].copy_from_slice(ephemeral_public.as_bytes());
+    salt[32..].copy_from_slice(auditor_public.as_bytes());
+
+    let mut key = SecureBuffer::with_mode(32, crate::crypto::get_locking_mode())?;
+    Hkdf::<Sha256>::new(Some(&salt), shared)
+        .expand(HKDF_INFO, key.as_mut_slice())
+        .map_err(|e| SignerError::KeyDerivationFailed(e.to_string()))?;
+    Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit::{payload_hash, AuditEvent};
+
+    fn setup() -> (AuditLog, EncryptedKeyContainer, String, SecureBuffer) {
+        let mut log = AuditLog::in_memory();
+        for n in 0..4u8 {
+            log.append(AuditEvent::Signed {
+                chain: "solana".to_string(),
+                public_key: "pubkey".to_string(),
+                container_id: None,
+                payload_hash: payload_hash(&[n]),
+                signature: format!("sig{}", n),
+                counter: None,
+            })
+            .unwrap();
+        }
+
+        let container = EncryptedKeyContainer::encrypt(&[7u8; 32], "pass").unwrap();
+        let exporter = container.public_key.clone().unwrap();
+        let auditor_secret = SecureBuffer::from_slice_permissive(&[9u8; 32]).unwrap();
+        (log, container, exporter, auditor_secret)
+    }
+
+    #[test]
+    fn test_export_roundtrip() {
+        let (log, container, exporter, auditor_secret) = setup();
+        let auditor = auditor_public_key(&auditor_secret).unwrap();
+
+        let export = log.export_segment(1, 2, &container, "pass", &auditor).unwrap();
+        assert_eq!(export.anchor_hash, log.entries()[0].hash);
+
+        let entries = verify_audit_export(&export, &auditor_secret, &exporter).unwrap();
+        assert_eq!(entries, log.entries()[1..=2].to_vec());
+
+        // Wrong auditor key cannot decrypt
+        let other = SecureBuffer::from_slice_permissive(&[1u8; 32]).unwrap();
+        assert!(verify_audit_export(&export, &other, &exporter).is_err());
+
+        // A low-order auditor key would leave the export readable by anyone
+        assert!(log.export_segment(1, 2, &container, "pass", &[0u8; 32]).is_err());
+    }
+
+    #[test]
+    fn test_tampered_export_rejected() {
+        let (log, container, exporter, auditor_secret) = setup();
+        let auditor = auditor_public_key(&auditor_secret).unwrap();
+        let export = log.export_segment(0, 3, &container, "pass", &auditor).unwrap();
+
+        let mut truncated = export.clone();
+        truncated.last_seq = 2;
+        assert!(verify_audit_export(&truncated, &auditor_secret, &exporter).is_err());
+
+        assert!(verify_audit_export(&export, &auditor_secret, "someone-else").is_err());
+    }
+}
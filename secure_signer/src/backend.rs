@@ -0,0 +1,264 @@
+//! Pluggable signing backends
+//!
+//! A [`SignerBackend`] holds (or has access to) a key and produces the same
+//! [`SigningResult`] / [`EVMSigningResult`] types regardless of where the
+//! key lives:
+//!
+//! - [`ContainerBackend`]: an [`EncryptedKeyContainer`] decrypted into
+//!   locked memory for each operation (used by `decrypt_and_sign*`)
+//! - `LedgerBackend` (feature `ledger`): a Ledger device over HID
+//! - `Pkcs11Backend` (feature `pkcs11`): a key pair inside an HSM
+//! - `KeyringBackend` (Linux): a container unlocked once, with the unlock key
+//!   cached in the kernel session keyring
+//! - [`SigningSession`](crate::session::SigningSession): a container unlocked
+//!   once and held in locked memory until closed, idle, or expired
+//!
+//! # EVM Signing
+//!
+//! Software keys can sign a bare 32-byte hash. Hardware wallets generally
+//! refuse to, and need the full unsigned transaction so they can display it;
+//! callers that want to support both should use
+//! [`SignerBackend::sign_evm_transaction`].
+
+use alloc::format;
+use alloc::string::ToString;
+
+use sha3::{Digest, Keccak256};
+
+use crate::crypto::{
+    sign_evm_with_nonce_mode, sign_message_with_secure_key, sign_schnorr_with_secure_key, sign_with_secure_key,
+    EVMSigningResult, EncryptedKeyContainer, NonceMode, SchnorrSigningResult, SigningResult,
+};
+use crate::error::SignerError;
+use crate::keyfile::Keyfile;
+use crate::secure_buffer::SecureBuffer;
+
+/// A source of Ed25519 and secp256k1 signatures
+pub trait SignerBackend {
+    /// Sign a Solana transaction message (Ed25519)
+    ///
+    /// Fails with [`SignerError::InvalidTransaction`] unless the message
+    /// passes [`check_solana_message`](crate::crypto::check_solana_message)
+    /// for this backend's key.
+    fn sign_solana(&self, message: &[u8]) -> Result<SigningResult, SignerError>;
+
+    /// Sign arbitrary bytes with the Solana key (Ed25519), for off-chain
+    /// messages such as Sign-In with Solana
+    ///
+    /// No transaction checks are made and the result has no
+    /// `signed_transaction`. The default fails, for backends whose device
+    /// only signs transactions.
+    fn sign_solana_message(&self, message: &[u8]) -> Result<SigningResult, SignerError> {
+        let _ = message;
+        Err(SignerError::BackendError(
+            "off-chain message signing is not supported by this backend".to_string(),
+        ))
+    }
+
+    /// Sign a 32-byte keccak256 hash (secp256k1)
+    fn sign_evm_hash(&self, message_hash: &[u8]) -> Result<EVMSigningResult, SignerError>;
+
+    /// Sign a 32-byte keccak256 hash with the given ECDSA nonce derivation
+    ///
+    /// The default only supports [`NonceMode::Deterministic`], for backends
+    /// whose device or HSM derives its own nonces.
+    fn sign_evm_hash_with_nonce(&self, message_hash: &[u8], nonce: NonceMode) -> Result<EVMSigningResult, SignerError> {
+        match nonce {
+            NonceMode::Deterministic => self.sign_evm_hash(message_hash),
+            _ => Err(SignerError::BackendError(
+                "hedged ECDSA nonces are not supported by this backend".to_string(),
+            )),
+        }
+    }
+
+    /// Sign an unsigned EVM transaction (legacy RLP or typed envelope)
+    ///
+    /// The default hashes the transaction and calls
+    /// [`SignerBackend::sign_evm_hash`].
+    fn sign_evm_transaction(&self, unsigned_tx: &[u8]) -> Result<EVMSigningResult, SignerError> {
+        self.sign_evm_hash(&Keccak256::digest(unsigned_tx))
+    }
+
+    /// Sign a message with BIP-340 Schnorr (secp256k1)
+    ///
+    /// Uses the same key as [`SignerBackend::sign_evm_hash`]. The default
+    /// fails, for backends whose device or HSM has no Schnorr support.
+    fn sign_schnorr(&self, message: &[u8]) -> Result<SchnorrSigningResult, SignerError> {
+        let _ = message;
+        Err(SignerError::BackendError(
+            "BIP-340 Schnorr signing is not supported by this backend".to_string(),
+        ))
+    }
+}
+
+/// Backend for keys stored in an encrypted container
+///
+/// The key is decrypted into a SecureBuffer for each call and zeroized
+/// before the call returns.
+/// A [`KdfCache`](crate::kdf_cache::KdfCache) (with `std`) spares repeated
+/// calls the KDF, but not the decryption.
+pub struct ContainerBackend<'a> {
+    container: &'a EncryptedKeyContainer,
+    passphrase: &'a str,
+    keyfile: Option<&'a Keyfile>,
+    #[cfg(feature = "std")]
+    kdf_cache: Option<&'a crate::kdf_cache::KdfCache>,
+}
+
+impl<'a> ContainerBackend<'a> {
+    /// Create a backend for a container and its passphrase
+    pub fn new(container: &'a EncryptedKeyContainer, passphrase: &'a str) -> Self {
+        Self {
+            container,
+            passphrase,
+            keyfile: None,
+            #[cfg(feature = "std")]
+            kdf_cache: None,
+        }
+    }
+
+    /// Supply the keyfile a two-factor container needs
+    pub fn with_keyfile(mut self, keyfile: &'a Keyfile) -> Self {
+        self.keyfile = Some(keyfile);
+        self
+    }
+
+    /// Reuse derived keys from `cache` instead of running the KDF on
+    /// every call
+    #[cfg(feature = "std")]
+    pub fn with_kdf_cache(mut self, cache: &'a crate::kdf_cache::KdfCache) -> Self {
+        self.kdf_cache = Some(cache);
+        self
+    }
+
+    pub(crate) fn container(&self) -> &EncryptedKeyContainer {
+        self.container
+    }
+
+    pub(crate) fn decrypt_key(&self) -> Result<SecureBuffer, SignerError> {
+        #[cfg(feature = "std")]
+        if let Some(cache) = self.kdf_cache {
+            return cache.decrypt_key(self.container, self.passphrase, self.keyfile);
+        }
+        traced_unlock(self.container, false, || {
+            self.container.decrypt_key_with_keyfile(self.passphrase, self.keyfile)
+        })
+    }
+}
+
+/// Run `f` in a `coldstar.unlock` span (feature `tracing`)
+pub(crate) fn traced_unlock<T>(
+    container: &EncryptedKeyContainer,
+    cached: bool,
+    f: impl FnOnce() -> Result<T, SignerError>,
+) -> Result<T, SignerError> {
+    #[cfg(feature = "tracing")]
+    return crate::telemetry::unlock(container, cached, f);
+    #[cfg(not(feature = "tracing"))]
+    {
+        let _ = (container, cached);
+        f()
+    }
+}
+
+/// Run `f` in a `coldstar.sign` span (feature `tracing`), and record it
+/// in the [`metrics`](crate::metrics)
+fn traced_sign<T>(
+    container: &EncryptedKeyContainer,
+    chain: &'static str,
+    f: impl FnOnce() -> Result<T, SignerError>,
+) -> Result<T, SignerError> {
+    crate::metrics::sign(chain, || {
+        #[cfg(feature = "tracing")]
+        return crate::telemetry::sign(container, chain, f);
+        #[cfg(not(feature = "tracing"))]
+        {
+            let _ = container;
+            f()
+        }
+    })
+}
+
+impl SignerBackend for ContainerBackend<'_> {
+    fn sign_solana(&self, message: &[u8]) -> Result<SigningResult, SignerError> {
+        traced_sign(self.container, "solana", || {
+            let mut secure_key = self.decrypt_key()?;
+
+            // MEMORY LIFECYCLE: The signing key is created from our secure buffer
+            // and will be zeroized when dropped (ed25519-dalek supports zeroize)
+            let result = sign_with_secure_key(&secure_key, message);
+            secure_key.zeroize();
+
+            result
+        })
+    }
+
+    fn sign_solana_message(&self, message: &[u8]) -> Result<SigningResult, SignerError> {
+        traced_sign(self.container, "solana", || {
+            let mut secure_key = self.decrypt_key()?;
+            let result = sign_message_with_secure_key(&secure_key, message);
+            secure_key.zeroize();
+
+            result
+        })
+    }
+
+    fn sign_evm_hash(&self, message_hash: &[u8]) -> Result<EVMSigningResult, SignerError> {
+        self.sign_evm_hash_with_nonce(message_hash, NonceMode::Deterministic)
+    }
+
+    fn sign_evm_hash_with_nonce(&self, message_hash: &[u8], nonce: NonceMode) -> Result<EVMSigningResult, SignerError> {
+        if message_hash.len() != 32 {
+            return Err(SignerError::InvalidTransaction(format!(
+                "EVM message hash must be 32 bytes, got {}",
+                message_hash.len()
+            )));
+        }
+
+        traced_sign(self.container, "evm", || {
+            let mut secure_key = self.decrypt_key()?;
+            let result = sign_evm_with_nonce_mode(&secure_key, message_hash, nonce);
+            secure_key.zeroize();
+
+            result
+        })
+    }
+
+    fn sign_schnorr(&self, message: &[u8]) -> Result<SchnorrSigningResult, SignerError> {
+        traced_sign(self.container, "schnorr", || {
+            let mut secure_key = self.decrypt_key()?;
+            let result = sign_schnorr_with_secure_key(&secure_key, message);
+            secure_key.zeroize();
+
+            result
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kdf::KdfParams;
+    use crate::Cipher;
+
+    #[test]
+    fn test_container_backend_signs_both_chains() {
+        let container =
+            EncryptedKeyContainer::encrypt_with_kdf(&[3u8; 32], "pass", Cipher::default(), KdfParams::MINIMUM)
+                .unwrap();
+        let backend = ContainerBackend::new(&container, "pass");
+
+        let solana = backend.sign_solana(&crate::crypto::test_transaction(&[3u8; 32])).unwrap();
+        assert_eq!(Some(solana.public_key), container.public_key.clone());
+        assert!(matches!(backend.sign_solana(b"message bytes"), Err(SignerError::InvalidTransaction(_))));
+        let message = backend.sign_solana_message(b"message bytes").unwrap();
+        assert!(message.signed_transaction.is_none());
+        assert_ne!(message.signature, solana.signature);
+
+        let tx = [0x02u8, 0xc0];
+        let by_tx = backend.sign_evm_transaction(&tx).unwrap();
+        let by_hash = backend.sign_evm_hash(&Keccak256::digest(tx)).unwrap();
+        assert_eq!(by_tx.signature, by_hash.signature);
+        assert!(backend.sign_evm_hash(&[0u8; 31]).is_err());
+    }
+}
@@ -0,0 +1,157 @@
+//! Signing many payloads with one unlock
+//!
+//! Every `decrypt_and_sign*` call runs the container's KDF (Argon2id, 64 MiB
+//! by default), which dominates the cost of signing. Payout and airdrop
+//! tools signing hundreds of transactions pay it once here instead: the
+//! batch is validated, the key is decrypted into one locked buffer, every
+//! payload is signed in order, and the buffer is zeroized.
+//!
+//! A batch is all-or-nothing. An invalid request is reported, by index or
+//! ID, before the passphrase is tried; a signing failure discards the
+//! signatures already made.
+//!
+//! For Solana transactions that should come back with their signature
+//! slots filled in, see [`decrypt_and_sign_all`](crate::solana::decrypt_and_sign_all).
+
+use crate::crypto::{
+    sign_evm_with_secure_key, sign_with_secure_key, EVMSigningResult, EncryptedKeyContainer, SigningResult,
+};
+use crate::error::SignerError;
+use crate::secure_buffer::SecureBuffer;
+
+/// One payload in a batch
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TxRequest {
+    /// Caller's label for the request, used in errors
+    pub id: Option<String>,
+    /// Bytes to sign: a Solana message, or a 32-byte EVM hash
+    pub payload: Vec<u8>,
+}
+
+impl TxRequest {
+    /// A request without an ID
+    pub fn new(payload: impl Into<Vec<u8>>) -> Self {
+        Self {
+            id: None,
+            payload: payload.into(),
+        }
+    }
+
+    /// Label the request
+    pub fn with_id(mut self, id: impl Into<String>) -> Self {
+        self.id = Some(id.into());
+        self
+    }
+
+    fn label(&self, index: usize) -> String {
+        match &self.id {
+            Some(id) => format!("request {} ('{}')", index, id),
+            None => format!("request {}", index),
+        }
+    }
+}
+
+/// Decrypt once and sign each request with `sign`
+fn sign_batch<T>(
+    container_json: &str,
+    passphrase: &str,
+    requests: &[TxRequest],
+    validate: impl Fn(&TxRequest) -> Result<(), String>,
+    sign: impl Fn(&SecureBuffer, &[u8]) -> Result<T, SignerError>,
+) -> Result<Vec<T>, SignerError> {
+    let container = EncryptedKeyContainer::from_json(container_json)?;
+    for (index, request) in requests.iter().enumerate() {
+        validate(request).map_err(|e| SignerError::InvalidTransaction(format!("{}: {}", request.label(index), e)))?;
+    }
+
+    let mut secure_key = container.decrypt_key(passphrase)?;
+    let results = requests
+        .iter()
+        .enumerate()
+        .map(|(index, request)| {
+            sign(&secure_key, &request.payload)
+                .map_err(|e| SignerError::SigningFailed(format!("{}: {}", request.label(index), e)))
+        })
+        .collect();
+    secure_key.zeroize();
+    results
+}
+
+/// Decrypt a container once and Ed25519-sign every request
+///
+/// Results are in request order and match what [`decrypt_and_sign`](crate::decrypt_and_sign)
+/// returns for each payload.
+pub fn decrypt_and_sign_batch(
+    container_json: &str,
+    passphrase: &str,
+    requests: &[TxRequest],
+) -> Result<Vec<SigningResult>, SignerError> {
+    sign_batch(
+        container_json,
+        passphrase,
+        requests,
+        |request| {
+            if request.payload.is_empty() {
+                return Err("empty payload".to_string());
+            }
+            Ok(())
+        },
+        sign_with_secure_key,
+    )
+}
+
+/// Decrypt a container once and sign every request's 32-byte EVM hash
+///
+/// The batch analog of [`decrypt_and_sign_evm`](crate::decrypt_and_sign_evm).
+pub fn decrypt_and_sign_evm_batch(
+    container_json: &str,
+    passphrase: &str,
+    requests: &[TxRequest],
+) -> Result<Vec<EVMSigningResult>, SignerError> {
+    sign_batch(
+        container_json,
+        passphrase,
+        requests,
+        |request| match request.payload.len() {
+            32 => Ok(()),
+            len => Err(format!("EVM message hash must be 32 bytes, got {}", len)),
+        },
+        sign_evm_with_secure_key,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::Cipher;
+    use crate::kdf::KdfParams;
+
+    #[test]
+    fn test_batch_matches_single_signing() {
+        std::env::set_var("SIGNER_ALLOW_INSECURE_MEMORY", "1");
+        let container =
+            EncryptedKeyContainer::encrypt_with_kdf(&[7u8; 32], "pw", Cipher::Aes256Gcm, KdfParams::MINIMUM).unwrap();
+        let json = container.to_json().unwrap();
+
+        let first = crate::crypto::test_transaction(&[7u8; 32]);
+        let second = [[0x80].as_slice(), &first].concat();
+        let requests = vec![TxRequest::new(first), TxRequest::new(second).with_id("payout-2")];
+        let results = decrypt_and_sign_batch(&json, "pw", &requests).unwrap();
+        assert_eq!(results.len(), 2);
+        for (request, result) in requests.iter().zip(&results) {
+            let single = crate::decrypt_and_sign(&json, "pw", &request.payload).unwrap();
+            assert_eq!(result.signature, single.signature);
+        }
+
+        let hashes = [TxRequest::new([1u8; 32]), TxRequest::new([2u8; 32])];
+        let results = decrypt_and_sign_evm_batch(&json, "pw", &hashes).unwrap();
+        let single = crate::decrypt_and_sign_evm(&json, "pw", &[2u8; 32]).unwrap();
+        assert_eq!(results[1].signature, single.signature);
+
+        // Rejected up front, naming the request, even with a wrong passphrase
+        let bad = [TxRequest::new([1u8; 32]), TxRequest::new([2u8; 31]).with_id("short")];
+        let err = decrypt_and_sign_evm_batch(&json, "wrong", &bad).err().unwrap().to_string();
+        assert!(err.contains("request 1 ('short')"), "{}", err);
+        assert!(decrypt_and_sign_batch(&json, "wrong", &requests).is_err());
+    }
+}
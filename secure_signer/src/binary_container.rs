@@ -0,0 +1,214 @@
+//! Compact binary encoding of [`EncryptedKeyContainer`]
+//!
+//! JSON with base64 fields costs roughly 2.5 times the raw size, too much
+//! for a QR code, an NFC tag, or a microcontroller's flash. The binary form
+//! carries the same fields as raw bytes:
+//!
+//! | Size | Field |
+//! |------|-------|
+//! | 3 | magic `CSK` |
+//! | 1 | container version (1 to 3, as in JSON) |
+//! | 1 | cipher: 0 AES-256-GCM, 1 XChaCha20-Poly1305, 2 AES-256-GCM-SIV |
+//! | 1 | KDF: 0 Argon2id, 1 scrypt, 2 PBKDF2-HMAC-SHA256; bit 7 set when a keyfile is required, bit 6 when a validity window follows |
+//! | 12, 9 or 4 | KDF parameters, little-endian: Argon2id memory, time, parallelism (`u32` each); scrypt `log_n` (`u8`), `r`, `p` (`u32`); PBKDF2 iterations (`u32`) |
+//! | 0 or 16 | validity window: `not_before`, `not_after` (`u64`, little-endian; 0 and `u64::MAX` when unbounded) |
+//! | 1 + n | salt length, salt |
+//! | 12 or 24 | nonce (size set by the cipher) |
+//! | 1 + n | public key length (0 when absent), raw public key |
+//! | 2 + n | ciphertext length (`u16`, little-endian), ciphertext |
+//!
+//! Parsing is strict: unknown codes, a nonce of the wrong size, and
+//! trailing bytes are all rejected. A container converted either way keeps
+//! its [`container_id`](EncryptedKeyContainer::container_id).
+
+use alloc::format;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+
+use crate::crypto::{Cipher, EncryptedKeyContainer};
+use crate::error::SignerError;
+use crate::kdf::{Kdf, KdfParams, Pbkdf2Params, ScryptParams};
+use crate::reader::ByteReader;
+use crate::validity::Validity;
+
+/// First bytes of every binary container
+pub const MAGIC: &[u8; 3] = b"CSK";
+
+/// Flag in the KDF code for containers that need a keyfile
+const KEYFILE_FLAG: u8 = 0x80;
+
+/// Flag in the KDF code for containers with a validity window
+const VALIDITY_FLAG: u8 = 0x40;
+
+impl EncryptedKeyContainer {
+    /// Serialize the container to its binary form
+    pub fn to_bytes(&self) -> Result<Vec<u8>, SignerError> {
+        let salt = STANDARD.decode(&self.salt)?;
+        let nonce = STANDARD.decode(&self.nonce)?;
+        let ciphertext = STANDARD.decode(&self.ciphertext)?;
+        let public_key = match &self.public_key {
+            Some(public_key) => bs58::decode(public_key).into_vec()?,
+            None => Vec::new(),
+        };
+        if self.duress.is_some() {
+            return Err(SignerError::ContainerError("binary containers cannot carry a duress slot".to_string()));
+        }
+        if nonce.len() != self.cipher.nonce_size() {
+            return Err(SignerError::ContainerError("nonce size does not match the cipher".to_string()));
+        }
+        let too_long = |field: &str| SignerError::ContainerError(format!("{} too long for a binary container", field));
+
+        let mut bytes = Vec::with_capacity(64 + salt.len() + ciphertext.len());
+        bytes.extend_from_slice(MAGIC);
+        self.write_header(&mut bytes);
+        bytes.push(u8::try_from(salt.len()).map_err(|_| too_long("salt"))?);
+        bytes.extend_from_slice(&salt);
+        bytes.extend_from_slice(&nonce);
+        bytes.push(u8::try_from(public_key.len()).map_err(|_| too_long("public key"))?);
+        bytes.extend_from_slice(&public_key);
+        bytes.extend_from_slice(&u16::try_from(ciphertext.len()).map_err(|_| too_long("ciphertext"))?.to_le_bytes());
+        bytes.extend_from_slice(&ciphertext);
+        Ok(bytes)
+    }
+
+    /// Version, cipher, KDF with its parameters, and validity window, as
+    /// laid out above
+    pub(crate) fn write_header(&self, bytes: &mut Vec<u8>) {
+        bytes.push(self.version);
+        bytes.push(match self.cipher {
+            Cipher::Aes256Gcm => 0,
+            Cipher::XChaCha20Poly1305 => 1,
+            Cipher::Aes256GcmSiv => 2,
+        });
+        let validity = self.validity();
+        let bounded = validity != Validity::default();
+        let mut flag = if self.keyfile { KEYFILE_FLAG } else { 0 };
+        if bounded {
+            flag |= VALIDITY_FLAG;
+        }
+        match self.kdf {
+            Kdf::Argon2id(params) => {
+                bytes.push(flag);
+                bytes.extend_from_slice(&params.memory_cost.to_le_bytes());
+                bytes.extend_from_slice(&params.time_cost.to_le_bytes());
+                bytes.extend_from_slice(&params.parallelism.to_le_bytes());
+            }
+            Kdf::Scrypt(params) => {
+                bytes.push(flag | 1);
+                bytes.push(params.log_n);
+                bytes.extend_from_slice(&params.r.to_le_bytes());
+                bytes.extend_from_slice(&params.p.to_le_bytes());
+            }
+            Kdf::Pbkdf2Sha256(params) => {
+                bytes.push(flag | 2);
+                bytes.extend_from_slice(&params.iterations.to_le_bytes());
+            }
+        }
+        if bounded {
+            bytes.extend_from_slice(&validity.not_before.unwrap_or(0).to_le_bytes());
+            bytes.extend_from_slice(&validity.not_after.unwrap_or(u64::MAX).to_le_bytes());
+        }
+    }
+
+    /// Parse a container from its binary form
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SignerError> {
+        let mut reader = ByteReader::new(bytes, "binary container");
+        if reader.array::<3>()? != *MAGIC {
+            return Err(reader.invalid("not a binary container").into());
+        }
+        let version = reader.u8()?;
+        let cipher = match reader.u8()? {
+            0 => Cipher::Aes256Gcm,
+            1 => Cipher::XChaCha20Poly1305,
+            2 => Cipher::Aes256GcmSiv,
+            code => return Err(reader.invalid(format!("unknown cipher {}", code)).into()),
+        };
+        let kdf_code = reader.u8()?;
+        let kdf = match kdf_code & !(KEYFILE_FLAG | VALIDITY_FLAG) {
+            0 => Kdf::Argon2id(KdfParams {
+                memory_cost: reader.u32_le()?,
+                time_cost: reader.u32_le()?,
+                parallelism: reader.u32_le()?,
+            }),
+            1 => Kdf::Scrypt(ScryptParams {
+                log_n: reader.u8()?,
+                r: reader.u32_le()?,
+                p: reader.u32_le()?,
+            }),
+            2 => Kdf::Pbkdf2Sha256(Pbkdf2Params {
+                iterations: reader.u32_le()?,
+            }),
+            code => return Err(reader.invalid(format!("unknown KDF {}", code)).into()),
+        };
+        let validity = if kdf_code & VALIDITY_FLAG != 0 {
+            Validity {
+                not_before: Some(u64::from_le_bytes(reader.array()?)).filter(|&t| t != 0),
+                not_after: Some(u64::from_le_bytes(reader.array()?)).filter(|&t| t != u64::MAX),
+            }
+        } else {
+            Validity::default()
+        };
+        let salt_len = reader.u8()?;
+        let salt = reader.take_len(salt_len.into())?;
+        let nonce = reader.take(cipher.nonce_size())?;
+        let public_key_len = reader.u8()?;
+        let public_key = reader.take_len(public_key_len.into())?;
+        let ciphertext_len = reader.u16_le()?;
+        let ciphertext = reader.take_len(ciphertext_len.into())?;
+        reader.finish()?;
+
+        let container = Self {
+            version,
+            cipher,
+            kdf,
+            salt: STANDARD.encode(salt),
+            nonce: STANDARD.encode(nonce),
+            ciphertext: STANDARD.encode(ciphertext),
+            public_key: (!public_key.is_empty()).then(|| bs58::encode(public_key).into_string()),
+            keyfile: kdf_code & KEYFILE_FLAG != 0,
+            not_before: validity.not_before,
+            not_after: validity.not_after,
+            duress: None,
+        };
+        container.check_version()?;
+        Ok(container)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_binary_roundtrip_is_compact_and_strict() {
+        std::env::set_var("SIGNER_ALLOW_INSECURE_MEMORY", "1");
+        let container =
+            EncryptedKeyContainer::encrypt_with_kdf(&[4u8; 32], "pw", Cipher::XChaCha20Poly1305, KdfParams::MINIMUM)
+                .unwrap();
+        let bytes = container.to_bytes().unwrap();
+        assert!(bytes.len() * 2 < container.to_json().unwrap().len(), "{} bytes", bytes.len());
+
+        let parsed = EncryptedKeyContainer::from_bytes(&bytes).unwrap();
+        assert_eq!(parsed.to_json().unwrap(), container.to_json().unwrap());
+        assert_eq!(parsed.container_id().unwrap(), container.container_id().unwrap());
+        assert_eq!(*parsed.decrypt_key("pw").unwrap(), [4u8; 32]);
+
+        // Trailing bytes, truncation, and unknown codes are all refused
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert!(matches!(EncryptedKeyContainer::from_bytes(&trailing), Err(SignerError::ParseError(_))));
+        assert!(EncryptedKeyContainer::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        let mut unknown_cipher = bytes.clone();
+        unknown_cipher[4] = 9;
+        assert!(EncryptedKeyContainer::from_bytes(&unknown_cipher).is_err());
+        let mut unknown_version = bytes;
+        unknown_version[3] = 7;
+        assert!(matches!(
+            EncryptedKeyContainer::from_bytes(&unknown_version),
+            Err(SignerError::ContainerError(_))
+        ));
+    }
+}
@@ -0,0 +1,27 @@
+//! Bitcoin signing: segwit v0 and taproot sighashes, and PSBT signing
+//!
+//! The container's 32-byte key is used as a secp256k1 scalar, the same key
+//! that signs EVM transactions and BIP-340 messages. Two output types are
+//! supported:
+//!
+//! - **P2WPKH** (`OP_0 <hash160(pubkey)>`): BIP-143 sighash, low-S DER
+//!   ECDSA signature with the compressed public key.
+//! - **P2TR key path** (`OP_1 <output key>`): BIP-341 sighash, BIP-340
+//!   Schnorr signature with the key tweaked as in BIP-86 (or with the
+//!   input's taproot merkle root, if the PSBT provides one).
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use coldstar_secure_signer::bitcoin::decrypt_and_sign_psbt;
+//!
+//! let result = decrypt_and_sign_psbt(&container_json, "passphrase", &psbt_base64)?;
+//! println!("signed inputs {:?}", result.signed_inputs);
+//! // result.psbt goes back to the wallet for finalizing
+//! ```
+
+pub mod psbt;
+pub mod transaction;
+
+pub use psbt::{decrypt_and_sign_psbt, Psbt, PsbtMap, PsbtSigningResult};
+pub use transaction::{OutPoint, Transaction, TxIn, TxOut};
@@ -0,0 +1,513 @@
+//! Partially Signed Bitcoin Transactions (BIP-174, version 0)
+//!
+//! [`Psbt`] keeps every map as an ordered list of raw key/value pairs, so
+//! fields this module does not understand (BIP-32 derivations, proprietary
+//! keys, later PSBT extensions) survive a sign-and-return round trip
+//! byte for byte. Signing only ever adds `PSBT_IN_PARTIAL_SIG` or
+//! `PSBT_IN_TAP_KEY_SIG` entries; finalizing and extracting the network
+//! transaction is left to the wallet that built the PSBT.
+
+use base64::Engine;
+use k256::ecdsa::signature::hazmat::PrehashSigner;
+use k256::elliptic_curve::point::AffineCoordinates;
+use k256::elliptic_curve::PrimeField;
+use k256::{ProjectivePoint, Scalar};
+use ripemd::Ripemd160;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use zeroize::Zeroize;
+
+use super::transaction::{
+    p2wpkh_script_code, read_bytes, tagged_hash, write_bytes, Transaction, TxOut, SIGHASH_ALL, SIGHASH_DEFAULT,
+};
+use crate::crypto::{get_locking_mode, EncryptedKeyContainer};
+use crate::entropy::{fill_random, health_check};
+use crate::error::SignerError;
+use crate::reader::ByteReader;
+use crate::secure_buffer::SecureBuffer;
+
+/// PSBT magic bytes
+const PSBT_MAGIC: &[u8] = b"psbt\xff";
+
+const PSBT_GLOBAL_UNSIGNED_TX: u8 = 0x00;
+const PSBT_IN_NON_WITNESS_UTXO: u8 = 0x00;
+const PSBT_IN_WITNESS_UTXO: u8 = 0x01;
+const PSBT_IN_PARTIAL_SIG: u8 = 0x02;
+const PSBT_IN_SIGHASH_TYPE: u8 = 0x03;
+const PSBT_IN_FINAL_SCRIPTSIG: u8 = 0x07;
+const PSBT_IN_FINAL_SCRIPTWITNESS: u8 = 0x08;
+const PSBT_IN_TAP_KEY_SIG: u8 = 0x13;
+const PSBT_IN_TAP_INTERNAL_KEY: u8 = 0x17;
+const PSBT_IN_TAP_MERKLE_ROOT: u8 = 0x18;
+
+/// One PSBT key/value map, in wire order
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PsbtMap {
+    /// Raw `(key, value)` pairs; the first key byte is the field type
+    pub pairs: Vec<(Vec<u8>, Vec<u8>)>,
+}
+
+impl PsbtMap {
+    /// Value of the field with a one-byte key of type `key_type`
+    pub fn get(&self, key_type: u8) -> Option<&[u8]> {
+        self.pairs
+            .iter()
+            .find(|(key, _)| key.as_slice() == [key_type])
+            .map(|(_, value)| value.as_slice())
+    }
+
+    /// Whether any field of type `key_type` is present
+    pub fn contains_type(&self, key_type: u8) -> bool {
+        self.pairs.iter().any(|(key, _)| key.first() == Some(&key_type))
+    }
+
+    /// Insert a field, replacing any existing value under the same key
+    pub fn insert(&mut self, key: Vec<u8>, value: Vec<u8>) {
+        match self.pairs.iter_mut().find(|(k, _)| *k == key) {
+            Some(pair) => pair.1 = value,
+            None => self.pairs.push((key, value)),
+        }
+    }
+
+    fn parse(reader: &mut ByteReader) -> Result<Self, SignerError> {
+        let mut pairs: Vec<(Vec<u8>, Vec<u8>)> = Vec::new();
+        loop {
+            let key = read_bytes(reader)?;
+            if key.is_empty() {
+                return Ok(Self { pairs });
+            }
+            if pairs.iter().any(|(k, _)| k == key) {
+                return Err(reader.invalid("duplicate PSBT key").into());
+            }
+            pairs.push((key.to_vec(), read_bytes(reader)?.to_vec()));
+        }
+    }
+
+    fn serialize_into(&self, out: &mut Vec<u8>) {
+        for (key, value) in &self.pairs {
+            write_bytes(out, key);
+            write_bytes(out, value);
+        }
+        out.push(0x00);
+    }
+}
+
+/// A version 0 PSBT
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Psbt {
+    /// The transaction being signed (no scriptSigs or witnesses)
+    pub unsigned_tx: Transaction,
+    /// Global map, including the unsigned transaction itself
+    pub global: PsbtMap,
+    /// One map per transaction input
+    pub inputs: Vec<PsbtMap>,
+    /// One map per transaction output
+    pub outputs: Vec<PsbtMap>,
+}
+
+impl Psbt {
+    /// Parse a binary PSBT
+    pub fn parse(bytes: &[u8]) -> Result<Self, SignerError> {
+        if !bytes.starts_with(PSBT_MAGIC) {
+            return Err(SignerError::InvalidTransaction("missing PSBT magic".to_string()));
+        }
+        let mut reader = ByteReader::new(&bytes[PSBT_MAGIC.len()..], "PSBT");
+
+        let global = PsbtMap::parse(&mut reader)?;
+        let unsigned_tx = Transaction::parse(
+            global
+                .get(PSBT_GLOBAL_UNSIGNED_TX)
+                .ok_or_else(|| SignerError::InvalidTransaction("PSBT has no unsigned transaction".to_string()))?,
+        )?;
+        if unsigned_tx
+            .inputs
+            .iter()
+            .any(|input| !input.script_sig.is_empty() || !input.witness.is_empty())
+        {
+            return Err(SignerError::InvalidTransaction(
+                "PSBT unsigned transaction has scriptSigs or witnesses".to_string(),
+            ));
+        }
+
+        let inputs = (0..unsigned_tx.inputs.len())
+            .map(|_| PsbtMap::parse(&mut reader))
+            .collect::<Result<Vec<_>, _>>()?;
+        let outputs = (0..unsigned_tx.outputs.len())
+            .map(|_| PsbtMap::parse(&mut reader))
+            .collect::<Result<Vec<_>, _>>()?;
+        reader.finish()?;
+
+        Ok(Self {
+            unsigned_tx,
+            global,
+            inputs,
+            outputs,
+        })
+    }
+
+    /// Parse a base64 PSBT, the usual exchange format
+    pub fn from_base64(psbt: &str) -> Result<Self, SignerError> {
+        Self::parse(&base64::engine::general_purpose::STANDARD.decode(psbt.trim())?)
+    }
+
+    /// Serialize to binary
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut out = PSBT_MAGIC.to_vec();
+        self.global.serialize_into(&mut out);
+        self.inputs.iter().for_each(|map| map.serialize_into(&mut out));
+        self.outputs.iter().for_each(|map| map.serialize_into(&mut out));
+        out
+    }
+
+    /// Serialize to base64
+    pub fn to_base64(&self) -> String {
+        base64::engine::general_purpose::STANDARD.encode(self.serialize())
+    }
+
+    /// The output spent by input `index`, from its UTXO fields
+    pub fn spent_output(&self, index: usize) -> Result<TxOut, SignerError> {
+        let map = self
+            .inputs
+            .get(index)
+            .ok_or_else(|| SignerError::InvalidTransaction(format!("input {} does not exist", index)))?;
+        if let Some(utxo) = map.get(PSBT_IN_WITNESS_UTXO) {
+            return TxOut::parse(utxo);
+        }
+
+        let previous = map
+            .get(PSBT_IN_NON_WITNESS_UTXO)
+            .ok_or_else(|| SignerError::InvalidTransaction(format!("input {} has no UTXO information", index)))?;
+        let previous = Transaction::parse(previous)?;
+        let outpoint = &self.unsigned_tx.inputs[index].previous_output;
+        if previous.txid() != outpoint.txid {
+            return Err(SignerError::InvalidTransaction(format!(
+                "input {} previous transaction does not match its txid",
+                index
+            )));
+        }
+        previous.outputs.get(outpoint.vout as usize).cloned().ok_or_else(|| {
+            SignerError::InvalidTransaction(format!("input {} spends a missing output", index))
+        })
+    }
+
+    fn sighash_type(&self, index: usize) -> Result<Option<u8>, SignerError> {
+        self.inputs[index]
+            .get(PSBT_IN_SIGHASH_TYPE)
+            .map(|value| {
+                let value: [u8; 4] = value
+                    .try_into()
+                    .map_err(|_| SignerError::InvalidTransaction("malformed PSBT sighash type".to_string()))?;
+                u8::try_from(u32::from_le_bytes(value))
+                    .map_err(|_| SignerError::InvalidTransaction("unsupported PSBT sighash type".to_string()))
+            })
+            .transpose()
+    }
+}
+
+/// Result of signing a PSBT
+#[derive(Serialize, Deserialize)]
+pub struct PsbtSigningResult {
+    /// The updated PSBT (base64)
+    pub psbt: String,
+    /// Indices of the inputs that were signed
+    pub signed_inputs: Vec<usize>,
+    /// The compressed secp256k1 public key that signed (hex)
+    pub public_key: String,
+}
+
+/// Sign every input of `psbt` controlled by a key in a secure buffer
+///
+/// Signs P2WPKH inputs paying to the key's compressed public key (BIP-143
+/// ECDSA) and P2TR key-path inputs whose output key is the key tweaked
+/// with the input's taproot merkle root, or with none (BIP-341 Schnorr).
+/// Finalized inputs and inputs for other scripts are left untouched.
+/// Returns the signed input indices.
+pub(crate) fn sign_psbt_with_secure_key(
+    secure_key: &mut SecureBuffer,
+    psbt: &mut Psbt,
+) -> Result<Vec<usize>, SignerError> {
+    if secure_key.len() != 32 {
+        return Err(SignerError::InvalidKeyFormat(secure_key.len()));
+    }
+    health_check()?;
+    let ecdsa_key = k256::ecdsa::SigningKey::from_slice(secure_key.as_slice())
+        .map_err(|e| SignerError::SigningFailed(format!("Invalid secp256k1 key: {}", e)))?;
+    let public_key = ecdsa_key.verifying_key().to_encoded_point(true);
+    let public_key = public_key.as_bytes();
+    let x_only: [u8; 32] = public_key[1..].try_into().expect("compressed key is 33 bytes");
+
+    let mut p2wpkh_script = vec![0x00, 0x14];
+    p2wpkh_script.extend_from_slice(&Ripemd160::digest(Sha256::digest(public_key)));
+
+    let mut signed = Vec::new();
+    let mut all_prevouts: Option<Vec<TxOut>> = None;
+
+    for index in 0..psbt.inputs.len() {
+        let map = &psbt.inputs[index];
+        if map.contains_type(PSBT_IN_FINAL_SCRIPTSIG) || map.contains_type(PSBT_IN_FINAL_SCRIPTWITNESS) {
+            continue;
+        }
+        let Ok(spent) = psbt.spent_output(index) else {
+            continue;
+        };
+        let sighash_type = psbt.sighash_type(index)?;
+
+        if spent.script_pubkey == p2wpkh_script {
+            let sighash_type = sighash_type.unwrap_or(SIGHASH_ALL);
+            if !matches!(sighash_type, 0x01..=0x03 | 0x81..=0x83) {
+                return Err(SignerError::InvalidTransaction(format!(
+                    "invalid sighash type {:#04x} for input {}",
+                    sighash_type, index
+                )));
+            }
+            let script_code = p2wpkh_script_code(p2wpkh_script[2..].try_into().expect("20-byte hash"));
+            let sighash = psbt
+                .unsigned_tx
+                .segwit_v0_sighash(index, &script_code, spent.value, sighash_type)?;
+
+            // k256 always produces low-S signatures, as Bitcoin policy requires
+            let signature: k256::ecdsa::Signature = ecdsa_key
+                .sign_prehash(&sighash)
+                .map_err(|e| SignerError::SigningFailed(format!("ECDSA signing failed: {}", e)))?;
+            let mut value = signature.to_der().as_bytes().to_vec();
+            value.push(sighash_type);
+
+            let mut key = vec![PSBT_IN_PARTIAL_SIG];
+            key.extend_from_slice(public_key);
+            psbt.inputs[index].insert(key, value);
+            signed.push(index);
+        } else if spent.script_pubkey.len() == 34 && spent.script_pubkey[..2] == [0x51, 0x20] {
+            if map.get(PSBT_IN_TAP_INTERNAL_KEY).is_some_and(|key| key != x_only) {
+                continue;
+            }
+            let merkle_root = map.get(PSBT_IN_TAP_MERKLE_ROOT).map(<[u8]>::to_vec);
+            let mut tweaked = taproot_tweaked_key(secure_key, &x_only, merkle_root.as_deref())?;
+            let signing_key = k256::schnorr::SigningKey::from_bytes(tweaked.as_slice())
+                .map_err(|e| SignerError::SigningFailed(format!("Invalid tweaked key: {}", e)));
+            tweaked.zeroize();
+            let signing_key = signing_key?;
+            if signing_key.verifying_key().to_bytes().as_slice() != &spent.script_pubkey[2..] {
+                continue;
+            }
+
+            if all_prevouts.is_none() {
+                all_prevouts = Some(
+                    (0..psbt.inputs.len())
+                        .map(|i| psbt.spent_output(i))
+                        .collect::<Result<Vec<_>, _>>()?,
+                );
+            }
+            let sighash_type = sighash_type.unwrap_or(SIGHASH_DEFAULT);
+            let sighash = psbt.unsigned_tx.taproot_key_spend_sighash(
+                index,
+                all_prevouts.as_deref().expect("prevouts collected"),
+                sighash_type,
+            )?;
+
+            let mut aux_rand = [0u8; 32];
+            fill_random(&mut aux_rand)?;
+            let signature = signing_key
+                .sign_raw(&sighash, &aux_rand)
+                .map_err(|e| SignerError::SigningFailed(format!("Schnorr signing failed: {}", e)))?;
+            let mut value = signature.to_bytes().to_vec();
+            if sighash_type != SIGHASH_DEFAULT {
+                value.push(sighash_type);
+            }
+
+            psbt.inputs[index].insert(vec![PSBT_IN_TAP_KEY_SIG], value);
+            signed.push(index);
+        }
+    }
+
+    Ok(signed)
+}
+
+/// BIP-341 output key secret: `d' = ±d + hash_TapTweak(P.x || merkle_root)`
+///
+/// `d` is negated first when `P = d·G` has an odd y coordinate.
+fn taproot_tweaked_key(
+    secure_key: &SecureBuffer,
+    x_only: &[u8; 32],
+    merkle_root: Option<&[u8]>,
+) -> Result<SecureBuffer, SignerError> {
+    let tweak = tagged_hash("TapTweak", &[x_only, merkle_root.unwrap_or_default()]);
+    let tweak = Option::<Scalar>::from(Scalar::from_repr(tweak.into()))
+        .ok_or_else(|| SignerError::SigningFailed("taproot tweak out of range".to_string()))?;
+
+    let mut secret = Option::<Scalar>::from(Scalar::from_repr(
+        <[u8; 32]>::try_from(secure_key.as_slice()).expect("32-byte key").into(),
+    ))
+    .ok_or_else(|| SignerError::SigningFailed("Invalid secp256k1 key".to_string()))?;
+    if bool::from((ProjectivePoint::GENERATOR * secret).to_affine().y_is_odd()) {
+        secret = -secret;
+    }
+
+    let mut tweaked = secret + tweak;
+    secret.zeroize();
+    if bool::from(tweaked.is_zero()) {
+        return Err(SignerError::SigningFailed("taproot tweak produced a zero key".to_string()));
+    }
+    let mut bytes: [u8; 32] = tweaked.to_bytes().into();
+    tweaked.zeroize();
+    let buffer = SecureBuffer::from_slice_with_mode(&bytes, get_locking_mode());
+    bytes.zeroize();
+    buffer
+}
+
+/// Decrypt a key container and sign the PSBT inputs it controls
+///
+/// Same security model as `decrypt_and_sign`. The container's 32-byte key
+/// is used as a secp256k1 scalar, as for EVM signing.
+///
+/// # Arguments
+/// * `container_json` - JSON-serialized EncryptedKeyContainer
+/// * `passphrase` - The passphrase for decryption
+/// * `psbt` - The PSBT (base64)
+pub fn decrypt_and_sign_psbt(
+    container_json: &str,
+    passphrase: &str,
+    psbt: &str,
+) -> Result<PsbtSigningResult, SignerError> {
+    let container = EncryptedKeyContainer::from_json(container_json)?;
+    let mut psbt = Psbt::from_base64(psbt)?;
+
+    let mut secure_key = container.decrypt_key(passphrase)?;
+    let public_key = k256::ecdsa::SigningKey::from_slice(secure_key.as_slice())
+        .map(|key| hex::encode(key.verifying_key().to_encoded_point(true).as_bytes()));
+    let signed = sign_psbt_with_secure_key(&mut secure_key, &mut psbt);
+    secure_key.zeroize();
+
+    Ok(PsbtSigningResult {
+        psbt: psbt.to_base64(),
+        signed_inputs: signed?,
+        public_key: public_key.map_err(|e| SignerError::SigningFailed(format!("Invalid secp256k1 key: {}", e)))?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitcoin::transaction::{OutPoint, TxIn};
+    use crate::crypto::Cipher;
+    use crate::kdf::KdfParams;
+    use k256::ecdsa::signature::hazmat::PrehashVerifier;
+
+    fn psbt_for(spent: &[TxOut]) -> Psbt {
+        let tx = Transaction {
+            version: 2,
+            inputs: (0..spent.len())
+                .map(|i| TxIn {
+                    previous_output: OutPoint {
+                        txid: [i as u8 + 1; 32],
+                        vout: i as u32,
+                    },
+                    script_sig: Vec::new(),
+                    sequence: 0xffff_fffd,
+                    witness: Vec::new(),
+                })
+                .collect(),
+            outputs: vec![TxOut {
+                value: 90_000,
+                script_pubkey: vec![0x00, 0x14, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9],
+            }],
+            lock_time: 0,
+        };
+        let mut global = PsbtMap::default();
+        global.insert(vec![PSBT_GLOBAL_UNSIGNED_TX], tx.serialize());
+        // Unknown global field, which must survive signing
+        global.insert(vec![0xfc, 1, 2], vec![3]);
+
+        let inputs = spent
+            .iter()
+            .map(|output| {
+                let mut map = PsbtMap::default();
+                map.insert(vec![PSBT_IN_WITNESS_UTXO], output.serialize());
+                map
+            })
+            .collect();
+        Psbt {
+            unsigned_tx: tx,
+            global,
+            inputs,
+            outputs: vec![PsbtMap::default()],
+        }
+    }
+
+    #[test]
+    fn test_sign_psbt_p2wpkh_and_p2tr() {
+        std::env::set_var("SIGNER_ALLOW_INSECURE_MEMORY", "1");
+        let seed = [5u8; 32];
+        let container =
+            EncryptedKeyContainer::encrypt_with_kdf(&seed, "pw", Cipher::Aes256Gcm, KdfParams::MINIMUM).unwrap();
+        let ecdsa_key = k256::ecdsa::SigningKey::from_slice(&seed).unwrap();
+        let public_key = ecdsa_key.verifying_key().to_encoded_point(true).as_bytes().to_vec();
+        let x_only: [u8; 32] = public_key[1..].try_into().unwrap();
+
+        let mut p2wpkh = vec![0x00, 0x14];
+        p2wpkh.extend_from_slice(&Ripemd160::digest(Sha256::digest(&public_key)));
+        let secure = SecureBuffer::from_slice_with_mode(&seed, get_locking_mode()).unwrap();
+        let output_key = k256::schnorr::SigningKey::from_bytes(taproot_tweaked_key(&secure, &x_only, None).unwrap().as_slice())
+            .unwrap()
+            .verifying_key()
+            .to_bytes();
+        let mut p2tr = vec![0x51, 0x20];
+        p2tr.extend_from_slice(&output_key);
+
+        let spent = [
+            TxOut { value: 50_000, script_pubkey: p2wpkh },
+            TxOut { value: 45_000, script_pubkey: p2tr },
+            TxOut { value: 1_000, script_pubkey: vec![0x51, 0x20, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1] },
+        ];
+        let original = psbt_for(&spent);
+        let result = decrypt_and_sign_psbt(&container.to_json().unwrap(), "pw", &original.to_base64()).unwrap();
+        assert_eq!(result.signed_inputs, vec![0, 1]);
+        assert_eq!(result.public_key, hex::encode(&public_key));
+
+        let signed = Psbt::from_base64(&result.psbt).unwrap();
+        assert_eq!(signed.global, original.global);
+        assert_eq!(signed.inputs[2], original.inputs[2]);
+
+        // P2WPKH: DER signature plus SIGHASH_ALL under the compressed key
+        let mut key = vec![PSBT_IN_PARTIAL_SIG];
+        key.extend_from_slice(&public_key);
+        let (_, value) = signed.inputs[0].pairs.iter().find(|(k, _)| *k == key).unwrap();
+        assert_eq!(*value.last().unwrap(), SIGHASH_ALL);
+        let signature = k256::ecdsa::Signature::from_der(&value[..value.len() - 1]).unwrap();
+        assert!(signature.normalize_s().is_none());
+        let sighash = original
+            .unsigned_tx
+            .segwit_v0_sighash(0, &p2wpkh_script_code(spent[0].script_pubkey[2..].try_into().unwrap()), 50_000, SIGHASH_ALL)
+            .unwrap();
+        assert!(ecdsa_key.verifying_key().verify_prehash(&sighash, &signature).is_ok());
+
+        // P2TR: 64-byte SIGHASH_DEFAULT signature under the tweaked output key
+        let value = signed.inputs[1].get(PSBT_IN_TAP_KEY_SIG).unwrap();
+        assert_eq!(value.len(), 64);
+        let sighash = original.unsigned_tx.taproot_key_spend_sighash(1, &spent, SIGHASH_DEFAULT).unwrap();
+        let verifying_key = k256::schnorr::VerifyingKey::from_bytes(&output_key).unwrap();
+        let signature = k256::schnorr::Signature::try_from(value).unwrap();
+        assert!(verifying_key.verify_raw(&sighash, &signature).is_ok());
+
+        assert!(decrypt_and_sign_psbt(&container.to_json().unwrap(), "wrong", &original.to_base64()).is_err());
+    }
+
+    #[test]
+    fn test_bip86_output_key() {
+        std::env::set_var("SIGNER_ALLOW_INSECURE_MEMORY", "1");
+        // BIP-86 test vector: first receiving address of the test mnemonic
+        let secret = hex::decode("41f41d69260df4cf277826a9b65a3717e4eeddbeedf637f212ca096576479361").unwrap();
+        let secure = SecureBuffer::from_slice_with_mode(&secret, get_locking_mode()).unwrap();
+        let internal = k256::schnorr::SigningKey::from_bytes(&secret).unwrap().verifying_key().to_bytes();
+        assert_eq!(
+            hex::encode(internal),
+            "cc8a4bc64d897bddc5fbc2f670f7a8ba0b386779106cf1223c6fc5d7cd6fc115"
+        );
+
+        let tweaked = taproot_tweaked_key(&secure, &internal.into(), None).unwrap();
+        let output = k256::schnorr::SigningKey::from_bytes(tweaked.as_slice()).unwrap().verifying_key().to_bytes();
+        assert_eq!(
+            hex::encode(output),
+            "a60869f0dbcf1dc659c9cecbaf8050135ea9e8cdc487053f1dc6880949dc684c"
+        );
+    }
+}
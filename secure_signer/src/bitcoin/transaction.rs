@@ -0,0 +1,426 @@
+//! Bitcoin transactions and signature hashes
+//!
+//! Parses and serializes transactions (with or without segwit witnesses)
+//! and computes the two signature hashes needed for modern outputs:
+//! BIP-143 (segwit v0, e.g. P2WPKH) and BIP-341 (taproot key path).
+
+use sha2::{Digest, Sha256};
+
+use crate::error::SignerError;
+use crate::reader::{ByteReader, ParseError};
+
+/// Sign all inputs and outputs (segwit v0 default)
+pub const SIGHASH_ALL: u8 = 0x01;
+/// Sign no outputs
+pub const SIGHASH_NONE: u8 = 0x02;
+/// Sign only the output at the same index
+pub const SIGHASH_SINGLE: u8 = 0x03;
+/// Sign only this input (combined with one of the above)
+pub const SIGHASH_ANYONECANPAY: u8 = 0x80;
+/// Taproot default: like `SIGHASH_ALL`, without a trailing sighash byte
+pub const SIGHASH_DEFAULT: u8 = 0x00;
+
+/// Reference to a previous transaction output
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OutPoint {
+    /// Previous transaction ID (internal byte order)
+    pub txid: [u8; 32],
+    /// Output index
+    pub vout: u32,
+}
+
+/// Transaction input
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TxIn {
+    /// The output being spent
+    pub previous_output: OutPoint,
+    /// Legacy unlocking script
+    pub script_sig: Vec<u8>,
+    /// Sequence number
+    pub sequence: u32,
+    /// Segwit witness stack
+    pub witness: Vec<Vec<u8>>,
+}
+
+/// Transaction output
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TxOut {
+    /// Amount in satoshis
+    pub value: u64,
+    /// Locking script
+    pub script_pubkey: Vec<u8>,
+}
+
+impl TxOut {
+    /// Parse a serialized output (`value || script_pubkey`)
+    pub fn parse(bytes: &[u8]) -> Result<Self, SignerError> {
+        let mut reader = ByteReader::new(bytes, "Bitcoin output");
+        let output = read_tx_out(&mut reader)?;
+        reader.finish()?;
+        Ok(output)
+    }
+
+    /// Serialize as `value || script_pubkey`
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(9 + self.script_pubkey.len());
+        self.serialize_into(&mut out);
+        out
+    }
+
+    fn serialize_into(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.value.to_le_bytes());
+        write_bytes(out, &self.script_pubkey);
+    }
+}
+
+/// A Bitcoin transaction
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Transaction {
+    /// Version
+    pub version: i32,
+    /// Inputs
+    pub inputs: Vec<TxIn>,
+    /// Outputs
+    pub outputs: Vec<TxOut>,
+    /// Lock time
+    pub lock_time: u32,
+}
+
+impl Transaction {
+    /// Parse a serialized transaction (legacy or segwit format)
+    pub fn parse(bytes: &[u8]) -> Result<Self, SignerError> {
+        let mut reader = ByteReader::new(bytes, "Bitcoin transaction");
+        let tx = read_transaction(&mut reader)?;
+        reader.finish()?;
+        Ok(tx)
+    }
+
+    /// Serialize, including witnesses if any input has one
+    pub fn serialize(&self) -> Vec<u8> {
+        let with_witness = self.inputs.iter().any(|input| !input.witness.is_empty());
+        self.serialize_with(with_witness)
+    }
+
+    /// Serialize without witnesses (the form hashed into the txid)
+    pub fn serialize_no_witness(&self) -> Vec<u8> {
+        self.serialize_with(false)
+    }
+
+    /// Transaction ID (internal byte order; displayed reversed)
+    pub fn txid(&self) -> [u8; 32] {
+        sha256d(&self.serialize_no_witness())
+    }
+
+    fn serialize_with(&self, with_witness: bool) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(&self.version.to_le_bytes());
+        if with_witness {
+            out.extend_from_slice(&[0x00, 0x01]);
+        }
+
+        write_compact_size(&mut out, self.inputs.len() as u64);
+        for input in &self.inputs {
+            write_outpoint(&mut out, &input.previous_output);
+            write_bytes(&mut out, &input.script_sig);
+            out.extend_from_slice(&input.sequence.to_le_bytes());
+        }
+
+        write_compact_size(&mut out, self.outputs.len() as u64);
+        self.outputs.iter().for_each(|output| output.serialize_into(&mut out));
+
+        if with_witness {
+            for input in &self.inputs {
+                write_compact_size(&mut out, input.witness.len() as u64);
+                input.witness.iter().for_each(|item| write_bytes(&mut out, item));
+            }
+        }
+
+        out.extend_from_slice(&self.lock_time.to_le_bytes());
+        out
+    }
+
+    /// BIP-143 signature hash for a segwit v0 input
+    ///
+    /// `script_code` is the script being satisfied (for P2WPKH, the
+    /// equivalent P2PKH script, see [`p2wpkh_script_code`]) and `value` the
+    /// amount of the output being spent.
+    pub fn segwit_v0_sighash(
+        &self,
+        input_index: usize,
+        script_code: &[u8],
+        value: u64,
+        sighash_type: u8,
+    ) -> Result<[u8; 32], SignerError> {
+        let input = self.input(input_index)?;
+        let anyone_can_pay = sighash_type & SIGHASH_ANYONECANPAY != 0;
+        let base_type = sighash_type & 0x1f;
+
+        let hash_prevouts = if anyone_can_pay {
+            [0u8; 32]
+        } else {
+            sha256d(&self.prevouts_bytes())
+        };
+        let hash_sequence = if anyone_can_pay || base_type == SIGHASH_SINGLE || base_type == SIGHASH_NONE {
+            [0u8; 32]
+        } else {
+            sha256d(&self.sequences_bytes())
+        };
+        let hash_outputs = match base_type {
+            SIGHASH_SINGLE if input_index < self.outputs.len() => sha256d(&self.outputs[input_index].serialize()),
+            SIGHASH_SINGLE | SIGHASH_NONE => [0u8; 32],
+            _ => sha256d(&self.outputs_bytes()),
+        };
+
+        let mut preimage = Vec::with_capacity(160 + script_code.len());
+        preimage.extend_from_slice(&self.version.to_le_bytes());
+        preimage.extend_from_slice(&hash_prevouts);
+        preimage.extend_from_slice(&hash_sequence);
+        write_outpoint(&mut preimage, &input.previous_output);
+        write_bytes(&mut preimage, script_code);
+        preimage.extend_from_slice(&value.to_le_bytes());
+        preimage.extend_from_slice(&input.sequence.to_le_bytes());
+        preimage.extend_from_slice(&hash_outputs);
+        preimage.extend_from_slice(&self.lock_time.to_le_bytes());
+        preimage.extend_from_slice(&(sighash_type as u32).to_le_bytes());
+        Ok(sha256d(&preimage))
+    }
+
+    /// BIP-341 signature hash for a taproot key-path spend
+    ///
+    /// `prevouts` are the outputs spent by every input, in input order.
+    pub fn taproot_key_spend_sighash(
+        &self,
+        input_index: usize,
+        prevouts: &[TxOut],
+        sighash_type: u8,
+    ) -> Result<[u8; 32], SignerError> {
+        if !matches!(sighash_type, 0x00..=0x03 | 0x81..=0x83) {
+            return Err(SignerError::InvalidTransaction(format!(
+                "invalid taproot sighash type {:#04x}",
+                sighash_type
+            )));
+        }
+        if prevouts.len() != self.inputs.len() {
+            return Err(SignerError::InvalidTransaction(
+                "taproot sighash needs the spent output of every input".to_string(),
+            ));
+        }
+        let input = self.input(input_index)?;
+        let anyone_can_pay = sighash_type & SIGHASH_ANYONECANPAY != 0;
+        let output_type = sighash_type & 0x03;
+
+        // Epoch 0
+        let mut msg = vec![0x00, sighash_type];
+        msg.extend_from_slice(&self.version.to_le_bytes());
+        msg.extend_from_slice(&self.lock_time.to_le_bytes());
+
+        if !anyone_can_pay {
+            msg.extend_from_slice(&Sha256::digest(self.prevouts_bytes()));
+            let amounts: Vec<u8> = prevouts.iter().flat_map(|p| p.value.to_le_bytes()).collect();
+            msg.extend_from_slice(&Sha256::digest(amounts));
+            let mut scripts = Vec::new();
+            prevouts.iter().for_each(|p| write_bytes(&mut scripts, &p.script_pubkey));
+            msg.extend_from_slice(&Sha256::digest(scripts));
+            msg.extend_from_slice(&Sha256::digest(self.sequences_bytes()));
+        }
+        if output_type != SIGHASH_NONE && output_type != SIGHASH_SINGLE {
+            msg.extend_from_slice(&Sha256::digest(self.outputs_bytes()));
+        }
+
+        // Key path, no annex
+        msg.push(0x00);
+
+        if anyone_can_pay {
+            write_outpoint(&mut msg, &input.previous_output);
+            prevouts[input_index].serialize_into(&mut msg);
+            msg.extend_from_slice(&input.sequence.to_le_bytes());
+        } else {
+            msg.extend_from_slice(&(input_index as u32).to_le_bytes());
+        }
+
+        if output_type == SIGHASH_SINGLE {
+            let output = self.outputs.get(input_index).ok_or_else(|| {
+                SignerError::InvalidTransaction("SIGHASH_SINGLE without a matching output".to_string())
+            })?;
+            msg.extend_from_slice(&Sha256::digest(output.serialize()));
+        }
+
+        Ok(tagged_hash("TapSighash", &[&msg]))
+    }
+
+    fn input(&self, index: usize) -> Result<&TxIn, SignerError> {
+        self.inputs
+            .get(index)
+            .ok_or_else(|| SignerError::InvalidTransaction(format!("input {} does not exist", index)))
+    }
+
+    fn prevouts_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(36 * self.inputs.len());
+        self.inputs.iter().for_each(|i| write_outpoint(&mut out, &i.previous_output));
+        out
+    }
+
+    fn sequences_bytes(&self) -> Vec<u8> {
+        self.inputs.iter().flat_map(|i| i.sequence.to_le_bytes()).collect()
+    }
+
+    fn outputs_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        self.outputs.iter().for_each(|o| o.serialize_into(&mut out));
+        out
+    }
+}
+
+/// BIP-143 script code for a P2WPKH output with public key hash `pkh`
+pub fn p2wpkh_script_code(pkh: &[u8; 20]) -> Vec<u8> {
+    let mut script = vec![0x76, 0xa9, 0x14];
+    script.extend_from_slice(pkh);
+    script.extend_from_slice(&[0x88, 0xac]);
+    script
+}
+
+/// Double SHA-256
+pub(crate) fn sha256d(data: &[u8]) -> [u8; 32] {
+    Sha256::digest(Sha256::digest(data)).into()
+}
+
+/// BIP-340 tagged hash
+pub(crate) fn tagged_hash(tag: &str, parts: &[&[u8]]) -> [u8; 32] {
+    let tag_hash = Sha256::digest(tag.as_bytes());
+    let mut hasher = Sha256::new();
+    hasher.update(tag_hash);
+    hasher.update(tag_hash);
+    parts.iter().for_each(|part| hasher.update(part));
+    hasher.finalize().into()
+}
+
+/// Write a Bitcoin CompactSize integer
+pub(crate) fn write_compact_size(out: &mut Vec<u8>, value: u64) {
+    match value {
+        0..=0xfc => out.push(value as u8),
+        0xfd..=0xffff => {
+            out.push(0xfd);
+            out.extend_from_slice(&(value as u16).to_le_bytes());
+        }
+        0x1_0000..=0xffff_ffff => {
+            out.push(0xfe);
+            out.extend_from_slice(&(value as u32).to_le_bytes());
+        }
+        _ => {
+            out.push(0xff);
+            out.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+}
+
+/// Write length-prefixed bytes
+pub(crate) fn write_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    write_compact_size(out, bytes.len() as u64);
+    out.extend_from_slice(bytes);
+}
+
+fn write_outpoint(out: &mut Vec<u8>, outpoint: &OutPoint) {
+    out.extend_from_slice(&outpoint.txid);
+    out.extend_from_slice(&outpoint.vout.to_le_bytes());
+}
+
+/// Read a CompactSize integer
+pub(crate) fn read_compact_size(r: &mut ByteReader) -> Result<u64, ParseError> {
+    Ok(match r.u8()? {
+        0xfd => u64::from(r.u16_le()?),
+        0xfe => u64::from(r.u32_le()?),
+        0xff => r.u64_le()?,
+        n => u64::from(n),
+    })
+}
+
+/// Read CompactSize-prefixed bytes
+pub(crate) fn read_bytes<'a>(r: &mut ByteReader<'a>) -> Result<&'a [u8], ParseError> {
+    let len = read_compact_size(r)?;
+    r.take_len(len)
+}
+
+/// Read a CompactSize element count, rejecting counts that cannot fit
+fn read_count(r: &mut ByteReader, min_item_size: usize) -> Result<usize, ParseError> {
+    let count = read_compact_size(r)?;
+    r.count(count, min_item_size)
+}
+
+fn read_tx_out(r: &mut ByteReader) -> Result<TxOut, ParseError> {
+    Ok(TxOut {
+        value: r.u64_le()?,
+        script_pubkey: read_bytes(r)?.to_vec(),
+    })
+}
+
+fn read_transaction(r: &mut ByteReader) -> Result<Transaction, ParseError> {
+    let version = r.u32_le()? as i32;
+    let segwit = r.peek(0) == Some(0x00) && r.peek(1) == Some(0x01);
+    if segwit {
+        r.take(2)?;
+    }
+
+    let input_count = read_count(r, 41)?;
+    let mut inputs = Vec::with_capacity(input_count);
+    for _ in 0..input_count {
+        inputs.push(TxIn {
+            previous_output: OutPoint {
+                txid: r.array()?,
+                vout: r.u32_le()?,
+            },
+            script_sig: read_bytes(r)?.to_vec(),
+            sequence: r.u32_le()?,
+            witness: Vec::new(),
+        });
+    }
+
+    let output_count = read_count(r, 9)?;
+    let outputs = (0..output_count).map(|_| read_tx_out(r)).collect::<Result<Vec<_>, _>>()?;
+
+    if segwit {
+        for input in &mut inputs {
+            let items = read_count(r, 1)?;
+            input.witness = (0..items)
+                .map(|_| read_bytes(r).map(<[u8]>::to_vec))
+                .collect::<Result<Vec<_>, _>>()?;
+        }
+    }
+
+    Ok(Transaction {
+        version,
+        inputs,
+        outputs,
+        lock_time: r.u32_le()?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bip143_native_p2wpkh_sighash() {
+        // BIP-143 "Native P2WPKH" example, second input
+        let tx = Transaction::parse(&hex::decode(
+            "0100000002fff7f7881a8099afa6940d42d1e7f6362bec38171ea3edf433541db4e4ad969f0000000000eeffffff\
+             ef51e1b804cc89d182d279655c3aa89e815b1b309fe287d9b2b55d57b90ec68a0100000000ffffffff02202cb206\
+             000000001976a9148280b37df378db99f66f85c95a783a76ac7a6d5988ac9093510d000000001976a9143bde42db\
+             ee7e4dbe6a21b2d50ce2f0167faa815988ac11000000",
+        )
+        .unwrap())
+        .unwrap();
+        assert_eq!(tx.inputs.len(), 2);
+        assert_eq!(tx.lock_time, 0x11);
+
+        let pkh: [u8; 20] = hex::decode("1d0f172a0ecb48aee1be1f2687d2963ae33f71a1").unwrap().try_into().unwrap();
+        let sighash = tx.segwit_v0_sighash(1, &p2wpkh_script_code(&pkh), 600_000_000, SIGHASH_ALL).unwrap();
+        assert_eq!(hex::encode(sighash), "c37af31116d1b27caf68aae9e3ac82f1477929014d5b917657d0eb49478cb670");
+
+        // Round trip, with and without a witness
+        assert_eq!(Transaction::parse(&tx.serialize()).unwrap(), tx);
+        let mut witnessed = tx.clone();
+        witnessed.inputs[1].witness = vec![vec![1, 2, 3], vec![4]];
+        assert_eq!(Transaction::parse(&witnessed.serialize()).unwrap(), witnessed);
+        assert_eq!(witnessed.txid(), tx.txid());
+    }
+}
@@ -0,0 +1,712 @@
+//! Broadcast helper for signed transactions
+//!
+//! Submits signed transactions to user-configured JSON-RPC endpoints:
+//! - Solana: `sendTransaction` (base64 wire format)
+//! - EVM: `eth_sendRawTransaction` (0x-prefixed hex)
+//!
+//! and tracks them until they reach a target commitment:
+//! - Solana: `getSignatureStatuses` (processed / confirmed / finalized)
+//! - EVM: `eth_getTransactionReceipt`, finalized once the `finalized` block
+//!   tag has passed the receipt's block
+//!
+//! Status results can be appended to an [`AuditLog`], referencing the
+//! `Signed` entry they belong to, which gives a signed → confirmed record.
+//!
+//! `Broadcaster` also implements [`FeeEstimator`] from live data
+//! (`getRecentPrioritizationFees`, `eth_feeHistory`).
+//!
+//! # Air-Gapped Builds
+//!
+//! This module is only compiled with the `broadcast` feature, which is off
+//! by default. Air-gapped signer builds must never enable it: the signing
+//! core itself performs no network I/O.
+//!
+//! # Privacy
+//!
+//! Requests can be routed through a SOCKS5 proxy such as Tor
+//! (`socks5://127.0.0.1:9050`). Hostnames are resolved by the proxy, so the
+//! RPC endpoint is never looked up through local DNS.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::audit::{AuditEvent, AuditLog};
+use crate::error::SignerError;
+use crate::fees::{Eip1559Fee, FeeEstimator, SolanaFee};
+
+/// Default request timeout
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Compute-unit limit used for Solana fee estimates (runtime default per transaction)
+const DEFAULT_COMPUTE_UNIT_LIMIT: u32 = 200_000;
+
+/// Number of recent blocks sampled for EVM priority fees
+const FEE_HISTORY_BLOCKS: u64 = 5;
+
+/// Chain-specific transaction identifier
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(tag = "chain", content = "id", rename_all = "lowercase")]
+pub enum TxId {
+    /// Solana transaction signature (base58)
+    Solana(String),
+    /// EVM transaction hash (0x-prefixed hex)
+    Evm(String),
+}
+
+impl TxId {
+    /// Chain family label used in audit events
+    pub fn chain(&self) -> &'static str {
+        match self {
+            TxId::Solana(_) => "solana",
+            TxId::Evm(_) => "evm",
+        }
+    }
+
+    /// The raw identifier
+    pub fn id(&self) -> &str {
+        match self {
+            TxId::Solana(id) | TxId::Evm(id) => id,
+        }
+    }
+}
+
+/// Confirmation depth, ordered from weakest to strongest
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Commitment {
+    /// Seen in a block (Solana: processed)
+    Processed,
+    /// Voted on by a supermajority (Solana) / included in a block (EVM)
+    Confirmed,
+    /// Rooted (Solana) / behind the finalized checkpoint (EVM)
+    Finalized,
+}
+
+/// Observed status of a broadcast transaction
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(tag = "status", rename_all = "lowercase")]
+pub enum TxStatus {
+    /// Not (yet) known to the node
+    Pending,
+    /// Landed successfully at the given depth
+    Landed {
+        commitment: Commitment,
+        /// Slot (Solana) or block number (EVM)
+        slot: u64,
+    },
+    /// Landed but execution failed
+    Failed {
+        slot: u64,
+        reason: String,
+    },
+}
+
+impl TxStatus {
+    /// Whether polling can stop for the given target commitment
+    pub fn is_final_for(&self, target: Commitment) -> bool {
+        match self {
+            TxStatus::Pending => false,
+            TxStatus::Landed { commitment, .. } => *commitment >= target,
+            TxStatus::Failed { .. } => true,
+        }
+    }
+
+    /// Build the audit event recording this status
+    pub fn to_audit_event(&self, signed_entry: u64, tx: &TxId) -> AuditEvent {
+        let (status, slot, error) = match self {
+            TxStatus::Pending => ("pending".to_string(), None, None),
+            TxStatus::Landed { commitment, slot } => {
+                let label = serde_json::to_value(commitment)
+                    .ok()
+                    .and_then(|v| v.as_str().map(str::to_string))
+                    .unwrap_or_default();
+                (label, Some(*slot), None)
+            }
+            TxStatus::Failed { slot, reason } => {
+                ("failed".to_string(), Some(*slot), Some(reason.clone()))
+            }
+        };
+
+        AuditEvent::TxStatus {
+            signed_entry,
+            chain: tx.chain().to_string(),
+            tx_id: tx.id().to_string(),
+            status,
+            slot,
+            error,
+        }
+    }
+}
+
+/// Connection settings for an RPC endpoint
+#[derive(Clone, Debug)]
+pub struct BroadcastConfig {
+    /// JSON-RPC endpoint URL (http or https)
+    pub endpoint: String,
+    /// Optional proxy, e.g. `socks5://127.0.0.1:9050` for Tor
+    pub proxy: Option<String>,
+    /// Timeout for each request
+    pub timeout: Duration,
+}
+
+impl BroadcastConfig {
+    /// Direct connection to `endpoint` with the default timeout
+    pub fn new(endpoint: &str) -> Self {
+        Self {
+            endpoint: endpoint.to_string(),
+            proxy: None,
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// Route requests through a proxy
+    ///
+    /// `socks5h://` is accepted as an alias for `socks5://`; remote DNS
+    /// resolution is always used for SOCKS5.
+    pub fn with_proxy(mut self, proxy: &str) -> Self {
+        self.proxy = Some(proxy.to_string());
+        self
+    }
+
+    /// Override the request timeout
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+/// JSON-RPC client that submits signed transactions
+pub struct Broadcaster {
+    agent: ureq::Agent,
+    endpoint: String,
+    next_id: AtomicU64,
+}
+
+impl Broadcaster {
+    /// Create a broadcaster for the configured endpoint
+    pub fn new(config: &BroadcastConfig) -> Result<Self, SignerError> {
+        if !config.endpoint.starts_with("http://") && !config.endpoint.starts_with("https://") {
+            return Err(SignerError::BroadcastError(format!(
+                "RPC endpoint must be an http(s) URL: {}",
+                config.endpoint
+            )));
+        }
+
+        let mut builder = ureq::AgentBuilder::new().timeout(config.timeout);
+
+        if let Some(proxy) = &config.proxy {
+            let normalized = match proxy.strip_prefix("socks5h://") {
+                Some(rest) => format!("socks5://{}", rest),
+                None => proxy.clone(),
+            };
+            let proxy = ureq::Proxy::new(&normalized)
+                .map_err(|e| SignerError::BroadcastError(format!("Invalid proxy: {}", e)))?;
+            builder = builder.proxy(proxy);
+        }
+
+        Ok(Self {
+            agent: builder.build(),
+            endpoint: config.endpoint.clone(),
+            next_id: AtomicU64::new(1),
+        })
+    }
+
+    /// Submit a signed Solana transaction (wire format)
+    ///
+    /// # Returns
+    /// The transaction signature (base58) reported by the node
+    pub fn send_solana_transaction(&self, signed_tx: &[u8]) -> Result<String, SignerError> {
+        let encoded = base64::Engine::encode(&base64::engine::general_purpose::STANDARD, signed_tx);
+        let result = self.call(
+            "sendTransaction",
+            json!([encoded, { "encoding": "base64" }]),
+        )?;
+        expect_string(result, "sendTransaction")
+    }
+
+    /// Submit a signed, RLP-encoded EVM transaction
+    ///
+    /// # Returns
+    /// The transaction hash (0x-prefixed hex) reported by the node
+    pub fn send_evm_raw_transaction(&self, raw_tx: &[u8]) -> Result<String, SignerError> {
+        let encoded = format!("0x{}", hex::encode(raw_tx));
+        let result = self.call("eth_sendRawTransaction", json!([encoded]))?;
+        expect_string(result, "eth_sendRawTransaction")
+    }
+
+    /// Submit a signed transaction and record the broadcast in the audit log
+    ///
+    /// `signed_entry` is the sequence number of the `Signed` audit entry for
+    /// this transaction.
+    pub fn send_and_record(
+        &self,
+        audit: &mut AuditLog,
+        signed_entry: u64,
+        chain: &str,
+        signed_tx: &[u8],
+    ) -> Result<TxId, SignerError> {
+        let tx = match chain {
+            "solana" => TxId::Solana(self.send_solana_transaction(signed_tx)?),
+            "evm" => TxId::Evm(self.send_evm_raw_transaction(signed_tx)?),
+            other => {
+                return Err(SignerError::BroadcastError(format!(
+                    "Unsupported chain: {}",
+                    other
+                )))
+            }
+        };
+
+        audit.append(AuditEvent::Broadcast {
+            signed_entry,
+            chain: tx.chain().to_string(),
+            tx_id: tx.id().to_string(),
+        })?;
+        Ok(tx)
+    }
+
+    /// Query the current status of a transaction
+    pub fn status(&self, tx: &TxId) -> Result<TxStatus, SignerError> {
+        match tx {
+            TxId::Solana(signature) => self.solana_status(signature),
+            TxId::Evm(hash) => self.evm_status(hash),
+        }
+    }
+
+    /// Poll until the transaction reaches `target`, fails, or `timeout` elapses
+    ///
+    /// Returns the last observed status; on timeout this may be `Pending`
+    /// or a weaker commitment than requested.
+    pub fn wait_for(
+        &self,
+        tx: &TxId,
+        target: Commitment,
+        timeout: Duration,
+        poll_interval: Duration,
+    ) -> Result<TxStatus, SignerError> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let status = self.status(tx)?;
+            if status.is_final_for(target) || Instant::now() + poll_interval > deadline {
+                return Ok(status);
+            }
+            std::thread::sleep(poll_interval);
+        }
+    }
+
+    /// Wait for a transaction and append the outcome to the audit log
+    pub fn track(
+        &self,
+        audit: &mut AuditLog,
+        signed_entry: u64,
+        tx: &TxId,
+        target: Commitment,
+        timeout: Duration,
+        poll_interval: Duration,
+    ) -> Result<TxStatus, SignerError> {
+        let status = self.wait_for(tx, target, timeout, poll_interval)?;
+        audit.append(status.to_audit_event(signed_entry, tx))?;
+        Ok(status)
+    }
+
+    fn solana_status(&self, signature: &str) -> Result<TxStatus, SignerError> {
+        let result = self.call(
+            "getSignatureStatuses",
+            json!([[signature], { "searchTransactionHistory": true }]),
+        )?;
+
+        let entry = &result["value"][0];
+        if entry.is_null() {
+            return Ok(TxStatus::Pending);
+        }
+
+        let slot = entry["slot"].as_u64().unwrap_or(0);
+        if !entry["err"].is_null() {
+            return Ok(TxStatus::Failed {
+                slot,
+                reason: entry["err"].to_string(),
+            });
+        }
+
+        let commitment = match entry["confirmationStatus"].as_str() {
+            Some("finalized") => Commitment::Finalized,
+            Some("confirmed") => Commitment::Confirmed,
+            _ => Commitment::Processed,
+        };
+        Ok(TxStatus::Landed { commitment, slot })
+    }
+
+    fn evm_status(&self, hash: &str) -> Result<TxStatus, SignerError> {
+        let receipt = self.call("eth_getTransactionReceipt", json!([hash]))?;
+        if receipt.is_null() {
+            return Ok(TxStatus::Pending);
+        }
+
+        let block = parse_quantity(&receipt["blockNumber"])?;
+        if receipt["status"].as_str() == Some("0x0") {
+            return Ok(TxStatus::Failed {
+                slot: block,
+                reason: "execution reverted".to_string(),
+            });
+        }
+
+        let finalized = self.call("eth_getBlockByNumber", json!(["finalized", false]))?;
+        let commitment = match finalized.get("number").map(parse_quantity) {
+            Some(Ok(n)) if n >= block => Commitment::Finalized,
+            _ => Commitment::Confirmed,
+        };
+        Ok(TxStatus::Landed {
+            commitment,
+            slot: block,
+        })
+    }
+
+    /// Perform a JSON-RPC 2.0 call and return its `result`
+    pub fn call(&self, method: &str, params: Value) -> Result<Value, SignerError> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": method,
+            "params": params,
+        });
+
+        let response = self
+            .agent
+            .post(&self.endpoint)
+            .set("Content-Type", "application/json")
+            .send_string(&body.to_string());
+
+        let text = match response {
+            Ok(resp) => resp.into_string()?,
+            // JSON-RPC servers often report errors with a non-2xx status and a JSON body
+            Err(ureq::Error::Status(_, resp)) => resp.into_string()?,
+            Err(e) => return Err(SignerError::BroadcastError(e.to_string())),
+        };
+
+        parse_rpc_response(&text)
+    }
+}
+
+impl FeeEstimator for Broadcaster {
+    /// Median of recent prioritization fees for the given accounts
+    fn solana_fee(&self, writable_accounts: &[[u8; 32]]) -> Result<SolanaFee, SignerError> {
+        let accounts: Vec<String> = writable_accounts
+            .iter()
+            .map(|a| bs58::encode(a).into_string())
+            .collect();
+        let result = self
+            .call("getRecentPrioritizationFees", json!([accounts]))
+            .map_err(|e| SignerError::FeeEstimationError(e.to_string()))?;
+
+        let fees: Vec<u64> = result
+            .as_array()
+            .map(|entries| {
+                entries
+                    .iter()
+                    .filter_map(|e| e["prioritizationFee"].as_u64())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(SolanaFee {
+            compute_unit_limit: DEFAULT_COMPUTE_UNIT_LIMIT,
+            compute_unit_price: median(fees).unwrap_or(0),
+        })
+    }
+
+    /// Next-block base fee and the median tip over recent blocks
+    fn evm_fee(&self, chain_id: u64) -> Result<Eip1559Fee, SignerError> {
+        let remote_chain = parse_quantity(&self.call("eth_chainId", json!([]))?)?;
+        if remote_chain != chain_id {
+            return Err(SignerError::FeeEstimationError(format!(
+                "endpoint serves chain {}, expected {}",
+                remote_chain, chain_id
+            )));
+        }
+
+        let history = self.call(
+            "eth_feeHistory",
+            json!([format!("0x{:x}", FEE_HISTORY_BLOCKS), "latest", [50]]),
+        )?;
+
+        // The last base fee entry is the projected base fee of the next block
+        let base_fee = history["baseFeePerGas"]
+            .as_array()
+            .and_then(|fees| fees.last())
+            .map(parse_quantity_u128)
+            .transpose()?
+            .ok_or_else(|| SignerError::FeeEstimationError("missing baseFeePerGas".to_string()))?;
+
+        let tips = history["reward"]
+            .as_array()
+            .map(|rows| {
+                rows.iter()
+                    .filter_map(|row| row.get(0).map(parse_quantity_u128))
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .transpose()?
+            .unwrap_or_default();
+
+        Eip1559Fee::from_base_fee(base_fee, median(tips).unwrap_or(0))
+    }
+}
+
+fn median<T: Ord + Copy>(mut values: Vec<T>) -> Option<T> {
+    values.sort_unstable();
+    values.get(values.len() / 2).copied()
+}
+
+/// Extract `result` from a JSON-RPC response, mapping `error` to SignerError
+fn parse_rpc_response(text: &str) -> Result<Value, SignerError> {
+    let mut value: Value = serde_json::from_str(text)
+        .map_err(|e| SignerError::BroadcastError(format!("Invalid RPC response: {}", e)))?;
+
+    if let Some(error) = value.get("error") {
+        let code = error.get("code").and_then(Value::as_i64).unwrap_or(0);
+        let message = error
+            .get("message")
+            .and_then(Value::as_str)
+            .unwrap_or("unknown error");
+        return Err(SignerError::BroadcastError(format!(
+            "RPC error {}: {}",
+            code, message
+        )));
+    }
+
+    match value.get_mut("result") {
+        Some(result) => Ok(result.take()),
+        None => Err(SignerError::BroadcastError(
+            "RPC response has neither result nor error".to_string(),
+        )),
+    }
+}
+
+/// Parse an EVM hex quantity ("0x1b4")
+fn parse_quantity(value: &Value) -> Result<u64, SignerError> {
+    parse_quantity_u128(value)?
+        .try_into()
+        .map_err(|_| SignerError::BroadcastError(format!("Quantity out of range: {}", value)))
+}
+
+fn parse_quantity_u128(value: &Value) -> Result<u128, SignerError> {
+    value
+        .as_str()
+        .and_then(|s| s.strip_prefix("0x"))
+        .and_then(|s| u128::from_str_radix(s, 16).ok())
+        .ok_or_else(|| SignerError::BroadcastError(format!("Invalid quantity: {}", value)))
+}
+
+fn expect_string(value: Value, method: &str) -> Result<String, SignerError> {
+    value.as_str().map(str::to_string).ok_or_else(|| {
+        SignerError::BroadcastError(format!("{} returned a non-string result", method))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+
+    /// Serve a single HTTP request with a fixed JSON body, returning the request body
+    fn serve_once(response_body: &'static str) -> (String, std::thread::JoinHandle<String>) {
+        let (url, handle) = serve_sequence(vec![response_body]);
+        (url, std::thread::spawn(move || handle.join().unwrap().remove(0)))
+    }
+
+    /// Serve one HTTP request per response body, returning the request bodies
+    fn serve_sequence(
+        responses: Vec<&'static str>,
+    ) -> (String, std::thread::JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+
+        let handle = std::thread::spawn(move || {
+            responses
+                .into_iter()
+                .map(|body| handle_request(&listener, body))
+                .collect()
+        });
+
+        (url, handle)
+    }
+
+    fn handle_request(listener: &TcpListener, response_body: &str) -> String {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+        let mut content_length = 0;
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            if line == "\r\n" {
+                break;
+            }
+            if let Some(v) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                content_length = v.trim().parse().unwrap();
+            }
+        }
+        let mut body = vec![0u8; content_length];
+        reader.read_exact(&mut body).unwrap();
+
+        let mut stream = stream;
+        write!(
+            stream,
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nConnection: close\r\nContent-Length: {}\r\n\r\n{}",
+            response_body.len(),
+            response_body
+        )
+        .unwrap();
+        String::from_utf8(body).unwrap()
+    }
+
+    #[test]
+    fn test_send_solana_transaction() {
+        let (url, server) = serve_once(r#"{"jsonrpc":"2.0","id":1,"result":"5sig"}"#);
+        let broadcaster = Broadcaster::new(&BroadcastConfig::new(&url)).unwrap();
+
+        let signature = broadcaster.send_solana_transaction(&[1, 2, 3]).unwrap();
+        assert_eq!(signature, "5sig");
+
+        let request: Value = serde_json::from_str(&server.join().unwrap()).unwrap();
+        assert_eq!(request["method"], "sendTransaction");
+        assert_eq!(request["params"][0], "AQID");
+        assert_eq!(request["params"][1]["encoding"], "base64");
+    }
+
+    #[test]
+    fn test_send_evm_raw_transaction() {
+        let (url, server) = serve_once(r#"{"jsonrpc":"2.0","id":1,"result":"0xabc"}"#);
+        let broadcaster = Broadcaster::new(&BroadcastConfig::new(&url)).unwrap();
+
+        let hash = broadcaster.send_evm_raw_transaction(&[0x02, 0xf8]).unwrap();
+        assert_eq!(hash, "0xabc");
+
+        let request: Value = serde_json::from_str(&server.join().unwrap()).unwrap();
+        assert_eq!(request["method"], "eth_sendRawTransaction");
+        assert_eq!(request["params"][0], "0x02f8");
+    }
+
+    #[test]
+    fn test_solana_status_tracked_into_audit_log() {
+        let (url, server) = serve_sequence(vec![
+            r#"{"jsonrpc":"2.0","id":1,"result":{"value":[null]}}"#,
+            r#"{"jsonrpc":"2.0","id":2,"result":{"value":[{"slot":42,"err":null,"confirmationStatus":"confirmed"}]}}"#,
+        ]);
+        let broadcaster = Broadcaster::new(&BroadcastConfig::new(&url)).unwrap();
+
+        let mut audit = AuditLog::in_memory();
+        audit
+            .append(AuditEvent::Signed {
+                chain: "solana".into(),
+                public_key: "pk".into(),
+                container_id: None,
+                payload_hash: "00".into(),
+                signature: "5sig".into(),
+                counter: None,
+            })
+            .unwrap();
+
+        let tx = TxId::Solana("5sig".to_string());
+        let status = broadcaster
+            .track(
+                &mut audit,
+                0,
+                &tx,
+                Commitment::Confirmed,
+                Duration::from_secs(5),
+                Duration::from_millis(1),
+            )
+            .unwrap();
+        server.join().unwrap();
+
+        assert_eq!(
+            status,
+            TxStatus::Landed {
+                commitment: Commitment::Confirmed,
+                slot: 42
+            }
+        );
+        match &audit.entries()[1].event {
+            AuditEvent::TxStatus {
+                signed_entry,
+                status,
+                slot,
+                ..
+            } => {
+                assert_eq!(*signed_entry, 0);
+                assert_eq!(status, "confirmed");
+                assert_eq!(*slot, Some(42));
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_evm_receipt_status() {
+        let (url, server) = serve_sequence(vec![
+            r#"{"jsonrpc":"2.0","id":1,"result":{"blockNumber":"0x10","status":"0x1"}}"#,
+            r#"{"jsonrpc":"2.0","id":2,"result":{"number":"0x20"}}"#,
+            r#"{"jsonrpc":"2.0","id":3,"result":{"blockNumber":"0x11","status":"0x0"}}"#,
+        ]);
+        let broadcaster = Broadcaster::new(&BroadcastConfig::new(&url)).unwrap();
+
+        let tx = TxId::Evm("0xabc".to_string());
+        assert_eq!(
+            broadcaster.status(&tx).unwrap(),
+            TxStatus::Landed {
+                commitment: Commitment::Finalized,
+                slot: 16
+            }
+        );
+        assert!(matches!(
+            broadcaster.status(&tx).unwrap(),
+            TxStatus::Failed { slot: 17, .. }
+        ));
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_fee_estimates_from_rpc() {
+        let (url, server) = serve_sequence(vec![
+            r#"{"jsonrpc":"2.0","id":1,"result":[{"slot":1,"prioritizationFee":100},{"slot":2,"prioritizationFee":300},{"slot":3,"prioritizationFee":200}]}"#,
+            r#"{"jsonrpc":"2.0","id":2,"result":"0x2105"}"#,
+            r#"{"jsonrpc":"2.0","id":3,"result":{"baseFeePerGas":["0x64","0xc8"],"reward":[["0xa"],["0x1e"],["0x14"]]}}"#,
+        ]);
+        let broadcaster = Broadcaster::new(&BroadcastConfig::new(&url)).unwrap();
+
+        let solana = broadcaster.solana_fee(&[[1u8; 32]]).unwrap();
+        assert_eq!(solana.compute_unit_price, 200);
+        assert_eq!(solana.compute_unit_limit, DEFAULT_COMPUTE_UNIT_LIMIT);
+
+        let evm = broadcaster.evm_fee(8453).unwrap();
+        assert_eq!(evm.max_priority_fee_per_gas, 20);
+        assert_eq!(evm.max_fee_per_gas, 2 * 200 + 20);
+
+        let requests = server.join().unwrap();
+        assert!(requests[0].contains("getRecentPrioritizationFees"));
+        assert!(requests[2].contains("eth_feeHistory"));
+    }
+
+    #[test]
+    fn test_rpc_error_is_reported() {
+        let result = parse_rpc_response(
+            r#"{"jsonrpc":"2.0","id":1,"error":{"code":-32002,"message":"Blockhash not found"}}"#,
+        );
+        match result {
+            Err(SignerError::BroadcastError(msg)) => assert!(msg.contains("Blockhash not found")),
+            other => panic!("unexpected: {:?}", other.map(|v| v.to_string())),
+        }
+    }
+
+    #[test]
+    fn test_proxy_and_endpoint_validation() {
+        let config = BroadcastConfig::new("https://rpc.example").with_proxy("socks5h://127.0.0.1:9050");
+        assert!(Broadcaster::new(&config).is_ok());
+
+        assert!(Broadcaster::new(&BroadcastConfig::new("ftp://rpc.example")).is_err());
+    }
+}
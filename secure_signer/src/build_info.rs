@@ -0,0 +1,73 @@
+//! Build metadata
+//!
+//! `build.rs` records the git commit, compiler, target, profile, and
+//! enabled features at compile time. [`build_info`] exposes them so a
+//! signature (via its audit entry) can be tied to the exact build that
+//! produced it, and that build reproduced from source.
+//!
+//! The recorded values contain no timestamps or paths, so two reproducible
+//! builds of the same commit report the same [`BuildInfo::fingerprint`].
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Metadata of the running build
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct BuildInfo {
+    /// Crate version
+    pub version: String,
+    /// Git commit the build was made from (`"unknown"` outside a checkout)
+    pub git_commit: String,
+    /// `rustc --version` of the compiler
+    pub rustc: String,
+    /// Target triple
+    pub target: String,
+    /// Cargo profile (`debug` / `release`)
+    pub profile: String,
+    /// Enabled cargo features, sorted
+    pub features: Vec<String>,
+}
+
+impl BuildInfo {
+    /// Short identifier of this metadata
+    ///
+    /// `hex(SHA-256(canonical JSON))`, truncated to 16 bytes. Audit entries
+    /// record it so they stay small; [`build_info`] gives the full record.
+    pub fn fingerprint(&self) -> String {
+        let json = serde_json::to_vec(self).expect("BuildInfo serializes");
+        hex::encode(&Sha256::digest(json)[..16])
+    }
+}
+
+/// Metadata of the running build
+pub fn build_info() -> BuildInfo {
+    BuildInfo {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        git_commit: env!("COLDSTAR_BUILD_GIT_COMMIT").to_string(),
+        rustc: env!("COLDSTAR_BUILD_RUSTC").to_string(),
+        target: env!("COLDSTAR_BUILD_TARGET").to_string(),
+        profile: env!("COLDSTAR_BUILD_PROFILE").to_string(),
+        features: env!("COLDSTAR_BUILD_FEATURES")
+            .split(',')
+            .filter(|f| !f.is_empty())
+            .map(str::to_string)
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_info_is_populated() {
+        let info = build_info();
+        assert_eq!(info.version, crate::VERSION);
+        assert!(info.rustc.starts_with("rustc "));
+        assert!(!info.target.is_empty());
+        assert_eq!(info.features.contains(&"ffi".to_string()), cfg!(feature = "ffi"));
+
+        assert_eq!(info.fingerprint(), build_info().fingerprint());
+        assert_eq!(info.fingerprint().len(), 32);
+    }
+}
@@ -0,0 +1,418 @@
+//! Offline signing bundles
+//!
+//! A PSBT-like envelope for signing on an air-gapped machine, the same for
+//! Solana and EVM so host tooling need not know the chain:
+//!
+//! 1. The online host builds a [`SigningRequest`]: the unsigned
+//!    transaction, the signer it expects, and the derivation path of that
+//!    key, under a random request ID.
+//! 2. The offline signer [`validate`](SigningRequest::validate)s it, shows
+//!    its [`preview`](SigningRequest::preview), and
+//!    [`sign`](SigningRequest::sign)s it with any [`SignerBackend`],
+//!    producing a [`SigningResponse`] that holds only the signature.
+//! 3. The host [`finalize`](SigningRequest::finalize)s its own copy of the
+//!    request with the response, which checks the signature against the
+//!    transaction it asked for and returns the bytes to broadcast.
+//!
+//! Both travel as JSON, or as QR frames through [`ur`](crate::ur).
+
+use std::str::FromStr;
+
+use k256::ecdsa::{RecoveryId, Signature as K256Signature, VerifyingKey as K256VerifyingKey};
+use serde::{Deserialize, Serialize};
+
+use crate::approval::ApprovalRequest;
+use crate::backend::SignerBackend;
+use crate::crypto::evm_address_from_pubkey;
+use crate::encoding::Encoding;
+use crate::entropy::fill_random;
+use crate::error::SignerError;
+use crate::evm::chain::ChainProfile;
+use crate::evm::transaction::EvmTransaction;
+use crate::hd::DerivationPath;
+use crate::solana::transaction::SolanaTransaction;
+use crate::ur::UrPayload;
+
+/// Version of the bundle format
+pub const BUNDLE_VERSION: u8 = 1;
+
+/// The transaction a request asks to have signed
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum UnsignedTransaction {
+    /// A serialized (possibly partially-signed) transaction or bare
+    /// message (base64)
+    Solana(String),
+    /// An unsigned legacy or EIP-1559 transaction
+    Evm(EvmTransaction),
+}
+
+/// A signature in a response
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BundleSignature {
+    /// Ed25519 signature over the message
+    Solana {
+        /// Signer (base58)
+        public_key: String,
+        /// Signature (base58)
+        signature: String,
+    },
+    /// Recoverable ECDSA signature over the transaction's signing hash
+    Evm {
+        /// Signer (0x-prefixed, checksummed)
+        address: String,
+        /// `r || s || v` (0x-prefixed hex, `v` 27 or 28)
+        signature: String,
+    },
+}
+
+/// What the online host sends to the offline signer
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct SigningRequest {
+    /// Bundle format version
+    pub version: u8,
+    /// Random ID (hex) that the response repeats
+    pub id: String,
+    /// The transaction to sign
+    pub transaction: UnsignedTransaction,
+    /// Expected signer: a base58 public key or an EVM address
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signer: Option<String>,
+    /// Derivation path of the expected signer's key
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub derivation_path: Option<String>,
+}
+
+/// What the offline signer sends back
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct SigningResponse {
+    /// Bundle format version
+    pub version: u8,
+    /// ID of the request that was signed
+    pub request_id: String,
+    /// The signature
+    pub signature: BundleSignature,
+}
+
+fn invalid(reason: impl Into<String>) -> SignerError {
+    SignerError::InvalidTransaction(reason.into())
+}
+
+impl SigningRequest {
+    fn new(transaction: UnsignedTransaction) -> Result<Self, SignerError> {
+        let mut id = [0u8; 16];
+        fill_random(&mut id)?;
+        let request = Self {
+            version: BUNDLE_VERSION,
+            id: hex::encode(id),
+            transaction,
+            signer: None,
+            derivation_path: None,
+        };
+        request.validate()?;
+        Ok(request)
+    }
+
+    /// Request a signature on a Solana transaction or bare message
+    pub fn solana(transaction: &[u8]) -> Result<Self, SignerError> {
+        Self::new(UnsignedTransaction::Solana(base64::Engine::encode(
+            &base64::engine::general_purpose::STANDARD,
+            transaction,
+        )))
+    }
+
+    /// Request a signature on an EVM transaction
+    pub fn evm(transaction: EvmTransaction) -> Result<Self, SignerError> {
+        Self::new(UnsignedTransaction::Evm(transaction))
+    }
+
+    /// Name the key that should sign; a response from another key is
+    /// refused
+    pub fn with_signer(mut self, signer: impl Into<String>) -> Self {
+        self.signer = Some(signer.into());
+        self
+    }
+
+    /// Record where the expected signer's key is derived from
+    pub fn with_derivation_path(mut self, path: &DerivationPath) -> Self {
+        self.derivation_path = Some(path.to_string());
+        self
+    }
+
+    /// Parse and [`validate`](SigningRequest::validate) a request
+    pub fn from_json(json: &str) -> Result<Self, SignerError> {
+        let request: Self = serde_json::from_str(json)?;
+        request.validate()?;
+        Ok(request)
+    }
+
+    pub fn to_json(&self) -> Result<String, SignerError> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    /// Check the version, the transaction, and the derivation path
+    ///
+    /// EVM transactions are also checked against their chain's
+    /// [`ChainProfile`].
+    pub fn validate(&self) -> Result<(), SignerError> {
+        if self.version != BUNDLE_VERSION {
+            return Err(invalid(format!("unsupported bundle version {}", self.version)));
+        }
+        if let Some(path) = &self.derivation_path {
+            DerivationPath::from_str(path)?;
+        }
+        match &self.transaction {
+            UnsignedTransaction::Solana(_) => self.solana_transaction().map(|_| ()),
+            UnsignedTransaction::Evm(transaction) => ChainProfile::for_chain_id(transaction.chain_id).check(transaction),
+        }
+    }
+
+    fn solana_transaction(&self) -> Result<SolanaTransaction, SignerError> {
+        let UnsignedTransaction::Solana(transaction) = &self.transaction else {
+            return Err(invalid("not a Solana request"));
+        };
+        let bytes = base64::Engine::decode(&base64::engine::general_purpose::STANDARD, transaction)
+            .map_err(|e| SignerError::Base64Error(e.to_string()))?;
+        SolanaTransaction::parse_or_from_message(&bytes)
+    }
+
+    /// What the signer's operator should see before approving
+    pub fn preview(&self) -> Result<ApprovalRequest, SignerError> {
+        let mut preview = match &self.transaction {
+            UnsignedTransaction::Solana(_) => ApprovalRequest::solana(self.solana_transaction()?.message_bytes())?,
+            UnsignedTransaction::Evm(transaction) => ApprovalRequest::evm(transaction),
+        };
+        if let Some(signer) = &self.signer {
+            preview = preview.with_detail(format!("Signer: {}", signer));
+        }
+        if let Some(path) = &self.derivation_path {
+            preview = preview.with_detail(format!("Key path: {}", path));
+        }
+        Ok(preview.with_detail(format!("Request: {}", self.id)))
+    }
+
+    /// Validate the request and sign it with `backend`
+    ///
+    /// Fails, without returning the signature, if the backend's key is not
+    /// the expected signer (or, on Solana, not a required signer).
+    pub fn sign(&self, backend: &dyn SignerBackend) -> Result<SigningResponse, SignerError> {
+        self.validate()?;
+        let signature = match &self.transaction {
+            UnsignedTransaction::Solana(_) => {
+                let transaction = self.solana_transaction()?;
+                let result = backend.sign_solana(transaction.message_bytes())?;
+                BundleSignature::Solana {
+                    public_key: result.public_key,
+                    signature: result.signature,
+                }
+            }
+            UnsignedTransaction::Evm(transaction) => {
+                let result = backend.sign_evm_transaction(&transaction.unsigned_bytes())?;
+                BundleSignature::Evm {
+                    address: result.address,
+                    signature: result.signature,
+                }
+            }
+        };
+        let response = SigningResponse {
+            version: BUNDLE_VERSION,
+            request_id: self.id.clone(),
+            signature,
+        };
+        self.finalize(&response)?;
+        Ok(response)
+    }
+
+    /// Apply a response to this request, returning the transaction to
+    /// broadcast
+    ///
+    /// The response must answer this request, come from the expected
+    /// signer, and verify over this request's transaction. Solana
+    /// transactions come back in wire format with the signature in its
+    /// slot (other slots as the request had them); EVM transactions as the
+    /// signed RLP encoding.
+    pub fn finalize(&self, response: &SigningResponse) -> Result<Vec<u8>, SignerError> {
+        if response.version != BUNDLE_VERSION {
+            return Err(invalid(format!("unsupported bundle version {}", response.version)));
+        }
+        if response.request_id != self.id {
+            return Err(invalid(format!(
+                "response answers request {}, not {}",
+                response.request_id, self.id
+            )));
+        }
+        match (&self.transaction, &response.signature) {
+            (UnsignedTransaction::Solana(_), BundleSignature::Solana { public_key, signature }) => {
+                self.check_signer(public_key)?;
+                let public_key: [u8; 32] = bs58::decode(public_key)
+                    .into_vec()?
+                    .try_into()
+                    .map_err(|_| invalid("malformed public key in response"))?;
+                let signature: [u8; 64] = bs58::decode(signature)
+                    .into_vec()?
+                    .try_into()
+                    .map_err(|_| invalid("malformed signature in response"))?;
+                let mut transaction = self.solana_transaction()?;
+                transaction.add_signature(&public_key, &signature)?;
+                Ok(transaction.serialize())
+            }
+            (UnsignedTransaction::Evm(transaction), BundleSignature::Evm { address, signature }) => {
+                self.check_signer(address)?;
+                let signature = Encoding::Hex.decode(signature)?;
+                if signature.len() != 65 || !matches!(signature[64], 27 | 28) {
+                    return Err(invalid("malformed EVM signature in response"));
+                }
+                let recovery_id = signature[64] - 27;
+                let recovered = K256Signature::from_slice(&signature[..64])
+                    .ok()
+                    .zip(RecoveryId::from_byte(recovery_id))
+                    .and_then(|(sig, id)| {
+                        K256VerifyingKey::recover_from_prehash(&transaction.signing_hash(), &sig, id).ok()
+                    })
+                    .map(|key| evm_address_from_pubkey(&key));
+                if !recovered.is_some_and(|recovered| recovered.eq_ignore_ascii_case(address)) {
+                    return Err(SignerError::SigningFailed(
+                        "signature does not verify over the transaction".to_string(),
+                    ));
+                }
+                let r: [u8; 32] = signature[..32].try_into().expect("length checked");
+                let s: [u8; 32] = signature[32..64].try_into().expect("length checked");
+                Ok(transaction.encode_signed(recovery_id, &r, &s))
+            }
+            _ => Err(invalid("response is for a different chain")),
+        }
+    }
+
+    fn check_signer(&self, signer: &str) -> Result<(), SignerError> {
+        match &self.signer {
+            Some(expected) if !expected.eq_ignore_ascii_case(signer) => Err(invalid(format!(
+                "request is for {}, but {} signed",
+                expected, signer
+            ))),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl SigningResponse {
+    pub fn from_json(json: &str) -> Result<Self, SignerError> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    pub fn to_json(&self) -> Result<String, SignerError> {
+        Ok(serde_json::to_string(self)?)
+    }
+}
+
+impl UrPayload for SigningRequest {
+    const UR_TYPE: &'static str = "coldstar-sign-request";
+
+    fn to_ur_payload(&self) -> Result<Vec<u8>, SignerError> {
+        Ok(self.to_json()?.into_bytes())
+    }
+
+    fn from_ur_payload(payload: &[u8]) -> Result<Self, SignerError> {
+        Self::from_json(std::str::from_utf8(payload).map_err(|e| SignerError::SerializationError(e.to_string()))?)
+    }
+}
+
+impl UrPayload for SigningResponse {
+    const UR_TYPE: &'static str = "coldstar-sign-response";
+
+    fn to_ur_payload(&self) -> Result<Vec<u8>, SignerError> {
+        Ok(self.to_json()?.into_bytes())
+    }
+
+    fn from_ur_payload(payload: &[u8]) -> Result<Self, SignerError> {
+        Self::from_json(std::str::from_utf8(payload).map_err(|e| SignerError::SerializationError(e.to_string()))?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::ContainerBackend;
+    use crate::crypto::{Cipher, EncryptedKeyContainer};
+    use crate::evm::transaction::GasPricing;
+    use crate::kdf::KdfParams;
+    use crate::ur::{UrDecoder, DEFAULT_MAX_FRAGMENT_LEN};
+
+    fn new_container(seed: u8) -> EncryptedKeyContainer {
+        std::env::set_var("SIGNER_ALLOW_INSECURE_MEMORY", "1");
+        EncryptedKeyContainer::encrypt_with_kdf(&[seed; 32], "pw", Cipher::Aes256Gcm, KdfParams::MINIMUM).unwrap()
+    }
+
+    /// A legacy message with `payer` as its only signer
+    fn solana_message(payer: &[u8; 32]) -> Vec<u8> {
+        let mut message = vec![1, 0, 1, 2];
+        message.extend_from_slice(payer);
+        message.extend_from_slice(&[9u8; 32]); // program
+        message.extend_from_slice(&[0u8; 32]); // recent blockhash
+        message.extend_from_slice(&[1, 1, 1, 0, 0]);
+        message
+    }
+
+    #[test]
+    fn test_solana_round_trip_through_ur() {
+        let container = new_container(3);
+        let public_key = container.public_key.clone().unwrap();
+        let payer: [u8; 32] = bs58::decode(&public_key).into_vec().unwrap().try_into().unwrap();
+        let request = SigningRequest::solana(&solana_message(&payer))
+            .unwrap()
+            .with_signer(public_key.clone())
+            .with_derivation_path(&"m/44'/501'/0'/0'".parse().unwrap());
+
+        // Host to signer as QR frames
+        let mut decoder = UrDecoder::new();
+        for frame in request.to_ur_frames(DEFAULT_MAX_FRAGMENT_LEN).unwrap() {
+            decoder.receive(&frame).unwrap();
+            if decoder.is_complete() {
+                break;
+            }
+        }
+        let received: SigningRequest = decoder.result().unwrap().decode().unwrap();
+        assert!(received.preview().unwrap().render().contains("Key path: m/44'/501'/0'/0'"));
+
+        let response = received.sign(&ContainerBackend::new(&container, "pw")).unwrap();
+        let signed = request.finalize(&SigningResponse::from_json(&response.to_json().unwrap()).unwrap()).unwrap();
+        assert!(SolanaTransaction::parse(&signed).unwrap().is_fully_signed());
+
+        // Another key, or a response to another request, is refused
+        let other = new_container(4);
+        assert!(received.sign(&ContainerBackend::new(&other, "pw")).is_err());
+        let mut stray = response.clone();
+        stray.request_id = "00".to_string();
+        assert!(request.finalize(&stray).is_err());
+    }
+
+    #[test]
+    fn test_evm_response_must_match_transaction() {
+        let container = new_container(5);
+        let transaction = EvmTransaction {
+            chain_id: 1,
+            nonce: 0,
+            gas_limit: 21_000,
+            to: Some([0x11; 20]),
+            value: 10u128.pow(18),
+            data: Vec::new(),
+            pricing: GasPricing::Legacy { gas_price: 30_000_000_000 },
+        };
+        let request = SigningRequest::evm(transaction.clone()).unwrap();
+        let request = SigningRequest::from_json(&request.to_json().unwrap()).unwrap();
+        let response = request.sign(&ContainerBackend::new(&container, "pw")).unwrap();
+
+        let backend = ContainerBackend::new(&container, "pw");
+        let expected = transaction.sign_with(&backend).unwrap();
+        assert_eq!(
+            format!("0x{}", hex::encode(request.finalize(&response).unwrap())),
+            expected.raw_transaction
+        );
+
+        // The same signature does not finalize a different transaction
+        let mut other = request.clone();
+        other.transaction = UnsignedTransaction::Evm(EvmTransaction { nonce: 1, ..transaction });
+        other.id = request.id.clone();
+        assert!(other.finalize(&response).is_err());
+    }
+}
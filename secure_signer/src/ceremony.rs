@@ -0,0 +1,416 @@
+//! Key generation ceremony transcripts
+//!
+//! Institutions need evidence that a key was generated properly: who was
+//! present, which entropy went into the key, and what came out. A
+//! [`KeyCeremony`] generates keys and records:
+//!
+//! - Participants and their roles
+//! - Entropy sources (OS RNG plus optional participant contributions, which
+//!   are recorded only as SHA-256 commitments)
+//! - Public keys and fingerprints of generated keys
+//!
+//! Finishing the ceremony produces a [`CeremonyTranscript`] signed with a
+//! designated key. Participants can then sign the transcript digest with
+//! their own Ed25519 keys and attach the result as attestations.
+//!
+//! # Key Generation
+//!
+//! `seed = SHA-256(os_random || SHA-256(contribution_1) || ...)`. The OS RNG
+//! alone provides full entropy; contributions cannot reduce it.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::crypto::{get_locking_mode, EncryptedKeyContainer};
+use crate::entropy::{fill_random, EntropyRng};
+use crate::error::SignerError;
+use crate::secure_buffer::SecureBuffer;
+
+/// Transcript format version
+const TRANSCRIPT_VERSION: u8 = 1;
+
+/// A ceremony participant
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Participant {
+    /// Display name
+    pub name: String,
+    /// Role in the ceremony ("operator", "witness", ...)
+    pub role: String,
+    /// Ed25519 public key used for attestations (base58, optional)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub public_key: Option<String>,
+}
+
+/// An entropy source that contributed to generated keys
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct EntropyRecord {
+    /// Source description ("os-rng", "participant:<name>", ...)
+    pub source: String,
+    /// Number of bytes contributed
+    pub bytes: usize,
+    /// SHA-256 commitment to the contribution (absent for the OS RNG)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commitment: Option<String>,
+}
+
+/// A key produced by the ceremony
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct GeneratedKey {
+    /// Caller-supplied label
+    pub label: String,
+    /// Ed25519 public key (base58)
+    pub public_key: String,
+    /// SHA-256 of the public key bytes (hex)
+    pub fingerprint: String,
+}
+
+/// A participant's signature over the transcript digest
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Attestation {
+    /// Name of the attesting participant
+    pub participant: String,
+    /// Free-form statement ("I witnessed the ceremony ...")
+    pub statement: String,
+    /// Ed25519 signature over `digest || statement` (base58)
+    pub signature: String,
+}
+
+/// Signed record of a key generation ceremony
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct CeremonyTranscript {
+    /// Transcript format version
+    pub version: u8,
+    /// Random ceremony identifier (hex)
+    pub ceremony_id: String,
+    /// Ceremony name
+    pub name: String,
+    /// Unix timestamp the ceremony started
+    pub started_at: u64,
+    /// Unix timestamp the ceremony finished
+    pub completed_at: u64,
+    /// Participants
+    pub participants: Vec<Participant>,
+    /// Entropy sources used
+    pub entropy_sources: Vec<EntropyRecord>,
+    /// Generated keys
+    pub keys: Vec<GeneratedKey>,
+    /// Key that signed the transcript (base58)
+    pub signer_public_key: String,
+    /// Signature over the transcript digest (base58)
+    pub signature: String,
+    /// Participant attestations, added after the ceremony
+    #[serde(default)]
+    pub attestations: Vec<Attestation>,
+}
+
+#[derive(Serialize)]
+struct TranscriptBody<'a> {
+    version: u8,
+    ceremony_id: &'a str,
+    name: &'a str,
+    started_at: u64,
+    completed_at: u64,
+    participants: &'a [Participant],
+    entropy_sources: &'a [EntropyRecord],
+    keys: &'a [GeneratedKey],
+    signer_public_key: &'a str,
+}
+
+impl CeremonyTranscript {
+    /// SHA-256 of the transcript body (everything except signature and
+    /// attestations); this is what the signer and participants sign
+    pub fn digest(&self) -> Result<[u8; 32], SignerError> {
+        let body = serde_json::to_vec(&TranscriptBody {
+            version: self.version,
+            ceremony_id: &self.ceremony_id,
+            name: &self.name,
+            started_at: self.started_at,
+            completed_at: self.completed_at,
+            participants: &self.participants,
+            entropy_sources: &self.entropy_sources,
+            keys: &self.keys,
+            signer_public_key: &self.signer_public_key,
+        })?;
+        Ok(Sha256::digest(body).into())
+    }
+
+    /// Message a participant signs to attest to the transcript
+    pub fn attestation_message(&self, statement: &str) -> Result<Vec<u8>, SignerError> {
+        let mut message = self.digest()?.to_vec();
+        message.extend_from_slice(statement.as_bytes());
+        Ok(message)
+    }
+
+    /// Attach a participant attestation, verifying it against the
+    /// participant's registered public key
+    pub fn add_attestation(
+        &mut self,
+        participant: &str,
+        statement: &str,
+        signature: &str,
+    ) -> Result<(), SignerError> {
+        let attestation = Attestation {
+            participant: participant.to_string(),
+            statement: statement.to_string(),
+            signature: signature.to_string(),
+        };
+        self.verify_attestation(&attestation)?;
+        self.attestations.push(attestation);
+        Ok(())
+    }
+
+    /// Verify the transcript signature and all attestations
+    pub fn verify(&self) -> Result<(), SignerError> {
+        verify_ed25519(&self.signer_public_key, &self.digest()?, &self.signature)?;
+        for attestation in &self.attestations {
+            self.verify_attestation(attestation)?;
+        }
+        Ok(())
+    }
+
+    fn verify_attestation(&self, attestation: &Attestation) -> Result<(), SignerError> {
+        let public_key = self
+            .participants
+            .iter()
+            .find(|p| p.name == attestation.participant)
+            .and_then(|p| p.public_key.as_deref())
+            .ok_or_else(|| {
+                SignerError::CeremonyError(format!(
+                    "participant {} has no registered public key",
+                    attestation.participant
+                ))
+            })?;
+        verify_ed25519(
+            public_key,
+            &self.attestation_message(&attestation.statement)?,
+            &attestation.signature,
+        )
+    }
+
+    /// Serialize the transcript to JSON
+    pub fn to_json(&self) -> Result<String, SignerError> {
+        serde_json::to_string_pretty(self).map_err(|e| SignerError::SerializationError(e.to_string()))
+    }
+}
+
+/// A key generation ceremony in progress
+pub struct KeyCeremony {
+    ceremony_id: String,
+    name: String,
+    started_at: u64,
+    participants: Vec<Participant>,
+    entropy_sources: Vec<EntropyRecord>,
+    /// Hashes of participant contributions, mixed into every generated key
+    contributions: Vec<[u8; 32]>,
+    keys: Vec<GeneratedKey>,
+}
+
+impl KeyCeremony {
+    /// Start a ceremony
+    pub fn new(name: &str) -> Self {
+        let mut id = [0u8; 16];
+        EntropyRng.fill_bytes(&mut id);
+
+        Self {
+            ceremony_id: hex::encode(id),
+            name: name.to_string(),
+            started_at: unix_now(),
+            participants: Vec::new(),
+            entropy_sources: Vec::new(),
+            contributions: Vec::new(),
+            keys: Vec::new(),
+        }
+    }
+
+    /// Register a participant
+    pub fn add_participant(&mut self, name: &str, role: &str, public_key: Option<&str>) -> Result<(), SignerError> {
+        if self.participants.iter().any(|p| p.name == name) {
+            return Err(SignerError::CeremonyError(format!(
+                "duplicate participant {}",
+                name
+            )));
+        }
+        if let Some(key) = public_key {
+            parse_public_key(key)?;
+        }
+
+        self.participants.push(Participant {
+            name: name.to_string(),
+            role: role.to_string(),
+            public_key: public_key.map(str::to_string),
+        });
+        Ok(())
+    }
+
+    /// Mix entropy contributed by a registered participant into all keys
+    /// generated afterwards; only a commitment is recorded
+    pub fn contribute_entropy(&mut self, participant: &str, entropy: &[u8]) -> Result<(), SignerError> {
+        if !self.participants.iter().any(|p| p.name == participant) {
+            return Err(SignerError::CeremonyError(format!(
+                "unknown participant {}",
+                participant
+            )));
+        }
+
+        let commitment: [u8; 32] = Sha256::digest(entropy).into();
+        self.contributions.push(commitment);
+        self.entropy_sources.push(EntropyRecord {
+            source: format!("participant:{}", participant),
+            bytes: entropy.len(),
+            commitment: Some(hex::encode(commitment)),
+        });
+        Ok(())
+    }
+
+    /// Generate an Ed25519 key and return it in an encrypted container
+    pub fn generate_key(&mut self, label: &str, passphrase: &str) -> Result<EncryptedKeyContainer, SignerError> {
+        let mut os_random = SecureBuffer::with_mode(32, get_locking_mode())?;
+        fill_random(os_random.as_mut_slice())?;
+
+        let mut hasher = Sha256::new();
+        hasher.update(os_random.as_slice());
+        for contribution in &self.contributions {
+            hasher.update(contribution);
+        }
+        let mut digest = hasher.finalize();
+        let mut seed = SecureBuffer::from_slice_with_mode(&digest, get_locking_mode())?;
+        digest.as_mut_slice().fill(0);
+        os_random.zeroize();
+
+        let container = EncryptedKeyContainer::encrypt(seed.as_slice(), passphrase);
+        seed.zeroize();
+        let container = container?;
+
+        if !self.entropy_sources.iter().any(|e| e.source == "os-rng") {
+            self.entropy_sources.insert(
+                0,
+                EntropyRecord {
+                    source: "os-rng".to_string(),
+                    bytes: 32,
+                    commitment: None,
+                },
+            );
+        }
+
+        let public_key = container
+            .public_key
+            .clone()
+            .ok_or_else(|| SignerError::CeremonyError("container has no public key".to_string()))?;
+        self.keys.push(GeneratedKey {
+            label: label.to_string(),
+            fingerprint: hex::encode(Sha256::digest(parse_public_key(&public_key)?.as_bytes())),
+            public_key,
+        });
+
+        Ok(container)
+    }
+
+    /// Finish the ceremony, signing the transcript with `signer`
+    ///
+    /// The signer is typically one of the generated keys (proving the
+    /// transcript was produced with access to it) or an institution key.
+    pub fn finish(self, signer: &EncryptedKeyContainer, passphrase: &str) -> Result<CeremonyTranscript, SignerError> {
+        if self.keys.is_empty() {
+            return Err(SignerError::CeremonyError("no keys were generated".to_string()));
+        }
+
+        let mut secret = signer.decrypt_key(passphrase)?;
+        let signing_key = SigningKey::from_bytes(
+            secret
+                .as_slice()
+                .try_into()
+                .map_err(|_| SignerError::InvalidKeyFormat(secret.len()))?,
+        );
+        secret.zeroize();
+
+        let mut transcript = CeremonyTranscript {
+            version: TRANSCRIPT_VERSION,
+            ceremony_id: self.ceremony_id,
+            name: self.name,
+            started_at: self.started_at,
+            completed_at: unix_now(),
+            participants: self.participants,
+            entropy_sources: self.entropy_sources,
+            keys: self.keys,
+            signer_public_key: bs58::encode(signing_key.verifying_key().as_bytes()).into_string(),
+            signature: String::new(),
+            attestations: Vec::new(),
+        };
+        let signature: Signature = signing_key.sign(&transcript.digest()?);
+        transcript.signature = bs58::encode(signature.to_bytes()).into_string();
+
+        Ok(transcript)
+    }
+}
+
+fn parse_public_key(public_key: &str) -> Result<VerifyingKey, SignerError> {
+    let bytes: [u8; 32] = bs58::decode(public_key)
+        .into_vec()?
+        .try_into()
+        .map_err(|_| SignerError::CeremonyError(format!("invalid public key {}", public_key)))?;
+    VerifyingKey::from_bytes(&bytes).map_err(|e| SignerError::CeremonyError(e.to_string()))
+}
+
+fn verify_ed25519(public_key: &str, message: &[u8], signature: &str) -> Result<(), SignerError> {
+    let signature: [u8; 64] = bs58::decode(signature)
+        .into_vec()?
+        .try_into()
+        .map_err(|_| SignerError::CeremonyError("invalid signature encoding".to_string()))?;
+    parse_public_key(public_key)?
+        .verify(message, &Signature::from_bytes(&signature))
+        .map_err(|_| SignerError::CeremonyError("signature verification failed".to_string()))
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ceremony_transcript_signed_and_attested() {
+        let witness = SigningKey::from_bytes(&[5u8; 32]);
+        let witness_pub = bs58::encode(witness.verifying_key().as_bytes()).into_string();
+
+        let mut ceremony = KeyCeremony::new("treasury-2026");
+        ceremony.add_participant("alice", "operator", None).unwrap();
+        ceremony.add_participant("bob", "witness", Some(&witness_pub)).unwrap();
+        ceremony.contribute_entropy("alice", b"dice rolls 3 1 4 1 5").unwrap();
+        assert!(ceremony.contribute_entropy("mallory", b"x").is_err());
+
+        let container = ceremony.generate_key("treasury", "pass").unwrap();
+        let mut transcript = ceremony.finish(&container, "pass").unwrap();
+
+        assert_eq!(transcript.keys[0].public_key, container.public_key.clone().unwrap());
+        assert_eq!(transcript.signer_public_key, transcript.keys[0].public_key);
+        assert_eq!(transcript.entropy_sources[0].source, "os-rng");
+        assert!(!transcript.to_json().unwrap().contains("dice rolls"));
+
+        let statement = "I witnessed the ceremony";
+        let message = transcript.attestation_message(statement).unwrap();
+        let signature = bs58::encode(witness.sign(&message).to_bytes()).into_string();
+        transcript.add_attestation("bob", statement, &signature).unwrap();
+        assert!(transcript.add_attestation("alice", statement, &signature).is_err());
+        assert!(transcript.verify().is_ok());
+
+        // Editing the transcript invalidates the signature
+        transcript.keys[0].label = "other".to_string();
+        assert!(transcript.verify().is_err());
+    }
+
+    #[test]
+    fn test_finish_requires_a_key() {
+        let ceremony = KeyCeremony::new("empty");
+        let signer = EncryptedKeyContainer::encrypt(&[1u8; 32], "pass").unwrap();
+        assert!(ceremony.finish(&signer, "pass").is_err());
+    }
+}
@@ -0,0 +1,339 @@
+//! Network configuration shared across signing calls
+//!
+//! A [`ChainConfig`] names the network a key signs for: its CAIP-2 id, the
+//! BIP-44 coin type, how addresses are written, which signature scheme it
+//! uses and where transactions can be looked up. Signing through a config
+//! ([`ChainConfig::sign_solana`], [`ChainConfig::sign_evm_transaction`])
+//! rejects transactions for another network, derives EVM `v` values from
+//! the config's chain id, and tags the result with the network it targets.
+//!
+//! [`ChainRegistry`] holds the built-ins (Solana mainnet and devnet,
+//! Ethereum, Base) plus any custom entries an integration registers.
+
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Keccak256};
+
+use crate::backend::SignerBackend;
+use crate::crypto::SigningResult;
+use crate::error::SignerError;
+use crate::evm::chain::ChainProfile;
+use crate::evm::transaction::{EVMTransactionResult, EvmTransaction};
+use crate::hd::DerivationPreset;
+
+/// How a chain writes account addresses
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AddressFormat {
+    /// Base58 of the 32-byte Ed25519 public key (Solana)
+    Base58,
+    /// 0x-prefixed hex with EIP-55 mixed-case checksum
+    Eip55,
+}
+
+/// Signature scheme of a chain's accounts
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SigningScheme {
+    Ed25519,
+    /// Recoverable secp256k1 ECDSA over keccak256
+    Secp256k1,
+}
+
+/// One network a key can sign for
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ChainConfig {
+    /// CAIP-2 chain id, e.g. `eip155:8453`
+    pub id: String,
+    /// Human-readable network name
+    pub name: String,
+    /// BIP-44 coin type (SLIP-44)
+    pub coin_type: u32,
+    pub address_format: AddressFormat,
+    pub scheme: SigningScheme,
+    /// Transaction explorer URL with `{tx}` in place of the signature or hash
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub explorer_url: Option<String>,
+}
+
+/// Genesis hash prefixes Solana's CAIP-2 ids use
+const SOLANA_MAINNET_ID: &str = "solana:5eykt4UsFv8P8NJdTREpY1vzqKqZKvdp";
+const SOLANA_DEVNET_ID: &str = "solana:EtWTRABZaYq6iMfeYKouRu166VU2xqa1";
+
+impl ChainConfig {
+    pub fn solana_mainnet() -> Self {
+        Self::solana(SOLANA_MAINNET_ID, "Solana Mainnet", "https://explorer.solana.com/tx/{tx}")
+    }
+
+    pub fn solana_devnet() -> Self {
+        Self::solana(
+            SOLANA_DEVNET_ID,
+            "Solana Devnet",
+            "https://explorer.solana.com/tx/{tx}?cluster=devnet",
+        )
+    }
+
+    pub fn ethereum() -> Self {
+        Self::evm(1, "Ethereum").with_explorer_url("https://etherscan.io/tx/{tx}")
+    }
+
+    pub fn base() -> Self {
+        Self::evm(8453, "Base").with_explorer_url("https://basescan.org/tx/{tx}")
+    }
+
+    fn solana(id: &str, name: &str, explorer_url: &str) -> Self {
+        ChainConfig {
+            id: id.to_string(),
+            name: name.to_string(),
+            coin_type: 501,
+            address_format: AddressFormat::Base58,
+            scheme: SigningScheme::Ed25519,
+            explorer_url: Some(explorer_url.to_string()),
+        }
+    }
+
+    /// An EVM chain by EIP-155 chain id, without an explorer
+    pub fn evm(chain_id: u64, name: &str) -> Self {
+        ChainConfig {
+            id: format!("eip155:{}", chain_id),
+            name: name.to_string(),
+            coin_type: 60,
+            address_format: AddressFormat::Eip55,
+            scheme: SigningScheme::Secp256k1,
+            explorer_url: None,
+        }
+    }
+
+    pub fn with_explorer_url(mut self, explorer_url: &str) -> Self {
+        self.explorer_url = Some(explorer_url.to_string());
+        self
+    }
+
+    /// EIP-155 chain id, for `eip155:` configs
+    pub fn evm_chain_id(&self) -> Option<u64> {
+        self.id.strip_prefix("eip155:")?.parse().ok()
+    }
+
+    /// The common software-wallet derivation preset for this chain's coin type
+    pub fn derivation_preset(&self) -> Option<DerivationPreset> {
+        match self.coin_type {
+            501 => Some(DerivationPreset::Solana),
+            60 => Some(DerivationPreset::Ethereum),
+            _ => None,
+        }
+    }
+
+    /// Write an address the way this chain does
+    ///
+    /// Takes a 32-byte Ed25519 public key for [`AddressFormat::Base58`], or
+    /// a 20-byte address for [`AddressFormat::Eip55`].
+    pub fn format_address(&self, bytes: &[u8]) -> Result<String, SignerError> {
+        match (self.address_format, bytes.len()) {
+            (AddressFormat::Base58, 32) => Ok(bs58::encode(bytes).into_string()),
+            (AddressFormat::Eip55, 20) => Ok(eip55(&hex::encode(bytes))),
+            (format, len) => Err(SignerError::SerializationError(format!(
+                "{} bytes cannot be a {:?} address on {}",
+                len, format, self.name
+            ))),
+        }
+    }
+
+    /// Explorer link of a transaction signature or hash
+    pub fn explorer_link(&self, tx: &str) -> Option<String> {
+        self.explorer_url.as_ref().map(|url| url.replace("{tx}", tx))
+    }
+
+    fn require_scheme(&self, scheme: SigningScheme) -> Result<(), SignerError> {
+        if self.scheme != scheme {
+            return Err(SignerError::InvalidTransaction(format!(
+                "{} does not use {:?} signatures",
+                self.name, scheme
+            )));
+        }
+        Ok(())
+    }
+
+    /// Sign a Solana transaction message for this network
+    pub fn sign_solana(
+        &self,
+        backend: &dyn SignerBackend,
+        message: &[u8],
+    ) -> Result<ChainSigned<SigningResult>, SignerError> {
+        self.require_scheme(SigningScheme::Ed25519)?;
+        let result = backend.sign_solana(message)?;
+        Ok(self.tag(result.signature.clone(), result))
+    }
+
+    /// Sign an EVM transaction for this network
+    ///
+    /// The transaction's chain id must be this config's, so the EIP-155 `v`
+    /// value and replay protection match the network named in the result.
+    /// The sender comes back in this chain's address format.
+    pub fn sign_evm_transaction(
+        &self,
+        backend: &dyn SignerBackend,
+        transaction: &EvmTransaction,
+    ) -> Result<ChainSigned<EVMTransactionResult>, SignerError> {
+        self.require_scheme(SigningScheme::Secp256k1)?;
+        let chain_id = self.evm_chain_id().ok_or_else(|| {
+            SignerError::InvalidTransaction(format!("{} ({}) is not an EIP-155 chain", self.name, self.id))
+        })?;
+        if transaction.chain_id != chain_id {
+            return Err(SignerError::InvalidTransaction(format!(
+                "transaction is for chain {}, not {} ({})",
+                transaction.chain_id, self.name, chain_id
+            )));
+        }
+        ChainProfile::for_chain_id(chain_id).check(transaction)?;
+
+        let mut result = transaction.sign_with(backend)?;
+        if self.address_format == AddressFormat::Eip55 {
+            result.from = eip55(result.from.trim_start_matches("0x"));
+        }
+        Ok(self.tag(result.hash.clone(), result))
+    }
+
+    fn tag<T>(&self, tx: String, result: T) -> ChainSigned<T> {
+        ChainSigned {
+            chain: self.id.clone(),
+            network: self.name.clone(),
+            explorer_url: self.explorer_link(&tx),
+            result,
+        }
+    }
+}
+
+/// A signing result tagged with the network it targets
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct ChainSigned<T> {
+    /// CAIP-2 chain id
+    pub chain: String,
+    /// Network name
+    pub network: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub explorer_url: Option<String>,
+    #[serde(flatten)]
+    pub result: T,
+}
+
+/// EIP-55 checksum of 40 hex digits
+pub(crate) fn eip55(address: &str) -> String {
+    let lower = address.to_ascii_lowercase();
+    let hash = Keccak256::digest(lower.as_bytes());
+    let mixed: String = lower
+        .chars()
+        .enumerate()
+        .map(|(i, c)| {
+            let nibble = (hash[i / 2] >> if i % 2 == 0 { 4 } else { 0 }) & 0x0f;
+            if nibble >= 8 {
+                c.to_ascii_uppercase()
+            } else {
+                c
+            }
+        })
+        .collect();
+    format!("0x{}", mixed)
+}
+
+/// Built-in and custom chain configurations
+#[derive(Clone, Debug)]
+pub struct ChainRegistry {
+    chains: Vec<ChainConfig>,
+}
+
+impl Default for ChainRegistry {
+    fn default() -> Self {
+        ChainRegistry {
+            chains: vec![
+                ChainConfig::solana_mainnet(),
+                ChainConfig::solana_devnet(),
+                ChainConfig::ethereum(),
+                ChainConfig::base(),
+            ],
+        }
+    }
+}
+
+impl ChainRegistry {
+    /// Add a custom chain, replacing any entry with the same id
+    pub fn register(&mut self, config: ChainConfig) {
+        self.chains.retain(|c| c.id != config.id);
+        self.chains.push(config);
+    }
+
+    /// Look a chain up by CAIP-2 id or (case-insensitive) name
+    pub fn get(&self, id_or_name: &str) -> Option<&ChainConfig> {
+        self.chains
+            .iter()
+            .find(|c| c.id == id_or_name || c.name.eq_ignore_ascii_case(id_or_name))
+    }
+
+    /// The EVM chain with this EIP-155 chain id
+    pub fn evm(&self, chain_id: u64) -> Option<&ChainConfig> {
+        self.get(&format!("eip155:{}", chain_id))
+    }
+
+    pub fn chains(&self) -> &[ChainConfig] {
+        &self.chains
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::ContainerBackend;
+    use crate::crypto::{Cipher, EncryptedKeyContainer};
+    use crate::kdf::KdfParams;
+    use crate::evm::transaction::GasPricing;
+
+    #[test]
+    fn test_registry_and_address_formats() {
+        let mut registry = ChainRegistry::default();
+        assert_eq!(registry.get("base").unwrap().evm_chain_id(), Some(8453));
+        assert_eq!(registry.get(SOLANA_DEVNET_ID).unwrap().name, "Solana Devnet");
+        registry.register(ChainConfig::evm(10, "OP Mainnet"));
+        assert_eq!(registry.evm(10).unwrap().coin_type, 60);
+        assert_eq!(registry.evm(10).unwrap().derivation_preset(), Some(DerivationPreset::Ethereum));
+
+        // EIP-55 test vector
+        let address = hex::decode("5aaeb6053f3e94c9b9a09f33669435e7ef1beaed").unwrap();
+        assert_eq!(
+            ChainConfig::ethereum().format_address(&address).unwrap(),
+            "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed"
+        );
+        assert!(ChainConfig::solana_mainnet().format_address(&address).is_err());
+        assert_eq!(
+            ChainConfig::solana_devnet().explorer_link("abc").unwrap(),
+            "https://explorer.solana.com/tx/abc?cluster=devnet"
+        );
+    }
+
+    #[test]
+    fn test_signing_reports_network() {
+        std::env::set_var("SIGNER_ALLOW_INSECURE_MEMORY", "1");
+        let container =
+            EncryptedKeyContainer::encrypt_with_kdf(&[7u8; 32], "test", Cipher::Aes256Gcm, KdfParams::MINIMUM).unwrap();
+        let backend = ContainerBackend::new(&container, "test");
+        let transaction = EvmTransaction {
+            chain_id: 8453,
+            nonce: 0,
+            gas_limit: 21_000,
+            to: Some([0x11; 20]),
+            value: 1,
+            data: Vec::new(),
+            pricing: GasPricing::Legacy { gas_price: 1 },
+        };
+
+        let signed = ChainConfig::base().sign_evm_transaction(&backend, &transaction).unwrap();
+        assert_eq!((signed.chain.as_str(), signed.network.as_str()), ("eip155:8453", "Base"));
+        assert_eq!(signed.result.v, 35 + 2 * 8453 + u64::from(signed.result.v.is_multiple_of(2)));
+        assert_ne!(signed.result.from, signed.result.from.to_ascii_lowercase());
+        let json = serde_json::to_value(&signed).unwrap();
+        assert!(json["raw_transaction"].is_string());
+        assert!(json["explorer_url"].as_str().unwrap().ends_with(&signed.result.hash));
+
+        assert!(ChainConfig::ethereum().sign_evm_transaction(&backend, &transaction).is_err());
+        assert!(ChainConfig::solana_mainnet().sign_evm_transaction(&backend, &transaction).is_err());
+        let solana = ChainConfig::solana_devnet().sign_solana(&backend, &crate::crypto::test_transaction(&[7u8; 32])).unwrap();
+        assert_eq!(solana.chain, SOLANA_DEVNET_ID);
+    }
+}
@@ -0,0 +1,186 @@
+//! Cosmos SDK signing (SIGN_MODE_DIRECT)
+//!
+//! Cosmos chains sign the protobuf-encoded `SignDoc` of a transaction:
+//! the SHA-256 of its bytes is signed with secp256k1 ECDSA and the
+//! signature is the 64-byte `r || s` with low S. The container's 32-byte
+//! key is the secp256k1 scalar, as for EVM signing.
+//!
+//! Account addresses are `RIPEMD-160(SHA-256(compressed public key))`
+//! encoded as bech32 with the chain's human-readable prefix (`cosmos`,
+//! `osmo`, `juno`, ...).
+
+use bech32::{Bech32, Hrp};
+use k256::ecdsa::signature::hazmat::PrehashSigner;
+use k256::ecdsa::{Signature, SigningKey};
+use ripemd::Ripemd160;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::crypto::{get_locking_mode, EncryptedKeyContainer};
+use crate::entropy::health_check;
+use crate::error::SignerError;
+use crate::secure_buffer::SecureBuffer;
+
+/// Address prefix of the Cosmos Hub
+pub const DEFAULT_HRP: &str = "cosmos";
+
+/// Result of a Cosmos signing operation
+#[derive(Serialize, Deserialize)]
+pub struct CosmosSigningResult {
+    /// The signature (base64, 64 bytes `r || s`), as placed in `TxRaw.signatures`
+    pub signature: String,
+    /// The compressed secp256k1 public key (base64, 33 bytes), as placed in
+    /// a `secp256k1.PubKey`
+    pub public_key: String,
+    /// Bech32 account address
+    pub address: String,
+}
+
+/// Encode a `SignDoc` for SIGN_MODE_DIRECT
+///
+/// `body_bytes` and `auth_info_bytes` are the already-encoded `TxBody` and
+/// `AuthInfo`, exactly as they will appear in the broadcast `TxRaw`.
+pub fn sign_doc_bytes(body_bytes: &[u8], auth_info_bytes: &[u8], chain_id: &str, account_number: u64) -> Vec<u8> {
+    let mut out = Vec::with_capacity(body_bytes.len() + auth_info_bytes.len() + chain_id.len() + 20);
+    // proto3 omits fields with default values
+    for (field, value) in [(1u64, body_bytes), (2, auth_info_bytes), (3, chain_id.as_bytes())] {
+        if !value.is_empty() {
+            write_varint(&mut out, field << 3 | 2);
+            write_varint(&mut out, value.len() as u64);
+            out.extend_from_slice(value);
+        }
+    }
+    if account_number != 0 {
+        write_varint(&mut out, 4 << 3);
+        write_varint(&mut out, account_number);
+    }
+    out
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+/// Bech32 account address of a compressed secp256k1 public key
+pub fn cosmos_address(public_key: &[u8; 33], hrp: &str) -> Result<String, SignerError> {
+    let hrp = Hrp::parse(hrp)
+        .map_err(|e| SignerError::InvalidTransaction(format!("Invalid bech32 prefix: {}", e)))?;
+    bech32::encode::<Bech32>(hrp, &Ripemd160::digest(Sha256::digest(public_key)))
+        .map_err(|e| SignerError::SerializationError(format!("bech32 encoding failed: {}", e)))
+}
+
+/// Sign a `SignDoc` with a key in a secure buffer
+pub(crate) fn sign_cosmos_with_secure_key(
+    secure_key: &mut SecureBuffer,
+    sign_doc: &[u8],
+    hrp: &str,
+) -> Result<CosmosSigningResult, SignerError> {
+    if secure_key.len() != 32 {
+        return Err(SignerError::InvalidKeyFormat(secure_key.len()));
+    }
+    health_check()?;
+
+    let signing_key = SigningKey::from_slice(secure_key.as_slice())
+        .map_err(|e| SignerError::SigningFailed(format!("Invalid secp256k1 key: {}", e)))?;
+    let public_key: [u8; 33] = signing_key
+        .verifying_key()
+        .to_encoded_point(true)
+        .as_bytes()
+        .try_into()
+        .expect("compressed key is 33 bytes");
+    let address = cosmos_address(&public_key, hrp)?;
+
+    // k256 always produces low-S signatures, as the Cosmos SDK requires
+    let signature: Signature = signing_key
+        .sign_prehash(&Sha256::digest(sign_doc))
+        .map_err(|e| SignerError::SigningFailed(format!("ECDSA signing failed: {}", e)))?;
+
+    let base64 = base64::engine::general_purpose::STANDARD;
+    Ok(CosmosSigningResult {
+        signature: base64::Engine::encode(&base64, signature.to_bytes()),
+        public_key: base64::Engine::encode(&base64, public_key),
+        address,
+    })
+}
+
+/// Decrypt a key container and sign a Cosmos `SignDoc`
+///
+/// Same security model as `decrypt_and_sign` but uses secp256k1 ECDSA over
+/// SHA-256, as in SIGN_MODE_DIRECT.
+///
+/// # Arguments
+/// * `container_json` - JSON-serialized EncryptedKeyContainer
+/// * `passphrase` - The passphrase for decryption
+/// * `sign_doc` - The protobuf-encoded `SignDoc` (see [`sign_doc_bytes`])
+/// * `hrp` - Bech32 prefix of the chain's addresses, e.g. [`DEFAULT_HRP`]
+pub fn decrypt_and_sign_cosmos(
+    container_json: &str,
+    passphrase: &str,
+    sign_doc: &[u8],
+    hrp: &str,
+) -> Result<CosmosSigningResult, SignerError> {
+    let container = EncryptedKeyContainer::from_json(container_json)?;
+
+    let mut secure_key = container.decrypt_key(passphrase)?;
+    let result = sign_cosmos_with_secure_key(&mut secure_key, sign_doc, hrp);
+    secure_key.zeroize();
+
+    result
+}
+
+/// Sign a Cosmos `SignDoc` with a raw private key
+///
+/// # Security Warning
+/// Prefer using decrypt_and_sign_cosmos() for the full secure workflow.
+pub fn sign_cosmos(private_key: &[u8], sign_doc: &[u8], hrp: &str) -> Result<CosmosSigningResult, SignerError> {
+    let mut secure_key = SecureBuffer::from_slice_with_mode(private_key, get_locking_mode())?;
+    let result = sign_cosmos_with_secure_key(&mut secure_key, sign_doc, hrp);
+    secure_key.zeroize();
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::Cipher;
+    use crate::kdf::KdfParams;
+    use k256::ecdsa::signature::Verifier;
+    use k256::ecdsa::VerifyingKey;
+
+    #[test]
+    fn test_cosmos_signature_and_address() {
+        std::env::set_var("SIGNER_ALLOW_INSECURE_MEMORY", "1");
+        let container =
+            EncryptedKeyContainer::encrypt_with_kdf(&[3u8; 32], "pw", Cipher::Aes256Gcm, KdfParams::MINIMUM).unwrap();
+        let json = container.to_json().unwrap();
+
+        let sign_doc = sign_doc_bytes(b"body", b"auth", "cosmoshub-4", 300);
+        assert_eq!(
+            sign_doc,
+            [&[0x0a, 4][..], b"body", &[0x12, 4], b"auth", &[0x1a, 11], b"cosmoshub-4", &[0x20, 0xac, 0x02]].concat()
+        );
+
+        let result = decrypt_and_sign_cosmos(&json, "pw", &sign_doc, DEFAULT_HRP).unwrap();
+        let decode = |s: &str| base64::Engine::decode(&base64::engine::general_purpose::STANDARD, s).unwrap();
+        let public_key = VerifyingKey::from_sec1_bytes(&decode(&result.public_key)).unwrap();
+        let signature = Signature::from_slice(&decode(&result.signature)).unwrap();
+        assert!(signature.normalize_s().is_none());
+        // The Verifier impl hashes with SHA-256, like the Cosmos SDK
+        assert!(public_key.verify(&sign_doc, &signature).is_ok());
+
+        // Same key, different chain prefix
+        let (hrp, hash) = bech32::decode(&result.address).unwrap();
+        assert_eq!(hrp.as_str(), "cosmos");
+        assert_eq!(hash, Ripemd160::digest(Sha256::digest(decode(&result.public_key))).to_vec());
+        let osmo = sign_cosmos(&[3u8; 32], &sign_doc, "osmo").unwrap().address;
+        assert_eq!(bech32::decode(&osmo).unwrap().1, hash);
+        assert!(osmo.starts_with("osmo1"));
+
+        assert!(decrypt_and_sign_cosmos(&json, "wrong", &sign_doc, DEFAULT_HRP).is_err());
+        assert!(decrypt_and_sign_cosmos(&json, "pw", &sign_doc, "bad prefix").is_err());
+    }
+}
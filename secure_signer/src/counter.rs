@@ -0,0 +1,191 @@
+//! Per-key signature counters
+//!
+//! Every signature can be numbered: [`SignatureCounter::next`] returns
+//! 1, 2, 3, ... for each public key, and the number is recorded in the
+//! `counter` field of the audit log's `Signed` event. Because the sequence
+//! has no holes, [`crate::audit::verify_counters`] can point at signatures
+//! that were produced but never logged (a gap) or a counter file restored
+//! from an older backup (a repeat).
+//!
+//! # Storage
+//!
+//! The counter file is rewritten with write-to-temp-then-rename before
+//! `next` returns, so a number is never handed out twice by a crash. It is
+//! authenticated with HMAC-SHA256 under a caller-supplied key (for example
+//! `derive_app_secret(&container, passphrase, "signature-counter")`), so
+//! edits are rejected on open. Deleting or rolling back the file cannot be
+//! detected from the file alone; that is what the audit log check is for.
+
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use crate::crypto::get_locking_mode;
+use crate::error::SignerError;
+use crate::secure_buffer::SecureBuffer;
+
+/// Domain separator for the file MAC
+const MAC_DOMAIN: &[u8] = b"coldstar-signature-counter-v1";
+
+/// Shortest accepted MAC key
+const MIN_MAC_KEY_SIZE: usize = 16;
+
+/// On-disk representation of the counters
+#[derive(Serialize, Deserialize)]
+struct CounterFile {
+    version: u8,
+    counters: BTreeMap<String, u64>,
+    /// HMAC-SHA256 over the counters (hex)
+    mac: String,
+}
+
+/// Monotonic signature counters keyed by public key
+pub struct SignatureCounter {
+    path: Option<PathBuf>,
+    mac_key: Option<SecureBuffer>,
+    counters: BTreeMap<String, u64>,
+}
+
+impl SignatureCounter {
+    /// Create counters that live only in memory
+    pub fn in_memory() -> Self {
+        Self {
+            path: None,
+            mac_key: None,
+            counters: BTreeMap::new(),
+        }
+    }
+
+    /// Open (or create) counters persisted at `path`
+    ///
+    /// Fails with [`SignerError::IntegrityError`] if the file was modified
+    /// or was written under a different key.
+    pub fn open(path: impl AsRef<Path>, mac_key: &SecureBuffer) -> Result<Self, SignerError> {
+        if mac_key.len() < MIN_MAC_KEY_SIZE {
+            return Err(SignerError::IntegrityError(format!(
+                "counter MAC key must be at least {} bytes",
+                MIN_MAC_KEY_SIZE
+            )));
+        }
+        let path = path.as_ref().to_path_buf();
+        let mut store = Self {
+            path: None,
+            mac_key: Some(SecureBuffer::from_slice_with_mode(mac_key.as_slice(), get_locking_mode())?),
+            counters: BTreeMap::new(),
+        };
+
+        if path.exists() {
+            let file: CounterFile = serde_json::from_str(&std::fs::read_to_string(&path)?)?;
+            let expected = hex::decode(&file.mac).unwrap_or_default();
+            store
+                .mac(&file.counters)?
+                .verify_slice(&expected)
+                .map_err(|_| SignerError::IntegrityError("signature counter file was modified".to_string()))?;
+            store.counters = file.counters;
+        }
+
+        store.path = Some(path);
+        Ok(store)
+    }
+
+    /// Number of signatures counted for a public key so far
+    pub fn current(&self, public_key: &str) -> u64 {
+        self.counters.get(public_key).copied().unwrap_or(0)
+    }
+
+    /// Count a new signature, returning its number (starting at 1)
+    ///
+    /// The new value is persisted before it is returned.
+    pub fn next(&mut self, public_key: &str) -> Result<u64, SignerError> {
+        let value = self
+            .current(public_key)
+            .checked_add(1)
+            .ok_or_else(|| SignerError::IntegrityError("signature counter overflow".to_string()))?;
+        self.counters.insert(public_key.to_string(), value);
+
+        if let Err(e) = self.persist() {
+            self.counters.insert(public_key.to_string(), value - 1);
+            return Err(e);
+        }
+        Ok(value)
+    }
+
+    fn mac(&self, counters: &BTreeMap<String, u64>) -> Result<Hmac<Sha256>, SignerError> {
+        let key = self
+            .mac_key
+            .as_ref()
+            .ok_or_else(|| SignerError::IntegrityError("no counter MAC key".to_string()))?;
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(key.as_slice())
+            .map_err(|e| SignerError::IntegrityError(e.to_string()))?;
+        mac.update(MAC_DOMAIN);
+        mac.update(&serde_json::to_vec(counters)?);
+        Ok(mac)
+    }
+
+    fn persist(&self) -> Result<(), SignerError> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        let file = CounterFile {
+            version: 1,
+            counters: self.counters.clone(),
+            mac: hex::encode(self.mac(&self.counters)?.finalize().into_bytes()),
+        };
+        let json = serde_json::to_string(&file)?;
+
+        // A fresh temp file, so it is created with the owner-only mode
+        let tmp_path = path.with_extension("tmp");
+        if let Err(e) = std::fs::remove_file(&tmp_path) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                return Err(e.into());
+            }
+        }
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        options.open(&tmp_path)?.write_all(json.as_bytes())?;
+        std::fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counters_persist_and_detect_edits() {
+        let path = std::env::temp_dir().join(format!("coldstar-counter-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let key = SecureBuffer::from_slice_with_mode(&[3u8; 32], get_locking_mode()).unwrap();
+
+        let mut counter = SignatureCounter::open(&path, &key).unwrap();
+        assert_eq!(counter.next("alice").unwrap(), 1);
+        assert_eq!(counter.next("alice").unwrap(), 2);
+        assert_eq!(counter.next("bob").unwrap(), 1);
+
+        let reopened = SignatureCounter::open(&path, &key).unwrap();
+        assert_eq!(reopened.current("alice"), 2);
+        assert_eq!(reopened.current("carol"), 0);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        }
+
+        let other_key = SecureBuffer::from_slice_with_mode(&[4u8; 32], get_locking_mode()).unwrap();
+        assert!(matches!(SignatureCounter::open(&path, &other_key), Err(SignerError::IntegrityError(_))));
+
+        let edited = std::fs::read_to_string(&path).unwrap().replace("\"alice\":2", "\"alice\":1");
+        std::fs::write(&path, edited).unwrap();
+        assert!(matches!(SignatureCounter::open(&path, &key), Err(SignerError::IntegrityError(_))));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -2,61 +2,349 @@
 //!
 //! This module handles:
 //! - Key derivation (Argon2id)
-//! - Symmetric encryption/decryption (AES-256-GCM)
+//! - Symmetric encryption/decryption (AES-256-GCM, XChaCha20-Poly1305)
 //! - Ed25519 signing (Solana-compatible)
 //! - secp256k1 ECDSA signing (EVM/Base-compatible)
+//! - secp256k1 BIP-340 Schnorr signing (Taproot-compatible)
 //!
 //! # Security Model
 //!
 //! All operations involving plaintext private keys use SecureBuffer
 //! to ensure memory is locked and zeroized.
 
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicU8, Ordering};
+
 use aes_gcm::{
-    aead::{Aead, KeyInit},
+    aead::{Aead, AeadInPlace, KeyInit, Payload},
     Aes256Gcm, Nonce,
 };
-use argon2::{Argon2, Params, Version};
+use aes_gcm_siv::Aes256GcmSiv;
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
 use ed25519_dalek::{Signature, Signer, SigningKey};
+use k256::ecdsa::hazmat::{self, SignPrimitive};
 use k256::ecdsa::{SigningKey as K256SigningKey, VerifyingKey as K256VerifyingKey};
-use rand::rngs::OsRng;
-use rand::RngCore;
+use k256::Secp256k1;
 use serde::{Deserialize, Serialize};
 use sha3::{Digest, Keccak256};
+use zeroize::Zeroizing;
 
+use crate::backend::{ContainerBackend, SignerBackend};
+use crate::encoding::{Encoding, OutputEncoding};
+use crate::entropy::{fill_random, health_check};
 use crate::error::SignerError;
+use crate::kdf::{derive_key, Kdf, KdfParams};
+use crate::reader::{ByteReader, ParseError};
+use crate::duress::{self, DuressSlot};
+use crate::keyfile::Keyfile;
+use crate::metrics::{self, Counter, Histogram};
+use crate::keys::SecureKdfKey;
+use crate::validity::Validity;
 use crate::secure_buffer::{LockingMode, SecureBuffer};
+use crate::secure_vec::SecureVec;
 
 /// Environment variable to allow insecure memory (permissive mode)
 /// Set to "1" or "true" to allow operation when mlock fails.
 /// WARNING: Only use this for testing or on systems that don't support mlock.
+#[cfg(feature = "std")]
 const ENV_ALLOW_INSECURE: &str = "SIGNER_ALLOW_INSECURE_MEMORY";
 
-/// Get the appropriate locking mode based on environment
-fn get_locking_mode() -> LockingMode {
-    match std::env::var(ENV_ALLOW_INSECURE) {
-        Ok(val) if val == "1" || val.eq_ignore_ascii_case("true") => LockingMode::Permissive,
-        _ => LockingMode::Strict,
+/// Environment variable to put keys in guard-paged buffers
+/// ([`LockingMode::Hardened`]). Ignored when insecure memory is allowed.
+#[cfg(feature = "std")]
+const ENV_HARDENED: &str = "SIGNER_HARDENED_MEMORY";
+
+/// Environment variable to verify every signature before returning it:
+/// `1`/`true` or `0`/`false`. Unset, it follows the locking mode (on in
+/// [`LockingMode::Hardened`], off otherwise).
+#[cfg(feature = "std")]
+const ENV_VERIFY_SIGNATURES: &str = "SIGNER_VERIFY_SIGNATURES";
+
+/// Locking mode set with [`set_locking_mode`] (0: not set)
+static LOCKING_MODE: AtomicU8 = AtomicU8::new(0);
+
+/// Set the locking mode for every key the library decrypts, overriding
+/// the environment
+///
+/// Without `std` there are no environment variables, and this is the only
+/// way to choose a mode other than [`LockingMode::Strict`].
+pub fn set_locking_mode(mode: LockingMode) {
+    let value = match mode {
+        LockingMode::Strict => 1,
+        LockingMode::Permissive => 2,
+        LockingMode::Hardened => 3,
+    };
+    LOCKING_MODE.store(value, Ordering::SeqCst);
+}
+
+/// Settings a [`Signer`](crate::signer::Signer) applies to its calls
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct ThreadSettings {
+    pub(crate) locking_mode: LockingMode,
+    pub(crate) verify_signatures: Option<bool>,
+}
+
+#[cfg(feature = "std")]
+std::thread_local! {
+    static THREAD_SETTINGS: core::cell::Cell<Option<ThreadSettings>> = const { core::cell::Cell::new(None) };
+}
+
+/// Run `f` with `settings` in place of the process-wide ones on this
+/// thread, restoring the previous settings afterwards (even on a panic)
+#[cfg(feature = "std")]
+pub(crate) fn with_thread_settings<T>(settings: ThreadSettings, f: impl FnOnce() -> T) -> T {
+    struct Restore(Option<ThreadSettings>);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            THREAD_SETTINGS.with(|current| current.set(self.0));
+        }
     }
+    let _restore = Restore(THREAD_SETTINGS.with(|current| current.replace(Some(settings))));
+    f()
+}
+
+#[cfg(feature = "std")]
+fn thread_settings() -> Option<ThreadSettings> {
+    THREAD_SETTINGS.with(|current| current.get())
 }
 
-/// Argon2 parameters for key derivation
-/// These are intentionally strong to resist brute-force attacks
-const ARGON2_MEMORY_COST: u32 = 65536; // 64 MB
-const ARGON2_TIME_COST: u32 = 3; // 3 iterations
-const ARGON2_PARALLELISM: u32 = 4; // 4 parallel lanes
+#[cfg(not(feature = "std"))]
+fn thread_settings() -> Option<ThreadSettings> {
+    None
+}
+
+/// Get the appropriate locking mode: a [`Signer`](crate::signer::Signer)'s
+/// on its calls, else [`set_locking_mode`]'s, else the environment's
+///
+/// Browser WebAssembly has neither lockable memory nor environment
+/// variables, so there it is always [`LockingMode::Permissive`]: buffers
+/// are zeroized but never locked.
+pub(crate) fn get_locking_mode() -> LockingMode {
+    if let Some(settings) = thread_settings() {
+        return settings.locking_mode;
+    }
+    match LOCKING_MODE.load(Ordering::SeqCst) {
+        1 => return LockingMode::Strict,
+        2 => return LockingMode::Permissive,
+        3 => return LockingMode::Hardened,
+        _ => {}
+    }
+    env_locking_mode()
+}
+
+#[cfg(not(feature = "std"))]
+fn env_locking_mode() -> LockingMode {
+    LockingMode::Strict
+}
+
+#[cfg(feature = "std")]
+fn env_locking_mode() -> LockingMode {
+    if cfg!(all(target_arch = "wasm32", target_os = "unknown")) {
+        return LockingMode::Permissive;
+    }
+    let enabled = |name| matches!(std::env::var(name), Ok(val) if val == "1" || val.eq_ignore_ascii_case("true"));
+    if enabled(ENV_ALLOW_INSECURE) {
+        LockingMode::Permissive
+    } else if enabled(ENV_HARDENED) {
+        LockingMode::Hardened
+    } else {
+        LockingMode::Strict
+    }
+}
+
+/// Whether fresh signatures are verified before they are released
+///
+/// A fault injected while signing (a glitch, a flipped bit) can yield a
+/// wrong signature, and one wrong ECDSA or Ed25519 signature can be enough
+/// to recover the key. Checking each signature against the public key
+/// catches that before it leaves the process.
+pub(crate) fn verify_after_signing() -> bool {
+    if let Some(settings) = thread_settings() {
+        return settings.verify_signatures.unwrap_or(settings.locking_mode == LockingMode::Hardened);
+    }
+    #[cfg(feature = "std")]
+    return verify_signatures_setting(std::env::var(ENV_VERIFY_SIGNATURES).ok().as_deref(), get_locking_mode());
+    #[cfg(not(feature = "std"))]
+    return verify_signatures_setting(None, get_locking_mode());
+}
+
+fn verify_signatures_setting(setting: Option<&str>, mode: LockingMode) -> bool {
+    match setting {
+        Some(value) if value == "1" || value.eq_ignore_ascii_case("true") => true,
+        Some(value) if value == "0" || value.eq_ignore_ascii_case("false") => false,
+        _ => mode == LockingMode::Hardened,
+    }
+}
+
+/// The error for a signature that failed [`verify_after_signing`]; the
+/// signature itself is discarded
+pub(crate) fn faulty_signature(algorithm: &str) -> SignerError {
+    SignerError::IntegrityError(format!(
+        "{} signature failed verification after signing and was discarded (possible fault)",
+        algorithm
+    ))
+}
 
 /// Size constants
-const KEY_SIZE: usize = 32; // 256 bits for AES-256
 const NONCE_SIZE: usize = 12; // 96 bits for AES-GCM
-const SALT_SIZE: usize = 32; // 256 bits for Argon2
-const ED25519_SEED_SIZE: usize = 32;
+const XCHACHA_NONCE_SIZE: usize = 24; // 192 bits for XChaCha20-Poly1305
+pub(crate) const SALT_SIZE: usize = 32; // 256 bits for Argon2
+pub(crate) const ED25519_SEED_SIZE: usize = 32;
 const ED25519_KEYPAIR_SIZE: usize = 64;
 
+/// Container format versions
+const CONTAINER_VERSION_V1: u8 = 1; // AES-256-GCM only, no cipher field
+pub(crate) const CONTAINER_VERSION_V2: u8 = 2; // Adds the cipher and kdf fields
+pub(crate) const CONTAINER_VERSION: u8 = 3; // Authenticates the other fields as associated data
+
+/// Domain separator for a container's associated data
+const CONTAINER_AAD_DOMAIN: &[u8] = b"coldstar-container-aad-v3";
+
+/// Domain separator for container IDs
+const CONTAINER_ID_DOMAIN: &[u8] = b"coldstar-container-id-v1";
+
+/// Symmetric cipher used to encrypt the private key inside a container
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Cipher {
+    /// AES-256-GCM with a 96-bit random nonce (the only cipher in v1 containers)
+    #[default]
+    #[serde(rename = "aes-256-gcm")]
+    Aes256Gcm,
+    /// XChaCha20-Poly1305 with a 192-bit random nonce, safe to generate at
+    /// random for any number of containers
+    #[serde(rename = "xchacha20-poly1305")]
+    XChaCha20Poly1305,
+    /// AES-256-GCM-SIV (RFC 8452) with a 96-bit nonce; a repeated nonce
+    /// only reveals whether two plaintexts are equal, instead of the key
+    /// stream and authentication key
+    #[serde(rename = "aes-256-gcm-siv")]
+    Aes256GcmSiv,
+}
+
+impl core::str::FromStr for Cipher {
+    type Err = SignerError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "aes-256-gcm" => Ok(Cipher::Aes256Gcm),
+            "xchacha20-poly1305" => Ok(Cipher::XChaCha20Poly1305),
+            "aes-256-gcm-siv" => Ok(Cipher::Aes256GcmSiv),
+            other => Err(SignerError::ContainerError(format!(
+                "unknown cipher '{}' (expected aes-256-gcm, xchacha20-poly1305 or aes-256-gcm-siv)",
+                other
+            ))),
+        }
+    }
+}
+
+impl Cipher {
+    /// Nonce size in bytes for this cipher
+    pub fn nonce_size(&self) -> usize {
+        match self {
+            Cipher::Aes256Gcm | Cipher::Aes256GcmSiv => NONCE_SIZE,
+            Cipher::XChaCha20Poly1305 => XCHACHA_NONCE_SIZE,
+        }
+    }
+
+    /// Encrypt `plaintext`, returning ciphertext with the auth tag appended
+    pub(crate) fn encrypt(&self, key: &[u8], nonce: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, SignerError> {
+        self.encrypt_with_aad(key, nonce, plaintext, b"")
+    }
+
+    /// [`Cipher::encrypt`], also authenticating `aad`
+    pub(crate) fn encrypt_with_aad(
+        &self,
+        key: &[u8],
+        nonce: &[u8],
+        plaintext: &[u8],
+        aad: &[u8],
+    ) -> Result<Vec<u8>, SignerError> {
+        let payload = Payload { msg: plaintext, aad };
+        let result = match self {
+            Cipher::Aes256Gcm => Aes256Gcm::new_from_slice(key)
+                .map_err(|e| SignerError::KeyDerivationFailed(e.to_string()))?
+                .encrypt(Nonce::from_slice(nonce), payload),
+            Cipher::XChaCha20Poly1305 => XChaCha20Poly1305::new_from_slice(key)
+                .map_err(|e| SignerError::KeyDerivationFailed(e.to_string()))?
+                .encrypt(XNonce::from_slice(nonce), payload),
+            Cipher::Aes256GcmSiv => Aes256GcmSiv::new_from_slice(key)
+                .map_err(|e| SignerError::KeyDerivationFailed(e.to_string()))?
+                .encrypt(Nonce::from_slice(nonce), payload),
+        };
+        result.map_err(|_| SignerError::SigningFailed("Encryption failed".to_string()))
+    }
+
+    /// Decrypt and authenticate `buffer` in place, so the plaintext only
+    /// ever exists in locked memory
+    ///
+    /// The tag is checked before anything is decrypted; on failure the
+    /// buffer still holds the ciphertext.
+    pub(crate) fn decrypt_in_place(&self, key: &[u8], nonce: &[u8], buffer: &mut SecureVec) -> Result<(), SignerError> {
+        self.decrypt_in_place_with_aad(key, nonce, buffer, b"")
+    }
+
+    /// [`Cipher::decrypt_in_place`], also authenticating `aad`
+    pub(crate) fn decrypt_in_place_with_aad(
+        &self,
+        key: &[u8],
+        nonce: &[u8],
+        buffer: &mut SecureVec,
+        aad: &[u8],
+    ) -> Result<(), SignerError> {
+        self.check_nonce(nonce)?;
+        let result = match self {
+            Cipher::Aes256Gcm => Aes256Gcm::new_from_slice(key)
+                .map_err(|e| SignerError::KeyDerivationFailed(e.to_string()))?
+                .decrypt_in_place(Nonce::from_slice(nonce), aad, buffer),
+            Cipher::XChaCha20Poly1305 => XChaCha20Poly1305::new_from_slice(key)
+                .map_err(|e| SignerError::KeyDerivationFailed(e.to_string()))?
+                .decrypt_in_place(XNonce::from_slice(nonce), aad, buffer),
+            Cipher::Aes256GcmSiv => Aes256GcmSiv::new_from_slice(key)
+                .map_err(|e| SignerError::KeyDerivationFailed(e.to_string()))?
+                .decrypt_in_place(Nonce::from_slice(nonce), aad, buffer),
+        };
+        result.map_err(|_| SignerError::DecryptionFailed)
+    }
+
+    /// Decrypt `ciphertext` into an exactly sized locked buffer
+    pub(crate) fn decrypt_to_secure(&self, key: &[u8], nonce: &[u8], ciphertext: &[u8]) -> Result<SecureBuffer, SignerError> {
+        self.decrypt_to_secure_with_aad(key, nonce, ciphertext, b"")
+    }
+
+    /// [`Cipher::decrypt_to_secure`], also authenticating `aad`
+    pub(crate) fn decrypt_to_secure_with_aad(
+        &self,
+        key: &[u8],
+        nonce: &[u8],
+        ciphertext: &[u8],
+        aad: &[u8],
+    ) -> Result<SecureBuffer, SignerError> {
+        let mut plaintext = SecureVec::from_slice_with_mode(ciphertext, get_locking_mode())?;
+        self.decrypt_in_place_with_aad(key, nonce, &mut plaintext, aad)?;
+        plaintext.into_secure_buffer()
+    }
+
+    fn check_nonce(&self, nonce: &[u8]) -> Result<(), SignerError> {
+        if nonce.len() != self.nonce_size() {
+            return Err(SignerError::ContainerError(format!(
+                "nonce must be {} bytes for {:?}, got {}",
+                self.nonce_size(),
+                self,
+                nonce.len()
+            )));
+        }
+        Ok(())
+    }
+}
+
 /// Encrypted key container format
 ///
 /// This structure holds all data needed to decrypt a private key:
-/// - Salt for key derivation
-/// - Nonce for AES-GCM
+/// - Salt and KDF parameters (Argon2id; scrypt/PBKDF2 for imported keys)
+/// - Cipher and nonce (AES-256-GCM, XChaCha20-Poly1305 or AES-256-GCM-SIV)
 /// - Encrypted private key (ciphertext + auth tag)
 ///
 /// The container can be serialized to JSON for storage/transmission.
@@ -64,20 +352,72 @@ const ED25519_KEYPAIR_SIZE: usize = 64;
 pub struct EncryptedKeyContainer {
     /// Version for future format changes
     pub version: u8,
-    /// Salt for Argon2 key derivation (base64)
+    /// Symmetric cipher (absent in version 1 containers, which are AES-256-GCM)
+    #[serde(default)]
+    pub cipher: Cipher,
+    /// Key derivation function and parameters (absent in older containers,
+    /// which used the default Argon2id parameters)
+    #[serde(default)]
+    pub kdf: Kdf,
+    /// Salt for key derivation (base64)
     pub salt: String,
-    /// Nonce for AES-GCM (base64)
+    /// Nonce for the cipher (base64)
     pub nonce: String,
     /// Encrypted private key with auth tag (base64)
     pub ciphertext: String,
     /// Public key for verification (base58, optional)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub public_key: Option<String>,
+    /// Decryption also needs a [`Keyfile`] (version 3 and later)
+    #[serde(default, skip_serializing_if = "core::ops::Not::not")]
+    pub keyfile: bool,
+    /// Unix time before which the key may not be used (version 3 and later)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub not_before: Option<u64>,
+    /// Unix time after which the key may not be used (version 3 and later)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub not_after: Option<u64>,
+    /// Decoy key opened by a duress passphrase
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duress: Option<DuressSlot>,
+}
+
+/// How [`EncryptedKeyContainer::seal`] builds a container
+#[derive(Clone, Copy)]
+pub(crate) struct Sealing<'a> {
+    pub cipher: Cipher,
+    pub kdf: KdfParams,
+    /// 2 or 3 (3 for keyfiles and validity windows)
+    pub version: u8,
+    pub keyfile: Option<&'a Keyfile>,
+    pub with_public_key: bool,
+    pub validity: Validity,
+    /// Authenticated along with the container's own fields, and needed
+    /// again to decrypt (see [`EncryptedKeyContainer::decrypt_key_in_context`])
+    pub context: &'a [u8],
+}
+
+impl Sealing<'_> {
+    /// A current-version container with its public key and no extras
+    pub fn new(cipher: Cipher, kdf: KdfParams) -> Self {
+        Self {
+            cipher,
+            kdf,
+            version: CONTAINER_VERSION,
+            keyfile: None,
+            with_public_key: true,
+            validity: Validity::default(),
+            context: b"",
+        }
+    }
 }
 
 impl EncryptedKeyContainer {
     /// Create a new encrypted key container from a plaintext private key
     ///
+    /// Uses AES-256-GCM. See [`EncryptedKeyContainer::encrypt_with_cipher`]
+    /// to select a different cipher.
+    ///
     /// # Arguments
     /// * `private_key` - The 32-byte Ed25519 seed or 64-byte keypair
     /// * `passphrase` - The passphrase to encrypt with
@@ -89,6 +429,81 @@ impl EncryptedKeyContainer {
     /// The private key is copied into a secure buffer for processing,
     /// and all intermediate values are zeroized.
     pub fn encrypt(private_key: &[u8], passphrase: &str) -> Result<Self, SignerError> {
+        Self::encrypt_with_cipher(private_key, passphrase, Cipher::Aes256Gcm)
+    }
+
+    /// Create a new encrypted key container using the given cipher
+    pub fn encrypt_with_cipher(
+        private_key: &[u8],
+        passphrase: &str,
+        cipher: Cipher,
+    ) -> Result<Self, SignerError> {
+        Self::encrypt_with_kdf(private_key, passphrase, cipher, KdfParams::default())
+    }
+
+    /// Create a new encrypted key container with explicit cipher and KDF parameters
+    ///
+    /// The parameters are stored in the container and used again on
+    /// decryption. Parameters below [`KdfParams::MINIMUM`] are rejected.
+    pub fn encrypt_with_kdf(
+        private_key: &[u8],
+        passphrase: &str,
+        cipher: Cipher,
+        kdf: KdfParams,
+    ) -> Result<Self, SignerError> {
+        Self::seal(private_key, passphrase, &Sealing::new(cipher, kdf))
+    }
+
+    /// Create a container that takes both `passphrase` and `keyfile` to
+    /// decrypt
+    ///
+    /// The keyfile is Argon2id's secret input, so the container is useless
+    /// without it however the passphrase is obtained. See [`Keyfile`].
+    pub fn encrypt_with_keyfile(
+        private_key: &[u8],
+        passphrase: &str,
+        keyfile: &Keyfile,
+        cipher: Cipher,
+        kdf: KdfParams,
+    ) -> Result<Self, SignerError> {
+        let sealing = Sealing {
+            keyfile: Some(keyfile),
+            ..Sealing::new(cipher, kdf)
+        };
+        Self::seal(private_key, passphrase, &sealing)
+    }
+
+    /// [`EncryptedKeyContainer::encrypt_with_kdf`] without the public key,
+    /// for secrets (such as Shamir shares) that are not signing keys, bound
+    /// to `context`
+    pub(crate) fn encrypt_without_public_key(
+        secret: &[u8],
+        passphrase: &str,
+        cipher: Cipher,
+        kdf: KdfParams,
+        context: &[u8],
+    ) -> Result<Self, SignerError> {
+        let sealing = Sealing {
+            with_public_key: false,
+            context,
+            ..Sealing::new(cipher, kdf)
+        };
+        Self::seal(secret, passphrase, &sealing)
+    }
+
+    /// Encrypt `private_key` into a new container as `sealing` describes
+    pub(crate) fn seal(private_key: &[u8], passphrase: &str, sealing: &Sealing) -> Result<Self, SignerError> {
+        let Sealing {
+            cipher,
+            kdf,
+            version,
+            keyfile,
+            with_public_key,
+            validity,
+            context,
+        } = *sealing;
+        kdf.check_minimum()?;
+
         // Validate key size
         if private_key.len() != ED25519_SEED_SIZE && private_key.len() != ED25519_KEYPAIR_SIZE {
             return Err(SignerError::InvalidKeyFormat(private_key.len()));
@@ -102,20 +517,15 @@ impl EncryptedKeyContainer {
 
         // Generate random salt and nonce
         let mut salt = [0u8; SALT_SIZE];
-        let mut nonce = [0u8; NONCE_SIZE];
-        OsRng.fill_bytes(&mut salt);
-        OsRng.fill_bytes(&mut nonce);
+        let mut nonce = vec![0u8; cipher.nonce_size()];
+        fill_random(&mut salt)?;
+        fill_random(&mut nonce)?;
 
         // Derive encryption key from passphrase
-        let mut derived_key = derive_key(passphrase.as_bytes(), &salt)?;
-
-        // Encrypt the private key
-        let cipher = Aes256Gcm::new_from_slice(derived_key.as_slice())
-            .map_err(|e| SignerError::KeyDerivationFailed(e.to_string()))?;
-
-        let ciphertext = cipher
-            .encrypt(Nonce::from_slice(&nonce), secure_key.as_slice())
-            .map_err(|_| SignerError::SigningFailed("Encryption failed".to_string()))?;
+        let mut derived_key = match keyfile {
+            Some(keyfile) => Kdf::Argon2id(kdf).derive_with_secret(passphrase.as_bytes(), &salt, keyfile.as_slice())?,
+            None => derive_key(passphrase.as_bytes(), &salt, &kdf)?,
+        };
 
         // Get public key for verification
         let signing_key = SigningKey::from_bytes(
@@ -125,17 +535,48 @@ impl EncryptedKeyContainer {
         );
         let public_key = bs58::encode(signing_key.verifying_key().as_bytes()).into_string();
 
+        let mut container = Self {
+            version,
+            cipher,
+            kdf: Kdf::Argon2id(kdf),
+            salt: base64::Engine::encode(&base64::engine::general_purpose::STANDARD, salt),
+            nonce: base64::Engine::encode(&base64::engine::general_purpose::STANDARD, &nonce),
+            ciphertext: String::new(),
+            public_key: with_public_key.then_some(public_key),
+            keyfile: keyfile.is_some(),
+            not_before: validity.not_before,
+            not_after: validity.not_after,
+            duress: None,
+        };
+        container.check_version()?;
+
+        // Encrypt the private key, authenticating every other field
+        let mut aad = container.associated_data()?;
+        aad.extend_from_slice(context);
+        let ciphertext = cipher.encrypt_with_aad(derived_key.as_slice(), &nonce, secure_key.as_slice(), &aad);
+
         // Zeroize sensitive data
         secure_key.zeroize();
         derived_key.zeroize();
 
-        Ok(Self {
-            version: 1,
-            salt: base64::Engine::encode(&base64::engine::general_purpose::STANDARD, salt),
-            nonce: base64::Engine::encode(&base64::engine::general_purpose::STANDARD, nonce),
-            ciphertext: base64::Engine::encode(&base64::engine::general_purpose::STANDARD, ciphertext),
-            public_key: Some(public_key),
-        })
+        container.ciphertext = base64::Engine::encode(&base64::engine::general_purpose::STANDARD, ciphertext?);
+        Ok(container)
+    }
+
+    /// Re-encrypt the key under a new passphrase
+    ///
+    /// The cipher and validity window are kept; the salt, nonce and KDF
+    /// parameters are fresh (current defaults), so the result has a new
+    /// [`container_id`](EncryptedKeyContainer::container_id).
+    pub fn rekey(&self, passphrase: &str, new_passphrase: &str) -> Result<Self, SignerError> {
+        let mut key = self.decrypt_key(passphrase)?;
+        let sealing = Sealing {
+            validity: self.validity(),
+            ..Sealing::new(self.cipher, KdfParams::default())
+        };
+        let container = Self::seal(key.as_slice(), new_passphrase, &sealing);
+        key.zeroize();
+        container
     }
 
     /// Serialize the container to JSON
@@ -147,6 +588,194 @@ impl EncryptedKeyContainer {
     pub fn from_json(json: &str) -> Result<Self, SignerError> {
         serde_json::from_str(json).map_err(|e| SignerError::ContainerError(e.to_string()))
     }
+
+    /// Stable identifier for this container
+    ///
+    /// `hex(SHA-256("coldstar-container-id-v1" || public_key || 0 || salt))`,
+    /// truncated to 16 bytes. It depends only on the public key and the salt
+    /// chosen at creation, so it survives re-serialization and copies, but
+    /// re-encrypting the key (new salt) yields a new ID. Vaults, policies,
+    /// and audit entries refer to containers by this ID.
+    pub fn container_id(&self) -> Result<String, SignerError> {
+        use sha2::{Digest as _, Sha256};
+
+        let public_key = self
+            .public_key
+            .as_deref()
+            .ok_or_else(|| SignerError::ContainerError("container has no public key".to_string()))?;
+        let salt = base64::Engine::decode(&base64::engine::general_purpose::STANDARD, &self.salt)?;
+
+        let mut hasher = Sha256::new();
+        hasher.update(CONTAINER_ID_DOMAIN);
+        hasher.update(public_key.as_bytes());
+        hasher.update([0u8]);
+        hasher.update(&salt);
+        Ok(hex::encode(&hasher.finalize()[..16]))
+    }
+
+    /// Decrypt the private key into a secure buffer
+    ///
+    /// Fails outside the container's [`Validity`] window. The container's
+    /// duress passphrase, if it has one, yields the decoy key.
+    ///
+    /// # Memory Lifecycle
+    /// The plaintext is moved into a SecureBuffer immediately and the
+    /// intermediate copy and derived key are zeroized before returning.
+    pub(crate) fn decrypt_key(&self, passphrase: &str) -> Result<SecureBuffer, SignerError> {
+        let mut unlock_key = self.derive_unlock_key(passphrase)?;
+        let result = self.decrypt_key_with_unlock_key(&unlock_key);
+        unlock_key.zeroize();
+        result
+    }
+
+    /// Decrypt a container sealed with a [`Sealing::context`], which must
+    /// match
+    ///
+    /// Such containers (Shamir shares) have no validity window or duress
+    /// slot.
+    pub(crate) fn decrypt_key_in_context(&self, passphrase: &str, context: &[u8]) -> Result<SecureBuffer, SignerError> {
+        let mut unlock_key = self.derive_unlock_key(passphrase)?;
+        let nonce = base64::Engine::decode(&base64::engine::general_purpose::STANDARD, &self.nonce)?;
+        let ciphertext = base64::Engine::decode(&base64::engine::general_purpose::STANDARD, &self.ciphertext)?;
+        let mut aad = self.associated_data()?;
+        aad.extend_from_slice(context);
+        let result = self.cipher.decrypt_to_secure_with_aad(unlock_key.as_slice(), &nonce, &ciphertext, &aad);
+        unlock_key.zeroize();
+        result
+    }
+
+    /// [`EncryptedKeyContainer::decrypt_key`] for a container that may need
+    /// a keyfile
+    pub(crate) fn decrypt_key_with_keyfile(
+        &self,
+        passphrase: &str,
+        keyfile: Option<&Keyfile>,
+    ) -> Result<SecureBuffer, SignerError> {
+        let mut unlock_key = self.derive_unlock_key_with_keyfile(passphrase, keyfile)?;
+        let result = self.decrypt_key_with_unlock_key(&unlock_key);
+        unlock_key.zeroize();
+        result
+    }
+
+    /// Run the container's KDF, producing the key that decrypts it
+    ///
+    /// Callers that sign repeatedly can keep this key (e.g. in the kernel
+    /// keyring) and skip the KDF with
+    /// [`EncryptedKeyContainer::decrypt_key_with_unlock_key`].
+    pub(crate) fn derive_unlock_key(&self, passphrase: &str) -> Result<SecureKdfKey, SignerError> {
+        self.derive_unlock_key_with_keyfile(passphrase, None)
+    }
+
+    /// [`EncryptedKeyContainer::derive_unlock_key`] for a container that
+    /// may need a keyfile
+    pub(crate) fn derive_unlock_key_with_keyfile(
+        &self,
+        passphrase: &str,
+        keyfile: Option<&Keyfile>,
+    ) -> Result<SecureKdfKey, SignerError> {
+        self.check_version()?;
+        let salt = base64::Engine::decode(&base64::engine::general_purpose::STANDARD, &self.salt)?;
+        let derive = |secret: Option<&[u8]>| {
+            metrics::time(Histogram::KdfDuration, &[("kdf", self.kdf.name())], || match secret {
+                Some(secret) => self.kdf.derive_with_secret(passphrase.as_bytes(), &salt, secret),
+                None => self.kdf.derive(passphrase.as_bytes(), &salt),
+            })
+        };
+        let key = match (self.keyfile, keyfile) {
+            (true, Some(keyfile)) => derive(Some(keyfile.as_slice()))?,
+            (false, None) => derive(None)?,
+            (true, None) => return Err(SignerError::ContainerError("container requires a keyfile".to_string())),
+            (false, Some(_)) => {
+                return Err(SignerError::ContainerError("container does not use a keyfile".to_string()))
+            }
+        };
+        SecureKdfKey::from_buffer(key)
+    }
+
+    /// Decrypt the private key with a key from
+    /// [`EncryptedKeyContainer::derive_unlock_key`]
+    ///
+    /// Fails outside the container's [`Validity`] window.
+    pub(crate) fn decrypt_key_with_unlock_key(&self, unlock_key: &SecureKdfKey) -> Result<SecureBuffer, SignerError> {
+        self.check_validity()?;
+        self.decrypt_key_at_any_time(unlock_key)
+    }
+
+    /// [`EncryptedKeyContainer::decrypt_key_with_unlock_key`] without the
+    /// validity check, for changing the window itself
+    pub(crate) fn decrypt_key_at_any_time(&self, unlock_key: &SecureKdfKey) -> Result<SecureBuffer, SignerError> {
+        self.check_version()?;
+
+        // A duress passphrase derives the key to the decoy slot instead
+        let result = match self.open_slot(unlock_key, &self.nonce, &self.ciphertext) {
+            Err(SignerError::DecryptionFailed) if self.duress.is_some() => {
+                let slot = self.duress.as_ref().expect("checked above");
+                self.open_slot(unlock_key, &slot.nonce, &slot.ciphertext).inspect(|_| duress::raise(self))
+            }
+            result => result,
+        };
+        if let Err(SignerError::DecryptionFailed) = result {
+            metrics::increment(Counter::DecryptionFailures, &[]);
+        }
+        result
+    }
+
+    /// Decrypt one key slot (base64 nonce and ciphertext)
+    pub(crate) fn open_slot(
+        &self,
+        unlock_key: &SecureKdfKey,
+        nonce: &str,
+        ciphertext: &str,
+    ) -> Result<SecureBuffer, SignerError> {
+        // Decode base64 fields
+        let nonce = base64::Engine::decode(&base64::engine::general_purpose::STANDARD, nonce)?;
+        let ciphertext = base64::Engine::decode(&base64::engine::general_purpose::STANDARD, ciphertext)?;
+
+        // Decrypt inside locked memory; the plaintext never touches the heap
+        self.cipher
+            .decrypt_to_secure_with_aad(unlock_key.as_slice(), &nonce, &ciphertext, &self.associated_data()?)
+    }
+
+    /// The fields a version 3 container authenticates along with its key
+    /// (and its decoy key)
+    ///
+    /// The version, cipher, KDF and its parameters, and the public key are
+    /// bound to the ciphertext, so editing any of them (a weaker KDF, a
+    /// different public key, an older version) makes decryption fail rather
+    /// than be silently accepted. Older versions authenticate nothing.
+    pub(crate) fn associated_data(&self) -> Result<Vec<u8>, SignerError> {
+        if self.version < CONTAINER_VERSION {
+            return Ok(Vec::new());
+        }
+        let public_key = match &self.public_key {
+            Some(public_key) => bs58::decode(public_key).into_vec()?,
+            None => Vec::new(),
+        };
+        let mut aad = CONTAINER_AAD_DOMAIN.to_vec();
+        self.write_header(&mut aad);
+        aad.extend_from_slice(&(public_key.len() as u32).to_le_bytes());
+        aad.extend_from_slice(&public_key);
+        Ok(aad)
+    }
+
+    pub(crate) fn check_version(&self) -> Result<(), SignerError> {
+        match self.version {
+            CONTAINER_VERSION_V1 if self.cipher != Cipher::Aes256Gcm => Err(SignerError::ContainerError(
+                "version 1 containers only support AES-256-GCM".to_string(),
+            )),
+            CONTAINER_VERSION_V1 | CONTAINER_VERSION_V2 if self.keyfile => Err(SignerError::ContainerError(
+                "keyfiles need a version 3 container".to_string(),
+            )),
+            CONTAINER_VERSION_V1 | CONTAINER_VERSION_V2 if self.validity() != Validity::default() => Err(
+                SignerError::ContainerError("validity windows need a version 3 container".to_string()),
+            ),
+            CONTAINER_VERSION_V1 | CONTAINER_VERSION_V2 | CONTAINER_VERSION => Ok(()),
+            v => Err(SignerError::ContainerError(format!(
+                "unsupported container version {}",
+                v
+            ))),
+        }
+    }
 }
 
 /// Result of a signing operation
@@ -159,6 +788,33 @@ pub struct SigningResult {
     pub signed_transaction: Option<String>,
     /// The public key that signed (base58 encoded)
     pub public_key: String,
+    /// Number of this signature, from a session's
+    /// [`SignatureCounter`](crate::counter::SignatureCounter)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub counter: Option<u64>,
+}
+
+impl SigningResult {
+    /// Re-encode the fields, which are produced in the default encodings
+    pub fn with_encoding(self, encoding: &OutputEncoding) -> Result<Self, SignerError> {
+        let defaults = OutputEncoding::DEFAULT;
+        let convert = |value: String, from: Encoding, to: Encoding| -> Result<String, SignerError> {
+            if from == to {
+                return Ok(value);
+            }
+            Ok(to.encode(&from.decode(&value)?))
+        };
+
+        Ok(Self {
+            signature: convert(self.signature, defaults.signature, encoding.signature)?,
+            signed_transaction: self
+                .signed_transaction
+                .map(|tx| convert(tx, defaults.transaction, encoding.transaction))
+                .transpose()?,
+            public_key: convert(self.public_key, defaults.public_key, encoding.public_key)?,
+            counter: self.counter,
+        })
+    }
 }
 
 /// Decrypt a key container and sign a transaction
@@ -189,55 +845,116 @@ pub fn decrypt_and_sign(
     // Parse the container
     let container = EncryptedKeyContainer::from_json(container_json)?;
 
-    // Decode base64 fields
-    let salt = base64::Engine::decode(&base64::engine::general_purpose::STANDARD, &container.salt)?;
-    let nonce = base64::Engine::decode(&base64::engine::general_purpose::STANDARD, &container.nonce)?;
-    let ciphertext = base64::Engine::decode(&base64::engine::general_purpose::STANDARD, &container.ciphertext)?;
-
-    // Derive decryption key
-    let mut derived_key = derive_key(passphrase.as_bytes(), &salt)?;
-
-    // Decrypt the private key into secure buffer
-    let cipher = Aes256Gcm::new_from_slice(derived_key.as_slice())
-        .map_err(|e| SignerError::KeyDerivationFailed(e.to_string()))?;
-
-    let plaintext = cipher
-        .decrypt(Nonce::from_slice(&nonce), ciphertext.as_slice())
-        .map_err(|_| SignerError::DecryptionFailed)?;
-
-    // Immediately move to secure buffer and zeroize intermediate
-    let mut secure_key = SecureBuffer::from_slice_with_mode(&plaintext, get_locking_mode())?;
+    // Decrypt, sign, and zeroize inside the container backend
+    ContainerBackend::new(&container, passphrase).sign_solana(transaction_bytes)
+}
 
-    // Zeroize the derived key and plaintext copy
-    derived_key.zeroize();
-    // Note: plaintext is owned by cipher, can't zeroize it directly
-    // But we've copied to secure buffer immediately
+/// Decrypt a key container and sign arbitrary bytes (an off-chain message,
+/// a Sign-In with Solana message) without the transaction checks of
+/// [`decrypt_and_sign`]
+pub fn decrypt_and_sign_message(
+    container_json: &str,
+    passphrase: &str,
+    message: &[u8],
+) -> Result<SigningResult, SignerError> {
+    let container = EncryptedKeyContainer::from_json(container_json)?;
+    ContainerBackend::new(&container, passphrase).sign_solana_message(message)
+}
 
-    // Create signing key from secure buffer
-    // MEMORY LIFECYCLE: The signing key is created from our secure buffer
-    // and will be zeroized when dropped (ed25519-dalek supports zeroize)
-    let result = sign_with_secure_key(&mut secure_key, transaction_bytes);
+/// Check that `message` is a Solana transaction message for `signer`
+///
+/// The header must be well-formed, `signer` must be one of the required
+/// signers, and the recent blockhash must be present. Instructions are not
+/// checked here; previews parse them in full.
+pub fn check_solana_message(message: &[u8], signer: &[u8; 32]) -> Result<(), SignerError> {
+    let required_signers = solana_required_signers(message)
+        .map_err(|e| SignerError::InvalidTransaction(format!("not a Solana transaction: {}", e)))?;
+    if !required_signers.chunks_exact(32).any(|key| key == signer) {
+        return Err(SignerError::InvalidTransaction(format!(
+            "{} is not a required signer of this transaction",
+            bs58::encode(signer).into_string()
+        )));
+    }
+    Ok(())
+}
 
-    // Explicit zeroization (also happens on drop)
-    secure_key.zeroize();
+/// Read a message up to its recent blockhash, returning the required
+/// signers' keys
+fn solana_required_signers(message: &[u8]) -> Result<&[u8], ParseError> {
+    let mut reader = ByteReader::new(message, "Solana message");
+    if let Some(prefix) = reader.peek(0).filter(|prefix| prefix & 0x80 != 0) {
+        if prefix != 0x80 {
+            return Err(reader.invalid(format!("unsupported message version {}", prefix & 0x7f)));
+        }
+        reader.u8()?;
+    }
+    let [required, readonly_signed, readonly_unsigned] = reader.array::<3>()?.map(usize::from);
+    if required == 0 || readonly_signed >= required {
+        return Err(reader.invalid(format!(
+            "header requires {} signatures with {} read-only",
+            required, readonly_signed
+        )));
+    }
+    let num_keys = reader.compact_u16()?;
+    if required + readonly_unsigned > num_keys {
+        return Err(reader.invalid(format!(
+            "header describes {} accounts but the message lists {}",
+            required + readonly_unsigned,
+            num_keys
+        )));
+    }
+    let keys = reader.take(num_keys * 32)?;
+    reader.take(32).map_err(|_| reader.invalid("missing recent blockhash"))?;
+    Ok(&keys[..required * 32])
+}
 
-    result
+/// A minimal legacy transaction message (no instructions) with the key
+/// from `seed` as its only signer
+#[cfg(test)]
+pub(crate) fn test_transaction(seed: &[u8; 32]) -> Vec<u8> {
+    let mut message = vec![1, 0, 0, 1];
+    message.extend_from_slice(SigningKey::from_bytes(seed).verifying_key().as_bytes());
+    message.extend_from_slice(&[7u8; 32]);
+    message.push(0);
+    message
 }
 
-/// Sign a transaction with a key in a secure buffer
+/// Sign a transaction with a key in a secure buffer, after
+/// [`check_solana_message`]
 ///
 /// # Memory Lifecycle
-/// The secure buffer is borrowed mutably and its contents are used
+/// The secure buffer is borrowed and its contents are used
 /// to create a signing key. The signing key itself supports zeroization.
-fn sign_with_secure_key(
-    secure_key: &mut SecureBuffer,
+pub(crate) fn sign_with_secure_key(
+    secure_key: &SecureBuffer,
     transaction_bytes: &[u8],
+) -> Result<SigningResult, SignerError> {
+    sign_ed25519(secure_key, transaction_bytes, true)
+}
+
+/// Sign arbitrary bytes with a key in a secure buffer, with no
+/// transaction checks and no signed transaction in the result
+pub(crate) fn sign_message_with_secure_key(
+    secure_key: &SecureBuffer,
+    message: &[u8],
+) -> Result<SigningResult, SignerError> {
+    sign_ed25519(secure_key, message, false)
+}
+
+fn sign_ed25519(
+    secure_key: &SecureBuffer,
+    transaction_bytes: &[u8],
+    transaction: bool,
 ) -> Result<SigningResult, SignerError> {
     // Validate key size
     if secure_key.len() != ED25519_SEED_SIZE {
         return Err(SignerError::InvalidKeyFormat(secure_key.len()));
     }
 
+    // Ed25519 nonces are deterministic, but a broken RNG means the salts
+    // and nonces this process generates are suspect: refuse to sign
+    health_check()?;
+
     // Create signing key - ed25519-dalek's SigningKey implements Zeroize
     let signing_key = SigningKey::from_bytes(
         secure_key.as_slice().try_into().map_err(|_| {
@@ -248,37 +965,48 @@ fn sign_with_secure_key(
     // Get the public key
     let public_key = signing_key.verifying_key();
     let public_key_b58 = bs58::encode(public_key.as_bytes()).into_string();
+    if transaction {
+        check_solana_message(transaction_bytes, public_key.as_bytes())?;
+    }
 
     // Sign the transaction message
     let signature: Signature = signing_key.sign(transaction_bytes);
+    if verify_after_signing() && public_key.verify_strict(transaction_bytes, &signature).is_err() {
+        return Err(faulty_signature("Ed25519"));
+    }
 
     // For Solana transactions, we need to embed the signature
     // The transaction format is: signatures_count + signatures + message
     // We'll return just the signature; the caller can construct the full tx
     let signature_b58 = bs58::encode(signature.to_bytes()).into_string();
 
-    // Build signed transaction if this looks like a Solana transaction message
-    let signed_transaction = if transaction_bytes.len() >= 3 {
-        // Simple signed transaction: 1 signature count + signature + message
-        let mut signed_tx = Vec::with_capacity(1 + 64 + transaction_bytes.len());
-        signed_tx.push(1u8); // One signature
-        signed_tx.extend_from_slice(&signature.to_bytes());
-        signed_tx.extend_from_slice(transaction_bytes);
-        Some(base64::Engine::encode(
-            &base64::engine::general_purpose::STANDARD,
-            &signed_tx,
-        ))
-    } else {
-        None
-    };
-
     Ok(SigningResult {
         signature: signature_b58,
-        signed_transaction,
+        signed_transaction: transaction
+            .then(|| assemble_signed_transaction(&signature.to_bytes(), transaction_bytes))
+            .flatten(),
         public_key: public_key_b58,
+        counter: None,
     })
 }
 
+/// Build a signed transaction if this looks like a Solana transaction message
+pub(crate) fn assemble_signed_transaction(signature: &[u8; 64], transaction_bytes: &[u8]) -> Option<String> {
+    if transaction_bytes.len() < 3 {
+        return None;
+    }
+
+    // Simple signed transaction: 1 signature count + signature + message
+    let mut signed_tx = Vec::with_capacity(1 + 64 + transaction_bytes.len());
+    signed_tx.push(1u8); // One signature
+    signed_tx.extend_from_slice(signature);
+    signed_tx.extend_from_slice(transaction_bytes);
+    Some(base64::Engine::encode(
+        &base64::engine::general_purpose::STANDARD,
+        &signed_tx,
+    ))
+}
+
 /// Sign a transaction with a raw (already decrypted) private key
 ///
 /// # Security Warning
@@ -296,7 +1024,7 @@ pub fn sign_transaction(
     let mut secure_key = SecureBuffer::from_slice_with_mode(private_key, get_locking_mode())?;
 
     // Sign
-    let result = sign_with_secure_key(&mut secure_key, transaction_bytes);
+    let result = sign_with_secure_key(&secure_key, transaction_bytes);
 
     // Zeroize
     secure_key.zeroize();
@@ -315,6 +1043,16 @@ pub fn create_encrypted_key_container(
     container.to_json()
 }
 
+/// Create an encrypted key container using the given cipher
+pub fn create_encrypted_key_container_with_cipher(
+    private_key: &[u8],
+    passphrase: &str,
+    cipher: Cipher,
+) -> Result<String, SignerError> {
+    let container = EncryptedKeyContainer::encrypt_with_cipher(private_key, passphrase, cipher)?;
+    container.to_json()
+}
+
 // ════════════════════════════════════════════════════════════
 //  EVM (secp256k1) signing support
 // ════════════════════════════════════════════════════════════
@@ -328,12 +1066,31 @@ pub struct EVMSigningResult {
     pub address: String,
     /// Recovery ID (v value: 27 or 28)
     pub v: u8,
+    /// Number of this signature, from a session's
+    /// [`SignatureCounter`](crate::counter::SignatureCounter)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub counter: Option<u64>,
+}
+
+impl EVMSigningResult {
+    /// Re-encode the signature, which is produced as 0x-prefixed hex
+    ///
+    /// The address stays in its checksummed form.
+    pub fn with_encoding(self, signature: Encoding) -> Result<Self, SignerError> {
+        if signature == Encoding::Hex {
+            return Ok(self);
+        }
+        Ok(Self {
+            signature: signature.encode(&Encoding::Hex.decode(&self.signature)?),
+            ..self
+        })
+    }
 }
 
 /// Derive an EVM address from a secp256k1 public key
 ///
 /// EVM address = last 20 bytes of keccak256(uncompressed_pubkey[1..])
-fn evm_address_from_pubkey(verifying_key: &K256VerifyingKey) -> String {
+pub(crate) fn evm_address_from_pubkey(verifying_key: &K256VerifyingKey) -> String {
     let uncompressed = verifying_key.to_encoded_point(false);
     let pubkey_bytes = &uncompressed.as_bytes()[1..]; // skip 0x04 prefix
     let hash = Keccak256::digest(pubkey_bytes);
@@ -341,17 +1098,44 @@ fn evm_address_from_pubkey(verifying_key: &K256VerifyingKey) -> String {
     format!("0x{}", hex::encode(addr_bytes))
 }
 
+/// How the ECDSA nonce `k` is derived
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NonceMode {
+    /// RFC 6979: `k` depends only on the key and the hash
+    #[default]
+    Deterministic,
+    /// RFC 6979 with 32 fresh bytes from the entropy source as additional
+    /// data (section 3.6)
+    Hedged,
+    /// RFC 6979 with caller-provided additional data
+    HedgedWith([u8; 32]),
+}
+
 /// Sign an EVM transaction hash with a key in a secure buffer
 ///
 /// For EVM, we sign a 32-byte hash (the tx hash), not the raw transaction bytes.
 /// The caller is responsible for hashing the transaction with keccak256 first.
-fn sign_evm_with_secure_key(
-    secure_key: &mut SecureBuffer,
+pub(crate) fn sign_evm_with_secure_key(
+    secure_key: &SecureBuffer,
     message_hash: &[u8],
+) -> Result<EVMSigningResult, SignerError> {
+    sign_evm_with_nonce_mode(secure_key, message_hash, NonceMode::Deterministic)
+}
+
+/// [`sign_evm_with_secure_key`] with a choice of nonce derivation
+///
+/// Hedged nonces still fall back to RFC 6979, so a bad RNG cannot leak the
+/// key, while the extra entropy stops a fault injected into one of two
+/// signatures of the same hash from revealing it.
+pub(crate) fn sign_evm_with_nonce_mode(
+    secure_key: &SecureBuffer,
+    message_hash: &[u8],
+    nonce: NonceMode,
 ) -> Result<EVMSigningResult, SignerError> {
     if secure_key.len() != 32 {
         return Err(SignerError::InvalidKeyFormat(secure_key.len()));
     }
+    health_check()?;
 
     // Create secp256k1 signing key
     let signing_key = K256SigningKey::from_bytes(
@@ -361,10 +1145,38 @@ fn sign_evm_with_secure_key(
     let verifying_key = signing_key.verifying_key();
     let address = evm_address_from_pubkey(verifying_key);
 
+    let mut extra_entropy = Zeroizing::new([0u8; 32]);
+    let additional_data: &[u8] = match nonce {
+        NonceMode::Deterministic => &[],
+        NonceMode::Hedged => {
+            fill_random(extra_entropy.as_mut())?;
+            extra_entropy.as_ref()
+        }
+        NonceMode::HedgedWith(entropy) => {
+            *extra_entropy = entropy;
+            extra_entropy.as_ref()
+        }
+    };
+
     // Sign the message hash (recoverable signature)
-    let (signature, recovery_id) = signing_key
-        .sign_prehash_recoverable(message_hash)
-        .map_err(|e| SignerError::SigningFailed(format!("ECDSA signing failed: {}", e)))?;
+    let signed = hazmat::bits2field::<Secp256k1>(message_hash).and_then(|z| {
+        signing_key
+            .as_nonzero_scalar()
+            .try_sign_prehashed_rfc6979::<sha2::Sha256>(&z, additional_data)
+    });
+    let (signature, recovery_id) = match signed {
+        Ok((signature, Some(recovery_id))) => (signature, recovery_id),
+        Ok((_, None)) => return Err(SignerError::SigningFailed("ECDSA signing failed: no recovery ID".to_string())),
+        Err(e) => return Err(SignerError::SigningFailed(format!("ECDSA signing failed: {}", e))),
+    };
+
+    // Recovery also verifies the signature, and checks `v` with it
+    if verify_after_signing() {
+        let recovered = K256VerifyingKey::recover_from_prehash(message_hash, &signature, recovery_id);
+        if recovered.ok().as_ref() != Some(verifying_key) {
+            return Err(faulty_signature("ECDSA"));
+        }
+    }
 
     // Build 65-byte signature: r (32) || s (32) || v (1)
     let r = signature.r().to_bytes();
@@ -380,6 +1192,7 @@ fn sign_evm_with_secure_key(
         signature: format!("0x{}", hex::encode(&sig_bytes)),
         address,
         v,
+        counter: None,
     })
 }
 
@@ -405,29 +1218,18 @@ pub fn decrypt_and_sign_evm(
     // Parse the container
     let container = EncryptedKeyContainer::from_json(container_json)?;
 
-    // Decode base64 fields
-    let salt = base64::Engine::decode(&base64::engine::general_purpose::STANDARD, &container.salt)?;
-    let nonce = base64::Engine::decode(&base64::engine::general_purpose::STANDARD, &container.nonce)?;
-    let ciphertext = base64::Engine::decode(&base64::engine::general_purpose::STANDARD, &container.ciphertext)?;
-
-    // Derive decryption key
-    let mut derived_key = derive_key(passphrase.as_bytes(), &salt)?;
-
-    // Decrypt the private key into secure buffer
-    let cipher = Aes256Gcm::new_from_slice(derived_key.as_slice())
-        .map_err(|e| SignerError::KeyDerivationFailed(e.to_string()))?;
-
-    let plaintext = cipher
-        .decrypt(Nonce::from_slice(&nonce), ciphertext.as_slice())
-        .map_err(|_| SignerError::DecryptionFailed)?;
-
-    let mut secure_key = SecureBuffer::from_slice_with_mode(&plaintext, get_locking_mode())?;
-    derived_key.zeroize();
-
-    let result = sign_evm_with_secure_key(&mut secure_key, message_hash);
-    secure_key.zeroize();
+    ContainerBackend::new(&container, passphrase).sign_evm_hash(message_hash)
+}
 
-    result
+/// [`decrypt_and_sign_evm`] with a choice of nonce derivation
+pub fn decrypt_and_sign_evm_with_nonce(
+    container_json: &str,
+    passphrase: &str,
+    message_hash: &[u8],
+    nonce: NonceMode,
+) -> Result<EVMSigningResult, SignerError> {
+    let container = EncryptedKeyContainer::from_json(container_json)?;
+    ContainerBackend::new(&container, passphrase).sign_evm_hash_with_nonce(message_hash, nonce)
 }
 
 /// Sign an EVM message hash with a raw private key
@@ -439,39 +1241,76 @@ pub fn sign_evm_transaction(
     message_hash: &[u8],
 ) -> Result<EVMSigningResult, SignerError> {
     let mut secure_key = SecureBuffer::from_slice_with_mode(private_key, get_locking_mode())?;
-    let result = sign_evm_with_secure_key(&mut secure_key, message_hash);
+    let result = sign_evm_with_secure_key(&secure_key, message_hash);
     secure_key.zeroize();
     result
 }
 
-/// Derive an encryption key from a passphrase using Argon2id
+// ════════════════════════════════════════════════════════════
+//  BIP-340 Schnorr (secp256k1) signing support
+// ════════════════════════════════════════════════════════════
+
+/// Result of a BIP-340 Schnorr signing operation
+#[derive(Serialize, Deserialize)]
+pub struct SchnorrSigningResult {
+    /// The signature (hex-encoded, 64 bytes: R.x || s)
+    pub signature: String,
+    /// The x-only public key that signed (hex-encoded, 32 bytes)
+    pub public_key: String,
+    /// Number of this signature, from a session's
+    /// [`SignatureCounter`](crate::counter::SignatureCounter)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub counter: Option<u64>,
+}
+
+/// Sign a message with BIP-340 Schnorr using a key in a secure buffer
 ///
-/// # Memory Lifecycle
-/// Returns a SecureBuffer containing the derived key.
-fn derive_key(passphrase: &[u8], salt: &[u8]) -> Result<SecureBuffer, SignerError> {
-    let params = Params::new(
-        ARGON2_MEMORY_COST,
-        ARGON2_TIME_COST,
-        ARGON2_PARALLELISM,
-        Some(KEY_SIZE),
-    )
-    .map_err(|e| SignerError::KeyDerivationFailed(e.to_string()))?;
+/// Uses the same secp256k1 scalar as [`sign_evm_with_secure_key`], so one
+/// key serves both EVM and Taproot. Fresh auxiliary randomness is mixed
+/// into every nonce, as BIP-340 recommends.
+pub(crate) fn sign_schnorr_with_secure_key(
+    secure_key: &SecureBuffer,
+    message: &[u8],
+) -> Result<SchnorrSigningResult, SignerError> {
+    if secure_key.len() != 32 {
+        return Err(SignerError::InvalidKeyFormat(secure_key.len()));
+    }
 
-    let argon2 = Argon2::new(argon2::Algorithm::Argon2id, Version::V0x13, params);
+    let signing_key = k256::schnorr::SigningKey::from_bytes(secure_key.as_slice())
+        .map_err(|e| SignerError::SigningFailed(format!("Invalid secp256k1 key: {}", e)))?;
 
-    // Use env-based locking mode for derived keys
-    let mut key = SecureBuffer::with_mode(KEY_SIZE, get_locking_mode())?;
+    let mut aux_rand = [0u8; 32];
+    fill_random(&mut aux_rand)?;
+    let signature = signing_key
+        .sign_raw(message, &aux_rand)
+        .map_err(|e| SignerError::SigningFailed(format!("Schnorr signing failed: {}", e)))?;
+    if verify_after_signing() && signing_key.verifying_key().verify_raw(message, &signature).is_err() {
+        return Err(faulty_signature("Schnorr"));
+    }
 
-    argon2
-        .hash_password_into(passphrase, salt, key.as_mut_slice())
-        .map_err(|e| SignerError::KeyDerivationFailed(e.to_string()))?;
+    Ok(SchnorrSigningResult {
+        signature: hex::encode(signature.to_bytes()),
+        public_key: hex::encode(signing_key.verifying_key().to_bytes()),
+        counter: None,
+    })
+}
 
-    Ok(key)
+/// Sign a message with BIP-340 Schnorr using a raw private key
+///
+/// # Security Warning
+/// Prefer using a [`SignerBackend`] over an encrypted container.
+pub fn sign_schnorr(private_key: &[u8], message: &[u8]) -> Result<SchnorrSigningResult, SignerError> {
+    let mut secure_key = SecureBuffer::from_slice_with_mode(private_key, get_locking_mode())?;
+    let result = sign_schnorr_with_secure_key(&secure_key, message);
+    secure_key.zeroize();
+    result
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use rand::rngs::OsRng;
+    use rand::RngCore;
 
     /// Helper to enable permissive mode for tests (mlock may not be available)
     fn enable_permissive_mode() {
@@ -492,10 +1331,10 @@ mod tests {
         let json = container.to_json().unwrap();
 
         // Create a test message
-        let message = b"test transaction message";
+        let message = test_transaction(&seed);
 
         // Decrypt and sign
-        let result = decrypt_and_sign(&json, passphrase, message).unwrap();
+        let result = decrypt_and_sign(&json, passphrase, &message).unwrap();
 
         // Verify the signature
         let signing_key = SigningKey::from_bytes(&seed);
@@ -521,6 +1360,205 @@ mod tests {
         assert!(matches!(result, Err(SignerError::DecryptionFailed)));
     }
 
+    #[test]
+    fn test_xchacha_container_roundtrip() {
+        enable_permissive_mode();
+
+        let mut seed = [0u8; 32];
+        OsRng.fill_bytes(&mut seed);
+
+        let container =
+            EncryptedKeyContainer::encrypt_with_cipher(&seed, "pass", Cipher::XChaCha20Poly1305)
+                .unwrap();
+        assert_eq!(container.version, CONTAINER_VERSION);
+        assert_eq!(container.cipher, Cipher::XChaCha20Poly1305);
+
+        let json = container.to_json().unwrap();
+        assert!(json.contains("\"cipher\":\"xchacha20-poly1305\""));
+
+        let result = decrypt_and_sign(&json, "pass", &test_transaction(&seed)).unwrap();
+        let expected = SigningKey::from_bytes(&seed).verifying_key();
+        assert_eq!(result.public_key, bs58::encode(expected.as_bytes()).into_string());
+    }
+
+    #[test]
+    fn test_gcm_siv_container_roundtrip() {
+        enable_permissive_mode();
+
+        // RFC 8452 appendix C.2, first AEAD_AES_256_GCM_SIV vector
+        let mut key = [0u8; 32];
+        key[0] = 1;
+        let mut nonce = [0u8; NONCE_SIZE];
+        nonce[0] = 3;
+        let tag = Cipher::Aes256GcmSiv.encrypt(&key, &nonce, b"").unwrap();
+        assert_eq!(hex::encode(tag), "07f5f4169bbf55a8400cd47ea6fd400f");
+
+        let container = EncryptedKeyContainer::encrypt_with_cipher(&[8u8; 32], "pass", Cipher::Aes256GcmSiv).unwrap();
+        let json = container.to_json().unwrap();
+        assert!(json.contains("\"cipher\":\"aes-256-gcm-siv\""));
+        assert_eq!("AES-256-GCM-SIV".parse::<Cipher>().unwrap(), Cipher::Aes256GcmSiv);
+
+        let result = decrypt_and_sign(&json, "pass", &test_transaction(&[8u8; 32])).unwrap();
+        let expected = SigningKey::from_bytes(&[8u8; 32]).verifying_key();
+        assert_eq!(result.public_key, bs58::encode(expected.as_bytes()).into_string());
+        let binary = EncryptedKeyContainer::from_bytes(&container.to_bytes().unwrap()).unwrap();
+        assert_eq!(binary.cipher, Cipher::Aes256GcmSiv);
+    }
+
+    #[test]
+    fn test_rekey_keeps_key_and_cipher() {
+        enable_permissive_mode();
+
+        let mut seed = [0u8; 32];
+        OsRng.fill_bytes(&mut seed);
+
+        let container =
+            EncryptedKeyContainer::encrypt_with_cipher(&seed, "old", Cipher::XChaCha20Poly1305).unwrap();
+        assert!(matches!(container.rekey("wrong", "new"), Err(SignerError::DecryptionFailed)));
+
+        let rekeyed = container.rekey("old", "new").unwrap();
+        assert_eq!(rekeyed.cipher, Cipher::XChaCha20Poly1305);
+        assert_eq!(rekeyed.public_key, container.public_key);
+        assert_ne!(rekeyed.container_id().unwrap(), container.container_id().unwrap());
+
+        let json = rekeyed.to_json().unwrap();
+        assert!(decrypt_and_sign(&json, "old", b"message").is_err());
+        assert!(decrypt_and_sign(&json, "new", &test_transaction(&seed)).is_ok());
+    }
+
+    #[test]
+    fn test_v1_container_still_decrypts() {
+        enable_permissive_mode();
+
+        let mut seed = [0u8; 32];
+        OsRng.fill_bytes(&mut seed);
+
+        // A v1 container is an AES-256-GCM container without the cipher field
+        // or associated data
+        let mut container = EncryptedKeyContainer::encrypt(&seed, "pass").unwrap();
+        let salt = base64::Engine::decode(&base64::engine::general_purpose::STANDARD, &container.salt).unwrap();
+        let nonce = base64::Engine::decode(&base64::engine::general_purpose::STANDARD, &container.nonce).unwrap();
+        let key = container.kdf.derive(b"pass", &salt).unwrap();
+        let ciphertext = Cipher::Aes256Gcm.encrypt(key.as_slice(), &nonce, &seed).unwrap();
+        container.ciphertext = base64::Engine::encode(&base64::engine::general_purpose::STANDARD, ciphertext);
+        let mut value = serde_json::to_value(&container).unwrap();
+        value["version"] = serde_json::json!(1);
+        value.as_object_mut().unwrap().remove("cipher");
+        let json = value.to_string();
+
+        assert!(decrypt_and_sign(&json, "pass", &test_transaction(&seed)).is_ok());
+    }
+
+    #[test]
+    fn test_custom_kdf_params_roundtrip() {
+        enable_permissive_mode();
+
+        let mut seed = [0u8; 32];
+        OsRng.fill_bytes(&mut seed);
+
+        let params = KdfParams::new(32768, 2, 2).unwrap();
+        let container =
+            EncryptedKeyContainer::encrypt_with_kdf(&seed, "pass", Cipher::Aes256Gcm, params)
+                .unwrap();
+        let json = container.to_json().unwrap();
+        assert_eq!(EncryptedKeyContainer::from_json(&json).unwrap().kdf, Kdf::Argon2id(params));
+
+        assert!(decrypt_and_sign(&json, "pass", &test_transaction(&seed)).is_ok());
+    }
+
+    #[test]
+    fn test_imported_scrypt_and_pbkdf2_containers_decrypt() {
+        use crate::kdf::{Pbkdf2Params, ScryptParams};
+
+        enable_permissive_mode();
+
+        let mut seed = [0u8; 32];
+        OsRng.fill_bytes(&mut seed);
+        let expected = bs58::encode(SigningKey::from_bytes(&seed).verifying_key().as_bytes())
+            .into_string();
+
+        for kdf in [
+            Kdf::Scrypt(ScryptParams { log_n: 10, r: 8, p: 1 }),
+            Kdf::Pbkdf2Sha256(Pbkdf2Params { iterations: 1000 }),
+        ] {
+            // Build the container the way an importer would
+            let salt = [9u8; SALT_SIZE];
+            let nonce = [3u8; NONCE_SIZE];
+            let key = kdf.derive(b"interop", &salt).unwrap();
+            let ciphertext = Cipher::Aes256Gcm.encrypt(key.as_slice(), &nonce, &seed).unwrap();
+
+            let container = EncryptedKeyContainer {
+                version: CONTAINER_VERSION_V2,
+                cipher: Cipher::Aes256Gcm,
+                kdf,
+                salt: base64::Engine::encode(&base64::engine::general_purpose::STANDARD, salt),
+                nonce: base64::Engine::encode(&base64::engine::general_purpose::STANDARD, nonce),
+                ciphertext: base64::Engine::encode(&base64::engine::general_purpose::STANDARD, ciphertext),
+                public_key: None,
+                keyfile: false,
+                not_before: None,
+                not_after: None,
+                duress: None,
+            };
+            let json = container.to_json().unwrap();
+
+            let result = decrypt_and_sign(&json, "interop", &test_transaction(&seed)).unwrap();
+            assert_eq!(result.public_key, expected);
+        }
+    }
+
+    #[test]
+    fn test_weak_kdf_params_rejected() {
+        enable_permissive_mode();
+
+        let weak = KdfParams {
+            memory_cost: 1024,
+            time_cost: 1,
+            parallelism: 1,
+        };
+        let result =
+            EncryptedKeyContainer::encrypt_with_kdf(&[1u8; 32], "pass", Cipher::Aes256Gcm, weak);
+        assert!(matches!(result, Err(SignerError::KeyDerivationFailed(_))));
+    }
+
+    #[test]
+    fn test_unsupported_version_rejected() {
+        enable_permissive_mode();
+
+        let mut seed = [0u8; 32];
+        OsRng.fill_bytes(&mut seed);
+
+        let mut container = EncryptedKeyContainer::encrypt(&seed, "pass").unwrap();
+        container.version = 99;
+        let json = container.to_json().unwrap();
+
+        let result = decrypt_and_sign(&json, "pass", b"message");
+        assert!(matches!(result, Err(SignerError::ContainerError(_))));
+    }
+
+    #[test]
+    fn test_metadata_is_authenticated() {
+        enable_permissive_mode();
+
+        let container =
+            EncryptedKeyContainer::encrypt_with_kdf(&[5u8; 32], "pass", Cipher::Aes256Gcm, KdfParams::MINIMUM).unwrap();
+        assert_eq!(container.version, CONTAINER_VERSION);
+        assert!(container.decrypt_key("pass").is_ok());
+
+        // Relabeled public key, downgraded version, or edited KDF parameters
+        let mut relabeled = container.clone();
+        relabeled.public_key = Some(bs58::encode([6u8; 32]).into_string());
+        let mut downgraded = container.clone();
+        downgraded.version = CONTAINER_VERSION_V2;
+        let mut weakened = container.clone();
+        if let Kdf::Argon2id(params) = &mut weakened.kdf {
+            params.parallelism += 1;
+        }
+        for tampered in [relabeled, downgraded, weakened] {
+            assert!(matches!(tampered.decrypt_key("pass"), Err(SignerError::DecryptionFailed)));
+        }
+    }
+
     #[test]
     fn test_signature_verification() {
         enable_permissive_mode();
@@ -529,16 +1567,72 @@ mod tests {
 
         let mut seed = [0u8; 32];
         OsRng.fill_bytes(&mut seed);
-        let message = b"Hello, Solana!";
+        let message = test_transaction(&seed);
 
-        let result = sign_transaction(&seed, message).unwrap();
+        let result = sign_transaction(&seed, &message).unwrap();
 
         // Verify signature
         let signing_key = SigningKey::from_bytes(&seed);
         let signature_bytes = bs58::decode(&result.signature).into_vec().unwrap();
         let signature = Signature::from_slice(&signature_bytes).unwrap();
 
-        assert!(signing_key.verifying_key().verify(message, &signature).is_ok());
+        assert!(signing_key.verifying_key().verify(&message, &signature).is_ok());
+    }
+
+    #[test]
+    fn test_solana_message_checks() {
+        enable_permissive_mode();
+
+        let seed = [3u8; 32];
+        let signer = *SigningKey::from_bytes(&seed).verifying_key().as_bytes();
+        let message = test_transaction(&seed);
+        check_solana_message(&message, &signer).unwrap();
+        let mut v0 = vec![0x80];
+        v0.extend_from_slice(&message);
+        check_solana_message(&v0, &signer).unwrap();
+
+        let rejected = |message: &[u8]| matches!(check_solana_message(message, &signer), Err(SignerError::InvalidTransaction(_)));
+        assert!(rejected(b"arbitrary bytes"));
+        assert!(rejected(&[[0x81].as_slice(), &message].concat()));
+        assert!(rejected(&[[0, 0, 0].as_slice(), &message[3..]].concat()));
+        assert!(rejected(&[[1, 1, 0].as_slice(), &message[3..]].concat()));
+        assert!(rejected(&[[1, 0, 1].as_slice(), &message[3..]].concat()));
+        assert!(rejected(&message[..4 + 32 + 16]));
+        assert!(rejected(&test_transaction(&[4u8; 32])));
+
+        // Only transactions are checked
+        assert!(decrypt_and_sign_message(
+            &EncryptedKeyContainer::encrypt_with_kdf(&seed, "pass", Cipher::Aes256Gcm, KdfParams::MINIMUM)
+                .unwrap()
+                .to_json()
+                .unwrap(),
+            "pass",
+            b"arbitrary bytes",
+        )
+        .unwrap()
+        .signed_transaction
+        .is_none());
+        assert!(sign_transaction(&seed, b"arbitrary bytes").is_err());
+    }
+
+    #[test]
+    fn test_output_encodings() {
+        enable_permissive_mode();
+
+        let result = sign_transaction(&[9u8; 32], &test_transaction(&[9u8; 32])).unwrap();
+        let default = result.with_encoding(&OutputEncoding::default()).unwrap();
+
+        let hex = sign_transaction(&[9u8; 32], &test_transaction(&[9u8; 32]))
+            .unwrap()
+            .with_encoding(&OutputEncoding::all(Encoding::Hex))
+            .unwrap();
+        assert_eq!(hex.signature, hex::encode(bs58::decode(&default.signature).into_vec().unwrap()));
+        assert_eq!(hex.public_key.len(), 64);
+
+        let evm = sign_evm_transaction(&[9u8; 32], &[1u8; 32]).unwrap();
+        let raw = hex::decode(&evm.signature[2..]).unwrap();
+        let b64 = evm.with_encoding(Encoding::Base64).unwrap();
+        assert_eq!(Encoding::Base64.decode(&b64.signature).unwrap(), raw);
     }
 
     // ── EVM (secp256k1) tests ──────────────────────────────
@@ -617,4 +1711,72 @@ mod tests {
         let result = decrypt_and_sign_evm(&json, "pass", &bad_hash);
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_post_sign_verification() {
+        // Explicit settings win; unset, only hardened memory turns it on
+        assert!(verify_signatures_setting(Some("1"), LockingMode::Strict));
+        assert!(!verify_signatures_setting(Some("false"), LockingMode::Hardened));
+        assert!(verify_signatures_setting(None, LockingMode::Hardened));
+        assert!(!verify_signatures_setting(Some("maybe"), LockingMode::Permissive));
+
+        // Every algorithm passes its own check when it is on
+        enable_permissive_mode();
+        std::env::set_var(ENV_VERIFY_SIGNATURES, "1");
+        let key = SecureBuffer::from_slice(&[9u8; 32]).unwrap();
+        sign_with_secure_key(&key, &test_transaction(&[9u8; 32])).unwrap();
+        sign_evm_with_nonce_mode(&key, &[1u8; 32], NonceMode::Hedged).unwrap();
+        sign_schnorr_with_secure_key(&key, &[2u8; 32]).unwrap();
+        crate::eddsa::sign_with_context_with_secure_key(&key, b"message", b"ctx").unwrap();
+    }
+
+    #[test]
+    fn test_evm_nonce_modes() {
+        use k256::ecdsa::{RecoveryId, Signature as K256Signature};
+        enable_permissive_mode();
+
+        let key = SecureBuffer::from_slice(&[9u8; 32]).unwrap();
+        let hash = [1u8; 32];
+        let sign = |nonce| sign_evm_with_nonce_mode(&key, &hash, nonce).unwrap().signature;
+
+        // Deterministic is plain RFC 6979, as k256 computes it
+        let (expected, recovery_id) = K256SigningKey::from_bytes(&[9u8; 32].into())
+            .unwrap()
+            .sign_prehash_recoverable(&hash)
+            .unwrap();
+        let mut expected = expected.to_bytes().to_vec();
+        expected.push(recovery_id.to_byte() + 27);
+        assert_eq!(sign(NonceMode::Deterministic), format!("0x{}", hex::encode(expected)));
+
+        // Extra entropy changes the nonce; the same entropy repeats it
+        let hedged = sign(NonceMode::Hedged);
+        assert_ne!(hedged, sign(NonceMode::Hedged));
+        assert_ne!(sign(NonceMode::HedgedWith([7u8; 32])), sign(NonceMode::Deterministic));
+        assert_eq!(sign(NonceMode::HedgedWith([7u8; 32])), sign(NonceMode::HedgedWith([7u8; 32])));
+
+        // Hedged signatures still recover to the signer
+        let bytes = hex::decode(&hedged[2..]).unwrap();
+        let recovered = K256VerifyingKey::recover_from_prehash(
+            &hash,
+            &K256Signature::from_slice(&bytes[..64]).unwrap(),
+            RecoveryId::from_byte(bytes[64] - 27).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(evm_address_from_pubkey(&recovered), sign_evm_transaction(&[9u8; 32], &hash).unwrap().address);
+    }
+
+    #[test]
+    fn test_schnorr_signature_verifies() {
+        enable_permissive_mode();
+
+        let seed = [9u8; 32];
+        let message = [0x42u8; 32];
+        let result = sign_schnorr(&seed, &message).unwrap();
+
+        let verifying_key =
+            k256::schnorr::VerifyingKey::from_bytes(&hex::decode(&result.public_key).unwrap()).unwrap();
+        let signature = k256::schnorr::Signature::try_from(hex::decode(&result.signature).unwrap().as_slice()).unwrap();
+        assert!(verifying_key.verify_raw(&message, &signature).is_ok());
+        assert!(verifying_key.verify_raw(&[0u8; 32], &signature).is_err());
+    }
 }
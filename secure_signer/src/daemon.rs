@@ -0,0 +1,996 @@
+//! Unix domain socket signing daemon
+//!
+//! One process holds the keys; local clients ask it for signatures over a
+//! Unix socket instead of each decrypting containers themselves. The
+//! protocol is the JSON-lines one of [`serve`](crate::serve), with keys
+//! named in the daemon's configuration rather than unlocked per client:
+//!
+//! ```text
+//! {"id":0,"action":"hello"}
+//! {"id":1,"action":"unlock","key":"treasury","passphrase":"..."}
+//! {"id":2,"action":"sign","key":"treasury","transaction":"<base64>"}
+//! {"id":3,"action":"sign_evm_transaction","key":"treasury","transaction":{...}}
+//! {"id":4,"action":"sign_evm_hash","container_id":"<hex>","hash":"0x..."}
+//! {"id":5,"action":"get_pubkey","key":"treasury"}
+//! {"id":6,"action":"lock","key":"treasury"}
+//! ```
+//!
+//! A key can be named by its [container ID](EncryptedKeyContainer::container_id)
+//! instead of its configured name. `hello` returns the daemon's
+//! [`build_info`], and every signature and key response carries the key's
+//! `container_id` and the build's [fingerprint](crate::build_info::BuildInfo::fingerprint),
+//! so a receipt ties each signature to a container and a build. With a
+//! [signature counter](Daemon::with_counter) it also carries the
+//! signature's number, which an [audit log](Daemon::with_audit_log)
+//! records next to it.
+//!
+//! # Access control
+//!
+//! Each connection is identified by its peer credentials (`SO_PEERCRED`
+//! on Linux, `getpeereid` elsewhere), never by anything the client sends.
+//! The first [`ClientRule`] matching the peer's uid/gid decides which keys
+//! it may use, whether it may unlock and lock them, whether it may sign
+//! bare EVM hashes, and the [`Policy`] its EVM transactions are checked
+//! against. Solana policy rules only apply to built transfers, so a
+//! configuration with one is refused. A peer no rule matches is told so
+//! and disconnected.
+//!
+//! # Sealed configuration
+//!
+//! The configuration can also be the settings of an
+//! [`EncryptedConfig`] ([`DaemonConfig::from_sealed`]), which authenticates
+//! the client rules and policies under a boot passphrase or a TPM-sealed
+//! key. Its `passphrase.<key>` secrets unlock keys at startup
+//! ([`Daemon::unlock_from`]).
+//!
+//! # Lifetime
+//!
+//! The socket is taken from systemd socket activation (`LISTEN_FDS`) when
+//! present, and otherwise bound at [`DaemonConfig::socket`]. Unlocked keys
+//! are [`SigningSession`]s with the configured idle timeout and TTL, each
+//! behind its own lock, so signing with one key does not wait on another.
+//! [`Daemon::shutdown`], or SIGTERM/SIGINT once
+//! [`handle_termination_signals`] has run, stops accepting, lets open
+//! connections finish their current request, zeroizes every key, and
+//! removes the socket file.
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::io::{AsRawFd, FromRawFd};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+use zeroize::Zeroizing;
+
+use crate::audit::AuditLog;
+use crate::backend::SignerBackend;
+use crate::build_info::build_info;
+use crate::counter::SignatureCounter;
+use crate::crypto::EncryptedKeyContainer;
+use crate::encoding::{Encoding, OutputEncoding};
+use crate::error::SignerError;
+use crate::evm::EvmTransaction;
+use crate::idempotency::{self, IdempotencyStore};
+use crate::policy::Policy;
+use crate::secure_config::EncryptedConfig;
+use crate::serve::Response;
+use crate::session::{SessionConfig, SigningSession};
+
+/// Most connections served at once
+pub const MAX_CONNECTIONS: usize = 32;
+
+/// First file descriptor passed by systemd socket activation
+const SD_LISTEN_FDS_START: i32 = 3;
+
+/// How often blocked accepts and reads check for shutdown
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Prefix of the sealed-config secrets holding key passphrases
+pub const PASSPHRASE_SECRET_PREFIX: &str = "passphrase.";
+
+/// Sealed-config secret holding the MAC key of [`DaemonConfig::counter_file`]
+pub const COUNTER_KEY_SECRET: &str = "counter_mac_key";
+
+/// Set by SIGTERM/SIGINT once [`handle_termination_signals`] has run
+static TERMINATION_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// Daemon configuration, usually read from a JSON file
+///
+/// ```json
+/// {
+///   "socket": "/run/coldstar/signer.sock",
+///   "keys": {"treasury": "/etc/coldstar/treasury.json"},
+///   "idle_timeout_secs": 900,
+///   "clients": [
+///     {"uid": 0, "keys": ["treasury"], "may_unlock": true},
+///     {"gid": 1500, "keys": ["treasury"], "policy": {"rules": [{"rule": "forbid_unlimited_approvals"}]}}
+///   ]
+/// }
+/// ```
+#[derive(Deserialize, Clone, Debug)]
+pub struct DaemonConfig {
+    /// Socket path; unused under socket activation
+    #[serde(default)]
+    pub socket: Option<PathBuf>,
+    /// Permissions of a socket the daemon binds itself
+    #[serde(default = "default_socket_mode")]
+    pub socket_mode: u32,
+    /// Key names and their container files
+    pub keys: BTreeMap<String, PathBuf>,
+    /// Lock a key after this long without a signature
+    #[serde(default = "default_idle_timeout_secs")]
+    pub idle_timeout_secs: u64,
+    /// Lock a key this long after it is unlocked, if set
+    #[serde(default)]
+    pub ttl_secs: Option<u64>,
+    /// Close a connection after this long without a request
+    #[serde(default = "default_connection_timeout_secs")]
+    pub connection_timeout_secs: u64,
+    /// Who may connect, first match wins
+    pub clients: Vec<ClientRule>,
+    /// Audit log every signature is appended to, if set
+    #[serde(default)]
+    pub audit_log: Option<PathBuf>,
+    /// Signature counter file, if set; its MAC key is the sealed config's
+    /// [`COUNTER_KEY_SECRET`]
+    #[serde(default)]
+    pub counter_file: Option<PathBuf>,
+}
+
+fn default_socket_mode() -> u32 {
+    0o660
+}
+
+fn default_idle_timeout_secs() -> u64 {
+    15 * 60
+}
+
+fn default_connection_timeout_secs() -> u64 {
+    60
+}
+
+impl DaemonConfig {
+    /// Parse a configuration from JSON
+    pub fn from_json(json: &str) -> Result<Self, SignerError> {
+        let config: Self = serde_json::from_str(json).map_err(|e| SignerError::SerializationError(e.to_string()))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Take the configuration from the settings of a sealed config
+    pub fn from_sealed(sealed: &EncryptedConfig) -> Result<Self, SignerError> {
+        let config: Self = serde_json::from_value(serde_json::Value::Object(sealed.settings().clone()))
+            .map_err(|e| SignerError::SerializationError(e.to_string()))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Refuse rules the daemon cannot enforce
+    ///
+    /// Solana policy rules check [`Transfer`](crate::solana::Transfer)s
+    /// from a builder; the daemon signs raw messages, which they cannot be
+    /// applied to.
+    pub fn validate(&self) -> Result<(), SignerError> {
+        if self.clients.iter().any(|rule| rule.policy.as_ref().is_some_and(Policy::has_solana_rules)) {
+            return Err(SignerError::PolicyViolation(
+                "Solana policy rules cannot be enforced on raw messages; the daemon only applies EVM rules"
+                    .to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    fn session_config(&self) -> SessionConfig {
+        SessionConfig {
+            idle_timeout: Duration::from_secs(self.idle_timeout_secs),
+            ttl: self.ttl_secs.map(Duration::from_secs),
+        }
+    }
+}
+
+/// What peers with a given uid and/or gid may do
+#[derive(Deserialize, Clone, Debug, Default)]
+pub struct ClientRule {
+    /// Peer user ID to match, if set
+    #[serde(default)]
+    pub uid: Option<u32>,
+    /// Peer group ID to match, if set
+    #[serde(default)]
+    pub gid: Option<u32>,
+    /// Keys the peer may sign with
+    pub keys: Vec<String>,
+    /// Whether the peer may unlock and lock those keys
+    #[serde(default)]
+    pub may_unlock: bool,
+    /// Whether the peer may sign bare 32-byte EVM hashes, which no policy
+    /// can inspect
+    #[serde(default)]
+    pub allow_blind_hashes: bool,
+    /// Checked against the calldata of every EVM transaction; Solana rules
+    /// are refused by [`DaemonConfig::validate`]
+    #[serde(default)]
+    pub policy: Option<Policy>,
+}
+
+impl ClientRule {
+    fn matches(&self, peer: &PeerCredentials) -> bool {
+        (self.uid.is_some() || self.gid.is_some())
+            && self.uid.is_none_or(|uid| uid == peer.uid)
+            && self.gid.is_none_or(|gid| gid == peer.gid)
+    }
+
+    fn check_key(&self, key: &str) -> Result<(), SignerError> {
+        if self.keys.iter().any(|allowed| allowed == key) {
+            Ok(())
+        } else {
+            Err(not_permitted())
+        }
+    }
+}
+
+/// The one error for a key a client may not use, so that refusals do not
+/// reveal which keys or containers exist
+fn not_permitted() -> SignerError {
+    SignerError::PolicyViolation("this key is not permitted for this client".to_string())
+}
+
+/// Credentials of the process at the other end of a connection
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PeerCredentials {
+    /// Process ID, where the platform reports it
+    pub pid: Option<i32>,
+    pub uid: u32,
+    pub gid: u32,
+}
+
+/// The kernel's record of who connected
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn peer_credentials(stream: &UnixStream) -> Result<PeerCredentials, SignerError> {
+    // SAFETY: ucred is plain data and `len` matches its size
+    unsafe {
+        let mut cred = std::mem::zeroed::<libc::ucred>();
+        let mut len = std::mem::size_of::<libc::ucred>() as libc::socklen_t;
+        if libc::getsockopt(
+            stream.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_PEERCRED,
+            &mut cred as *mut libc::ucred as *mut libc::c_void,
+            &mut len,
+        ) != 0
+        {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        Ok(PeerCredentials {
+            pid: Some(cred.pid),
+            uid: cred.uid,
+            gid: cred.gid,
+        })
+    }
+}
+
+/// The kernel's record of who connected
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub fn peer_credentials(stream: &UnixStream) -> Result<PeerCredentials, SignerError> {
+    let mut uid = 0;
+    let mut gid = 0;
+    // SAFETY: both out-pointers are valid for the call
+    if unsafe { libc::getpeereid(stream.as_raw_fd(), &mut uid, &mut gid) } != 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    Ok(PeerCredentials { pid: None, uid, gid })
+}
+
+/// Make SIGTERM and SIGINT shut every running [`Daemon`] down gracefully
+pub fn handle_termination_signals() {
+    extern "C" fn request_termination(_signal: libc::c_int) {
+        TERMINATION_REQUESTED.store(true, Ordering::SeqCst);
+    }
+
+    let handler = request_termination as extern "C" fn(libc::c_int) as libc::sighandler_t;
+    // SAFETY: the handler only stores to an atomic, which is async-signal-safe
+    unsafe {
+        libc::signal(libc::SIGTERM, handler);
+        libc::signal(libc::SIGINT, handler);
+    }
+}
+
+#[derive(Deserialize)]
+struct Request {
+    #[serde(default)]
+    id: Option<serde_json::Value>,
+    #[serde(default)]
+    idempotency_key: Option<String>,
+    #[serde(flatten)]
+    action: Action,
+}
+
+/// A configured key, by name or by container ID
+#[derive(Deserialize)]
+struct KeyRef {
+    #[serde(default)]
+    key: Option<String>,
+    #[serde(default)]
+    container_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+enum Action {
+    Hello,
+    Unlock {
+        #[serde(flatten)]
+        target: KeyRef,
+        passphrase: String,
+    },
+    Lock {
+        #[serde(flatten)]
+        target: KeyRef,
+    },
+    Sign {
+        #[serde(flatten)]
+        target: KeyRef,
+        transaction: String,
+        #[serde(default)]
+        encoding: OutputEncoding,
+    },
+    /// The transaction is parsed from the line again as an
+    /// [`EvmTransactionRequest`]: its u128 fields cannot pass through the
+    /// buffering `tag` and `flatten` do
+    SignEvmTransaction {
+        #[serde(flatten)]
+        target: KeyRef,
+    },
+    SignEvmHash {
+        #[serde(flatten)]
+        target: KeyRef,
+        hash: String,
+    },
+    GetPubkey {
+        #[serde(flatten)]
+        target: KeyRef,
+    },
+}
+
+#[derive(Deserialize)]
+struct EvmTransactionRequest {
+    transaction: EvmTransaction,
+}
+
+/// An unlocked key and the container it came from
+struct UnlockedKey {
+    container_id: Option<String>,
+    session: Mutex<SigningSession>,
+}
+
+impl UnlockedKey {
+    fn session(&self) -> MutexGuard<'_, SigningSession> {
+        self.session.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn close(&self) {
+        self.session().close();
+    }
+
+    /// `data` with this key's container ID and the build fingerprint added
+    fn receipt(&self, mut data: serde_json::Value) -> serde_json::Value {
+        if let Some(fields) = data.as_object_mut() {
+            fields.insert("container_id".to_string(), serde_json::json!(self.container_id));
+            fields.insert("build".to_string(), serde_json::json!(build_info().fingerprint()));
+        }
+        data
+    }
+}
+
+/// The key holder behind the socket
+pub struct Daemon {
+    config: DaemonConfig,
+    sessions: Mutex<HashMap<String, Arc<UnlockedKey>>>,
+    idempotency: Mutex<IdempotencyStore>,
+    counter: Option<Arc<Mutex<SignatureCounter>>>,
+    audit: Option<Arc<Mutex<AuditLog>>>,
+    connections: AtomicUsize,
+    shutdown: AtomicBool,
+}
+
+impl Daemon {
+    /// A daemon with every key locked
+    pub fn new(config: DaemonConfig) -> Self {
+        Self {
+            config,
+            sessions: Mutex::new(HashMap::new()),
+            idempotency: Mutex::new(IdempotencyStore::in_memory(idempotency::DEFAULT_CAPACITY)),
+            counter: None,
+            audit: None,
+            connections: AtomicUsize::new(0),
+            shutdown: AtomicBool::new(false),
+        }
+    }
+
+    /// Answer retried requests from `store` instead of an in-memory one
+    pub fn with_idempotency_store(mut self, store: IdempotencyStore) -> Self {
+        self.idempotency = Mutex::new(store);
+        self
+    }
+
+    /// Number every signature of keys unlocked from now on with `counter`
+    pub fn with_counter(mut self, counter: SignatureCounter) -> Self {
+        self.counter = Some(Arc::new(Mutex::new(counter)));
+        self
+    }
+
+    /// Log every signature of keys unlocked from now on to `log`, with the
+    /// key's name as the session label
+    pub fn with_audit_log(mut self, log: AuditLog) -> Self {
+        self.audit = Some(Arc::new(Mutex::new(log)));
+        self
+    }
+
+    /// Names of the configured keys
+    pub fn key_names(&self) -> Vec<String> {
+        self.config.keys.keys().cloned().collect()
+    }
+
+    /// Decrypt a configured key and hold it for signing
+    pub fn unlock(&self, key: &str, passphrase: &str) -> Result<Option<String>, SignerError> {
+        let path = self
+            .config
+            .keys
+            .get(key)
+            .ok_or_else(|| SignerError::ContainerError(format!("no key named '{}'", key)))?;
+        let container = EncryptedKeyContainer::from_json(&std::fs::read_to_string(path)?)?;
+        let mut session = SigningSession::unlock(&container, passphrase, self.config.session_config())?;
+        if let Some(counter) = &self.counter {
+            session = session.count_with(counter.clone());
+        }
+        if let Some(log) = &self.audit {
+            session = session.audit_to(log.clone(), key);
+        }
+        let public_key = session.public_key().map(str::to_string);
+        let unlocked = Arc::new(UnlockedKey {
+            container_id: container.container_id().ok(),
+            session: Mutex::new(session),
+        });
+        if let Some(previous) = self.sessions().insert(key.to_string(), unlocked) {
+            previous.close();
+        }
+        Ok(public_key)
+    }
+
+    /// Unlock every key `sealed` holds a `passphrase.<key>` secret for,
+    /// returning their names
+    pub fn unlock_from(&self, sealed: &EncryptedConfig) -> Result<Vec<String>, SignerError> {
+        let mut unlocked = Vec::new();
+        for key in self.key_names() {
+            if let Some(passphrase) = sealed.secret(&format!("{}{}", PASSPHRASE_SECRET_PREFIX, key)) {
+                let passphrase = std::str::from_utf8(passphrase.as_slice())
+                    .map_err(|_| SignerError::ContainerError(format!("passphrase for '{}' is not UTF-8", key)))?;
+                self.unlock(&key, passphrase)?;
+                unlocked.push(key);
+            }
+        }
+        Ok(unlocked)
+    }
+
+    /// Zeroize a key, returning whether it was unlocked
+    pub fn lock(&self, key: &str) -> bool {
+        self.sessions().remove(key).inspect(|unlocked| unlocked.close()).is_some()
+    }
+
+    /// Zeroize every key
+    pub fn lock_all(&self) {
+        let unlocked: Vec<_> = self.sessions().drain().collect();
+        for (_, key) in unlocked {
+            key.close();
+        }
+    }
+
+    /// Ask [`Daemon::serve`] to stop
+    pub fn shutdown(&self) {
+        self.shutdown.store(true, Ordering::SeqCst);
+    }
+
+    fn stopping(&self) -> bool {
+        self.shutdown.load(Ordering::SeqCst) || TERMINATION_REQUESTED.load(Ordering::SeqCst)
+    }
+
+    /// The socket passed by systemd, or else a new one bound at
+    /// [`DaemonConfig::socket`]
+    pub fn listener(&self) -> Result<UnixListener, SignerError> {
+        if let Some(listener) = activated_listener() {
+            return Ok(listener);
+        }
+
+        let path = self
+            .config
+            .socket
+            .as_ref()
+            .ok_or_else(|| SignerError::IoError("no socket path configured and none passed by systemd".to_string()))?;
+        if std::fs::symlink_metadata(path).is_ok() {
+            // A socket left by an earlier run refuses connections
+            if UnixStream::connect(path).is_ok() {
+                return Err(SignerError::IoError(format!("{} is in use by another daemon", path.display())));
+            }
+            std::fs::remove_file(path)?;
+        }
+        let listener = UnixListener::bind(path)?;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(self.config.socket_mode))?;
+        Ok(listener)
+    }
+
+    /// Accept connections until shut down, then zeroize every key
+    ///
+    /// Each connection is served on its own thread; a socket file the
+    /// daemon bound itself is removed on the way out.
+    pub fn serve(&self, listener: UnixListener) -> Result<(), SignerError> {
+        listener.set_nonblocking(true)?;
+        let bound_path = listener
+            .local_addr()
+            .ok()
+            .and_then(|addr| addr.as_pathname().map(PathBuf::from))
+            .filter(|path| self.config.socket.as_ref() == Some(path));
+
+        let result = std::thread::scope(|scope| {
+            while !self.stopping() {
+                match listener.accept() {
+                    Ok((stream, _)) => {
+                        if self.connections.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+                            self.connections.fetch_sub(1, Ordering::SeqCst);
+                            continue;
+                        }
+                        scope.spawn(move || {
+                            let _ = self.handle_connection(stream);
+                            self.connections.fetch_sub(1, Ordering::SeqCst);
+                        });
+                    }
+                    Err(e) if e.kind() == ErrorKind::WouldBlock => std::thread::sleep(POLL_INTERVAL),
+                    Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                    Err(e) => return Err(SignerError::from(e)),
+                }
+            }
+            Ok(())
+        });
+
+        self.lock_all();
+        if let Some(path) = bound_path {
+            let _ = std::fs::remove_file(path);
+        }
+        result
+    }
+
+    /// Serve one client until it disconnects, idles out, or the daemon stops
+    pub fn handle_connection(&self, stream: UnixStream) -> Result<(), SignerError> {
+        stream.set_nonblocking(false)?;
+        let peer = peer_credentials(&stream)?;
+        let mut writer = stream.try_clone()?;
+
+        let rule = match self.config.clients.iter().find(|rule| rule.matches(&peer)) {
+            Some(rule) => rule,
+            None => {
+                let response = error_response(None, &format!("uid {} is not allowed to connect", peer.uid));
+                writeln!(writer, "{}", serde_json::to_string(&response)?)?;
+                return Ok(());
+            }
+        };
+
+        stream.set_read_timeout(Some(POLL_INTERVAL))?;
+        let idle_limit = Duration::from_secs(self.config.connection_timeout_secs);
+        let mut reader = BufReader::new(stream);
+        let mut line = Zeroizing::new(String::new());
+        let mut last_request = Instant::now();
+
+        while !self.stopping() && last_request.elapsed() < idle_limit {
+            match reader.read_line(&mut line) {
+                Ok(0) => break,
+                Ok(_) => {
+                    last_request = Instant::now();
+                    if !line.trim().is_empty() {
+                        let response = self.handle_line(rule, &peer, &line);
+                        writeln!(writer, "{}", serde_json::to_string(&response)?)?;
+                        writer.flush()?;
+                    }
+                    line.clear();
+                }
+                // A partial line stays in `line` and is completed next time
+                Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_line(&self, rule: &ClientRule, peer: &PeerCredentials, line: &str) -> Response {
+        let request: Request = match serde_json::from_str(line) {
+            Ok(request) => request,
+            Err(e) => return error_response(None, &format!("Invalid request: {}", e)),
+        };
+        let result = match &request.idempotency_key {
+            Some(key) => self.execute_once(rule, peer, key, request.action, line),
+            None => self.execute(rule, request.action, line),
+        };
+        match result {
+            Ok(data) => Response {
+                id: request.id,
+                success: true,
+                data: Some(data),
+                error: None,
+            },
+            Err(e) => error_response(request.id, &e.to_string()),
+        }
+    }
+
+    /// Run `action` unless this peer already ran a request with this key
+    ///
+    /// The store is not held while signing, so two copies of a request
+    /// that arrive together may both be signed.
+    fn execute_once(
+        &self,
+        rule: &ClientRule,
+        peer: &PeerCredentials,
+        key: &str,
+        action: Action,
+        line: &str,
+    ) -> Result<serde_json::Value, SignerError> {
+        // Keys are per peer: another client reusing one gets a conflict, not
+        // this client's response
+        let mut request = self.idempotency().canonical_request(line)?;
+        request.extend_from_slice(&peer.uid.to_le_bytes());
+        if let Some(cached) = self.idempotency().lookup(key, &request)? {
+            return Ok(cached);
+        }
+        let data = self.execute(rule, action, line)?;
+        self.idempotency().record(key, &request, &data)?;
+        Ok(data)
+    }
+
+    fn execute(&self, rule: &ClientRule, action: Action, line: &str) -> Result<serde_json::Value, SignerError> {
+        match action {
+            Action::Hello => Ok(serde_json::json!({ "build": build_info(), "keys": rule.keys })),
+            Action::Unlock { target, passphrase } => {
+                let key = self.resolve(rule, target)?;
+                check_may_unlock(rule)?;
+                let passphrase = Zeroizing::new(passphrase);
+                let public_key = self.unlock(&key, &passphrase)?;
+                Ok(self.unlocked(&key)?.receipt(serde_json::json!({ "key": key, "public_key": public_key })))
+            }
+            Action::Lock { target } => {
+                let key = self.resolve(rule, target)?;
+                check_may_unlock(rule)?;
+                Ok(serde_json::json!({ "locked": self.lock(&key) }))
+            }
+            Action::Sign {
+                target,
+                transaction,
+                encoding,
+            } => {
+                let key = self.resolve(rule, target)?;
+                let transaction = Encoding::Base64.decode(&transaction)?;
+                let unlocked = self.unlocked(&key)?;
+                let result = unlocked.session().sign(&transaction)?;
+                Ok(unlocked.receipt(serde_json::to_value(result.with_encoding(&encoding)?)?))
+            }
+            Action::SignEvmTransaction { target } => {
+                let key = self.resolve(rule, target)?;
+                let EvmTransactionRequest { transaction } = serde_json::from_str(line)?;
+                if let Some(policy) = &rule.policy {
+                    policy.check_evm_call(&transaction.data)?;
+                }
+                let unlocked = self.unlocked(&key)?;
+                let result = transaction.sign_with(&*unlocked.session())?;
+                Ok(unlocked.receipt(serde_json::to_value(result)?))
+            }
+            Action::SignEvmHash { target, hash } => {
+                let key = self.resolve(rule, target)?;
+                if !rule.allow_blind_hashes {
+                    return Err(SignerError::PolicyViolation(
+                        "signing bare hashes is not allowed for this client".to_string(),
+                    ));
+                }
+                let hash = Encoding::Hex.decode(&hash)?;
+                let unlocked = self.unlocked(&key)?;
+                let result = unlocked.session().sign_evm_hash(&hash)?;
+                Ok(unlocked.receipt(serde_json::to_value(result)?))
+            }
+            Action::GetPubkey { target } => {
+                let key = self.resolve(rule, target)?;
+                let unlocked = self.unlocked(&key)?;
+                let public_key = unlocked.session().public_key().map(str::to_string);
+                Ok(unlocked.receipt(serde_json::json!({ "key": key, "public_key": public_key })))
+            }
+        }
+    }
+
+    /// The configured name of `target`, if `rule` allows it
+    fn resolve(&self, rule: &ClientRule, target: KeyRef) -> Result<String, SignerError> {
+        match (target.key, target.container_id) {
+            (Some(key), None) => {
+                rule.check_key(&key)?;
+                Ok(key)
+            }
+            (named, Some(container_id)) => {
+                if let Some(named) = &named {
+                    rule.check_key(named)?;
+                }
+                self.key_for_container(rule, named.as_deref(), &container_id)
+            }
+            (None, None) => Err(SignerError::ContainerError("request names no key or container_id".to_string())),
+        }
+    }
+
+    /// The key `rule` allows (`named`, if given) whose container has
+    /// `container_id`
+    ///
+    /// Only the client's own keys are looked at, and every miss is the same
+    /// [`not_permitted`] error.
+    fn key_for_container(
+        &self,
+        rule: &ClientRule,
+        named: Option<&str>,
+        container_id: &str,
+    ) -> Result<String, SignerError> {
+        let allowed = |key: &str| rule.check_key(key).is_ok() && named.is_none_or(|named| named == key);
+        let unlocked = self
+            .sessions()
+            .iter()
+            .find(|(key, unlocked)| allowed(key) && unlocked.container_id.as_deref() == Some(container_id))
+            .map(|(key, _)| key.clone());
+        if let Some(key) = unlocked {
+            return Ok(key);
+        }
+        for (key, path) in self.config.keys.iter().filter(|(key, _)| allowed(key)) {
+            let container = std::fs::read_to_string(path)
+                .ok()
+                .and_then(|json| EncryptedKeyContainer::from_json(&json).ok());
+            if container.is_some_and(|container| container.container_id().is_ok_and(|id| id == container_id)) {
+                return Ok(key.clone());
+            }
+        }
+        Err(not_permitted())
+    }
+
+    /// The unlocked `key`; the sessions map is only held to look it up
+    fn unlocked(&self, key: &str) -> Result<Arc<UnlockedKey>, SignerError> {
+        let locked = || SignerError::SessionLocked(format!("key '{}' is locked", key));
+        let unlocked = self.sessions().get(key).cloned().ok_or_else(locked)?;
+        if unlocked.session().is_locked() {
+            let mut sessions = self.sessions();
+            if sessions.get(key).is_some_and(|current| Arc::ptr_eq(current, &unlocked)) {
+                sessions.remove(key);
+            }
+            return Err(locked());
+        }
+        Ok(unlocked)
+    }
+
+    fn sessions(&self) -> MutexGuard<'_, HashMap<String, Arc<UnlockedKey>>> {
+        self.sessions.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn idempotency(&self) -> MutexGuard<'_, IdempotencyStore> {
+        self.idempotency.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Drop for Daemon {
+    fn drop(&mut self) {
+        self.lock_all();
+    }
+}
+
+fn check_may_unlock(rule: &ClientRule) -> Result<(), SignerError> {
+    if rule.may_unlock {
+        Ok(())
+    } else {
+        Err(SignerError::PolicyViolation("this client may not unlock or lock keys".to_string()))
+    }
+}
+
+fn error_response(id: Option<serde_json::Value>, error: &str) -> Response {
+    Response {
+        id,
+        success: false,
+        data: None,
+        error: Some(error.to_string()),
+    }
+}
+
+/// The listening socket systemd passed, if this process was socket-activated
+fn activated_listener() -> Option<UnixListener> {
+    let pid: u32 = std::env::var("LISTEN_PID").ok()?.parse().ok()?;
+    let fds: i32 = std::env::var("LISTEN_FDS").ok()?.parse().ok()?;
+    if pid != std::process::id() || fds < 1 {
+        return None;
+    }
+    std::env::remove_var("LISTEN_PID");
+    std::env::remove_var("LISTEN_FDS");
+    // SAFETY: systemd hands over this descriptor, and nothing else owns it
+    Some(unsafe { UnixListener::from_raw_fd(SD_LISTEN_FDS_START) })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kdf::KdfParams;
+    use crate::policy::PolicyRule;
+    use crate::Cipher;
+
+    fn request(stream: &mut UnixStream, reader: &mut BufReader<UnixStream>, json: impl std::fmt::Display) -> serde_json::Value {
+        writeln!(stream, "{}", json).unwrap();
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        serde_json::from_str(&line).unwrap()
+    }
+
+    #[test]
+    fn test_peer_rules_gate_keys_and_policy() {
+        std::env::set_var("SIGNER_ALLOW_INSECURE_MEMORY", "1");
+        let container =
+            EncryptedKeyContainer::encrypt_with_kdf(&[9u8; 32], "pw", Cipher::Aes256Gcm, KdfParams::MINIMUM).unwrap();
+        let path = std::env::temp_dir().join(format!("coldstar-daemon-test-{}.json", std::process::id()));
+        std::fs::write(&path, container.to_json().unwrap()).unwrap();
+        let vault =
+            EncryptedKeyContainer::encrypt_with_kdf(&[8u8; 32], "pw", Cipher::Aes256Gcm, KdfParams::MINIMUM).unwrap();
+        let vault_path = std::env::temp_dir().join(format!("coldstar-daemon-vault-{}.json", std::process::id()));
+        std::fs::write(&vault_path, vault.to_json().unwrap()).unwrap();
+
+        // SAFETY: getuid/getgid cannot fail
+        let (uid, gid) = unsafe { (libc::getuid(), libc::getgid()) };
+        let config = DaemonConfig {
+            socket: None,
+            socket_mode: default_socket_mode(),
+            keys: BTreeMap::from([("hot".to_string(), path.clone()), ("vault".to_string(), vault_path.clone())]),
+            idle_timeout_secs: 60,
+            ttl_secs: None,
+            connection_timeout_secs: 5,
+            audit_log: None,
+            counter_file: None,
+            clients: vec![ClientRule {
+                uid: Some(uid),
+                gid: Some(gid),
+                keys: vec!["hot".to_string()],
+                may_unlock: true,
+                allow_blind_hashes: false,
+                policy: Some(Policy::new(vec![PolicyRule::ForbidUnlimitedApprovals])),
+            }],
+        };
+        let daemon = Daemon::new(config.clone()).with_counter(SignatureCounter::in_memory());
+
+        let solana_rules = r#"{"keys": {}, "clients": [{"uid": 0, "keys": [], "policy": {"rules": [{"rule": "max_lamports", "lamports": 1}]}}]}"#;
+        assert!(DaemonConfig::from_json(solana_rules).is_err());
+
+        let (mut client, server) = UnixStream::pair().unwrap();
+        let mut reader = BufReader::new(client.try_clone().unwrap());
+        std::thread::scope(|scope| {
+            scope.spawn(|| daemon.handle_connection(server).unwrap());
+
+            let locked = request(&mut client, &mut reader, serde_json::json!({"id": 1, "action": "get_pubkey", "key": "hot"}));
+            assert_eq!(locked["id"], 1);
+            assert!(locked["error"].as_str().unwrap().contains("locked"));
+
+            let unlocked = request(
+                &mut client,
+                &mut reader,
+                serde_json::json!({"action": "unlock", "key": "hot", "passphrase": "pw"}),
+            );
+            assert_eq!(unlocked["data"]["public_key"].as_str(), container.public_key.as_deref());
+
+            let transaction = base64::Engine::encode(
+                &base64::engine::general_purpose::STANDARD,
+                crate::crypto::test_transaction(&[9u8; 32]),
+            );
+            let signed = request(&mut client, &mut reader, serde_json::json!({"action": "sign", "key": "hot", "transaction": transaction}));
+            assert_eq!(signed["success"], true);
+
+            // Receipts name the container, the build, and the signature's number
+            let container_id = container.container_id().unwrap();
+            assert_eq!(signed["data"]["container_id"], container_id);
+            assert_eq!(signed["data"]["counter"], 1);
+            assert_eq!(signed["data"]["build"], build_info().fingerprint());
+            let hello = request(&mut client, &mut reader, serde_json::json!({"action": "hello"}));
+            assert_eq!(hello["data"]["build"]["git_commit"], build_info().git_commit);
+            let by_id = request(
+                &mut client,
+                &mut reader,
+                serde_json::json!({"action": "sign", "container_id": container_id, "transaction": transaction}),
+            );
+            assert_eq!(by_id["data"]["signature"], signed["data"]["signature"]);
+            let mismatch = request(
+                &mut client,
+                &mut reader,
+                serde_json::json!({"action": "sign", "key": "cold", "container_id": container_id, "transaction": transaction}),
+            );
+            assert!(mismatch["error"].as_str().unwrap().contains("not permitted"));
+
+            // Keys the client may not use look the same as keys that do not
+            // exist, by name or by container ID
+            let other = request(&mut client, &mut reader, serde_json::json!({"action": "sign", "key": "cold", "transaction": transaction}));
+            let vault = request(
+                &mut client,
+                &mut reader,
+                serde_json::json!({"action": "sign", "container_id": vault.container_id().unwrap(), "transaction": transaction}),
+            );
+            let unknown = request(
+                &mut client,
+                &mut reader,
+                serde_json::json!({"action": "sign", "container_id": "00", "transaction": transaction}),
+            );
+            assert!(other["error"].as_str().unwrap().contains("not permitted"));
+            assert_eq!(vault["error"], other["error"]);
+            assert_eq!(unknown["error"], other["error"]);
+
+            let blind = request(&mut client, &mut reader, serde_json::json!({"action": "sign_evm_hash", "key": "hot", "hash": hex::encode([1u8; 32])}));
+            assert!(blind["error"].as_str().unwrap().contains("bare hashes"));
+
+            // approve(spender, 2^256 - 1)
+            let mut calldata = hex::decode("095ea7b3").unwrap();
+            calldata.extend([0u8; 32]);
+            calldata.extend([0xffu8; 32]);
+            let transaction = EvmTransaction {
+                chain_id: 1,
+                nonce: 0,
+                gas_limit: 60_000,
+                to: Some([0x11; 20]),
+                value: 100 * 10u128.pow(18),
+                data: calldata,
+                pricing: crate::evm::GasPricing::Legacy { gas_price: 1 },
+            };
+            // Values past u64 need the transaction serialized straight to text
+            let line = format!(
+                r#"{{"action":"sign_evm_transaction","key":"hot","transaction":{}}}"#,
+                serde_json::to_string(&transaction).unwrap()
+            );
+            let unlimited = request(&mut client, &mut reader, line);
+            assert!(unlimited["error"].as_str().unwrap().contains("Policy violation"), "{}", unlimited);
+
+            daemon.shutdown();
+        });
+        daemon.lock_all();
+        assert!(daemon.sessions().is_empty());
+
+        // A peer no rule matches is turned away
+        let strangers = Daemon::new(DaemonConfig {
+            clients: vec![ClientRule {
+                uid: Some(uid.wrapping_add(1)),
+                ..ClientRule::default()
+            }],
+            ..config
+        });
+        let (client, server) = UnixStream::pair().unwrap();
+        strangers.handle_connection(server).unwrap();
+        let mut line = String::new();
+        BufReader::new(client).read_line(&mut line).unwrap();
+        assert!(line.contains("not allowed to connect"));
+
+        std::fs::remove_file(path).unwrap();
+        std::fs::remove_file(vault_path).unwrap();
+    }
+
+    #[test]
+    fn test_sealed_config_unlocks_keys() {
+        use crate::secure_config::ConfigKeySource;
+
+        std::env::set_var("SIGNER_ALLOW_INSECURE_MEMORY", "1");
+        let container =
+            EncryptedKeyContainer::encrypt_with_kdf(&[9u8; 32], "pw", Cipher::Aes256Gcm, KdfParams::MINIMUM).unwrap();
+        let path = std::env::temp_dir().join(format!("coldstar-daemon-sealed-{}.json", std::process::id()));
+        std::fs::write(&path, container.to_json().unwrap()).unwrap();
+
+        let mut sealed = EncryptedConfig::new();
+        sealed.set_setting("keys", serde_json::json!({"hot": path, "cold": path}));
+        sealed.set_setting("clients", serde_json::json!([{"uid": 0, "keys": ["hot"]}]));
+        sealed.set_secret("passphrase.hot", b"pw").unwrap();
+        let json = sealed.to_json(&ConfigKeySource::Passphrase("boot")).unwrap();
+        let sealed = EncryptedConfig::from_json(&json, &ConfigKeySource::Passphrase("boot")).unwrap();
+
+        let daemon = Daemon::new(DaemonConfig::from_sealed(&sealed).unwrap());
+        assert_eq!(daemon.unlock_from(&sealed).unwrap(), ["hot"]);
+        assert!(daemon.sessions().contains_key("hot"));
+        assert!(!daemon.sessions().contains_key("cold"));
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
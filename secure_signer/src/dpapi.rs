@@ -0,0 +1,229 @@
+//! Windows DPAPI protection for containers
+//!
+//! An optional second envelope around [`EncryptedKeyContainer`]: the
+//! serialized container is encrypted with `CryptProtectData`, which binds it
+//! to the Windows user account (or the machine). A copied container file is
+//! useless on another account even with the passphrase, and the passphrase
+//! is still required after unwrapping.
+//!
+//! Blobs are created with a fixed application entropy value so other
+//! programs running as the same user cannot ask DPAPI to open them without
+//! knowing it, and with `CRYPTPROTECT_UI_FORBIDDEN` so no dialog is shown.
+
+use std::ffi::c_void;
+use std::ptr;
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use serde::{Deserialize, Serialize};
+
+use crate::crypto::{get_locking_mode, EncryptedKeyContainer};
+use crate::error::SignerError;
+use crate::secure_buffer::SecureBuffer;
+
+/// Envelope format version
+const ENVELOPE_VERSION: u8 = 1;
+
+/// Application entropy mixed into every blob
+const DPAPI_ENTROPY: &[u8] = b"coldstar-dpapi-v1";
+
+const CRYPTPROTECT_UI_FORBIDDEN: u32 = 0x1;
+const CRYPTPROTECT_LOCAL_MACHINE: u32 = 0x4;
+
+#[repr(C)]
+struct DataBlob {
+    cb_data: u32,
+    pb_data: *mut u8,
+}
+
+#[link(name = "crypt32")]
+extern "system" {
+    fn CryptProtectData(
+        data_in: *const DataBlob,
+        description: *const u16,
+        entropy: *const DataBlob,
+        reserved: *mut c_void,
+        prompt: *mut c_void,
+        flags: u32,
+        data_out: *mut DataBlob,
+    ) -> i32;
+
+    fn CryptUnprotectData(
+        data_in: *const DataBlob,
+        description: *mut *mut u16,
+        entropy: *const DataBlob,
+        reserved: *mut c_void,
+        prompt: *mut c_void,
+        flags: u32,
+        data_out: *mut DataBlob,
+    ) -> i32;
+}
+
+extern "system" {
+    fn LocalFree(mem: *mut c_void) -> *mut c_void;
+    fn GetLastError() -> u32;
+}
+
+/// Who can open a DPAPI blob
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DpapiScope {
+    /// Only the current Windows user
+    #[default]
+    CurrentUser,
+    /// Any user on this machine (for services running under other accounts)
+    LocalMachine,
+}
+
+/// An [`EncryptedKeyContainer`] additionally protected with DPAPI
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DpapiWrappedContainer {
+    /// Envelope format version
+    pub version: u8,
+    /// DPAPI scope the blob was created with
+    pub scope: DpapiScope,
+    /// DPAPI blob of the container JSON (base64)
+    pub ciphertext: String,
+}
+
+impl DpapiWrappedContainer {
+    /// Protect a container for the given scope
+    pub fn wrap(container: &EncryptedKeyContainer, scope: DpapiScope) -> Result<Self, SignerError> {
+        let json = container.to_json()?;
+        Ok(Self {
+            version: ENVELOPE_VERSION,
+            scope,
+            ciphertext: BASE64.encode(protect(json.as_bytes(), scope)?),
+        })
+    }
+
+    /// Remove the DPAPI layer, returning the (still passphrase-encrypted)
+    /// container
+    pub fn unwrap(&self) -> Result<EncryptedKeyContainer, SignerError> {
+        if self.version != ENVELOPE_VERSION {
+            return Err(SignerError::ContainerError(format!(
+                "unsupported DPAPI envelope version {}",
+                self.version
+            )));
+        }
+        let json = unprotect(&BASE64.decode(&self.ciphertext)?)?;
+        let json = std::str::from_utf8(json.as_slice())
+            .map_err(|_| SignerError::ContainerError("envelope does not hold a container".to_string()))?;
+        EncryptedKeyContainer::from_json(json)
+    }
+
+    /// Serialize to JSON
+    pub fn to_json(&self) -> Result<String, SignerError> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Parse from JSON
+    pub fn from_json(json: &str) -> Result<Self, SignerError> {
+        Ok(serde_json::from_str(json)?)
+    }
+}
+
+/// Encrypt bytes with `CryptProtectData`
+pub fn protect(plaintext: &[u8], scope: DpapiScope) -> Result<Vec<u8>, SignerError> {
+    let flags = CRYPTPROTECT_UI_FORBIDDEN
+        | match scope {
+            DpapiScope::CurrentUser => 0,
+            DpapiScope::LocalMachine => CRYPTPROTECT_LOCAL_MACHINE,
+        };
+    let input = blob(plaintext)?;
+    let entropy = blob(DPAPI_ENTROPY)?;
+    let mut output = DataBlob {
+        cb_data: 0,
+        pb_data: ptr::null_mut(),
+    };
+
+    let ok = unsafe {
+        CryptProtectData(
+            &input,
+            ptr::null(),
+            &entropy,
+            ptr::null_mut(),
+            ptr::null_mut(),
+            flags,
+            &mut output,
+        )
+    };
+    if ok == 0 {
+        return Err(last_error("CryptProtectData"));
+    }
+
+    let protected = unsafe { std::slice::from_raw_parts(output.pb_data, output.cb_data as usize).to_vec() };
+    unsafe { LocalFree(output.pb_data as *mut c_void) };
+    Ok(protected)
+}
+
+/// Decrypt a `CryptProtectData` blob into a secure buffer
+///
+/// The buffer DPAPI allocates for the plaintext is zeroized before it is
+/// freed.
+pub fn unprotect(protected: &[u8]) -> Result<SecureBuffer, SignerError> {
+    let input = blob(protected)?;
+    let entropy = blob(DPAPI_ENTROPY)?;
+    let mut output = DataBlob {
+        cb_data: 0,
+        pb_data: ptr::null_mut(),
+    };
+
+    let ok = unsafe {
+        CryptUnprotectData(
+            &input,
+            ptr::null_mut(),
+            &entropy,
+            ptr::null_mut(),
+            ptr::null_mut(),
+            CRYPTPROTECT_UI_FORBIDDEN,
+            &mut output,
+        )
+    };
+    if ok == 0 {
+        return Err(last_error("CryptUnprotectData"));
+    }
+
+    let plaintext = unsafe { std::slice::from_raw_parts_mut(output.pb_data, output.cb_data as usize) };
+    let result = SecureBuffer::from_slice_with_mode(plaintext, get_locking_mode());
+    zeroize::Zeroize::zeroize(plaintext);
+    unsafe { LocalFree(output.pb_data as *mut c_void) };
+    result
+}
+
+fn blob(data: &[u8]) -> Result<DataBlob, SignerError> {
+    Ok(DataBlob {
+        cb_data: u32::try_from(data.len())
+            .map_err(|_| SignerError::PlatformError("data too large for DPAPI".to_string()))?,
+        // DPAPI does not write through input blobs
+        pb_data: data.as_ptr() as *mut u8,
+    })
+}
+
+fn last_error(function: &str) -> SignerError {
+    let code = unsafe { GetLastError() };
+    SignerError::PlatformError(format!("{} failed (error {:#x})", function, code))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::Cipher;
+    use crate::kdf::KdfParams;
+
+    #[test]
+    fn test_wrap_unwrap_roundtrip() {
+        let container =
+            EncryptedKeyContainer::encrypt_with_kdf(&[5u8; 32], "pw", Cipher::Aes256Gcm, KdfParams::MINIMUM).unwrap();
+
+        let wrapped = DpapiWrappedContainer::wrap(&container, DpapiScope::CurrentUser).unwrap();
+        let parsed = DpapiWrappedContainer::from_json(&wrapped.to_json().unwrap()).unwrap();
+        let unwrapped = parsed.unwrap().unwrap();
+        assert_eq!(unwrapped.container_id().unwrap(), container.container_id().unwrap());
+
+        // Tampered blobs are rejected by DPAPI
+        let mut blob = BASE64.decode(&wrapped.ciphertext).unwrap();
+        let last = blob.len() - 1;
+        blob[last] ^= 1;
+        assert!(unprotect(&blob).is_err());
+    }
+}
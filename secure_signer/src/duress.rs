@@ -0,0 +1,206 @@
+//! Duress passphrases
+//!
+//! A container can hold a second, decoy key, encrypted under a duress
+//! passphrase. Someone forced to unlock their wallet gives the duress
+//! passphrase: decryption succeeds, and signing proceeds normally with the
+//! decoy key (typically a wallet holding a small balance). The result
+//! carries no duress flag.
+//!
+//! The host application learns of it through [`on_duress`] hooks, run each
+//! time a decoy key is unlocked, or by polling [`duress_raised`]. What it
+//! does then (alert someone, log quietly, keep behaving normally) is up to
+//! the application; a hook should do nothing a coercer would notice.
+//!
+//! Both passphrases go through the same KDF with the same salt, so
+//! unlocking takes the same time either way. The slot itself is visible:
+//! anyone who reads the container file sees a `duress` field, and the
+//! decoy key's signatures do not match the container's `public_key`. It
+//! protects against being made to sign, not against inspection of the
+//! file.
+//!
+//! [`rekey`](EncryptedKeyContainer::rekey), [`migrate`](EncryptedKeyContainer::migrate),
+//! and [`with_validity`](EncryptedKeyContainer::with_validity) re-encrypt
+//! under a new salt and so drop the duress slot; add it again afterwards.
+
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+#[cfg(feature = "std")]
+use std::panic::AssertUnwindSafe;
+
+use serde::{Deserialize, Serialize};
+
+use crate::crypto::{get_locking_mode, EncryptedKeyContainer, ED25519_SEED_SIZE};
+use crate::entropy::fill_random;
+use crate::error::SignerError;
+use crate::secure_buffer::SecureBuffer;
+use crate::sync::{Mutex, MutexGuard};
+
+/// A decoy key encrypted under the duress passphrase
+///
+/// Shares the container's cipher, KDF, and salt.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct DuressSlot {
+    /// Nonce for the cipher (base64)
+    pub nonce: String,
+    /// Encrypted decoy key with auth tag (base64)
+    pub ciphertext: String,
+}
+
+/// What a duress hook is told
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DuressEvent {
+    /// ID of the container unlocked with its duress passphrase, when it
+    /// has a public key
+    pub container_id: Option<String>,
+}
+
+type Hook = Arc<dyn Fn(&DuressEvent) + Send + Sync>;
+
+static HOOKS: Mutex<Vec<(u64, Hook)>> = Mutex::new(Vec::new());
+static NEXT_HOOK_ID: AtomicU64 = AtomicU64::new(0);
+static RAISED: AtomicBool = AtomicBool::new(false);
+
+fn hooks() -> MutexGuard<'static, Vec<(u64, Hook)>> {
+    HOOKS.lock()
+}
+
+/// Registration of a duress hook; unregisters it when dropped
+#[must_use = "the hook is unregistered when this handle is dropped"]
+pub struct DuressHook {
+    id: u64,
+}
+
+impl Drop for DuressHook {
+    fn drop(&mut self) {
+        hooks().retain(|(id, _)| *id != self.id);
+    }
+}
+
+/// Run `hook` whenever a container is unlocked with its duress passphrase
+///
+/// Hooks run on the signing thread before the decoy key is returned, so
+/// they should be quick. With `std` a panicking hook is ignored.
+pub fn on_duress(hook: impl Fn(&DuressEvent) + Send + Sync + 'static) -> DuressHook {
+    let id = NEXT_HOOK_ID.fetch_add(1, Ordering::Relaxed);
+    hooks().push((id, Arc::new(hook)));
+    DuressHook { id }
+}
+
+/// Whether any container has been unlocked with a duress passphrase since
+/// the process started (or since [`clear_duress`])
+pub fn duress_raised() -> bool {
+    RAISED.load(Ordering::SeqCst)
+}
+
+/// Reset [`duress_raised`]
+pub fn clear_duress() {
+    RAISED.store(false, Ordering::SeqCst);
+}
+
+/// Record that `container`'s decoy key was unlocked
+pub(crate) fn raise(container: &EncryptedKeyContainer) {
+    RAISED.store(true, Ordering::SeqCst);
+    let event = DuressEvent {
+        container_id: container.container_id().ok(),
+    };
+    let snapshot: Vec<Hook> = hooks().iter().map(|(_, hook)| Arc::clone(hook)).collect();
+    for hook in &snapshot {
+        #[cfg(feature = "std")]
+        let _ = std::panic::catch_unwind(AssertUnwindSafe(|| hook(&event)));
+        #[cfg(not(feature = "std"))]
+        hook(&event);
+    }
+}
+
+impl EncryptedKeyContainer {
+    /// Add (or replace) a decoy key that `duress_passphrase` unlocks
+    ///
+    /// `passphrase` must open the real key, and both the passphrase and
+    /// the key must differ from the real ones. The container keeps its
+    /// salt and [`container_id`](EncryptedKeyContainer::container_id).
+    pub fn with_duress(
+        &self,
+        passphrase: &str,
+        duress_passphrase: &str,
+        decoy_key: &[u8],
+    ) -> Result<Self, SignerError> {
+        if decoy_key.len() != ED25519_SEED_SIZE && decoy_key.len() != 2 * ED25519_SEED_SIZE {
+            return Err(SignerError::InvalidKeyFormat(decoy_key.len()));
+        }
+        let decoy = SecureBuffer::from_slice_with_mode(&decoy_key[..ED25519_SEED_SIZE], get_locking_mode())?;
+
+        let mut unlock_key = self.derive_unlock_key(passphrase)?;
+        let real = self.open_slot(&unlock_key, &self.nonce, &self.ciphertext);
+        unlock_key.zeroize();
+        let mut real = real?;
+        let same_key = real.as_slice() == decoy.as_slice();
+        real.zeroize();
+        if same_key {
+            return Err(SignerError::ContainerError("the decoy key must differ from the real key".to_string()));
+        }
+
+        let mut duress_key = self.derive_unlock_key(duress_passphrase)?;
+        if self.open_slot(&duress_key, &self.nonce, &self.ciphertext).is_ok() {
+            duress_key.zeroize();
+            return Err(SignerError::ContainerError(
+                "the duress passphrase must differ from the passphrase".to_string(),
+            ));
+        }
+        let mut nonce = vec![0u8; self.cipher.nonce_size()];
+        let ciphertext = fill_random(&mut nonce).and_then(|()| {
+            self.cipher
+                .encrypt_with_aad(duress_key.as_slice(), &nonce, decoy.as_slice(), &self.associated_data()?)
+        });
+        duress_key.zeroize();
+
+        let mut container = self.clone();
+        container.duress = Some(DuressSlot {
+            nonce: base64::Engine::encode(&base64::engine::general_purpose::STANDARD, &nonce),
+            ciphertext: base64::Engine::encode(&base64::engine::general_purpose::STANDARD, ciphertext?),
+        });
+        Ok(container)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::Cipher;
+    use crate::kdf::KdfParams;
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_duress_passphrase_unlocks_decoy_and_raises() {
+        std::env::set_var("SIGNER_ALLOW_INSECURE_MEMORY", "1");
+        let container =
+            EncryptedKeyContainer::encrypt_with_kdf(&[1u8; 32], "real", Cipher::Aes256Gcm, KdfParams::MINIMUM).unwrap();
+        assert!(container.with_duress("wrong", "duress", &[2u8; 32]).is_err());
+        assert!(container.with_duress("real", "real", &[2u8; 32]).is_err());
+        assert!(container.with_duress("real", "duress", &[1u8; 32]).is_err());
+        let container = container.with_duress("real", "duress", &[2u8; 32]).unwrap();
+        let json = container.to_json().unwrap();
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let seen = Arc::clone(&events);
+        let _hook = on_duress(move |event| seen.lock().unwrap().push(event.clone()));
+
+        let real = crate::decrypt_and_sign(&json, "real", &crate::crypto::test_transaction(&[1u8; 32])).unwrap();
+        assert_eq!(Some(real.public_key), container.public_key);
+        assert!(events.lock().unwrap().is_empty());
+
+        let message = crate::crypto::test_transaction(&[2u8; 32]);
+        let decoy = crate::decrypt_and_sign(&json, "duress", &message).unwrap();
+        let expected = crate::sign_transaction(&[2u8; 32], &message).unwrap();
+        assert_eq!(decoy.public_key, expected.public_key);
+        assert!(duress_raised());
+        assert_eq!(events.lock().unwrap()[0].container_id, container.container_id().ok());
+
+        assert!(matches!(
+            crate::decrypt_and_sign(&json, "neither", b"message"),
+            Err(SignerError::DecryptionFailed)
+        ));
+    }
+}
@@ -0,0 +1,233 @@
+//! Encryption to Ed25519 signing identities
+//!
+//! A Solana key can receive messages as well as sign: its Ed25519 public
+//! key converts to an X25519 public key (the birationally equivalent
+//! Montgomery point), and its seed to the matching X25519 scalar
+//! (SHA-512 of the seed, first half, as Ed25519 itself uses).
+//!
+//! ```ignore
+//! let message = encrypt_message_to_pubkey(&recipient_address, b"hello")?;
+//! let plaintext = decrypt_message_to_me(&container, passphrase, &message)?;
+//! ```
+//!
+//! Messages are ECIES: an ephemeral X25519 agreement, HKDF-SHA256 salted
+//! with both public keys, and XChaCha20-Poly1305. The layout is
+//!
+//! | Bytes | Field |
+//! |-------|-------|
+//! | 1 | version (1) |
+//! | 32 | ephemeral X25519 public key |
+//! | 24 | nonce |
+//! | rest | ciphertext and tag |
+//!
+//! and the version and ephemeral key are authenticated as associated data.
+//! The recipient's scalar, the shared secret, and the plaintext live in
+//! [`SecureBuffer`]s.
+
+use chacha20poly1305::aead::{AeadInPlace, KeyInit};
+use chacha20poly1305::{Tag, XChaCha20Poly1305, XNonce};
+use curve25519_dalek::edwards::CompressedEdwardsY;
+use ed25519_dalek::SigningKey;
+use hkdf::Hkdf;
+use sha2::Sha256;
+use x25519_dalek::{EphemeralSecret, PublicKey, StaticSecret};
+use zeroize::Zeroizing;
+
+use crate::backend::ContainerBackend;
+use crate::crypto::{get_locking_mode, EncryptedKeyContainer, ED25519_SEED_SIZE};
+use crate::entropy::{fill_random, EntropyRng};
+use crate::error::SignerError;
+use crate::secure_buffer::SecureBuffer;
+
+/// Message format version
+const ECIES_VERSION: u8 = 1;
+
+/// HKDF info string for the message key
+const HKDF_INFO: &[u8] = b"coldstar-ecies-v1";
+
+const HEADER_SIZE: usize = 1 + 32;
+const NONCE_SIZE: usize = 24;
+const TAG_SIZE: usize = 16;
+
+/// X25519 public key for an Ed25519 public key
+///
+/// Fails for bytes that are not a point on the curve, or that are a
+/// low-order point.
+pub fn ed25519_to_x25519_public(public_key: &[u8; 32]) -> Result<[u8; 32], SignerError> {
+    let point = CompressedEdwardsY(*public_key)
+        .decompress()
+        .filter(|point| !point.is_small_order())
+        .ok_or_else(|| SignerError::SigningFailed("not a valid Ed25519 public key".to_string()))?;
+    Ok(point.to_montgomery().to_bytes())
+}
+
+/// X25519 scalar for an Ed25519 seed, in a [`SecureBuffer`]
+pub(crate) fn ed25519_to_x25519_secret(seed: &SecureBuffer) -> Result<SecureBuffer, SignerError> {
+    let seed: &[u8; ED25519_SEED_SIZE] = seed
+        .as_slice()
+        .try_into()
+        .map_err(|_| SignerError::InvalidKeyFormat(seed.len()))?;
+    let scalar = Zeroizing::new(SigningKey::from_bytes(seed).to_scalar_bytes());
+    SecureBuffer::from_slice_with_mode(scalar.as_ref(), get_locking_mode())
+}
+
+impl EncryptedKeyContainer {
+    /// X25519 public key for the container's Solana key, from its stored
+    /// public key (no passphrase needed)
+    pub fn x25519_public_key(&self) -> Result<[u8; 32], SignerError> {
+        let public_key = self
+            .public_key
+            .as_deref()
+            .ok_or_else(|| SignerError::ContainerError("container has no public key".to_string()))?;
+        ed25519_to_x25519_public(&decode_address(public_key)?)
+    }
+}
+
+/// Encrypt `plaintext` to the holder of a Solana address (base58 Ed25519
+/// public key)
+pub fn encrypt_message_to_pubkey(recipient: &str, plaintext: &[u8]) -> Result<Vec<u8>, SignerError> {
+    let recipient = PublicKey::from(ed25519_to_x25519_public(&decode_address(recipient)?)?);
+    let ephemeral = EphemeralSecret::random_from_rng(EntropyRng);
+    let ephemeral_public = PublicKey::from(&ephemeral);
+    let shared = ephemeral.diffie_hellman(&recipient);
+    let mut key = derive_message_key(shared.as_bytes(), &ephemeral_public, &recipient)?;
+
+    let mut message = Vec::with_capacity(HEADER_SIZE + NONCE_SIZE + plaintext.len() + TAG_SIZE);
+    message.push(ECIES_VERSION);
+    message.extend_from_slice(ephemeral_public.as_bytes());
+    let mut nonce = [0u8; NONCE_SIZE];
+    fill_random(&mut nonce)?;
+    message.extend_from_slice(&nonce);
+    message.extend_from_slice(plaintext);
+
+    let (header, body) = message.split_at_mut(HEADER_SIZE + NONCE_SIZE);
+    let tag = XChaCha20Poly1305::new_from_slice(key.as_slice())
+        .map_err(|e| SignerError::KeyDerivationFailed(e.to_string()))?
+        .encrypt_in_place_detached(XNonce::from_slice(&nonce), &header[..HEADER_SIZE], body)
+        .map_err(|_| SignerError::SigningFailed("message encryption failed".to_string()))?;
+    key.zeroize();
+    message.extend_from_slice(&tag);
+    Ok(message)
+}
+
+/// Decrypt a message sent to the container's Solana key
+pub fn decrypt_message_to_me(
+    container: &EncryptedKeyContainer,
+    passphrase: &str,
+    message: &[u8],
+) -> Result<SecureBuffer, SignerError> {
+    ContainerBackend::new(container, passphrase).decrypt_message_to_me(message)
+}
+
+impl ContainerBackend<'_> {
+    /// Decrypt a message sent to this backend's Solana key, with its
+    /// keyfile and KDF cache
+    pub fn decrypt_message_to_me(&self, message: &[u8]) -> Result<SecureBuffer, SignerError> {
+        if message.len() < HEADER_SIZE + NONCE_SIZE + TAG_SIZE {
+            return Err(SignerError::ContainerError("message is truncated".to_string()));
+        }
+        if message[0] != ECIES_VERSION {
+            return Err(SignerError::ContainerError(format!(
+                "unsupported message version {}",
+                message[0]
+            )));
+        }
+        let (header, rest) = message.split_at(HEADER_SIZE);
+        let (nonce, rest) = rest.split_at(NONCE_SIZE);
+        let (ciphertext, tag) = rest.split_at(rest.len() - TAG_SIZE);
+        let ephemeral_public = PublicKey::from(<[u8; 32]>::try_from(&header[1..]).expect("32-byte header field"));
+
+        let mut seed = self.decrypt_key()?;
+        let scalar = ed25519_to_x25519_secret(&seed);
+        seed.zeroize();
+        let mut scalar = scalar?;
+        let secret = StaticSecret::from(<[u8; 32]>::try_from(scalar.as_slice()).expect("32-byte scalar"));
+        scalar.zeroize();
+        let recipient = PublicKey::from(&secret);
+        let shared = secret.diffie_hellman(&ephemeral_public);
+        drop(secret);
+        if !shared.was_contributory() {
+            return Err(SignerError::DecryptionFailed);
+        }
+        let mut key = derive_message_key(shared.as_bytes(), &ephemeral_public, &recipient)?;
+        drop(shared);
+
+        let mut plaintext = SecureBuffer::from_slice_with_mode(ciphertext, get_locking_mode())?;
+        let result = XChaCha20Poly1305::new_from_slice(key.as_slice())
+            .map_err(|e| SignerError::KeyDerivationFailed(e.to_string()))?
+            .decrypt_in_place_detached(XNonce::from_slice(nonce), header, plaintext.as_mut_slice(), Tag::from_slice(tag));
+        key.zeroize();
+        if result.is_err() {
+            plaintext.zeroize();
+            return Err(SignerError::DecryptionFailed);
+        }
+        Ok(plaintext)
+    }
+}
+
+fn decode_address(address: &str) -> Result<[u8; 32], SignerError> {
+    bs58::decode(address)
+        .into_vec()?
+        .try_into()
+        .map_err(|_| SignerError::Base58Error("expected a 32-byte public key".to_string()))
+}
+
+/// HKDF-SHA256 over the shared secret, salted with both public keys
+fn derive_message_key(
+    shared: &[u8; 32],
+    ephemeral_public: &PublicKey,
+    recipient_public: &PublicKey,
+) -> Result<SecureBuffer, SignerError> {
+    let mut salt = [0u8; 64];
+    salt[..32].copy_from_slice(ephemeral_public.as_bytes());
+    salt[32..].copy_from_slice(recipient_public.as_bytes());
+
+    let mut key = SecureBuffer::with_mode(32, get_locking_mode())?;
+    Hkdf::<Sha256>::new(Some(&salt), shared)
+        .expand(HKDF_INFO, key.as_mut_slice())
+        .map_err(|e| SignerError::KeyDerivationFailed(e.to_string()))?;
+    Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::Cipher;
+    use crate::kdf::KdfParams;
+
+    #[test]
+    fn test_x25519_conversion_agrees() {
+        std::env::set_var("SIGNER_ALLOW_INSECURE_MEMORY", "1");
+        let seed = SecureBuffer::from_slice(&[9u8; 32]).unwrap();
+        let public = SigningKey::from_bytes(&[9u8; 32]).verifying_key().to_bytes();
+        let scalar = ed25519_to_x25519_secret(&seed).unwrap();
+        let secret = StaticSecret::from(<[u8; 32]>::try_from(scalar.as_slice()).unwrap());
+        assert_eq!(PublicKey::from(&secret).to_bytes(), ed25519_to_x25519_public(&public).unwrap());
+        // The identity point has small order
+        let mut identity = [0u8; 32];
+        identity[0] = 1;
+        assert!(ed25519_to_x25519_public(&identity).is_err());
+    }
+
+    #[test]
+    fn test_message_round_trip() {
+        std::env::set_var("SIGNER_ALLOW_INSECURE_MEMORY", "1");
+        let container =
+            EncryptedKeyContainer::encrypt_with_kdf(&[5u8; 32], "pw", Cipher::Aes256Gcm, KdfParams::MINIMUM).unwrap();
+        let address = container.public_key.clone().unwrap();
+        assert!(container.x25519_public_key().is_ok());
+
+        let message = encrypt_message_to_pubkey(&address, b"for your eyes only").unwrap();
+        assert_eq!(message.len(), HEADER_SIZE + NONCE_SIZE + 18 + TAG_SIZE);
+        let plaintext = decrypt_message_to_me(&container, "pw", &message).unwrap();
+        assert_eq!(plaintext.as_slice(), b"for your eyes only");
+
+        // The ephemeral key is authenticated, and other keys can't decrypt
+        let mut tampered = message.clone();
+        tampered[1] ^= 1;
+        assert!(decrypt_message_to_me(&container, "pw", &tampered).is_err());
+        let other =
+            EncryptedKeyContainer::encrypt_with_kdf(&[6u8; 32], "pw", Cipher::Aes256Gcm, KdfParams::MINIMUM).unwrap();
+        assert!(matches!(decrypt_message_to_me(&other, "pw", &message), Err(SignerError::DecryptionFailed)));
+    }
+}
@@ -0,0 +1,300 @@
+//! Ed25519ph and Ed25519ctx (RFC 8032 §5.1)
+//!
+//! Plain Ed25519 signs the message itself, with no domain separation. The
+//! two other RFC 8032 variants put a `dom2(flag, context)` prefix in both
+//! signing hashes:
+//!
+//! - **Ed25519ph** signs the SHA-512 digest of the message, so a large
+//!   payload can be hashed where it lives and only the 64-byte digest sent
+//!   to the signer. The context string (0–255 bytes) is optional.
+//! - **Ed25519ctx** signs the message with a mandatory context string
+//!   (1–255 bytes), so a signature for one protocol cannot be replayed in
+//!   another that uses a different context.
+//!
+//! Both use the container's ordinary Ed25519 key; neither signature
+//! verifies as a plain Ed25519 signature, or as the other variant.
+
+use alloc::format;
+use alloc::vec::Vec;
+
+use curve25519_dalek::edwards::{CompressedEdwardsY, EdwardsPoint};
+use curve25519_dalek::scalar::Scalar;
+use ed25519_dalek::hazmat::ExpandedSecretKey;
+use ed25519_dalek::VerifyingKey;
+use sha2::{Digest, Sha512};
+
+use crate::crypto::{
+    faulty_signature, get_locking_mode, verify_after_signing, EncryptedKeyContainer, SigningResult, ED25519_SEED_SIZE,
+};
+use crate::entropy::health_check;
+use crate::error::SignerError;
+use crate::secure_buffer::SecureBuffer;
+
+/// Longest context string RFC 8032 allows
+pub const MAX_CONTEXT_LEN: usize = 255;
+
+/// Which dom2-prefixed variant to use
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Variant {
+    /// Ed25519ctx (`phflag = 0`)
+    Context,
+    /// Ed25519ph (`phflag = 1`)
+    Prehashed,
+}
+
+/// SHA-512 of a message, the digest Ed25519ph signs
+pub fn prehash(message: &[u8]) -> [u8; 64] {
+    Sha512::digest(message).into()
+}
+
+/// The dom2 prefix, after checking the context length
+fn dom2(variant: Variant, context: &[u8]) -> Result<Vec<u8>, SignerError> {
+    let min = if variant == Variant::Context { 1 } else { 0 };
+    if context.len() < min || context.len() > MAX_CONTEXT_LEN {
+        return Err(SignerError::SigningFailed(format!(
+            "context must be {}-{} bytes, got {}",
+            min,
+            MAX_CONTEXT_LEN,
+            context.len()
+        )));
+    }
+    let mut prefix = b"SigEd25519 no Ed25519 collisions".to_vec();
+    prefix.push(variant as u8);
+    prefix.push(context.len() as u8);
+    prefix.extend_from_slice(context);
+    Ok(prefix)
+}
+
+fn sign_dom2(
+    secure_key: &SecureBuffer,
+    variant: Variant,
+    context: &[u8],
+    message: &[u8],
+) -> Result<SigningResult, SignerError> {
+    let dom2 = dom2(variant, context)?;
+    let seed: &[u8; ED25519_SEED_SIZE] = secure_key
+        .as_slice()
+        .try_into()
+        .map_err(|_| SignerError::InvalidKeyFormat(secure_key.len()))?;
+    health_check()?;
+
+    // Zeroized on drop
+    let expanded = ExpandedSecretKey::from(seed);
+    let public_key = EdwardsPoint::mul_base(&expanded.scalar).compress();
+
+    let r = Scalar::from_hash(
+        Sha512::new()
+            .chain_update(&dom2)
+            .chain_update(expanded.hash_prefix)
+            .chain_update(message),
+    );
+    let big_r = EdwardsPoint::mul_base(&r).compress();
+    let k = Scalar::from_hash(
+        Sha512::new()
+            .chain_update(&dom2)
+            .chain_update(big_r.as_bytes())
+            .chain_update(public_key.as_bytes())
+            .chain_update(message),
+    );
+    let s = k * expanded.scalar + r;
+
+    let mut signature = [0u8; 64];
+    signature[..32].copy_from_slice(big_r.as_bytes());
+    signature[32..].copy_from_slice(s.as_bytes());
+    if verify_after_signing() && !verify_dom2(public_key.as_bytes(), variant, context, message, &signature) {
+        return Err(faulty_signature("Ed25519"));
+    }
+    Ok(SigningResult {
+        signature: bs58::encode(signature).into_string(),
+        signed_transaction: None,
+        public_key: bs58::encode(public_key.as_bytes()).into_string(),
+        counter: None,
+    })
+}
+
+fn verify_dom2(public_key: &[u8; 32], variant: Variant, context: &[u8], message: &[u8], signature: &[u8; 64]) -> bool {
+    let Ok(dom2) = dom2(variant, context) else {
+        return false;
+    };
+    let Ok(verifying_key) = VerifyingKey::from_bytes(public_key) else {
+        return false;
+    };
+    let big_r = CompressedEdwardsY(signature[..32].try_into().expect("32 bytes"));
+    let Some(s) = Option::<Scalar>::from(Scalar::from_canonical_bytes(signature[32..].try_into().expect("32 bytes")))
+    else {
+        return false;
+    };
+    let k = Scalar::from_hash(
+        Sha512::new()
+            .chain_update(&dom2)
+            .chain_update(big_r.as_bytes())
+            .chain_update(public_key)
+            .chain_update(message),
+    );
+    // [s]B - [k]A == R
+    let minus_a = -verifying_key.to_edwards();
+    EdwardsPoint::vartime_double_scalar_mul_basepoint(&k, &minus_a, &s).compress() == big_r
+}
+
+/// Sign a SHA-512 digest (Ed25519ph) with a key in a secure buffer
+pub(crate) fn sign_prehashed_with_secure_key(
+    secure_key: &SecureBuffer,
+    digest: &[u8; 64],
+    context: &[u8],
+) -> Result<SigningResult, SignerError> {
+    sign_dom2(secure_key, Variant::Prehashed, context, digest)
+}
+
+/// Sign a message with a context string (Ed25519ctx) with a key in a secure buffer
+pub(crate) fn sign_with_context_with_secure_key(
+    secure_key: &SecureBuffer,
+    message: &[u8],
+    context: &[u8],
+) -> Result<SigningResult, SignerError> {
+    sign_dom2(secure_key, Variant::Context, context, message)
+}
+
+/// Decrypt a key container and sign a SHA-512 digest with Ed25519ph
+///
+/// `digest` is [`prehash`] of the payload; `context` may be empty.
+pub fn decrypt_and_sign_prehashed(
+    container_json: &str,
+    passphrase: &str,
+    digest: &[u8; 64],
+    context: &[u8],
+) -> Result<SigningResult, SignerError> {
+    let container = EncryptedKeyContainer::from_json(container_json)?;
+    let mut secure_key = container.decrypt_key(passphrase)?;
+    let result = sign_prehashed_with_secure_key(&secure_key, digest, context);
+    secure_key.zeroize();
+    result
+}
+
+/// Decrypt a key container and sign a message with Ed25519ctx
+///
+/// `context` must be 1-255 bytes.
+pub fn decrypt_and_sign_with_context(
+    container_json: &str,
+    passphrase: &str,
+    message: &[u8],
+    context: &[u8],
+) -> Result<SigningResult, SignerError> {
+    let container = EncryptedKeyContainer::from_json(container_json)?;
+    let mut secure_key = container.decrypt_key(passphrase)?;
+    let result = sign_with_context_with_secure_key(&secure_key, message, context);
+    secure_key.zeroize();
+    result
+}
+
+/// Sign a SHA-512 digest with Ed25519ph using a raw private key
+///
+/// # Security Warning
+/// Prefer using decrypt_and_sign_prehashed() for the full secure workflow.
+pub fn sign_transaction_prehashed(
+    private_key: &[u8],
+    digest: &[u8; 64],
+    context: &[u8],
+) -> Result<SigningResult, SignerError> {
+    let mut secure_key = SecureBuffer::from_slice_with_mode(private_key, get_locking_mode())?;
+    let result = sign_prehashed_with_secure_key(&secure_key, digest, context);
+    secure_key.zeroize();
+    result
+}
+
+/// Sign a message with Ed25519ctx using a raw private key
+///
+/// # Security Warning
+/// Prefer using decrypt_and_sign_with_context() for the full secure workflow.
+pub fn sign_transaction_with_context(
+    private_key: &[u8],
+    message: &[u8],
+    context: &[u8],
+) -> Result<SigningResult, SignerError> {
+    let mut secure_key = SecureBuffer::from_slice_with_mode(private_key, get_locking_mode())?;
+    let result = sign_with_context_with_secure_key(&secure_key, message, context);
+    secure_key.zeroize();
+    result
+}
+
+/// Verify an Ed25519ph signature over a SHA-512 digest
+pub fn verify_prehashed(public_key: &[u8; 32], digest: &[u8; 64], context: &[u8], signature: &[u8; 64]) -> bool {
+    verify_dom2(public_key, Variant::Prehashed, context, digest, signature)
+}
+
+/// Verify an Ed25519ctx signature
+pub fn verify_with_context(public_key: &[u8; 32], message: &[u8], context: &[u8], signature: &[u8; 64]) -> bool {
+    verify_dom2(public_key, Variant::Context, context, message, signature)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(result: &SigningResult) -> ([u8; 32], [u8; 64]) {
+        let public_key = bs58::decode(&result.public_key).into_vec().unwrap();
+        let signature = bs58::decode(&result.signature).into_vec().unwrap();
+        (public_key.try_into().unwrap(), signature.try_into().unwrap())
+    }
+
+    #[test]
+    fn test_rfc8032_vectors() {
+        std::env::set_var("SIGNER_ALLOW_INSECURE_MEMORY", "1");
+
+        // RFC 8032 §7.3, Ed25519ph of "abc"
+        let key = hex::decode("833fe62409237b9d62ec77587520911e9a759cec1d19755b7da901b96dca3d42").unwrap();
+        let digest = prehash(b"abc");
+        let (public_key, signature) = decode(&sign_transaction_prehashed(&key, &digest, b"").unwrap());
+        assert_eq!(
+            hex::encode(public_key),
+            "ec172b93ad5e563bf4932c70e1245034c35467ef2efd4d64ebf819683467e2bf"
+        );
+        assert_eq!(
+            hex::encode(signature),
+            "98a70222f0b8121aa9d30f813d683f809e462b469c7ff87639499bb94e6dae41\
+             31f85042463c2a355a2003d062adf5aaa10b8c61e636062aaad11c2a26083406"
+        );
+        assert!(verify_prehashed(&public_key, &digest, b"", &signature));
+        assert!(!verify_prehashed(&public_key, &digest, b"other", &signature));
+        assert!(!verify_with_context(&public_key, &digest, b"x", &signature));
+
+        // RFC 8032 §7.2, Ed25519ctx with context "foo"
+        let key = hex::decode("0305334e381af78f141cb666f6199f57bc3495335a256a95bd2a55bf546663f6").unwrap();
+        let message = hex::decode("f726936d19c800494e3fdaff20b276a8").unwrap();
+        let (public_key, signature) = decode(&sign_transaction_with_context(&key, &message, b"foo").unwrap());
+        assert_eq!(
+            hex::encode(signature),
+            "55a4cc2f70a54e04288c5f4cd1e45a7bb520b36292911876cada7323198dd87a\
+             8b36950b95130022907a7fb7c4e9b2d5f6cca685a587b4b21f4b888e4e7edb0d"
+        );
+        assert!(verify_with_context(&public_key, &message, b"foo", &signature));
+        assert!(!verify_with_context(&public_key, &message, b"bar", &signature));
+
+        // Ed25519ctx requires a context
+        assert!(sign_transaction_with_context(&key, &message, b"").is_err());
+        assert!(sign_transaction_prehashed(&key, &digest, &[0; 256]).is_err());
+    }
+
+    #[test]
+    fn test_decrypt_and_sign_prehashed() {
+        use crate::crypto::Cipher;
+        use crate::kdf::KdfParams;
+
+        std::env::set_var("SIGNER_ALLOW_INSECURE_MEMORY", "1");
+        let container =
+            EncryptedKeyContainer::encrypt_with_kdf(&[7u8; 32], "pw", Cipher::Aes256Gcm, KdfParams::MINIMUM).unwrap();
+        let json = container.to_json().unwrap();
+
+        let digest = prehash(b"payload");
+        let result = decrypt_and_sign_prehashed(&json, "pw", &digest, b"coldstar").unwrap();
+        assert_eq!(Some(&result.public_key), container.public_key.as_ref());
+        let (public_key, signature) = decode(&result);
+        // Cross-checked against an independent RFC 8032 implementation
+        assert_eq!(
+            hex::encode(signature),
+            "88cf3e07c4fd52bce63e88308301ed66e4d5a74ba7840de40fbd1a377cc0cb04\
+             4933b5be79607a4f9bd93e8b94cbfe44b1fa1a7321a90f3018bcef6f36e7ea0b"
+        );
+        assert!(verify_prehashed(&public_key, &digest, b"coldstar", &signature));
+        assert!(decrypt_and_sign_prehashed(&json, "wrong", &digest, b"coldstar").is_err());
+    }
+}
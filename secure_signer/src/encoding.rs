@@ -0,0 +1,134 @@
+//! Output encodings for signing results
+//!
+//! Signing results default to the Solana conventions (base58 signatures
+//! and public keys, base64 transactions). Callers that store or forward
+//! them in another form can ask for it up front with an
+//! [`OutputEncoding`] instead of decoding and re-encoding every result.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::SignerError;
+
+/// A text encoding for binary values
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Encoding {
+    /// Bitcoin-alphabet base58
+    Base58,
+    /// Standard base64 with padding
+    Base64,
+    /// Lowercase hex without a `0x` prefix
+    Hex,
+}
+
+impl Encoding {
+    /// Encode bytes
+    pub fn encode(&self, bytes: &[u8]) -> String {
+        match self {
+            Encoding::Base58 => bs58::encode(bytes).into_string(),
+            Encoding::Base64 => base64::Engine::encode(&base64::engine::general_purpose::STANDARD, bytes),
+            Encoding::Hex => hex::encode(bytes),
+        }
+    }
+
+    /// Decode a string (hex may carry a `0x` prefix)
+    pub fn decode(&self, value: &str) -> Result<Vec<u8>, SignerError> {
+        match self {
+            Encoding::Base58 => Ok(bs58::decode(value).into_vec()?),
+            Encoding::Base64 => Ok(base64::Engine::decode(&base64::engine::general_purpose::STANDARD, value)?),
+            Encoding::Hex => hex::decode(value.strip_prefix("0x").unwrap_or(value))
+                .map_err(|e| SignerError::SerializationError(format!("Hex decoding error: {}", e))),
+        }
+    }
+}
+
+impl FromStr for Encoding {
+    type Err = SignerError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "base58" => Ok(Encoding::Base58),
+            "base64" => Ok(Encoding::Base64),
+            "hex" => Ok(Encoding::Hex),
+            other => Err(SignerError::SerializationError(format!(
+                "unknown encoding '{}' (expected base58, base64, or hex)",
+                other
+            ))),
+        }
+    }
+}
+
+/// Encodings for the fields of a [`SigningResult`](crate::SigningResult)
+///
+/// Missing fields in JSON fall back to the defaults.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(default)]
+pub struct OutputEncoding {
+    /// Signature encoding (default base58)
+    pub signature: Encoding,
+    /// Public key encoding (default base58)
+    pub public_key: Encoding,
+    /// Signed transaction encoding (default base64)
+    pub transaction: Encoding,
+}
+
+impl OutputEncoding {
+    /// The default encodings: base58 signature and public key, base64 transaction
+    pub const DEFAULT: OutputEncoding = OutputEncoding {
+        signature: Encoding::Base58,
+        public_key: Encoding::Base58,
+        transaction: Encoding::Base64,
+    };
+
+    /// Use one encoding for every field
+    pub fn all(encoding: Encoding) -> Self {
+        Self {
+            signature: encoding,
+            public_key: encoding,
+            transaction: encoding,
+        }
+    }
+}
+
+impl Default for OutputEncoding {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// CRC-16/XMODEM, the checksum of TON addresses and Stellar strkeys
+pub(crate) fn crc16_xmodem(data: &[u8]) -> u16 {
+    let mut crc = 0u16;
+    for &byte in data {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 { (crc << 1) ^ 0x1021 } else { crc << 1 };
+        }
+    }
+    crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encodings_roundtrip() {
+        let bytes = [0u8, 1, 2, 254, 255];
+        for encoding in [Encoding::Base58, Encoding::Base64, Encoding::Hex] {
+            assert_eq!(encoding.decode(&encoding.encode(&bytes)).unwrap(), bytes);
+        }
+        assert_eq!(Encoding::Hex.decode("0x00ff").unwrap(), vec![0, 255]);
+        assert_eq!("HEX".parse::<Encoding>().unwrap(), Encoding::Hex);
+        assert!("base32".parse::<Encoding>().is_err());
+
+        let partial: OutputEncoding = serde_json::from_str(r#"{"signature":"hex"}"#).unwrap();
+        assert_eq!(partial.signature, Encoding::Hex);
+        assert_eq!(partial.transaction, Encoding::Base64);
+    }
+}
@@ -0,0 +1,401 @@
+//! Entropy sources and their health checks
+//!
+//! Every salt, nonce, and generated key comes from the process-wide
+//! [`EntropySource`], which [`set_entropy_source`] replaces:
+//!
+//! - [`OsEntropy`]: the operating system RNG (the default, with `std`).
+//!   Without `std` there is no default: until a source is installed,
+//!   everything that needs randomness fails.
+//! - [`HmacDrbg`]: an SP 800-90A HMAC_DRBG (SHA-256) seeded by the
+//!   caller, for deterministic test vectors. Never use it for real keys.
+//! - [`MixedEntropy`]: the XOR of several sources, such as a hardware TRNG
+//!   and [`OsEntropy`]. Its output is as strong as its best source, provided
+//!   the sources are independent.
+//!
+//! A hardware TRNG plugs in by implementing [`EntropySource`]. APIs that
+//! take an RNG get [`EntropyRng`], a `rand_core` adapter over the
+//! current source.
+//!
+//! A broken RNG is catastrophic for a signer: repeated salts and AEAD
+//! nonces break container encryption, and a repeated Schnorr or MuSig2
+//! nonce reveals the private key. Before generating randomness, and before
+//! every signature, a fresh sample from the current entropy source is run
+//! through the two continuous health tests of NIST SP 800-90B (section
+//! 4.4):
+//!
+//! - **Repetition count test**: fails if one byte value repeats
+//!   [`REPETITION_CUTOFF`] times in a row.
+//! - **Adaptive proportion test**: fails if the first byte of a
+//!   [`PROPORTION_WINDOW`]-byte window occurs [`PROPORTION_CUTOFF`] or more
+//!   times in it.
+//!
+//! The cutoffs are loose enough for a slightly biased hardware source, so a
+//! failure means the source is stuck or badly biased, not unlucky.
+//! Failures return [`SignerError::EntropyError`] and nothing is generated
+//! or signed.
+
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use hmac::{Hmac, Mac};
+#[cfg(feature = "std")]
+use rand::rngs::OsRng;
+use rand::{CryptoRng, RngCore};
+use sha2::Sha256;
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::error::SignerError;
+use crate::sync::Mutex;
+
+/// Run length of one byte value that fails the repetition count test
+///
+/// SP 800-90B's `1 + ceil(-log2(alpha) / H)` for full entropy (H = 8 bits
+/// per byte) at a false-positive rate alpha = 2^-40.
+pub const REPETITION_CUTOFF: usize = 6;
+
+/// Sample size of the adaptive proportion test
+pub const PROPORTION_WINDOW: usize = 512;
+
+/// Occurrences of the first byte in a window that fail the adaptive
+/// proportion test
+///
+/// SP 800-90B's `1 + CRITBINOM(W, 2^-H, 1 - alpha)` for W = 512 at a
+/// false-positive rate alpha = 2^-20, with an assumed min-entropy of
+/// H = 6.75 bits per byte (any H from 6.7 to 6.8 gives 19). Full-entropy
+/// bytes (H = 8) would give a cutoff of 13; the lower H keeps sources
+/// that are slightly short of full entropy from failing.
+pub const PROPORTION_CUTOFF: usize = 19;
+
+/// SP 800-90B repetition count test over `sample`
+pub fn repetition_count_test(sample: &[u8]) -> Result<(), SignerError> {
+    let mut run = 0usize;
+    let mut previous = None;
+    for &byte in sample {
+        run = if previous == Some(byte) { run + 1 } else { 1 };
+        previous = Some(byte);
+        if run >= REPETITION_CUTOFF {
+            return Err(SignerError::EntropyError(format!(
+                "repetition count test failed: byte {:#04x} repeated {} times",
+                byte, run
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// SP 800-90B adaptive proportion test over each full window of `sample`
+pub fn adaptive_proportion_test(sample: &[u8]) -> Result<(), SignerError> {
+    for window in sample.chunks_exact(PROPORTION_WINDOW) {
+        let count = window.iter().filter(|&&byte| byte == window[0]).count();
+        if count >= PROPORTION_CUTOFF {
+            return Err(SignerError::EntropyError(format!(
+                "adaptive proportion test failed: byte {:#04x} occurred {} times in {}",
+                window[0], count, PROPORTION_WINDOW
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Where the signer's randomness comes from
+pub trait EntropySource: Send + Sync {
+    /// Short description for logs and self-test reports
+    fn name(&self) -> &str;
+
+    /// Fill `dest` entirely, or fail without generating anything
+    fn fill(&self, dest: &mut [u8]) -> Result<(), SignerError>;
+}
+
+/// The operating system RNG
+#[cfg(feature = "std")]
+pub struct OsEntropy;
+
+#[cfg(feature = "std")]
+impl EntropySource for OsEntropy {
+    fn name(&self) -> &str {
+        "os"
+    }
+
+    fn fill(&self, dest: &mut [u8]) -> Result<(), SignerError> {
+        OsRng
+            .try_fill_bytes(dest)
+            .map_err(|e| SignerError::EntropyError(format!("OS RNG failed: {}", e)))
+    }
+}
+
+struct DrbgState {
+    key: Zeroizing<[u8; 32]>,
+    value: Zeroizing<[u8; 32]>,
+}
+
+impl DrbgState {
+    fn hmac(&self, parts: &[&[u8]]) -> Zeroizing<[u8; 32]> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&*self.key).expect("HMAC takes any key length");
+        for part in parts {
+            mac.update(part);
+        }
+        Zeroizing::new(mac.finalize().into_bytes().into())
+    }
+
+    /// HMAC_DRBG_Update (SP 800-90A, 10.1.2.2)
+    fn update(&mut self, provided: &[u8]) {
+        for (round, separator) in [0x00u8, 0x01].into_iter().enumerate() {
+            if round == 1 && provided.is_empty() {
+                break;
+            }
+            self.key = self.hmac(&[&*self.value, &[separator], provided]);
+            self.value = self.hmac(&[&*self.value]);
+        }
+    }
+}
+
+/// SP 800-90A HMAC_DRBG with SHA-256, seeded by the caller
+///
+/// The same seed always produces the same output, which makes it useful
+/// for reproducible test vectors and nothing else.
+pub struct HmacDrbg {
+    state: Mutex<DrbgState>,
+}
+
+impl HmacDrbg {
+    /// Instantiate from `seed` (entropy input, nonce, and personalization
+    /// string concatenated)
+    pub fn new(seed: &[u8]) -> Self {
+        let mut state = DrbgState {
+            key: Zeroizing::new([0x00; 32]),
+            value: Zeroizing::new([0x01; 32]),
+        };
+        state.update(seed);
+        Self {
+            state: Mutex::new(state),
+        }
+    }
+}
+
+impl EntropySource for HmacDrbg {
+    fn name(&self) -> &str {
+        "hmac-drbg"
+    }
+
+    fn fill(&self, dest: &mut [u8]) -> Result<(), SignerError> {
+        let mut state = self.state.lock();
+        for chunk in dest.chunks_mut(32) {
+            state.value = state.hmac(&[&*state.value]);
+            chunk.copy_from_slice(&state.value[..chunk.len()]);
+        }
+        state.update(&[]);
+        Ok(())
+    }
+}
+
+/// The XOR of several independent sources
+pub struct MixedEntropy {
+    name: String,
+    sources: Vec<Box<dyn EntropySource>>,
+}
+
+impl MixedEntropy {
+    pub fn new(sources: Vec<Box<dyn EntropySource>>) -> Self {
+        let names: Vec<&str> = sources.iter().map(|source| source.name()).collect();
+        Self {
+            name: format!("mixed({})", names.join(", ")),
+            sources,
+        }
+    }
+}
+
+impl EntropySource for MixedEntropy {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Fails if any source fails, so a dead TRNG is noticed rather than
+    /// silently mixed out
+    fn fill(&self, dest: &mut [u8]) -> Result<(), SignerError> {
+        if self.sources.is_empty() {
+            return Err(SignerError::EntropyError("no entropy sources to mix".to_string()));
+        }
+        dest.fill(0);
+        let mut buffer = Zeroizing::new(vec![0u8; dest.len()]);
+        for source in &self.sources {
+            source.fill(&mut buffer)?;
+            dest.iter_mut().zip(buffer.iter()).for_each(|(out, byte)| *out ^= byte);
+        }
+        Ok(())
+    }
+}
+
+/// Stands in for a missing source on targets without an OS RNG
+#[cfg(not(feature = "std"))]
+struct NoEntropy;
+
+#[cfg(not(feature = "std"))]
+impl EntropySource for NoEntropy {
+    fn name(&self) -> &str {
+        "none"
+    }
+
+    fn fill(&self, _dest: &mut [u8]) -> Result<(), SignerError> {
+        Err(SignerError::EntropyError(
+            "no entropy source installed; call set_entropy_source".to_string(),
+        ))
+    }
+}
+
+static SOURCE: Mutex<Option<Arc<dyn EntropySource>>> = Mutex::new(None);
+
+/// The process-wide entropy source
+pub fn entropy_source() -> Arc<dyn EntropySource> {
+    let source = SOURCE.lock();
+    #[cfg(feature = "std")]
+    return source.clone().unwrap_or_else(|| Arc::new(OsEntropy));
+    #[cfg(not(feature = "std"))]
+    return source.clone().unwrap_or_else(|| Arc::new(NoEntropy));
+}
+
+/// Replace the process-wide entropy source
+///
+/// Affects every later salt, nonce, and key in the process, so set it once
+/// at startup.
+pub fn set_entropy_source(source: Arc<dyn EntropySource>) {
+    *SOURCE.lock() = Some(source);
+}
+
+/// Go back to the OS RNG (or, without `std`, to no source)
+pub fn reset_entropy_source() {
+    *SOURCE.lock() = None;
+}
+
+/// Draw a fresh sample from the current source and run both health tests
+/// on it
+///
+/// Also fails once a [`selftest`](crate::selftest::selftest) has refused
+/// signing.
+pub fn health_check() -> Result<(), SignerError> {
+    crate::selftest::check()?;
+    let mut sample = [0u8; PROPORTION_WINDOW];
+    entropy_source().fill(&mut sample)?;
+    let result = repetition_count_test(&sample).and_then(|_| adaptive_proportion_test(&sample));
+    sample.zeroize();
+    result
+}
+
+/// Fill `dest` from the current source after a passing health check
+///
+/// Use this for every salt, nonce, and key generated by the signer.
+pub fn fill_random(dest: &mut [u8]) -> Result<(), SignerError> {
+    health_check()?;
+    entropy_source().fill(dest)
+}
+
+/// `rand_core` RNG over the current source, for APIs that take one
+///
+/// Like `OsRng`, `fill_bytes` panics if the source fails; `try_fill_bytes`
+/// reports it.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct EntropyRng;
+
+impl RngCore for EntropyRng {
+    fn next_u32(&mut self) -> u32 {
+        rand_core::impls::next_u32_via_fill(self)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        rand_core::impls::next_u64_via_fill(self)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        if let Err(e) = self.try_fill_bytes(dest) {
+            panic!("entropy source failed: {}", e);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        #[cfg(feature = "std")]
+        return entropy_source().fill(dest).map_err(rand::Error::new);
+        // `rand::Error` carries only a code without `std`
+        #[cfg(not(feature = "std"))]
+        return entropy_source().fill(dest).map_err(|_| {
+            rand::Error::from(core::num::NonZeroU32::new(rand::Error::CUSTOM_START).expect("non-zero"))
+        });
+    }
+}
+
+impl CryptoRng for EntropyRng {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_health_tests_reject_broken_sources() {
+        // The live source passes, and fills its output
+        health_check().unwrap();
+        let mut bytes = [0u8; 64];
+        fill_random(&mut bytes).unwrap();
+        assert_ne!(bytes, [0u8; 64]);
+
+        // A stuck source
+        let stuck = [0x42u8; PROPORTION_WINDOW];
+        assert!(matches!(repetition_count_test(&stuck), Err(SignerError::EntropyError(_))));
+
+        // These tests only catch gross failures: a counter passes both
+        let counter: Vec<u8> = (0..PROPORTION_WINDOW).map(|i| i as u8).collect();
+        repetition_count_test(&counter).unwrap();
+        adaptive_proportion_test(&counter).unwrap();
+
+        // A biased source without long runs: 0x00 in every other byte
+        let biased: Vec<u8> = (0..PROPORTION_WINDOW)
+            .map(|i| if i % 2 == 0 { 0 } else { i as u8 | 1 })
+            .collect();
+        repetition_count_test(&biased).unwrap();
+        assert!(matches!(adaptive_proportion_test(&biased), Err(SignerError::EntropyError(_))));
+    }
+
+    #[test]
+    fn test_hmac_drbg_is_deterministic() {
+        let mut first = [0u8; 40];
+        let mut second = [0u8; 40];
+        HmacDrbg::new(b"seed").fill(&mut first).unwrap();
+        HmacDrbg::new(b"seed").fill(&mut second).unwrap();
+        assert_eq!(first, second);
+        assert_eq!(
+            hex::encode(first),
+            "945418b8333283ae441104ff0af8ab77c755914dbcd4971f9db434098d72cc5fbcb6778fbaa207c9"
+        );
+
+        // Later requests continue the stream rather than repeat it
+        let drbg = HmacDrbg::new(b"seed");
+        drbg.fill(&mut first).unwrap();
+        drbg.fill(&mut second).unwrap();
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn test_mixed_source_needs_every_source() {
+        struct Failing;
+        impl EntropySource for Failing {
+            fn name(&self) -> &str {
+                "failing"
+            }
+            fn fill(&self, _dest: &mut [u8]) -> Result<(), SignerError> {
+                Err(SignerError::EntropyError("TRNG unplugged".to_string()))
+            }
+        }
+
+        // Correlated sources cancel out, which is why they must be independent
+        let mixed = MixedEntropy::new(vec![Box::new(HmacDrbg::new(b"seed")), Box::new(HmacDrbg::new(b"seed"))]);
+        let mut bytes = [0xffu8; 16];
+        mixed.fill(&mut bytes).unwrap();
+        assert_eq!(bytes, [0u8; 16]);
+        assert_eq!(mixed.name(), "mixed(hmac-drbg, hmac-drbg)");
+
+        let mixed = MixedEntropy::new(vec![Box::new(OsEntropy), Box::new(Failing)]);
+        assert!(mixed.fill(&mut bytes).is_err());
+        assert!(EntropyRng.try_fill_bytes(&mut bytes).is_ok());
+    }
+}
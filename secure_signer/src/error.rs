@@ -2,8 +2,12 @@
 //!
 //! All errors are designed to be informative without leaking sensitive data.
 
+use alloc::string::{String, ToString};
+
 use thiserror::Error;
 
+use crate::reader::ParseError;
+
 /// Errors that can occur during signing operations
 #[derive(Error, Debug)]
 pub enum SignerError {
@@ -31,6 +35,10 @@ pub enum SignerError {
     #[error("Invalid transaction format: {0}")]
     InvalidTransaction(String),
 
+    /// Malformed input rejected by a parser
+    #[error("Parse error: {0}")]
+    ParseError(#[from] ParseError),
+
     /// Serialization error
     #[error("Serialization error: {0}")]
     SerializationError(String),
@@ -50,8 +58,112 @@ pub enum SignerError {
     /// I/O error
     #[error("I/O error: {0}")]
     IoError(String),
+
+    /// Broadcasting a signed transaction failed
+    #[error("Broadcast failed: {0}")]
+    BroadcastError(String),
+
+    /// Request rejected by the signing policy
+    #[error("Policy violation: {0}")]
+    PolicyViolation(String),
+
+    /// Signing backend (hardware device, HSM, ...) reported an error
+    #[error("Backend error: {0}")]
+    BackendError(String),
+
+    /// HD key derivation failed
+    #[error("Derivation error: {0}")]
+    DerivationError(String),
+
+    /// Key ceremony or transcript verification failed
+    #[error("Key ceremony error: {0}")]
+    CeremonyError(String),
+
+    /// Fee estimation failed or returned unusable values
+    #[error("Fee estimation failed: {0}")]
+    FeeEstimationError(String),
+
+    /// Platform key store (Secure Enclave, Keychain, DPAPI, ...) failed
+    #[error("Platform key store error: {0}")]
+    PlatformError(String),
+
+    /// The binary failed its integrity self-check
+    #[error("Integrity check failed: {0}")]
+    IntegrityError(String),
+
+    /// Cloud KMS request failed or returned an unusable response
+    #[error("KMS error: {0}")]
+    KmsError(String),
+
+    /// Audit log is corrupted or could not be written
+    #[error("Audit log error: {0}")]
+    AuditError(String),
+
+    /// The OS random number generator failed or failed a health test
+    #[error("Entropy source failed health check: {0}")]
+    EntropyError(String),
+
+    /// An unlocked session was locked (system suspend, idle timeout, ...)
+    /// and must be unlocked again
+    #[error("Session locked: {0}")]
+    SessionLocked(String),
+
+    /// The container is outside its validity window
+    #[error("Container not valid: {0}")]
+    ContainerNotValid(String),
+
+    /// Idempotency key was already used for a different request
+    #[error("Idempotency key '{0}' was already used for a different request")]
+    IdempotencyConflict(String),
+
+    /// Key rotation attestation could not be produced or verified
+    #[error("Key rotation error: {0}")]
+    RotationError(String),
+
+    /// Not inside a trusted execution environment, or an attestation quote
+    /// could not be produced or checked
+    #[error("Attestation error: {0}")]
+    AttestationError(String),
+}
+
+impl SignerError {
+    /// The variant's name, without its message, for logs and metrics that
+    /// must not carry request data
+    pub fn kind(&self) -> &'static str {
+        match self {
+            SignerError::MemoryLockFailed(_) => "MemoryLockFailed",
+            SignerError::KeyDerivationFailed(_) => "KeyDerivationFailed",
+            SignerError::DecryptionFailed => "DecryptionFailed",
+            SignerError::InvalidKeyFormat(_) => "InvalidKeyFormat",
+            SignerError::SigningFailed(_) => "SigningFailed",
+            SignerError::InvalidTransaction(_) => "InvalidTransaction",
+            SignerError::ParseError(_) => "ParseError",
+            SignerError::SerializationError(_) => "SerializationError",
+            SignerError::Base58Error(_) => "Base58Error",
+            SignerError::Base64Error(_) => "Base64Error",
+            SignerError::ContainerError(_) => "ContainerError",
+            SignerError::IoError(_) => "IoError",
+            SignerError::BroadcastError(_) => "BroadcastError",
+            SignerError::PolicyViolation(_) => "PolicyViolation",
+            SignerError::BackendError(_) => "BackendError",
+            SignerError::DerivationError(_) => "DerivationError",
+            SignerError::CeremonyError(_) => "CeremonyError",
+            SignerError::FeeEstimationError(_) => "FeeEstimationError",
+            SignerError::PlatformError(_) => "PlatformError",
+            SignerError::IntegrityError(_) => "IntegrityError",
+            SignerError::KmsError(_) => "KmsError",
+            SignerError::AuditError(_) => "AuditError",
+            SignerError::EntropyError(_) => "EntropyError",
+            SignerError::SessionLocked(_) => "SessionLocked",
+            SignerError::ContainerNotValid(_) => "ContainerNotValid",
+            SignerError::IdempotencyConflict(_) => "IdempotencyConflict",
+            SignerError::RotationError(_) => "RotationError",
+            SignerError::AttestationError(_) => "AttestationError",
+        }
+    }
 }
 
+#[cfg(feature = "std")]
 impl From<std::io::Error> for SignerError {
     fn from(e: std::io::Error) -> Self {
         SignerError::IoError(e.to_string())
@@ -0,0 +1,241 @@
+//! Calldata encoding for common token calls
+//!
+//! [`TokenCall`] encodes ERC-20 `transfer` / `approve` and ERC-721
+//! `safeTransferFrom` from typed arguments, and
+//! [`TokenCall::to_transaction`] wraps the calldata in an
+//! [`EvmTransaction`] ready for [`EvmTransaction::sign_with`].
+//!
+//! Amounts are [`U256`] base units. [`U256::parse_units`] converts a
+//! human-readable amount such as `"12.5"` with the token's decimals, and
+//! refuses more fractional digits than the token has instead of rounding.
+
+use std::fmt;
+
+use crate::error::SignerError;
+use crate::evm::nft::{APPROVE, SAFE_TRANSFER_FROM_721};
+use crate::evm::transaction::{EvmTransaction, GasPricing};
+
+/// `transfer(address,uint256)` (ERC-20)
+pub const TRANSFER: [u8; 4] = [0xa9, 0x05, 0x9c, 0xbb];
+
+/// An unsigned 256-bit ABI integer (big-endian)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct U256(pub [u8; 32]);
+
+impl U256 {
+    /// The largest value (an "unlimited" ERC-20 allowance)
+    pub const MAX: U256 = U256([0xff; 32]);
+
+    /// Convert a decimal amount to base units: `"1.5"` with 6 decimals is
+    /// 1,500,000
+    pub fn parse_units(amount: &str, decimals: u8) -> Result<Self, SignerError> {
+        let invalid = || SignerError::InvalidTransaction(format!("invalid amount '{}' for {} decimals", amount, decimals));
+        let (whole, fraction) = amount.split_once('.').unwrap_or((amount, ""));
+        if (whole.is_empty() && fraction.is_empty())
+            || fraction.len() > decimals as usize
+            || !whole.bytes().chain(fraction.bytes()).all(|b| b.is_ascii_digit())
+        {
+            return Err(invalid());
+        }
+        let padding = std::iter::repeat_n(b'0', decimals as usize - fraction.len());
+        let mut value = U256::default();
+        for digit in whole.bytes().chain(fraction.bytes()).chain(padding) {
+            value = value.mul_add(10, digit - b'0').ok_or_else(invalid)?;
+        }
+        Ok(value)
+    }
+
+    /// `self * mul + add`, or `None` on overflow
+    fn mul_add(mut self, mul: u8, add: u8) -> Option<Self> {
+        let mut carry = u16::from(add);
+        for byte in self.0.iter_mut().rev() {
+            let value = u16::from(*byte) * u16::from(mul) + carry;
+            *byte = value as u8;
+            carry = value >> 8;
+        }
+        (carry == 0).then_some(self)
+    }
+}
+
+impl From<u128> for U256 {
+    fn from(value: u128) -> Self {
+        let mut word = [0u8; 32];
+        word[16..].copy_from_slice(&value.to_be_bytes());
+        U256(word)
+    }
+}
+
+/// Decimal
+impl fmt::Display for U256 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut value = self.0;
+        let mut digits = Vec::new();
+        while value.iter().any(|&b| b != 0) {
+            let mut remainder = 0u16;
+            for byte in value.iter_mut() {
+                let current = (remainder << 8) | u16::from(*byte);
+                *byte = (current / 10) as u8;
+                remainder = current % 10;
+            }
+            digits.push(b'0' + remainder as u8);
+        }
+        if digits.is_empty() {
+            digits.push(b'0');
+        }
+        digits.reverse();
+        f.write_str(std::str::from_utf8(&digits).expect("ASCII digits"))
+    }
+}
+
+/// A token contract call
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TokenCall {
+    /// ERC-20 `transfer(to, amount)`
+    Erc20Transfer { to: [u8; 20], amount: U256 },
+    /// ERC-20 `approve(spender, amount)`
+    Erc20Approve { spender: [u8; 20], amount: U256 },
+    /// ERC-721 `safeTransferFrom(from, to, tokenId)`
+    Erc721SafeTransferFrom { from: [u8; 20], to: [u8; 20], token_id: U256 },
+}
+
+fn address_word(address: &[u8; 20]) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    word[12..].copy_from_slice(address);
+    word
+}
+
+impl TokenCall {
+    /// The ABI-encoded calldata
+    pub fn calldata(&self) -> Vec<u8> {
+        let (selector, words) = match self {
+            TokenCall::Erc20Transfer { to, amount } => (TRANSFER, vec![address_word(to), amount.0]),
+            TokenCall::Erc20Approve { spender, amount } => (APPROVE, vec![address_word(spender), amount.0]),
+            TokenCall::Erc721SafeTransferFrom { from, to, token_id } => (
+                SAFE_TRANSFER_FROM_721,
+                vec![address_word(from), address_word(to), token_id.0],
+            ),
+        };
+        let mut calldata = selector.to_vec();
+        words.iter().for_each(|word| calldata.extend_from_slice(word));
+        calldata
+    }
+
+    /// Decode calldata this type encodes, returning `None` for anything else
+    ///
+    /// ERC-20 `approve` and ERC-721 `approve` share a selector; it decodes
+    /// as [`TokenCall::Erc20Approve`].
+    pub fn decode(calldata: &[u8]) -> Option<Self> {
+        let (selector, args) = calldata.split_first_chunk::<4>()?;
+        let word = |index: usize| -> Option<[u8; 32]> { args.get(index * 32..index * 32 + 32)?.try_into().ok() };
+        let address = |index: usize| -> Option<[u8; 20]> {
+            let word = word(index)?;
+            word[..12].iter().all(|&b| b == 0).then(|| word[12..].try_into().expect("20 bytes"))
+        };
+        match (*selector, args.len()) {
+            (TRANSFER, 64) => Some(TokenCall::Erc20Transfer {
+                to: address(0)?,
+                amount: U256(word(1)?),
+            }),
+            (APPROVE, 64) => Some(TokenCall::Erc20Approve {
+                spender: address(0)?,
+                amount: U256(word(1)?),
+            }),
+            (SAFE_TRANSFER_FROM_721, 96) => Some(TokenCall::Erc721SafeTransferFrom {
+                from: address(0)?,
+                to: address(1)?,
+                token_id: U256(word(2)?),
+            }),
+            _ => None,
+        }
+    }
+
+    /// One-line human-readable preview (amounts in base units)
+    pub fn summary(&self) -> String {
+        match self {
+            TokenCall::Erc20Transfer { to, amount } => {
+                format!("Transfer {} token units to 0x{}", amount, hex::encode(to))
+            }
+            TokenCall::Erc20Approve { spender, amount } if *amount == U256::MAX => {
+                format!("Approve 0x{} to spend UNLIMITED tokens", hex::encode(spender))
+            }
+            TokenCall::Erc20Approve { spender, amount } => {
+                format!("Approve 0x{} to spend {} token units", hex::encode(spender), amount)
+            }
+            TokenCall::Erc721SafeTransferFrom { from, to, token_id } => format!(
+                "Transfer NFT {} from 0x{} to 0x{}",
+                token_id,
+                hex::encode(from),
+                hex::encode(to)
+            ),
+        }
+    }
+
+    /// An unsigned transaction making this call on `contract`, with no
+    /// value attached
+    pub fn to_transaction(
+        &self,
+        contract: [u8; 20],
+        chain_id: u64,
+        nonce: u64,
+        gas_limit: u64,
+        pricing: GasPricing,
+    ) -> EvmTransaction {
+        EvmTransaction {
+            chain_id,
+            nonce,
+            gas_limit,
+            to: Some(contract),
+            value: 0,
+            data: self.calldata(),
+            pricing,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sha3::{Digest, Keccak256};
+
+    #[test]
+    fn test_calldata_matches_abi() {
+        assert_eq!(Keccak256::digest(b"transfer(address,uint256)")[..4], TRANSFER);
+        assert_eq!(Keccak256::digest(b"approve(address,uint256)")[..4], APPROVE);
+        assert_eq!(Keccak256::digest(b"safeTransferFrom(address,address,uint256)")[..4], SAFE_TRANSFER_FROM_721);
+
+        let call = TokenCall::Erc20Transfer {
+            to: [0x11; 20],
+            amount: U256::parse_units("1.5", 6).unwrap(),
+        };
+        let calldata = call.calldata();
+        assert_eq!(
+            hex::encode(&calldata),
+            format!("a9059cbb{}{}{:0>64}", "00".repeat(12), "11".repeat(20), "16e360")
+        );
+        assert_eq!(TokenCall::decode(&calldata), Some(call));
+        let transaction = call.to_transaction([0x22; 20], 1, 0, 60_000, GasPricing::Legacy { gas_price: 1 });
+        assert_eq!((transaction.to, transaction.value, transaction.data), (Some([0x22; 20]), 0, calldata));
+        assert_eq!(call.summary(), format!("Transfer 1500000 token units to 0x{}", "11".repeat(20)));
+
+        // The NFT decoder reads the same approve and safeTransferFrom encodings
+        let nft = TokenCall::Erc721SafeTransferFrom {
+            from: [1; 20],
+            to: [2; 20],
+            token_id: U256::from(42),
+        };
+        let decoded = crate::evm::decode_nft_call(&nft.calldata()).unwrap().unwrap();
+        assert!(matches!(decoded, crate::evm::NftCall::SafeTransferFrom { token_id, .. } if token_id == "0x2a"));
+    }
+
+    #[test]
+    fn test_parse_units_is_exact() {
+        assert_eq!(U256::parse_units("12", 18).unwrap().to_string(), format!("12{}", "0".repeat(18)));
+        assert_eq!(U256::parse_units(".25", 2).unwrap(), U256::from(25));
+        assert_eq!(U256::parse_units("7.", 0).unwrap(), U256::from(7));
+        for bad in ["1.234", "", ".", "1,5", "-1", "1e3"] {
+            assert!(U256::parse_units(bad, 2).is_err(), "{}", bad);
+        }
+        assert!(U256::parse_units(&"9".repeat(78), 0).is_err());
+        assert_eq!(U256::MAX.to_string().len(), 78);
+    }
+}
@@ -0,0 +1,207 @@
+//! Per-chain EVM transaction defaults
+//!
+//! EVM chains agree on the transaction formats but not on which one to use.
+//! BNB Smart Chain runs with no meaningful base fee and its tooling still
+//! expects legacy gas-priced transactions, and Polygon PoS nodes drop
+//! transactions tipping less than 25 gwei. A [`ChainProfile`] records this
+//! per chain id so a transaction built from it is accepted as is; chain ids
+//! without a profile get EIP-1559 with no fee floor.
+
+use serde::Serialize;
+
+use crate::error::SignerError;
+use crate::evm::transaction::{EvmTransaction, GasPricing, MAX_CHAIN_ID};
+use crate::fees::{Eip1559Fee, FeeEstimator};
+
+const GWEI: u128 = 1_000_000_000;
+
+/// Transaction type a chain is given by default
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TxType {
+    /// Legacy gas price with EIP-155 `v`
+    Legacy,
+    /// EIP-1559 (type 2) with y-parity `v`
+    Eip1559,
+}
+
+/// Transaction defaults of one chain
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ChainProfile {
+    /// EIP-155 chain id
+    pub chain_id: u64,
+    /// Human-readable network name
+    pub name: &'static str,
+    /// Type of transactions built for this chain
+    pub tx_type: TxType,
+    /// Lowest tip (or legacy gas price) nodes accept, in wei
+    pub min_priority_fee_per_gas: u128,
+}
+
+const fn profile(chain_id: u64, name: &'static str, tx_type: TxType, min_priority_fee_per_gas: u128) -> ChainProfile {
+    ChainProfile {
+        chain_id,
+        name,
+        tx_type,
+        min_priority_fee_per_gas,
+    }
+}
+
+/// Built-in profiles
+pub const PROFILES: &[ChainProfile] = &[
+    profile(1, "Ethereum", TxType::Eip1559, 0),
+    profile(11_155_111, "Sepolia", TxType::Eip1559, 0),
+    profile(10, "OP Mainnet", TxType::Eip1559, 0),
+    profile(8453, "Base", TxType::Eip1559, 0),
+    profile(84_532, "Base Sepolia", TxType::Eip1559, 0),
+    profile(42_161, "Arbitrum One", TxType::Eip1559, 0),
+    profile(43_114, "Avalanche C-Chain", TxType::Eip1559, 0),
+    profile(137, "Polygon PoS", TxType::Eip1559, 25 * GWEI),
+    profile(80_002, "Polygon Amoy", TxType::Eip1559, 25 * GWEI),
+    profile(56, "BNB Smart Chain", TxType::Legacy, 0),
+    profile(97, "BNB Smart Chain Testnet", TxType::Legacy, 0),
+];
+
+impl ChainProfile {
+    /// Built-in profile of `chain_id`, if any
+    pub fn lookup(chain_id: u64) -> Option<ChainProfile> {
+        PROFILES.iter().find(|p| p.chain_id == chain_id).copied()
+    }
+
+    /// Profile of `chain_id`, defaulting to EIP-1559 without a fee floor
+    pub fn for_chain_id(chain_id: u64) -> ChainProfile {
+        Self::lookup(chain_id).unwrap_or(profile(chain_id, "unknown", TxType::Eip1559, 0))
+    }
+
+    /// Gas pricing of this chain's transaction type
+    ///
+    /// The tip is raised to the chain's minimum. Legacy transactions pay
+    /// `base fee + tip` as their gas price; EIP-1559 transactions allow the
+    /// base fee to double, as [`Eip1559Fee::from_base_fee`].
+    pub fn pricing(&self, base_fee_per_gas: u128, priority_fee_per_gas: u128) -> Result<GasPricing, SignerError> {
+        let tip = priority_fee_per_gas.max(self.min_priority_fee_per_gas);
+        match self.tx_type {
+            TxType::Legacy => Ok(GasPricing::Legacy {
+                gas_price: base_fee_per_gas
+                    .checked_add(tip)
+                    .ok_or_else(|| SignerError::FeeEstimationError("gas price overflows u128".to_string()))?,
+            }),
+            TxType::Eip1559 => Ok(GasPricing::Eip1559(Eip1559Fee::from_base_fee(base_fee_per_gas, tip)?)),
+        }
+    }
+
+    /// Gas pricing of this chain's transaction type from `estimator`
+    ///
+    /// The tip is raised to the chain's minimum, and the max fee by as
+    /// much. Legacy transactions pay the estimate's max fee as their gas
+    /// price, since an estimate does not say what the base fee is.
+    pub fn estimated_pricing(&self, estimator: &dyn FeeEstimator) -> Result<GasPricing, SignerError> {
+        let estimate = estimator.evm_fee(self.chain_id)?;
+        estimate.validate()?;
+        let raise = self
+            .min_priority_fee_per_gas
+            .saturating_sub(estimate.max_priority_fee_per_gas);
+        let max_fee = estimate
+            .max_fee_per_gas
+            .checked_add(raise)
+            .ok_or_else(|| SignerError::FeeEstimationError("max fee overflows u128".to_string()))?;
+        match self.tx_type {
+            TxType::Legacy => Ok(GasPricing::Legacy { gas_price: max_fee }),
+            TxType::Eip1559 => Ok(GasPricing::Eip1559(Eip1559Fee {
+                max_fee_per_gas: max_fee,
+                max_priority_fee_per_gas: estimate.max_priority_fee_per_gas + raise,
+            })),
+        }
+    }
+
+    /// Reject a transaction this chain's nodes would not accept
+    pub fn check(&self, transaction: &EvmTransaction) -> Result<(), SignerError> {
+        if transaction.chain_id != self.chain_id {
+            return Err(SignerError::InvalidTransaction(format!(
+                "transaction is for chain {}, profile is {} ({})",
+                transaction.chain_id, self.name, self.chain_id
+            )));
+        }
+        if transaction.chain_id == 0 || transaction.chain_id > MAX_CHAIN_ID {
+            return Err(SignerError::InvalidTransaction(format!(
+                "chain id {} is out of range",
+                transaction.chain_id
+            )));
+        }
+        if let GasPricing::Eip1559(fee) = transaction.pricing {
+            fee.validate()?;
+        }
+        if transaction.pricing.priority_fee_per_gas() < self.min_priority_fee_per_gas {
+            return Err(SignerError::FeeEstimationError(format!(
+                "{} requires a tip of at least {} wei per gas",
+                self.name, self.min_priority_fee_per_gas
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fees::FixedFeeEstimator;
+
+    fn transaction(chain_id: u64, pricing: GasPricing) -> EvmTransaction {
+        EvmTransaction {
+            chain_id,
+            nonce: 0,
+            gas_limit: 21_000,
+            to: Some([0x11; 20]),
+            value: 1,
+            data: Vec::new(),
+            pricing,
+        }
+    }
+
+    #[test]
+    fn test_profiles_pick_transaction_type() {
+        let bsc = ChainProfile::for_chain_id(56);
+        let pricing = bsc.pricing(0, GWEI).unwrap();
+        assert_eq!(pricing, GasPricing::Legacy { gas_price: GWEI });
+        let bsc_tx = transaction(56, pricing);
+        assert_eq!(bsc_tx.v(1), 36 + 2 * 56);
+        assert!(bsc_tx.unsigned_bytes()[0] >= 0xc0);
+
+        let polygon = ChainProfile::for_chain_id(137);
+        let GasPricing::Eip1559(fee) = polygon.pricing(100 * GWEI, GWEI).unwrap() else {
+            panic!("Polygon should use EIP-1559");
+        };
+        assert_eq!(fee.max_priority_fee_per_gas, 25 * GWEI);
+        let polygon_tx = transaction(137, GasPricing::Eip1559(fee));
+        assert_eq!(polygon_tx.v(1), 1);
+        assert_eq!(polygon_tx.unsigned_bytes()[0], 0x02);
+
+        let unknown = ChainProfile::for_chain_id(31_337);
+        assert_eq!((unknown.tx_type, unknown.name), (TxType::Eip1559, "unknown"));
+
+        // Estimates are fitted to the profile too
+        let estimator = FixedFeeEstimator {
+            solana: None,
+            evm: Some(Eip1559Fee::from_base_fee(30 * GWEI, GWEI).unwrap()),
+        };
+        let GasPricing::Eip1559(fee) = polygon.estimated_pricing(&estimator).unwrap() else {
+            panic!("Polygon should use EIP-1559");
+        };
+        assert_eq!((fee.max_fee_per_gas, fee.max_priority_fee_per_gas), (85 * GWEI, 25 * GWEI));
+        assert!(polygon.check(&transaction(137, GasPricing::Eip1559(fee))).is_ok());
+        assert_eq!(bsc.estimated_pricing(&estimator).unwrap(), GasPricing::Legacy { gas_price: 61 * GWEI });
+        assert!(bsc.estimated_pricing(&FixedFeeEstimator::default()).is_err());
+    }
+
+    #[test]
+    fn test_check_rejects_unacceptable_transactions() {
+        let polygon = ChainProfile::for_chain_id(137);
+        let underpriced = transaction(137, GasPricing::Eip1559(Eip1559Fee::from_base_fee(GWEI, GWEI).unwrap()));
+        assert!(polygon.check(&underpriced).is_err());
+        assert!(ChainProfile::for_chain_id(1).check(&underpriced).is_err());
+
+        let legacy = transaction(137, GasPricing::Legacy { gas_price: 30 * GWEI });
+        assert!(polygon.check(&legacy).is_ok());
+        assert!(ChainProfile::for_chain_id(0).check(&transaction(0, legacy.pricing)).is_err());
+    }
+}
@@ -0,0 +1,38 @@
+//! EIP-191 personal messages (`personal_sign`)
+//!
+//! Wallets sign `keccak256("\x19Ethereum Signed Message:\n" || len ||
+//! message)` so a signed message can never be replayed as a transaction.
+//! The length is the message's byte length in decimal.
+
+use sha3::{Digest, Keccak256};
+
+use crate::backend::SignerBackend;
+use crate::crypto::EVMSigningResult;
+use crate::error::SignerError;
+
+/// The hash `personal_sign` signs for `message`
+pub fn personal_message_hash(message: &[u8]) -> [u8; 32] {
+    let mut hasher = Keccak256::new();
+    hasher.update(format!("\x19Ethereum Signed Message:\n{}", message.len()).as_bytes());
+    hasher.update(message);
+    hasher.finalize().into()
+}
+
+/// Sign `message` the way `personal_sign` does (`v` is 27 or 28)
+pub fn sign_personal_message(backend: &dyn SignerBackend, message: &[u8]) -> Result<EVMSigningResult, SignerError> {
+    backend.sign_evm_hash(&personal_message_hash(message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_personal_message_hash() {
+        // `ethers.hashMessage("Hello World")`
+        assert_eq!(
+            hex::encode(personal_message_hash(b"Hello World")),
+            "a1de988600a42c4b4ab089b619297c17d53cffae5d5120d82d8a92d0bb3b78f2"
+        );
+    }
+}
@@ -0,0 +1,25 @@
+//! EVM transaction support
+//!
+//! - [`transaction`]: RLP building and signing of legacy and EIP-1559 transactions
+//! - [`chain`]: per-chain transaction type and fee defaults
+//! - [`abi`]: ERC-20 / ERC-721 calldata encoding from typed arguments
+//! - [`nft`]: ERC-721 / ERC-1155 and approval calldata decoding
+//! - [`message`]: EIP-191 `personal_sign`
+//! - [`siwe`]: Sign-In with Ethereum (EIP-4361) messages
+//! - [`recover`]: signer recovery from a signature (`ecrecover`)
+
+pub mod abi;
+pub mod chain;
+pub mod message;
+pub mod nft;
+pub mod recover;
+pub mod siwe;
+pub mod transaction;
+
+pub use abi::{TokenCall, U256};
+pub use chain::{ChainProfile, TxType};
+pub use message::{personal_message_hash, sign_personal_message};
+pub use nft::{decode_nft_call, NftCall};
+pub use recover::{recover_evm_address, recover_evm_public_key};
+pub use siwe::{SiweMessage, SiweSignature};
+pub use transaction::{decrypt_and_sign_evm_transaction, EVMTransactionResult, EvmTransaction, GasPricing};
@@ -0,0 +1,314 @@
+//! ERC-721 / ERC-1155 calldata decoding
+//!
+//! Recognizes the calls NFT drainers rely on: transfers and approvals,
+//! including collection-wide `setApprovalForAll`. `transferFrom` and
+//! `approve` share selectors between ERC-20 and ERC-721, so their last
+//! argument is reported as a raw `value` (token id or amount).
+
+use serde::Serialize;
+
+use crate::error::SignerError;
+
+/// `safeTransferFrom(address,address,uint256)` (ERC-721)
+pub const SAFE_TRANSFER_FROM_721: [u8; 4] = [0x42, 0x84, 0x2e, 0x0e];
+/// `safeTransferFrom(address,address,uint256,bytes)` (ERC-721)
+pub const SAFE_TRANSFER_FROM_721_DATA: [u8; 4] = [0xb8, 0x8d, 0x4f, 0xde];
+/// `transferFrom(address,address,uint256)` (ERC-20 / ERC-721)
+pub const TRANSFER_FROM: [u8; 4] = [0x23, 0xb8, 0x72, 0xdd];
+/// `approve(address,uint256)` (ERC-20 / ERC-721)
+pub const APPROVE: [u8; 4] = [0x09, 0x5e, 0xa7, 0xb3];
+/// `setApprovalForAll(address,bool)` (ERC-721 / ERC-1155)
+pub const SET_APPROVAL_FOR_ALL: [u8; 4] = [0xa2, 0x2c, 0xb4, 0x65];
+/// `safeTransferFrom(address,address,uint256,uint256,bytes)` (ERC-1155)
+pub const SAFE_TRANSFER_FROM_1155: [u8; 4] = [0xf2, 0x42, 0x43, 0x2a];
+/// `safeBatchTransferFrom(address,address,uint256[],uint256[],bytes)` (ERC-1155)
+pub const SAFE_BATCH_TRANSFER_FROM_1155: [u8; 4] = [0x2e, 0xb2, 0xc2, 0xd6];
+
+/// A decoded NFT-related contract call
+///
+/// Addresses are lowercase 0x-prefixed hex; uint256 values are 0x-prefixed
+/// hex without leading zeros.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum NftCall {
+    /// ERC-721 `safeTransferFrom`
+    SafeTransferFrom {
+        /// Current owner
+        from: String,
+        /// Recipient
+        to: String,
+        /// Token id
+        token_id: String,
+    },
+    /// `transferFrom` (ERC-721 token id or ERC-20 amount)
+    TransferFrom {
+        /// Current owner
+        from: String,
+        /// Recipient
+        to: String,
+        /// Token id or amount
+        value: String,
+    },
+    /// `approve` (ERC-721 token id or ERC-20 allowance)
+    Approve {
+        /// Approved spender
+        spender: String,
+        /// Token id or allowance
+        value: String,
+    },
+    /// Collection-wide operator approval
+    SetApprovalForAll {
+        /// Operator
+        operator: String,
+        /// Whether the approval is granted (`false` revokes it)
+        approved: bool,
+    },
+    /// ERC-1155 `safeTransferFrom`
+    Erc1155Transfer {
+        /// Current owner
+        from: String,
+        /// Recipient
+        to: String,
+        /// Token id
+        id: String,
+        /// Amount
+        amount: String,
+    },
+    /// ERC-1155 `safeBatchTransferFrom`
+    Erc1155BatchTransfer {
+        /// Current owner
+        from: String,
+        /// Recipient
+        to: String,
+        /// Token ids
+        ids: Vec<String>,
+        /// Amounts
+        amounts: Vec<String>,
+    },
+}
+
+impl NftCall {
+    /// Recipient of a transfer, if this call moves tokens
+    pub fn recipient(&self) -> Option<&str> {
+        match self {
+            NftCall::SafeTransferFrom { to, .. }
+            | NftCall::TransferFrom { to, .. }
+            | NftCall::Erc1155Transfer { to, .. }
+            | NftCall::Erc1155BatchTransfer { to, .. } => Some(to),
+            _ => None,
+        }
+    }
+
+    /// One-line human-readable preview
+    pub fn summary(&self) -> String {
+        match self {
+            NftCall::SafeTransferFrom { from, to, token_id } => {
+                format!("Transfer NFT {} from {} to {}", token_id, from, to)
+            }
+            NftCall::TransferFrom { from, to, value } => {
+                format!("Transfer token {} from {} to {}", value, from, to)
+            }
+            NftCall::Approve { spender, value } => format!("Approve {} for token {}", spender, value),
+            NftCall::SetApprovalForAll { operator, approved: true } => {
+                format!("Approve {} to move ALL tokens in this collection", operator)
+            }
+            NftCall::SetApprovalForAll { operator, approved: false } => {
+                format!("Revoke collection approval of {}", operator)
+            }
+            NftCall::Erc1155Transfer { from, to, id, amount } => {
+                format!("Transfer {} of token {} from {} to {}", amount, id, from, to)
+            }
+            NftCall::Erc1155BatchTransfer { from, to, ids, .. } => {
+                format!("Transfer {} token types from {} to {}", ids.len(), from, to)
+            }
+        }
+    }
+}
+
+/// Decode calldata, returning `None` for unrecognized selectors
+pub fn decode_nft_call(calldata: &[u8]) -> Result<Option<NftCall>, SignerError> {
+    if calldata.len() < 4 {
+        return Ok(None);
+    }
+    let selector: [u8; 4] = calldata[..4].try_into().expect("checked length");
+    let args = Args(&calldata[4..]);
+
+    let call = match selector {
+        SAFE_TRANSFER_FROM_721 | SAFE_TRANSFER_FROM_721_DATA => NftCall::SafeTransferFrom {
+            from: args.address(0)?,
+            to: args.address(1)?,
+            token_id: args.uint(2)?,
+        },
+        TRANSFER_FROM => NftCall::TransferFrom {
+            from: args.address(0)?,
+            to: args.address(1)?,
+            value: args.uint(2)?,
+        },
+        APPROVE => NftCall::Approve {
+            spender: args.address(0)?,
+            value: args.uint(1)?,
+        },
+        SET_APPROVAL_FOR_ALL => NftCall::SetApprovalForAll {
+            operator: args.address(0)?,
+            approved: args.bool(1)?,
+        },
+        SAFE_TRANSFER_FROM_1155 => NftCall::Erc1155Transfer {
+            from: args.address(0)?,
+            to: args.address(1)?,
+            id: args.uint(2)?,
+            amount: args.uint(3)?,
+        },
+        SAFE_BATCH_TRANSFER_FROM_1155 => NftCall::Erc1155BatchTransfer {
+            from: args.address(0)?,
+            to: args.address(1)?,
+            ids: args.uint_array(2)?,
+            amounts: args.uint_array(3)?,
+        },
+        _ => return Ok(None),
+    };
+    Ok(Some(call))
+}
+
+/// ABI-encoded arguments (32-byte words)
+struct Args<'a>(&'a [u8]);
+
+impl Args<'_> {
+    fn word_at(&self, offset: usize) -> Result<&[u8], SignerError> {
+        offset
+            .checked_add(32)
+            .and_then(|end| self.0.get(offset..end))
+            .ok_or_else(|| SignerError::InvalidTransaction("calldata truncated".to_string()))
+    }
+
+    fn word(&self, index: usize) -> Result<&[u8], SignerError> {
+        self.word_at(index * 32)
+    }
+
+    fn address(&self, index: usize) -> Result<String, SignerError> {
+        let word = self.word(index)?;
+        if word[..12].iter().any(|&b| b != 0) {
+            return Err(SignerError::InvalidTransaction("invalid address argument".to_string()));
+        }
+        Ok(format!("0x{}", hex::encode(&word[12..])))
+    }
+
+    fn uint(&self, index: usize) -> Result<String, SignerError> {
+        Ok(format_uint(self.word(index)?))
+    }
+
+    fn bool(&self, index: usize) -> Result<bool, SignerError> {
+        let word = self.word(index)?;
+        match (word[..31].iter().all(|&b| b == 0), word[31]) {
+            (true, 0) => Ok(false),
+            (true, 1) => Ok(true),
+            _ => Err(SignerError::InvalidTransaction("invalid bool argument".to_string())),
+        }
+    }
+
+    fn small(&self, word: &[u8]) -> Result<usize, SignerError> {
+        if word[..24].iter().any(|&b| b != 0) {
+            return Err(SignerError::InvalidTransaction("offset out of range".to_string()));
+        }
+        Ok(u64::from_be_bytes(word[24..].try_into().expect("8 bytes")) as usize)
+    }
+
+    /// Dynamic `uint256[]` argument (head holds the offset)
+    fn uint_array(&self, index: usize) -> Result<Vec<String>, SignerError> {
+        let offset = self.small(self.word(index)?)?;
+        let len = self.small(self.word_at(offset)?)?;
+        if len > self.0.len() / 32 {
+            return Err(SignerError::InvalidTransaction("array length out of range".to_string()));
+        }
+        (0..len)
+            .map(|i| self.word_at(offset.saturating_add(32 + i * 32)).map(format_uint))
+            .collect()
+    }
+}
+
+/// Format a big-endian uint256 word as minimal 0x-hex
+pub(crate) fn format_uint(word: &[u8]) -> String {
+    let digits = hex::encode(word);
+    let trimmed = digits.trim_start_matches('0');
+    format!("0x{}", if trimmed.is_empty() { "0" } else { trimmed })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sha3::{Digest, Keccak256};
+
+    fn word_address(byte: u8) -> Vec<u8> {
+        let mut w = vec![0u8; 12];
+        w.extend_from_slice(&[byte; 20]);
+        w
+    }
+
+    fn word_uint(v: u64) -> Vec<u8> {
+        let mut w = vec![0u8; 24];
+        w.extend_from_slice(&v.to_be_bytes());
+        w
+    }
+
+    #[test]
+    fn test_selectors_match_signatures() {
+        let selector = |sig: &str| -> [u8; 4] { Keccak256::digest(sig.as_bytes())[..4].try_into().unwrap() };
+        assert_eq!(selector("safeTransferFrom(address,address,uint256)"), SAFE_TRANSFER_FROM_721);
+        assert_eq!(selector("safeTransferFrom(address,address,uint256,bytes)"), SAFE_TRANSFER_FROM_721_DATA);
+        assert_eq!(selector("transferFrom(address,address,uint256)"), TRANSFER_FROM);
+        assert_eq!(selector("approve(address,uint256)"), APPROVE);
+        assert_eq!(selector("setApprovalForAll(address,bool)"), SET_APPROVAL_FOR_ALL);
+        assert_eq!(
+            selector("safeTransferFrom(address,address,uint256,uint256,bytes)"),
+            SAFE_TRANSFER_FROM_1155
+        );
+        assert_eq!(
+            selector("safeBatchTransferFrom(address,address,uint256[],uint256[],bytes)"),
+            SAFE_BATCH_TRANSFER_FROM_1155
+        );
+    }
+
+    #[test]
+    fn test_decode_set_approval_for_all() {
+        let mut calldata = SET_APPROVAL_FOR_ALL.to_vec();
+        calldata.extend(word_address(0xaa));
+        calldata.extend(word_uint(1));
+
+        let call = decode_nft_call(&calldata).unwrap().unwrap();
+        assert_eq!(
+            call,
+            NftCall::SetApprovalForAll {
+                operator: format!("0x{}", "aa".repeat(20)),
+                approved: true,
+            }
+        );
+        assert!(call.summary().contains("ALL tokens"));
+        assert!(decode_nft_call(&calldata[..40]).is_err());
+        assert!(decode_nft_call(&[0xde, 0xad, 0xbe, 0xef]).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_decode_erc1155_batch_transfer() {
+        let mut calldata = SAFE_BATCH_TRANSFER_FROM_1155.to_vec();
+        calldata.extend(word_address(1));
+        calldata.extend(word_address(2));
+        calldata.extend(word_uint(160)); // ids offset
+        calldata.extend(word_uint(256)); // amounts offset
+        calldata.extend(word_uint(352)); // data offset
+        calldata.extend(word_uint(2));
+        calldata.extend(word_uint(7));
+        calldata.extend(word_uint(8));
+        calldata.extend(word_uint(2));
+        calldata.extend(word_uint(1));
+        calldata.extend(word_uint(5));
+        calldata.extend(word_uint(0));
+
+        let call = decode_nft_call(&calldata).unwrap().unwrap();
+        match call {
+            NftCall::Erc1155BatchTransfer { ids, amounts, .. } => {
+                assert_eq!(ids, vec!["0x7", "0x8"]);
+                assert_eq!(amounts, vec!["0x1", "0x5"]);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,93 @@
+//! Signer recovery (`ecrecover`)
+//!
+//! Recovers the public key, and from it the address, that produced a
+//! 65-byte `r || s || v` signature over a 32-byte hash, the way the
+//! `ecrecover` precompile does. `v` may be the bare recovery id (0 or 1,
+//! `yParity`), the `personal_sign` form (27 or 28), or an EIP-155 value
+//! (`35 + 2 × chainId` plus the recovery id).
+//!
+//! Signatures with a high `s` are rejected: every signature this crate
+//! produces has a low `s`, and EIP-2 made high-`s` transaction signatures
+//! invalid.
+
+use k256::ecdsa::{RecoveryId, Signature, VerifyingKey};
+
+use crate::crypto::evm_address_from_pubkey;
+use crate::error::SignerError;
+
+/// Recover the address (0x-prefixed lowercase hex, as in
+/// [`EVMSigningResult::address`](crate::crypto::EVMSigningResult::address))
+/// that signed `message_hash`
+pub fn recover_evm_address(message_hash: &[u8], signature: &[u8; 65]) -> Result<String, SignerError> {
+    Ok(evm_address_from_pubkey(&recover(message_hash, signature)?))
+}
+
+/// Recover the uncompressed public key (`0x04 || x || y`) that signed
+/// `message_hash`
+pub fn recover_evm_public_key(message_hash: &[u8], signature: &[u8; 65]) -> Result<[u8; 65], SignerError> {
+    let mut public_key = [0u8; 65];
+    public_key.copy_from_slice(recover(message_hash, signature)?.to_encoded_point(false).as_bytes());
+    Ok(public_key)
+}
+
+fn recover(message_hash: &[u8], signature: &[u8; 65]) -> Result<VerifyingKey, SignerError> {
+    if message_hash.len() != 32 {
+        return Err(SignerError::InvalidTransaction(format!(
+            "EVM message hash must be 32 bytes, got {}",
+            message_hash.len()
+        )));
+    }
+    let recovery_id = match signature[64] {
+        v @ (0 | 1) => v,
+        v @ (27 | 28) => v - 27,
+        v if v >= 35 => (v - 35) % 2,
+        v => return Err(SignerError::InvalidTransaction(format!("invalid signature v {}", v))),
+    };
+    let parsed = Signature::from_slice(&signature[..64])
+        .map_err(|_| SignerError::InvalidTransaction("invalid signature r or s".to_string()))?;
+    if parsed.normalize_s().is_some() {
+        return Err(SignerError::InvalidTransaction("signature s is not in the lower half of the order".to_string()));
+    }
+    let recovery_id = RecoveryId::from_byte(recovery_id).expect("recovery id is 0 or 1");
+    VerifyingKey::recover_from_prehash(message_hash, &parsed, recovery_id)
+        .map_err(|_| SignerError::InvalidTransaction("signature does not recover to a public key".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::sign_evm_transaction;
+
+    #[test]
+    fn test_recovers_eip155_example_and_own_signatures() {
+        std::env::set_var("SIGNER_ALLOW_INSECURE_MEMORY", "1");
+        // The EIP-155 example transaction, signed by 0x4646...46 on chain 1 (v = 37)
+        let hash = hex::decode("daf5a779ae972f972197303d7b574746c7ef83eadac0f2791ad23db92e4c8e53").unwrap();
+        let mut signature = [0u8; 65];
+        hex::decode_to_slice(
+            "28ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276\
+             67cbe9d8997f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83",
+            &mut signature[..64],
+        )
+        .unwrap();
+        signature[64] = 37;
+        assert_eq!(recover_evm_address(&hash, &signature).unwrap(), "0x9d8a62f656a8d1615c1294fd71e9cfb3e4855a4f");
+        signature[64] = 0;
+        let public_key = recover_evm_public_key(&hash, &signature).unwrap();
+        assert_eq!(public_key[0], 0x04);
+
+        let signed = sign_evm_transaction(&[7u8; 32], &[1u8; 32]).unwrap();
+        let bytes: [u8; 65] = hex::decode(signed.signature.trim_start_matches("0x")).unwrap().try_into().unwrap();
+        assert_eq!(recover_evm_address(&[1u8; 32], &bytes).unwrap(), signed.address);
+        assert_ne!(recover_evm_address(&[2u8; 32], &bytes).unwrap(), signed.address);
+
+        // The high-s twin of a valid signature, and a bad v
+        let mut high_s = bytes;
+        let s = Signature::from_slice(&bytes[..64]).unwrap().s();
+        high_s[32..64].copy_from_slice(&(-*s).to_bytes());
+        assert!(recover_evm_address(&[1u8; 32], &high_s).is_err());
+        let mut bad_v = bytes;
+        bad_v[64] = 29;
+        assert!(recover_evm_address(&[1u8; 32], &bad_v).is_err());
+    }
+}
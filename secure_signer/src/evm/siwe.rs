@@ -0,0 +1,418 @@
+//! Sign-In with Ethereum (EIP-4361)
+//!
+//! [`SiweMessage`] builds the exact text EIP-4361 specifies from structured
+//! fields, or parses one a dApp sent, and [`SiweMessage::sign`] signs it
+//! with `personal_sign`. The fiddly parts live here rather than in every
+//! integration: the blank-line layout around the optional statement, an
+//! EIP-55 checksummed address, RFC 3339 timestamps, and nonces of at least
+//! eight alphanumeric characters.
+//!
+//! Signing refuses a message for another address or one that has already
+//! expired. Checking the domain against the requesting origin is left to
+//! the caller, which is the only party that knows it.
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::Serialize;
+
+use crate::backend::SignerBackend;
+use crate::chain::eip55;
+use crate::error::SignerError;
+use crate::evm::message::sign_personal_message;
+use crate::validity::{format_rfc3339, parse_rfc3339, unix_now};
+
+const HEADER_SUFFIX: &str = " wants you to sign in with your Ethereum account:";
+
+/// An EIP-4361 message
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SiweMessage {
+    /// URI scheme of the origin, when it is not `https`
+    pub scheme: Option<String>,
+    /// RFC 3986 authority requesting the sign-in
+    pub domain: String,
+    /// EIP-55 checksummed address
+    pub address: String,
+    /// Human-readable assertion, on a single line
+    pub statement: Option<String>,
+    /// Subject of the signing (usually the dApp's URL)
+    pub uri: String,
+    /// Always "1"
+    pub version: String,
+    pub chain_id: u64,
+    /// At least eight alphanumeric characters, chosen by the dApp
+    pub nonce: String,
+    /// RFC 3339
+    pub issued_at: String,
+    /// RFC 3339
+    pub expiration_time: Option<String>,
+    /// RFC 3339
+    pub not_before: Option<String>,
+    pub request_id: Option<String>,
+    pub resources: Vec<String>,
+}
+
+/// A signed message, as dApps expect it back
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct SiweSignature {
+    /// The exact text that was signed
+    pub message: String,
+    /// 0x-prefixed hex, 65 bytes (r || s || v)
+    pub signature: String,
+    /// EIP-55 checksummed address
+    pub address: String,
+}
+
+fn invalid(reason: impl Into<String>) -> SignerError {
+    SignerError::InvalidTransaction(format!("SIWE message: {}", reason.into()))
+}
+
+impl SiweMessage {
+    /// A message issued now
+    ///
+    /// The address may be given in any case; it is stored checksummed.
+    pub fn new(domain: &str, address: &str, uri: &str, chain_id: u64, nonce: &str) -> Result<Self, SignerError> {
+        let hex = address
+            .strip_prefix("0x")
+            .filter(|hex| hex.len() == 40 && hex.bytes().all(|b| b.is_ascii_hexdigit()))
+            .ok_or_else(|| invalid(format!("'{}' is not an EVM address", address)))?;
+        let message = SiweMessage {
+            scheme: None,
+            domain: domain.to_string(),
+            address: eip55(hex),
+            statement: None,
+            uri: uri.to_string(),
+            version: "1".to_string(),
+            chain_id,
+            nonce: nonce.to_string(),
+            issued_at: format_rfc3339(unix_now()),
+            expiration_time: None,
+            not_before: None,
+            request_id: None,
+            resources: Vec::new(),
+        };
+        message.validate()?;
+        Ok(message)
+    }
+
+    pub fn with_statement(mut self, statement: &str) -> Self {
+        self.statement = Some(statement.to_string());
+        self
+    }
+
+    /// Expire at `unix_secs`
+    pub fn with_expiration_time(mut self, unix_secs: u64) -> Self {
+        self.expiration_time = Some(format_rfc3339(unix_secs));
+        self
+    }
+
+    /// Not valid before `unix_secs`
+    pub fn with_not_before(mut self, unix_secs: u64) -> Self {
+        self.not_before = Some(format_rfc3339(unix_secs));
+        self
+    }
+
+    pub fn with_request_id(mut self, request_id: &str) -> Self {
+        self.request_id = Some(request_id.to_string());
+        self
+    }
+
+    pub fn with_resources(mut self, resources: Vec<String>) -> Self {
+        self.resources = resources;
+        self
+    }
+
+    /// Check every field against EIP-4361
+    pub fn validate(&self) -> Result<(), SignerError> {
+        let single_line = |value: &str| !value.is_empty() && !value.contains(['\n', '\r']);
+        if !single_line(&self.domain) || self.domain.contains(char::is_whitespace) {
+            return Err(invalid("domain must be a non-empty authority"));
+        }
+        if let Some(scheme) = &self.scheme {
+            if scheme.is_empty() || !scheme.bytes().all(|b| b.is_ascii_alphanumeric() || b"+-.".contains(&b)) {
+                return Err(invalid(format!("invalid scheme '{}'", scheme)));
+            }
+        }
+        let hex = self.address.strip_prefix("0x").filter(|hex| hex.len() == 40);
+        if hex.is_none_or(|hex| eip55(hex) != self.address) {
+            return Err(invalid(format!("address '{}' is not EIP-55 checksummed", self.address)));
+        }
+        if self.statement.as_deref().is_some_and(|statement| !single_line(statement)) {
+            return Err(invalid("statement must be a single non-empty line"));
+        }
+        if !single_line(&self.uri) || self.uri.contains(char::is_whitespace) || !self.uri.contains(':') {
+            return Err(invalid("uri must be an absolute URI"));
+        }
+        if self.version != "1" {
+            return Err(invalid(format!("unsupported version '{}'", self.version)));
+        }
+        if self.nonce.len() < 8 || !self.nonce.bytes().all(|b| b.is_ascii_alphanumeric()) {
+            return Err(invalid("nonce must be at least 8 alphanumeric characters"));
+        }
+        for (name, value) in [
+            ("issued-at", Some(&self.issued_at)),
+            ("expiration-time", self.expiration_time.as_ref()),
+            ("not-before", self.not_before.as_ref()),
+        ] {
+            if value.is_some_and(|value| parse_rfc3339(value).is_none()) {
+                return Err(invalid(format!("{} is not an RFC 3339 timestamp", name)));
+            }
+        }
+        if self.request_id.as_deref().is_some_and(|id| id.contains(['\n', '\r'])) {
+            return Err(invalid("request id must be a single line"));
+        }
+        if self.resources.iter().any(|resource| !single_line(resource)) {
+            return Err(invalid("resources must be single-line URIs"));
+        }
+        Ok(())
+    }
+
+    /// Fail unless the message is valid at `now` (Unix seconds)
+    pub fn check_time(&self, now: u64) -> Result<(), SignerError> {
+        let now = now as i64;
+        if let Some(expiration) = self.expiration_time.as_deref().and_then(parse_rfc3339) {
+            if now >= expiration {
+                return Err(invalid(format!("expired at {}", self.expiration_time.as_deref().unwrap_or(""))));
+            }
+        }
+        if let Some(not_before) = self.not_before.as_deref().and_then(parse_rfc3339) {
+            if now < not_before {
+                return Err(invalid(format!("not valid before {}", self.not_before.as_deref().unwrap_or(""))));
+            }
+        }
+        Ok(())
+    }
+
+    /// Sign the message with `personal_sign`
+    ///
+    /// Fails if the message is malformed or expired, or if the backend's
+    /// key is not the message's address. A `not-before` in the future is
+    /// allowed: the dApp checks it when the signature is presented.
+    pub fn sign(&self, backend: &dyn SignerBackend) -> Result<SiweSignature, SignerError> {
+        self.validate()?;
+        if let Some(expiration) = self.expiration_time.as_deref().and_then(parse_rfc3339) {
+            if unix_now() as i64 >= expiration {
+                return Err(invalid("message has expired"));
+            }
+        }
+        let message = self.to_string();
+        let result = sign_personal_message(backend, message.as_bytes())?;
+        if !result.address.eq_ignore_ascii_case(&self.address) {
+            return Err(invalid(format!(
+                "message is for {}, but the key is {}",
+                self.address, result.address
+            )));
+        }
+        Ok(SiweSignature {
+            message,
+            signature: result.signature,
+            address: self.address.clone(),
+        })
+    }
+}
+
+impl fmt::Display for SiweMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(scheme) = &self.scheme {
+            write!(f, "{}://", scheme)?;
+        }
+        writeln!(f, "{}{}", self.domain, HEADER_SUFFIX)?;
+        writeln!(f, "{}", self.address)?;
+        writeln!(f)?;
+        if let Some(statement) = &self.statement {
+            writeln!(f, "{}", statement)?;
+        }
+        writeln!(f)?;
+        writeln!(f, "URI: {}", self.uri)?;
+        writeln!(f, "Version: {}", self.version)?;
+        writeln!(f, "Chain ID: {}", self.chain_id)?;
+        writeln!(f, "Nonce: {}", self.nonce)?;
+        write!(f, "Issued At: {}", self.issued_at)?;
+        if let Some(expiration_time) = &self.expiration_time {
+            write!(f, "\nExpiration Time: {}", expiration_time)?;
+        }
+        if let Some(not_before) = &self.not_before {
+            write!(f, "\nNot Before: {}", not_before)?;
+        }
+        if let Some(request_id) = &self.request_id {
+            write!(f, "\nRequest ID: {}", request_id)?;
+        }
+        if !self.resources.is_empty() {
+            write!(f, "\nResources:")?;
+            for resource in &self.resources {
+                write!(f, "\n- {}", resource)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parse the text of an EIP-4361 message
+impl FromStr for SiweMessage {
+    type Err = SignerError;
+
+    fn from_str(text: &str) -> Result<Self, SignerError> {
+        let mut lines = text.split('\n').peekable();
+        let mut next = |expected: &str| lines.next().ok_or_else(|| invalid(format!("missing {}", expected)));
+
+        let header = next("header")?;
+        let origin = header
+            .strip_suffix(HEADER_SUFFIX)
+            .ok_or_else(|| invalid("first line is not a sign-in request"))?;
+        let (scheme, domain) = match origin.split_once("://") {
+            Some((scheme, domain)) => (Some(scheme.to_string()), domain.to_string()),
+            None => (None, origin.to_string()),
+        };
+        let address = next("address")?.to_string();
+        if !next("blank line")?.is_empty() {
+            return Err(invalid("expected a blank line after the address"));
+        }
+        let statement = match next("statement")? {
+            "" => None,
+            statement => {
+                if !next("blank line")?.is_empty() {
+                    return Err(invalid("expected a blank line after the statement"));
+                }
+                Some(statement.to_string())
+            }
+        };
+
+        let mut field = |name: &str| -> Result<String, SignerError> {
+            let line = lines.next().ok_or_else(|| invalid(format!("missing {}", name)))?;
+            line.strip_prefix(name)
+                .and_then(|rest| rest.strip_prefix(": "))
+                .map(str::to_string)
+                .ok_or_else(|| invalid(format!("expected '{}:', found '{}'", name, line)))
+        };
+        let uri = field("URI")?;
+        let version = field("Version")?;
+        let chain_id = field("Chain ID")?
+            .parse()
+            .map_err(|_| invalid("chain id is not a number"))?;
+        let nonce = field("Nonce")?;
+        let issued_at = field("Issued At")?;
+
+        let mut optional = |name: &str| -> Option<String> {
+            let value = lines.peek()?.strip_prefix(name)?.strip_prefix(": ")?.to_string();
+            lines.next();
+            Some(value)
+        };
+        let expiration_time = optional("Expiration Time");
+        let not_before = optional("Not Before");
+        let request_id = optional("Request ID");
+
+        let mut resources = Vec::new();
+        if lines.next_if_eq(&"Resources:").is_some() {
+            while let Some(resource) = lines.next_if(|line| line.starts_with("- ")) {
+                resources.push(resource[2..].to_string());
+            }
+        }
+        if let Some(line) = lines.next() {
+            return Err(invalid(format!("unexpected line '{}'", line)));
+        }
+
+        let message = SiweMessage {
+            scheme,
+            domain,
+            address,
+            statement,
+            uri,
+            version,
+            chain_id,
+            nonce,
+            issued_at,
+            expiration_time,
+            not_before,
+            request_id,
+            resources,
+        };
+        message.validate()?;
+        Ok(message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::ContainerBackend;
+    use crate::crypto::{Cipher, EncryptedKeyContainer};
+    use crate::evm::message::personal_message_hash;
+    use crate::validity::{format_rfc3339, parse_rfc3339};
+    use crate::KdfParams;
+    use k256::ecdsa::{RecoveryId, Signature, VerifyingKey};
+
+    // The example message from EIP-4361
+    const EXAMPLE: &str = "service.invalid wants you to sign in with your Ethereum account:
+0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2
+
+I accept the ServiceOrg Terms of Service: https://service.invalid/tos
+
+URI: https://service.invalid/login
+Version: 1
+Chain ID: 1
+Nonce: 32891756
+Issued At: 2021-09-30T16:25:24Z
+Resources:
+- ipfs://bafybeiemxf5abjwjbikoz4mc3a3dla6ual3jsgpdr4cjr3oz3evfyavhwq/
+- https://example.com/my-web2-claim.json";
+
+    #[test]
+    fn test_parse_and_format_round_trip() {
+        let message: SiweMessage = EXAMPLE.parse().unwrap();
+        assert_eq!(message.domain, "service.invalid");
+        assert_eq!(message.resources.len(), 2);
+        assert_eq!(message.to_string(), EXAMPLE);
+        assert_eq!(parse_rfc3339(&message.issued_at), Some(1_633_019_124));
+        assert_eq!(format_rfc3339(1_633_019_124), message.issued_at);
+        assert_eq!(parse_rfc3339("2021-09-30T18:25:24.512+02:00"), Some(1_633_019_124));
+        assert_eq!(parse_rfc3339("2021-02-29T00:00:00Z"), None);
+
+        // No statement: three line breaks between the address and the URI
+        let bare = SiweMessage::new(
+            "example.com",
+            "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2",
+            "https://example.com",
+            8453,
+            "abcdefgh1",
+        )
+        .unwrap()
+        .with_expiration_time(2_000_000_000);
+        assert!(bare.to_string().contains("Cc2\n\n\nURI: https://example.com\n"));
+        assert!(bare.to_string().ends_with("\nExpiration Time: 2033-05-18T03:33:20Z"));
+        assert_eq!(bare.to_string().parse::<SiweMessage>().unwrap(), bare);
+        assert!(bare.check_time(2_000_000_000).is_err());
+
+        let lowercase = EXAMPLE.replace("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2", "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2");
+        assert!(lowercase.parse::<SiweMessage>().is_err());
+        assert!(EXAMPLE.replace("32891756", "1234").parse::<SiweMessage>().is_err());
+    }
+
+    #[test]
+    fn test_sign_with_personal_sign() {
+        std::env::set_var("SIGNER_ALLOW_INSECURE_MEMORY", "1");
+        let container =
+            EncryptedKeyContainer::encrypt_with_kdf(&[9u8; 32], "pw", Cipher::Aes256Gcm, KdfParams::MINIMUM).unwrap();
+        let backend = ContainerBackend::new(&container, "pw");
+        let address = backend.sign_evm_hash(&[0u8; 32]).unwrap().address;
+
+        let message = SiweMessage::new("example.com", &address, "https://example.com/login", 1, "nonce12345")
+            .unwrap()
+            .with_statement("Sign in to Example");
+        let signed = message.sign(&backend).unwrap();
+        assert_eq!(signed.message, message.to_string());
+
+        let signature = hex::decode(signed.signature.trim_start_matches("0x")).unwrap();
+        let recovered = VerifyingKey::recover_from_prehash(
+            &personal_message_hash(signed.message.as_bytes()),
+            &Signature::from_slice(&signature[..64]).unwrap(),
+            RecoveryId::from_byte(signature[64] - 27).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(crate::crypto::evm_address_from_pubkey(&recovered), address.to_ascii_lowercase());
+
+        let other = SiweMessage::new("example.com", &format!("0x{}", "11".repeat(20)), "https://example.com", 1, "nonce12345").unwrap();
+        assert!(other.sign(&backend).is_err());
+        let expired = message.with_expiration_time(1_000);
+        assert!(expired.sign(&backend).is_err());
+    }
+}
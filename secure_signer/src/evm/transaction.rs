@@ -0,0 +1,302 @@
+//! EVM transaction building and signing
+//!
+//! [`EvmTransaction`] is an unsigned legacy or EIP-1559 transaction. What
+//! gets signed is keccak256 of its RLP encoding:
+//!
+//! - legacy (EIP-155): `rlp([nonce, gasPrice, gas, to, value, data, chainId, 0, 0])`,
+//!   submitted as `rlp([nonce, ..., data, v, r, s])` with
+//!   `v = recovery id + 35 + 2 × chainId`
+//! - EIP-1559: `0x02 || rlp([chainId, nonce, tip, maxFee, gas, to, value, data, []])`,
+//!   submitted with the bare recovery id (`yParity`) before `r` and `s`
+//!
+//! Which of the two a chain gets, and the fee floor it enforces, comes from
+//! its [`ChainProfile`].
+
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Keccak256};
+
+use crate::backend::{ContainerBackend, SignerBackend};
+use crate::crypto::EncryptedKeyContainer;
+use crate::encoding::Encoding;
+use crate::error::SignerError;
+use crate::evm::chain::ChainProfile;
+use crate::fees::Eip1559Fee;
+
+/// EIP-2718 type byte of EIP-1559 transactions
+const EIP1559_TX_TYPE: u8 = 0x02;
+
+/// Largest chain id whose EIP-155 `v` fits in a u64 (EIP-2294)
+pub const MAX_CHAIN_ID: u64 = u64::MAX / 2 - 36;
+
+/// How a transaction pays for gas
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum GasPricing {
+    /// Single gas price (legacy, EIP-155 replay-protected)
+    Legacy {
+        /// Price per gas in wei
+        gas_price: u128,
+    },
+    /// Base fee plus tip (EIP-1559, type 2)
+    Eip1559(Eip1559Fee),
+}
+
+impl GasPricing {
+    /// The tip a block producer receives per gas at most
+    pub fn priority_fee_per_gas(&self) -> u128 {
+        match self {
+            GasPricing::Legacy { gas_price } => *gas_price,
+            GasPricing::Eip1559(fee) => fee.max_priority_fee_per_gas,
+        }
+    }
+}
+
+/// An unsigned EVM transaction
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct EvmTransaction {
+    /// EIP-155 chain id
+    pub chain_id: u64,
+    /// Sender nonce
+    pub nonce: u64,
+    /// Gas limit
+    pub gas_limit: u64,
+    /// Recipient, or `None` for a contract creation
+    pub to: Option<[u8; 20]>,
+    /// Value in wei
+    pub value: u128,
+    /// Calldata (or init code)
+    pub data: Vec<u8>,
+    /// Fee settings, which also select the transaction type
+    pub pricing: GasPricing,
+}
+
+/// Result of signing an [`EvmTransaction`]
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct EVMTransactionResult {
+    /// Signed transaction (0x-prefixed hex), for `eth_sendRawTransaction`
+    pub raw_transaction: String,
+    /// Transaction hash (0x-prefixed hex)
+    pub hash: String,
+    /// The EVM address that signed
+    pub from: String,
+    /// `v` as encoded in the transaction (EIP-155 value or y-parity)
+    pub v: u64,
+    /// Number of this signature, from a session's
+    /// [`SignatureCounter`](crate::counter::SignatureCounter)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub counter: Option<u64>,
+}
+
+impl EvmTransaction {
+    /// Bytes whose keccak256 hash is signed
+    pub fn unsigned_bytes(&self) -> Vec<u8> {
+        match self.pricing {
+            GasPricing::Legacy { .. } => {
+                let mut fields = self.fields();
+                rlp_uint(&mut fields, u128::from(self.chain_id));
+                rlp_uint(&mut fields, 0);
+                rlp_uint(&mut fields, 0);
+                rlp_list(&fields)
+            }
+            GasPricing::Eip1559(_) => [&[EIP1559_TX_TYPE][..], &rlp_list(&self.fields())].concat(),
+        }
+    }
+
+    /// keccak256 of [`EvmTransaction::unsigned_bytes`]
+    pub fn signing_hash(&self) -> [u8; 32] {
+        Keccak256::digest(self.unsigned_bytes()).into()
+    }
+
+    /// The `v` value for a signature with `recovery_id` (0 or 1)
+    pub fn v(&self, recovery_id: u8) -> u64 {
+        match self.pricing {
+            GasPricing::Legacy { .. } => u64::from(recovery_id) + 35 + 2 * self.chain_id,
+            GasPricing::Eip1559(_) => u64::from(recovery_id),
+        }
+    }
+
+    /// Encode the transaction with a signature, ready to broadcast
+    pub fn encode_signed(&self, recovery_id: u8, r: &[u8; 32], s: &[u8; 32]) -> Vec<u8> {
+        let mut fields = self.fields();
+        rlp_uint(&mut fields, u128::from(self.v(recovery_id)));
+        rlp_bytes(&mut fields, strip_zeros(r));
+        rlp_bytes(&mut fields, strip_zeros(s));
+
+        match self.pricing {
+            GasPricing::Legacy { .. } => rlp_list(&fields),
+            GasPricing::Eip1559(_) => [&[EIP1559_TX_TYPE][..], &rlp_list(&fields)].concat(),
+        }
+    }
+
+    /// Check the transaction against its chain's profile and sign it
+    ///
+    /// The backend receives the unsigned transaction rather than its hash,
+    /// so hardware wallets can display it.
+    pub fn sign_with(&self, backend: &dyn SignerBackend) -> Result<EVMTransactionResult, SignerError> {
+        ChainProfile::for_chain_id(self.chain_id).check(self)?;
+
+        let result = backend.sign_evm_transaction(&self.unsigned_bytes())?;
+        let signature = Encoding::Hex.decode(&result.signature)?;
+        if signature.len() != 65 || !matches!(signature[64], 27 | 28) {
+            return Err(SignerError::SigningFailed(
+                "backend returned a malformed EVM signature".to_string(),
+            ));
+        }
+        let recovery_id = signature[64] - 27;
+        let r: [u8; 32] = signature[..32].try_into().expect("length checked");
+        let s: [u8; 32] = signature[32..64].try_into().expect("length checked");
+
+        let raw = self.encode_signed(recovery_id, &r, &s);
+        Ok(EVMTransactionResult {
+            hash: format!("0x{}", hex::encode(Keccak256::digest(&raw))),
+            raw_transaction: format!("0x{}", hex::encode(raw)),
+            from: result.address,
+            v: self.v(recovery_id),
+            counter: result.counter,
+        })
+    }
+
+    /// RLP items shared by the unsigned and signed encodings
+    fn fields(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        match self.pricing {
+            GasPricing::Legacy { gas_price } => {
+                rlp_uint(&mut out, u128::from(self.nonce));
+                rlp_uint(&mut out, gas_price);
+            }
+            GasPricing::Eip1559(fee) => {
+                rlp_uint(&mut out, u128::from(self.chain_id));
+                rlp_uint(&mut out, u128::from(self.nonce));
+                rlp_uint(&mut out, fee.max_priority_fee_per_gas);
+                rlp_uint(&mut out, fee.max_fee_per_gas);
+            }
+        }
+        rlp_uint(&mut out, u128::from(self.gas_limit));
+        rlp_bytes(&mut out, self.to.as_ref().map_or(&[][..], |to| &to[..]));
+        rlp_uint(&mut out, self.value);
+        rlp_bytes(&mut out, &self.data);
+        if let GasPricing::Eip1559(_) = self.pricing {
+            // empty access list
+            out.extend_from_slice(&rlp_list(&[]));
+        }
+        out
+    }
+}
+
+/// Decrypt a key container and sign an EVM transaction
+///
+/// Same security model as `decrypt_and_sign_evm`; the transaction is first
+/// checked against its chain's [`ChainProfile`].
+pub fn decrypt_and_sign_evm_transaction(
+    container_json: &str,
+    passphrase: &str,
+    transaction: &EvmTransaction,
+) -> Result<EVMTransactionResult, SignerError> {
+    let container = EncryptedKeyContainer::from_json(container_json)?;
+    transaction.sign_with(&ContainerBackend::new(&container, passphrase))
+}
+
+fn strip_zeros(bytes: &[u8]) -> &[u8] {
+    let start = bytes.iter().position(|&b| b != 0).unwrap_or(bytes.len());
+    &bytes[start..]
+}
+
+fn rlp_length(out: &mut Vec<u8>, len: usize, offset: u8) {
+    if len < 56 {
+        out.push(offset + len as u8);
+    } else {
+        let len_bytes = len.to_be_bytes();
+        let len_bytes = strip_zeros(&len_bytes);
+        out.push(offset + 55 + len_bytes.len() as u8);
+        out.extend_from_slice(len_bytes);
+    }
+}
+
+fn rlp_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    if bytes.len() != 1 || bytes[0] >= 0x80 {
+        rlp_length(out, bytes.len(), 0x80);
+    }
+    out.extend_from_slice(bytes);
+}
+
+fn rlp_uint(out: &mut Vec<u8>, value: u128) {
+    rlp_bytes(out, strip_zeros(&value.to_be_bytes()));
+}
+
+fn rlp_list(payload: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(payload.len() + 9);
+    rlp_length(&mut out, payload.len(), 0xc0);
+    out.extend_from_slice(payload);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::Cipher;
+    use crate::kdf::KdfParams;
+
+    #[test]
+    fn test_eip155_example() {
+        std::env::set_var("SIGNER_ALLOW_INSECURE_MEMORY", "1");
+        // The worked example from EIP-155
+        let transaction = EvmTransaction {
+            chain_id: 1,
+            nonce: 9,
+            gas_limit: 21_000,
+            to: Some([0x35; 20]),
+            value: 1_000_000_000_000_000_000,
+            data: Vec::new(),
+            pricing: GasPricing::Legacy { gas_price: 20_000_000_000 },
+        };
+        assert_eq!(
+            hex::encode(transaction.unsigned_bytes()),
+            "ec098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a764000080018080"
+        );
+
+        let container =
+            EncryptedKeyContainer::encrypt_with_kdf(&[0x46; 32], "pw", Cipher::Aes256Gcm, KdfParams::MINIMUM).unwrap();
+        let result = decrypt_and_sign_evm_transaction(&container.to_json().unwrap(), "pw", &transaction).unwrap();
+        assert_eq!(result.v, 37);
+        assert_eq!(
+            result.raw_transaction,
+            "0xf86c098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a76400008025\
+             a028ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276\
+             a067cbe9d8997f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83"
+        );
+    }
+
+    #[test]
+    fn test_eip1559_transaction() {
+        std::env::set_var("SIGNER_ALLOW_INSECURE_MEMORY", "1");
+        let transaction = EvmTransaction {
+            chain_id: 137,
+            nonce: 3,
+            gas_limit: 60_000,
+            to: Some([0x11; 20]),
+            value: 5,
+            data: vec![0xa9, 0x05, 0x9c, 0xbb],
+            pricing: ChainProfile::for_chain_id(137).pricing(100_000_000_000, 0).unwrap(),
+        };
+
+        let container =
+            EncryptedKeyContainer::encrypt_with_kdf(&[0x46; 32], "pw", Cipher::Aes256Gcm, KdfParams::MINIMUM).unwrap();
+        let json = container.to_json().unwrap();
+        let result = decrypt_and_sign_evm_transaction(&json, "pw", &transaction).unwrap();
+        assert_eq!(result.v, 0);
+        assert_eq!(
+            result.raw_transaction,
+            "0x02f8718189038505d21dba008534630b8a0082ea609411111111111111111111111111111111111111110584a9059cbbc080\
+             a0fb37355f012b9334bc0354fd4f5b288fe4e198e8d2bd04fd7da395cc51414b15\
+             a0640a5609b9d761ebaf07511a6d2b1103637e934804e7a0b5e9077fefccee1cf9"
+        );
+        assert_eq!(result.hash, "0xa699699d6f16ceff6009871650d81fb7163e331fd8437d52ad701f7b9ffcf493");
+
+        // A tip below Polygon's floor is refused
+        let underpriced = EvmTransaction {
+            pricing: GasPricing::Eip1559(Eip1559Fee::from_base_fee(100_000_000_000, 1).unwrap()),
+            ..transaction
+        };
+        assert!(decrypt_and_sign_evm_transaction(&json, "pw", &underpriced).is_err());
+    }
+}
@@ -0,0 +1,191 @@
+//! Fee estimation for transaction builders
+//!
+//! The signing core never talks to the network, so fees are supplied by the
+//! embedder through the [`FeeEstimator`] trait:
+//! - Solana: compute-unit limit and priority fee (micro-lamports per CU)
+//! - EVM: EIP-1559 max fee and max priority fee per gas
+//!
+//! Air-gapped setups use [`FixedFeeEstimator`] with values prepared on the
+//! online machine. With the `broadcast` feature, `Broadcaster` implements
+//! the trait from live RPC data.
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::SignerError;
+
+/// Compute Budget program id (base58)
+pub const COMPUTE_BUDGET_PROGRAM_ID: &str = "ComputeBudget111111111111111111111111111111";
+
+/// Compute Budget instruction discriminators
+const SET_COMPUTE_UNIT_LIMIT: u8 = 2;
+const SET_COMPUTE_UNIT_PRICE: u8 = 3;
+
+/// Solana fee settings
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SolanaFee {
+    /// Compute units requested for the transaction
+    pub compute_unit_limit: u32,
+    /// Priority fee in micro-lamports per compute unit
+    pub compute_unit_price: u64,
+}
+
+impl SolanaFee {
+    /// Data for the `SetComputeUnitLimit` instruction
+    pub fn set_compute_unit_limit_data(&self) -> Vec<u8> {
+        let mut data = vec![SET_COMPUTE_UNIT_LIMIT];
+        data.extend_from_slice(&self.compute_unit_limit.to_le_bytes());
+        data
+    }
+
+    /// Data for the `SetComputeUnitPrice` instruction
+    pub fn set_compute_unit_price_data(&self) -> Vec<u8> {
+        let mut data = vec![SET_COMPUTE_UNIT_PRICE];
+        data.extend_from_slice(&self.compute_unit_price.to_le_bytes());
+        data
+    }
+
+    /// Priority fee in lamports (rounded up), excluding the base signature fee
+    pub fn priority_fee_lamports(&self) -> Result<u64, SignerError> {
+        let micro = u128::from(self.compute_unit_limit) * u128::from(self.compute_unit_price);
+        u64::try_from(micro.div_ceil(1_000_000))
+            .map_err(|_| SignerError::FeeEstimationError("priority fee overflows u64".to_string()))
+    }
+}
+
+/// EIP-1559 fee settings (wei)
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Eip1559Fee {
+    /// Maximum total fee per gas
+    pub max_fee_per_gas: u128,
+    /// Maximum priority fee (tip) per gas
+    pub max_priority_fee_per_gas: u128,
+}
+
+impl Eip1559Fee {
+    /// Build a fee from the next block's base fee and a tip, allowing the
+    /// base fee to double before the transaction becomes unincludable
+    pub fn from_base_fee(base_fee_per_gas: u128, priority_fee_per_gas: u128) -> Result<Self, SignerError> {
+        let max_fee = base_fee_per_gas
+            .checked_mul(2)
+            .and_then(|f| f.checked_add(priority_fee_per_gas))
+            .ok_or_else(|| SignerError::FeeEstimationError("max fee overflows u128".to_string()))?;
+
+        Ok(Self {
+            max_fee_per_gas: max_fee,
+            max_priority_fee_per_gas: priority_fee_per_gas,
+        })
+    }
+
+    /// Validate internal consistency (tip may not exceed the max fee)
+    pub fn validate(&self) -> Result<(), SignerError> {
+        if self.max_priority_fee_per_gas > self.max_fee_per_gas {
+            return Err(SignerError::FeeEstimationError(
+                "max_priority_fee_per_gas exceeds max_fee_per_gas".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Worst-case cost in wei for a given gas limit
+    pub fn max_cost(&self, gas_limit: u64) -> Result<u128, SignerError> {
+        self.max_fee_per_gas
+            .checked_mul(u128::from(gas_limit))
+            .ok_or_else(|| SignerError::FeeEstimationError("max cost overflows u128".to_string()))
+    }
+}
+
+/// Source of fee settings consulted by transaction builders
+///
+/// Both methods default to "unsupported" so an estimator can serve a single
+/// chain family.
+pub trait FeeEstimator {
+    /// Fee for a Solana transaction writing to `writable_accounts`
+    fn solana_fee(&self, writable_accounts: &[[u8; 32]]) -> Result<SolanaFee, SignerError> {
+        let _ = writable_accounts;
+        Err(SignerError::FeeEstimationError(
+            "Solana fee estimation not supported by this estimator".to_string(),
+        ))
+    }
+
+    /// EIP-1559 fee for an EVM transaction on `chain_id`
+    fn evm_fee(&self, chain_id: u64) -> Result<Eip1559Fee, SignerError> {
+        let _ = chain_id;
+        Err(SignerError::FeeEstimationError(
+            "EVM fee estimation not supported by this estimator".to_string(),
+        ))
+    }
+}
+
+/// Estimator returning pre-configured values (for offline signing)
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct FixedFeeEstimator {
+    /// Fee for Solana transactions
+    pub solana: Option<SolanaFee>,
+    /// Fee for EVM transactions
+    pub evm: Option<Eip1559Fee>,
+}
+
+impl FeeEstimator for FixedFeeEstimator {
+    fn solana_fee(&self, _writable_accounts: &[[u8; 32]]) -> Result<SolanaFee, SignerError> {
+        self.solana.ok_or_else(|| {
+            SignerError::FeeEstimationError("no Solana fee configured".to_string())
+        })
+    }
+
+    fn evm_fee(&self, _chain_id: u64) -> Result<Eip1559Fee, SignerError> {
+        let fee = self
+            .evm
+            .ok_or_else(|| SignerError::FeeEstimationError("no EVM fee configured".to_string()))?;
+        fee.validate()?;
+        Ok(fee)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compute_budget_instruction_data() {
+        let fee = SolanaFee {
+            compute_unit_limit: 200_000,
+            compute_unit_price: 5_000,
+        };
+        assert_eq!(fee.set_compute_unit_limit_data(), vec![2, 0x40, 0x0d, 0x03, 0x00]);
+        assert_eq!(
+            fee.set_compute_unit_price_data(),
+            vec![3, 0x88, 0x13, 0, 0, 0, 0, 0, 0]
+        );
+        assert_eq!(fee.priority_fee_lamports().unwrap(), 1_000);
+    }
+
+    #[test]
+    fn test_eip1559_from_base_fee() {
+        let fee = Eip1559Fee::from_base_fee(30_000_000_000, 2_000_000_000).unwrap();
+        assert_eq!(fee.max_fee_per_gas, 62_000_000_000);
+        assert_eq!(fee.max_cost(21_000).unwrap(), 1_302_000_000_000_000);
+        assert!(Eip1559Fee::from_base_fee(u128::MAX, 1).is_err());
+    }
+
+    #[test]
+    fn test_fixed_estimator() {
+        let estimator = FixedFeeEstimator {
+            solana: None,
+            evm: Some(Eip1559Fee {
+                max_fee_per_gas: 10,
+                max_priority_fee_per_gas: 1,
+            }),
+        };
+        assert!(estimator.solana_fee(&[]).is_err());
+        assert_eq!(estimator.evm_fee(8453).unwrap().max_fee_per_gas, 10);
+
+        let inconsistent = FixedFeeEstimator {
+            solana: None,
+            evm: Some(Eip1559Fee {
+                max_fee_per_gas: 1,
+                max_priority_fee_per_gas: 10,
+            }),
+        };
+        assert!(inconsistent.evm_fee(1).is_err());
+    }
+}
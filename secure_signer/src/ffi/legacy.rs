@@ -0,0 +1,724 @@
+//! The original `signer_*` functions (`include/signer.h`)
+//!
+//! Kept for existing ctypes callers such as `python_integration.py`. New
+//! bindings should use the versioned `coldstar_*` ABI in the parent module,
+//! which reports every [`SignerError`](crate::SignerError) variant instead of
+//! collapsing them into code 4.
+//!
+//! # Memory Management
+//!
+//! All strings returned by FFI functions are allocated by Rust and must be
+//! freed by calling the corresponding `free_*` functions.
+//!
+//! # Thread Safety
+//!
+//! These functions are thread-safe and can be called from multiple threads.
+//!
+//! # Panics
+//!
+//! A panic inside a function is caught before it reaches the caller and
+//! reported as error code 6, as for the `coldstar_*` functions.
+
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+
+use zeroize::Zeroizing;
+
+use super::{catch_panic, PANIC_MESSAGE};
+use crate::crypto::{create_encrypted_key_container, decrypt_and_sign, decrypt_and_sign_evm};
+use crate::encoding::OutputEncoding;
+
+/// Error code for a panic caught at the boundary
+const PANIC_CODE: i32 = 6;
+
+/// Result code for FFI operations
+#[repr(C)]
+pub struct SignerResult {
+    /// 0 for success, non-zero for error
+    pub error_code: i32,
+    /// Result string (JSON for success, error message for failure)
+    /// Must be freed with free_string()
+    pub result: *mut c_char,
+}
+
+impl SignerResult {
+    fn success(result: String) -> Self {
+        Self {
+            error_code: 0,
+            result: CString::new(result).unwrap_or_default().into_raw(),
+        }
+    }
+
+    fn error(code: i32, message: &str) -> Self {
+        Self {
+            error_code: code,
+            result: CString::new(message).unwrap_or_default().into_raw(),
+        }
+    }
+}
+
+/// Run an entry point, turning a panic into [`PANIC_CODE`]
+fn guarded(f: impl FnOnce() -> SignerResult) -> SignerResult {
+    catch_panic(f, || SignerResult::error(PANIC_CODE, PANIC_MESSAGE))
+}
+
+/// Run a raw entry point, turning a panic into [`PANIC_CODE`]
+fn guarded_code(f: impl FnOnce() -> i32) -> i32 {
+    catch_panic(f, || PANIC_CODE)
+}
+
+/// Create an encrypted key container from a private key
+///
+/// # Arguments
+/// * `private_key_b58` - Base58-encoded private key (32 or 64 bytes)
+/// * `passphrase` - Null-terminated passphrase string
+///
+/// # Returns
+/// SignerResult with JSON container on success
+///
+/// # Safety
+/// All pointers must be valid, null-terminated C strings.
+#[no_mangle]
+pub unsafe extern "C" fn signer_create_container(
+    private_key_b58: *const c_char,
+    passphrase: *const c_char,
+) -> SignerResult {
+    guarded(|| {
+        // Validate inputs
+        if private_key_b58.is_null() || passphrase.is_null() {
+            return SignerResult::error(1, "Null pointer argument");
+        }
+
+        let private_key_str = match CStr::from_ptr(private_key_b58).to_str() {
+            Ok(s) => s,
+            Err(_) => return SignerResult::error(2, "Invalid UTF-8 in private key"),
+        };
+
+        let passphrase_str = match CStr::from_ptr(passphrase).to_str() {
+            Ok(s) => s,
+            Err(_) => return SignerResult::error(2, "Invalid UTF-8 in passphrase"),
+        };
+
+        // Decode the private key
+        let private_key = match bs58::decode(private_key_str).into_vec() {
+            Ok(k) => Zeroizing::new(k),
+            Err(e) => return SignerResult::error(3, &format!("Base58 decode error: {}", e)),
+        };
+
+        // Create container
+        match create_encrypted_key_container(&private_key, passphrase_str) {
+            Ok(json) => SignerResult::success(json),
+            Err(e) => SignerResult::error(4, &e.to_string()),
+        }
+    })
+}
+
+/// Decrypt a key container and sign a transaction
+///
+/// # Arguments
+/// * `container_json` - Null-terminated JSON string of the encrypted container
+/// * `passphrase` - Null-terminated passphrase string
+/// * `transaction_b64` - Base64-encoded unsigned transaction bytes
+///
+/// # Returns
+/// SignerResult with JSON signing result on success
+///
+/// # Safety
+/// All pointers must be valid, null-terminated C strings.
+#[no_mangle]
+pub unsafe extern "C" fn signer_sign_transaction(
+    container_json: *const c_char,
+    passphrase: *const c_char,
+    transaction_b64: *const c_char,
+) -> SignerResult {
+    guarded(|| {
+        sign_transaction_encoded(container_json, passphrase, transaction_b64, &OutputEncoding::default())
+    })
+}
+
+unsafe fn sign_transaction_encoded(
+    container_json: *const c_char,
+    passphrase: *const c_char,
+    transaction_b64: *const c_char,
+    encoding: &OutputEncoding,
+) -> SignerResult {
+    // Validate inputs
+    if container_json.is_null() || passphrase.is_null() || transaction_b64.is_null() {
+        return SignerResult::error(1, "Null pointer argument");
+    }
+
+    let container_str = match CStr::from_ptr(container_json).to_str() {
+        Ok(s) => s,
+        Err(_) => return SignerResult::error(2, "Invalid UTF-8 in container"),
+    };
+
+    let passphrase_str = match CStr::from_ptr(passphrase).to_str() {
+        Ok(s) => s,
+        Err(_) => return SignerResult::error(2, "Invalid UTF-8 in passphrase"),
+    };
+
+    let transaction_str = match CStr::from_ptr(transaction_b64).to_str() {
+        Ok(s) => s,
+        Err(_) => return SignerResult::error(2, "Invalid UTF-8 in transaction"),
+    };
+
+    // Decode the transaction
+    let transaction_bytes =
+        match base64::Engine::decode(&base64::engine::general_purpose::STANDARD, transaction_str) {
+            Ok(t) => t,
+            Err(e) => return SignerResult::error(3, &format!("Base64 decode error: {}", e)),
+        };
+
+    // Decrypt and sign
+    match decrypt_and_sign(container_str, passphrase_str, &transaction_bytes)
+        .and_then(|result| result.with_encoding(encoding))
+    {
+        Ok(result) => match serde_json::to_string(&result) {
+            Ok(json) => SignerResult::success(json),
+            Err(e) => SignerResult::error(5, &format!("Serialization error: {}", e)),
+        },
+        Err(e) => SignerResult::error(4, &e.to_string()),
+    }
+}
+
+/// Decrypt a key container and sign a transaction, choosing the output
+/// encodings
+///
+/// # Arguments
+/// * `container_json` - Null-terminated JSON string of the encrypted container
+/// * `passphrase` - Null-terminated passphrase string
+/// * `transaction_b64` - Base64-encoded unsigned transaction bytes
+/// * `encoding_json` - JSON object such as `{"signature":"hex","public_key":"hex"}`
+///   (fields: `signature`, `public_key`, `transaction`; values: `base58`,
+///   `base64`, `hex`); missing fields and a null pointer use the defaults
+///
+/// # Returns
+/// SignerResult with JSON signing result on success
+///
+/// # Safety
+/// All non-null pointers must be valid, null-terminated C strings.
+#[no_mangle]
+pub unsafe extern "C" fn signer_sign_transaction_encoded(
+    container_json: *const c_char,
+    passphrase: *const c_char,
+    transaction_b64: *const c_char,
+    encoding_json: *const c_char,
+) -> SignerResult {
+    guarded(|| {
+        let encoding: OutputEncoding = if encoding_json.is_null() {
+            OutputEncoding::default()
+        } else {
+            let parsed = CStr::from_ptr(encoding_json)
+                .to_str()
+                .map_err(|_| "Invalid UTF-8 in encoding".to_string())
+                .and_then(|s| serde_json::from_str(s).map_err(|e| format!("Invalid encoding: {}", e)));
+            match parsed {
+                Ok(encoding) => encoding,
+                Err(e) => return SignerResult::error(2, &e),
+            }
+        };
+
+        sign_transaction_encoded(container_json, passphrase, transaction_b64, &encoding)
+    })
+}
+
+/// Sign a Solana transaction directly with a base58-encoded private key
+///
+/// # Security Warning
+/// This function accepts a plaintext private key. Prefer using
+/// signer_sign_transaction with an encrypted container for better security.
+///
+/// # Arguments
+/// * `private_key_b58` - Base58-encoded private key
+/// * `message_b64` - Base64-encoded unsigned transaction message; anything
+///   else fails with error code 4
+///
+/// # Returns
+/// SignerResult with JSON signing result on success
+///
+/// # Safety
+/// All pointers must be valid, null-terminated C strings.
+#[no_mangle]
+pub unsafe extern "C" fn signer_sign_direct(
+    private_key_b58: *const c_char,
+    message_b64: *const c_char,
+) -> SignerResult {
+    guarded(|| {
+        if private_key_b58.is_null() || message_b64.is_null() {
+            return SignerResult::error(1, "Null pointer argument");
+        }
+
+        let private_key_str = match CStr::from_ptr(private_key_b58).to_str() {
+            Ok(s) => s,
+            Err(_) => return SignerResult::error(2, "Invalid UTF-8 in private key"),
+        };
+
+        let message_str = match CStr::from_ptr(message_b64).to_str() {
+            Ok(s) => s,
+            Err(_) => return SignerResult::error(2, "Invalid UTF-8 in message"),
+        };
+
+        // Decode inputs
+        let private_key = match bs58::decode(private_key_str).into_vec() {
+            Ok(k) => Zeroizing::new(k),
+            Err(e) => return SignerResult::error(3, &format!("Base58 decode error: {}", e)),
+        };
+
+        let message =
+            match base64::Engine::decode(&base64::engine::general_purpose::STANDARD, message_str) {
+                Ok(m) => m,
+                Err(e) => return SignerResult::error(3, &format!("Base64 decode error: {}", e)),
+            };
+
+        // Sign
+        match crate::crypto::sign_transaction(&private_key, &message) {
+            Ok(result) => match serde_json::to_string(&result) {
+                Ok(json) => SignerResult::success(json),
+                Err(e) => SignerResult::error(5, &format!("Serialization error: {}", e)),
+            },
+            Err(e) => SignerResult::error(4, &e.to_string()),
+        }
+    })
+}
+
+// ════════════════════════════════════════════════════════════
+//  EVM (secp256k1) FFI bindings
+// ════════════════════════════════════════════════════════════
+
+/// Decrypt a key container and sign an EVM transaction hash (secp256k1)
+///
+/// # Arguments
+/// * `container_json` - Null-terminated JSON string of the encrypted container
+/// * `passphrase` - Null-terminated passphrase string
+/// * `message_hash_hex` - Hex-encoded 32-byte keccak256 hash (with or without 0x prefix)
+///
+/// # Returns
+/// SignerResult with JSON EVMSigningResult on success
+///
+/// # Safety
+/// All pointers must be valid, null-terminated C strings.
+#[no_mangle]
+pub unsafe extern "C" fn signer_sign_evm_transaction(
+    container_json: *const c_char,
+    passphrase: *const c_char,
+    message_hash_hex: *const c_char,
+) -> SignerResult {
+    guarded(|| {
+        if container_json.is_null() || passphrase.is_null() || message_hash_hex.is_null() {
+            return SignerResult::error(1, "Null pointer argument");
+        }
+
+        let container_str = match CStr::from_ptr(container_json).to_str() {
+            Ok(s) => s,
+            Err(_) => return SignerResult::error(2, "Invalid UTF-8 in container"),
+        };
+
+        let passphrase_str = match CStr::from_ptr(passphrase).to_str() {
+            Ok(s) => s,
+            Err(_) => return SignerResult::error(2, "Invalid UTF-8 in passphrase"),
+        };
+
+        let hash_str = match CStr::from_ptr(message_hash_hex).to_str() {
+            Ok(s) => s,
+            Err(_) => return SignerResult::error(2, "Invalid UTF-8 in message hash"),
+        };
+
+        // Decode hex hash (strip 0x prefix if present)
+        let hash_hex = hash_str.strip_prefix("0x").unwrap_or(hash_str);
+        let message_hash = match hex::decode(hash_hex) {
+            Ok(h) => h,
+            Err(e) => return SignerResult::error(3, &format!("Hex decode error: {}", e)),
+        };
+
+        // Decrypt and sign
+        match decrypt_and_sign_evm(container_str, passphrase_str, &message_hash) {
+            Ok(result) => match serde_json::to_string(&result) {
+                Ok(json) => SignerResult::success(json),
+                Err(e) => SignerResult::error(5, &format!("Serialization error: {}", e)),
+            },
+            Err(e) => SignerResult::error(4, &e.to_string()),
+        }
+    })
+}
+
+// ════════════════════════════════════════════════════════════
+//  Raw-bytes FFI bindings
+// ════════════════════════════════════════════════════════════
+//
+// These take and return byte buffers with explicit lengths and return only
+// an error code (same codes as `SignerResult`), so callers that sign in a
+// tight loop skip base64/hex/base58 and JSON entirely and allocate nothing
+// that needs freeing.
+
+/// Size of an Ed25519 signature written by the raw functions
+pub const SIGNER_SIGNATURE_SIZE: usize = 64;
+/// Size of an Ed25519 public key written by the raw functions
+pub const SIGNER_PUBLIC_KEY_SIZE: usize = 32;
+/// Size of an EVM signature (r || s || v) written by the raw functions
+pub const SIGNER_EVM_SIGNATURE_SIZE: usize = 65;
+
+/// Copy a base58 value of exactly `out.len()` bytes into `out`
+fn decode_into(value: &str, out: &mut [u8]) -> Result<(), i32> {
+    let bytes = bs58::decode(value).into_vec().map_err(|_| 5)?;
+    if bytes.len() != out.len() {
+        return Err(5);
+    }
+    out.copy_from_slice(&bytes);
+    Ok(())
+}
+
+/// The seed of a 32-byte seed or a 64-byte keypair whose second half is
+/// the seed's public key
+fn direct_seed(private_key: &[u8]) -> Option<&[u8]> {
+    match private_key.len() {
+        32 => Some(private_key),
+        64 => {
+            let (seed, public_key) = private_key.split_at(32);
+            let signing_key = ed25519_dalek::SigningKey::from_bytes(seed.try_into().ok()?);
+            (signing_key.verifying_key().as_bytes() == public_key).then_some(seed)
+        }
+        _ => None,
+    }
+}
+
+/// Decrypt a key container and sign raw transaction message bytes
+///
+/// # Arguments
+/// * `container_json` - Null-terminated JSON string of the encrypted container
+/// * `passphrase` - Null-terminated passphrase string
+/// * `message` / `message_len` - Unsigned transaction message bytes
+/// * `signature_out` - Buffer of `SIGNER_SIGNATURE_SIZE` bytes
+/// * `public_key_out` - Buffer of `SIGNER_PUBLIC_KEY_SIZE` bytes, or null
+///
+/// # Returns
+/// 0 on success, otherwise a `SignerResult` error code
+///
+/// # Safety
+/// The strings must be valid and null-terminated, `message` must point to
+/// `message_len` readable bytes, and the output buffers must be writable
+/// for their documented sizes.
+#[no_mangle]
+pub unsafe extern "C" fn signer_sign_transaction_raw(
+    container_json: *const c_char,
+    passphrase: *const c_char,
+    message: *const u8,
+    message_len: usize,
+    signature_out: *mut u8,
+    public_key_out: *mut u8,
+) -> i32 {
+    guarded_code(|| {
+        if container_json.is_null() || passphrase.is_null() || message.is_null() || signature_out.is_null() {
+            return 1;
+        }
+
+        let (Ok(container_str), Ok(passphrase_str)) =
+            (CStr::from_ptr(container_json).to_str(), CStr::from_ptr(passphrase).to_str())
+        else {
+            return 2;
+        };
+        let message = std::slice::from_raw_parts(message, message_len);
+
+        let result = match decrypt_and_sign(container_str, passphrase_str, message) {
+            Ok(result) => result,
+            Err(_) => return 4,
+        };
+
+        let signature_out = std::slice::from_raw_parts_mut(signature_out, SIGNER_SIGNATURE_SIZE);
+        if let Err(code) = decode_into(&result.signature, signature_out) {
+            return code;
+        }
+        if !public_key_out.is_null() {
+            let public_key_out = std::slice::from_raw_parts_mut(public_key_out, SIGNER_PUBLIC_KEY_SIZE);
+            if let Err(code) = decode_into(&result.public_key, public_key_out) {
+                return code;
+            }
+        }
+        0
+    })
+}
+
+/// Sign raw transaction message bytes directly with a raw private key
+///
+/// # Security Warning
+/// This function accepts a plaintext private key. Prefer
+/// `signer_sign_transaction_raw` with an encrypted container.
+///
+/// # Arguments
+/// * `private_key` / `private_key_len` - 32-byte seed, or 64-byte keypair
+///   (seed || public key) whose public key must match the seed
+/// * `message` / `message_len` - Unsigned transaction message bytes;
+///   anything else fails with error code 4
+/// * `signature_out` - Buffer of `SIGNER_SIGNATURE_SIZE` bytes
+///
+/// # Returns
+/// 0 on success, otherwise a `SignerResult` error code
+///
+/// # Safety
+/// Input pointers must point to the given number of readable bytes and
+/// `signature_out` must be writable for `SIGNER_SIGNATURE_SIZE` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn signer_sign_direct_raw(
+    private_key: *const u8,
+    private_key_len: usize,
+    message: *const u8,
+    message_len: usize,
+    signature_out: *mut u8,
+) -> i32 {
+    guarded_code(|| {
+        if private_key.is_null() || message.is_null() || signature_out.is_null() {
+            return 1;
+        }
+
+        let private_key = std::slice::from_raw_parts(private_key, private_key_len);
+        let message = std::slice::from_raw_parts(message, message_len);
+        let Some(seed) = direct_seed(private_key) else {
+            return 4;
+        };
+
+        let result = match crate::crypto::sign_transaction(seed, message) {
+            Ok(result) => result,
+            Err(_) => return 4,
+        };
+
+        let signature_out = std::slice::from_raw_parts_mut(signature_out, SIGNER_SIGNATURE_SIZE);
+        match decode_into(&result.signature, signature_out) {
+            Ok(()) => 0,
+            Err(code) => code,
+        }
+    })
+}
+
+/// Decrypt a key container and sign a raw 32-byte EVM hash (secp256k1)
+///
+/// # Arguments
+/// * `container_json` - Null-terminated JSON string of the encrypted container
+/// * `passphrase` - Null-terminated passphrase string
+/// * `message_hash` - 32-byte keccak256 hash
+/// * `signature_out` - Buffer of `SIGNER_EVM_SIGNATURE_SIZE` bytes, receives
+///   r || s || v (v is 27 or 28)
+///
+/// # Returns
+/// 0 on success, otherwise a `SignerResult` error code
+///
+/// # Safety
+/// The strings must be valid and null-terminated, `message_hash` must point
+/// to 32 readable bytes, and `signature_out` must be writable for
+/// `SIGNER_EVM_SIGNATURE_SIZE` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn signer_sign_evm_raw(
+    container_json: *const c_char,
+    passphrase: *const c_char,
+    message_hash: *const u8,
+    signature_out: *mut u8,
+) -> i32 {
+    guarded_code(|| {
+        if container_json.is_null() || passphrase.is_null() || message_hash.is_null() || signature_out.is_null() {
+            return 1;
+        }
+
+        let (Ok(container_str), Ok(passphrase_str)) =
+            (CStr::from_ptr(container_json).to_str(), CStr::from_ptr(passphrase).to_str())
+        else {
+            return 2;
+        };
+        let message_hash = std::slice::from_raw_parts(message_hash, 32);
+
+        let result = match decrypt_and_sign_evm(container_str, passphrase_str, message_hash) {
+            Ok(result) => result,
+            Err(_) => return 4,
+        };
+
+        let signature = match hex::decode(result.signature.trim_start_matches("0x")) {
+            Ok(signature) if signature.len() == SIGNER_EVM_SIGNATURE_SIZE => signature,
+            _ => return 5,
+        };
+        std::slice::from_raw_parts_mut(signature_out, SIGNER_EVM_SIGNATURE_SIZE).copy_from_slice(&signature);
+        0
+    })
+}
+
+/// Free a string allocated by Rust
+///
+/// # Safety
+/// The pointer must have been returned by a signer_* function.
+/// After calling this, the pointer is invalid.
+#[no_mangle]
+pub unsafe extern "C" fn signer_free_string(ptr: *mut c_char) {
+    if !ptr.is_null() {
+        // Convert back to CString and let it drop
+        catch_panic(|| drop(CString::from_raw(ptr)), || ());
+    }
+}
+
+/// Free a SignerResult
+///
+/// # Safety
+/// The result must have been returned by a signer_* function.
+#[no_mangle]
+pub unsafe extern "C" fn signer_free_result(result: SignerResult) {
+    signer_free_string(result.result);
+}
+
+/// Get the library version
+///
+/// # Returns
+/// Null-terminated version string. Do NOT free this pointer.
+#[no_mangle]
+pub extern "C" fn signer_version() -> *const c_char {
+    static VERSION: &str = concat!(env!("CARGO_PKG_VERSION"), "\0");
+    VERSION.as_ptr() as *const c_char
+}
+
+/// Get the build metadata as JSON
+///
+/// # Returns
+/// Null-terminated JSON ([`crate::build_info::BuildInfo`]), or null on an
+/// internal error. Free with `signer_free_string`.
+#[no_mangle]
+pub extern "C" fn signer_build_info() -> *mut c_char {
+    catch_panic(
+        || {
+            let json = serde_json::to_string(&crate::build_info::build_info()).expect("BuildInfo serializes");
+            CString::new(json).expect("JSON has no NUL bytes").into_raw()
+        },
+        std::ptr::null_mut,
+    )
+}
+
+/// Check if memory locking is supported on this platform
+///
+/// # Returns
+/// 1 if memory locking is supported, 0 otherwise
+#[no_mangle]
+pub extern "C" fn signer_check_mlock_support() -> i32 {
+    catch_panic(
+        || match crate::secure_buffer::SecureBuffer::new(64) {
+            Ok(buf) => {
+                if buf.is_locked() {
+                    1
+                } else {
+                    0
+                }
+            }
+            Err(_) => 0,
+        },
+        || 0,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CString;
+
+    #[test]
+    fn test_ffi_create_container() {
+        // Generate a test key
+        let mut seed = [0u8; 32];
+        rand::RngCore::fill_bytes(&mut rand::rngs::OsRng, &mut seed);
+        let key_b58 = bs58::encode(&seed).into_string();
+
+        let key_cstr = CString::new(key_b58).unwrap();
+        let pass_cstr = CString::new("test_password").unwrap();
+
+        unsafe {
+            let result = signer_create_container(key_cstr.as_ptr(), pass_cstr.as_ptr());
+            assert_eq!(result.error_code, 0);
+            assert!(!result.result.is_null());
+
+            let result_str = CStr::from_ptr(result.result).to_str().unwrap();
+            assert!(result_str.contains("\"version\":3"));
+
+            signer_free_result(result);
+        }
+    }
+
+    #[test]
+    fn test_ffi_raw_signing_matches_json() {
+        std::env::set_var("SIGNER_ALLOW_INSECURE_MEMORY", "1");
+        let seed = [5u8; 32];
+        let message = crate::crypto::test_transaction(&seed);
+        let expected = crate::crypto::sign_transaction(&seed, &message).unwrap();
+
+        let mut signature = [0u8; SIGNER_SIGNATURE_SIZE];
+        let code = unsafe {
+            signer_sign_direct_raw(seed.as_ptr(), seed.len(), message.as_ptr(), message.len(), signature.as_mut_ptr())
+        };
+        assert_eq!(code, 0);
+        assert_eq!(bs58::encode(signature).into_string(), expected.signature);
+
+        // A 64-byte keypair signs with its seed, if its public half matches
+        let public = ed25519_dalek::SigningKey::from_bytes(&seed).verifying_key();
+        let mut keypair = [seed.as_slice(), public.as_bytes()].concat();
+        let mut from_keypair = [0u8; SIGNER_SIGNATURE_SIZE];
+        let code = unsafe {
+            signer_sign_direct_raw(
+                keypair.as_ptr(),
+                keypair.len(),
+                message.as_ptr(),
+                message.len(),
+                from_keypair.as_mut_ptr(),
+            )
+        };
+        assert_eq!(code, 0);
+        assert_eq!(from_keypair, signature);
+        keypair[63] ^= 1;
+        let code = unsafe {
+            signer_sign_direct_raw(
+                keypair.as_ptr(),
+                keypair.len(),
+                message.as_ptr(),
+                message.len(),
+                from_keypair.as_mut_ptr(),
+            )
+        };
+        assert_eq!(code, 4);
+
+        let container = crate::crypto::EncryptedKeyContainer::encrypt_with_kdf(
+            &seed,
+            "pw",
+            crate::crypto::Cipher::Aes256Gcm,
+            crate::kdf::KdfParams::MINIMUM,
+        )
+        .unwrap();
+        let container_cstr = CString::new(container.to_json().unwrap()).unwrap();
+        let pass_cstr = CString::new("pw").unwrap();
+        let mut public_key = [0u8; SIGNER_PUBLIC_KEY_SIZE];
+        let code = unsafe {
+            signer_sign_transaction_raw(
+                container_cstr.as_ptr(),
+                pass_cstr.as_ptr(),
+                message.as_ptr(),
+                message.len(),
+                signature.as_mut_ptr(),
+                public_key.as_mut_ptr(),
+            )
+        };
+        assert_eq!(code, 0);
+        assert_eq!(bs58::encode(signature).into_string(), expected.signature);
+        assert_eq!(bs58::encode(public_key).into_string(), expected.public_key);
+
+        let code = unsafe {
+            signer_sign_transaction_raw(
+                container_cstr.as_ptr(),
+                pass_cstr.as_ptr(),
+                std::ptr::null(),
+                0,
+                signature.as_mut_ptr(),
+                std::ptr::null_mut(),
+            )
+        };
+        assert_eq!(code, 1);
+    }
+
+    #[test]
+    fn test_ffi_version() {
+        let version_ptr = signer_version();
+        assert!(!version_ptr.is_null());
+
+        unsafe {
+            let version = CStr::from_ptr(version_ptr).to_str().unwrap();
+            assert!(!version.is_empty());
+        }
+    }
+}
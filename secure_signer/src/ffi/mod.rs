@@ -0,0 +1,549 @@
+//! Versioned C ABI
+//!
+//! The `coldstar_*` functions are the stable interface for C, C++, Go and
+//! other languages that link the `cdylib`/`staticlib`. The header,
+//! `include/coldstar.h`, is generated from this module with cbindgen
+//! (`cbindgen --config cbindgen.toml --output include/coldstar.h src/ffi/mod.rs`).
+//!
+//! # Conventions
+//!
+//! - Every fallible function returns a [`ColdstarStatus`]; results are
+//!   written through out-parameters, which are left untouched on failure.
+//! - On failure, [`coldstar_last_error_message`] describes the error. The
+//!   message is per thread and is cleared by the next `coldstar_*` call.
+//! - Memory the library allocates is released only with the matching
+//!   `coldstar_free_*` function, never with the caller's `free`.
+//! - A panic inside the library never unwinds into the caller: the call
+//!   returns [`ColdstarStatus::Panic`] instead. Key material the call held
+//!   lives in zeroize-on-drop buffers and is wiped as the panic unwinds.
+//!   This needs `panic = "unwind"`: build the library with
+//!   `--profile release-ffi`, not `--release` (`panic = "abort"`, where a
+//!   panic ends the process rather than returning).
+//! - [`COLDSTAR_ABI_VERSION`] changes whenever a signature, struct layout
+//!   or status value changes incompatibly; callers should compare it with
+//!   [`coldstar_abi_version`] at load time.
+//!
+//! # Thread Safety
+//!
+//! All functions are thread-safe.
+
+use std::cell::RefCell;
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::panic::AssertUnwindSafe;
+use std::ptr;
+
+use crate::crypto::{create_encrypted_key_container, decrypt_and_sign, decrypt_and_sign_evm};
+use crate::error::SignerError;
+
+pub mod legacy;
+
+pub use legacy::*;
+
+/// Version of the `coldstar_*` ABI described by `include/coldstar.h`
+pub const COLDSTAR_ABI_VERSION: u32 = 1;
+
+/// Size of an Ed25519 signature
+pub const COLDSTAR_ED25519_SIGNATURE_SIZE: usize = 64;
+/// Size of an Ed25519 public key
+pub const COLDSTAR_ED25519_PUBLIC_KEY_SIZE: usize = 32;
+/// Size of an EVM signature (r || s || v)
+pub const COLDSTAR_EVM_SIGNATURE_SIZE: usize = 65;
+/// Size of an EVM address
+pub const COLDSTAR_EVM_ADDRESS_SIZE: usize = 20;
+/// Size of the hash signed by `coldstar_sign_evm_hash`
+pub const COLDSTAR_EVM_HASH_SIZE: usize = 32;
+
+/// Status returned by every fallible `coldstar_*` function
+///
+/// Values below 100 are argument errors detected at the boundary; values
+/// from 100 mirror the variants of [`SignerError`] one to one. New values
+/// may be added without an ABI version change, so treat unknown values as
+/// generic failures.
+#[repr(i32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColdstarStatus {
+    Ok = 0,
+    /// A required pointer argument was null
+    NullPointer = 1,
+    /// A string argument was not valid UTF-8
+    InvalidUtf8 = 2,
+    /// The library panicked; the call was abandoned and wrote no output
+    Panic = 3,
+
+    MemoryLockFailed = 100,
+    KeyDerivationFailed = 101,
+    /// Wrong passphrase or corrupted container
+    DecryptionFailed = 102,
+    InvalidKeyFormat = 103,
+    SigningFailed = 104,
+    InvalidTransaction = 105,
+    ParseError = 106,
+    SerializationError = 107,
+    Base58Error = 108,
+    Base64Error = 109,
+    ContainerError = 110,
+    IoError = 111,
+    BroadcastError = 112,
+    PolicyViolation = 113,
+    BackendError = 114,
+    DerivationError = 115,
+    CeremonyError = 116,
+    FeeEstimationError = 117,
+    PlatformError = 118,
+    IntegrityError = 119,
+    KmsError = 120,
+    AuditError = 121,
+    EntropyError = 122,
+    SessionLocked = 123,
+    IdempotencyConflict = 124,
+    ContainerNotValid = 125,
+    RotationError = 126,
+    AttestationError = 127,
+}
+
+impl From<&SignerError> for ColdstarStatus {
+    fn from(error: &SignerError) -> Self {
+        // Exhaustive, so a new SignerError variant cannot go unmapped
+        match error {
+            SignerError::MemoryLockFailed(_) => Self::MemoryLockFailed,
+            SignerError::KeyDerivationFailed(_) => Self::KeyDerivationFailed,
+            SignerError::DecryptionFailed => Self::DecryptionFailed,
+            SignerError::InvalidKeyFormat(_) => Self::InvalidKeyFormat,
+            SignerError::SigningFailed(_) => Self::SigningFailed,
+            SignerError::InvalidTransaction(_) => Self::InvalidTransaction,
+            SignerError::ParseError(_) => Self::ParseError,
+            SignerError::SerializationError(_) => Self::SerializationError,
+            SignerError::Base58Error(_) => Self::Base58Error,
+            SignerError::Base64Error(_) => Self::Base64Error,
+            SignerError::ContainerError(_) => Self::ContainerError,
+            SignerError::IoError(_) => Self::IoError,
+            SignerError::BroadcastError(_) => Self::BroadcastError,
+            SignerError::PolicyViolation(_) => Self::PolicyViolation,
+            SignerError::BackendError(_) => Self::BackendError,
+            SignerError::DerivationError(_) => Self::DerivationError,
+            SignerError::CeremonyError(_) => Self::CeremonyError,
+            SignerError::FeeEstimationError(_) => Self::FeeEstimationError,
+            SignerError::PlatformError(_) => Self::PlatformError,
+            SignerError::IntegrityError(_) => Self::IntegrityError,
+            SignerError::KmsError(_) => Self::KmsError,
+            SignerError::AuditError(_) => Self::AuditError,
+            SignerError::EntropyError(_) => Self::EntropyError,
+            SignerError::SessionLocked(_) => Self::SessionLocked,
+            SignerError::IdempotencyConflict(_) => Self::IdempotencyConflict,
+            SignerError::ContainerNotValid(_) => Self::ContainerNotValid,
+            SignerError::RotationError(_) => Self::RotationError,
+            SignerError::AttestationError(_) => Self::AttestationError,
+        }
+    }
+}
+
+/// Bytes allocated by the library; release with `coldstar_free_buffer`
+#[repr(C)]
+pub struct ColdstarBuffer {
+    pub data: *mut u8,
+    pub len: usize,
+}
+
+impl ColdstarBuffer {
+    const EMPTY: Self = Self {
+        data: ptr::null_mut(),
+        len: 0,
+    };
+
+    fn from_vec(bytes: Vec<u8>) -> Self {
+        let bytes = Box::into_raw(bytes.into_boxed_slice());
+        Self {
+            data: bytes as *mut u8,
+            len: bytes.len(),
+        }
+    }
+}
+
+/// Output of `coldstar_sign_solana`; release with
+/// `coldstar_free_solana_signature`
+#[repr(C)]
+pub struct ColdstarSolanaSignature {
+    pub signature: [u8; COLDSTAR_ED25519_SIGNATURE_SIZE],
+    pub public_key: [u8; COLDSTAR_ED25519_PUBLIC_KEY_SIZE],
+    /// The transaction with the signature inserted
+    pub signed_transaction: ColdstarBuffer,
+}
+
+/// Output of `coldstar_sign_evm_hash`; owns no memory
+#[repr(C)]
+pub struct ColdstarEvmSignature {
+    /// r || s || v, with v = 27 or 28
+    pub signature: [u8; COLDSTAR_EVM_SIGNATURE_SIZE],
+    pub address: [u8; COLDSTAR_EVM_ADDRESS_SIZE],
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(message: String) {
+    // Messages never contain NUL, but drop them rather than the message
+    let message = CString::new(message.replace('\0', "")).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+}
+
+/// Last error for a caught panic; the payload is not included, as it
+/// could quote the data being processed
+pub(crate) const PANIC_MESSAGE: &str = "internal error: the signer panicked";
+
+/// Run `f`, or `on_panic` if it panics, so that no unwind reaches C
+///
+/// Everything `f` owns is dropped while unwinding, so its `SecureBuffer`s
+/// and other zeroizing values are wiped before this returns.
+pub(crate) fn catch_panic<T>(f: impl FnOnce() -> T, on_panic: impl FnOnce() -> T) -> T {
+    std::panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|_| on_panic())
+}
+
+/// Run one ABI call: clear the last error, then record the failure, if any
+fn call(f: impl FnOnce() -> Result<(), (ColdstarStatus, String)>) -> ColdstarStatus {
+    LAST_ERROR.with(|last| *last.borrow_mut() = None);
+    let result = catch_panic(f, || Err((ColdstarStatus::Panic, PANIC_MESSAGE.to_string())));
+    match result {
+        Ok(()) => ColdstarStatus::Ok,
+        Err((status, message)) => {
+            set_last_error(message);
+            status
+        }
+    }
+}
+
+fn signer_error(error: SignerError) -> (ColdstarStatus, String) {
+    (ColdstarStatus::from(&error), error.to_string())
+}
+
+fn non_null<T>(pointer: *const T, name: &str) -> Result<(), (ColdstarStatus, String)> {
+    if pointer.is_null() {
+        return Err((ColdstarStatus::NullPointer, format!("{} is null", name)));
+    }
+    Ok(())
+}
+
+unsafe fn c_str<'a>(pointer: *const c_char, name: &str) -> Result<&'a str, (ColdstarStatus, String)> {
+    non_null(pointer, name)?;
+    CStr::from_ptr(pointer)
+        .to_str()
+        .map_err(|_| (ColdstarStatus::InvalidUtf8, format!("{} is not valid UTF-8", name)))
+}
+
+unsafe fn byte_slice<'a>(pointer: *const u8, len: usize, name: &str) -> Result<&'a [u8], (ColdstarStatus, String)> {
+    if len == 0 {
+        return Ok(&[]);
+    }
+    non_null(pointer, name)?;
+    Ok(std::slice::from_raw_parts(pointer, len))
+}
+
+/// Decode a value the signer produced in a fixed text encoding
+fn decode_fixed<const N: usize>(bytes: Result<Vec<u8>, String>) -> Result<[u8; N], (ColdstarStatus, String)> {
+    bytes
+        .and_then(|bytes| <[u8; N]>::try_from(bytes).map_err(|b| format!("unexpected length {}", b.len())))
+        .map_err(|e| (ColdstarStatus::SerializationError, e))
+}
+
+/// The ABI version this library implements ([`COLDSTAR_ABI_VERSION`])
+#[no_mangle]
+pub extern "C" fn coldstar_abi_version() -> u32 {
+    COLDSTAR_ABI_VERSION
+}
+
+/// The crate version, as a static string that must not be freed
+#[no_mangle]
+pub extern "C" fn coldstar_version() -> *const c_char {
+    static VERSION: &str = concat!(env!("CARGO_PKG_VERSION"), "\0");
+    VERSION.as_ptr() as *const c_char
+}
+
+/// Message for the last failed call on this thread, or null
+///
+/// The pointer stays valid until the next `coldstar_*` call on the same
+/// thread; copy the string to keep it. Do not free it.
+#[no_mangle]
+pub extern "C" fn coldstar_last_error_message() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map_or(ptr::null(), |message| message.as_ptr()))
+}
+
+/// Whether secure buffers can be locked in memory on this system
+#[no_mangle]
+pub extern "C" fn coldstar_mlock_supported() -> bool {
+    catch_panic(
+        || crate::secure_buffer::SecureBuffer::new(64).is_ok_and(|buffer| buffer.is_locked()),
+        || false,
+    )
+}
+
+/// Build metadata as a JSON object
+///
+/// # Safety
+/// `json_out` must be writable. The string written to it must be released
+/// with `coldstar_free_string`.
+#[no_mangle]
+pub unsafe extern "C" fn coldstar_build_info(json_out: *mut *mut c_char) -> ColdstarStatus {
+    call(|| {
+        non_null(json_out, "json_out")?;
+        let json = serde_json::to_string(&crate::build_info::build_info()).map_err(|e| signer_error(e.into()))?;
+        *json_out = CString::new(json).expect("JSON has no NUL bytes").into_raw();
+        Ok(())
+    })
+}
+
+/// Encrypt a private key into a new container
+///
+/// # Arguments
+/// * `private_key` / `private_key_len` - 32-byte seed or 64-byte keypair
+/// * `passphrase` - Null-terminated UTF-8 passphrase
+/// * `container_json_out` - Receives the container JSON
+///
+/// # Safety
+/// `private_key` must point to `private_key_len` readable bytes,
+/// `passphrase` must be a valid C string and `container_json_out` must be
+/// writable. The string written to it must be released with
+/// `coldstar_free_string`.
+#[no_mangle]
+pub unsafe extern "C" fn coldstar_create_container(
+    private_key: *const u8,
+    private_key_len: usize,
+    passphrase: *const c_char,
+    container_json_out: *mut *mut c_char,
+) -> ColdstarStatus {
+    call(|| {
+        let private_key = byte_slice(private_key, private_key_len, "private_key")?;
+        let passphrase = c_str(passphrase, "passphrase")?;
+        non_null(container_json_out, "container_json_out")?;
+        let json = create_encrypted_key_container(private_key, passphrase).map_err(signer_error)?;
+        *container_json_out = CString::new(json).expect("JSON has no NUL bytes").into_raw();
+        Ok(())
+    })
+}
+
+/// Decrypt a container and sign a Solana transaction
+///
+/// # Arguments
+/// * `container_json` - Null-terminated container JSON
+/// * `passphrase` - Null-terminated UTF-8 passphrase
+/// * `message` / `message_len` - Serialized unsigned transaction message;
+///   anything else, or a message this key is not a required signer of,
+///   fails with `COLDSTAR_STATUS_INVALID_TRANSACTION`
+/// * `signature_out` - Receives the signature, public key and signed
+///   transaction
+///
+/// # Safety
+/// The strings must be valid C strings, `message` must point to
+/// `message_len` readable bytes and `signature_out` must be writable.
+/// On success, `signature_out` must be released with
+/// `coldstar_free_solana_signature`.
+#[no_mangle]
+pub unsafe extern "C" fn coldstar_sign_solana(
+    container_json: *const c_char,
+    passphrase: *const c_char,
+    message: *const u8,
+    message_len: usize,
+    signature_out: *mut ColdstarSolanaSignature,
+) -> ColdstarStatus {
+    call(|| {
+        let container_json = c_str(container_json, "container_json")?;
+        let passphrase = c_str(passphrase, "passphrase")?;
+        let message = byte_slice(message, message_len, "message")?;
+        non_null(signature_out, "signature_out")?;
+
+        let result = decrypt_and_sign(container_json, passphrase, message).map_err(signer_error)?;
+        let signature = decode_fixed(bs58::decode(&result.signature).into_vec().map_err(|e| e.to_string()))?;
+        let public_key = decode_fixed(bs58::decode(&result.public_key).into_vec().map_err(|e| e.to_string()))?;
+        let signed_transaction = match result.signed_transaction {
+            Some(b64) => ColdstarBuffer::from_vec(
+                base64::Engine::decode(&base64::engine::general_purpose::STANDARD, b64)
+                    .map_err(|e| signer_error(e.into()))?,
+            ),
+            None => ColdstarBuffer::EMPTY,
+        };
+        ptr::write(
+            signature_out,
+            ColdstarSolanaSignature {
+                signature,
+                public_key,
+                signed_transaction,
+            },
+        );
+        Ok(())
+    })
+}
+
+/// Decrypt a container and sign a 32-byte EVM hash (secp256k1)
+///
+/// # Arguments
+/// * `container_json` - Null-terminated container JSON
+/// * `passphrase` - Null-terminated UTF-8 passphrase
+/// * `hash` - `COLDSTAR_EVM_HASH_SIZE` bytes, normally a keccak256 digest
+/// * `signature_out` - Receives the signature and signing address
+///
+/// # Safety
+/// The strings must be valid C strings, `hash` must point to
+/// `COLDSTAR_EVM_HASH_SIZE` readable bytes and `signature_out` must be
+/// writable.
+#[no_mangle]
+pub unsafe extern "C" fn coldstar_sign_evm_hash(
+    container_json: *const c_char,
+    passphrase: *const c_char,
+    hash: *const u8,
+    signature_out: *mut ColdstarEvmSignature,
+) -> ColdstarStatus {
+    call(|| {
+        let container_json = c_str(container_json, "container_json")?;
+        let passphrase = c_str(passphrase, "passphrase")?;
+        non_null(hash, "hash")?;
+        let hash = std::slice::from_raw_parts(hash, COLDSTAR_EVM_HASH_SIZE);
+        non_null(signature_out, "signature_out")?;
+
+        let result = decrypt_and_sign_evm(container_json, passphrase, hash).map_err(signer_error)?;
+        let from_hex = |value: &str| hex::decode(value.trim_start_matches("0x")).map_err(|e| e.to_string());
+        ptr::write(
+            signature_out,
+            ColdstarEvmSignature {
+                signature: decode_fixed(from_hex(&result.signature))?,
+                address: decode_fixed(from_hex(&result.address))?,
+            },
+        );
+        Ok(())
+    })
+}
+
+/// Release a string returned through an out-parameter
+///
+/// # Safety
+/// `string` must be null or a string from a `coldstar_*` function that has
+/// not been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn coldstar_free_string(string: *mut c_char) {
+    if !string.is_null() {
+        catch_panic(|| drop(CString::from_raw(string)), || ());
+    }
+}
+
+/// Release a buffer and reset it to empty, so freeing twice is harmless
+///
+/// # Safety
+/// `buffer` must be null or point to a buffer filled in by a `coldstar_*`
+/// function.
+#[no_mangle]
+pub unsafe extern "C" fn coldstar_free_buffer(buffer: *mut ColdstarBuffer) {
+    let Some(buffer) = buffer.as_mut() else {
+        return;
+    };
+    if !buffer.data.is_null() {
+        let data = ptr::slice_from_raw_parts_mut(buffer.data, buffer.len);
+        catch_panic(|| drop(Box::from_raw(data)), || ());
+    }
+    *buffer = ColdstarBuffer::EMPTY;
+}
+
+/// Release the memory owned by a `ColdstarSolanaSignature`
+///
+/// # Safety
+/// `signature` must be null or point to a value filled in by
+/// `coldstar_sign_solana`.
+#[no_mangle]
+pub unsafe extern "C" fn coldstar_free_solana_signature(signature: *mut ColdstarSolanaSignature) {
+    if let Some(signature) = signature.as_mut() {
+        coldstar_free_buffer(&mut signature.signed_transaction);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::{Cipher, EncryptedKeyContainer};
+    use crate::kdf::KdfParams;
+
+    fn last_error() -> String {
+        unsafe { CStr::from_ptr(coldstar_last_error_message()) }.to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_abi_signing_and_errors() {
+        std::env::set_var("SIGNER_ALLOW_INSECURE_MEMORY", "1");
+        let seed = [9u8; 32];
+        let container = EncryptedKeyContainer::encrypt_with_kdf(&seed, "pw", Cipher::Aes256Gcm, KdfParams::MINIMUM)
+            .unwrap()
+            .to_json()
+            .unwrap();
+        let container = CString::new(container).unwrap();
+        let passphrase = CString::new("pw").unwrap();
+        let message = crate::crypto::test_transaction(&seed);
+
+        let mut out = std::mem::MaybeUninit::<ColdstarSolanaSignature>::uninit();
+        let status = unsafe {
+            coldstar_sign_solana(container.as_ptr(), passphrase.as_ptr(), message.as_ptr(), message.len(), out.as_mut_ptr())
+        };
+        assert_eq!(status, ColdstarStatus::Ok);
+        assert!(coldstar_last_error_message().is_null());
+        let mut out = unsafe { out.assume_init() };
+        let expected = crate::crypto::sign_transaction(&seed, &message).unwrap();
+        assert_eq!(bs58::encode(out.signature).into_string(), expected.signature);
+        assert_eq!(bs58::encode(out.public_key).into_string(), expected.public_key);
+        unsafe {
+            coldstar_free_solana_signature(&mut out);
+            coldstar_free_solana_signature(&mut out);
+        }
+
+        let mut evm = std::mem::MaybeUninit::<ColdstarEvmSignature>::uninit();
+        let status =
+            unsafe { coldstar_sign_evm_hash(container.as_ptr(), passphrase.as_ptr(), [1u8; 32].as_ptr(), evm.as_mut_ptr()) };
+        assert_eq!(status, ColdstarStatus::Ok);
+        let evm = unsafe { evm.assume_init() };
+        let expected = crate::crypto::decrypt_and_sign_evm(container.to_str().unwrap(), "pw", &[1u8; 32]).unwrap();
+        assert_eq!(format!("0x{}", hex::encode(evm.signature)), expected.signature);
+        assert_eq!(hex::encode(evm.address), expected.address[2..].to_lowercase());
+
+        // Wrong passphrase maps to its own status, with a message
+        let wrong = CString::new("wrong").unwrap();
+        let status = unsafe {
+            coldstar_sign_solana(container.as_ptr(), wrong.as_ptr(), message.as_ptr(), message.len(), ptr::null_mut())
+        };
+        assert_eq!(status, ColdstarStatus::NullPointer);
+        assert_eq!(last_error(), "signature_out is null");
+        let mut out = std::mem::MaybeUninit::<ColdstarSolanaSignature>::uninit();
+        let status = unsafe {
+            coldstar_sign_solana(container.as_ptr(), wrong.as_ptr(), message.as_ptr(), message.len(), out.as_mut_ptr())
+        };
+        assert_eq!(status, ColdstarStatus::DecryptionFailed);
+        assert!(last_error().contains("invalid passphrase"));
+    }
+
+    #[test]
+    fn test_abi_create_container_and_header() {
+        std::env::set_var("SIGNER_ALLOW_INSECURE_MEMORY", "1");
+        let passphrase = CString::new("pw").unwrap();
+        let mut json = ptr::null_mut();
+        let status = unsafe { coldstar_create_container([3u8; 32].as_ptr(), 32, passphrase.as_ptr(), &mut json) };
+        assert_eq!(status, ColdstarStatus::Ok);
+        assert!(unsafe { CStr::from_ptr(json) }.to_str().unwrap().contains("\"version\":3"));
+        unsafe { coldstar_free_string(json) };
+
+        let status = unsafe { coldstar_create_container([3u8; 31].as_ptr(), 31, passphrase.as_ptr(), &mut json) };
+        assert_eq!(status, ColdstarStatus::InvalidKeyFormat);
+
+        // A panic is contained, and what the call held is still dropped
+        let key = crate::secure_buffer::SecureBuffer::from_slice(&[7u8; 32]).unwrap();
+        let status = call(move || {
+            let _key = key;
+            panic!("internal assertion");
+        });
+        assert_eq!(status, ColdstarStatus::Panic);
+        assert_eq!(last_error(), PANIC_MESSAGE);
+        assert_eq!(legacy::signer_check_mlock_support(), coldstar_mlock_supported() as i32);
+
+        // The shipped header must be regenerated when the ABI changes
+        let header = include_str!("../../include/coldstar.h");
+        assert!(header.contains(&format!("#define COLDSTAR_ABI_VERSION {}", COLDSTAR_ABI_VERSION)));
+        for function in include_str!("mod.rs").split("extern \"C\" fn ").skip(1) {
+            let name = &function[..function.find('(').unwrap()];
+            assert!(header.contains(name), "{} missing from include/coldstar.h", name);
+        }
+        assert_eq!(coldstar_abi_version(), COLDSTAR_ABI_VERSION);
+    }
+}
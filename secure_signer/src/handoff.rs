@@ -0,0 +1,412 @@
+//! Key handoff between processes
+//!
+//! Moves an unlocked key from one coldstar process to another (a UI
+//! process that asked for the passphrase, and a sandboxed signer) without
+//! re-encrypting it under a passphrase or writing it anywhere in plaintext:
+//!
+//! 1. The receiver creates a [`HandoffReceiver`] and sends its
+//!    [`HandoffRequest`]: an ephemeral X25519 public key and an expiry.
+//! 2. The sender unlocks its container and calls
+//!    [`ContainerBackend::wrap_for_handoff`], which makes its own ephemeral
+//!    key, derives the session key (HKDF-SHA256 over the shared secret) and
+//!    seals the seed under it with XChaCha20-Poly1305, returning a
+//!    [`HandoffPackage`].
+//! 3. The receiver [`accept`](HandoffReceiver::accept)s the package into a
+//!    [`SigningSession`].
+//!
+//! The package carries the container's `not_after` (bound into the
+//! ciphertext): [`accept`](HandoffReceiver::accept) refuses a key whose
+//! validity window has closed, and the session it returns locks at that
+//! time at the latest.
+//!
+//! The sender zeroizes its session key as soon as the seed is sealed. The
+//! receiver's ephemeral secret lives in locked memory under an
+//! [`IdleWatchdog`], which zeroizes it at the expiry or after one
+//! `accept`, whichever comes first.
+//!
+//! The handshake is not authenticated: exchange the messages over a
+//! channel only the two processes share (a socketpair or inherited pipe).
+
+use std::time::Duration;
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chacha20poly1305::aead::{AeadInPlace, KeyInit};
+use chacha20poly1305::{Tag, XChaCha20Poly1305, XNonce};
+use ed25519_dalek::SigningKey;
+use hkdf::Hkdf;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use x25519_dalek::{EphemeralSecret, PublicKey, StaticSecret};
+
+use crate::backend::ContainerBackend;
+use crate::crypto::{get_locking_mode, EncryptedKeyContainer, ED25519_SEED_SIZE};
+use crate::entropy::{fill_random, EntropyRng};
+use crate::error::SignerError;
+use crate::secure_buffer::SecureBuffer;
+use crate::session::{SessionConfig, SigningSession};
+use crate::validity::{unix_now, Validity};
+use crate::watchdog::IdleWatchdog;
+
+/// Handoff message format version (2 adds `not_after`)
+const HANDOFF_VERSION: u8 = 2;
+
+/// HKDF info string for the session key
+const HKDF_INFO: &[u8] = b"coldstar-handoff-v2";
+
+const NONCE_SIZE: usize = 24;
+const TAG_SIZE: usize = 16;
+
+/// The receiver's half of the handshake
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct HandoffRequest {
+    /// Message format version
+    pub version: u8,
+    /// Ephemeral X25519 public key (base64)
+    pub public_key: String,
+    /// Unix timestamp after which the receiver refuses the package
+    pub expires_at: u64,
+}
+
+/// A key sealed to one [`HandoffRequest`]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct HandoffPackage {
+    /// Message format version
+    pub version: u8,
+    /// The sender's ephemeral X25519 public key (base64)
+    pub sender_key: String,
+    /// The handed-off key's Solana public key (base58)
+    pub public_key: String,
+    /// The request's expiry, bound into the ciphertext
+    pub expires_at: u64,
+    /// End of the container's validity window, bound into the ciphertext
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub not_after: Option<u64>,
+    /// XChaCha20-Poly1305 nonce (base64)
+    pub nonce: String,
+    /// Sealed seed and tag (base64)
+    pub ciphertext: String,
+}
+
+impl HandoffPackage {
+    /// Serialize the package to JSON
+    pub fn to_json(&self) -> Result<String, SignerError> {
+        serde_json::to_string(self).map_err(|e| SignerError::SerializationError(e.to_string()))
+    }
+
+    /// Parse a package from JSON
+    pub fn from_json(json: &str) -> Result<Self, SignerError> {
+        serde_json::from_str(json).map_err(|e| SignerError::SerializationError(e.to_string()))
+    }
+}
+
+/// The receiving end of a handoff, valid until accepted or expired
+pub struct HandoffReceiver {
+    secret: IdleWatchdog<SecureBuffer>,
+    request: HandoffRequest,
+}
+
+impl HandoffReceiver {
+    /// Generate an ephemeral key that accepts one package within `ttl`
+    pub fn new(ttl: Duration) -> Result<Self, SignerError> {
+        let mut secret = SecureBuffer::with_mode(32, get_locking_mode())?;
+        fill_random(secret.as_mut_slice())?;
+        let public_key = PublicKey::from(&static_secret(&secret));
+
+        Ok(Self {
+            secret: IdleWatchdog::new(secret, ttl).expire_after(ttl),
+            request: HandoffRequest {
+                version: HANDOFF_VERSION,
+                public_key: BASE64.encode(public_key.as_bytes()),
+                expires_at: unix_now() + ttl.as_secs().max(1),
+            },
+        })
+    }
+
+    /// The request to send to the key's current holder
+    pub fn request(&self) -> &HandoffRequest {
+        &self.request
+    }
+
+    /// Open `package` into a signing session
+    ///
+    /// Fails once the key's validity window has closed, and the session
+    /// locks when it does. The ephemeral secret is zeroized afterwards,
+    /// whether or not the package opened.
+    pub fn accept(&self, package: &HandoffPackage, config: SessionConfig) -> Result<SigningSession, SignerError> {
+        let result = self
+            .secret
+            .with(|secret| self.open(secret, package))
+            .map_err(|_| SignerError::ContainerError("handoff request expired or already used".to_string()))
+            .and_then(|opened| opened);
+        self.secret.lock();
+        let mut seed = result?;
+
+        let validity = Validity {
+            not_before: None,
+            not_after: package.not_after,
+        };
+        if let Err(e) = validity.check(unix_now()) {
+            seed.zeroize();
+            return Err(e);
+        }
+        Ok(SigningSession::from_key(seed, Some(package.public_key.clone()), config.within(validity)))
+    }
+
+    /// Whether the request can no longer be accepted
+    pub fn is_expired(&self) -> bool {
+        self.secret.is_locked()
+    }
+
+    /// Zeroize the ephemeral secret now
+    pub fn close(&self) {
+        self.secret.lock();
+    }
+
+    fn open(&self, secret: &SecureBuffer, package: &HandoffPackage) -> Result<SecureBuffer, SignerError> {
+        if package.version != HANDOFF_VERSION {
+            return Err(SignerError::ContainerError(format!(
+                "unsupported handoff version {}",
+                package.version
+            )));
+        }
+        if package.expires_at != self.request.expires_at {
+            return Err(SignerError::ContainerError("package was made for another request".to_string()));
+        }
+        let sender = decode_key(&package.sender_key)?;
+        let receiver = decode_key(&self.request.public_key)?;
+        let nonce: [u8; NONCE_SIZE] = BASE64
+            .decode(&package.nonce)?
+            .try_into()
+            .map_err(|_| SignerError::ContainerError("invalid handoff nonce".to_string()))?;
+        let ciphertext = BASE64.decode(&package.ciphertext)?;
+        if ciphertext.len() != ED25519_SEED_SIZE + TAG_SIZE {
+            return Err(SignerError::ContainerError("invalid handoff ciphertext".to_string()));
+        }
+
+        let shared = static_secret(secret).diffie_hellman(&sender);
+        if !shared.was_contributory() {
+            return Err(SignerError::DecryptionFailed);
+        }
+        let mut key = session_key(shared.as_bytes(), &sender, &receiver)?;
+        drop(shared);
+
+        let (sealed, tag) = ciphertext.split_at(ED25519_SEED_SIZE);
+        let mut seed = SecureBuffer::from_slice_with_mode(sealed, get_locking_mode())?;
+        let result = XChaCha20Poly1305::new_from_slice(key.as_slice())
+            .map_err(|e| SignerError::KeyDerivationFailed(e.to_string()))?
+            .decrypt_in_place_detached(
+                XNonce::from_slice(&nonce),
+                &associated_data(package, &sender, &receiver)?,
+                seed.as_mut_slice(),
+                Tag::from_slice(tag),
+            );
+        key.zeroize();
+        if result.is_err() {
+            seed.zeroize();
+            return Err(SignerError::DecryptionFailed);
+        }
+
+        let seed_bytes: &[u8; ED25519_SEED_SIZE] = seed.as_slice().try_into().expect("seed-sized buffer");
+        let public_key = bs58::encode(SigningKey::from_bytes(seed_bytes).verifying_key().as_bytes()).into_string();
+        if public_key != package.public_key {
+            seed.zeroize();
+            return Err(SignerError::ContainerError("handed-off key does not match its public key".to_string()));
+        }
+        Ok(seed)
+    }
+}
+
+impl ContainerBackend<'_> {
+    /// Seal this backend's key to `request`, for a [`HandoffReceiver`] in
+    /// another process
+    pub fn wrap_for_handoff(&self, request: &HandoffRequest) -> Result<HandoffPackage, SignerError> {
+        if request.version != HANDOFF_VERSION {
+            return Err(SignerError::ContainerError(format!(
+                "unsupported handoff version {}",
+                request.version
+            )));
+        }
+        if request.expires_at <= unix_now() {
+            return Err(SignerError::ContainerError("handoff request has expired".to_string()));
+        }
+        let receiver = decode_key(&request.public_key)?;
+
+        let mut seed = self.decrypt_key()?;
+        let sealed = seal(&seed, request, &receiver, self.container().validity().not_after);
+        seed.zeroize();
+        sealed
+    }
+}
+
+/// Seal `seed` to `request` ([`ContainerBackend::wrap_for_handoff`] with a
+/// container and passphrase)
+pub fn wrap_for_handoff(
+    container: &EncryptedKeyContainer,
+    passphrase: &str,
+    request: &HandoffRequest,
+) -> Result<HandoffPackage, SignerError> {
+    ContainerBackend::new(container, passphrase).wrap_for_handoff(request)
+}
+
+fn seal(
+    seed: &SecureBuffer,
+    request: &HandoffRequest,
+    receiver: &PublicKey,
+    not_after: Option<u64>,
+) -> Result<HandoffPackage, SignerError> {
+    let seed_bytes: &[u8; ED25519_SEED_SIZE] = seed
+        .as_slice()
+        .try_into()
+        .map_err(|_| SignerError::InvalidKeyFormat(seed.len()))?;
+    let ephemeral = EphemeralSecret::random_from_rng(EntropyRng);
+    let sender = PublicKey::from(&ephemeral);
+    let shared = ephemeral.diffie_hellman(receiver);
+    if !shared.was_contributory() {
+        return Err(SignerError::ContainerError("handoff key is a low-order point".to_string()));
+    }
+    let mut key = session_key(shared.as_bytes(), &sender, receiver)?;
+    drop(shared);
+
+    let mut nonce = [0u8; NONCE_SIZE];
+    fill_random(&mut nonce)?;
+    let mut package = HandoffPackage {
+        version: HANDOFF_VERSION,
+        sender_key: BASE64.encode(sender.as_bytes()),
+        public_key: bs58::encode(SigningKey::from_bytes(seed_bytes).verifying_key().as_bytes()).into_string(),
+        expires_at: request.expires_at,
+        not_after,
+        nonce: BASE64.encode(nonce),
+        ciphertext: String::new(),
+    };
+
+    let mut sealed = SecureBuffer::from_slice_with_mode(seed.as_slice(), get_locking_mode())?;
+    let tag = XChaCha20Poly1305::new_from_slice(key.as_slice())
+        .map_err(|e| SignerError::KeyDerivationFailed(e.to_string()))
+        .and_then(|cipher| {
+            cipher
+                .encrypt_in_place_detached(
+                    XNonce::from_slice(&nonce),
+                    &associated_data(&package, &sender, receiver)?,
+                    sealed.as_mut_slice(),
+                )
+                .map_err(|_| SignerError::SigningFailed("handoff encryption failed".to_string()))
+        });
+    key.zeroize();
+    let tag = tag?;
+    package.ciphertext = BASE64.encode([sealed.as_slice(), tag.as_slice()].concat());
+    Ok(package)
+}
+
+/// HKDF-SHA256 over the shared secret, salted with both public keys
+fn session_key(shared: &[u8; 32], sender: &PublicKey, receiver: &PublicKey) -> Result<SecureBuffer, SignerError> {
+    let mut salt = [0u8; 64];
+    salt[..32].copy_from_slice(sender.as_bytes());
+    salt[32..].copy_from_slice(receiver.as_bytes());
+
+    let mut key = SecureBuffer::with_mode(32, get_locking_mode())?;
+    Hkdf::<Sha256>::new(Some(&salt), shared)
+        .expand(HKDF_INFO, key.as_mut_slice())
+        .map_err(|e| SignerError::KeyDerivationFailed(e.to_string()))?;
+    Ok(key)
+}
+
+/// version || sender key || receiver key || expiry || not_after (`u64::MAX`
+/// for none) || Solana public key
+fn associated_data(package: &HandoffPackage, sender: &PublicKey, receiver: &PublicKey) -> Result<Vec<u8>, SignerError> {
+    let mut aad = vec![package.version];
+    aad.extend_from_slice(sender.as_bytes());
+    aad.extend_from_slice(receiver.as_bytes());
+    aad.extend_from_slice(&package.expires_at.to_le_bytes());
+    aad.extend_from_slice(&package.not_after.unwrap_or(u64::MAX).to_le_bytes());
+    aad.extend_from_slice(&bs58::decode(&package.public_key).into_vec()?);
+    Ok(aad)
+}
+
+fn static_secret(secret: &SecureBuffer) -> StaticSecret {
+    StaticSecret::from(<[u8; 32]>::try_from(secret.as_slice()).expect("32-byte X25519 secret"))
+}
+
+fn decode_key(key: &str) -> Result<PublicKey, SignerError> {
+    let key: [u8; 32] = BASE64
+        .decode(key)?
+        .try_into()
+        .map_err(|_| SignerError::ContainerError("invalid X25519 public key".to_string()))?;
+    Ok(PublicKey::from(key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::SignerBackend;
+    use crate::crypto::{test_transaction, Cipher};
+    use crate::kdf::KdfParams;
+
+    #[test]
+    fn test_handoff_moves_key_once() {
+        std::env::set_var("SIGNER_ALLOW_INSECURE_MEMORY", "1");
+        let container =
+            EncryptedKeyContainer::encrypt_with_kdf(&[6u8; 32], "pw", Cipher::Aes256Gcm, KdfParams::MINIMUM).unwrap();
+        let receiver = HandoffReceiver::new(Duration::from_secs(60)).unwrap();
+        assert!(wrap_for_handoff(&container, "wrong", receiver.request()).is_err());
+
+        // A low-order receiver key would make the session key public
+        let mut degenerate = receiver.request().clone();
+        degenerate.public_key = BASE64.encode([0u8; 32]);
+        assert!(wrap_for_handoff(&container, "pw", &degenerate).is_err());
+
+        let package = wrap_for_handoff(&container, "pw", receiver.request()).unwrap();
+        let package = HandoffPackage::from_json(&package.to_json().unwrap()).unwrap();
+
+        // Tampering is caught, and a failed accept still uses up the request
+        let mut tampered = package.clone();
+        tampered.public_key = bs58::encode([9u8; 32]).into_string();
+        assert!(receiver.accept(&tampered, SessionConfig::default()).is_err());
+        assert!(receiver.is_expired());
+        assert!(receiver.accept(&package, SessionConfig::default()).is_err());
+
+        let receiver = HandoffReceiver::new(Duration::from_secs(60)).unwrap();
+        let package = wrap_for_handoff(&container, "pw", receiver.request()).unwrap();
+        let session = receiver.accept(&package, SessionConfig::default()).unwrap();
+        let message = test_transaction(&[6u8; 32]);
+        let expected = ContainerBackend::new(&container, "pw").sign_solana(&message).unwrap();
+        assert_eq!(session.sign(&message).unwrap().signature, expected.signature);
+        assert_eq!(session.public_key(), container.public_key.as_deref());
+        assert!(receiver.is_expired());
+    }
+
+    #[test]
+    fn test_handoff_expires() {
+        std::env::set_var("SIGNER_ALLOW_INSECURE_MEMORY", "1");
+        let container =
+            EncryptedKeyContainer::encrypt_with_kdf(&[6u8; 32], "pw", Cipher::Aes256Gcm, KdfParams::MINIMUM).unwrap();
+        let receiver = HandoffReceiver::new(Duration::from_millis(200)).unwrap();
+        let package = wrap_for_handoff(&container, "pw", receiver.request()).unwrap();
+        std::thread::sleep(Duration::from_millis(400));
+        assert!(receiver.is_expired());
+        assert!(receiver.accept(&package, SessionConfig::default()).is_err());
+
+        let mut stale = receiver.request().clone();
+        stale.expires_at = unix_now() - 1;
+        assert!(wrap_for_handoff(&container, "pw", &stale).is_err());
+
+        // The container's validity window travels with the key
+        let container = container.with_validity("pw", Validity::until(unix_now() + 3)).unwrap();
+        let receiver = HandoffReceiver::new(Duration::from_secs(60)).unwrap();
+        let mut package = wrap_for_handoff(&container, "pw", receiver.request()).unwrap();
+        package.not_after = None;
+        assert!(receiver.accept(&package, SessionConfig::default()).is_err());
+
+        let receiver = HandoffReceiver::new(Duration::from_secs(60)).unwrap();
+        let package = wrap_for_handoff(&container, "pw", receiver.request()).unwrap();
+        let session = receiver.accept(&package, SessionConfig::default()).unwrap();
+        let late = HandoffReceiver::new(Duration::from_secs(60)).unwrap();
+        let late_package = wrap_for_handoff(&container, "pw", late.request()).unwrap();
+        std::thread::sleep(Duration::from_millis(4500));
+        let message = test_transaction(&[6u8; 32]);
+        assert!(matches!(session.sign(&message), Err(SignerError::SessionLocked(_))));
+        assert!(matches!(
+            late.accept(&late_package, SessionConfig::default()),
+            Err(SignerError::ContainerNotValid(_))
+        ));
+    }
+}
@@ -0,0 +1,193 @@
+//! Process-level protection for the keys in SecureBuffers
+//!
+//! Locked, dump-excluded buffers still leak if a debugger can attach to the
+//! process or the kernel writes a full core file. [`harden_process`]
+//! closes those paths once, at startup:
+//!
+//! - denies debugger attachment: `prctl(PR_SET_DUMPABLE, 0)` on Linux
+//!   (which also stops core dumps and `/proc/<pid>/mem` access by the same
+//!   user), `PT_DENY_ATTACH` on macOS, `PROC_TRACE_CTL` on FreeBSD
+//! - sets `RLIMIT_CORE` to zero
+//! - optionally raises `RLIMIT_MEMLOCK` so every key can be locked
+//!
+//! Each step is attempted independently, and the returned
+//! [`HardeningReport`] says which took effect; the caller decides whether
+//! a failure is fatal.
+
+use serde::Serialize;
+
+/// Optional steps of [`harden_process`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct HardeningOptions {
+    /// Raise the soft `RLIMIT_MEMLOCK` to at least this many bytes (and
+    /// the hard limit with it, which needs `CAP_SYS_RESOURCE`)
+    pub memlock_bytes: Option<u64>,
+}
+
+/// What happened to one hardening step
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", content = "detail", rename_all = "snake_case")]
+pub enum StepOutcome {
+    /// The protection is in place
+    Applied,
+    /// The call failed, with the OS error
+    Failed(String),
+    /// The platform has no such protection
+    Unsupported,
+    /// Not requested
+    Skipped,
+}
+
+impl StepOutcome {
+    #[cfg(unix)]
+    fn from_status(ok: bool) -> Self {
+        if ok {
+            StepOutcome::Applied
+        } else {
+            StepOutcome::Failed(std::io::Error::last_os_error().to_string())
+        }
+    }
+}
+
+/// Result of [`harden_process`]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct HardeningReport {
+    /// Debuggers (ptrace) cannot attach
+    pub debugger_attach_denied: StepOutcome,
+    /// `RLIMIT_CORE` is zero
+    pub core_dumps_disabled: StepOutcome,
+    /// `RLIMIT_MEMLOCK` is at least the requested size
+    pub memlock_raised: StepOutcome,
+}
+
+impl HardeningReport {
+    /// Whether every requested step that the platform supports took effect
+    pub fn is_complete(&self) -> bool {
+        [&self.debugger_attach_denied, &self.core_dumps_disabled, &self.memlock_raised]
+            .iter()
+            .all(|step| !matches!(step, StepOutcome::Failed(_)))
+    }
+}
+
+/// Apply process hardening; see the [module docs](self)
+///
+/// Affects the whole process and cannot be undone, so libraries embedding
+/// the signer should leave the call to the application.
+pub fn harden_process(options: &HardeningOptions) -> HardeningReport {
+    HardeningReport {
+        debugger_attach_denied: deny_debugger_attach(),
+        core_dumps_disabled: disable_core_dumps(),
+        memlock_raised: match options.memlock_bytes {
+            Some(bytes) => raise_memlock(bytes),
+            None => StepOutcome::Skipped,
+        },
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn deny_debugger_attach() -> StepOutcome {
+    // SAFETY: PR_SET_DUMPABLE takes a plain integer argument
+    StepOutcome::from_status(unsafe { libc::prctl(libc::PR_SET_DUMPABLE, 0, 0, 0, 0) } == 0)
+}
+
+#[cfg(target_os = "macos")]
+fn deny_debugger_attach() -> StepOutcome {
+    // SAFETY: PT_DENY_ATTACH ignores the pid, address and data arguments
+    StepOutcome::from_status(unsafe { libc::ptrace(libc::PT_DENY_ATTACH, 0, std::ptr::null_mut(), 0) } == 0)
+}
+
+#[cfg(target_os = "freebsd")]
+fn deny_debugger_attach() -> StepOutcome {
+    let mut disable: libc::c_int = libc::PROC_TRACE_CTL_DISABLE;
+    // SAFETY: PROC_TRACE_CTL reads one int from the pointer given
+    let status = unsafe {
+        libc::procctl(
+            libc::P_PID,
+            0,
+            libc::PROC_TRACE_CTL,
+            &mut disable as *mut libc::c_int as *mut libc::c_void,
+        )
+    };
+    StepOutcome::from_status(status == 0)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android", target_os = "macos", target_os = "freebsd")))]
+fn deny_debugger_attach() -> StepOutcome {
+    StepOutcome::Unsupported
+}
+
+#[cfg(unix)]
+fn disable_core_dumps() -> StepOutcome {
+    let limit = libc::rlimit { rlim_cur: 0, rlim_max: 0 };
+    // SAFETY: setrlimit only reads the struct it is given
+    StepOutcome::from_status(unsafe { libc::setrlimit(libc::RLIMIT_CORE, &limit) } == 0)
+}
+
+#[cfg(not(unix))]
+fn disable_core_dumps() -> StepOutcome {
+    StepOutcome::Unsupported
+}
+
+#[cfg(unix)]
+fn raise_memlock(bytes: u64) -> StepOutcome {
+    let mut limit = libc::rlimit { rlim_cur: 0, rlim_max: 0 };
+    // SAFETY: getrlimit only writes the struct it is given
+    if unsafe { libc::getrlimit(libc::RLIMIT_MEMLOCK, &mut limit) } != 0 {
+        return StepOutcome::from_status(false);
+    }
+    let wanted: libc::rlim_t = bytes;
+    if limit.rlim_cur == libc::RLIM_INFINITY || limit.rlim_cur >= wanted {
+        return StepOutcome::Applied;
+    }
+    let raised = libc::rlimit {
+        rlim_cur: wanted,
+        rlim_max: if limit.rlim_max == libc::RLIM_INFINITY { limit.rlim_max } else { limit.rlim_max.max(wanted) },
+    };
+    // SAFETY: setrlimit only reads the struct it is given
+    StepOutcome::from_status(unsafe { libc::setrlimit(libc::RLIMIT_MEMLOCK, &raised) } == 0)
+}
+
+#[cfg(not(unix))]
+fn raise_memlock(_bytes: u64) -> StepOutcome {
+    // Windows sizes the lockable set per process with SetProcessWorkingSetSize
+    StepOutcome::Unsupported
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    /// Hardening is irreversible, so it runs in a child process: this test
+    /// re-executes the test binary with only itself selected
+    #[test]
+    fn test_harden_process_in_child() {
+        if std::env::var_os("COLDSTAR_HARDENING_CHILD").is_none() {
+            let status = std::process::Command::new(std::env::current_exe().unwrap())
+                .args(["--exact", "hardening::tests::test_harden_process_in_child", "--test-threads=1"])
+                .env("COLDSTAR_HARDENING_CHILD", "1")
+                .status()
+                .unwrap();
+            assert!(status.success());
+            return;
+        }
+
+        let mut limit = libc::rlimit { rlim_cur: 0, rlim_max: 0 };
+        unsafe { libc::getrlimit(libc::RLIMIT_MEMLOCK, &mut limit) };
+        // Asking for no more than the current limit always succeeds
+        let report = harden_process(&HardeningOptions {
+            memlock_bytes: Some(limit.rlim_cur.min(64 * 1024)),
+        });
+        assert!(report.is_complete(), "{:?}", report);
+        assert_eq!(report.core_dumps_disabled, StepOutcome::Applied);
+        assert_eq!(report.memlock_raised, StepOutcome::Applied);
+
+        unsafe { libc::getrlimit(libc::RLIMIT_CORE, &mut limit) };
+        assert_eq!(limit.rlim_cur, 0);
+        #[cfg(target_os = "linux")]
+        assert_eq!(unsafe { libc::prctl(libc::PR_GET_DUMPABLE) }, 0);
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["debugger_attach_denied"]["status"], "applied");
+        assert_eq!(harden_process(&HardeningOptions::default()).memlock_raised, StepOutcome::Skipped);
+    }
+}
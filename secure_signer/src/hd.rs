@@ -0,0 +1,338 @@
+//! Hierarchical deterministic key derivation
+//!
+//! - Ed25519 (Solana): SLIP-10, hardened derivation only
+//! - secp256k1 (EVM): BIP-32
+//!
+//! Seeds and derived keys are held in [`SecureBuffer`]s; intermediate HMAC
+//! outputs are zeroized.
+//!
+//! [`DerivationPreset`] gives the paths used by common wallets, so callers
+//! select an account by index instead of writing paths by hand.
+
+use std::fmt;
+use std::str::FromStr;
+
+use hmac::{Hmac, Mac};
+use k256::elliptic_curve::sec1::ToEncodedPoint;
+use k256::{NonZeroScalar, SecretKey};
+use serde::{Deserialize, Serialize};
+use sha2::Sha512;
+use zeroize::Zeroize;
+
+use crate::crypto::{evm_address_from_pubkey, get_locking_mode};
+use crate::error::SignerError;
+use crate::secure_buffer::SecureBuffer;
+
+type HmacSha512 = Hmac<Sha512>;
+
+/// Offset marking a hardened child index
+pub const HARDENED: u32 = 0x8000_0000;
+
+/// PBKDF2 rounds for BIP-39 mnemonic to seed
+const BIP39_ROUNDS: u32 = 2048;
+
+/// A BIP-32 derivation path such as `m/44'/501'/0'/0'`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DerivationPath(Vec<u32>);
+
+impl DerivationPath {
+    /// Child indexes, hardened ones offset by [`HARDENED`]
+    pub fn indexes(&self) -> &[u32] {
+        &self.0
+    }
+}
+
+impl FromStr for DerivationPath {
+    type Err = SignerError;
+
+    fn from_str(path: &str) -> Result<Self, Self::Err> {
+        let invalid = || SignerError::DerivationError(format!("invalid derivation path {}", path));
+
+        let mut parts = path.split('/');
+        if parts.next() != Some("m") {
+            return Err(invalid());
+        }
+
+        parts
+            .map(|part| {
+                let (digits, hardened) = match part.strip_suffix('\'').or_else(|| part.strip_suffix('h')) {
+                    Some(digits) => (digits, true),
+                    None => (part, false),
+                };
+                let index: u32 = digits.parse().map_err(|_| invalid())?;
+                if index >= HARDENED {
+                    return Err(invalid());
+                }
+                Ok(if hardened { index | HARDENED } else { index })
+            })
+            .collect::<Result<_, _>>()
+            .map(DerivationPath)
+    }
+}
+
+impl fmt::Display for DerivationPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "m")?;
+        for index in &self.0 {
+            if index & HARDENED != 0 {
+                write!(f, "/{}'", index & !HARDENED)?;
+            } else {
+                write!(f, "/{}", index)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Curve a key is derived for
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Curve {
+    /// Ed25519 (SLIP-10)
+    Ed25519,
+    /// secp256k1 (BIP-32)
+    Secp256k1,
+}
+
+/// Standard derivation paths used by common wallets
+///
+/// `index` selects the account shown in the wallet UI (first account = 0).
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DerivationPreset {
+    /// Solana, Phantom/Solflare/Backpack: `m/44'/501'/{index}'/0'`
+    Solana,
+    /// Solana, Ledger Live: `m/44'/501'/{index}'`
+    SolanaLedgerLive,
+    /// Ethereum/Base, MetaMask and most software wallets: `m/44'/60'/0'/0/{index}`
+    Ethereum,
+    /// Ethereum/Base, Ledger Live: `m/44'/60'/{index}'/0/0`
+    EthereumLedgerLive,
+    /// Ethereum/Base, Ledger legacy (MEW/MyCrypto): `m/44'/60'/0'/{index}`
+    EthereumLedgerLegacy,
+}
+
+impl DerivationPreset {
+    /// Derivation path for the account at `index`
+    pub fn path(&self, index: u32) -> Result<DerivationPath, SignerError> {
+        if index >= HARDENED {
+            return Err(SignerError::DerivationError(format!(
+                "account index {} out of range",
+                index
+            )));
+        }
+
+        let h = |i: u32| i | HARDENED;
+        Ok(DerivationPath(match self {
+            DerivationPreset::Solana => vec![h(44), h(501), h(index), h(0)],
+            DerivationPreset::SolanaLedgerLive => vec![h(44), h(501), h(index)],
+            DerivationPreset::Ethereum => vec![h(44), h(60), h(0), 0, index],
+            DerivationPreset::EthereumLedgerLive => vec![h(44), h(60), h(index), 0, 0],
+            DerivationPreset::EthereumLedgerLegacy => vec![h(44), h(60), h(0), index],
+        }))
+    }
+
+    /// Curve used by the chain this preset belongs to
+    pub fn curve(&self) -> Curve {
+        match self {
+            DerivationPreset::Solana | DerivationPreset::SolanaLedgerLive => Curve::Ed25519,
+            _ => Curve::Secp256k1,
+        }
+    }
+
+    /// Derive the private key for the account at `index`
+    pub fn derive(&self, seed: &SecureBuffer, index: u32) -> Result<SecureBuffer, SignerError> {
+        derive(self.curve(), seed, &self.path(index)?)
+    }
+
+    /// Address of the account at `index` (base58 public key for Solana,
+    /// 0x-prefixed address for EVM)
+    pub fn address(&self, seed: &SecureBuffer, index: u32) -> Result<String, SignerError> {
+        let mut key = self.derive(seed, index)?;
+        let address = public_address(self.curve(), &key);
+        key.zeroize();
+        address
+    }
+}
+
+/// Derive the BIP-39 seed from a mnemonic phrase and optional passphrase
+///
+/// The phrase is not checked against the wordlist; callers must validate
+/// it. Only ASCII phrases are accepted, since NFKD normalization is not
+/// applied.
+pub fn seed_from_mnemonic(mnemonic: &str, passphrase: &str) -> Result<SecureBuffer, SignerError> {
+    if !mnemonic.is_ascii() || !passphrase.is_ascii() {
+        return Err(SignerError::DerivationError(
+            "mnemonic and passphrase must be ASCII".to_string(),
+        ));
+    }
+
+    let mut phrase = mnemonic.split_whitespace().collect::<Vec<_>>().join(" ");
+    let mut salt = format!("mnemonic{}", passphrase);
+    let mut seed = SecureBuffer::with_mode(64, get_locking_mode())?;
+    pbkdf2::pbkdf2_hmac::<Sha512>(phrase.as_bytes(), salt.as_bytes(), BIP39_ROUNDS, seed.as_mut_slice());
+    salt.zeroize();
+    phrase.zeroize();
+
+    Ok(seed)
+}
+
+/// Derive a 32-byte private key for `curve` along `path`
+pub fn derive(curve: Curve, seed: &SecureBuffer, path: &DerivationPath) -> Result<SecureBuffer, SignerError> {
+    match curve {
+        Curve::Ed25519 => derive_ed25519(seed, path),
+        Curve::Secp256k1 => derive_secp256k1(seed, path),
+    }
+}
+
+/// SLIP-10 Ed25519 derivation (all indexes must be hardened)
+pub fn derive_ed25519(seed: &SecureBuffer, path: &DerivationPath) -> Result<SecureBuffer, SignerError> {
+    if path.0.iter().any(|i| i & HARDENED == 0) {
+        return Err(SignerError::DerivationError(
+            "Ed25519 derivation supports hardened indexes only".to_string(),
+        ));
+    }
+
+    let mut node = hmac_sha512(b"ed25519 seed", &[seed.as_slice()])?;
+    for index in &path.0 {
+        let (key, chain_code) = node.as_slice().split_at(32);
+        let child = hmac_sha512(chain_code, &[&[0u8], key, &index.to_be_bytes()])?;
+        node = child;
+    }
+
+    let key = SecureBuffer::from_slice_with_mode(&node.as_slice()[..32], get_locking_mode());
+    node.zeroize();
+    key
+}
+
+/// BIP-32 secp256k1 derivation
+pub fn derive_secp256k1(seed: &SecureBuffer, path: &DerivationPath) -> Result<SecureBuffer, SignerError> {
+    let mut node = hmac_sha512(b"Bitcoin seed", &[seed.as_slice()])?;
+    let mut key = parse_scalar(&node.as_slice()[..32])?;
+
+    for index in &path.0 {
+        let chain_code = &node.as_slice()[32..];
+        let child = if index & HARDENED != 0 {
+            hmac_sha512(chain_code, &[&[0u8], &key.to_bytes(), &index.to_be_bytes()])?
+        } else {
+            let public = key.public_key().to_encoded_point(true);
+            hmac_sha512(chain_code, &[public.as_bytes(), &index.to_be_bytes()])?
+        };
+
+        // child = parse256(IL) + k (mod n); invalid IL or zero key is an error
+        let tweak = parse_scalar(&child.as_slice()[..32])?;
+        let sum = *tweak.to_nonzero_scalar() + *key.to_nonzero_scalar();
+        let sum = Option::<NonZeroScalar>::from(NonZeroScalar::new(sum))
+            .ok_or_else(|| SignerError::DerivationError("derived key is zero".to_string()))?;
+        key = SecretKey::from(sum);
+        node = child;
+    }
+    node.zeroize();
+
+    let mut bytes = key.to_bytes();
+    let result = SecureBuffer::from_slice_with_mode(&bytes, get_locking_mode());
+    bytes.zeroize();
+    result
+}
+
+/// Public address for a derived key
+fn public_address(curve: Curve, key: &SecureBuffer) -> Result<String, SignerError> {
+    match curve {
+        Curve::Ed25519 => {
+            let signing_key = ed25519_dalek::SigningKey::from_bytes(
+                key.as_slice()
+                    .try_into()
+                    .map_err(|_| SignerError::InvalidKeyFormat(key.len()))?,
+            );
+            Ok(bs58::encode(signing_key.verifying_key().as_bytes()).into_string())
+        }
+        Curve::Secp256k1 => {
+            let signing_key = k256::ecdsa::SigningKey::from_slice(key.as_slice())
+                .map_err(|e| SignerError::DerivationError(e.to_string()))?;
+            Ok(evm_address_from_pubkey(signing_key.verifying_key()))
+        }
+    }
+}
+
+fn parse_scalar(bytes: &[u8]) -> Result<SecretKey, SignerError> {
+    SecretKey::from_slice(bytes)
+        .map_err(|_| SignerError::DerivationError("derived scalar is invalid".to_string()))
+}
+
+fn hmac_sha512(key: &[u8], data: &[&[u8]]) -> Result<SecureBuffer, SignerError> {
+    let mut mac = HmacSha512::new_from_slice(key)
+        .map_err(|e| SignerError::DerivationError(e.to_string()))?;
+    for part in data {
+        mac.update(part);
+    }
+    let mut output = mac.finalize().into_bytes();
+    let result = SecureBuffer::from_slice_with_mode(&output, get_locking_mode());
+    output.zeroize();
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_MNEMONIC: &str =
+        "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+    fn seed(hex_seed: &str) -> SecureBuffer {
+        SecureBuffer::from_slice_permissive(&hex::decode(hex_seed).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_path_parsing() {
+        let path: DerivationPath = "m/44'/60'/0'/0/7".parse().unwrap();
+        assert_eq!(path.indexes(), &[44 | HARDENED, 60 | HARDENED, HARDENED, 0, 7]);
+        assert_eq!(path.to_string(), "m/44'/60'/0'/0/7");
+        assert!("44'/60'".parse::<DerivationPath>().is_err());
+        assert!("m/2147483648".parse::<DerivationPath>().is_err());
+    }
+
+    #[test]
+    fn test_slip10_ed25519_vector() {
+        let seed = seed("000102030405060708090a0b0c0d0e0f");
+        let master = derive_ed25519(&seed, &"m".parse().unwrap()).unwrap();
+        assert_eq!(
+            hex::encode(master.as_slice()),
+            "2b4be7f19ee27bbf30c667b642d5f4aa69fd169872f8fc3059c08ebae2eb19e7"
+        );
+        let child = derive_ed25519(&seed, &"m/0'".parse().unwrap()).unwrap();
+        assert_eq!(
+            hex::encode(child.as_slice()),
+            "68e0fe46dfb67e368c75379acec591dad19df3cde26e63b93a8e704f1dade7a3"
+        );
+        assert!(derive_ed25519(&seed, &"m/0".parse().unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_bip32_secp256k1_vector() {
+        let seed = seed("000102030405060708090a0b0c0d0e0f");
+        let key = derive_secp256k1(&seed, &"m/0'/1".parse().unwrap()).unwrap();
+        assert_eq!(
+            hex::encode(key.as_slice()),
+            "3c6cb8d0f6a264c91ea8b5030fadaa8e538b020f0a387421a12de9319dc93368"
+        );
+    }
+
+    #[test]
+    fn test_presets_match_wallets() {
+        let seed = seed_from_mnemonic(TEST_MNEMONIC, "").unwrap();
+
+        // MetaMask first account
+        assert_eq!(
+            DerivationPreset::Ethereum.address(&seed, 0).unwrap(),
+            "0x9858effd232b4033e47d90003d41ec34ecaeda94"
+        );
+        // Phantom first account
+        assert_eq!(
+            DerivationPreset::Solana.address(&seed, 0).unwrap(),
+            "HAgk14JpMQLgt6rVgv7cBQFJWFto5Dqxi472uT3DKpqk"
+        );
+        assert_eq!(
+            DerivationPreset::EthereumLedgerLive.path(2).unwrap().to_string(),
+            "m/44'/60'/2'/0/0"
+        );
+    }
+}
@@ -0,0 +1,386 @@
+//! Idempotency store for retried signing requests
+//!
+//! Clients that retry a request (after a timeout, a dropped pipe, etc.) can
+//! attach an idempotency key. The first response produced for that key is
+//! cached and returned verbatim on every retry, so a retry never yields a
+//! second, different signed transaction that could also be broadcast.
+//!
+//! # Storage
+//!
+//! The store is bounded: once `capacity` entries are held, the oldest entry
+//! is evicted. When opened with a path, every insert is persisted with a
+//! write-to-temp-then-rename so a crash never leaves a truncated file.
+//!
+//! Only responses (signatures, signed transactions, public keys) are cached.
+//! Requests are reduced to a SHA3-256 fingerprint of
+//! [`IdempotencyStore::canonical_request`], which replaces secret fields
+//! with an HMAC under a random key the store holds only in memory: a plain
+//! hash over a passphrase and the container it opens would let anyone who
+//! can read the store test guesses offline. A retry with a different secret
+//! is therefore a conflict, never the cached response. After the store is
+//! reopened its key is new, so a retried request that carries a secret
+//! conflicts rather than matching an entry from before. The file is readable
+//! by its owner only.
+
+use std::collections::VecDeque;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use sha3::{Digest, Sha3_256};
+use zeroize::Zeroizing;
+
+use crate::entropy::EntropyRng;
+use crate::error::SignerError;
+
+/// Default number of idempotency entries retained
+pub const DEFAULT_CAPACITY: usize = 1024;
+
+/// Maximum accepted length of a client-supplied idempotency key
+pub const MAX_KEY_LENGTH: usize = 128;
+
+/// Request fields fingerprinted only through the store's HMAC key
+pub const SECRET_FIELDS: &[&str] = &["passphrase", "private_key"];
+
+/// A cached response for one idempotency key
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct IdempotencyEntry {
+    /// The client-supplied key
+    pub key: String,
+    /// SHA3-256 fingerprint of the request the key was first used with (hex)
+    pub request_fingerprint: String,
+    /// The response returned for the original request
+    pub response: serde_json::Value,
+    /// Unix timestamp (seconds) when the entry was recorded
+    pub created_at: u64,
+}
+
+/// On-disk representation of the store
+#[derive(Serialize, Deserialize, Default)]
+struct StoreFile {
+    version: u8,
+    entries: Vec<IdempotencyEntry>,
+}
+
+/// Bounded, optionally persisted idempotency cache
+pub struct IdempotencyStore {
+    path: Option<PathBuf>,
+    capacity: usize,
+    entries: VecDeque<IdempotencyEntry>,
+    /// HMAC key for [`SECRET_FIELDS`], never persisted
+    secret_key: Zeroizing<[u8; 32]>,
+}
+
+impl IdempotencyStore {
+    /// Create a store that lives only in memory
+    pub fn in_memory(capacity: usize) -> Self {
+        let mut secret_key = Zeroizing::new([0u8; 32]);
+        EntropyRng.fill_bytes(secret_key.as_mut());
+        Self {
+            path: None,
+            capacity: capacity.max(1),
+            entries: VecDeque::new(),
+            secret_key,
+        }
+    }
+
+    /// Open (or create) a store persisted at `path`
+    ///
+    /// A missing file is treated as an empty store. If the file holds more
+    /// entries than `capacity`, the oldest ones are dropped.
+    pub fn open(path: impl AsRef<Path>, capacity: usize) -> Result<Self, SignerError> {
+        let path = path.as_ref().to_path_buf();
+        let mut store = Self::in_memory(capacity);
+
+        if path.exists() {
+            let data = std::fs::read_to_string(&path)?;
+            let file: StoreFile = serde_json::from_str(&data)?;
+            store.entries = file.entries.into();
+            store.evict();
+        }
+
+        store.path = Some(path);
+        Ok(store)
+    }
+
+    /// Number of cached entries
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Check if the store holds no entries
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Look up a previously recorded response
+    ///
+    /// # Returns
+    /// * `Ok(Some(response))` - The key was seen before with the same request
+    /// * `Ok(None)` - The key is unknown
+    /// * `Err(SignerError::IdempotencyConflict)` - The key was already used
+    ///   for a different request
+    pub fn lookup(
+        &self,
+        key: &str,
+        request: &[u8],
+    ) -> Result<Option<serde_json::Value>, SignerError> {
+        validate_key(key)?;
+        let fingerprint = fingerprint(request);
+
+        match self.entries.iter().find(|e| e.key == key) {
+            Some(entry) if entry.request_fingerprint == fingerprint => {
+                Ok(Some(entry.response.clone()))
+            }
+            Some(_) => Err(SignerError::IdempotencyConflict(key.to_string())),
+            None => Ok(None),
+        }
+    }
+
+    /// Record the response produced for a request
+    ///
+    /// Re-recording an existing key with the same request is a no-op; using
+    /// it with a different request is rejected.
+    pub fn record(
+        &mut self,
+        key: &str,
+        request: &[u8],
+        response: &serde_json::Value,
+    ) -> Result<(), SignerError> {
+        if self.lookup(key, request)?.is_some() {
+            return Ok(());
+        }
+
+        self.entries.push_back(IdempotencyEntry {
+            key: key.to_string(),
+            request_fingerprint: fingerprint(request),
+            response: response.clone(),
+            created_at: unix_now(),
+        });
+        self.evict();
+        self.persist()
+    }
+
+    /// Return the cached response for `key`, or run `f` and cache its result
+    ///
+    /// Errors returned by `f` are not cached, so a failed request can be
+    /// retried with the same key.
+    pub fn get_or_insert_with<F>(
+        &mut self,
+        key: &str,
+        request: &[u8],
+        f: F,
+    ) -> Result<serde_json::Value, SignerError>
+    where
+        F: FnOnce() -> Result<serde_json::Value, SignerError>,
+    {
+        if let Some(cached) = self.lookup(key, request)? {
+            return Ok(cached);
+        }
+
+        let response = f()?;
+        self.record(key, request, &response)?;
+        Ok(response)
+    }
+
+    fn evict(&mut self) {
+        while self.entries.len() > self.capacity {
+            self.entries.pop_front();
+        }
+    }
+
+    fn persist(&self) -> Result<(), SignerError> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        let file = StoreFile {
+            version: 1,
+            entries: self.entries.iter().cloned().collect(),
+        };
+        let json = serde_json::to_string(&file)?;
+
+        // A fresh temp file, so it is created with the owner-only mode
+        let tmp_path = path.with_extension("tmp");
+        if let Err(e) = std::fs::remove_file(&tmp_path) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                return Err(e.into());
+            }
+        }
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        options.open(&tmp_path)?.write_all(json.as_bytes())?;
+        std::fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+}
+
+impl IdempotencyStore {
+    /// Canonical bytes identifying a JSON request, independent of key
+    /// order, whitespace, and the request `id` and `idempotency_key`
+    ///
+    /// The [`SECRET_FIELDS`] are replaced by their HMAC under this store's
+    /// key, so they take part in matching without being recoverable from
+    /// the store. A body that is not valid JSON is rejected.
+    pub fn canonical_request(&self, json: &str) -> Result<Vec<u8>, SignerError> {
+        let mut value: serde_json::Value = serde_json::from_str(json)?;
+        if let Some(obj) = value.as_object_mut() {
+            obj.remove("id");
+            obj.remove("idempotency_key");
+            for field in SECRET_FIELDS {
+                if let Some(secret) = obj.get_mut(*field) {
+                    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(self.secret_key.as_ref())
+                        .map_err(|e| SignerError::IntegrityError(e.to_string()))?;
+                    mac.update(field.as_bytes());
+                    mac.update(&Zeroizing::new(serde_json::to_vec(secret)?));
+                    *secret = serde_json::Value::String(hex::encode(mac.finalize().into_bytes()));
+                }
+            }
+        }
+        Ok(serde_json::to_vec(&value)?)
+    }
+}
+
+fn validate_key(key: &str) -> Result<(), SignerError> {
+    if key.is_empty() || key.len() > MAX_KEY_LENGTH {
+        return Err(SignerError::InvalidTransaction(format!(
+            "Idempotency key must be 1-{} bytes, got {}",
+            MAX_KEY_LENGTH,
+            key.len()
+        )));
+    }
+    Ok(())
+}
+
+fn fingerprint(request: &[u8]) -> String {
+    hex::encode(Sha3_256::digest(request))
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_retry_returns_cached_response() {
+        let mut store = IdempotencyStore::in_memory(8);
+        let mut calls = 0;
+
+        let first = store
+            .get_or_insert_with("req-1", b"payload", || {
+                calls += 1;
+                Ok(json!({"signature": "abc"}))
+            })
+            .unwrap();
+        let second = store
+            .get_or_insert_with("req-1", b"payload", || {
+                calls += 1;
+                Ok(json!({"signature": "different"}))
+            })
+            .unwrap();
+
+        assert_eq!(first, second);
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn test_key_reuse_with_different_request_conflicts() {
+        let mut store = IdempotencyStore::in_memory(8);
+        store.record("req-1", b"payload-a", &json!({})).unwrap();
+
+        let result = store.lookup("req-1", b"payload-b");
+        assert!(matches!(result, Err(SignerError::IdempotencyConflict(_))));
+    }
+
+    #[test]
+    fn test_capacity_evicts_oldest() {
+        let mut store = IdempotencyStore::in_memory(2);
+        store.record("a", b"1", &json!(1)).unwrap();
+        store.record("b", b"2", &json!(2)).unwrap();
+        store.record("c", b"3", &json!(3)).unwrap();
+
+        assert_eq!(store.len(), 2);
+        assert!(store.lookup("a", b"1").unwrap().is_none());
+        assert_eq!(store.lookup("c", b"3").unwrap(), Some(json!(3)));
+    }
+
+    #[test]
+    fn test_persisted_store_survives_reopen() {
+        let path = std::env::temp_dir().join(format!(
+            "coldstar-idempotency-{}.json",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+
+        {
+            let mut store = IdempotencyStore::open(&path, 8).unwrap();
+            store.record("req-1", b"payload", &json!({"ok": true})).unwrap();
+        }
+
+        let store = IdempotencyStore::open(&path, 8).unwrap();
+        assert_eq!(
+            store.lookup("req-1", b"payload").unwrap(),
+            Some(json!({"ok": true}))
+        );
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_secrets_stay_out_of_the_store() {
+        let store = IdempotencyStore::in_memory(8);
+        let a = store
+            .canonical_request(r#"{"action":"sign","passphrase":"hunter2","transaction":"AA=="}"#)
+            .unwrap();
+        let b = store
+            .canonical_request(r#"{"transaction":"AA==","idempotency_key":"k","action":"sign","passphrase":"hunter2"}"#)
+            .unwrap();
+        assert_eq!(a, b);
+        assert!(!String::from_utf8(a.clone()).unwrap().contains("hunter2"));
+
+        // A retry with another secret is a different request, and so is
+        // anything under another store's key
+        let wrong = store
+            .canonical_request(r#"{"action":"sign","passphrase":"hunter3","transaction":"AA=="}"#)
+            .unwrap();
+        assert_ne!(a, wrong);
+        let other = IdempotencyStore::in_memory(8)
+            .canonical_request(r#"{"action":"sign","passphrase":"hunter2","transaction":"AA=="}"#)
+            .unwrap();
+        assert_ne!(a, other);
+
+        // Bodies that are not JSON do not all share one fingerprint
+        assert!(store.canonical_request("not json").is_err());
+        assert!(store.canonical_request("").is_err());
+
+        let path = std::env::temp_dir().join(format!("coldstar-idempotency-mode-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        IdempotencyStore::open(&path, 8).unwrap().record("req-1", &a, &json!({})).unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_invalid_key_rejected() {
+        let store = IdempotencyStore::in_memory(8);
+        assert!(store.lookup("", b"x").is_err());
+        assert!(store.lookup(&"k".repeat(MAX_KEY_LENGTH + 1), b"x").is_err());
+    }
+}
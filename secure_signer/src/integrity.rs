@@ -0,0 +1,229 @@
+//! Binary integrity self-check
+//!
+//! Release binaries carry a signed manifest appended after the executable
+//! image:
+//!
+//! ```text
+//! executable bytes || manifest JSON || manifest length (u32 LE) || "CSINTEG1"
+//! ```
+//!
+//! The manifest records the SHA-256 and length of the executable bytes and
+//! an Ed25519 signature over them. [`verify_self`] recomputes the hash of
+//! the running binary and checks the signature against the public key
+//! compiled in from `COLDSTAR_INTEGRITY_PUBKEY` (base58) at build time, so a
+//! patched binary, or one re-sealed with another key, is reported as
+//! tampered.
+//!
+//! Builds without `COLDSTAR_INTEGRITY_PUBKEY` report
+//! [`IntegrityStatus::Unconfigured`] instead of failing, so development
+//! builds keep working. Release tooling seals a binary with [`seal`] (or the
+//! `seal-binary` CLI command).
+
+use std::path::Path;
+
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::crypto::EncryptedKeyContainer;
+use crate::error::SignerError;
+
+/// Marks the end of a sealed binary
+const TRAILER_MAGIC: &[u8; 8] = b"CSINTEG1";
+
+/// Domain separator for manifest signatures
+const SIGNATURE_DOMAIN: &[u8] = b"coldstar-integrity-v1";
+
+/// Manifest format version
+const MANIFEST_VERSION: u8 = 1;
+
+/// Release signing key, base58, fixed at build time
+pub const TRUSTED_KEY: Option<&str> = option_env!("COLDSTAR_INTEGRITY_PUBKEY");
+
+/// Manifest appended to a sealed binary
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct IntegrityManifest {
+    /// Manifest format version
+    pub version: u8,
+    /// Length of the executable bytes covered by the hash
+    pub length: u64,
+    /// SHA-256 of the executable bytes (hex)
+    pub sha256: String,
+    /// Public key that signed the manifest (base58)
+    pub signer: String,
+    /// Ed25519 signature (base58)
+    pub signature: String,
+}
+
+/// Outcome of a successful self-check
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum IntegrityStatus {
+    /// The binary matches a manifest signed by the trusted key
+    Verified {
+        /// SHA-256 of the executable bytes (hex)
+        sha256: String,
+    },
+    /// No trusted key was compiled in; nothing was checked
+    Unconfigured,
+}
+
+/// Check the running binary against its embedded manifest
+///
+/// Returns an error if a trusted key is configured and the binary is
+/// unsealed, modified, or sealed by a different key.
+pub fn verify_self() -> Result<IntegrityStatus, SignerError> {
+    let Some(trusted_key) = TRUSTED_KEY else {
+        return Ok(IntegrityStatus::Unconfigured);
+    };
+    let exe = std::env::current_exe()?;
+    verify_file(exe, trusted_key)
+}
+
+/// Check a sealed binary on disk
+pub fn verify_file(path: impl AsRef<Path>, trusted_key: &str) -> Result<IntegrityStatus, SignerError> {
+    verify_bytes(&std::fs::read(path)?, trusted_key)
+}
+
+/// Check sealed binary bytes
+pub fn verify_bytes(sealed: &[u8], trusted_key: &str) -> Result<IntegrityStatus, SignerError> {
+    let (image, manifest) = split_sealed(sealed)?
+        .ok_or_else(|| SignerError::IntegrityError("binary has no integrity manifest".to_string()))?;
+
+    if manifest.version != MANIFEST_VERSION {
+        return Err(SignerError::IntegrityError(format!(
+            "unsupported manifest version {}",
+            manifest.version
+        )));
+    }
+    if manifest.signer != trusted_key {
+        return Err(SignerError::IntegrityError("manifest signed by an untrusted key".to_string()));
+    }
+
+    let digest = Sha256::digest(image);
+    if manifest.length != image.len() as u64 || manifest.sha256 != hex::encode(digest) {
+        return Err(SignerError::IntegrityError("binary does not match its manifest".to_string()));
+    }
+
+    let public_key: [u8; 32] = bs58::decode(&manifest.signer)
+        .into_vec()?
+        .try_into()
+        .map_err(|_| SignerError::IntegrityError("invalid signer key".to_string()))?;
+    let signature: [u8; 64] = bs58::decode(&manifest.signature)
+        .into_vec()?
+        .try_into()
+        .map_err(|_| SignerError::IntegrityError("invalid signature".to_string()))?;
+
+    VerifyingKey::from_bytes(&public_key)
+        .and_then(|key| {
+            key.verify(
+                &signed_message(&digest.into(), manifest.length),
+                &Signature::from_bytes(&signature),
+            )
+        })
+        .map_err(|_| SignerError::IntegrityError("manifest signature is invalid".to_string()))?;
+
+    Ok(IntegrityStatus::Verified {
+        sha256: manifest.sha256,
+    })
+}
+
+/// Seal a binary, signing its manifest with a container's key
+///
+/// An existing manifest is replaced, so re-sealing is idempotent.
+pub fn seal(binary: &[u8], container: &EncryptedKeyContainer, passphrase: &str) -> Result<Vec<u8>, SignerError> {
+    let image = match split_sealed(binary)? {
+        Some((image, _)) => image,
+        None => binary,
+    };
+    let digest: [u8; 32] = Sha256::digest(image).into();
+    let length = image.len() as u64;
+
+    let mut seed = container.decrypt_key(passphrase)?;
+    let signer = SigningKey::from_bytes(
+        seed.as_slice()
+            .try_into()
+            .map_err(|_| SignerError::InvalidKeyFormat(seed.len()))?,
+    );
+    seed.zeroize();
+
+    let manifest = IntegrityManifest {
+        version: MANIFEST_VERSION,
+        length,
+        sha256: hex::encode(digest),
+        signer: bs58::encode(signer.verifying_key().as_bytes()).into_string(),
+        signature: bs58::encode(signer.sign(&signed_message(&digest, length)).to_bytes()).into_string(),
+    };
+    let manifest_json = serde_json::to_vec(&manifest)?;
+
+    let mut sealed = image.to_vec();
+    sealed.extend_from_slice(&manifest_json);
+    sealed.extend_from_slice(&(manifest_json.len() as u32).to_le_bytes());
+    sealed.extend_from_slice(TRAILER_MAGIC);
+    Ok(sealed)
+}
+
+fn signed_message(digest: &[u8; 32], length: u64) -> Vec<u8> {
+    let mut message = SIGNATURE_DOMAIN.to_vec();
+    message.extend_from_slice(digest);
+    message.extend_from_slice(&length.to_le_bytes());
+    message
+}
+
+/// Split a sealed binary into image and manifest; `None` if unsealed
+fn split_sealed(bytes: &[u8]) -> Result<Option<(&[u8], IntegrityManifest)>, SignerError> {
+    let Some(rest) = bytes.strip_suffix(TRAILER_MAGIC) else {
+        return Ok(None);
+    };
+    let malformed = || SignerError::IntegrityError("malformed integrity trailer".to_string());
+
+    let len_start = rest.len().checked_sub(4).ok_or_else(malformed)?;
+    let manifest_len = u32::from_le_bytes(rest[len_start..].try_into().expect("4 bytes")) as usize;
+    let manifest_start = len_start.checked_sub(manifest_len).ok_or_else(malformed)?;
+
+    let manifest = serde_json::from_slice(&rest[manifest_start..len_start]).map_err(|_| malformed())?;
+    Ok(Some((&rest[..manifest_start], manifest)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::Cipher;
+    use crate::kdf::KdfParams;
+
+    fn release_key() -> (EncryptedKeyContainer, String) {
+        let container =
+            EncryptedKeyContainer::encrypt_with_kdf(&[6u8; 32], "pw", Cipher::Aes256Gcm, KdfParams::MINIMUM).unwrap();
+        let public_key = container.public_key.clone().unwrap();
+        (container, public_key)
+    }
+
+    #[test]
+    fn test_sealed_binary_verifies() {
+        let (container, trusted) = release_key();
+        let binary = b"\x7fELF pretend executable".to_vec();
+
+        let sealed = seal(&binary, &container, "pw").unwrap();
+        assert!(matches!(verify_bytes(&sealed, &trusted).unwrap(), IntegrityStatus::Verified { .. }));
+
+        // Re-sealing replaces the manifest instead of hashing it
+        assert_eq!(seal(&sealed, &container, "pw").unwrap(), sealed);
+    }
+
+    #[test]
+    fn test_tampering_is_reported() {
+        let (container, trusted) = release_key();
+        let sealed = seal(b"executable image", &container, "pw").unwrap();
+
+        let mut patched = sealed.clone();
+        patched[0] ^= 1;
+        assert!(matches!(verify_bytes(&patched, &trusted), Err(SignerError::IntegrityError(_))));
+
+        assert!(verify_bytes(b"executable image", &trusted).is_err());
+
+        let other =
+            EncryptedKeyContainer::encrypt_with_kdf(&[7u8; 32], "pw", Cipher::Aes256Gcm, KdfParams::MINIMUM).unwrap();
+        let resealed = seal(&sealed, &other, "pw").unwrap();
+        assert!(verify_bytes(&resealed, &trusted).is_err());
+    }
+}
@@ -0,0 +1,395 @@
+//! Passphrase-based key derivation
+//!
+//! Containers store the Argon2id parameters they were created with, so the
+//! defaults can be raised over time without breaking existing containers.
+//!
+//! New containers are always Argon2id. scrypt and PBKDF2-HMAC-SHA256 are
+//! supported for decryption only, so key material exported from other
+//! ecosystems (which commonly use them) can be consumed without ever being
+//! written out in plaintext.
+//!
+//! # Parameter Bounds
+//!
+//! - At encryption time, parameters below [`KdfParams::MINIMUM`] are rejected
+//!   so callers cannot accidentally create weak containers.
+//! - At decryption time, parameters above [`KdfParams::MAXIMUM`] are
+//!   rejected so a crafted container cannot exhaust memory or CPU. Lowered
+//!   parameters simply derive a different key and fail authentication.
+
+use alloc::format;
+use alloc::string::ToString;
+
+use argon2::{Argon2, Params, Version};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use crate::crypto::get_locking_mode;
+use crate::error::SignerError;
+use crate::secure_buffer::SecureBuffer;
+
+/// Size of derived encryption keys (256 bits)
+pub(crate) const KEY_SIZE: usize = 32;
+
+/// Argon2id cost parameters
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KdfParams {
+    /// Memory cost in KiB
+    pub memory_cost: u32,
+    /// Number of iterations
+    pub time_cost: u32,
+    /// Degree of parallelism (lanes)
+    pub parallelism: u32,
+}
+
+impl KdfParams {
+    /// Default parameters: 64 MiB, 3 iterations, 4 lanes
+    ///
+    /// These are the parameters used by every container created before they
+    /// were stored, so containers without a `kdf` field decrypt with them.
+    pub const DEFAULT: KdfParams = KdfParams {
+        memory_cost: 65536,
+        time_cost: 3,
+        parallelism: 4,
+    };
+
+    /// Weakest parameters accepted for new containers (OWASP baseline: 19 MiB, t=2)
+    pub const MINIMUM: KdfParams = KdfParams {
+        memory_cost: 19456,
+        time_cost: 2,
+        parallelism: 1,
+    };
+
+    /// Strongest parameters accepted when decrypting: 4 GiB, 64 iterations, 64 lanes
+    pub const MAXIMUM: KdfParams = KdfParams {
+        memory_cost: 4 * 1024 * 1024,
+        time_cost: 64,
+        parallelism: 64,
+    };
+
+    /// Create parameters, enforcing the safe minimum
+    pub fn new(memory_cost: u32, time_cost: u32, parallelism: u32) -> Result<Self, SignerError> {
+        let params = Self {
+            memory_cost,
+            time_cost,
+            parallelism,
+        };
+        params.check_minimum()?;
+        Ok(params)
+    }
+
+    /// Reject parameters weaker than [`KdfParams::MINIMUM`]
+    pub fn check_minimum(&self) -> Result<(), SignerError> {
+        let min = Self::MINIMUM;
+        if self.memory_cost < min.memory_cost
+            || self.time_cost < min.time_cost
+            || self.parallelism < min.parallelism
+        {
+            return Err(SignerError::KeyDerivationFailed(format!(
+                "KDF parameters below minimum (m={} KiB, t={}, p={})",
+                min.memory_cost, min.time_cost, min.parallelism
+            )));
+        }
+        self.check_maximum()
+    }
+
+    /// Reject parameters stronger than [`KdfParams::MAXIMUM`]
+    pub fn check_maximum(&self) -> Result<(), SignerError> {
+        let max = Self::MAXIMUM;
+        if self.memory_cost > max.memory_cost
+            || self.time_cost > max.time_cost
+            || self.parallelism > max.parallelism
+        {
+            return Err(SignerError::KeyDerivationFailed(format!(
+                "KDF parameters above maximum (m={} KiB, t={}, p={})",
+                max.memory_cost, max.time_cost, max.parallelism
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// scrypt cost parameters (decryption only)
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ScryptParams {
+    /// log2 of the CPU/memory cost N
+    pub log_n: u8,
+    /// Block size
+    pub r: u32,
+    /// Parallelization
+    pub p: u32,
+}
+
+impl ScryptParams {
+    /// Largest accepted memory requirement (128 * r * N bytes): 4 GiB
+    const MAX_MEMORY: u64 = 4 * 1024 * 1024 * 1024;
+
+    fn check_maximum(&self) -> Result<(), SignerError> {
+        let memory = 128u64
+            .checked_mul(u64::from(self.r))
+            .and_then(|m| 1u64.checked_shl(u32::from(self.log_n)).and_then(|n| m.checked_mul(n)));
+        match memory {
+            Some(m) if m <= Self::MAX_MEMORY && self.p <= 64 => Ok(()),
+            _ => Err(SignerError::KeyDerivationFailed(
+                "scrypt parameters above maximum".to_string(),
+            )),
+        }
+    }
+}
+
+/// PBKDF2-HMAC-SHA256 parameters (decryption only)
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Pbkdf2Params {
+    /// Iteration count
+    pub iterations: u32,
+}
+
+impl Pbkdf2Params {
+    /// Largest accepted iteration count
+    const MAX_ITERATIONS: u32 = 10_000_000;
+}
+
+/// Key derivation function recorded in a container
+///
+/// Serialized with an `algorithm` tag, e.g.
+/// `{"algorithm":"scrypt","log_n":18,"r":8,"p":1}`. A `kdf` object without
+/// a tag is read as Argon2id parameters, and a missing `kdf` field means
+/// the default Argon2id parameters.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(from = "KdfRepr")]
+#[serde(tag = "algorithm", rename_all = "kebab-case")]
+pub enum Kdf {
+    /// Argon2id (the only KDF used for new containers)
+    Argon2id(KdfParams),
+    /// scrypt (imports only)
+    Scrypt(ScryptParams),
+    /// PBKDF2-HMAC-SHA256 (imports only)
+    Pbkdf2Sha256(Pbkdf2Params),
+}
+
+/// Accepts both tagged KDF objects and untagged Argon2id parameters
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum KdfRepr {
+    Tagged(TaggedKdf),
+    Argon2id(KdfParams),
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "algorithm", rename_all = "kebab-case")]
+enum TaggedKdf {
+    Argon2id(KdfParams),
+    Scrypt(ScryptParams),
+    Pbkdf2Sha256(Pbkdf2Params),
+}
+
+impl From<KdfRepr> for Kdf {
+    fn from(repr: KdfRepr) -> Self {
+        match repr {
+            KdfRepr::Tagged(TaggedKdf::Argon2id(p)) | KdfRepr::Argon2id(p) => Kdf::Argon2id(p),
+            KdfRepr::Tagged(TaggedKdf::Scrypt(p)) => Kdf::Scrypt(p),
+            KdfRepr::Tagged(TaggedKdf::Pbkdf2Sha256(p)) => Kdf::Pbkdf2Sha256(p),
+        }
+    }
+}
+
+impl Default for Kdf {
+    fn default() -> Self {
+        Kdf::Argon2id(KdfParams::default())
+    }
+}
+
+impl From<KdfParams> for Kdf {
+    fn from(params: KdfParams) -> Self {
+        Kdf::Argon2id(params)
+    }
+}
+
+impl Kdf {
+    /// The algorithm's name, as in the `algorithm` tag
+    pub fn name(&self) -> &'static str {
+        match self {
+            Kdf::Argon2id(_) => "argon2id",
+            Kdf::Scrypt(_) => "scrypt",
+            Kdf::Pbkdf2Sha256(_) => "pbkdf2-sha256",
+        }
+    }
+
+    /// Derive a 32-byte key from a passphrase
+    ///
+    /// Parameters above the accepted maximum are rejected before any work
+    /// is done.
+    ///
+    /// # Memory Lifecycle
+    /// Returns a SecureBuffer containing the derived key.
+    pub(crate) fn derive(&self, passphrase: &[u8], salt: &[u8]) -> Result<SecureBuffer, SignerError> {
+        match self {
+            Kdf::Argon2id(params) => derive_key(passphrase, salt, params),
+            Kdf::Scrypt(params) => {
+                params.check_maximum()?;
+                let params = scrypt::Params::new(params.log_n, params.r, params.p, KEY_SIZE)
+                    .map_err(|e| SignerError::KeyDerivationFailed(e.to_string()))?;
+
+                let mut key = SecureBuffer::with_mode(KEY_SIZE, get_locking_mode())?;
+                scrypt::scrypt(passphrase, salt, &params, key.as_mut_slice())
+                    .map_err(|e| SignerError::KeyDerivationFailed(e.to_string()))?;
+                Ok(key)
+            }
+            Kdf::Pbkdf2Sha256(params) => {
+                if params.iterations == 0 || params.iterations > Pbkdf2Params::MAX_ITERATIONS {
+                    return Err(SignerError::KeyDerivationFailed(format!(
+                        "PBKDF2 iterations must be 1-{}",
+                        Pbkdf2Params::MAX_ITERATIONS
+                    )));
+                }
+
+                let mut key = SecureBuffer::with_mode(KEY_SIZE, get_locking_mode())?;
+                pbkdf2::pbkdf2_hmac::<Sha256>(passphrase, salt, params.iterations, key.as_mut_slice());
+                Ok(key)
+            }
+        }
+    }
+
+    /// Derive a key from a passphrase and a keyfile's secret, which
+    /// Argon2id takes as its secret input (the other KDFs have none)
+    pub(crate) fn derive_with_secret(
+        &self,
+        passphrase: &[u8],
+        salt: &[u8],
+        secret: &[u8],
+    ) -> Result<SecureBuffer, SignerError> {
+        match self {
+            Kdf::Argon2id(params) => argon2id(passphrase, salt, params, Some(secret)),
+            Kdf::Scrypt(_) | Kdf::Pbkdf2Sha256(_) => Err(SignerError::KeyDerivationFailed(
+                "keyfiles require Argon2id".to_string(),
+            )),
+        }
+    }
+}
+
+/// Derive an encryption key from a passphrase using Argon2id
+///
+/// # Memory Lifecycle
+/// Returns a SecureBuffer containing the derived key.
+pub(crate) fn derive_key(
+    passphrase: &[u8],
+    salt: &[u8],
+    kdf: &KdfParams,
+) -> Result<SecureBuffer, SignerError> {
+    argon2id(passphrase, salt, kdf, None)
+}
+
+fn argon2id(
+    passphrase: &[u8],
+    salt: &[u8],
+    kdf: &KdfParams,
+    secret: Option<&[u8]>,
+) -> Result<SecureBuffer, SignerError> {
+    kdf.check_maximum()?;
+
+    let params = Params::new(kdf.memory_cost, kdf.time_cost, kdf.parallelism, Some(KEY_SIZE))
+        .map_err(|e| SignerError::KeyDerivationFailed(e.to_string()))?;
+
+    let argon2 = match secret {
+        Some(secret) => Argon2::new_with_secret(secret, argon2::Algorithm::Argon2id, Version::V0x13, params)
+            .map_err(|e| SignerError::KeyDerivationFailed(e.to_string()))?,
+        None => Argon2::new(argon2::Algorithm::Argon2id, Version::V0x13, params),
+    };
+
+    // Use env-based locking mode for derived keys
+    let mut key = SecureBuffer::with_mode(KEY_SIZE, get_locking_mode())?;
+
+    argon2
+        .hash_password_into(passphrase, salt, key.as_mut_slice())
+        .map_err(|e| SignerError::KeyDerivationFailed(e.to_string()))?;
+
+    Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_minimum_enforced() {
+        assert!(KdfParams::new(1024, 1, 1).is_err());
+        assert!(KdfParams::new(19456, 2, 1).is_ok());
+        assert!(KdfParams::DEFAULT.check_minimum().is_ok());
+    }
+
+    #[test]
+    fn test_maximum_enforced_on_derive() {
+        std::env::set_var("SIGNER_ALLOW_INSECURE_MEMORY", "1");
+
+        let huge = KdfParams {
+            memory_cost: u32::MAX,
+            time_cost: 3,
+            parallelism: 4,
+        };
+        assert!(derive_key(b"pass", &[0u8; 32], &huge).is_err());
+    }
+
+    #[test]
+    fn test_kdf_serialization() {
+        let scrypt = Kdf::Scrypt(ScryptParams { log_n: 14, r: 8, p: 1 });
+        let json = serde_json::to_string(&scrypt).unwrap();
+        assert_eq!(json, r#"{"algorithm":"scrypt","log_n":14,"r":8,"p":1}"#);
+        assert_eq!(serde_json::from_str::<Kdf>(&json).unwrap(), scrypt);
+
+        let pbkdf2: Kdf =
+            serde_json::from_str(r#"{"algorithm":"pbkdf2-sha256","iterations":600000}"#).unwrap();
+        assert_eq!(pbkdf2, Kdf::Pbkdf2Sha256(Pbkdf2Params { iterations: 600000 }));
+
+        // Untagged parameters are Argon2id
+        let untagged: Kdf =
+            serde_json::from_str(r#"{"memory_cost":65536,"time_cost":3,"parallelism":4}"#).unwrap();
+        assert_eq!(untagged, Kdf::default());
+    }
+
+    #[test]
+    fn test_pbkdf2_known_answer() {
+        std::env::set_var("SIGNER_ALLOW_INSECURE_MEMORY", "1");
+
+        // RFC 7914 section 11 / PBKDF2-HMAC-SHA256 test vector (first 32 bytes)
+        let kdf = Kdf::Pbkdf2Sha256(Pbkdf2Params { iterations: 1 });
+        let key = kdf.derive(b"passwd", b"salt").unwrap();
+        assert_eq!(
+            hex::encode(key.as_slice()),
+            "55ac046e56e3089fec1691c22544b605f94185216dde0465e68b9d57c20dacbc"
+        );
+    }
+
+    #[test]
+    fn test_oversized_import_params_rejected() {
+        std::env::set_var("SIGNER_ALLOW_INSECURE_MEMORY", "1");
+
+        let scrypt = Kdf::Scrypt(ScryptParams { log_n: 40, r: 8, p: 1 });
+        assert!(scrypt.derive(b"pass", b"salt").is_err());
+
+        let pbkdf2 = Kdf::Pbkdf2Sha256(Pbkdf2Params { iterations: u32::MAX });
+        assert!(pbkdf2.derive(b"pass", b"salt").is_err());
+    }
+
+    #[test]
+    fn test_params_change_derived_key() {
+        std::env::set_var("SIGNER_ALLOW_INSECURE_MEMORY", "1");
+
+        let salt = [7u8; 32];
+        let a = derive_key(b"pass", &salt, &KdfParams::MINIMUM).unwrap();
+        let b = derive_key(
+            b"pass",
+            &salt,
+            &KdfParams {
+                time_cost: 3,
+                ..KdfParams::MINIMUM
+            },
+        )
+        .unwrap();
+        assert_ne!(a.as_slice(), b.as_slice());
+    }
+}
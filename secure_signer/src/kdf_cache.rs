@@ -0,0 +1,212 @@
+//! Derived-key cache for repeated unlocks
+//!
+//! Every decryption runs the container's KDF, by default 64 MiB of Argon2id
+//! over three passes. An interactive wallet that previews a transaction
+//! and then signs it pays that twice within seconds. A [`KdfCache`] keeps
+//! the derived key in a [`SecureBuffer`] for a short TTL, so the second
+//! unlock of the same container with the same passphrase skips the KDF:
+//!
+//! ```ignore
+//! let cache = KdfCache::new(Duration::from_secs(30))?;
+//! let backend = ContainerBackend::new(&container, passphrase).with_kdf_cache(&cache);
+//! backend.sign_solana(&message)?; // runs Argon2id
+//! backend.sign_solana(&message)?; // reuses the derived key
+//! ```
+//!
+//! Entries are looked up by an HMAC, under a random per-cache key, of the
+//! KDF parameters, salt, passphrase, and keyfile, so neither the
+//! passphrase nor anything that could be brute-forced offline is kept.
+//! Only keys that decrypted their container are stored. An expired entry
+//! is zeroized on the next call to the cache, and every entry is zeroized
+//! when the cache is dropped, [`cleared`](KdfCache::clear), or the system
+//! suspends (see [`suspend`](crate::suspend)).
+//!
+//! The cache is opt-in: while an entry is live, the derived key is in
+//! memory even though no signature is in progress.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::backend::traced_unlock;
+use crate::crypto::{get_locking_mode, EncryptedKeyContainer};
+use crate::entropy::fill_random;
+use crate::error::SignerError;
+use crate::keyfile::Keyfile;
+use crate::keys::SecureKdfKey;
+use crate::secure_buffer::SecureBuffer;
+use crate::suspend::{on_suspend, SuspendHook};
+
+/// Domain separator for entry IDs
+const ENTRY_DOMAIN: &[u8] = b"coldstar-kdf-cache-v1";
+
+struct Entry {
+    key: SecureKdfKey,
+    expires_at: Instant,
+}
+
+type Entries = HashMap<[u8; 32], Entry>;
+
+/// Derived container keys, held for a TTL
+pub struct KdfCache {
+    ttl: Duration,
+    /// HMAC key for entry IDs
+    id_key: SecureBuffer,
+    entries: Arc<Mutex<Entries>>,
+    _suspend: SuspendHook,
+}
+
+impl KdfCache {
+    /// An empty cache whose entries live for `ttl` after they are derived
+    pub fn new(ttl: Duration) -> Result<Self, SignerError> {
+        let mut id_key = SecureBuffer::with_mode(32, get_locking_mode())?;
+        fill_random(id_key.as_mut_slice())?;
+        let entries = Arc::new(Mutex::new(Entries::new()));
+        let weak = Arc::downgrade(&entries);
+        let suspend = on_suspend(move || {
+            if let Some(entries) = weak.upgrade() {
+                lock(&entries).clear();
+            }
+        });
+        Ok(Self {
+            ttl,
+            id_key,
+            entries,
+            _suspend: suspend,
+        })
+    }
+
+    /// How long entries live
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Number of live entries
+    pub fn len(&self) -> usize {
+        self.live_entries().len()
+    }
+
+    /// Whether there are no live entries
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Zeroize every entry now
+    pub fn clear(&self) {
+        lock(&self.entries).clear();
+    }
+
+    /// Decrypt `container`'s key, deriving the unlock key only on a miss
+    pub(crate) fn decrypt_key(
+        &self,
+        container: &EncryptedKeyContainer,
+        passphrase: &str,
+        keyfile: Option<&Keyfile>,
+    ) -> Result<SecureBuffer, SignerError> {
+        let id = self.entry_id(container, passphrase, keyfile)?;
+        if let Some(entry) = self.live_entries().get(&id) {
+            return traced_unlock(container, true, || container.decrypt_key_with_unlock_key(&entry.key));
+        }
+
+        // Derive outside the lock, so other containers are not held up
+        let (unlock_key, secure_key) = traced_unlock(container, false, || {
+            let unlock_key = container.derive_unlock_key_with_keyfile(passphrase, keyfile)?;
+            let secure_key = container.decrypt_key_with_unlock_key(&unlock_key)?;
+            Ok((unlock_key, secure_key))
+        })?;
+        self.live_entries().insert(
+            id,
+            Entry {
+                key: unlock_key,
+                expires_at: Instant::now() + self.ttl,
+            },
+        );
+        Ok(secure_key)
+    }
+
+    /// The entries, with expired ones zeroized and removed
+    fn live_entries(&self) -> MutexGuard<'_, Entries> {
+        let mut entries = lock(&self.entries);
+        let now = Instant::now();
+        entries.retain(|_, entry| entry.expires_at > now);
+        entries
+    }
+
+    /// Everything the derived key depends on, under the cache's HMAC key
+    fn entry_id(
+        &self,
+        container: &EncryptedKeyContainer,
+        passphrase: &str,
+        keyfile: Option<&Keyfile>,
+    ) -> Result<[u8; 32], SignerError> {
+        let salt = base64::Engine::decode(&base64::engine::general_purpose::STANDARD, &container.salt)?;
+        let kdf = serde_json::to_vec(&container.kdf)?;
+        let mut mac = Hmac::<Sha256>::new_from_slice(self.id_key.as_slice()).expect("HMAC takes any key length");
+        mac.update(ENTRY_DOMAIN);
+        for field in [kdf.as_slice(), &salt, passphrase.as_bytes()] {
+            mac.update(&(field.len() as u64).to_le_bytes());
+            mac.update(field);
+        }
+        match keyfile {
+            Some(keyfile) => {
+                mac.update(&[1]);
+                mac.update(keyfile.as_slice());
+            }
+            None => mac.update(&[0]),
+        }
+        Ok(mac.finalize().into_bytes().into())
+    }
+}
+
+fn lock(entries: &Mutex<Entries>) -> MutexGuard<'_, Entries> {
+    entries.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+impl std::fmt::Debug for KdfCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("KdfCache")
+            .field("ttl", &self.ttl)
+            .field("entries", &lock(&self.entries).len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::{ContainerBackend, SignerBackend};
+    use crate::crypto::Cipher;
+    use crate::kdf::KdfParams;
+
+    #[test]
+    fn test_kdf_cache_reuses_derived_key_until_expiry() {
+        std::env::set_var("SIGNER_ALLOW_INSECURE_MEMORY", "1");
+        let container =
+            EncryptedKeyContainer::encrypt_with_kdf(&[8u8; 32], "pw", Cipher::Aes256Gcm, KdfParams::MINIMUM).unwrap();
+        let cache = KdfCache::new(Duration::from_millis(300)).unwrap();
+        let message = crate::crypto::test_transaction(&[8u8; 32]);
+
+        // A wrong passphrase is not cached
+        assert!(ContainerBackend::new(&container, "pv").with_kdf_cache(&cache).sign_solana(&message).is_err());
+        assert!(cache.is_empty());
+
+        let expected = ContainerBackend::new(&container, "pw").sign_solana(&message).unwrap();
+        let backend = ContainerBackend::new(&container, "pw").with_kdf_cache(&cache);
+        assert_eq!(backend.sign_solana(&message).unwrap().signature, expected.signature);
+        assert_eq!(cache.len(), 1);
+        assert_eq!(backend.sign_solana(&message).unwrap().signature, expected.signature);
+        assert_eq!(cache.len(), 1);
+
+        // A cached key does not admit another passphrase
+        assert!(ContainerBackend::new(&container, "pv").with_kdf_cache(&cache).sign_solana(&message).is_err());
+
+        std::thread::sleep(Duration::from_millis(400));
+        assert!(cache.is_empty());
+        assert!(backend.sign_solana(&message).is_ok());
+        cache.clear();
+        assert!(cache.is_empty());
+    }
+}
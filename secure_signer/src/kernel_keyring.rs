@@ -0,0 +1,329 @@
+//! Linux kernel keyring backend
+//!
+//! Running the KDF for every signature is slow by design, and keeping the
+//! decrypted key in a long-lived agent process exposes it to anything that
+//! can read that process's memory. This backend stores the container's
+//! *unlock key* (the KDF output, not the private key) in the kernel session
+//! keyring instead:
+//!
+//! - the KDF runs once, in [`KeyringBackend::unlock`]
+//! - the key lives in kernel memory with a timeout, after which the kernel
+//!   expires it and signing requires the passphrase again
+//! - each signature reads the unlock key into locked memory, decrypts the
+//!   container, signs, and zeroizes both
+//!
+//! Keys are restricted to possessors of the session keyring, so other
+//! sessions running as the same user cannot read them. They can be
+//! revoked or given a new expiry at any time.
+
+use std::ffi::CString;
+use std::io;
+use std::ptr;
+use std::time::Duration;
+
+use crate::backend::SignerBackend;
+use crate::crypto::{
+    get_locking_mode, sign_evm_with_nonce_mode, sign_message_with_secure_key, sign_with_secure_key,
+    EVMSigningResult, EncryptedKeyContainer, NonceMode, SigningResult,
+};
+use crate::error::SignerError;
+use crate::keys::SecureKdfKey;
+use crate::secure_buffer::SecureBuffer;
+use crate::suspend::{on_suspend, SuspendHook};
+
+// From <linux/keyctl.h>
+const KEY_SPEC_SESSION_KEYRING: libc::c_long = -3;
+const KEYCTL_REVOKE: libc::c_long = 3;
+const KEYCTL_SETPERM: libc::c_long = 5;
+const KEYCTL_SEARCH: libc::c_long = 10;
+const KEYCTL_READ: libc::c_long = 11;
+const KEYCTL_SET_TIMEOUT: libc::c_long = 15;
+
+/// View, read, write, search, link and setattr for possessors only
+const KEY_POS_ALL: libc::c_long = 0x3f00_0000;
+
+const KEY_TYPE: &str = "user";
+const DESCRIPTION_PREFIX: &str = "coldstar:unlock:";
+
+/// A `user` key in the session keyring
+#[derive(Debug)]
+pub struct KeyringKey {
+    serial: i32,
+}
+
+impl KeyringKey {
+    /// Add (or replace) a key with an expiry
+    pub fn store(description: &str, secret: &SecureBuffer, timeout: Duration) -> Result<Self, SignerError> {
+        let key_type = c_string(KEY_TYPE)?;
+        let description = c_string(description)?;
+
+        let serial = unsafe {
+            libc::syscall(
+                libc::SYS_add_key,
+                key_type.as_ptr(),
+                description.as_ptr(),
+                secret.as_slice().as_ptr(),
+                secret.len(),
+                KEY_SPEC_SESSION_KEYRING,
+            )
+        };
+        let key = Self {
+            serial: check(serial, "add_key")? as i32,
+        };
+
+        if let Err(e) = key
+            .keyctl(KEYCTL_SETPERM, KEY_POS_ALL, "setperm")
+            .and_then(|_| key.set_timeout(timeout))
+        {
+            let _ = key.keyctl(KEYCTL_REVOKE, 0, "revoke");
+            return Err(e);
+        }
+        Ok(key)
+    }
+
+    /// Find a key in the session keyring by description
+    pub fn find(description: &str) -> Result<Option<Self>, SignerError> {
+        let key_type = c_string(KEY_TYPE)?;
+        let description = c_string(description)?;
+
+        let serial = unsafe {
+            libc::syscall(
+                libc::SYS_keyctl,
+                KEYCTL_SEARCH,
+                KEY_SPEC_SESSION_KEYRING,
+                key_type.as_ptr(),
+                description.as_ptr(),
+                0 as libc::c_long,
+            )
+        };
+        if serial < 0 {
+            let err = io::Error::last_os_error();
+            return match err.raw_os_error() {
+                Some(libc::ENOKEY) | Some(libc::EKEYEXPIRED) | Some(libc::EKEYREVOKED) => Ok(None),
+                _ => Err(keyring_error("keyctl_search", err)),
+            };
+        }
+        Ok(Some(Self { serial: serial as i32 }))
+    }
+
+    /// Kernel serial number of the key
+    pub fn serial(&self) -> i32 {
+        self.serial
+    }
+
+    /// Read the key's payload into a secure buffer
+    pub fn read(&self) -> Result<SecureBuffer, SignerError> {
+        let len = self.read_into(ptr::null_mut(), 0)?;
+        let mut buffer = SecureBuffer::with_mode(len, get_locking_mode())?;
+        let read = self.read_into(buffer.as_mut_slice().as_mut_ptr(), len)?;
+        if read != len {
+            buffer.zeroize();
+            return Err(SignerError::PlatformError("keyring payload changed while reading".to_string()));
+        }
+        Ok(buffer)
+    }
+
+    /// Expire the key `timeout` from now (rounded up to whole seconds)
+    pub fn set_timeout(&self, timeout: Duration) -> Result<(), SignerError> {
+        let seconds = timeout.as_secs() + u64::from(timeout.subsec_nanos() > 0);
+        if seconds == 0 {
+            // A zero timeout means "never expire" to the kernel
+            return Err(SignerError::PlatformError("keyring timeout must be non-zero".to_string()));
+        }
+        let seconds = libc::c_long::try_from(seconds.min(u64::from(u32::MAX))).unwrap_or(libc::c_long::MAX);
+        self.keyctl(KEYCTL_SET_TIMEOUT, seconds, "keyctl_set_timeout")
+    }
+
+    /// Revoke the key; further reads fail immediately
+    pub fn revoke(self) -> Result<(), SignerError> {
+        self.keyctl(KEYCTL_REVOKE, 0, "keyctl_revoke")
+    }
+
+    fn read_into(&self, buffer: *mut u8, len: usize) -> Result<usize, SignerError> {
+        let ret = unsafe { libc::syscall(libc::SYS_keyctl, KEYCTL_READ, self.serial as libc::c_long, buffer, len) };
+        Ok(check(ret, "keyctl_read")? as usize)
+    }
+
+    fn keyctl(&self, operation: libc::c_long, arg: libc::c_long, name: &str) -> Result<(), SignerError> {
+        let ret = unsafe { libc::syscall(libc::SYS_keyctl, operation, self.serial as libc::c_long, arg) };
+        check(ret, name).map(|_| ())
+    }
+}
+
+/// Backend that signs with a container unlocked via the kernel keyring
+pub struct KeyringBackend<'a> {
+    container: &'a EncryptedKeyContainer,
+    key: KeyringKey,
+}
+
+impl<'a> KeyringBackend<'a> {
+    /// Run the KDF once and cache the unlock key for `timeout`
+    ///
+    /// The passphrase is checked by decrypting the container before
+    /// anything is stored.
+    pub fn unlock(
+        container: &'a EncryptedKeyContainer,
+        passphrase: &str,
+        timeout: Duration,
+    ) -> Result<Self, SignerError> {
+        let description = description(container)?;
+        let mut unlock_key = container.derive_unlock_key(passphrase)?;
+
+        let stored = container
+            .decrypt_key_with_unlock_key(&unlock_key)
+            .and_then(|mut secret| {
+                secret.zeroize();
+                KeyringKey::store(&description, unlock_key.as_buffer(), timeout)
+            });
+        unlock_key.zeroize();
+
+        Ok(Self {
+            container,
+            key: stored?,
+        })
+    }
+
+    /// Use an unlock key cached earlier (possibly by another process in the
+    /// same session)
+    pub fn attach(container: &'a EncryptedKeyContainer) -> Result<Self, SignerError> {
+        let description = description(container)?;
+        let key = KeyringKey::find(&description)?.ok_or_else(|| {
+            SignerError::PlatformError(format!("no unlock key in the session keyring for {}", description))
+        })?;
+        Ok(Self { container, key })
+    }
+
+    /// Move the expiry to `timeout` from now
+    pub fn expire_after(&self, timeout: Duration) -> Result<(), SignerError> {
+        self.key.set_timeout(timeout)
+    }
+
+    /// Revoke the cached unlock key
+    pub fn revoke(self) -> Result<(), SignerError> {
+        self.key.revoke()
+    }
+
+    /// Revoke the cached unlock key when the system suspends
+    ///
+    /// Kernel memory is part of a hibernation image like any other, so an
+    /// unlock key that outlives a suspend could be read back from disk.
+    /// The hook stays registered until the returned handle is dropped.
+    pub fn revoke_on_suspend(&self) -> SuspendHook {
+        let serial = self.key.serial;
+        on_suspend(move || {
+            let _ = KeyringKey { serial }.revoke();
+        })
+    }
+
+    fn decrypt_key(&self) -> Result<SecureBuffer, SignerError> {
+        let mut unlock_key = SecureKdfKey::from_buffer(self.key.read()?)?;
+        let result = self.container.decrypt_key_with_unlock_key(&unlock_key);
+        unlock_key.zeroize();
+        result
+    }
+}
+
+impl SignerBackend for KeyringBackend<'_> {
+    fn sign_solana(&self, message: &[u8]) -> Result<SigningResult, SignerError> {
+        let mut secure_key = self.decrypt_key()?;
+        let result = sign_with_secure_key(&secure_key, message);
+        secure_key.zeroize();
+
+        result
+    }
+
+    fn sign_solana_message(&self, message: &[u8]) -> Result<SigningResult, SignerError> {
+        let mut secure_key = self.decrypt_key()?;
+        let result = sign_message_with_secure_key(&secure_key, message);
+        secure_key.zeroize();
+
+        result
+    }
+
+    fn sign_evm_hash(&self, message_hash: &[u8]) -> Result<EVMSigningResult, SignerError> {
+        self.sign_evm_hash_with_nonce(message_hash, NonceMode::Deterministic)
+    }
+
+    fn sign_evm_hash_with_nonce(&self, message_hash: &[u8], nonce: NonceMode) -> Result<EVMSigningResult, SignerError> {
+        if message_hash.len() != 32 {
+            return Err(SignerError::InvalidTransaction(format!(
+                "EVM message hash must be 32 bytes, got {}",
+                message_hash.len()
+            )));
+        }
+
+        let mut secure_key = self.decrypt_key()?;
+        let result = sign_evm_with_nonce_mode(&secure_key, message_hash, nonce);
+        secure_key.zeroize();
+
+        result
+    }
+}
+
+fn description(container: &EncryptedKeyContainer) -> Result<String, SignerError> {
+    Ok(format!("{}{}", DESCRIPTION_PREFIX, container.container_id()?))
+}
+
+fn c_string(value: &str) -> Result<CString, SignerError> {
+    CString::new(value).map_err(|_| SignerError::PlatformError("keyring strings cannot contain NUL".to_string()))
+}
+
+fn check(ret: libc::c_long, function: &str) -> Result<libc::c_long, SignerError> {
+    if ret < 0 {
+        return Err(keyring_error(function, io::Error::last_os_error()));
+    }
+    Ok(ret)
+}
+
+fn keyring_error(function: &str, err: io::Error) -> SignerError {
+    let reason = match err.raw_os_error() {
+        Some(libc::ENOKEY) => "key not found".to_string(),
+        Some(libc::EKEYEXPIRED) => "key has expired".to_string(),
+        Some(libc::EKEYREVOKED) => "key was revoked".to_string(),
+        _ => err.to_string(),
+    };
+    SignerError::PlatformError(format!("{} failed: {}", function, reason))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::Cipher;
+    use crate::kdf::KdfParams;
+
+    fn container(seed: u8) -> EncryptedKeyContainer {
+        EncryptedKeyContainer::encrypt_with_kdf(&[seed; 32], "pw", Cipher::Aes256Gcm, KdfParams::MINIMUM).unwrap()
+    }
+
+    #[test]
+    fn test_unlock_attach_and_revoke() {
+        let container = container(8);
+        assert!(KeyringBackend::unlock(&container, "wrong", Duration::from_secs(60)).is_err());
+        assert!(KeyringBackend::attach(&container).is_err());
+
+        let backend = KeyringBackend::unlock(&container, "pw", Duration::from_secs(60)).unwrap();
+        let attached = KeyringBackend::attach(&container).unwrap();
+        assert_eq!(attached.key.serial(), backend.key.serial());
+
+        let message = crate::crypto::test_transaction(&[8u8; 32]);
+        let signed = attached.sign_solana(&message).unwrap();
+        assert_eq!(Some(signed.public_key), container.public_key.clone());
+        attached.expire_after(Duration::from_millis(1500)).unwrap();
+
+        backend.revoke().unwrap();
+        assert!(matches!(attached.sign_solana(&message), Err(SignerError::PlatformError(_))));
+        assert!(KeyringBackend::attach(&container).is_err());
+    }
+
+    #[test]
+    fn test_key_payload_roundtrip() {
+        let description = format!("{}test-{}", DESCRIPTION_PREFIX, std::process::id());
+        let secret = SecureBuffer::from_slice_with_mode(&[9u8; 32], get_locking_mode()).unwrap();
+
+        let key = KeyringKey::store(&description, &secret, Duration::from_secs(60)).unwrap();
+        assert_eq!(key.read().unwrap().as_slice(), secret.as_slice());
+        assert!(key.set_timeout(Duration::ZERO).is_err());
+        key.revoke().unwrap();
+        assert!(KeyringKey::find(&description).unwrap().is_none());
+    }
+}
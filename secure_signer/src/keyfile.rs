@@ -0,0 +1,147 @@
+//! Keyfiles: a second factor for containers
+//!
+//! A keyfile is a random 32-byte secret kept apart from the container, on
+//! another device or a USB stick. A container created with
+//! [`EncryptedKeyContainer::encrypt_with_keyfile`] passes the keyfile to
+//! Argon2id as its secret input, so decrypting it takes both the
+//! passphrase and the keyfile: a stolen container and an overheard
+//! passphrase are not enough.
+//!
+//! Keyfiles are written as the raw 32 bytes. Reading also accepts 64 hex
+//! digits, so a keyfile can be typed in from a paper backup.
+//!
+//! [`EncryptedKeyContainer::encrypt_with_keyfile`]: crate::crypto::EncryptedKeyContainer::encrypt_with_keyfile
+
+#[cfg(feature = "std")]
+use std::io::Write;
+#[cfg(feature = "std")]
+use std::path::Path;
+
+use crate::crypto::get_locking_mode;
+use crate::entropy::fill_random;
+use crate::error::SignerError;
+use crate::secure_buffer::SecureBuffer;
+
+/// Size of a keyfile's secret
+pub const KEYFILE_SIZE: usize = 32;
+
+/// A keyfile's secret in locked memory
+pub struct Keyfile(SecureBuffer);
+
+impl Keyfile {
+    /// Generate a new random keyfile
+    pub fn generate() -> Result<Self, SignerError> {
+        let mut secret = SecureBuffer::with_mode(KEYFILE_SIZE, get_locking_mode())?;
+        fill_random(secret.as_mut_slice())?;
+        Ok(Self(secret))
+    }
+
+    /// Copy a 32-byte secret into locked memory
+    pub fn from_bytes(secret: &[u8]) -> Result<Self, SignerError> {
+        if secret.len() != KEYFILE_SIZE {
+            return Err(SignerError::InvalidKeyFormat(secret.len()));
+        }
+        Ok(Self(SecureBuffer::from_slice_with_mode(secret, get_locking_mode())?))
+    }
+
+    /// Read a keyfile: 32 raw bytes, or 64 hex digits
+    #[cfg(feature = "std")]
+    pub fn read(path: impl AsRef<Path>) -> Result<Self, SignerError> {
+        let contents = zeroize::Zeroizing::new(std::fs::read(path)?);
+        if contents.len() == KEYFILE_SIZE {
+            return Self::from_bytes(&contents);
+        }
+        let secret = std::str::from_utf8(&contents)
+            .ok()
+            .and_then(|text| hex::decode(text.trim()).ok())
+            .map(zeroize::Zeroizing::new)
+            .ok_or_else(|| SignerError::ContainerError("keyfile is neither 32 bytes nor hex".to_string()))?;
+        Self::from_bytes(&secret)
+    }
+
+    /// Write the keyfile to `path`, which must not exist
+    ///
+    /// On Unix the file is readable by its owner only.
+    #[cfg(feature = "std")]
+    pub fn write_new(&self, path: impl AsRef<Path>) -> Result<(), SignerError> {
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        options.open(path)?.write_all(self.0.as_slice())?;
+        Ok(())
+    }
+
+    pub(crate) fn as_slice(&self) -> &[u8] {
+        self.0.as_slice()
+    }
+}
+
+impl core::fmt::Debug for Keyfile {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("Keyfile([REDACTED])")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::{Cipher, EncryptedKeyContainer};
+    use crate::kdf::KdfParams;
+    use crate::ContainerBackend;
+    use crate::SignerBackend;
+
+    #[test]
+    fn test_keyfile_container_needs_both_factors() {
+        std::env::set_var("SIGNER_ALLOW_INSECURE_MEMORY", "1");
+        let keyfile = Keyfile::generate().unwrap();
+        let container = EncryptedKeyContainer::encrypt_with_keyfile(
+            &[6u8; 32],
+            "pw",
+            &keyfile,
+            Cipher::Aes256Gcm,
+            KdfParams::MINIMUM,
+        )
+        .unwrap();
+        assert!(container.to_json().unwrap().contains("\"keyfile\":true"));
+        let message = crate::crypto::test_transaction(&[6u8; 32]);
+
+        let signed = ContainerBackend::new(&container, "pw").with_keyfile(&keyfile).sign_solana(&message);
+        assert_eq!(signed.unwrap().public_key, container.public_key.clone().unwrap());
+
+        // Passphrase alone, the wrong keyfile, or the wrong passphrase all fail
+        assert!(matches!(
+            ContainerBackend::new(&container, "pw").sign_solana(&message),
+            Err(SignerError::ContainerError(_))
+        ));
+        let other = Keyfile::generate().unwrap();
+        assert!(ContainerBackend::new(&container, "pw").with_keyfile(&other).sign_solana(&message).is_err());
+        assert!(ContainerBackend::new(&container, "pv").with_keyfile(&keyfile).sign_solana(&message).is_err());
+
+        // Clearing the flag does not turn it into a passphrase-only container
+        let mut stripped = container.clone();
+        stripped.keyfile = false;
+        assert!(ContainerBackend::new(&stripped, "pw").sign_solana(&message).is_err());
+    }
+
+    #[test]
+    fn test_keyfile_read_formats() {
+        std::env::set_var("SIGNER_ALLOW_INSECURE_MEMORY", "1");
+        let dir = std::env::temp_dir().join(format!("coldstar-keyfile-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let keyfile = Keyfile::generate().unwrap();
+        let raw = dir.join("raw.key");
+        keyfile.write_new(&raw).unwrap();
+        assert!(keyfile.write_new(&raw).is_err());
+        assert_eq!(Keyfile::read(&raw).unwrap().as_slice(), keyfile.as_slice());
+
+        let hex_path = dir.join("hex.key");
+        std::fs::write(&hex_path, format!("{}\n", hex::encode(keyfile.as_slice()))).unwrap();
+        assert_eq!(Keyfile::read(&hex_path).unwrap().as_slice(), keyfile.as_slice());
+
+        std::fs::write(&hex_path, b"too short").unwrap();
+        assert!(Keyfile::read(&hex_path).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,389 @@
+//! Multi-key keyrings
+//!
+//! A [`Keyring`] holds any number of Solana and EVM keys under one
+//! passphrase, each addressed by an alias. The passphrase goes through the
+//! KDF once per [`Keyring::unlock`], however many keys the keyring holds,
+//! instead of once per container.
+//!
+//! # Format
+//!
+//! The keyring stores one salt and KDF parameter set. Each entry is
+//! encrypted with XChaCha20-Poly1305 under its own key,
+//! `HKDF-SHA256(master, info = alias || 0 || chain || 0 || public_key)`, so
+//! an entry whose alias, chain, or public key was edited (or that was
+//! copied between keyrings) fails to decrypt.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use ed25519_dalek::SigningKey;
+use hkdf::Hkdf;
+use k256::ecdsa::SigningKey as K256SigningKey;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use crate::backend::SignerBackend;
+use crate::crypto::{
+    evm_address_from_pubkey, get_locking_mode, sign_evm_with_nonce_mode, sign_message_with_secure_key,
+    sign_with_secure_key, Cipher, EVMSigningResult, EncryptedKeyContainer, NonceMode, SigningResult, SALT_SIZE,
+};
+use crate::entropy::fill_random;
+use crate::error::SignerError;
+use crate::kdf::{Kdf, KdfParams};
+use crate::secure_buffer::SecureBuffer;
+
+/// Keyring format version
+const KEYRING_VERSION: u8 = 1;
+
+/// HKDF salt for per-entry keys
+const ENTRY_KEY_SALT: &[u8] = b"coldstar-keyring-v1";
+
+/// Cipher for entries; random 192-bit nonces are safe for any number of
+/// entries under one master key
+const ENTRY_CIPHER: Cipher = Cipher::XChaCha20Poly1305;
+
+/// Kind of key held by an entry
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ChainType {
+    /// Ed25519 seed (Solana)
+    Solana,
+    /// secp256k1 scalar (EVM)
+    Evm,
+}
+
+impl ChainType {
+    fn as_str(&self) -> &'static str {
+        match self {
+            ChainType::Solana => "solana",
+            ChainType::Evm => "evm",
+        }
+    }
+
+    /// Public key (base58) or address (0x-prefixed) of a 32-byte private key
+    fn public_key(&self, private_key: &[u8]) -> Result<String, SignerError> {
+        let bytes: &[u8; 32] = private_key
+            .try_into()
+            .map_err(|_| SignerError::InvalidKeyFormat(private_key.len()))?;
+        match self {
+            ChainType::Solana => Ok(bs58::encode(SigningKey::from_bytes(bytes).verifying_key().as_bytes()).into_string()),
+            ChainType::Evm => {
+                let key = K256SigningKey::from_bytes(bytes.into())
+                    .map_err(|_| SignerError::InvalidKeyFormat(private_key.len()))?;
+                Ok(evm_address_from_pubkey(key.verifying_key()))
+            }
+        }
+    }
+}
+
+/// One key in a keyring
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct KeyringEntry {
+    /// Unique name of the key
+    pub alias: String,
+    /// Kind of key
+    pub chain: ChainType,
+    /// Public key (base58) for Solana keys, address for EVM keys
+    pub public_key: String,
+    /// Unix timestamp (seconds) when the key was added
+    pub created_at: u64,
+    /// Nonce (base64)
+    pub nonce: String,
+    /// Encrypted private key with auth tag (base64)
+    pub ciphertext: String,
+}
+
+/// Several keys encrypted under one passphrase
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Keyring {
+    /// Format version
+    pub version: u8,
+    /// KDF and parameters for the master key
+    pub kdf: Kdf,
+    /// Salt for the master key (base64)
+    pub salt: String,
+    /// Keys, in the order they were added
+    pub entries: Vec<KeyringEntry>,
+}
+
+impl Keyring {
+    /// Create an empty keyring with the default KDF parameters
+    pub fn new() -> Self {
+        Self::with_kdf(KdfParams::default()).expect("default KDF parameters are valid and the RNG is healthy")
+    }
+
+    /// Create an empty keyring with explicit KDF parameters
+    pub fn with_kdf(kdf: KdfParams) -> Result<Self, SignerError> {
+        kdf.check_minimum()?;
+        let mut salt = [0u8; SALT_SIZE];
+        fill_random(&mut salt)?;
+
+        Ok(Self {
+            version: KEYRING_VERSION,
+            kdf: Kdf::Argon2id(kdf),
+            salt: BASE64.encode(salt),
+            entries: Vec::new(),
+        })
+    }
+
+    /// Look up an entry by alias
+    pub fn entry(&self, alias: &str) -> Option<&KeyringEntry> {
+        self.entries.iter().find(|e| e.alias == alias)
+    }
+
+    /// All aliases, in the order they were added
+    pub fn aliases(&self) -> impl Iterator<Item = &str> {
+        self.entries.iter().map(|e| e.alias.as_str())
+    }
+
+    /// Remove a key, returning whether it was present
+    pub fn remove(&mut self, alias: &str) -> bool {
+        let before = self.entries.len();
+        self.entries.retain(|e| e.alias != alias);
+        self.entries.len() != before
+    }
+
+    /// Derive the master key, checking the passphrase against an existing
+    /// entry
+    pub fn unlock(&mut self, passphrase: &str) -> Result<UnlockedKeyring<'_>, SignerError> {
+        if self.version != KEYRING_VERSION {
+            return Err(SignerError::ContainerError(format!(
+                "unsupported keyring version {}",
+                self.version
+            )));
+        }
+
+        let salt = BASE64.decode(&self.salt)?;
+        let unlocked = UnlockedKeyring {
+            master: self.kdf.derive(passphrase.as_bytes(), &salt)?,
+            keyring: self,
+        };
+        if let Some(first) = unlocked.keyring.entries.first() {
+            unlocked.decrypt_entry(first)?;
+        }
+        Ok(unlocked)
+    }
+
+    /// Serialize to JSON
+    pub fn to_json(&self) -> Result<String, SignerError> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Parse from JSON
+    pub fn from_json(json: &str) -> Result<Self, SignerError> {
+        serde_json::from_str(json).map_err(|e| SignerError::ContainerError(e.to_string()))
+    }
+}
+
+impl Default for Keyring {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A keyring with its master key in locked memory
+///
+/// The master key is zeroized when this is dropped.
+pub struct UnlockedKeyring<'a> {
+    keyring: &'a mut Keyring,
+    master: SecureBuffer,
+}
+
+impl UnlockedKeyring<'_> {
+    /// Add a 32-byte private key under a new alias
+    ///
+    /// Returns the public key (Solana) or address (EVM).
+    pub fn add(&mut self, alias: &str, chain: ChainType, private_key: &[u8]) -> Result<String, SignerError> {
+        if alias.is_empty() || self.keyring.entry(alias).is_some() {
+            return Err(SignerError::ContainerError(format!(
+                "alias '{}' is empty or already in use",
+                alias
+            )));
+        }
+        let public_key = chain.public_key(private_key)?;
+
+        let mut nonce = vec![0u8; ENTRY_CIPHER.nonce_size()];
+        fill_random(&mut nonce)?;
+        let mut key = self.entry_key(alias, chain, &public_key)?;
+        let ciphertext = ENTRY_CIPHER.encrypt(key.as_slice(), &nonce, private_key);
+        key.zeroize();
+
+        self.keyring.entries.push(KeyringEntry {
+            alias: alias.to_string(),
+            chain,
+            public_key: public_key.clone(),
+            created_at: unix_now(),
+            nonce: BASE64.encode(nonce),
+            ciphertext: BASE64.encode(ciphertext?),
+        });
+        Ok(public_key)
+    }
+
+    /// Generate a new key under `alias`
+    pub fn generate(&mut self, alias: &str, chain: ChainType) -> Result<String, SignerError> {
+        let mut key = SecureBuffer::with_mode(32, get_locking_mode())?;
+        loop {
+            fill_random(key.as_mut_slice())?;
+            // A random scalar is out of range with negligible probability
+            if chain != ChainType::Evm || K256SigningKey::from_slice(key.as_slice()).is_ok() {
+                break;
+            }
+        }
+        let result = self.add(alias, chain, key.as_slice());
+        key.zeroize();
+        result
+    }
+
+    /// Move a Solana container into the keyring
+    pub fn import_container(
+        &mut self,
+        alias: &str,
+        container: &EncryptedKeyContainer,
+        passphrase: &str,
+    ) -> Result<String, SignerError> {
+        let mut seed = container.decrypt_key(passphrase)?;
+        let result = self.add(alias, ChainType::Solana, seed.as_slice());
+        seed.zeroize();
+        result
+    }
+
+    /// Signer for the key under `alias`
+    pub fn sign_with(&self, alias: &str) -> Result<AliasSigner<'_>, SignerError> {
+        let entry = self
+            .keyring
+            .entry(alias)
+            .ok_or_else(|| SignerError::ContainerError(format!("no key with alias '{}'", alias)))?;
+        Ok(AliasSigner { keyring: self, entry })
+    }
+
+    fn entry_key(&self, alias: &str, chain: ChainType, public_key: &str) -> Result<SecureBuffer, SignerError> {
+        let info = [alias.as_bytes(), &[0], chain.as_str().as_bytes(), &[0], public_key.as_bytes()].concat();
+        let mut key = SecureBuffer::with_mode(32, get_locking_mode())?;
+        Hkdf::<Sha256>::new(Some(ENTRY_KEY_SALT), self.master.as_slice())
+            .expand(&info, key.as_mut_slice())
+            .map_err(|e| SignerError::KeyDerivationFailed(e.to_string()))?;
+        Ok(key)
+    }
+
+    fn decrypt_entry(&self, entry: &KeyringEntry) -> Result<SecureBuffer, SignerError> {
+        let nonce = BASE64.decode(&entry.nonce)?;
+        let ciphertext = BASE64.decode(&entry.ciphertext)?;
+
+        let mut key = self.entry_key(&entry.alias, entry.chain, &entry.public_key)?;
+        let secure_key = ENTRY_CIPHER.decrypt_to_secure(key.as_slice(), &nonce, &ciphertext);
+        key.zeroize();
+        secure_key
+    }
+}
+
+/// Signs with one key of an unlocked keyring
+pub struct AliasSigner<'a> {
+    keyring: &'a UnlockedKeyring<'a>,
+    entry: &'a KeyringEntry,
+}
+
+impl AliasSigner<'_> {
+    fn decrypt_for(&self, chain: ChainType) -> Result<SecureBuffer, SignerError> {
+        if self.entry.chain != chain {
+            return Err(SignerError::InvalidTransaction(format!(
+                "'{}' is a {} key",
+                self.entry.alias,
+                self.entry.chain.as_str()
+            )));
+        }
+        self.keyring.decrypt_entry(self.entry)
+    }
+}
+
+impl SignerBackend for AliasSigner<'_> {
+    fn sign_solana(&self, message: &[u8]) -> Result<SigningResult, SignerError> {
+        let mut secure_key = self.decrypt_for(ChainType::Solana)?;
+        let result = sign_with_secure_key(&secure_key, message);
+        secure_key.zeroize();
+
+        result
+    }
+
+    fn sign_solana_message(&self, message: &[u8]) -> Result<SigningResult, SignerError> {
+        let mut secure_key = self.decrypt_for(ChainType::Solana)?;
+        let result = sign_message_with_secure_key(&secure_key, message);
+        secure_key.zeroize();
+
+        result
+    }
+
+    fn sign_evm_hash(&self, message_hash: &[u8]) -> Result<EVMSigningResult, SignerError> {
+        self.sign_evm_hash_with_nonce(message_hash, NonceMode::Deterministic)
+    }
+
+    fn sign_evm_hash_with_nonce(&self, message_hash: &[u8], nonce: NonceMode) -> Result<EVMSigningResult, SignerError> {
+        if message_hash.len() != 32 {
+            return Err(SignerError::InvalidTransaction(format!(
+                "EVM message hash must be 32 bytes, got {}",
+                message_hash.len()
+            )));
+        }
+
+        let mut secure_key = self.decrypt_for(ChainType::Evm)?;
+        let result = sign_evm_with_nonce_mode(&secure_key, message_hash, nonce);
+        secure_key.zeroize();
+
+        result
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keyring_holds_several_keys() {
+        let mut keyring = Keyring::with_kdf(KdfParams::MINIMUM).unwrap();
+        let container =
+            EncryptedKeyContainer::encrypt_with_kdf(&[2u8; 32], "old", Cipher::Aes256Gcm, KdfParams::MINIMUM).unwrap();
+
+        let (hot, treasury) = {
+            let mut unlocked = keyring.unlock("pw").unwrap();
+            let hot = unlocked.generate("hot", ChainType::Solana).unwrap();
+            let treasury = unlocked.add("treasury", ChainType::Evm, &[7u8; 32]).unwrap();
+            unlocked.import_container("imported", &container, "old").unwrap();
+            assert!(unlocked.add("hot", ChainType::Solana, &[1u8; 32]).is_err());
+            (hot, treasury)
+        };
+
+        let mut keyring = Keyring::from_json(&keyring.to_json().unwrap()).unwrap();
+        assert_eq!(keyring.aliases().collect::<Vec<_>>(), vec!["hot", "treasury", "imported"]);
+        assert!(keyring.unlock("wrong").is_err());
+
+        let unlocked = keyring.unlock("pw").unwrap();
+        assert_eq!(unlocked.sign_with("hot").unwrap().sign_solana_message(b"msg").unwrap().public_key, hot);
+        assert_eq!(unlocked.sign_with("treasury").unwrap().sign_evm_hash(&[1u8; 32]).unwrap().address, treasury);
+        assert_eq!(
+            Some(unlocked.sign_with("imported").unwrap().sign_solana(&crate::crypto::test_transaction(&[2u8; 32])).unwrap().public_key),
+            container.public_key
+        );
+
+        // Chain mismatch and unknown aliases are rejected
+        assert!(unlocked.sign_with("treasury").unwrap().sign_solana(b"msg").is_err());
+        assert!(unlocked.sign_with("missing").is_err());
+    }
+
+    #[test]
+    fn test_relabelled_entry_fails_to_decrypt() {
+        let mut keyring = Keyring::with_kdf(KdfParams::MINIMUM).unwrap();
+        keyring.unlock("pw").unwrap().generate("a", ChainType::Solana).unwrap();
+        keyring.unlock("pw").unwrap().generate("b", ChainType::Solana).unwrap();
+
+        // Swap the aliases: each entry's key no longer derives correctly
+        keyring.entries[0].alias = "b".to_string();
+        keyring.entries[1].alias = "a".to_string();
+        assert!(keyring.unlock("pw").is_err());
+    }
+}
@@ -0,0 +1,204 @@
+//! Typed key material
+//!
+//! A container's secret is 32 bytes that can act as an Ed25519 seed or a
+//! secp256k1 scalar, and the KDF output that unlocks it is 32 bytes too.
+//! Passed around as bare [`SecureBuffer`]s, they are easy to mix up. The
+//! wrappers here fix the length at compile time and give each kind of key
+//! only the operations that make sense for it:
+//!
+//! - [`SecureEd25519Seed`]: Solana (and other Ed25519 chain) signing
+//! - [`SecureSecp256k1Scalar`]: EVM ECDSA and BIP-340 Schnorr signing,
+//!   validated as a scalar on construction
+//! - [`SecureKdfKey`]: the output of a container's KDF, which decrypts
+//!   that container and nothing else
+//!
+//! None of them expose their bytes outside the crate.
+
+use alloc::string::String;
+
+use ed25519_dalek::SigningKey;
+use k256::ecdsa::SigningKey as K256SigningKey;
+
+use crate::crypto::{
+    evm_address_from_pubkey, get_locking_mode, sign_evm_with_nonce_mode, sign_evm_with_secure_key,
+    sign_schnorr_with_secure_key, sign_with_secure_key, EVMSigningResult, EncryptedKeyContainer, NonceMode,
+    SchnorrSigningResult, SigningResult,
+};
+use crate::eddsa::sign_with_context_with_secure_key;
+use crate::error::SignerError;
+use crate::secure_buffer::SecureBuffer;
+
+/// Length of every typed key
+pub const KEY_LEN: usize = 32;
+
+/// Move `buffer` into a typed key, checking its length
+fn checked(buffer: SecureBuffer) -> Result<SecureBuffer, SignerError> {
+    match buffer.len() {
+        KEY_LEN => Ok(buffer),
+        len => Err(SignerError::InvalidKeyFormat(len)),
+    }
+}
+
+/// An Ed25519 signing seed in locked memory
+pub struct SecureEd25519Seed(SecureBuffer);
+
+impl SecureEd25519Seed {
+    /// Copy `seed` into locked memory; the caller should zeroize the source
+    pub fn from_bytes(seed: &[u8; KEY_LEN]) -> Result<Self, SignerError> {
+        Ok(Self(SecureBuffer::from_slice_with_mode(seed, get_locking_mode())?))
+    }
+
+    /// Decrypt a container's key as an Ed25519 seed
+    pub fn from_container(container: &EncryptedKeyContainer, passphrase: &str) -> Result<Self, SignerError> {
+        Self::from_buffer(container.decrypt_key(passphrase)?)
+    }
+
+    pub(crate) fn from_buffer(buffer: SecureBuffer) -> Result<Self, SignerError> {
+        checked(buffer).map(Self)
+    }
+
+    /// The Ed25519 public key
+    pub fn public_key(&self) -> [u8; 32] {
+        SigningKey::from_bytes(self.0.as_slice().try_into().expect("32-byte seed"))
+            .verifying_key()
+            .to_bytes()
+    }
+
+    /// Sign a Solana transaction message
+    pub fn sign(&self, message: &[u8]) -> Result<SigningResult, SignerError> {
+        sign_with_secure_key(&self.0, message)
+    }
+
+    /// Sign a message with Ed25519ctx under `context` (1-255 bytes)
+    pub fn sign_with_context(&self, message: &[u8], context: &[u8]) -> Result<SigningResult, SignerError> {
+        sign_with_context_with_secure_key(&self.0, message, context)
+    }
+}
+
+/// A secp256k1 private scalar in locked memory, known to be in range
+pub struct SecureSecp256k1Scalar(SecureBuffer);
+
+impl SecureSecp256k1Scalar {
+    /// Copy `scalar` into locked memory; the caller should zeroize the source
+    ///
+    /// Fails if `scalar` is zero or not below the curve order.
+    pub fn from_bytes(scalar: &[u8; KEY_LEN]) -> Result<Self, SignerError> {
+        Self::from_buffer(SecureBuffer::from_slice_with_mode(scalar, get_locking_mode())?)
+    }
+
+    /// Decrypt a container's key as a secp256k1 scalar
+    pub fn from_container(container: &EncryptedKeyContainer, passphrase: &str) -> Result<Self, SignerError> {
+        Self::from_buffer(container.decrypt_key(passphrase)?)
+    }
+
+    pub(crate) fn from_buffer(buffer: SecureBuffer) -> Result<Self, SignerError> {
+        let buffer = checked(buffer)?;
+        K256SigningKey::from_slice(buffer.as_slice())
+            .map_err(|_| SignerError::InvalidKeyFormat(KEY_LEN))?;
+        Ok(Self(buffer))
+    }
+
+    /// The EVM address (checksummed hex)
+    pub fn evm_address(&self) -> String {
+        let signing_key = K256SigningKey::from_slice(self.0.as_slice()).expect("validated scalar");
+        evm_address_from_pubkey(signing_key.verifying_key())
+    }
+
+    /// The uncompressed SEC1 public key (`0x04 || x || y`)
+    pub fn public_key(&self) -> [u8; 65] {
+        let signing_key = K256SigningKey::from_slice(self.0.as_slice()).expect("validated scalar");
+        signing_key
+            .verifying_key()
+            .to_encoded_point(false)
+            .as_bytes()
+            .try_into()
+            .expect("uncompressed point is 65 bytes")
+    }
+
+    /// Sign a 32-byte EVM hash (recoverable ECDSA)
+    pub fn sign_prehash(&self, message_hash: &[u8; 32]) -> Result<EVMSigningResult, SignerError> {
+        sign_evm_with_secure_key(&self.0, message_hash)
+    }
+
+    /// [`sign_prehash`](Self::sign_prehash) with a choice of nonce derivation
+    pub fn sign_prehash_with_nonce(
+        &self,
+        message_hash: &[u8; 32],
+        nonce: NonceMode,
+    ) -> Result<EVMSigningResult, SignerError> {
+        sign_evm_with_nonce_mode(&self.0, message_hash, nonce)
+    }
+
+    /// Sign a message with BIP-340 Schnorr
+    pub fn sign_schnorr(&self, message: &[u8]) -> Result<SchnorrSigningResult, SignerError> {
+        sign_schnorr_with_secure_key(&self.0, message)
+    }
+}
+
+/// The output of a container's KDF: decrypts that container, nothing more
+pub struct SecureKdfKey(SecureBuffer);
+
+impl SecureKdfKey {
+    pub(crate) fn from_buffer(buffer: SecureBuffer) -> Result<Self, SignerError> {
+        checked(buffer).map(Self)
+    }
+
+    pub(crate) fn as_slice(&self) -> &[u8] {
+        self.0.as_slice()
+    }
+
+    #[cfg(target_os = "linux")]
+    pub(crate) fn as_buffer(&self) -> &SecureBuffer {
+        &self.0
+    }
+
+    /// Zeroize now rather than on drop
+    pub fn zeroize(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+macro_rules! redacted_debug {
+    ($($name:ident),*) => {$(
+        impl core::fmt::Debug for $name {
+            fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                f.write_str(concat!(stringify!($name), "([REDACTED])"))
+            }
+        }
+    )*};
+}
+
+redacted_debug!(SecureEd25519Seed, SecureSecp256k1Scalar, SecureKdfKey);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::Cipher;
+    use crate::kdf::KdfParams;
+
+    #[test]
+    fn test_typed_keys_match_untyped_signing() {
+        std::env::set_var("SIGNER_ALLOW_INSECURE_MEMORY", "1");
+        let container =
+            EncryptedKeyContainer::encrypt_with_kdf(&[7u8; 32], "pw", Cipher::Aes256Gcm, KdfParams::MINIMUM).unwrap();
+        let json = container.to_json().unwrap();
+
+        let seed = SecureEd25519Seed::from_container(&container, "pw").unwrap();
+        let message = crate::crypto::test_transaction(&[7u8; 32]);
+        let expected = crate::decrypt_and_sign(&json, "pw", &message).unwrap();
+        assert_eq!(seed.sign(&message).unwrap().signature, expected.signature);
+        assert_eq!(bs58::encode(seed.public_key()).into_string(), expected.public_key);
+
+        let scalar = SecureSecp256k1Scalar::from_container(&container, "pw").unwrap();
+        let expected = crate::decrypt_and_sign_evm(&json, "pw", &[1u8; 32]).unwrap();
+        assert_eq!(scalar.sign_prehash(&[1u8; 32]).unwrap().signature, expected.signature);
+        assert_eq!(scalar.evm_address(), expected.address);
+
+        // Zero and the curve order are not scalars
+        assert!(SecureSecp256k1Scalar::from_bytes(&[0u8; 32]).is_err());
+        let order = hex::decode("fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141").unwrap();
+        assert!(SecureSecp256k1Scalar::from_bytes(order.as_slice().try_into().unwrap()).is_err());
+        assert!(SecureEd25519Seed::from_buffer(SecureBuffer::from_slice_permissive(&[1u8; 31]).unwrap()).is_err());
+        assert_eq!(format!("{:?}", seed), "SecureEd25519Seed([REDACTED])");
+    }
+}
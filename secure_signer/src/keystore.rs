@@ -0,0 +1,362 @@
+//! Web3 Secret Storage (keystore v3) import and export
+//!
+//! Converts Geth/MetaMask keystore files to and from the native
+//! [`EncryptedKeyContainer`]. The secp256k1 key is only ever decrypted into
+//! a [`SecureBuffer`]: AES-128-CTR is applied in place inside locked memory.
+//!
+//! # Format
+//!
+//! - KDF: scrypt or PBKDF2-HMAC-SHA256 with `dklen = 32`
+//! - Cipher: AES-128-CTR keyed with the first 16 derived bytes
+//! - MAC: `keccak256(derived[16..32] || ciphertext)`
+//!
+//! Exports always use scrypt.
+
+use aes::cipher::{KeyIvInit, StreamCipher};
+use k256::ecdsa::SigningKey as K256SigningKey;
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Keccak256};
+
+use crate::crypto::{evm_address_from_pubkey, get_locking_mode, Cipher, EncryptedKeyContainer};
+use crate::entropy::fill_random;
+use crate::error::SignerError;
+use crate::kdf::{Kdf, KdfParams, Pbkdf2Params, ScryptParams, KEY_SIZE};
+use crate::secure_buffer::SecureBuffer;
+
+type Aes128Ctr = ctr::Ctr128BE<aes::Aes128>;
+
+/// Keystore format version
+const KEYSTORE_VERSION: u32 = 3;
+
+/// AES-128-CTR IV size
+const IV_SIZE: usize = 16;
+
+/// Keystore salt size
+const SALT_SIZE: usize = 32;
+
+/// scrypt parameters used by Geth for new keystores (N = 2^18, r = 8, p = 1)
+pub const KEYSTORE_SCRYPT_PARAMS: ScryptParams = ScryptParams { log_n: 18, r: 8, p: 1 };
+
+#[derive(Serialize, Deserialize)]
+struct KeystoreV3 {
+    version: u32,
+    #[serde(default)]
+    id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    address: Option<String>,
+    // Old Geth versions wrote "Crypto"
+    #[serde(alias = "Crypto")]
+    crypto: KeystoreCrypto,
+}
+
+#[derive(Serialize, Deserialize)]
+struct KeystoreCrypto {
+    cipher: String,
+    ciphertext: String,
+    cipherparams: CipherParams,
+    kdf: String,
+    kdfparams: serde_json::Value,
+    mac: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CipherParams {
+    iv: String,
+}
+
+#[derive(Deserialize)]
+struct ScryptKdfParams {
+    dklen: usize,
+    n: u64,
+    r: u32,
+    p: u32,
+    salt: String,
+}
+
+#[derive(Deserialize)]
+struct Pbkdf2KdfParams {
+    dklen: usize,
+    c: u32,
+    prf: String,
+    salt: String,
+}
+
+impl EncryptedKeyContainer {
+    /// Import a keystore v3 file, re-encrypting the key with `password`
+    /// under the native format (Argon2id, AES-256-GCM)
+    ///
+    /// The MAC is checked before decryption, and the `address` field, when
+    /// present, must match the decrypted key.
+    pub fn from_keystore_v3(json: &str, password: &str) -> Result<Self, SignerError> {
+        let mut key = decrypt_keystore(json, password)?;
+        let container = Self::encrypt_with_kdf(
+            key.as_slice(),
+            password,
+            Cipher::default(),
+            KdfParams::default(),
+        );
+        key.zeroize();
+        container
+    }
+
+    /// Export the key as a keystore v3 file protected by `passphrase`,
+    /// using Geth's default scrypt parameters
+    pub fn to_keystore_v3(&self, passphrase: &str) -> Result<String, SignerError> {
+        self.to_keystore_v3_with_scrypt(passphrase, KEYSTORE_SCRYPT_PARAMS)
+    }
+
+    /// Export the key as a keystore v3 file with explicit scrypt parameters
+    pub fn to_keystore_v3_with_scrypt(
+        &self,
+        passphrase: &str,
+        params: ScryptParams,
+    ) -> Result<String, SignerError> {
+        let mut key = self.decrypt_key(passphrase)?;
+        let result = encrypt_keystore(&mut key, passphrase, params);
+        key.zeroize();
+        result
+    }
+}
+
+/// Decrypt a keystore v3 file into a secure buffer
+fn decrypt_keystore(json: &str, password: &str) -> Result<SecureBuffer, SignerError> {
+    let keystore: KeystoreV3 =
+        serde_json::from_str(json).map_err(|e| SignerError::ContainerError(e.to_string()))?;
+    if keystore.version != KEYSTORE_VERSION {
+        return Err(SignerError::ContainerError(format!(
+            "unsupported keystore version {}",
+            keystore.version
+        )));
+    }
+
+    let crypto = &keystore.crypto;
+    if crypto.cipher != "aes-128-ctr" {
+        return Err(SignerError::ContainerError(format!(
+            "unsupported keystore cipher {}",
+            crypto.cipher
+        )));
+    }
+
+    let (kdf, salt) = parse_kdf(&crypto.kdf, &crypto.kdfparams)?;
+    let iv = decode_hex(&crypto.cipherparams.iv)?;
+    let ciphertext = decode_hex(&crypto.ciphertext)?;
+    let mac = decode_hex(&crypto.mac)?;
+    if iv.len() != IV_SIZE || ciphertext.len() != KEY_SIZE {
+        return Err(SignerError::ContainerError(
+            "keystore iv or ciphertext has the wrong length".to_string(),
+        ));
+    }
+
+    let mut derived = kdf.derive(password.as_bytes(), &salt)?;
+    if !constant_time_eq(&keystore_mac(&derived, &ciphertext), &mac) {
+        derived.zeroize();
+        return Err(SignerError::DecryptionFailed);
+    }
+
+    let mut key = SecureBuffer::from_slice_with_mode(&ciphertext, get_locking_mode())?;
+    apply_ctr(&derived, &iv, &mut key)?;
+    derived.zeroize();
+
+    if let Some(address) = &keystore.address {
+        let signing_key = K256SigningKey::from_slice(key.as_slice())
+            .map_err(|e| SignerError::ContainerError(format!("invalid secp256k1 key: {}", e)))?;
+        let derived_address = evm_address_from_pubkey(signing_key.verifying_key());
+        if !derived_address[2..].eq_ignore_ascii_case(address.trim_start_matches("0x")) {
+            key.zeroize();
+            return Err(SignerError::ContainerError(
+                "keystore address does not match the decrypted key".to_string(),
+            ));
+        }
+    }
+
+    Ok(key)
+}
+
+/// Encrypt a key (32-byte secp256k1 scalar) into a keystore v3 file
+fn encrypt_keystore(
+    key: &mut SecureBuffer,
+    passphrase: &str,
+    params: ScryptParams,
+) -> Result<String, SignerError> {
+    let signing_key = K256SigningKey::from_slice(key.as_slice())
+        .map_err(|e| SignerError::ContainerError(format!("invalid secp256k1 key: {}", e)))?;
+    let address = evm_address_from_pubkey(signing_key.verifying_key());
+
+    let mut salt = [0u8; SALT_SIZE];
+    let mut iv = [0u8; IV_SIZE];
+    let mut id = [0u8; 16];
+    fill_random(&mut salt)?;
+    fill_random(&mut iv)?;
+    fill_random(&mut id)?;
+
+    let mut derived = Kdf::Scrypt(params).derive(passphrase.as_bytes(), &salt)?;
+
+    // Encrypt a locked copy in place so the caller's buffer stays intact
+    let mut encrypted = SecureBuffer::from_slice_with_mode(key.as_slice(), get_locking_mode())?;
+    apply_ctr(&derived, &iv, &mut encrypted)?;
+    let ciphertext = encrypted.as_slice().to_vec();
+    let mac = keystore_mac(&derived, &ciphertext);
+    derived.zeroize();
+
+    let keystore = KeystoreV3 {
+        version: KEYSTORE_VERSION,
+        id: Some(uuid_v4(id)),
+        address: Some(address[2..].to_string()),
+        crypto: KeystoreCrypto {
+            cipher: "aes-128-ctr".to_string(),
+            ciphertext: hex::encode(ciphertext),
+            cipherparams: CipherParams { iv: hex::encode(iv) },
+            kdf: "scrypt".to_string(),
+            kdfparams: serde_json::json!({
+                "dklen": KEY_SIZE,
+                "n": 1u64 << params.log_n,
+                "r": params.r,
+                "p": params.p,
+                "salt": hex::encode(salt),
+            }),
+            mac: hex::encode(mac),
+        },
+    };
+
+    serde_json::to_string(&keystore).map_err(|e| SignerError::SerializationError(e.to_string()))
+}
+
+/// Map keystore KDF parameters onto [`Kdf`], returning the salt
+fn parse_kdf(name: &str, params: &serde_json::Value) -> Result<(Kdf, Vec<u8>), SignerError> {
+    let invalid = |e: serde_json::Error| SignerError::ContainerError(format!("invalid kdfparams: {}", e));
+
+    let (kdf, dklen, salt) = match name {
+        "scrypt" => {
+            let p: ScryptKdfParams = serde_json::from_value(params.clone()).map_err(invalid)?;
+            if !p.n.is_power_of_two() || p.n < 2 {
+                return Err(SignerError::ContainerError(
+                    "scrypt n must be a power of two".to_string(),
+                ));
+            }
+            let log_n = p.n.trailing_zeros() as u8;
+            (Kdf::Scrypt(ScryptParams { log_n, r: p.r, p: p.p }), p.dklen, p.salt)
+        }
+        "pbkdf2" => {
+            let p: Pbkdf2KdfParams = serde_json::from_value(params.clone()).map_err(invalid)?;
+            if p.prf != "hmac-sha256" {
+                return Err(SignerError::ContainerError(format!(
+                    "unsupported PBKDF2 prf {}",
+                    p.prf
+                )));
+            }
+            (Kdf::Pbkdf2Sha256(Pbkdf2Params { iterations: p.c }), p.dklen, p.salt)
+        }
+        other => {
+            return Err(SignerError::ContainerError(format!(
+                "unsupported keystore kdf {}",
+                other
+            )));
+        }
+    };
+
+    if dklen != KEY_SIZE {
+        return Err(SignerError::ContainerError(format!(
+            "unsupported keystore dklen {}",
+            dklen
+        )));
+    }
+
+    Ok((kdf, decode_hex(&salt)?))
+}
+
+/// `keccak256(derived[16..32] || ciphertext)`
+fn keystore_mac(derived: &SecureBuffer, ciphertext: &[u8]) -> [u8; 32] {
+    let mut hasher = Keccak256::new();
+    hasher.update(&derived.as_slice()[16..KEY_SIZE]);
+    hasher.update(ciphertext);
+    hasher.finalize().into()
+}
+
+/// Apply the AES-128-CTR keystream in place
+fn apply_ctr(derived: &SecureBuffer, iv: &[u8], data: &mut SecureBuffer) -> Result<(), SignerError> {
+    let mut cipher = Aes128Ctr::new_from_slices(&derived.as_slice()[..16], iv)
+        .map_err(|e| SignerError::KeyDerivationFailed(e.to_string()))?;
+    cipher.apply_keystream(data.as_mut_slice());
+    Ok(())
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn decode_hex(value: &str) -> Result<Vec<u8>, SignerError> {
+    hex::decode(value.trim_start_matches("0x"))
+        .map_err(|e| SignerError::ContainerError(format!("invalid hex: {}", e)))
+}
+
+/// Format 16 random bytes as an RFC 4122 version 4 UUID
+fn uuid_v4(mut bytes: [u8; 16]) -> String {
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let h = hex::encode(bytes);
+    format!("{}-{}-{}-{}-{}", &h[..8], &h[8..12], &h[12..16], &h[16..20], &h[20..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_PARAMS: ScryptParams = ScryptParams { log_n: 10, r: 8, p: 1 };
+
+    /// Test vector from the Web3 Secret Storage definition (PBKDF2 variant)
+    const PBKDF2_VECTOR: &str = r#"{
+        "crypto": {
+            "cipher": "aes-128-ctr",
+            "cipherparams": {"iv": "6087dab2f9fdbbfaddc31a909735c1e6"},
+            "ciphertext": "5318b4d5bcd28de64ee5559e671353e16f075ecae9f99c7a79a38af5f869aa46",
+            "kdf": "pbkdf2",
+            "kdfparams": {
+                "c": 262144,
+                "dklen": 32,
+                "prf": "hmac-sha256",
+                "salt": "ae3cd4e7013836a3df6bd7241b12db061dbe2c6785853cce422d148a624ce0bd"
+            },
+            "mac": "517ead924a9d0dc3124507e3393d175ce3ff7c1e96529c6c555ce9e51205e9b2"
+        },
+        "id": "3198bc9c-6672-5ab3-d995-4942343ae5b6",
+        "version": 3
+    }"#;
+
+    #[test]
+    fn test_decrypts_reference_vector() {
+        let key = decrypt_keystore(PBKDF2_VECTOR, "testpassword").unwrap();
+        assert_eq!(
+            hex::encode(key.as_slice()),
+            "7a28b5ba57c53603b0b07b56bba752f7784bf506fa95edc395f5cf6c7514fe9d"
+        );
+        assert!(matches!(
+            decrypt_keystore(PBKDF2_VECTOR, "wrong"),
+            Err(SignerError::DecryptionFailed)
+        ));
+    }
+
+    #[test]
+    fn test_export_import_roundtrip() {
+        let secret = [0x42u8; 32];
+        let container = EncryptedKeyContainer::encrypt(&secret, "pass").unwrap();
+
+        let keystore = container.to_keystore_v3_with_scrypt("pass", TEST_PARAMS).unwrap();
+        let parsed: serde_json::Value = serde_json::from_str(&keystore).unwrap();
+        assert_eq!(parsed["version"], 3);
+        assert_eq!(parsed["crypto"]["kdfparams"]["n"], 1024);
+
+        let imported = EncryptedKeyContainer::from_keystore_v3(&keystore, "pass").unwrap();
+        assert_eq!(imported.decrypt_key("pass").unwrap().as_slice(), &secret);
+    }
+
+    #[test]
+    fn test_address_mismatch_rejected() {
+        let container = EncryptedKeyContainer::encrypt(&[0x42u8; 32], "pass").unwrap();
+        let keystore = container.to_keystore_v3_with_scrypt("pass", TEST_PARAMS).unwrap();
+
+        let mut parsed: serde_json::Value = serde_json::from_str(&keystore).unwrap();
+        parsed["address"] = serde_json::json!("0000000000000000000000000000000000000000");
+        let result = decrypt_keystore(&parsed.to_string(), "pass");
+        assert!(matches!(result, Err(SignerError::ContainerError(_))));
+    }
+}
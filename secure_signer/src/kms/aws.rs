@@ -0,0 +1,277 @@
+//! AWS KMS client
+//!
+//! Calls the KMS `Encrypt` / `Decrypt` JSON API directly, signing requests
+//! with Signature Version 4. The public key is passed as the encryption
+//! context under `coldstar:public_key`, which CloudTrail records with each
+//! `Decrypt` call.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use hmac::{Hmac, Mac};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use zeroize::{Zeroize, Zeroizing};
+
+use super::{read_response, take_blob, take_secret, KmsClient};
+use crate::error::SignerError;
+use crate::secure_buffer::SecureBuffer;
+
+/// Default request timeout
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Encryption context key holding the public key
+const CONTEXT_KEY: &str = "coldstar:public_key";
+
+const SERVICE: &str = "kms";
+
+/// Static AWS credentials
+pub struct AwsCredentials {
+    /// Access key ID
+    pub access_key_id: String,
+    secret_access_key: Zeroizing<String>,
+    /// Session token for temporary credentials
+    pub session_token: Option<String>,
+}
+
+impl AwsCredentials {
+    /// Create credentials from an access key pair
+    pub fn new(access_key_id: &str, secret_access_key: &str, session_token: Option<&str>) -> Self {
+        Self {
+            access_key_id: access_key_id.to_string(),
+            secret_access_key: Zeroizing::new(secret_access_key.to_string()),
+            session_token: session_token.map(str::to_string),
+        }
+    }
+
+    /// Read `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY` and (optionally)
+    /// `AWS_SESSION_TOKEN`
+    pub fn from_env() -> Result<Self, SignerError> {
+        let var = |name: &str| {
+            std::env::var(name).map_err(|_| SignerError::KmsError(format!("{} is not set", name)))
+        };
+        let mut secret = var("AWS_SECRET_ACCESS_KEY")?;
+        let credentials = Self::new(
+            &var("AWS_ACCESS_KEY_ID")?,
+            &secret,
+            std::env::var("AWS_SESSION_TOKEN").ok().as_deref(),
+        );
+        secret.zeroize();
+        Ok(credentials)
+    }
+}
+
+/// A symmetric AWS KMS key
+pub struct AwsKms {
+    agent: ureq::Agent,
+    endpoint: String,
+    host: String,
+    region: String,
+    key_id: String,
+    credentials: AwsCredentials,
+}
+
+impl AwsKms {
+    /// Create a client for `key_id` (key ID, ARN, or alias) in `region`
+    pub fn new(region: &str, key_id: &str, credentials: AwsCredentials) -> Self {
+        let host = format!("kms.{}.amazonaws.com", region);
+        Self {
+            agent: ureq::AgentBuilder::new().timeout(DEFAULT_TIMEOUT).build(),
+            endpoint: format!("https://{}/", host),
+            host,
+            region: region.to_string(),
+            key_id: key_id.to_string(),
+            credentials,
+        }
+    }
+
+    /// Use a different endpoint (VPC endpoint, FIPS endpoint)
+    pub fn with_endpoint(mut self, host: &str) -> Self {
+        self.host = host.to_string();
+        self.endpoint = format!("https://{}/", host);
+        self
+    }
+
+    fn call(&self, target: &str, body: serde_json::Value) -> Result<serde_json::Value, SignerError> {
+        let payload = body.to_string();
+        let amz_date = amz_timestamp(
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+        );
+        let target = format!("TrentService.{}", target);
+
+        let mut headers = vec![
+            ("content-type", "application/x-amz-json-1.1"),
+            ("host", self.host.as_str()),
+            ("x-amz-date", amz_date.as_str()),
+            ("x-amz-target", target.as_str()),
+        ];
+        if let Some(token) = &self.credentials.session_token {
+            headers.push(("x-amz-security-token", token.as_str()));
+        }
+        let authorization = sign_v4(
+            &self.credentials,
+            &self.region,
+            SERVICE,
+            "POST",
+            &headers,
+            payload.as_bytes(),
+        );
+
+        let mut request = self.agent.post(&self.endpoint);
+        for (name, value) in headers.iter().filter(|(name, _)| *name != "host") {
+            request = request.set(name, value);
+        }
+        read_response(request.set("authorization", &authorization).send_string(&payload))
+    }
+}
+
+impl KmsClient for AwsKms {
+    fn provider(&self) -> &'static str {
+        "aws"
+    }
+
+    fn key_id(&self) -> &str {
+        &self.key_id
+    }
+
+    fn encrypt(&self, plaintext: &[u8], context: &str) -> Result<Vec<u8>, SignerError> {
+        let body = json!({
+            "KeyId": self.key_id,
+            "Plaintext": BASE64.encode(plaintext),
+            "EncryptionContext": { CONTEXT_KEY: context },
+        });
+
+        take_blob(&self.call("Encrypt", body)?, "CiphertextBlob")
+    }
+
+    fn decrypt(&self, ciphertext: &[u8], context: &str) -> Result<SecureBuffer, SignerError> {
+        let body = json!({
+            "KeyId": self.key_id,
+            "CiphertextBlob": BASE64.encode(ciphertext),
+            "EncryptionContext": { CONTEXT_KEY: context },
+        });
+        take_secret(self.call("Decrypt", body)?, "Plaintext")
+    }
+}
+
+/// Build a SigV4 `Authorization` header for a request to `/` with no query
+///
+/// `headers` must use lowercase names and include `host` and `x-amz-date`.
+fn sign_v4(
+    credentials: &AwsCredentials,
+    region: &str,
+    service: &str,
+    method: &str,
+    headers: &[(&str, &str)],
+    payload: &[u8],
+) -> String {
+    let mut headers = headers.to_vec();
+    headers.sort_by_key(|(name, _)| *name);
+    let amz_date = headers
+        .iter()
+        .find(|(name, _)| *name == "x-amz-date")
+        .map(|(_, value)| *value)
+        .unwrap_or_default();
+    let date = &amz_date[..amz_date.len().min(8)];
+
+    let canonical_headers: String = headers
+        .iter()
+        .map(|(name, value)| format!("{}:{}\n", name, value.trim()))
+        .collect();
+    let signed_headers = headers.iter().map(|(name, _)| *name).collect::<Vec<_>>().join(";");
+    let canonical_request = format!(
+        "{}\n/\n\n{}\n{}\n{}",
+        method,
+        canonical_headers,
+        signed_headers,
+        hex::encode(Sha256::digest(payload))
+    );
+
+    let scope = format!("{}/{}/{}/aws4_request", date, region, service);
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        amz_date,
+        scope,
+        hex::encode(Sha256::digest(canonical_request.as_bytes()))
+    );
+
+    let secret = Zeroizing::new(format!("AWS4{}", credentials.secret_access_key.as_str()));
+    let mut key = hmac_sha256(secret.as_bytes(), date.as_bytes());
+    for part in [region, service, "aws4_request"] {
+        key = hmac_sha256(&key, part.as_bytes());
+    }
+    let signature = hex::encode(hmac_sha256(&key, string_to_sign.as_bytes()));
+
+    format!(
+        "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+        credentials.access_key_id, scope, signed_headers, signature
+    )
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Zeroizing<Vec<u8>> {
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(key).expect("HMAC accepts any key length");
+    mac.update(data);
+    Zeroizing::new(mac.finalize().into_bytes().to_vec())
+}
+
+/// Format a Unix timestamp as `YYYYMMDD'T'HHMMSS'Z'`
+fn amz_timestamp(unix_secs: u64) -> String {
+    let days = (unix_secs / 86_400) as i64;
+    let secs = unix_secs % 86_400;
+
+    // Civil-from-days (proleptic Gregorian), H. Hinnant
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+        year,
+        month,
+        day,
+        secs / 3_600,
+        secs % 3_600 / 60,
+        secs % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_amz_timestamp() {
+        assert_eq!(amz_timestamp(0), "19700101T000000Z");
+        assert_eq!(amz_timestamp(1_440_938_160), "20150830T123600Z");
+        assert_eq!(amz_timestamp(1_709_210_096), "20240229T123456Z");
+    }
+
+    #[test]
+    fn test_sign_v4_matches_aws_test_suite() {
+        // "get-vanilla" from the AWS SigV4 test suite
+        let credentials = AwsCredentials::new("AKIDEXAMPLE", "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY", None);
+        let authorization = sign_v4(
+            &credentials,
+            "us-east-1",
+            "service",
+            "GET",
+            &[("x-amz-date", "20150830T123600Z"), ("host", "example.amazonaws.com")],
+            b"",
+        );
+        assert_eq!(
+            authorization,
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/service/aws4_request, \
+             SignedHeaders=host;x-amz-date, \
+             Signature=5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31"
+        );
+    }
+}
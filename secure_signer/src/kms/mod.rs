@@ -0,0 +1,341 @@
+//! Cloud KMS envelope encryption
+//!
+//! For server-side deployments the passphrase can be replaced by a cloud
+//! KMS key. A [`KmsWrappedContainer`] encrypts the seed with a random
+//! data-encryption key (DEK), and only the DEK is sent to the KMS to be
+//! wrapped. Unwrapping goes through the KMS API, so access is governed by
+//! IAM policy and every unlock appears in the provider's audit trail
+//! (CloudTrail, Cloud Audit Logs); the seed itself never leaves the host
+//! and signing still happens locally in locked memory.
+//!
+//! The DEK is bound to the wallet's public key (AWS encryption context /
+//! GCP additional authenticated data), so a wrapped DEK cannot be replayed
+//! against another container, and the audit trail records which wallet
+//! was unlocked.
+//!
+//! Providers implement [`KmsClient`]:
+//! - [`aws::AwsKms`] (feature `kms-aws`)
+//! - [`gcp::GcpKms`] (feature `kms-gcp`)
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use ed25519_dalek::SigningKey;
+use serde::{Deserialize, Serialize};
+#[cfg(any(feature = "kms-aws", feature = "kms-gcp"))]
+use zeroize::Zeroize;
+
+use crate::backend::SignerBackend;
+use crate::crypto::{
+    get_locking_mode, sign_evm_with_nonce_mode, sign_message_with_secure_key, sign_with_secure_key, Cipher,
+    EVMSigningResult, EncryptedKeyContainer, NonceMode, SigningResult,
+};
+use crate::entropy::fill_random;
+use crate::error::SignerError;
+use crate::secure_buffer::SecureBuffer;
+
+#[cfg(feature = "kms-aws")]
+pub mod aws;
+#[cfg(feature = "kms-gcp")]
+pub mod gcp;
+
+/// Envelope format version
+const ENVELOPE_VERSION: u8 = 1;
+
+/// Size of the data-encryption key
+const DEK_SIZE: usize = 32;
+
+/// A cloud KMS key that can wrap and unwrap data-encryption keys
+pub trait KmsClient {
+    /// Provider name stored in the envelope (e.g. `"aws"`)
+    fn provider(&self) -> &'static str;
+
+    /// Key identifier stored in the envelope (ARN, alias, or resource name)
+    fn key_id(&self) -> &str;
+
+    /// Encrypt `plaintext`, binding it to `context`
+    fn encrypt(&self, plaintext: &[u8], context: &str) -> Result<Vec<u8>, SignerError>;
+
+    /// Decrypt a blob from [`KmsClient::encrypt`]; fails if `context` differs
+    fn decrypt(&self, ciphertext: &[u8], context: &str) -> Result<SecureBuffer, SignerError>;
+}
+
+/// A seed encrypted under a KMS-wrapped data-encryption key
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct KmsWrappedContainer {
+    /// Envelope format version
+    pub version: u8,
+    /// KMS provider (`"aws"`, `"gcp"`)
+    pub provider: String,
+    /// KMS key that wrapped the DEK
+    pub key_id: String,
+    /// DEK encrypted by the KMS (base64)
+    pub wrapped_key: String,
+    /// Cipher used with the DEK
+    pub cipher: Cipher,
+    /// Nonce for the cipher (base64)
+    pub nonce: String,
+    /// Encrypted seed with auth tag (base64)
+    pub ciphertext: String,
+    /// Public key (base58); also the KMS encryption context
+    pub public_key: String,
+}
+
+impl KmsWrappedContainer {
+    /// Encrypt a 32-byte seed (or 64-byte keypair) under a new DEK
+    pub fn encrypt(private_key: &[u8], kms: &impl KmsClient, cipher: Cipher) -> Result<Self, SignerError> {
+        if private_key.len() != 32 && private_key.len() != 64 {
+            return Err(SignerError::InvalidKeyFormat(private_key.len()));
+        }
+        let mut seed = SecureBuffer::from_slice_with_mode(&private_key[..32], get_locking_mode())?;
+        let result = Self::encrypt_seed(&seed, kms, cipher);
+        seed.zeroize();
+        result
+    }
+
+    /// Move a passphrase container under KMS protection
+    pub fn from_container(
+        container: &EncryptedKeyContainer,
+        passphrase: &str,
+        kms: &impl KmsClient,
+    ) -> Result<Self, SignerError> {
+        let mut seed = container.decrypt_key(passphrase)?;
+        let result = Self::encrypt_seed(&seed, kms, container.cipher);
+        seed.zeroize();
+        result
+    }
+
+    fn encrypt_seed(seed: &SecureBuffer, kms: &impl KmsClient, cipher: Cipher) -> Result<Self, SignerError> {
+        let signing_key = SigningKey::from_bytes(
+            seed.as_slice()
+                .try_into()
+                .map_err(|_| SignerError::InvalidKeyFormat(seed.len()))?,
+        );
+        let public_key = bs58::encode(signing_key.verifying_key().as_bytes()).into_string();
+
+        let mut dek = SecureBuffer::with_mode(DEK_SIZE, get_locking_mode())?;
+        fill_random(dek.as_mut_slice())?;
+        let mut nonce = vec![0u8; cipher.nonce_size()];
+        fill_random(&mut nonce)?;
+
+        let sealed = cipher
+            .encrypt(dek.as_slice(), &nonce, seed.as_slice())
+            .and_then(|ciphertext| Ok((ciphertext, kms.encrypt(dek.as_slice(), &public_key)?)));
+        dek.zeroize();
+        let (ciphertext, wrapped_key) = sealed?;
+
+        Ok(Self {
+            version: ENVELOPE_VERSION,
+            provider: kms.provider().to_string(),
+            key_id: kms.key_id().to_string(),
+            wrapped_key: BASE64.encode(wrapped_key),
+            cipher,
+            nonce: BASE64.encode(nonce),
+            ciphertext: BASE64.encode(ciphertext),
+            public_key,
+        })
+    }
+
+    /// Unwrap the DEK through the KMS and decrypt the seed
+    pub(crate) fn decrypt_key(&self, kms: &impl KmsClient) -> Result<SecureBuffer, SignerError> {
+        if self.version != ENVELOPE_VERSION {
+            return Err(SignerError::ContainerError(format!(
+                "unsupported KMS envelope version {}",
+                self.version
+            )));
+        }
+        if self.provider != kms.provider() || self.key_id != kms.key_id() {
+            return Err(SignerError::KmsError(format!(
+                "container is wrapped by {} key {}",
+                self.provider, self.key_id
+            )));
+        }
+
+        let nonce = BASE64.decode(&self.nonce)?;
+        let ciphertext = BASE64.decode(&self.ciphertext)?;
+        let mut dek = kms.decrypt(&BASE64.decode(&self.wrapped_key)?, &self.public_key)?;
+
+        let secure_key = self.cipher.decrypt_to_secure(dek.as_slice(), &nonce, &ciphertext);
+        dek.zeroize();
+        secure_key
+    }
+
+    /// Serialize to JSON
+    pub fn to_json(&self) -> Result<String, SignerError> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Parse from JSON
+    pub fn from_json(json: &str) -> Result<Self, SignerError> {
+        serde_json::from_str(json).map_err(|e| SignerError::ContainerError(e.to_string()))
+    }
+}
+
+/// Backend that unwraps a [`KmsWrappedContainer`] for each signature
+pub struct KmsBackend<'a, K: KmsClient> {
+    container: &'a KmsWrappedContainer,
+    kms: &'a K,
+}
+
+impl<'a, K: KmsClient> KmsBackend<'a, K> {
+    /// Create a backend for a container and the KMS key that wraps it
+    pub fn new(container: &'a KmsWrappedContainer, kms: &'a K) -> Self {
+        Self { container, kms }
+    }
+}
+
+impl<K: KmsClient> SignerBackend for KmsBackend<'_, K> {
+    fn sign_solana(&self, message: &[u8]) -> Result<SigningResult, SignerError> {
+        let mut secure_key = self.container.decrypt_key(self.kms)?;
+        let result = sign_with_secure_key(&secure_key, message);
+        secure_key.zeroize();
+
+        result
+    }
+
+    fn sign_solana_message(&self, message: &[u8]) -> Result<SigningResult, SignerError> {
+        let mut secure_key = self.container.decrypt_key(self.kms)?;
+        let result = sign_message_with_secure_key(&secure_key, message);
+        secure_key.zeroize();
+
+        result
+    }
+
+    fn sign_evm_hash(&self, message_hash: &[u8]) -> Result<EVMSigningResult, SignerError> {
+        self.sign_evm_hash_with_nonce(message_hash, NonceMode::Deterministic)
+    }
+
+    fn sign_evm_hash_with_nonce(&self, message_hash: &[u8], nonce: NonceMode) -> Result<EVMSigningResult, SignerError> {
+        if message_hash.len() != 32 {
+            return Err(SignerError::InvalidTransaction(format!(
+                "EVM message hash must be 32 bytes, got {}",
+                message_hash.len()
+            )));
+        }
+
+        let mut secure_key = self.container.decrypt_key(self.kms)?;
+        let result = sign_evm_with_nonce_mode(&secure_key, message_hash, nonce);
+        secure_key.zeroize();
+
+        result
+    }
+}
+
+/// Turn a KMS HTTP response into JSON, surfacing the provider's error body
+///
+/// The response text may hold an unwrapped key and is zeroized once parsed.
+#[cfg(any(feature = "kms-aws", feature = "kms-gcp"))]
+fn read_response(
+    response: Result<ureq::Response, ureq::Error>,
+) -> Result<serde_json::Value, SignerError> {
+    match response {
+        Ok(resp) => {
+            let mut text = resp.into_string()?;
+            let value = serde_json::from_str(&text);
+            text.zeroize();
+            Ok(value?)
+        }
+        Err(ureq::Error::Status(status, resp)) => Err(SignerError::KmsError(format!(
+            "HTTP {}: {}",
+            status,
+            resp.into_string().unwrap_or_default()
+        ))),
+        Err(e) => Err(SignerError::KmsError(e.to_string())),
+    }
+}
+
+/// Decode a base64 field from a KMS response into a secure buffer
+#[cfg(any(feature = "kms-aws", feature = "kms-gcp"))]
+fn take_secret(mut response: serde_json::Value, field: &str) -> Result<SecureBuffer, SignerError> {
+    let mut encoded = match response.get_mut(field).map(serde_json::Value::take) {
+        Some(serde_json::Value::String(s)) => s,
+        _ => return Err(SignerError::KmsError(format!("response has no {}", field))),
+    };
+    let decoded = BASE64.decode(&encoded);
+    encoded.zeroize();
+
+    let mut plaintext = decoded?;
+    let secret = SecureBuffer::from_slice_with_mode(&plaintext, get_locking_mode());
+    plaintext.zeroize();
+    secret
+}
+
+/// Decode a base64 field from a KMS response
+#[cfg(any(feature = "kms-aws", feature = "kms-gcp"))]
+fn take_blob(response: &serde_json::Value, field: &str) -> Result<Vec<u8>, SignerError> {
+    let encoded = response
+        .get(field)
+        .and_then(serde_json::Value::as_str)
+        .ok_or_else(|| SignerError::KmsError(format!("response has no {}", field)))?;
+    Ok(BASE64.decode(encoded)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kdf::KdfParams;
+
+    /// Stand-in KMS: AES-GCM under a fixed key, with the context as AAD
+    struct LocalKms {
+        key_id: String,
+    }
+
+    impl KmsClient for LocalKms {
+        fn provider(&self) -> &'static str {
+            "local"
+        }
+
+        fn key_id(&self) -> &str {
+            &self.key_id
+        }
+
+        fn encrypt(&self, plaintext: &[u8], context: &str) -> Result<Vec<u8>, SignerError> {
+            let mut blob = context.as_bytes().to_vec();
+            blob.push(0);
+            blob.extend(Cipher::Aes256Gcm.encrypt(&[1u8; 32], &[0u8; 12], plaintext)?);
+            Ok(blob)
+        }
+
+        fn decrypt(&self, ciphertext: &[u8], context: &str) -> Result<SecureBuffer, SignerError> {
+            let split = ciphertext.iter().position(|b| *b == 0).unwrap();
+            if &ciphertext[..split] != context.as_bytes() {
+                return Err(SignerError::KmsError("context mismatch".to_string()));
+            }
+            Cipher::Aes256Gcm.decrypt_to_secure(&[1u8; 32], &[0u8; 12], &ciphertext[split + 1..])
+        }
+    }
+
+    #[test]
+    fn test_envelope_roundtrip_and_signing() {
+        let kms = LocalKms {
+            key_id: "key-1".to_string(),
+        };
+        let container =
+            EncryptedKeyContainer::encrypt_with_kdf(&[4u8; 32], "pw", Cipher::Aes256Gcm, KdfParams::MINIMUM).unwrap();
+
+        let wrapped = KmsWrappedContainer::from_container(&container, "pw", &kms).unwrap();
+        let parsed = KmsWrappedContainer::from_json(&wrapped.to_json().unwrap()).unwrap();
+        assert_eq!(Some(&parsed.public_key), container.public_key.as_ref());
+
+        let backend = KmsBackend::new(&parsed, &kms);
+        let signed = backend.sign_solana(&crate::crypto::test_transaction(&[4u8; 32])).unwrap();
+        assert_eq!(signed.public_key, parsed.public_key);
+    }
+
+    #[test]
+    fn test_wrong_key_or_context_is_rejected() {
+        let kms = LocalKms {
+            key_id: "key-1".to_string(),
+        };
+        let a = KmsWrappedContainer::encrypt(&[5u8; 32], &kms, Cipher::XChaCha20Poly1305).unwrap();
+        let b = KmsWrappedContainer::encrypt(&[6u8; 32], &kms, Cipher::XChaCha20Poly1305).unwrap();
+
+        let other = LocalKms {
+            key_id: "key-2".to_string(),
+        };
+        assert!(matches!(a.decrypt_key(&other), Err(SignerError::KmsError(_))));
+
+        // A DEK wrapped for one wallet does not open another
+        let mut swapped = a.clone();
+        swapped.wrapped_key = b.wrapped_key.clone();
+        assert!(swapped.decrypt_key(&kms).is_err());
+    }
+}
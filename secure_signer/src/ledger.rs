@@ -0,0 +1,494 @@
+//! Ledger hardware wallet backend
+//!
+//! Implements [`SignerBackend`] against a Ledger device: Ed25519 through the
+//! Solana app and secp256k1 through the Ethereum app. Keys never leave the
+//! device; this module only formats APDUs and parses responses.
+//!
+//! # Transport
+//!
+//! APDUs go through a [`LedgerTransport`]. [`HidrawTransport`] talks to the
+//! device over Linux hidraw using Ledger's HID framing (64-byte reports,
+//! channel 0x0101, tag 0x05). Other platforms can plug in their own
+//! transport.
+//!
+//! # EVM
+//!
+//! The Ethereum app signs full transactions only, so
+//! [`SignerBackend::sign_evm_hash`] is rejected; use
+//! [`SignerBackend::sign_evm_transaction`].
+
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use k256::ecdsa::{RecoveryId, Signature as K256Signature, VerifyingKey as K256VerifyingKey};
+use sha3::{Digest, Keccak256};
+
+use crate::backend::SignerBackend;
+use crate::crypto::{
+    assemble_signed_transaction, check_solana_message, evm_address_from_pubkey, EVMSigningResult, SigningResult,
+};
+use crate::error::SignerError;
+use crate::hd::{DerivationPath, DerivationPreset};
+
+/// Ledger USB vendor id
+pub const LEDGER_VENDOR_ID: u16 = 0x2c97;
+
+/// APDU class byte used by the Solana and Ethereum apps
+const CLA: u8 = 0xe0;
+
+/// Largest APDU data field
+const MAX_CHUNK: usize = 255;
+
+// Solana app
+const SOL_INS_GET_PUBKEY: u8 = 0x05;
+const SOL_INS_SIGN_MESSAGE: u8 = 0x06;
+const SOL_P1_CONFIRM: u8 = 0x01;
+const SOL_P2_EXTEND: u8 = 0x01;
+const SOL_P2_MORE: u8 = 0x02;
+
+// Ethereum app
+const ETH_INS_GET_ADDRESS: u8 = 0x02;
+const ETH_INS_SIGN_TX: u8 = 0x04;
+const ETH_P1_MORE: u8 = 0x80;
+
+// HID framing
+const HID_PACKET_SIZE: usize = 64;
+const HID_CHANNEL: u16 = 0x0101;
+const HID_TAG_APDU: u8 = 0x05;
+
+/// Sends an APDU and returns the raw response, including the status word
+pub trait LedgerTransport {
+    /// Exchange one APDU with the device
+    fn exchange(&self, apdu: &[u8]) -> Result<Vec<u8>, SignerError>;
+}
+
+/// Ledger device backend
+pub struct LedgerBackend<T: LedgerTransport> {
+    transport: T,
+    solana_path: DerivationPath,
+    evm_path: DerivationPath,
+}
+
+impl<T: LedgerTransport> LedgerBackend<T> {
+    /// Create a backend using the first Ledger Live account on each chain
+    pub fn new(transport: T) -> Result<Self, SignerError> {
+        Ok(Self {
+            transport,
+            solana_path: DerivationPreset::SolanaLedgerLive.path(0)?,
+            evm_path: DerivationPreset::EthereumLedgerLive.path(0)?,
+        })
+    }
+
+    /// Use a different derivation path for Solana signing
+    pub fn with_solana_path(mut self, path: DerivationPath) -> Self {
+        self.solana_path = path;
+        self
+    }
+
+    /// Use a different derivation path for EVM signing
+    pub fn with_evm_path(mut self, path: DerivationPath) -> Self {
+        self.evm_path = path;
+        self
+    }
+
+    /// Ed25519 public key for the Solana path
+    pub fn solana_public_key(&self) -> Result<[u8; 32], SignerError> {
+        let response = self.send(SOL_INS_GET_PUBKEY, 0, 0, &serialize_path(&self.solana_path))?;
+        response
+            .as_slice()
+            .try_into()
+            .map_err(|_| SignerError::BackendError("unexpected public key length".to_string()))
+    }
+
+    /// secp256k1 public key (uncompressed) and address for the EVM path
+    pub fn evm_public_key(&self) -> Result<(K256VerifyingKey, String), SignerError> {
+        let response = self.send(ETH_INS_GET_ADDRESS, 0, 0, &serialize_path(&self.evm_path))?;
+
+        // pubkey_len || pubkey || address_len || address (ascii hex)
+        let key_len = *response.first().ok_or_else(malformed)? as usize;
+        let key_bytes = response.get(1..1 + key_len).ok_or_else(malformed)?;
+        let public_key = K256VerifyingKey::from_sec1_bytes(key_bytes)
+            .map_err(|e| SignerError::BackendError(format!("invalid public key: {}", e)))?;
+        let address = evm_address_from_pubkey(&public_key);
+        Ok((public_key, address))
+    }
+
+    /// Send one APDU and check the status word
+    fn send(&self, ins: u8, p1: u8, p2: u8, data: &[u8]) -> Result<Vec<u8>, SignerError> {
+        if data.len() > MAX_CHUNK {
+            return Err(SignerError::BackendError("APDU data too long".to_string()));
+        }
+
+        let mut apdu = vec![CLA, ins, p1, p2, data.len() as u8];
+        apdu.extend_from_slice(data);
+        let mut response = self.transport.exchange(&apdu)?;
+
+        if response.len() < 2 {
+            return Err(malformed());
+        }
+        let status = u16::from_be_bytes([response[response.len() - 2], response[response.len() - 1]]);
+        response.truncate(response.len() - 2);
+        check_status(status)?;
+        Ok(response)
+    }
+}
+
+impl<T: LedgerTransport> SignerBackend for LedgerBackend<T> {
+    fn sign_solana(&self, message: &[u8]) -> Result<SigningResult, SignerError> {
+        let public_key = self.solana_public_key()?;
+        check_solana_message(message, &public_key)?;
+
+        // Payload: signer count || path || message, split into chunks
+        let mut payload = vec![1u8];
+        payload.extend_from_slice(&serialize_path(&self.solana_path));
+        payload.extend_from_slice(message);
+
+        let chunks: Vec<&[u8]> = payload.chunks(MAX_CHUNK).collect();
+        let mut response = Vec::new();
+        for (i, chunk) in chunks.iter().enumerate() {
+            let mut p2 = 0;
+            if i > 0 {
+                p2 |= SOL_P2_EXTEND;
+            }
+            if i + 1 < chunks.len() {
+                p2 |= SOL_P2_MORE;
+            }
+            response = self.send(SOL_INS_SIGN_MESSAGE, SOL_P1_CONFIRM, p2, chunk)?;
+        }
+
+        let signature: [u8; 64] = response
+            .as_slice()
+            .try_into()
+            .map_err(|_| SignerError::BackendError("unexpected signature length".to_string()))?;
+
+        // Check the device signed what we sent with the key we expect
+        VerifyingKey::from_bytes(&public_key)
+            .and_then(|key| key.verify(message, &Signature::from_bytes(&signature)))
+            .map_err(|_| SignerError::BackendError("device returned an invalid signature".to_string()))?;
+
+        Ok(SigningResult {
+            signature: bs58::encode(signature).into_string(),
+            signed_transaction: assemble_signed_transaction(&signature, message),
+            public_key: bs58::encode(public_key).into_string(),
+            counter: None,
+        })
+    }
+
+    fn sign_evm_hash(&self, _message_hash: &[u8]) -> Result<EVMSigningResult, SignerError> {
+        Err(SignerError::BackendError(
+            "the Ledger Ethereum app signs full transactions only; use sign_evm_transaction".to_string(),
+        ))
+    }
+
+    fn sign_evm_transaction(&self, unsigned_tx: &[u8]) -> Result<EVMSigningResult, SignerError> {
+        let (public_key, address) = self.evm_public_key()?;
+
+        let mut payload = serialize_path(&self.evm_path);
+        payload.extend_from_slice(unsigned_tx);
+
+        let mut response = Vec::new();
+        for (i, chunk) in payload.chunks(MAX_CHUNK).enumerate() {
+            let p1 = if i == 0 { 0 } else { ETH_P1_MORE };
+            response = self.send(ETH_INS_SIGN_TX, p1, 0, chunk)?;
+        }
+
+        // Response: v || r || s. The device's v depends on the transaction
+        // type (and is truncated for large chain ids), so the recovery id is
+        // recomputed against the device's public key instead.
+        if response.len() != 65 {
+            return Err(SignerError::BackendError("unexpected signature length".to_string()));
+        }
+        let signature = K256Signature::from_slice(&response[1..])
+            .map_err(|e| SignerError::BackendError(format!("invalid signature: {}", e)))?;
+        let hash = Keccak256::digest(unsigned_tx);
+
+        let recovery_id = (0..2u8)
+            .filter_map(RecoveryId::from_byte)
+            .find(|id| {
+                K256VerifyingKey::recover_from_prehash(&hash, &signature, *id)
+                    .map(|key| key == public_key)
+                    .unwrap_or(false)
+            })
+            .ok_or_else(|| SignerError::BackendError("device returned an invalid signature".to_string()))?;
+
+        let v = recovery_id.to_byte() + 27;
+        let mut sig_bytes = signature.to_bytes().to_vec();
+        sig_bytes.push(v);
+
+        Ok(EVMSigningResult {
+            signature: format!("0x{}", hex::encode(&sig_bytes)),
+            address,
+            v,
+            counter: None,
+        })
+    }
+}
+
+/// Ledger path encoding: component count followed by big-endian indexes
+fn serialize_path(path: &DerivationPath) -> Vec<u8> {
+    let mut out = vec![path.indexes().len() as u8];
+    for index in path.indexes() {
+        out.extend_from_slice(&index.to_be_bytes());
+    }
+    out
+}
+
+fn check_status(status: u16) -> Result<(), SignerError> {
+    let message = match status {
+        0x9000 => return Ok(()),
+        0x6985 => "request rejected on the device",
+        0x5515 => "device is locked",
+        0x6d00 | 0x6e00 | 0x6e01 => "wrong or no app open on the device",
+        0x6a80 => "device rejected the data (blind signing may be disabled)",
+        _ => "device returned an error",
+    };
+    Err(SignerError::BackendError(format!("{} (status 0x{:04x})", message, status)))
+}
+
+fn malformed() -> SignerError {
+    SignerError::BackendError("malformed device response".to_string())
+}
+
+/// Split an APDU into HID reports
+fn frame_apdu(apdu: &[u8]) -> Vec<[u8; HID_PACKET_SIZE]> {
+    let mut data = (apdu.len() as u16).to_be_bytes().to_vec();
+    data.extend_from_slice(apdu);
+
+    data.chunks(HID_PACKET_SIZE - 5)
+        .enumerate()
+        .map(|(seq, chunk)| {
+            let mut packet = [0u8; HID_PACKET_SIZE];
+            packet[..2].copy_from_slice(&HID_CHANNEL.to_be_bytes());
+            packet[2] = HID_TAG_APDU;
+            packet[3..5].copy_from_slice(&(seq as u16).to_be_bytes());
+            packet[5..5 + chunk.len()].copy_from_slice(chunk);
+            packet
+        })
+        .collect()
+}
+
+/// Reassemble a response from HID reports supplied by `next_packet`
+fn read_framed_response(
+    mut next_packet: impl FnMut() -> Result<[u8; HID_PACKET_SIZE], SignerError>,
+) -> Result<Vec<u8>, SignerError> {
+    let mut data = Vec::new();
+    let mut expected = None;
+    let mut seq = 0u16;
+
+    loop {
+        let packet = next_packet()?;
+        if packet[..2] != HID_CHANNEL.to_be_bytes()
+            || packet[2] != HID_TAG_APDU
+            || packet[3..5] != seq.to_be_bytes()
+        {
+            return Err(malformed());
+        }
+        data.extend_from_slice(&packet[5..]);
+        seq = seq.wrapping_add(1);
+
+        if expected.is_none() && data.len() >= 2 {
+            expected = Some(u16::from_be_bytes([data[0], data[1]]) as usize);
+        }
+        if let Some(len) = expected {
+            if data.len() >= len + 2 {
+                data.truncate(len + 2);
+                data.drain(..2);
+                return Ok(data);
+            }
+        }
+    }
+}
+
+/// Ledger transport over Linux hidraw
+#[cfg(target_os = "linux")]
+pub struct HidrawTransport {
+    device: std::fs::File,
+}
+
+#[cfg(target_os = "linux")]
+impl HidrawTransport {
+    /// Open the first connected Ledger device
+    pub fn open_first() -> Result<Self, SignerError> {
+        let vendor = format!("{:08X}", LEDGER_VENDOR_ID);
+        let mut candidates = Vec::new();
+
+        for entry in std::fs::read_dir("/sys/class/hidraw")? {
+            let entry = entry?;
+            let uevent = match std::fs::read_to_string(entry.path().join("device/uevent")) {
+                Ok(u) => u,
+                Err(_) => continue,
+            };
+            let is_ledger = uevent
+                .lines()
+                .any(|l| l.starts_with("HID_ID=") && l.to_ascii_uppercase().contains(&vendor));
+            if is_ledger {
+                // Interface 0 carries APDUs; others are FIDO/keyboard
+                let primary = uevent.lines().any(|l| l.starts_with("HID_PHYS=") && l.ends_with("input0"));
+                candidates.push((!primary, entry.file_name()));
+            }
+        }
+
+        candidates.sort();
+        let (_, name) = candidates
+            .into_iter()
+            .next()
+            .ok_or_else(|| SignerError::BackendError("no Ledger device found".to_string()))?;
+        Self::open(std::path::Path::new("/dev").join(name))
+    }
+
+    /// Open a specific hidraw device node
+    pub fn open(path: impl AsRef<std::path::Path>) -> Result<Self, SignerError> {
+        let device = std::fs::OpenOptions::new().read(true).write(true).open(path)?;
+        Ok(Self { device })
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl LedgerTransport for HidrawTransport {
+    fn exchange(&self, apdu: &[u8]) -> Result<Vec<u8>, SignerError> {
+        use std::io::{Read, Write};
+
+        let mut device = &self.device;
+        for packet in frame_apdu(apdu) {
+            // Report id 0 precedes each report on write
+            let mut report = [0u8; HID_PACKET_SIZE + 1];
+            report[1..].copy_from_slice(&packet);
+            device.write_all(&report)?;
+        }
+
+        read_framed_response(|| {
+            let mut packet = [0u8; HID_PACKET_SIZE];
+            device.read_exact(&mut packet)?;
+            Ok(packet)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signer, SigningKey};
+    use k256::ecdsa::SigningKey as K256SigningKey;
+    use std::cell::RefCell;
+
+    /// Emulates the Solana and Ethereum apps with software keys
+    struct MockLedger {
+        ed25519: SigningKey,
+        secp256k1: K256SigningKey,
+        pending: RefCell<Vec<u8>>,
+        apdus: RefCell<Vec<Vec<u8>>>,
+    }
+
+    impl MockLedger {
+        fn new() -> Self {
+            Self {
+                ed25519: SigningKey::from_bytes(&[4u8; 32]),
+                secp256k1: K256SigningKey::from_slice(&[5u8; 32]).unwrap(),
+                pending: RefCell::new(Vec::new()),
+                apdus: RefCell::new(Vec::new()),
+            }
+        }
+    }
+
+    impl LedgerTransport for &MockLedger {
+        fn exchange(&self, apdu: &[u8]) -> Result<Vec<u8>, SignerError> {
+            self.apdus.borrow_mut().push(apdu.to_vec());
+            let (ins, p1, p2, data) = (apdu[1], apdu[2], apdu[3], &apdu[5..]);
+            let mut response = match ins {
+                SOL_INS_GET_PUBKEY => self.ed25519.verifying_key().to_bytes().to_vec(),
+                SOL_INS_SIGN_MESSAGE => {
+                    self.pending.borrow_mut().extend_from_slice(data);
+                    if p2 & SOL_P2_MORE != 0 {
+                        vec![]
+                    } else {
+                        let payload = self.pending.take();
+                        let path_len = 1 + 4 * payload[1] as usize;
+                        self.ed25519.sign(&payload[1 + path_len..]).to_bytes().to_vec()
+                    }
+                }
+                ETH_INS_GET_ADDRESS => {
+                    let point = self.secp256k1.verifying_key().to_encoded_point(false);
+                    let mut out = vec![65u8];
+                    out.extend_from_slice(point.as_bytes());
+                    out.push(40);
+                    out.extend_from_slice(&[b'0'; 40]);
+                    out
+                }
+                ETH_INS_SIGN_TX => {
+                    if p1 == 0 {
+                        self.pending.borrow_mut().clear();
+                    }
+                    self.pending.borrow_mut().extend_from_slice(data);
+                    if data.len() == MAX_CHUNK {
+                        vec![]
+                    } else {
+                        let payload = self.pending.take();
+                        let tx = &payload[1 + 4 * payload[0] as usize..];
+                        let (sig, id) = self
+                            .secp256k1
+                            .sign_prehash_recoverable(&Keccak256::digest(tx))
+                            .unwrap();
+                        // EIP-155 style v for chain id 8453, truncated to a byte
+                        let mut out = vec![(8453u32 * 2 + 35 + id.to_byte() as u32) as u8];
+                        out.extend_from_slice(&sig.to_bytes());
+                        out
+                    }
+                }
+                _ => return Ok(vec![0x6d, 0x00]),
+            };
+            response.extend_from_slice(&[0x90, 0x00]);
+            Ok(response)
+        }
+    }
+
+    #[test]
+    fn test_hid_framing_roundtrip() {
+        let apdu: Vec<u8> = (0..300u32).map(|i| i as u8).collect();
+        let packets = frame_apdu(&apdu);
+        assert_eq!(packets.len(), 6);
+        assert_eq!(&packets[0][..7], &[0x01, 0x01, 0x05, 0x00, 0x00, 0x01, 0x2c]);
+
+        let mut iter = packets.into_iter();
+        let decoded = read_framed_response(|| iter.next().ok_or_else(malformed)).unwrap();
+        assert_eq!(decoded, apdu);
+    }
+
+    #[test]
+    fn test_solana_signing_chunks_long_messages() {
+        let device = MockLedger::new();
+        let backend = LedgerBackend::new(&device).unwrap();
+
+        // One instruction carrying 500 bytes of data, signed by the device key
+        let mut message = vec![1, 0, 1, 2];
+        message.extend_from_slice(device.ed25519.verifying_key().as_bytes());
+        message.extend_from_slice(&[0x11; 32]);
+        message.extend_from_slice(&[7u8; 32]);
+        message.extend_from_slice(&[1, 1, 0, 0xf4, 0x03]);
+        message.extend_from_slice(&[7u8; 500]);
+        let result = backend.sign_solana(&message).unwrap();
+        assert_eq!(
+            result.public_key,
+            bs58::encode(device.ed25519.verifying_key().as_bytes()).into_string()
+        );
+
+        let apdus = device.apdus.borrow();
+        let p2: Vec<u8> = apdus.iter().filter(|a| a[1] == SOL_INS_SIGN_MESSAGE).map(|a| a[3]).collect();
+        assert_eq!(p2, vec![SOL_P2_MORE, SOL_P2_EXTEND | SOL_P2_MORE, SOL_P2_EXTEND]);
+        // m/44'/501'/0'
+        assert_eq!(&apdus[0][5..], &[3, 0x80, 0, 0, 44, 0x80, 0, 0x01, 0xf5, 0x80, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_evm_signing_recovers_v() {
+        let device = MockLedger::new();
+        let backend = LedgerBackend::new(&device).unwrap();
+        assert!(backend.sign_evm_hash(&[0u8; 32]).is_err());
+
+        let tx = vec![0xabu8; 400];
+        let result = backend.sign_evm_transaction(&tx).unwrap();
+        assert_eq!(result.address, evm_address_from_pubkey(device.secp256k1.verifying_key()));
+
+        // Same signature as signing the hash locally
+        let (sig, id) = device.secp256k1.sign_prehash_recoverable(&Keccak256::digest(&tx)).unwrap();
+        assert_eq!(result.v, id.to_byte() + 27);
+        assert!(result.signature.starts_with(&format!("0x{}", hex::encode(sig.to_bytes()))));
+    }
+}
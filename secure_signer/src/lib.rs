@@ -11,7 +11,7 @@
 //! # Security Model
 //!
 //! The private key is:
-//! 1. Received as an encrypted container (AES-256-GCM)
+//! 1. Received as an encrypted container (AES-256-GCM or XChaCha20-Poly1305)
 //! 2. Decrypted directly into a locked memory buffer
 //! 3. Used for signing within the secure context
 //! 4. Immediately zeroized after use (even on error/panic)
@@ -21,27 +21,237 @@
 //! - Gets logged or written to disk
 //! - Gets swapped to disk (memory is locked)
 //! - Survives beyond the signing function scope
+//!
+//! # Embedded Targets
+//!
+//! Without the default `std` feature the crate is `no_std + alloc` and
+//! contains only the signing core: containers, KDFs, secure buffers, and
+//! Ed25519 / secp256k1 signing. The host supplies what the standard library
+//! would: randomness through [`entropy::set_entropy_source`], page locking
+//! through [`secure_buffer::set_page_locker`], and the locking mode through
+//! [`crypto::set_locking_mode`] instead of environment variables. The
+//! crate's `cdylib` and `staticlib` outputs need `std`, so firmware builds
+//! these sources through the `no_std/` crate, an rlib-only package.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+// Helpers shared with the std-only modules go unused without them
+#![cfg_attr(not(feature = "std"), allow(dead_code))]
+
+extern crate alloc;
 
+#[cfg(feature = "std")]
+pub mod age;
+#[cfg(feature = "std")]
+pub mod app_secret;
+#[cfg(feature = "std")]
+pub mod approval;
+#[cfg(feature = "std")]
+pub mod aptos;
+#[cfg(feature = "std")]
+pub mod audit;
+#[cfg(feature = "std")]
+pub mod audit_export;
+pub mod backend;
+#[cfg(feature = "std")]
+pub mod batch;
+pub mod binary_container;
+#[cfg(feature = "std")]
+pub mod bitcoin;
+#[cfg(feature = "broadcast")]
+pub mod broadcast;
+#[cfg(feature = "std")]
+pub mod build_info;
+#[cfg(feature = "std")]
+pub mod bundle;
+#[cfg(feature = "std")]
+pub mod ceremony;
+#[cfg(feature = "std")]
+pub mod chain;
+#[cfg(feature = "std")]
+pub mod cosmos;
+#[cfg(feature = "std")]
+pub mod counter;
 pub mod crypto;
+#[cfg(all(feature = "daemon", unix))]
+pub mod daemon;
+#[cfg(all(feature = "std", windows))]
+pub mod dpapi;
+pub mod duress;
+#[cfg(feature = "std")]
+pub mod ecies;
+pub mod eddsa;
+pub mod encoding;
+pub mod entropy;
 pub mod error;
+#[cfg(feature = "std")]
+pub mod evm;
+#[cfg(feature = "std")]
+pub mod fees;
+#[cfg(feature = "std")]
+pub mod handoff;
+#[cfg(feature = "std")]
+pub mod hardening;
+#[cfg(feature = "std")]
+pub mod hd;
+#[cfg(feature = "std")]
+pub mod idempotency;
+#[cfg(feature = "std")]
+pub mod integrity;
+pub mod kdf;
+#[cfg(feature = "std")]
+pub mod kdf_cache;
+#[cfg(all(feature = "std", target_os = "linux"))]
+pub mod kernel_keyring;
+pub mod keyfile;
+#[cfg(feature = "std")]
+pub mod keyring;
+pub mod keys;
+#[cfg(feature = "std")]
+pub mod keystore;
+#[cfg(feature = "std")]
+pub mod kms;
+#[cfg(feature = "ledger")]
+pub mod ledger;
+pub mod metrics;
+#[cfg(feature = "std")]
+pub mod migrate;
+#[cfg(feature = "std")]
+pub mod musig;
+#[cfg(feature = "tokio")]
+pub mod nonblocking;
+#[cfg(feature = "pkcs11")]
+pub mod pkcs11;
+#[cfg(feature = "std")]
+pub mod policy;
+#[cfg(feature = "std")]
+pub mod pool;
+#[cfg(feature = "pq")]
+pub mod pq;
+#[cfg(feature = "std")]
+pub mod pubkey_cache;
+#[cfg(feature = "python")]
+pub mod python;
+pub mod reader;
+#[cfg(feature = "remote-signer")]
+pub mod remote_signer;
+#[cfg(feature = "std")]
+pub mod rotation;
 pub mod secure_buffer;
+#[cfg(feature = "std")]
+pub mod secure_config;
+#[cfg(all(feature = "secure-enclave", target_os = "macos"))]
+pub mod secure_enclave;
+pub mod secure_vec;
+pub mod selftest;
+#[cfg(feature = "std")]
+pub mod serve;
+#[cfg(feature = "std")]
+pub mod session;
+#[cfg(feature = "std")]
+pub mod shamir;
+#[cfg(feature = "std")]
+pub mod signer;
+#[cfg(feature = "std")]
+pub mod solana;
+#[cfg(feature = "std")]
+pub mod stellar;
+#[cfg(feature = "std")]
+pub mod stream;
+#[cfg(feature = "substrate")]
+pub mod substrate;
+#[cfg(feature = "std")]
+pub mod sui;
+#[cfg(feature = "std")]
+pub mod suspend;
+mod sync;
+#[cfg(all(feature = "tee", target_os = "linux"))]
+pub mod tee;
+#[cfg(feature = "tracing")]
+pub mod telemetry;
+#[cfg(feature = "std")]
+pub mod tezos;
+#[cfg(feature = "std")]
+pub mod ton;
+#[cfg(all(feature = "std", any(unix, windows)))]
+pub mod tty;
+#[cfg(feature = "std")]
+pub mod tweak;
+#[cfg(feature = "std")]
+pub mod ur;
+pub mod validity;
+#[cfg(feature = "std")]
+pub mod vault;
+#[cfg(all(feature = "wasm-bindgen", target_arch = "wasm32"))]
+pub mod wasm;
+#[cfg(feature = "std")]
+pub mod watchdog;
+#[cfg(feature = "web3signer")]
+pub mod web3signer;
 
 #[cfg(feature = "ffi")]
 pub mod ffi;
 
 // Solana (Ed25519)
 pub use crypto::{
-    create_encrypted_key_container, decrypt_and_sign, sign_transaction, EncryptedKeyContainer,
-    SigningResult,
+    check_solana_message, create_encrypted_key_container, create_encrypted_key_container_with_cipher,
+    decrypt_and_sign, decrypt_and_sign_message, sign_transaction, Cipher, EncryptedKeyContainer, SigningResult,
+};
+
+// Ed25519ph / Ed25519ctx
+pub use eddsa::{
+    decrypt_and_sign_prehashed, decrypt_and_sign_with_context, sign_transaction_prehashed,
+    sign_transaction_with_context,
 };
 
 // EVM (secp256k1)
 pub use crypto::{
-    decrypt_and_sign_evm, sign_evm_transaction, EVMSigningResult,
+    decrypt_and_sign_evm, decrypt_and_sign_evm_with_nonce, sign_evm_transaction, EVMSigningResult, NonceMode,
 };
 
+// BIP-340 Schnorr (secp256k1)
+pub use crypto::{sign_schnorr, SchnorrSigningResult};
+
+#[cfg(feature = "std")]
+pub use app_secret::derive_app_secret;
+pub use backend::{ContainerBackend, SignerBackend};
+#[cfg(feature = "std")]
+pub use batch::{decrypt_and_sign_batch, decrypt_and_sign_evm_batch, TxRequest};
+#[cfg(feature = "std")]
+pub use build_info::{build_info, BuildInfo};
+pub use encoding::{Encoding, OutputEncoding};
 pub use error::SignerError;
-pub use secure_buffer::{LockingMode, SecureBuffer};
+#[cfg(feature = "std")]
+pub use fees::{Eip1559Fee, FeeEstimator, FixedFeeEstimator, SolanaFee};
+#[cfg(feature = "std")]
+pub use hardening::{harden_process, HardeningOptions, HardeningReport};
+#[cfg(feature = "std")]
+pub use hd::{DerivationPath, DerivationPreset};
+#[cfg(feature = "std")]
+pub use idempotency::IdempotencyStore;
+pub use kdf::{Kdf, KdfParams};
+#[cfg(feature = "std")]
+pub use kdf_cache::KdfCache;
+pub use keyfile::Keyfile;
+#[cfg(feature = "std")]
+pub use keyring::{ChainType, Keyring};
+pub use keys::{SecureEd25519Seed, SecureKdfKey, SecureSecp256k1Scalar};
+#[cfg(feature = "std")]
+pub use migrate::{ContainerVersion, MigrationOptions, MigrationReport};
+#[cfg(feature = "std")]
+pub use policy::{Policy, PolicyRule};
+#[cfg(feature = "std")]
+pub use pool::{PoolConfig, SignerPool};
+pub use secure_buffer::{locking_report, LockingMode, LockingReport, SecureBuffer};
+pub use secure_vec::SecureVec;
+#[cfg(feature = "std")]
+pub use session::{SessionConfig, SigningSession};
+#[cfg(feature = "std")]
+pub use signer::{Signer, SignerConfig};
+#[cfg(feature = "std")]
+pub use stream::{SecureStreamDecryptor, SecureStreamEncryptor};
+pub use validity::Validity;
+#[cfg(feature = "std")]
+pub use vault::{ImportOutcome, Vault};
 
 /// Library version
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
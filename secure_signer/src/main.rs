@@ -12,25 +12,58 @@
 //! # Sign a transaction
 //! solana-signer sign --container <json_file> --passphrase <pass> --transaction <base64>
 //!
+//! # Generate a key, prompting for its passphrase without echo
+//! solana-signer keygen --output wallet.json
+//!
 //! # One-shot mode (stdin/stdout)
 //! echo '{"action":"sign",...}' | solana-signer --stdin
+//!
+//! # Child-process mode with unlocked sessions (JSON lines)
+//! solana-signer serve
 //! ```
 //!
 //! # Security
 //!
 //! - Passphrases can be provided via environment variable SIGNER_PASSPHRASE
+//! - keygen, import, export-pubkey, sign-solana, sign-evm, rekey and migrate
+//!   otherwise prompt on the terminal with echo off
 //! - The --stdin mode is preferred for automation to avoid command-line leaks
 //! - Memory is locked and zeroized for all operations
+//! - Release builds with an integrity key refuse to run if the binary does
+//!   not match its signed manifest
 
 use clap::{Parser, Subcommand};
 use serde::{Deserialize, Serialize};
 use std::io::{self, BufRead, Write};
+use zeroize::Zeroizing;
 
+use coldstar_secure_signer::approval::{self, ApprovalRequest, TerminalApproval};
+use coldstar_secure_signer::bitcoin::decrypt_and_sign_psbt;
+use coldstar_secure_signer::bundle::SigningRequest;
+use coldstar_secure_signer::ceremony::{CeremonyTranscript, KeyCeremony};
+use coldstar_secure_signer::rotation::{rotate_key, RotationAttestation};
+use coldstar_secure_signer::encoding::{Encoding, OutputEncoding};
+use coldstar_secure_signer::entropy::fill_random;
+use coldstar_secure_signer::idempotency::{self, IdempotencyStore};
+use coldstar_secure_signer::integrity;
+use coldstar_secure_signer::pubkey_cache::PublicKeyCache;
+use coldstar_secure_signer::selftest::{selftest, OnFailure};
+use coldstar_secure_signer::serve::Server;
+use coldstar_secure_signer::solana::{self, SolanaTransaction};
+use coldstar_secure_signer::tty;
 use coldstar_secure_signer::{
-    create_encrypted_key_container, decrypt_and_sign, sign_transaction, EncryptedKeyContainer,
-    SignerError,
+    create_encrypted_key_container, decrypt_and_sign_batch, decrypt_and_sign_evm_batch, harden_process,
+    sign_transaction, Cipher, ContainerBackend, ContainerVersion, EncryptedKeyContainer, HardeningOptions,
+    HardeningReport, KdfParams, Keyfile, MigrationOptions, NonceMode, SecureBuffer, SignerBackend, SignerError,
+    TxRequest,
 };
 
+/// What `harden_process` achieved at startup
+static HARDENING: std::sync::OnceLock<HardeningReport> = std::sync::OnceLock::new();
+
+/// How long `sign --confirm` waits for an answer
+const CONFIRM_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(120);
+
 #[derive(Parser)]
 #[command(name = "solana-signer")]
 #[command(about = "Secure signing core for Solana transactions")]
@@ -46,6 +79,11 @@ struct Cli {
     /// Output format: json or text
     #[arg(long, default_value = "json")]
     format: String,
+
+    /// Persist idempotency keys for stdin mode, `serve`, and `daemon` to
+    /// this file (default: kept in memory for the lifetime of the process)
+    #[arg(long)]
+    idempotency_store: Option<String>,
 }
 
 #[derive(Subcommand)]
@@ -78,6 +116,15 @@ enum Commands {
         /// Base64-encoded unsigned transaction
         #[arg(long)]
         transaction: String,
+
+        /// Encoding for every output field: base58, base64, or hex
+        /// (default: base58 signature and public key, base64 transaction)
+        #[arg(long)]
+        encoding: Option<Encoding>,
+
+        /// Show a preview on stderr and sign only after it is approved
+        #[arg(long)]
+        confirm: bool,
     },
 
     /// Sign directly with a private key (less secure)
@@ -89,10 +136,384 @@ enum Commands {
         /// Base64-encoded message to sign
         #[arg(long)]
         message: String,
+
+        /// Encoding for every output field: base58, base64, or hex
+        #[arg(long)]
+        encoding: Option<Encoding>,
     },
 
     /// Check system capabilities
     Check,
+
+    /// Add a signature to a (possibly partially-signed) transaction file
+    PartialSign {
+        /// Path to a file holding the base64 transaction (or bare message)
+        #[arg(long)]
+        transaction: String,
+
+        /// Path to encrypted container JSON file
+        #[arg(long)]
+        container: String,
+
+        /// Passphrase for decryption
+        #[arg(long, env = "SIGNER_PASSPHRASE")]
+        passphrase: String,
+
+        /// Output file (default: update the transaction file in place)
+        #[arg(long, short)]
+        output: Option<String>,
+    },
+
+    /// List a transaction file's required signers and which are missing
+    Signers {
+        /// Path to a file holding the base64 transaction (or bare message)
+        #[arg(long)]
+        transaction: String,
+    },
+
+    /// Sign the Bitcoin PSBT inputs (P2WPKH, P2TR key path) the container controls
+    SignPsbt {
+        /// Path to a file holding the base64 PSBT
+        #[arg(long)]
+        psbt: String,
+
+        /// Path to encrypted container JSON file
+        #[arg(long)]
+        container: String,
+
+        /// Passphrase for decryption
+        #[arg(long, env = "SIGNER_PASSPHRASE")]
+        passphrase: String,
+
+        /// Output file (default: update the PSBT file in place)
+        #[arg(long, short)]
+        output: Option<String>,
+    },
+
+    /// Show a container's ID, public key, and (once known) EVM address
+    Inspect {
+        /// Path to encrypted container JSON file
+        #[arg(long)]
+        container: String,
+
+        /// Passphrase, needed only while the EVM address is not cached
+        #[arg(long, env = "SIGNER_PASSPHRASE")]
+        passphrase: Option<String>,
+
+        /// Public key cache file; its MAC key (hex, 16+ bytes) is read from
+        /// SIGNER_CACHE_KEY
+        #[arg(long)]
+        cache: Option<String>,
+    },
+
+    /// Generate a key in a recorded ceremony and write a signed transcript
+    Ceremony {
+        /// Ceremony name
+        #[arg(long)]
+        name: String,
+
+        /// Participant as "name:role" or "name:role:<base58 attestation key>" (repeatable)
+        #[arg(long = "participant")]
+        participants: Vec<String>,
+
+        /// Label of the generated key
+        #[arg(long, default_value = "key")]
+        label: String,
+
+        /// Passphrase for the generated container
+        #[arg(long, env = "SIGNER_PASSPHRASE")]
+        passphrase: String,
+
+        /// Output file for the encrypted container
+        #[arg(long)]
+        output: String,
+
+        /// Output file for the transcript
+        #[arg(long)]
+        transcript: String,
+    },
+
+    /// Verify a ceremony transcript's signature and attestations
+    VerifyTranscript {
+        /// Path to the transcript JSON file
+        #[arg(long)]
+        transcript: String,
+    },
+
+    /// Append a signed integrity manifest to a release binary
+    SealBinary {
+        /// Path to the binary to seal
+        #[arg(long)]
+        binary: String,
+
+        /// Container holding the release signing key
+        #[arg(long)]
+        container: String,
+
+        /// Passphrase for the container
+        #[arg(long, env = "SIGNER_PASSPHRASE")]
+        passphrase: String,
+
+        /// Output file for the sealed binary
+        #[arg(long)]
+        output: String,
+    },
+
+    /// Answer JSON-line requests on stdin until it closes, holding unlocked
+    /// sessions (see the `serve` module)
+    Serve,
+
+    /// Run the Unix socket signing daemon (see the `daemon` module)
+    #[cfg(all(feature = "daemon", unix))]
+    Daemon {
+        /// Path to the daemon's JSON configuration
+        #[arg(long)]
+        config: String,
+
+        /// The configuration is a sealed config (see the `secure_config`
+        /// module), opened with a passphrase prompted on the terminal
+        #[arg(long)]
+        sealed: bool,
+
+        /// Open the sealed configuration with the key at this TPM 2.0
+        /// persistent handle instead
+        #[arg(long, value_name = "HANDLE")]
+        tpm_handle: Option<String>,
+
+        /// Prompt on the terminal for each key's passphrase before serving
+        /// (keys the sealed configuration unlocked are skipped)
+        #[arg(long)]
+        unlock: bool,
+    },
+
+    /// Serve the Web3Signer eth1 HTTP API over the given containers
+    #[cfg(feature = "web3signer")]
+    Web3signer {
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1:9000")]
+        listen: String,
+
+        /// Encrypted container JSON file (repeatable); passphrases come
+        /// from SIGNER_PASSPHRASE or a prompt per container
+        #[arg(long = "container", required = true)]
+        containers: Vec<String>,
+    },
+
+    /// Serve a validator's identity (and optionally vote) key to
+    /// authenticated validators over TCP (see the `remote_signer` module)
+    #[cfg(feature = "remote-signer")]
+    RemoteSigner {
+        /// Address to listen on
+        #[arg(long)]
+        listen: String,
+
+        /// Container of the Ed25519 key this server authenticates with
+        #[arg(long)]
+        transport_key: String,
+
+        /// Container of the validator identity key
+        #[arg(long)]
+        identity: String,
+
+        /// Container of the authorized voter key
+        #[arg(long)]
+        vote: Option<String>,
+
+        /// Base58 transport key of a validator allowed to connect
+        /// (repeatable)
+        #[arg(long = "authorized-client", required = true)]
+        authorized_clients: Vec<String>,
+    },
+
+    /// Produce an attestation quote binding this enclave (SGX or SEV-SNP)
+    /// to a container's keys
+    #[cfg(all(feature = "tee", target_os = "linux"))]
+    Attest {
+        /// Path to encrypted container JSON file
+        #[arg(long)]
+        container: String,
+
+        /// Output file for the quote
+        #[arg(long, short)]
+        output: String,
+    },
+
+    /// Check that an attestation quote binds its keys and measurement
+    /// (the quote's signature must be verified separately)
+    #[cfg(all(feature = "tee", target_os = "linux"))]
+    VerifyAttestation {
+        /// Path to the attestation quote JSON file
+        #[arg(long)]
+        quote: String,
+
+        /// Expected enclave measurement (hex)
+        #[arg(long)]
+        measurement: Option<String>,
+    },
+
+    /// Generate a new key into an encrypted container
+    ///
+    /// The passphrase is read from SIGNER_PASSPHRASE, or else typed twice
+    /// at the terminal.
+    Keygen {
+        /// Output file for the encrypted container (must not exist)
+        #[arg(long, short)]
+        output: String,
+
+        /// Also require this keyfile to decrypt (created if missing)
+        #[arg(long)]
+        keyfile: Option<String>,
+    },
+
+    /// Encrypt an existing base58 private key (32 or 64 bytes)
+    ///
+    /// The key is read from SIGNER_PRIVATE_KEY or typed at the terminal;
+    /// the passphrase as for `keygen`.
+    Import {
+        /// Output file for the encrypted container (must not exist)
+        #[arg(long, short)]
+        output: String,
+    },
+
+    /// Print a container's public key, and with --evm its EVM address
+    ExportPubkey {
+        /// Path to encrypted container JSON file
+        #[arg(long)]
+        container: String,
+
+        /// Also derive the EVM address (needs the passphrase)
+        #[arg(long)]
+        evm: bool,
+    },
+
+    /// Sign a Solana transaction, prompting for the passphrase
+    SignSolana {
+        /// Path to encrypted container JSON file
+        #[arg(long)]
+        container: String,
+
+        /// Base64-encoded unsigned transaction
+        #[arg(long)]
+        transaction: String,
+
+        /// Encoding for every output field: base58, base64, or hex
+        #[arg(long)]
+        encoding: Option<Encoding>,
+
+        /// Keyfile for a two-factor container
+        #[arg(long)]
+        keyfile: Option<String>,
+    },
+
+    /// Sign a 32-byte EVM hash, prompting for the passphrase
+    SignEvm {
+        /// Hex-encoded 32-byte hash
+        #[arg(long)]
+        hash: String,
+
+        /// Path to encrypted container JSON file
+        #[arg(long)]
+        container: String,
+
+        /// Mix fresh entropy into the RFC 6979 nonce
+        #[arg(long)]
+        hedged: bool,
+
+        /// Keyfile for a two-factor container
+        #[arg(long)]
+        keyfile: Option<String>,
+    },
+
+    /// Re-encrypt a container under a new passphrase
+    ///
+    /// The current passphrase comes from SIGNER_PASSPHRASE and the new one
+    /// from SIGNER_NEW_PASSPHRASE; either is prompted for when unset.
+    Rekey {
+        /// Path to encrypted container JSON file
+        #[arg(long)]
+        container: String,
+
+        /// Output file (default: replace the container in place)
+        #[arg(long, short)]
+        output: Option<String>,
+    },
+
+    /// Replace a container's key with a new one and write a cross-signed
+    /// rotation attestation
+    ///
+    /// The current passphrase comes from SIGNER_PASSPHRASE and the new
+    /// key's from SIGNER_NEW_PASSPHRASE; either is prompted for when unset.
+    Rotate {
+        /// Path to the container being retired
+        #[arg(long)]
+        container: String,
+
+        /// Output file for the new container (must not exist)
+        #[arg(long, short)]
+        output: String,
+
+        /// Output file for the attestation
+        #[arg(long)]
+        attestation: String,
+
+        /// Why the key is being rotated
+        #[arg(long, default_value = "scheduled")]
+        reason: String,
+    },
+
+    /// Verify a rotation attestation's signatures
+    VerifyRotation {
+        /// Path to the attestation JSON file
+        #[arg(long)]
+        attestation: String,
+    },
+
+    /// Upgrade a container to a newer format under the same passphrase
+    ///
+    /// Raises the KDF to at least the current defaults and optionally
+    /// changes the cipher. The passphrase comes from SIGNER_PASSPHRASE or
+    /// is prompted for.
+    Migrate {
+        /// Path to encrypted container JSON file
+        #[arg(long)]
+        container: String,
+
+        /// Target container version (default: the latest)
+        #[arg(long)]
+        to: Option<u8>,
+
+        /// Re-encrypt with this cipher (aes-256-gcm, xchacha20-poly1305 or aes-256-gcm-siv)
+        #[arg(long)]
+        cipher: Option<Cipher>,
+
+        /// Report what would change without writing anything
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Output file (default: replace the container in place)
+        #[arg(long, short)]
+        output: Option<String>,
+    },
+
+    /// Preview an offline signing request, then sign it once approved,
+    /// prompting for the passphrase
+    SignRequest {
+        /// Path to the signing request JSON file
+        #[arg(long)]
+        request: String,
+
+        /// Path to encrypted container JSON file
+        #[arg(long)]
+        container: String,
+
+        /// Keyfile for a two-factor container
+        #[arg(long)]
+        keyfile: Option<String>,
+
+        /// Output file for the response (default: print it only)
+        #[arg(long, short)]
+        output: Option<String>,
+    },
 }
 
 /// JSON input format for stdin mode
@@ -109,13 +530,50 @@ enum StdinCommand {
         container: String,
         passphrase: String,
         transaction: String,
+        #[serde(default)]
+        encoding: OutputEncoding,
+    },
+    #[serde(rename = "sign_all")]
+    SignAll {
+        container: String,
+        passphrase: String,
+        transactions: Vec<String>,
+    },
+    #[serde(rename = "sign_batch")]
+    SignBatch {
+        container: String,
+        passphrase: String,
+        transactions: Vec<String>,
+        #[serde(default)]
+        encoding: OutputEncoding,
+    },
+    #[serde(rename = "sign_evm_batch")]
+    SignEvmBatch {
+        container: String,
+        passphrase: String,
+        hashes: Vec<String>,
     },
     #[serde(rename = "sign_direct")]
-    SignDirect { private_key: String, message: String },
+    SignDirect {
+        private_key: String,
+        message: String,
+        #[serde(default)]
+        encoding: OutputEncoding,
+    },
     #[serde(rename = "check")]
     Check,
 }
 
+/// A stdin command with its optional idempotency key
+#[derive(Deserialize)]
+struct StdinRequest {
+    #[serde(flatten)]
+    command: StdinCommand,
+    /// Client-supplied key; retries with the same key return the cached response
+    #[serde(default)]
+    idempotency_key: Option<String>,
+}
+
 /// JSON output format
 #[derive(Serialize)]
 struct Output {
@@ -147,8 +605,25 @@ impl Output {
 fn main() {
     let cli = Cli::parse();
 
+    // Cold-start self-check: a tampered binary must not touch any key
+    if let Err(e) = integrity::verify_self() {
+        let output = Output::error(&e.to_string());
+        eprintln!("{}", serde_json::to_string_pretty(&output).unwrap());
+        std::process::exit(1);
+    }
+
+    // Best effort; `check` reports what took effect
+    let _ = HARDENING.set(harden_process(&HardeningOptions::default()));
+
     if cli.stdin {
-        run_stdin_mode();
+        match idempotency_store(cli.idempotency_store.as_deref()) {
+            Ok(mut store) => run_stdin_mode(&mut store),
+            Err(e) => {
+                let output = Output::error(&e.to_string());
+                eprintln!("{}", serde_json::to_string_pretty(&output).unwrap());
+                std::process::exit(1);
+            }
+        }
         return;
     }
 
@@ -163,12 +638,154 @@ fn main() {
             container,
             passphrase,
             transaction,
-        }) => handle_sign(&container, &passphrase, &transaction),
+            encoding,
+            confirm,
+        }) => {
+            if confirm {
+                confirm_solana(&transaction).and_then(|()| {
+                    handle_sign(&container, &passphrase, &transaction, &output_encoding(encoding), None)
+                })
+            } else {
+                handle_sign(&container, &passphrase, &transaction, &output_encoding(encoding), None)
+            }
+        }
 
-        Some(Commands::SignDirect { key, message }) => handle_sign_direct(&key, &message),
+        Some(Commands::SignDirect { key, message, encoding }) => {
+            handle_sign_direct(&key, &message, &output_encoding(encoding))
+        }
 
         Some(Commands::Check) => handle_check(),
 
+        Some(Commands::PartialSign {
+            transaction,
+            container,
+            passphrase,
+            output,
+        }) => handle_partial_sign(&transaction, &container, &passphrase, output.as_deref()),
+
+        Some(Commands::Signers { transaction }) => handle_signers(&transaction),
+
+        Some(Commands::SignPsbt {
+            psbt,
+            container,
+            passphrase,
+            output,
+        }) => handle_sign_psbt(&psbt, &container, &passphrase, output.as_deref()),
+
+        Some(Commands::Inspect {
+            container,
+            passphrase,
+            cache,
+        }) => handle_inspect(&container, passphrase.as_deref(), cache.as_deref()),
+
+        Some(Commands::Ceremony {
+            name,
+            participants,
+            label,
+            passphrase,
+            output,
+            transcript,
+        }) => handle_ceremony(&name, &participants, &label, &passphrase, &output, &transcript),
+
+        Some(Commands::VerifyTranscript { transcript }) => handle_verify_transcript(&transcript),
+
+        Some(Commands::SealBinary {
+            binary,
+            container,
+            passphrase,
+            output,
+        }) => handle_seal_binary(&binary, &container, &passphrase, &output),
+
+        Some(Commands::Serve) => match idempotency_store(cli.idempotency_store.as_deref())
+            .and_then(|store| Server::with_idempotency_store(store).run(io::stdin().lock(), io::stdout().lock()))
+        {
+            Ok(()) => return,
+            Err(e) => Err(e),
+        },
+
+        #[cfg(all(feature = "daemon", unix))]
+        Some(Commands::Daemon {
+            config,
+            sealed,
+            tpm_handle,
+            unlock,
+        }) => handle_daemon(
+            &config,
+            sealed,
+            tpm_handle.as_deref(),
+            unlock,
+            cli.idempotency_store.as_deref(),
+        ),
+
+        #[cfg(feature = "web3signer")]
+        Some(Commands::Web3signer { listen, containers }) => handle_web3signer(&listen, &containers),
+
+        #[cfg(feature = "remote-signer")]
+        Some(Commands::RemoteSigner {
+            listen,
+            transport_key,
+            identity,
+            vote,
+            authorized_clients,
+        }) => handle_remote_signer(&listen, &transport_key, &identity, vote.as_deref(), &authorized_clients),
+
+        #[cfg(all(feature = "tee", target_os = "linux"))]
+        Some(Commands::Attest { container, output }) => handle_attest(&container, &output),
+
+        #[cfg(all(feature = "tee", target_os = "linux"))]
+        Some(Commands::VerifyAttestation { quote, measurement }) => {
+            handle_verify_attestation(&quote, measurement.as_deref())
+        }
+
+        Some(Commands::Keygen { output, keyfile }) => handle_keygen(&output, keyfile.as_deref()),
+
+        Some(Commands::Import { output }) => handle_import(&output),
+
+        Some(Commands::ExportPubkey { container, evm }) => handle_export_pubkey(&container, evm),
+
+        Some(Commands::SignSolana {
+            container,
+            transaction,
+            encoding,
+            keyfile,
+        }) => keyfile.as_deref().map(Keyfile::read).transpose().and_then(|keyfile| {
+            let passphrase = passphrase("Passphrase: ")?;
+            handle_sign(&container, &passphrase, &transaction, &output_encoding(encoding), keyfile.as_ref())
+        }),
+
+        Some(Commands::SignEvm {
+            hash,
+            container,
+            hedged,
+            keyfile,
+        }) => handle_sign_evm(&hash, &container, hedged, keyfile.as_deref()),
+
+        Some(Commands::Rekey { container, output }) => handle_rekey(&container, output.as_deref()),
+
+        Some(Commands::Rotate {
+            container,
+            output,
+            attestation,
+            reason,
+        }) => handle_rotate(&container, &output, &attestation, &reason),
+
+        Some(Commands::VerifyRotation { attestation }) => handle_verify_rotation(&attestation),
+
+        Some(Commands::Migrate {
+            container,
+            to,
+            cipher,
+            dry_run,
+            output,
+        }) => handle_migrate(&container, to, cipher, dry_run, output.as_deref()),
+
+        Some(Commands::SignRequest {
+            request,
+            container,
+            keyfile,
+            output,
+        }) => handle_sign_request(&request, &container, keyfile.as_deref(), output.as_deref()),
+
         None => {
             eprintln!("No command specified. Use --help for usage.");
             std::process::exit(1);
@@ -189,7 +806,15 @@ fn main() {
     }
 }
 
-fn run_stdin_mode() {
+/// The `--idempotency-store` file, or an in-memory store
+fn idempotency_store(path: Option<&str>) -> Result<IdempotencyStore, SignerError> {
+    match path {
+        Some(path) => IdempotencyStore::open(path, idempotency::DEFAULT_CAPACITY),
+        None => Ok(IdempotencyStore::in_memory(idempotency::DEFAULT_CAPACITY)),
+    }
+}
+
+fn run_stdin_mode(store: &mut IdempotencyStore) {
     let stdin = io::stdin();
     let mut stdout = io::stdout();
 
@@ -207,20 +832,41 @@ fn run_stdin_mode() {
             continue;
         }
 
-        let result = process_stdin_command(&line);
+        let result = process_stdin_command(&line, store);
         let output_str = serde_json::to_string(&result).unwrap();
         writeln!(stdout, "{}", output_str).unwrap();
         stdout.flush().unwrap();
     }
 }
 
-fn process_stdin_command(json: &str) -> Output {
-    let command: StdinCommand = match serde_json::from_str(json) {
-        Ok(c) => c,
+fn process_stdin_command(json: &str, store: &mut IdempotencyStore) -> Output {
+    let request: StdinRequest = match serde_json::from_str(json) {
+        Ok(r) => r,
         Err(e) => return Output::error(&format!("Invalid JSON: {}", e)),
     };
 
-    let result = match command {
+    let result = match request.idempotency_key {
+        Some(key) => {
+            store.canonical_request(json).and_then(|fingerprint| {
+                store
+                    .get_or_insert_with(&key, &fingerprint, || {
+                        let output = execute_stdin_command(request.command)?;
+                        Ok(output.data.unwrap_or(serde_json::Value::Null))
+                    })
+                    .map(Output::success)
+            })
+        }
+        None => execute_stdin_command(request.command),
+    };
+
+    match result {
+        Ok(output) => output,
+        Err(e) => { let msg: String = e.to_string(); Output::error(&msg) },
+    }
+}
+
+fn execute_stdin_command(command: StdinCommand) -> Result<Output, SignerError> {
+    match command {
         StdinCommand::CreateContainer {
             private_key,
             passphrase,
@@ -230,19 +876,35 @@ fn process_stdin_command(json: &str) -> Output {
             container,
             passphrase,
             transaction,
-        } => handle_sign_inline(&container, &passphrase, &transaction),
+            encoding,
+        } => handle_sign_inline(&container, &passphrase, &transaction, &encoding, None),
+
+        StdinCommand::SignAll {
+            container,
+            passphrase,
+            transactions,
+        } => handle_sign_all(&container, &passphrase, &transactions),
+
+        StdinCommand::SignBatch {
+            container,
+            passphrase,
+            transactions,
+            encoding,
+        } => handle_sign_batch(&container, &passphrase, &transactions, &encoding),
+
+        StdinCommand::SignEvmBatch {
+            container,
+            passphrase,
+            hashes,
+        } => handle_sign_evm_batch(&container, &passphrase, &hashes),
 
         StdinCommand::SignDirect {
             private_key,
             message,
-        } => handle_sign_direct(&private_key, &message),
+            encoding,
+        } => handle_sign_direct(&private_key, &message, &encoding),
 
         StdinCommand::Check => handle_check(),
-    };
-
-    match result {
-        Ok(output) => output,
-        Err(e) => { let msg: String = e.to_string(); Output::error(&msg) },
     }
 }
 
@@ -272,10 +934,199 @@ fn handle_create_container(
     }
 }
 
+fn handle_ceremony(
+    name: &str,
+    participants: &[String],
+    label: &str,
+    passphrase: &str,
+    output_file: &str,
+    transcript_file: &str,
+) -> Result<Output, SignerError> {
+    let mut ceremony = KeyCeremony::new(name);
+    for participant in participants {
+        let mut parts = participant.splitn(3, ':');
+        match (parts.next(), parts.next()) {
+            (Some(name), Some(role)) => ceremony.add_participant(name, role, parts.next())?,
+            _ => {
+                return Err(SignerError::CeremonyError(format!(
+                    "participant must be name:role, got {}",
+                    participant
+                )))
+            }
+        }
+    }
+
+    // The generated key signs its own transcript
+    let container = ceremony.generate_key(label, passphrase)?;
+    let transcript = ceremony.finish(&container, passphrase)?;
+
+    std::fs::write(output_file, container.to_json()?)?;
+    std::fs::write(transcript_file, transcript.to_json()?)?;
+
+    Ok(Output::success(serde_json::json!({
+        "ceremony_id": transcript.ceremony_id,
+        "public_key": transcript.signer_public_key,
+        "fingerprint": transcript.keys[0].fingerprint,
+        "container": output_file,
+        "transcript": transcript_file
+    })))
+}
+
+fn handle_verify_transcript(transcript_file: &str) -> Result<Output, SignerError> {
+    let transcript: CeremonyTranscript = serde_json::from_str(&std::fs::read_to_string(transcript_file)?)?;
+    transcript.verify()?;
+
+    Ok(Output::success(serde_json::json!({
+        "ceremony_id": transcript.ceremony_id,
+        "signer_public_key": transcript.signer_public_key,
+        "attestations": transcript.attestations.len(),
+        "valid": true
+    })))
+}
+
+fn handle_seal_binary(
+    binary_file: &str,
+    container_file: &str,
+    passphrase: &str,
+    output_file: &str,
+) -> Result<Output, SignerError> {
+    let container = EncryptedKeyContainer::from_json(&std::fs::read_to_string(container_file)?)?;
+    let sealed = integrity::seal(&std::fs::read(binary_file)?, &container, passphrase)?;
+    std::fs::write(output_file, &sealed)?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(output_file, std::fs::Permissions::from_mode(0o755))?;
+    }
+
+    Ok(Output::success(serde_json::json!({
+        "output": output_file,
+        "signer": container.public_key,
+        "integrity": integrity::verify_bytes(&sealed, container.public_key.as_deref().unwrap_or_default())?,
+    })))
+}
+
+fn read_transaction_file(path: &str) -> Result<SolanaTransaction, SignerError> {
+    let bytes = base64::Engine::decode(
+        &base64::engine::general_purpose::STANDARD,
+        std::fs::read_to_string(path)?.trim(),
+    )
+    .map_err(|e| SignerError::Base64Error(e.to_string()))?;
+    SolanaTransaction::parse_or_from_message(&bytes)
+}
+
+fn signers_json(tx: &SolanaTransaction) -> serde_json::Value {
+    let signers: Vec<_> = tx
+        .signature_statuses()
+        .into_iter()
+        .enumerate()
+        .map(|(i, (key, status))| {
+            serde_json::json!({
+                "public_key": bs58::encode(key).into_string(),
+                "fee_payer": i == 0,
+                "status": status.as_str(),
+            })
+        })
+        .collect();
+    let missing = signers.iter().filter(|s| s["status"] != "valid").count();
+
+    serde_json::json!({
+        "signers": signers,
+        "missing": missing,
+        "complete": missing == 0,
+    })
+}
+
+fn handle_partial_sign(
+    transaction_file: &str,
+    container_file: &str,
+    passphrase: &str,
+    output_file: Option<&str>,
+) -> Result<Output, SignerError> {
+    let mut tx = read_transaction_file(transaction_file)?;
+    let container = EncryptedKeyContainer::from_json(&std::fs::read_to_string(container_file)?)?;
+    let signer = tx.sign_with(&ContainerBackend::new(&container, passphrase))?;
+
+    let output_file = output_file.unwrap_or(transaction_file);
+    std::fs::write(
+        output_file,
+        base64::Engine::encode(&base64::engine::general_purpose::STANDARD, tx.serialize()),
+    )?;
+
+    let mut data = signers_json(&tx);
+    data["signed_by"] = serde_json::json!(signer);
+    data["output"] = serde_json::json!(output_file);
+    Ok(Output::success(data))
+}
+
+fn handle_signers(transaction_file: &str) -> Result<Output, SignerError> {
+    Ok(Output::success(signers_json(&read_transaction_file(transaction_file)?)))
+}
+
+fn handle_sign_psbt(
+    psbt_file: &str,
+    container_file: &str,
+    passphrase: &str,
+    output_file: Option<&str>,
+) -> Result<Output, SignerError> {
+    let result = decrypt_and_sign_psbt(
+        &std::fs::read_to_string(container_file)?,
+        passphrase,
+        &std::fs::read_to_string(psbt_file)?,
+    )?;
+
+    let output_file = output_file.unwrap_or(psbt_file);
+    std::fs::write(output_file, &result.psbt)?;
+
+    Ok(Output::success(serde_json::json!({
+        "signed_inputs": result.signed_inputs,
+        "public_key": result.public_key,
+        "output": output_file,
+    })))
+}
+
+fn handle_inspect(container_file: &str, passphrase: Option<&str>, cache_file: Option<&str>) -> Result<Output, SignerError> {
+    let container = EncryptedKeyContainer::from_json(&std::fs::read_to_string(container_file)?)?;
+
+    let mut cache = match cache_file {
+        Some(path) => {
+            let key_hex = std::env::var("SIGNER_CACHE_KEY")
+                .map_err(|_| SignerError::IntegrityError("SIGNER_CACHE_KEY is not set".to_string()))?;
+            let key_bytes = hex::decode(key_hex.trim())
+                .map_err(|e| SignerError::IntegrityError(format!("SIGNER_CACHE_KEY is not hex: {}", e)))?;
+            PublicKeyCache::open(path, &SecureBuffer::from_slice(&key_bytes)?)?
+        }
+        None => PublicKeyCache::in_memory(),
+    };
+
+    let cached = cache.get(&container).is_some();
+    let keys = match (cached, passphrase) {
+        (true, _) => cache.get(&container).cloned(),
+        (false, Some(passphrase)) => Some(cache.get_or_compute(&container, passphrase)?.clone()),
+        (false, None) => None,
+    };
+
+    Ok(Output::success(serde_json::json!({
+        "container_id": container.container_id()?,
+        "version": container.version,
+        "public_key": container.public_key,
+        "evm_address": keys.as_ref().map(|k| &k.evm_address),
+        "secp256k1_public_key": keys.as_ref().map(|k| &k.secp256k1_public_key),
+        "cached": cached,
+    })))
+}
+
+fn output_encoding(encoding: Option<Encoding>) -> OutputEncoding {
+    encoding.map(OutputEncoding::all).unwrap_or_default()
+}
+
 fn handle_sign(
     container_path: &str,
     passphrase: &str,
     transaction_b64: &str,
+    encoding: &OutputEncoding,
+    keyfile: Option<&Keyfile>,
 ) -> Result<Output, SignerError> {
     // Read container
     let container_json = if container_path == "-" {
@@ -288,13 +1139,24 @@ fn handle_sign(
         std::fs::read_to_string(container_path)?
     };
 
-    handle_sign_inline(&container_json, passphrase, transaction_b64)
+    handle_sign_inline(&container_json, passphrase, transaction_b64, encoding, keyfile)
+}
+
+/// Ask on the terminal before a Solana transaction is signed
+fn confirm_solana(transaction_b64: &str) -> Result<(), SignerError> {
+    let bytes = base64::Engine::decode(&base64::engine::general_purpose::STANDARD, transaction_b64)
+        .map_err(|e| SignerError::Base64Error(e.to_string()))?;
+    let message = SolanaTransaction::parse_or_from_message(&bytes)?;
+    let request = ApprovalRequest::solana(message.message_bytes())?;
+    approval::require_approval(&TerminalApproval::stdio(), &request, CONFIRM_TIMEOUT)
 }
 
 fn handle_sign_inline(
     container_json: &str,
     passphrase: &str,
     transaction_b64: &str,
+    encoding: &OutputEncoding,
+    keyfile: Option<&Keyfile>,
 ) -> Result<Output, SignerError> {
     // Decode transaction
     let transaction_bytes = base64::Engine::decode(
@@ -304,12 +1166,74 @@ fn handle_sign_inline(
     .map_err(|e| SignerError::Base64Error(e.to_string()))?;
 
     // Sign
-    let result = decrypt_and_sign(container_json, passphrase, &transaction_bytes)?;
+    let container = EncryptedKeyContainer::from_json(container_json)?;
+    let result = container_backend(&container, passphrase, keyfile)
+        .sign_solana(&transaction_bytes)?
+        .with_encoding(encoding)?;
 
     Ok(Output::success(serde_json::to_value(&result)?))
 }
 
-fn handle_sign_direct(key_b58: &str, message_b64: &str) -> Result<Output, SignerError> {
+/// A backend for `container`, with the keyfile when one was given
+fn container_backend<'a>(
+    container: &'a EncryptedKeyContainer,
+    passphrase: &'a str,
+    keyfile: Option<&'a Keyfile>,
+) -> ContainerBackend<'a> {
+    let backend = ContainerBackend::new(container, passphrase);
+    match keyfile {
+        Some(keyfile) => backend.with_keyfile(keyfile),
+        None => backend,
+    }
+}
+
+fn handle_sign_all(container_json: &str, passphrase: &str, transactions_b64: &[String]) -> Result<Output, SignerError> {
+    let base64 = base64::engine::general_purpose::STANDARD;
+    let transactions = transactions_b64
+        .iter()
+        .map(|tx| base64::Engine::decode(&base64, tx).map_err(|e| SignerError::Base64Error(e.to_string())))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let signed: Vec<String> = solana::decrypt_and_sign_all(container_json, passphrase, &transactions)?
+        .iter()
+        .map(|tx| base64::Engine::encode(&base64, tx.serialize()))
+        .collect();
+
+    Ok(Output::success(serde_json::json!({ "transactions": signed })))
+}
+
+fn handle_sign_batch(
+    container_json: &str,
+    passphrase: &str,
+    transactions_b64: &[String],
+    encoding: &OutputEncoding,
+) -> Result<Output, SignerError> {
+    let base64 = base64::engine::general_purpose::STANDARD;
+    let requests = transactions_b64
+        .iter()
+        .map(|tx| base64::Engine::decode(&base64, tx).map(TxRequest::new))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| SignerError::Base64Error(e.to_string()))?;
+
+    let results = decrypt_and_sign_batch(container_json, passphrase, &requests)?
+        .into_iter()
+        .map(|result| result.with_encoding(encoding))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(Output::success(serde_json::json!({ "results": results })))
+}
+
+fn handle_sign_evm_batch(container_json: &str, passphrase: &str, hashes_hex: &[String]) -> Result<Output, SignerError> {
+    let requests = hashes_hex
+        .iter()
+        .map(|hash| Encoding::Hex.decode(hash).map(TxRequest::new))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let results = decrypt_and_sign_evm_batch(container_json, passphrase, &requests)?;
+    Ok(Output::success(serde_json::json!({ "results": results })))
+}
+
+fn handle_sign_direct(key_b58: &str, message_b64: &str, encoding: &OutputEncoding) -> Result<Output, SignerError> {
     // Decode inputs
     let private_key = bs58::decode(key_b58)
         .into_vec()
@@ -319,20 +1243,392 @@ fn handle_sign_direct(key_b58: &str, message_b64: &str) -> Result<Output, Signer
         .map_err(|e| SignerError::Base64Error(e.to_string()))?;
 
     // Sign
-    let result = sign_transaction(&private_key, &message)?;
+    let result = sign_transaction(&private_key, &message)?.with_encoding(encoding)?;
 
     Ok(Output::success(serde_json::to_value(&result)?))
 }
 
-fn handle_check() -> Result<Output, SignerError> {
-    use coldstar_secure_signer::SecureBuffer;
+/// The passphrase from SIGNER_PASSPHRASE, or else typed at the terminal
+fn passphrase(prompt: &str) -> Result<Zeroizing<String>, SignerError> {
+    match std::env::var("SIGNER_PASSPHRASE") {
+        Ok(passphrase) => Ok(Zeroizing::new(passphrase)),
+        Err(_) => tty::read_passphrase(prompt),
+    }
+}
+
+/// A passphrase for a new container from `var`, or else typed twice
+fn new_passphrase(var: &str) -> Result<Zeroizing<String>, SignerError> {
+    match std::env::var(var) {
+        Ok(passphrase) => Ok(Zeroizing::new(passphrase)),
+        Err(_) => tty::read_new_passphrase("New passphrase: "),
+    }
+}
+
+/// Write a new container file, refusing to replace an existing one
+fn write_new_container(path: &str, container: &EncryptedKeyContainer) -> Result<Output, SignerError> {
+    let mut file = std::fs::OpenOptions::new().write(true).create_new(true).open(path)?;
+    file.write_all(container.to_json()?.as_bytes())?;
+
+    Ok(Output::success(serde_json::json!({
+        "path": path,
+        "container_id": container.container_id()?,
+        "public_key": container.public_key,
+    })))
+}
+
+#[cfg(all(feature = "daemon", unix))]
+fn handle_daemon(
+    config_file: &str,
+    sealed: bool,
+    tpm_handle: Option<&str>,
+    unlock: bool,
+    store: Option<&str>,
+) -> Result<Output, SignerError> {
+    use coldstar_secure_signer::audit::AuditLog;
+    use coldstar_secure_signer::counter::SignatureCounter;
+    use coldstar_secure_signer::daemon::{self, Daemon, DaemonConfig};
+    use coldstar_secure_signer::secure_config::{ConfigKeySource, EncryptedConfig};
+
+    let sealed_config = if sealed || tpm_handle.is_some() {
+        let boot_passphrase;
+        let source = match tpm_handle {
+            Some(handle) => ConfigKeySource::Tpm2 { handle },
+            None => {
+                boot_passphrase = tty::read_passphrase("Config passphrase: ")?;
+                ConfigKeySource::Passphrase(&boot_passphrase)
+            }
+        };
+        Some(EncryptedConfig::load(config_file, &source)?)
+    } else {
+        None
+    };
+    let config = match &sealed_config {
+        Some(sealed) => DaemonConfig::from_sealed(sealed)?,
+        None => DaemonConfig::from_json(&std::fs::read_to_string(config_file)?)?,
+    };
+
+    let mut daemon = Daemon::new(config.clone()).with_idempotency_store(idempotency_store(store)?);
+    if let Some(path) = &config.audit_log {
+        daemon = daemon.with_audit_log(AuditLog::open(path)?);
+    }
+    if let Some(path) = &config.counter_file {
+        let mac_key = sealed_config
+            .as_ref()
+            .and_then(|sealed| sealed.secret(daemon::COUNTER_KEY_SECRET))
+            .ok_or_else(|| {
+                SignerError::IntegrityError(format!(
+                    "counter_file needs a sealed configuration with a {} secret",
+                    daemon::COUNTER_KEY_SECRET
+                ))
+            })?;
+        daemon = daemon.with_counter(SignatureCounter::open(path, mac_key)?);
+    }
+    let unlocked = match &sealed_config {
+        Some(sealed) => daemon.unlock_from(sealed)?,
+        None => Vec::new(),
+    };
+    if unlock {
+        for key in daemon.key_names().into_iter().filter(|key| !unlocked.contains(key)) {
+            let passphrase = tty::read_passphrase(&format!("Passphrase for {}: ", key))?;
+            daemon.unlock(&key, &passphrase)?;
+        }
+    }
+
+    daemon::handle_termination_signals();
+    daemon.serve(daemon.listener()?)?;
+    Ok(Output::success(serde_json::json!({ "stopped": true })))
+}
+
+#[cfg(feature = "web3signer")]
+fn handle_web3signer(listen: &str, container_files: &[String]) -> Result<Output, SignerError> {
+    use coldstar_secure_signer::web3signer::Web3Signer;
+
+    let mut signer = Web3Signer::new();
+    for path in container_files {
+        let container = EncryptedKeyContainer::from_json(&std::fs::read_to_string(path)?)?;
+        let passphrase = passphrase(&format!("Passphrase for {}: ", path))?;
+        let public_key = signer.add_key(&container, &passphrase)?;
+        eprintln!("Loaded {} ({})", public_key, path);
+    }
+
+    let listener = std::net::TcpListener::bind(listen)?;
+    eprintln!("Web3Signer API listening on {}", listener.local_addr()?);
+    signer.serve(listener)?;
+    Ok(Output::success(serde_json::json!({ "stopped": true })))
+}
+
+#[cfg(feature = "remote-signer")]
+fn handle_remote_signer(
+    listen: &str,
+    transport_file: &str,
+    identity_file: &str,
+    vote_file: Option<&str>,
+    authorized_clients: &[String],
+) -> Result<Output, SignerError> {
+    use coldstar_secure_signer::keys::SecureEd25519Seed;
+    use coldstar_secure_signer::remote_signer::{KeyRole, RemoteSigner};
+
+    let load = |path: &str| -> Result<SecureEd25519Seed, SignerError> {
+        let container = EncryptedKeyContainer::from_json(&std::fs::read_to_string(path)?)?;
+        SecureEd25519Seed::from_container(&container, &passphrase(&format!("Passphrase for {}: ", path))?)
+    };
+    let authorized = authorized_clients
+        .iter()
+        .map(|key| {
+            bs58::decode(key)
+                .into_vec()?
+                .try_into()
+                .map_err(|bytes: Vec<u8>| SignerError::InvalidKeyFormat(bytes.len()))
+        })
+        .collect::<Result<Vec<[u8; 32]>, SignerError>>()?;
+
+    let mut signer = RemoteSigner::new(load(transport_file)?, authorized);
+    let identity = signer.add_key("identity", KeyRole::Identity, load(identity_file)?);
+    eprintln!("Identity key {}", bs58::encode(identity).into_string());
+    if let Some(vote_file) = vote_file {
+        let vote = signer.add_key("vote", KeyRole::Vote, load(vote_file)?);
+        eprintln!("Vote key {}", bs58::encode(vote).into_string());
+    }
+
+    let listener = std::net::TcpListener::bind(listen)?;
+    eprintln!(
+        "Remote signer listening on {} as {}",
+        listener.local_addr()?,
+        bs58::encode(signer.transport_public_key()).into_string()
+    );
+    signer.serve(listener)?;
+    Ok(Output::success(serde_json::json!({ "stopped": true })))
+}
+
+#[cfg(all(feature = "tee", target_os = "linux"))]
+fn handle_attest(container_file: &str, output_file: &str) -> Result<Output, SignerError> {
+    use coldstar_secure_signer::tee::TeeBackend;
+
+    let container = EncryptedKeyContainer::from_json(&std::fs::read_to_string(container_file)?)?;
+    let passphrase = passphrase("Passphrase: ")?;
+    let quote = TeeBackend::new(&container, &passphrase)?.attest()?;
+    std::fs::write(output_file, quote.to_json()?)?;
+
+    Ok(Output::success(serde_json::json!({
+        "quote": output_file,
+        "tee": quote.tee,
+        "public_key": quote.public_key,
+        "evm_address": quote.evm_address,
+        "measurement": quote.measurement,
+    })))
+}
+
+#[cfg(all(feature = "tee", target_os = "linux"))]
+fn handle_verify_attestation(quote_file: &str, measurement: Option<&str>) -> Result<Output, SignerError> {
+    use coldstar_secure_signer::tee::AttestationQuote;
+
+    let quote = AttestationQuote::from_json(&std::fs::read_to_string(quote_file)?)?;
+    quote.verify(measurement)?;
+    Ok(Output::success(serde_json::json!({
+        "tee": quote.tee,
+        "public_key": quote.public_key,
+        "evm_address": quote.evm_address,
+        "measurement": quote.measurement,
+        "valid": true
+    })))
+}
+
+fn handle_keygen(output_file: &str, keyfile_path: Option<&str>) -> Result<Output, SignerError> {
+    let keyfile = match keyfile_path {
+        Some(path) if std::path::Path::new(path).exists() => Some(Keyfile::read(path)?),
+        Some(path) => {
+            let keyfile = Keyfile::generate()?;
+            keyfile.write_new(path)?;
+            Some(keyfile)
+        }
+        None => None,
+    };
+    let passphrase = new_passphrase("SIGNER_PASSPHRASE")?;
+
+    let mut seed = SecureBuffer::new(32)?;
+    fill_random(seed.as_mut_slice())?;
+    let container = match &keyfile {
+        Some(keyfile) => EncryptedKeyContainer::encrypt_with_keyfile(
+            seed.as_slice(),
+            &passphrase,
+            keyfile,
+            Cipher::Aes256Gcm,
+            KdfParams::default(),
+        ),
+        None => EncryptedKeyContainer::encrypt(seed.as_slice(), &passphrase),
+    };
+    seed.zeroize();
+
+    write_new_container(output_file, &container?)
+}
+
+fn handle_import(output_file: &str) -> Result<Output, SignerError> {
+    let key_b58 = match std::env::var("SIGNER_PRIVATE_KEY") {
+        Ok(key) => Zeroizing::new(key),
+        Err(_) => tty::read_passphrase("Private key (base58): ")?,
+    };
+    let private_key = Zeroizing::new(
+        bs58::decode(key_b58.trim())
+            .into_vec()
+            .map_err(|e| SignerError::Base58Error(e.to_string()))?,
+    );
+    let passphrase = new_passphrase("SIGNER_PASSPHRASE")?;
+
+    write_new_container(output_file, &EncryptedKeyContainer::encrypt(&private_key, &passphrase)?)
+}
+
+fn handle_export_pubkey(container_file: &str, evm: bool) -> Result<Output, SignerError> {
+    let container = EncryptedKeyContainer::from_json(&std::fs::read_to_string(container_file)?)?;
+
+    let evm_address = if evm {
+        let passphrase = passphrase("Passphrase: ")?;
+        Some(PublicKeyCache::in_memory().get_or_compute(&container, &passphrase)?.evm_address.clone())
+    } else {
+        None
+    };
+
+    Ok(Output::success(serde_json::json!({
+        "public_key": container.public_key,
+        "evm_address": evm_address,
+    })))
+}
+
+fn handle_sign_evm(
+    hash_hex: &str,
+    container_file: &str,
+    hedged: bool,
+    keyfile_path: Option<&str>,
+) -> Result<Output, SignerError> {
+    let message_hash = Encoding::Hex.decode(hash_hex)?;
+    let container = EncryptedKeyContainer::from_json(&std::fs::read_to_string(container_file)?)?;
+    let keyfile = keyfile_path.map(Keyfile::read).transpose()?;
+    let passphrase = passphrase("Passphrase: ")?;
+
+    let nonce = if hedged { NonceMode::Hedged } else { NonceMode::Deterministic };
+    let result =
+        container_backend(&container, &passphrase, keyfile.as_ref()).sign_evm_hash_with_nonce(&message_hash, nonce)?;
+    Ok(Output::success(serde_json::to_value(&result)?))
+}
+
+fn handle_rekey(container_file: &str, output_file: Option<&str>) -> Result<Output, SignerError> {
+    let container = EncryptedKeyContainer::from_json(&std::fs::read_to_string(container_file)?)?;
+    let passphrase = passphrase("Current passphrase: ")?;
+    let new_passphrase = new_passphrase("SIGNER_NEW_PASSPHRASE")?;
+
+    let rekeyed = container.rekey(&passphrase, &new_passphrase)?;
+    let path = output_file.unwrap_or(container_file);
+    std::fs::write(path, rekeyed.to_json()?)?;
+
+    Ok(Output::success(serde_json::json!({
+        "path": path,
+        "container_id": rekeyed.container_id()?,
+        "previous_container_id": container.container_id()?,
+        "public_key": rekeyed.public_key,
+    })))
+}
+
+fn handle_rotate(
+    container_file: &str,
+    output_file: &str,
+    attestation_file: &str,
+    reason: &str,
+) -> Result<Output, SignerError> {
+    let container = EncryptedKeyContainer::from_json(&std::fs::read_to_string(container_file)?)?;
+    let passphrase = passphrase("Current passphrase: ")?;
+    let new_passphrase = new_passphrase("SIGNER_NEW_PASSPHRASE")?;
+
+    let (rotated, attestation) = rotate_key(&container, &passphrase, &new_passphrase, reason)?;
+    write_new_container(output_file, &rotated)?;
+    std::fs::write(attestation_file, attestation.to_json()?)?;
+
+    Ok(Output::success(serde_json::json!({
+        "container": output_file,
+        "attestation": attestation_file,
+        "old_public_key": attestation.old_public_key,
+        "new_public_key": attestation.new_public_key,
+    })))
+}
+
+fn handle_verify_rotation(attestation_file: &str) -> Result<Output, SignerError> {
+    let attestation = RotationAttestation::from_json(&std::fs::read_to_string(attestation_file)?)?;
+    attestation.verify()?;
+
+    Ok(Output::success(serde_json::json!({
+        "old_public_key": attestation.old_public_key,
+        "new_public_key": attestation.new_public_key,
+        "reason": attestation.reason,
+        "issued_at": attestation.issued_at,
+        "valid": true
+    })))
+}
+
+fn handle_migrate(
+    container_file: &str,
+    to: Option<u8>,
+    cipher: Option<Cipher>,
+    dry_run: bool,
+    output_file: Option<&str>,
+) -> Result<Output, SignerError> {
+    let container = EncryptedKeyContainer::from_json(&std::fs::read_to_string(container_file)?)?;
+    let target = to.map(ContainerVersion::try_from).transpose()?.unwrap_or(ContainerVersion::LATEST);
+    let passphrase = passphrase("Passphrase: ")?;
+
+    let options = MigrationOptions { cipher, dry_run, ..Default::default() };
+    let report = container.migrate_with(&passphrase, target, &options)?;
+    let path = match &report.container {
+        Some(migrated) => {
+            let path = output_file.unwrap_or(container_file);
+            std::fs::write(path, migrated.to_json()?)?;
+            Some(path)
+        }
+        None => None,
+    };
+
+    Ok(Output::success(serde_json::json!({
+        "path": path,
+        "changed": report.changed(),
+        "dry_run": dry_run,
+        "from": report.from,
+        "to": report.to,
+    })))
+}
 
+fn handle_sign_request(
+    request_file: &str,
+    container_file: &str,
+    keyfile_path: Option<&str>,
+    output_file: Option<&str>,
+) -> Result<Output, SignerError> {
+    let request = SigningRequest::from_json(&std::fs::read_to_string(request_file)?)?;
+    let container = EncryptedKeyContainer::from_json(&std::fs::read_to_string(container_file)?)?;
+    let keyfile = keyfile_path.map(Keyfile::read).transpose()?;
+
+    let mut preview = request.preview()?;
+    if let Ok(id) = container.container_id() {
+        preview = preview.for_container(id);
+    }
+    approval::require_approval(&TerminalApproval::stdio(), &preview, CONFIRM_TIMEOUT)?;
+    let passphrase = passphrase("Passphrase: ")?;
+
+    let response = request.sign(&container_backend(&container, &passphrase, keyfile.as_ref()))?;
+    if let Some(path) = output_file {
+        std::fs::write(path, response.to_json()?)?;
+    }
+    Ok(Output::success(serde_json::to_value(&response)?))
+}
+
+fn handle_check() -> Result<Output, SignerError> {
     let buffer = SecureBuffer::new(64)?;
     let mlock_supported = buffer.is_locked();
 
     Ok(Output::success(serde_json::json!({
         "version": coldstar_secure_signer::VERSION,
         "mlock_supported": mlock_supported,
+        "locking": coldstar_secure_signer::locking_report(),
+        "integrity": integrity::verify_self()?,
+        "hardening": HARDENING.get(),
+        "selftest": selftest(OnFailure::Report),
+        "build": coldstar_secure_signer::build_info(),
         "platform": std::env::consts::OS,
         "arch": std::env::consts::ARCH,
     })))
@@ -348,4 +1644,51 @@ mod tests {
         let cmd: StdinCommand = serde_json::from_str(json).unwrap();
         assert!(matches!(cmd, StdinCommand::Check));
     }
+
+    #[test]
+    fn test_key_management_commands_parse() {
+        let cli = Cli::try_parse_from(["solana-signer", "rekey", "--container", "wallet.json"]).unwrap();
+        assert!(matches!(cli.command, Some(Commands::Rekey { output: None, .. })));
+
+        let cli = Cli::try_parse_from([
+            "solana-signer", "rotate", "--container", "w.json", "-o", "w2.json", "--attestation", "r.json",
+        ])
+        .unwrap();
+        assert!(matches!(cli.command, Some(Commands::Rotate { reason, .. }) if reason == "scheduled"));
+
+        let cli = Cli::try_parse_from([
+            "solana-signer", "migrate", "--container", "w.json", "--cipher", "xchacha20-poly1305", "--dry-run",
+        ])
+        .unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Migrate { cipher: Some(Cipher::XChaCha20Poly1305), dry_run: true, to: None, .. })
+        ));
+        assert!(Cli::try_parse_from(["solana-signer", "migrate", "--container", "w.json", "--cipher", "rot13"]).is_err());
+
+        let cli = Cli::try_parse_from(["solana-signer", "keygen", "-o", "k.json", "--keyfile", "k.key"]).unwrap();
+        assert!(matches!(cli.command, Some(Commands::Keygen { keyfile: Some(_), .. })));
+
+        let cli = Cli::try_parse_from(["solana-signer", "sign-request", "--request", "r.json", "--container", "w.json"]).unwrap();
+        assert!(matches!(cli.command, Some(Commands::SignRequest { output: None, .. })));
+
+        let cli = Cli::try_parse_from(["solana-signer", "export-pubkey", "--container", "wallet.json", "--evm"]).unwrap();
+        assert!(matches!(cli.command, Some(Commands::ExportPubkey { evm: true, .. })));
+
+        // The interactive commands take no passphrase argument
+        assert!(Cli::try_parse_from(["solana-signer", "keygen", "-o", "k.json", "--passphrase", "pw"]).is_err());
+    }
+
+    #[test]
+    fn test_stdin_request_idempotency_key() {
+        let json = r#"{"action":"check","idempotency_key":"retry-1"}"#;
+        let req: StdinRequest = serde_json::from_str(json).unwrap();
+        assert!(matches!(req.command, StdinCommand::Check));
+        assert_eq!(req.idempotency_key.as_deref(), Some("retry-1"));
+
+        // Key order and the key itself do not change the fingerprint
+        let reordered = r#"{"idempotency_key":"other","action":"check"}"#;
+        let store = IdempotencyStore::in_memory(8);
+        assert_eq!(store.canonical_request(json).unwrap(), store.canonical_request(reordered).unwrap());
+    }
 }
@@ -0,0 +1,196 @@
+//! Metrics hooks
+//!
+//! Operators running a fleet of signers need to alert on brute-force
+//! attempts and degraded memory locking without scraping logs. The library
+//! reports to a process-wide [`MetricsSink`], installed with
+//! [`set_metrics_sink`]; until one is, nothing is recorded.
+//!
+//! | Metric | Kind | Labels |
+//! |--------|------|--------|
+//! | `coldstar_sign_operations_total` | counter | `chain`, `outcome` |
+//! | `coldstar_decryption_failures_total` | counter | |
+//! | `coldstar_policy_rejections_total` | counter | `check` |
+//! | `coldstar_mlock_fallbacks_total` | counter | |
+//! | `coldstar_kdf_duration_seconds` | histogram | `kdf` |
+//! | `coldstar_sign_duration_seconds` | histogram | `chain` |
+//!
+//! Signatures are counted for [`ContainerBackend`] (and so
+//! `decrypt_and_sign*`) and [`SigningSession`]; `outcome` is `ok` or the
+//! error's [`kind`](SignerError::kind). Label values never include request
+//! data. Without `std` there is no clock, so histograms are not recorded.
+//!
+//! A Prometheus exporter implements the trait over its own registry:
+//!
+//! ```ignore
+//! impl MetricsSink for PrometheusSink {
+//!     fn increment(&self, counter: Counter, labels: &[(&str, &str)]) {
+//!         self.counters[&counter].with_label_values(&values(labels)).inc();
+//!     }
+//!     fn observe(&self, histogram: Histogram, value: f64, labels: &[(&str, &str)]) {
+//!         self.histograms[&histogram].with_label_values(&values(labels)).observe(value);
+//!     }
+//! }
+//! metrics::set_metrics_sink(Arc::new(PrometheusSink::register(&registry)?));
+//! ```
+//!
+//! [`ContainerBackend`]: crate::backend::ContainerBackend
+//! [`SigningSession`]: crate::session::SigningSession
+
+use alloc::sync::Arc;
+
+use crate::error::SignerError;
+use crate::sync::Mutex;
+
+/// Counters the library increments
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Counter {
+    /// A signature was requested (`chain`, `outcome`)
+    SignOperations,
+    /// A container failed to decrypt: a wrong passphrase or keyfile, or a
+    /// tampered container
+    DecryptionFailures,
+    /// A signing policy rejected a request (`check`)
+    PolicyRejections,
+    /// A buffer could not be locked and was used unlocked
+    /// ([`LockingMode::Permissive`](crate::secure_buffer::LockingMode::Permissive))
+    MlockFallbacks,
+}
+
+impl Counter {
+    /// Prometheus-style name
+    pub fn name(&self) -> &'static str {
+        match self {
+            Counter::SignOperations => "coldstar_sign_operations_total",
+            Counter::DecryptionFailures => "coldstar_decryption_failures_total",
+            Counter::PolicyRejections => "coldstar_policy_rejections_total",
+            Counter::MlockFallbacks => "coldstar_mlock_fallbacks_total",
+        }
+    }
+}
+
+/// Latencies the library observes, in seconds
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Histogram {
+    /// Running a container's KDF (`kdf`)
+    KdfDuration,
+    /// A signature, including any unlock it needed (`chain`)
+    SignDuration,
+}
+
+impl Histogram {
+    /// Prometheus-style name
+    pub fn name(&self) -> &'static str {
+        match self {
+            Histogram::KdfDuration => "coldstar_kdf_duration_seconds",
+            Histogram::SignDuration => "coldstar_sign_duration_seconds",
+        }
+    }
+}
+
+/// Receives the library's metrics
+///
+/// Called on the signing thread, so implementations should only update
+/// in-memory state.
+pub trait MetricsSink: Send + Sync {
+    /// Add one to `counter`
+    fn increment(&self, counter: Counter, labels: &[(&str, &str)]);
+
+    /// Record one observation of `histogram`
+    fn observe(&self, histogram: Histogram, value: f64, labels: &[(&str, &str)]);
+}
+
+static SINK: Mutex<Option<Arc<dyn MetricsSink>>> = Mutex::new(None);
+
+/// Send the library's metrics to `sink`
+pub fn set_metrics_sink(sink: Arc<dyn MetricsSink>) {
+    *SINK.lock() = Some(sink);
+}
+
+/// Stop recording metrics
+pub fn clear_metrics_sink() {
+    *SINK.lock() = None;
+}
+
+fn sink() -> Option<Arc<dyn MetricsSink>> {
+    SINK.lock().clone()
+}
+
+pub(crate) fn increment(counter: Counter, labels: &[(&str, &str)]) {
+    if let Some(sink) = sink() {
+        sink.increment(counter, labels);
+    }
+}
+
+/// Run `f`, observing how long it took in `histogram`
+pub(crate) fn time<T>(histogram: Histogram, labels: &[(&str, &str)], f: impl FnOnce() -> T) -> T {
+    #[cfg(feature = "std")]
+    {
+        let started = std::time::Instant::now();
+        let result = f();
+        if let Some(sink) = sink() {
+            sink.observe(histogram, started.elapsed().as_secs_f64(), labels);
+        }
+        result
+    }
+    #[cfg(not(feature = "std"))]
+    {
+        let _ = (histogram, labels);
+        f()
+    }
+}
+
+/// Run a signature on `chain`, counting it and timing it
+pub(crate) fn sign<T>(chain: &str, f: impl FnOnce() -> Result<T, SignerError>) -> Result<T, SignerError> {
+    let result = time(Histogram::SignDuration, &[("chain", chain)], f);
+    let outcome = match &result {
+        Ok(_) => "ok",
+        Err(e) => e.kind(),
+    };
+    increment(Counter::SignOperations, &[("chain", chain), ("outcome", outcome)]);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex as StdMutex;
+
+    use crate::backend::{ContainerBackend, SignerBackend};
+    use crate::crypto::{Cipher, EncryptedKeyContainer};
+    use crate::kdf::KdfParams;
+
+    #[derive(Default)]
+    struct Recorder(StdMutex<Vec<String>>);
+
+    impl MetricsSink for Recorder {
+        fn increment(&self, counter: Counter, labels: &[(&str, &str)]) {
+            self.0.lock().unwrap().push(format!("{}{:?}", counter.name(), labels));
+        }
+        fn observe(&self, histogram: Histogram, value: f64, labels: &[(&str, &str)]) {
+            assert!(value >= 0.0);
+            self.0.lock().unwrap().push(format!("{}{:?}", histogram.name(), labels));
+        }
+    }
+
+    #[test]
+    fn test_metrics_reach_the_sink() {
+        std::env::set_var("SIGNER_ALLOW_INSECURE_MEMORY", "1");
+        let container =
+            EncryptedKeyContainer::encrypt_with_kdf(&[10u8; 32], "pw", Cipher::Aes256Gcm, KdfParams::MINIMUM).unwrap();
+        // Other tests run alongside, so only look for what this one caused
+        let recorder = Arc::new(Recorder::default());
+        set_metrics_sink(recorder.clone());
+
+        ContainerBackend::new(&container, "pw").sign_solana(&crate::crypto::test_transaction(&[10u8; 32])).unwrap();
+        assert!(ContainerBackend::new(&container, "wrong").sign_evm_hash(&[1u8; 32]).is_err());
+
+        let seen = recorder.0.lock().unwrap().clone();
+        let has = |entry: &str| seen.iter().any(|seen| seen == entry);
+        assert!(has(r#"coldstar_sign_operations_total[("chain", "solana"), ("outcome", "ok")]"#), "{:?}", seen);
+        assert!(has(r#"coldstar_sign_operations_total[("chain", "evm"), ("outcome", "DecryptionFailed")]"#));
+        assert!(has("coldstar_decryption_failures_total[]"));
+        assert!(has(r#"coldstar_kdf_duration_seconds[("kdf", "argon2id")]"#));
+        assert!(has(r#"coldstar_sign_duration_seconds[("chain", "solana")]"#));
+        clear_metrics_sink();
+    }
+}
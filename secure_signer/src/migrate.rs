@@ -0,0 +1,271 @@
+//! Upgrading containers to a newer format
+//!
+//! [`EncryptedKeyContainer::migrate`] decrypts a container once, into
+//! locked memory, and re-encrypts it under the same passphrase with
+//! whatever has to change:
+//!
+//! - the format version (version 1 and 2 containers become version 3,
+//!   which authenticates its metadata)
+//! - the KDF: imported scrypt and PBKDF2 containers move to Argon2id, and
+//!   Argon2id parameters below [`KdfParams::DEFAULT`] are raised to it
+//! - the cipher, when [`MigrationOptions::cipher`] asks for another one
+//!
+//! A dry run decrypts too, so it proves the passphrase is right, but
+//! returns only the [`MigrationReport`]. Migrating never downgrades the
+//! version; the passphrase is changed with
+//! [`rekey`](EncryptedKeyContainer::rekey).
+
+use ed25519_dalek::SigningKey;
+use serde::Serialize;
+
+use crate::crypto::{
+    Cipher, EncryptedKeyContainer, Sealing, CONTAINER_VERSION, CONTAINER_VERSION_V2, ED25519_SEED_SIZE,
+};
+use crate::error::SignerError;
+use crate::kdf::{Kdf, KdfParams};
+
+/// A container format version that migrations can produce
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ContainerVersion {
+    /// Cipher and KDF fields, nothing authenticated but the key
+    V2,
+    /// Metadata authenticated as associated data
+    V3,
+}
+
+impl ContainerVersion {
+    /// The version new containers are written in
+    pub const LATEST: ContainerVersion = ContainerVersion::V3;
+
+    /// The number stored in the container's `version` field
+    pub fn number(self) -> u8 {
+        match self {
+            ContainerVersion::V2 => CONTAINER_VERSION_V2,
+            ContainerVersion::V3 => CONTAINER_VERSION,
+        }
+    }
+}
+
+impl TryFrom<u8> for ContainerVersion {
+    type Error = SignerError;
+
+    fn try_from(version: u8) -> Result<Self, Self::Error> {
+        match version {
+            CONTAINER_VERSION_V2 => Ok(ContainerVersion::V2),
+            CONTAINER_VERSION => Ok(ContainerVersion::V3),
+            v => Err(SignerError::ContainerError(format!("cannot migrate to container version {}", v))),
+        }
+    }
+}
+
+/// Changes a migration makes besides the version
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MigrationOptions {
+    /// Re-encrypt with this cipher (default: keep the current one)
+    pub cipher: Option<Cipher>,
+    /// Argon2id parameters to use (default: the current ones, raised to at
+    /// least [`KdfParams::DEFAULT`])
+    pub kdf: Option<KdfParams>,
+    /// Check the passphrase and report, without re-encrypting
+    pub dry_run: bool,
+}
+
+/// The format fields a migration can change
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct ContainerFormat {
+    pub version: u8,
+    pub cipher: Cipher,
+    pub kdf: Kdf,
+}
+
+/// Outcome of [`EncryptedKeyContainer::migrate_with`]
+#[derive(Clone, Serialize)]
+pub struct MigrationReport {
+    /// The container's format before
+    pub from: ContainerFormat,
+    /// Its format after (or, on a dry run, what it would be)
+    pub to: ContainerFormat,
+    /// The migrated container; `None` on a dry run or when nothing changes
+    #[serde(skip)]
+    pub container: Option<EncryptedKeyContainer>,
+}
+
+impl MigrationReport {
+    /// Whether the migration changes anything
+    pub fn changed(&self) -> bool {
+        self.from != self.to
+    }
+}
+
+impl EncryptedKeyContainer {
+    fn format(&self) -> ContainerFormat {
+        ContainerFormat {
+            version: self.version,
+            cipher: self.cipher,
+            kdf: self.kdf,
+        }
+    }
+
+    /// Upgrade the container to `target` with the default
+    /// [`MigrationOptions`]
+    ///
+    /// Returns a copy of the container when it is already up to date.
+    pub fn migrate(&self, passphrase: &str, target: ContainerVersion) -> Result<Self, SignerError> {
+        let report = self.migrate_with(passphrase, target, &MigrationOptions::default())?;
+        Ok(report.container.unwrap_or_else(|| self.clone()))
+    }
+
+    /// Upgrade the container to `target`, reporting what changes
+    ///
+    /// A migration that changes anything gives the container a fresh salt,
+    /// and so a new [`container_id`](EncryptedKeyContainer::container_id).
+    /// Fails if `target` is older than the container, or if the container's
+    /// public key does not belong to its key (version 1 and 2 containers do
+    /// not authenticate it, and version 3 would).
+    pub fn migrate_with(
+        &self,
+        passphrase: &str,
+        target: ContainerVersion,
+        options: &MigrationOptions,
+    ) -> Result<MigrationReport, SignerError> {
+        if self.version > target.number() {
+            return Err(SignerError::ContainerError(format!(
+                "cannot downgrade a version {} container to version {}",
+                self.version,
+                target.number()
+            )));
+        }
+        let kdf = match options.kdf {
+            Some(kdf) => kdf,
+            None => match self.kdf {
+                Kdf::Argon2id(current) => KdfParams {
+                    memory_cost: current.memory_cost.max(KdfParams::DEFAULT.memory_cost),
+                    time_cost: current.time_cost.max(KdfParams::DEFAULT.time_cost),
+                    parallelism: current.parallelism.max(KdfParams::DEFAULT.parallelism),
+                },
+                Kdf::Scrypt(_) | Kdf::Pbkdf2Sha256(_) => KdfParams::DEFAULT,
+            },
+        };
+        kdf.check_minimum()?;
+
+        let from = self.format();
+        let to = ContainerFormat {
+            version: target.number(),
+            cipher: options.cipher.unwrap_or(self.cipher),
+            kdf: Kdf::Argon2id(kdf),
+        };
+
+        let mut key = self.decrypt_key(passphrase)?;
+        let result = self.check_public_key(key.as_slice()).and_then(|()| {
+            if options.dry_run || from == to {
+                return Ok(None);
+            }
+            let sealing = Sealing {
+                version: to.version,
+                with_public_key: self.public_key.is_some(),
+                validity: self.validity(),
+                ..Sealing::new(to.cipher, kdf)
+            };
+            Self::seal(key.as_slice(), passphrase, &sealing).map(Some)
+        });
+        key.zeroize();
+
+        Ok(MigrationReport {
+            from,
+            to,
+            container: result?,
+        })
+    }
+
+    /// Make sure the stored public key, if any, is the key's own
+    fn check_public_key(&self, key: &[u8]) -> Result<(), SignerError> {
+        let Some(public_key) = &self.public_key else {
+            return Ok(());
+        };
+        let seed: &[u8; ED25519_SEED_SIZE] = key
+            .get(..ED25519_SEED_SIZE)
+            .and_then(|seed| seed.try_into().ok())
+            .ok_or(SignerError::InvalidKeyFormat(key.len()))?;
+        let derived = bs58::encode(SigningKey::from_bytes(seed).verifying_key().as_bytes()).into_string();
+        if derived != *public_key {
+            return Err(SignerError::ContainerError(
+                "public key does not match the encrypted key".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kdf::ScryptParams;
+
+    /// A version 1 container as older releases wrote it
+    fn v1_container(seed: &[u8; 32], passphrase: &str) -> EncryptedKeyContainer {
+        let kdf = Kdf::Scrypt(ScryptParams { log_n: 10, r: 8, p: 1 });
+        let salt = [5u8; 32];
+        let nonce = [6u8; 12];
+        let key = kdf.derive(passphrase.as_bytes(), &salt).unwrap();
+        let ciphertext = Cipher::Aes256Gcm.encrypt(key.as_slice(), &nonce, seed).unwrap();
+        EncryptedKeyContainer {
+            version: 1,
+            cipher: Cipher::Aes256Gcm,
+            kdf,
+            salt: base64::Engine::encode(&base64::engine::general_purpose::STANDARD, salt),
+            nonce: base64::Engine::encode(&base64::engine::general_purpose::STANDARD, nonce),
+            ciphertext: base64::Engine::encode(&base64::engine::general_purpose::STANDARD, ciphertext),
+            public_key: Some(bs58::encode(SigningKey::from_bytes(seed).verifying_key().as_bytes()).into_string()),
+            keyfile: false,
+            not_before: None,
+            not_after: None,
+            duress: None,
+        }
+    }
+
+    #[test]
+    fn test_migrate_v1_to_latest() {
+        std::env::set_var("SIGNER_ALLOW_INSECURE_MEMORY", "1");
+        let old = v1_container(&[8u8; 32], "pw");
+        let options = MigrationOptions {
+            cipher: Some(Cipher::XChaCha20Poly1305),
+            kdf: Some(KdfParams::MINIMUM),
+            dry_run: true,
+        };
+
+        // A dry run checks the passphrase and reports, but writes nothing
+        assert!(old.migrate_with("wrong", ContainerVersion::LATEST, &options).is_err());
+        let report = old.migrate_with("pw", ContainerVersion::LATEST, &options).unwrap();
+        assert!(report.changed() && report.container.is_none());
+        assert_eq!(report.to.version, 3);
+        assert_eq!(report.to.kdf, Kdf::Argon2id(KdfParams::MINIMUM));
+
+        let migrated = old
+            .migrate_with("pw", ContainerVersion::LATEST, &MigrationOptions { dry_run: false, ..options })
+            .unwrap()
+            .container
+            .unwrap();
+        assert_eq!(migrated.format(), report.to);
+        assert_eq!(migrated.public_key, old.public_key);
+        assert_eq!(*migrated.decrypt_key("pw").unwrap(), [8u8; 32]);
+
+        // Up to date: nothing changes; older targets are refused
+        let again = migrated.migrate_with("pw", ContainerVersion::V3, &options).unwrap();
+        assert!(!again.changed());
+        assert!(matches!(
+            migrated.migrate_with("pw", ContainerVersion::V2, &options),
+            Err(SignerError::ContainerError(_))
+        ));
+    }
+
+    #[test]
+    fn test_migrate_refuses_mislabeled_public_key() {
+        std::env::set_var("SIGNER_ALLOW_INSECURE_MEMORY", "1");
+        let mut old = v1_container(&[8u8; 32], "pw");
+        old.public_key = Some(bs58::encode([1u8; 32]).into_string());
+        assert!(matches!(
+            old.migrate("pw", ContainerVersion::LATEST),
+            Err(SignerError::ContainerError(_))
+        ));
+    }
+}
@@ -0,0 +1,535 @@
+//! MuSig2 multi-signatures (BIP-327)
+//!
+//! Several secp256k1 keys jointly produce one BIP-340 Schnorr signature
+//! that verifies under a single aggregate x-only public key, in two rounds:
+//!
+//! 1. Every signer calls [`nonce_gen`], keeps the [`SecretNonce`], and
+//!    sends the [`PublicNonce`] to the others (or to a coordinator), which
+//!    combine them with [`AggregateNonce::aggregate`].
+//! 2. Every signer calls [`MusigSigner::partial_sign`] and sends the
+//!    [`PartialSignature`]; [`aggregate_partial_signatures`] produces the
+//!    final 64-byte signature.
+//!
+//! Keys are 33-byte compressed public keys, aggregated in the order given
+//! (callers that want order independence should sort them first). Tweaks
+//! (Taproot key-path spends with a script tree) are not supported.
+//!
+//! # Nonce Reuse
+//!
+//! Signing twice with the same secret nonce leaks the private key.
+//! [`SecretNonce`] is neither `Clone` nor serializable, and
+//! [`MusigSigner::partial_sign`] consumes it.
+
+use k256::elliptic_curve::ops::Reduce;
+use k256::elliptic_curve::point::AffineCoordinates;
+use k256::elliptic_curve::sec1::ToEncodedPoint;
+use k256::elliptic_curve::PrimeField;
+use k256::{AffinePoint, ProjectivePoint, PublicKey, Scalar, U256};
+use sha2::{Digest, Sha256};
+use zeroize::Zeroize;
+
+use crate::crypto::EncryptedKeyContainer;
+use crate::entropy::fill_random;
+use crate::error::SignerError;
+use crate::secure_buffer::SecureBuffer;
+
+/// Size of a compressed secp256k1 public key
+pub const PUBLIC_KEY_SIZE: usize = 33;
+
+/// Size of a public or aggregate nonce (two compressed points)
+pub const NONCE_SIZE: usize = 66;
+
+/// BIP-340 tagged hash, reduced modulo the curve order
+fn tagged_scalar(tag: &str, parts: &[&[u8]]) -> Scalar {
+    <Scalar as Reduce<U256>>::reduce_bytes(&tagged_hash(tag, parts).into())
+}
+
+fn tagged_hash(tag: &str, parts: &[&[u8]]) -> [u8; 32] {
+    let tag_hash = Sha256::digest(tag.as_bytes());
+    let mut hasher = Sha256::new();
+    hasher.update(tag_hash);
+    hasher.update(tag_hash);
+    for part in parts {
+        hasher.update(part);
+    }
+    hasher.finalize().into()
+}
+
+fn decode_point(bytes: &[u8]) -> Result<ProjectivePoint, SignerError> {
+    PublicKey::from_sec1_bytes(bytes)
+        .map(|key| key.to_projective())
+        .map_err(|_| SignerError::SigningFailed("invalid secp256k1 point".to_string()))
+}
+
+/// Decode a point that may be the point at infinity (33 zero bytes)
+fn decode_point_ext(bytes: &[u8]) -> Result<ProjectivePoint, SignerError> {
+    if bytes.iter().all(|b| *b == 0) {
+        Ok(ProjectivePoint::IDENTITY)
+    } else {
+        decode_point(bytes)
+    }
+}
+
+fn encode_point_ext(point: &ProjectivePoint) -> [u8; PUBLIC_KEY_SIZE] {
+    let mut out = [0u8; PUBLIC_KEY_SIZE];
+    if *point != ProjectivePoint::IDENTITY {
+        out.copy_from_slice(point.to_affine().to_encoded_point(true).as_bytes());
+    }
+    out
+}
+
+fn x_bytes(point: &AffinePoint) -> [u8; 32] {
+    point.x().into()
+}
+
+fn has_even_y(point: &AffinePoint) -> bool {
+    !bool::from(point.y_is_odd())
+}
+
+fn secret_scalar(secure_key: &SecureBuffer) -> Result<Scalar, SignerError> {
+    let bytes: [u8; 32] = secure_key
+        .as_slice()
+        .try_into()
+        .map_err(|_| SignerError::InvalidKeyFormat(secure_key.len()))?;
+    Option::<Scalar>::from(Scalar::from_repr(bytes.into()))
+        .filter(|d| !bool::from(d.is_zero()))
+        .ok_or_else(|| SignerError::SigningFailed("Invalid secp256k1 key".to_string()))
+}
+
+/// The aggregate of an ordered list of public keys
+pub struct KeyAggContext {
+    public_keys: Vec<[u8; PUBLIC_KEY_SIZE]>,
+    list_hash: [u8; 32],
+    second_key: Option<[u8; PUBLIC_KEY_SIZE]>,
+    aggregate: AffinePoint,
+}
+
+impl KeyAggContext {
+    /// Aggregate compressed public keys (order matters)
+    pub fn new(public_keys: &[[u8; PUBLIC_KEY_SIZE]]) -> Result<Self, SignerError> {
+        if public_keys.is_empty() {
+            return Err(SignerError::SigningFailed("no public keys to aggregate".to_string()));
+        }
+
+        let list_hash = tagged_hash("KeyAgg list", &public_keys.iter().map(|k| k.as_slice()).collect::<Vec<_>>());
+        let second_key = public_keys.iter().find(|k| **k != public_keys[0]).copied();
+
+        let mut context = Self {
+            public_keys: public_keys.to_vec(),
+            list_hash,
+            second_key,
+            aggregate: AffinePoint::IDENTITY,
+        };
+
+        let mut q = ProjectivePoint::IDENTITY;
+        for key in public_keys {
+            q += decode_point(key)? * context.coefficient(key);
+        }
+        if q == ProjectivePoint::IDENTITY {
+            return Err(SignerError::SigningFailed("aggregate public key is infinite".to_string()));
+        }
+        context.aggregate = q.to_affine();
+        Ok(context)
+    }
+
+    /// The aggregate x-only public key that final signatures verify under
+    pub fn aggregate_public_key(&self) -> [u8; 32] {
+        x_bytes(&self.aggregate)
+    }
+
+    /// The public keys, in aggregation order
+    pub fn public_keys(&self) -> &[[u8; PUBLIC_KEY_SIZE]] {
+        &self.public_keys
+    }
+
+    fn coefficient(&self, public_key: &[u8; PUBLIC_KEY_SIZE]) -> Scalar {
+        if Some(*public_key) == self.second_key {
+            Scalar::ONE
+        } else {
+            tagged_scalar("KeyAgg coefficient", &[&self.list_hash, public_key])
+        }
+    }
+
+    /// -1 if the aggregate key has an odd Y coordinate, else 1
+    fn parity(&self) -> Scalar {
+        if has_even_y(&self.aggregate) {
+            Scalar::ONE
+        } else {
+            -Scalar::ONE
+        }
+    }
+
+    fn contains(&self, public_key: &[u8; PUBLIC_KEY_SIZE]) -> bool {
+        self.public_keys.contains(public_key)
+    }
+}
+
+/// A signer's secret nonce for one signing session
+///
+/// Zeroized on drop.
+pub struct SecretNonce {
+    k1: Scalar,
+    k2: Scalar,
+    public_key: [u8; PUBLIC_KEY_SIZE],
+}
+
+impl SecretNonce {
+    fn public_nonce(&self) -> PublicNonce {
+        let mut bytes = [0u8; NONCE_SIZE];
+        bytes[..PUBLIC_KEY_SIZE].copy_from_slice(&encode_point_ext(&(ProjectivePoint::GENERATOR * self.k1)));
+        bytes[PUBLIC_KEY_SIZE..].copy_from_slice(&encode_point_ext(&(ProjectivePoint::GENERATOR * self.k2)));
+        PublicNonce(bytes)
+    }
+}
+
+impl Drop for SecretNonce {
+    fn drop(&mut self) {
+        self.k1.zeroize();
+        self.k2.zeroize();
+    }
+}
+
+/// A signer's public nonce, sent to the other signers
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PublicNonce([u8; NONCE_SIZE]);
+
+impl PublicNonce {
+    /// Serialize as two compressed points
+    pub fn to_bytes(&self) -> [u8; NONCE_SIZE] {
+        self.0
+    }
+
+    /// Parse a nonce received from another signer
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SignerError> {
+        let bytes: [u8; NONCE_SIZE] = bytes
+            .try_into()
+            .map_err(|_| SignerError::SigningFailed(format!("public nonce must be {} bytes", NONCE_SIZE)))?;
+        decode_point(&bytes[..PUBLIC_KEY_SIZE])?;
+        decode_point(&bytes[PUBLIC_KEY_SIZE..])?;
+        Ok(Self(bytes))
+    }
+
+    fn points(&self) -> (ProjectivePoint, ProjectivePoint) {
+        let r1 = decode_point(&self.0[..PUBLIC_KEY_SIZE]).expect("validated on construction");
+        let r2 = decode_point(&self.0[PUBLIC_KEY_SIZE..]).expect("validated on construction");
+        (r1, r2)
+    }
+}
+
+/// The sum of all signers' public nonces
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AggregateNonce([u8; NONCE_SIZE]);
+
+impl AggregateNonce {
+    /// Combine the public nonces of all signers
+    pub fn aggregate(nonces: &[PublicNonce]) -> Result<Self, SignerError> {
+        if nonces.is_empty() {
+            return Err(SignerError::SigningFailed("no nonces to aggregate".to_string()));
+        }
+
+        let (mut r1, mut r2) = (ProjectivePoint::IDENTITY, ProjectivePoint::IDENTITY);
+        for nonce in nonces {
+            let (n1, n2) = nonce.points();
+            r1 += n1;
+            r2 += n2;
+        }
+
+        let mut bytes = [0u8; NONCE_SIZE];
+        bytes[..PUBLIC_KEY_SIZE].copy_from_slice(&encode_point_ext(&r1));
+        bytes[PUBLIC_KEY_SIZE..].copy_from_slice(&encode_point_ext(&r2));
+        Ok(Self(bytes))
+    }
+
+    /// Serialize as two compressed points (infinity as 33 zero bytes)
+    pub fn to_bytes(&self) -> [u8; NONCE_SIZE] {
+        self.0
+    }
+
+    /// Parse an aggregate nonce received from a coordinator
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SignerError> {
+        let bytes: [u8; NONCE_SIZE] = bytes
+            .try_into()
+            .map_err(|_| SignerError::SigningFailed(format!("aggregate nonce must be {} bytes", NONCE_SIZE)))?;
+        decode_point_ext(&bytes[..PUBLIC_KEY_SIZE])?;
+        decode_point_ext(&bytes[PUBLIC_KEY_SIZE..])?;
+        Ok(Self(bytes))
+    }
+}
+
+/// One signer's share of the final signature
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PartialSignature(Scalar);
+
+impl PartialSignature {
+    /// Serialize as a 32-byte big-endian scalar
+    pub fn to_bytes(&self) -> [u8; 32] {
+        self.0.to_bytes().into()
+    }
+
+    /// Parse a partial signature received from another signer
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SignerError> {
+        let bytes: [u8; 32] = bytes
+            .try_into()
+            .map_err(|_| SignerError::SigningFailed("partial signature must be 32 bytes".to_string()))?;
+        Option::<Scalar>::from(Scalar::from_repr(bytes.into()))
+            .map(Self)
+            .ok_or_else(|| SignerError::SigningFailed("partial signature out of range".to_string()))
+    }
+}
+
+/// Values every signer derives identically for one session
+struct Session {
+    b: Scalar,
+    r: AffinePoint,
+    e: Scalar,
+}
+
+impl Session {
+    fn new(context: &KeyAggContext, aggnonce: &AggregateNonce, message: &[u8]) -> Result<Self, SignerError> {
+        let q = context.aggregate_public_key();
+        let r1 = decode_point_ext(&aggnonce.0[..PUBLIC_KEY_SIZE])?;
+        let r2 = decode_point_ext(&aggnonce.0[PUBLIC_KEY_SIZE..])?;
+
+        let b = tagged_scalar("MuSig/noncecoef", &[&aggnonce.0, &q, message]);
+        let mut r = r1 + r2 * b;
+        if r == ProjectivePoint::IDENTITY {
+            r = ProjectivePoint::GENERATOR;
+        }
+        let r = r.to_affine();
+        let e = tagged_scalar("BIP0340/challenge", &[&x_bytes(&r), &q, message]);
+
+        Ok(Self { b, r, e })
+    }
+}
+
+/// Round 1: generate a nonce pair for signing `message` under `context`
+///
+/// `public_key` is the signer's own compressed key. The nonce is derived
+/// from fresh OS randomness, bound to the key, aggregate key, and message.
+pub fn nonce_gen(
+    public_key: &[u8; PUBLIC_KEY_SIZE],
+    context: &KeyAggContext,
+    message: &[u8],
+) -> Result<(SecretNonce, PublicNonce), SignerError> {
+    let mut rand = [0u8; 32];
+    fill_random(&mut rand)?;
+
+    let aggregate_key = context.aggregate_public_key();
+    let message_len = (message.len() as u64).to_be_bytes();
+    let k = |i: u8| {
+        tagged_scalar(
+            "MuSig/nonce",
+            &[
+                &rand,
+                &[PUBLIC_KEY_SIZE as u8],
+                public_key,
+                &[32],
+                &aggregate_key,
+                &[1],
+                &message_len,
+                message,
+                &0u32.to_be_bytes(),
+                &[i],
+            ],
+        )
+    };
+    let secnonce = SecretNonce {
+        k1: k(0),
+        k2: k(1),
+        public_key: *public_key,
+    };
+    rand.zeroize();
+
+    if bool::from(secnonce.k1.is_zero()) || bool::from(secnonce.k2.is_zero()) {
+        return Err(SignerError::SigningFailed("nonce generation produced zero".to_string()));
+    }
+
+    let pubnonce = secnonce.public_nonce();
+    Ok((secnonce, pubnonce))
+}
+
+/// Sign with a key in a secure buffer, consuming the secret nonce
+pub(crate) fn partial_sign_with_secure_key(
+    secure_key: &SecureBuffer,
+    secnonce: SecretNonce,
+    context: &KeyAggContext,
+    aggnonce: &AggregateNonce,
+    message: &[u8],
+) -> Result<PartialSignature, SignerError> {
+    let d = secret_scalar(secure_key)?;
+    let public_key = encode_point_ext(&(ProjectivePoint::GENERATOR * d));
+    if public_key != secnonce.public_key {
+        return Err(SignerError::SigningFailed("secret nonce belongs to a different key".to_string()));
+    }
+    if !context.contains(&public_key) {
+        return Err(SignerError::SigningFailed("key is not part of the aggregate".to_string()));
+    }
+
+    let session = Session::new(context, aggnonce, message)?;
+    let (k1, k2) = if has_even_y(&session.r) {
+        (secnonce.k1, secnonce.k2)
+    } else {
+        (-secnonce.k1, -secnonce.k2)
+    };
+    let d = context.parity() * d;
+    let s = k1 + session.b * k2 + session.e * context.coefficient(&public_key) * d;
+    let partial = PartialSignature(s);
+
+    // Catch faults before the partial signature leaves this process
+    if !partial_sig_verify(&partial, &secnonce.public_nonce(), &public_key, context, aggnonce, message)? {
+        return Err(SignerError::SigningFailed("partial signature failed self-verification".to_string()));
+    }
+    Ok(partial)
+}
+
+/// Check another signer's partial signature before aggregating
+pub fn partial_sig_verify(
+    partial: &PartialSignature,
+    pubnonce: &PublicNonce,
+    public_key: &[u8; PUBLIC_KEY_SIZE],
+    context: &KeyAggContext,
+    aggnonce: &AggregateNonce,
+    message: &[u8],
+) -> Result<bool, SignerError> {
+    if !context.contains(public_key) {
+        return Ok(false);
+    }
+
+    let session = Session::new(context, aggnonce, message)?;
+    let (r1, r2) = pubnonce.points();
+    let mut re = r1 + r2 * session.b;
+    if !has_even_y(&session.r) {
+        re = -re;
+    }
+
+    let p = decode_point(public_key)?;
+    let expected = re + p * (session.e * context.coefficient(public_key) * context.parity());
+    Ok(ProjectivePoint::GENERATOR * partial.0 == expected)
+}
+
+/// Combine all partial signatures into a BIP-340 signature (R.x || s)
+pub fn aggregate_partial_signatures(
+    context: &KeyAggContext,
+    aggnonce: &AggregateNonce,
+    message: &[u8],
+    partials: &[PartialSignature],
+) -> Result<[u8; 64], SignerError> {
+    let session = Session::new(context, aggnonce, message)?;
+    let s = partials.iter().fold(Scalar::ZERO, |acc, p| acc + p.0);
+
+    let mut signature = [0u8; 64];
+    signature[..32].copy_from_slice(&x_bytes(&session.r));
+    signature[32..].copy_from_slice(&s.to_bytes());
+    Ok(signature)
+}
+
+/// MuSig2 participant backed by an encrypted container
+///
+/// The secp256k1 key is the same one used for EVM signing. It is
+/// decrypted into locked memory for each call and zeroized afterwards.
+pub struct MusigSigner<'a> {
+    container: &'a EncryptedKeyContainer,
+    passphrase: &'a str,
+}
+
+impl<'a> MusigSigner<'a> {
+    /// Create a participant for a container and its passphrase
+    pub fn new(container: &'a EncryptedKeyContainer, passphrase: &'a str) -> Self {
+        Self { container, passphrase }
+    }
+
+    /// The compressed public key to contribute to key aggregation
+    pub fn public_key(&self) -> Result<[u8; PUBLIC_KEY_SIZE], SignerError> {
+        let mut secure_key = self.container.decrypt_key(self.passphrase)?;
+        let d = secret_scalar(&secure_key);
+        secure_key.zeroize();
+        Ok(encode_point_ext(&(ProjectivePoint::GENERATOR * d?)))
+    }
+
+    /// Round 2: produce this signer's partial signature
+    pub fn partial_sign(
+        &self,
+        secnonce: SecretNonce,
+        context: &KeyAggContext,
+        aggnonce: &AggregateNonce,
+        message: &[u8],
+    ) -> Result<PartialSignature, SignerError> {
+        let mut secure_key = self.container.decrypt_key(self.passphrase)?;
+        let result = partial_sign_with_secure_key(&secure_key, secnonce, context, aggnonce, message);
+        secure_key.zeroize();
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::Cipher;
+    use crate::kdf::KdfParams;
+
+    #[test]
+    fn test_three_party_signature_verifies_under_aggregate_key() {
+        std::env::set_var("SIGNER_ALLOW_INSECURE_MEMORY", "1");
+        let containers: Vec<_> = (1u8..=3)
+            .map(|i| {
+                EncryptedKeyContainer::encrypt_with_kdf(&[i; 32], "pw", Cipher::Aes256Gcm, KdfParams::MINIMUM).unwrap()
+            })
+            .collect();
+        let signers: Vec<_> = containers.iter().map(|c| MusigSigner::new(c, "pw")).collect();
+        let keys: Vec<_> = signers.iter().map(|s| s.public_key().unwrap()).collect();
+        let context = KeyAggContext::new(&keys).unwrap();
+        let message = b"taproot sighash";
+
+        let (secnonces, pubnonces): (Vec<_>, Vec<_>) =
+            keys.iter().map(|k| nonce_gen(k, &context, message).unwrap()).unzip();
+        let aggnonce = AggregateNonce::aggregate(&pubnonces).unwrap();
+
+        let partials: Vec<_> = signers
+            .iter()
+            .zip(secnonces)
+            .map(|(signer, secnonce)| signer.partial_sign(secnonce, &context, &aggnonce, message).unwrap())
+            .collect();
+        for (i, partial) in partials.iter().enumerate() {
+            assert!(partial_sig_verify(partial, &pubnonces[i], &keys[i], &context, &aggnonce, message).unwrap());
+        }
+        assert!(!partial_sig_verify(&partials[0], &pubnonces[1], &keys[1], &context, &aggnonce, message).unwrap());
+
+        let signature = aggregate_partial_signatures(&context, &aggnonce, message, &partials).unwrap();
+        let verifying_key = k256::schnorr::VerifyingKey::from_bytes(&context.aggregate_public_key()).unwrap();
+        let signature = k256::schnorr::Signature::try_from(signature.as_slice()).unwrap();
+        assert!(verifying_key.verify_raw(message, &signature).is_ok());
+    }
+
+    #[test]
+    fn test_key_aggregation_matches_bip327_vectors() {
+        let key = |h: &str| <[u8; PUBLIC_KEY_SIZE]>::try_from(hex::decode(h).unwrap()).unwrap();
+        let x1 = key("02F9308A019258C31049344F85F89D5229B531C845836F99B08601F113BCE036F9");
+        let x2 = key("03DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659");
+        let x3 = key("023590A94E768F8E1815C2F24B4D80A8E3149316C3518CE7B7AD338368D038CA66");
+
+        let aggregate = |keys: &[[u8; PUBLIC_KEY_SIZE]]| {
+            hex::encode_upper(KeyAggContext::new(keys).unwrap().aggregate_public_key())
+        };
+        assert_eq!(aggregate(&[x1, x2, x3]), "90539EEDE565F5D054F32CC0C220126889ED1E5D193BAF15AEF344FE59D4610C");
+        assert_eq!(aggregate(&[x3, x2, x1]), "6204DE8B083426DC6EAF9502D27024D53FC826BF7D2012148A0575435DF54B2B");
+        assert_eq!(aggregate(&[x1, x1, x1]), "B436E3BAD62B8CD409969A224731C193D051162D8C5AE8B109306127DA3AA935");
+        assert_eq!(aggregate(&[x1, x1, x2, x2]), "69BC22BFA5D106306E48A20679DE1D7389386124D07571D0D872686028C26A3E");
+    }
+
+    #[test]
+    fn test_secret_nonce_bound_to_its_key() {
+        std::env::set_var("SIGNER_ALLOW_INSECURE_MEMORY", "1");
+        let a = EncryptedKeyContainer::encrypt_with_kdf(&[1u8; 32], "pw", Cipher::Aes256Gcm, KdfParams::MINIMUM).unwrap();
+        let b = EncryptedKeyContainer::encrypt_with_kdf(&[2u8; 32], "pw", Cipher::Aes256Gcm, KdfParams::MINIMUM).unwrap();
+        let (a, b) = (MusigSigner::new(&a, "pw"), MusigSigner::new(&b, "pw"));
+        let keys = [a.public_key().unwrap(), b.public_key().unwrap()];
+        let context = KeyAggContext::new(&keys).unwrap();
+
+        let (a_secnonce, a_pubnonce) = nonce_gen(&keys[0], &context, b"m").unwrap();
+        let (_, b_pubnonce) = nonce_gen(&keys[1], &context, b"m").unwrap();
+        let aggnonce = AggregateNonce::aggregate(&[a_pubnonce, b_pubnonce]).unwrap();
+
+        // Signer b cannot use a's nonce
+        assert!(b.partial_sign(a_secnonce, &context, &aggnonce, b"m").is_err());
+    }
+}
@@ -0,0 +1,121 @@
+//! Async signing for tokio services
+//!
+//! Unlocking a container runs Argon2id, which holds a thread for hundreds
+//! of milliseconds with the default parameters. Called from an async task,
+//! that stalls every other task on the executor thread. The functions here
+//! take the same arguments as their blocking counterparts, copy them (the
+//! passphrase into a zeroizing string), and run the work on tokio's
+//! blocking thread pool.
+//!
+//! Requires the `tokio` feature and a running tokio runtime.
+
+use zeroize::Zeroizing;
+
+use crate::batch::{decrypt_and_sign_batch, decrypt_and_sign_evm_batch, TxRequest};
+use crate::crypto::{
+    create_encrypted_key_container, decrypt_and_sign, decrypt_and_sign_evm, EVMSigningResult, SigningResult,
+};
+use crate::error::SignerError;
+
+/// Run `f` on the blocking pool and wait for it
+pub(crate) async fn spawn_blocking<T, F>(f: F) -> Result<T, SignerError>
+where
+    F: FnOnce() -> Result<T, SignerError> + Send + 'static,
+    T: Send + 'static,
+{
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(|e| SignerError::BackendError(format!("blocking task failed: {}", e)))?
+}
+
+/// [`decrypt_and_sign`](crate::decrypt_and_sign) off the async executor
+pub async fn decrypt_and_sign_async(
+    container_json: &str,
+    passphrase: &str,
+    transaction_bytes: &[u8],
+) -> Result<SigningResult, SignerError> {
+    let container_json = container_json.to_string();
+    let passphrase = Zeroizing::new(passphrase.to_string());
+    let transaction_bytes = transaction_bytes.to_vec();
+    spawn_blocking(move || decrypt_and_sign(&container_json, &passphrase, &transaction_bytes)).await
+}
+
+/// [`decrypt_and_sign_evm`](crate::decrypt_and_sign_evm) off the async executor
+pub async fn decrypt_and_sign_evm_async(
+    container_json: &str,
+    passphrase: &str,
+    message_hash: &[u8],
+) -> Result<EVMSigningResult, SignerError> {
+    let container_json = container_json.to_string();
+    let passphrase = Zeroizing::new(passphrase.to_string());
+    let message_hash = message_hash.to_vec();
+    spawn_blocking(move || decrypt_and_sign_evm(&container_json, &passphrase, &message_hash)).await
+}
+
+/// [`decrypt_and_sign_batch`](crate::decrypt_and_sign_batch) off the async executor
+pub async fn decrypt_and_sign_batch_async(
+    container_json: &str,
+    passphrase: &str,
+    requests: &[TxRequest],
+) -> Result<Vec<SigningResult>, SignerError> {
+    let container_json = container_json.to_string();
+    let passphrase = Zeroizing::new(passphrase.to_string());
+    let requests = requests.to_vec();
+    spawn_blocking(move || decrypt_and_sign_batch(&container_json, &passphrase, &requests)).await
+}
+
+/// [`decrypt_and_sign_evm_batch`](crate::decrypt_and_sign_evm_batch) off the async executor
+pub async fn decrypt_and_sign_evm_batch_async(
+    container_json: &str,
+    passphrase: &str,
+    requests: &[TxRequest],
+) -> Result<Vec<EVMSigningResult>, SignerError> {
+    let container_json = container_json.to_string();
+    let passphrase = Zeroizing::new(passphrase.to_string());
+    let requests = requests.to_vec();
+    spawn_blocking(move || decrypt_and_sign_evm_batch(&container_json, &passphrase, &requests)).await
+}
+
+/// [`create_encrypted_key_container`](crate::create_encrypted_key_container) off the async executor
+pub async fn create_encrypted_key_container_async(
+    private_key: &[u8],
+    passphrase: &str,
+) -> Result<String, SignerError> {
+    let private_key = Zeroizing::new(private_key.to_vec());
+    let passphrase = Zeroizing::new(passphrase.to_string());
+    spawn_blocking(move || create_encrypted_key_container(&private_key, &passphrase)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::{Cipher, EncryptedKeyContainer};
+    use crate::kdf::KdfParams;
+    use crate::session::{SessionConfig, SigningSession};
+
+    #[test]
+    fn test_async_matches_blocking() {
+        std::env::set_var("SIGNER_ALLOW_INSECURE_MEMORY", "1");
+        let container =
+            EncryptedKeyContainer::encrypt_with_kdf(&[7u8; 32], "pw", Cipher::Aes256Gcm, KdfParams::MINIMUM).unwrap();
+        let json = container.to_json().unwrap();
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        let message = crate::crypto::test_transaction(&[7u8; 32]);
+
+        runtime.block_on(async {
+            let result = decrypt_and_sign_async(&json, "pw", &message).await.unwrap();
+            assert_eq!(result.signature, decrypt_and_sign(&json, "pw", &message).unwrap().signature);
+            assert!(decrypt_and_sign_async(&json, "wrong", &message).await.is_err());
+
+            let evm = decrypt_and_sign_evm_async(&json, "pw", &[1u8; 32]).await.unwrap();
+            assert_eq!(evm.signature, decrypt_and_sign_evm(&json, "pw", &[1u8; 32]).unwrap().signature);
+
+            let requests = [TxRequest::new(message.clone())];
+            let batch = decrypt_and_sign_batch_async(&json, "pw", &requests).await.unwrap();
+            assert_eq!(batch[0].signature, result.signature);
+
+            let session = SigningSession::unlock_async(&container, "pw", SessionConfig::default()).await.unwrap();
+            assert_eq!(session.sign(&message).unwrap().signature, result.signature);
+        });
+    }
+}
@@ -0,0 +1,320 @@
+//! PKCS#11 / HSM backend
+//!
+//! Implements [`SignerBackend`] against a key pair held in a PKCS#11 token.
+//! Key generation and signing (`CKM_EDDSA` for Solana, `CKM_ECDSA` for EVM)
+//! happen inside the HSM; private keys are created sensitive and
+//! non-extractable. This module only formats requests and converts results
+//! into [`SigningResult`] / [`EVMSigningResult`].
+//!
+//! Keys are addressed by `CKA_LABEL`; the private and public halves of a
+//! pair share the label.
+//!
+//! ```no_run
+//! use coldstar_secure_signer::pkcs11::Pkcs11Backend;
+//! use coldstar_secure_signer::SignerBackend;
+//!
+//! let backend = Pkcs11Backend::open("/usr/lib/softhsm/libsofthsm2.so", 0, "1234")?
+//!     .with_ed25519_key("treasury-sol");
+//! # let message = Vec::new();
+//! let result = backend.sign_solana(&message)?;
+//! # Ok::<(), coldstar_secure_signer::error::SignerError>(())
+//! ```
+
+use cryptoki::context::{CInitializeArgs, Pkcs11};
+use cryptoki::mechanism::eddsa::{EddsaParams, EddsaSignatureScheme};
+use cryptoki::mechanism::Mechanism;
+use cryptoki::object::{Attribute, AttributeType, KeyType, ObjectClass, ObjectHandle};
+use cryptoki::session::{Session, UserType};
+use cryptoki::types::AuthPin;
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use k256::ecdsa::{RecoveryId, Signature as K256Signature, VerifyingKey as K256VerifyingKey};
+
+use crate::backend::SignerBackend;
+use crate::crypto::{
+    assemble_signed_transaction, check_solana_message, evm_address_from_pubkey, EVMSigningResult, SigningResult,
+};
+use crate::error::SignerError;
+
+/// DER-encoded OID 1.3.101.112 (Ed25519)
+const ED25519_PARAMS: [u8; 5] = [0x06, 0x03, 0x2b, 0x65, 0x70];
+/// DER-encoded OID 1.3.132.0.10 (secp256k1)
+const SECP256K1_PARAMS: [u8; 7] = [0x06, 0x05, 0x2b, 0x81, 0x04, 0x00, 0x0a];
+
+/// Backend for keys stored in a PKCS#11 token
+pub struct Pkcs11Backend {
+    session: Session,
+    ed25519_label: Option<String>,
+    secp256k1_label: Option<String>,
+}
+
+impl Pkcs11Backend {
+    /// Load a PKCS#11 module, open a session on the `slot_index`-th slot
+    /// with a token and log in as the user
+    pub fn open(module_path: &str, slot_index: usize, pin: &str) -> Result<Self, SignerError> {
+        let context = Pkcs11::new(module_path).map_err(hsm_error)?;
+        context.initialize(CInitializeArgs::OsThreads).map_err(hsm_error)?;
+
+        let slot = *context
+            .get_slots_with_token()
+            .map_err(hsm_error)?
+            .get(slot_index)
+            .ok_or_else(|| SignerError::BackendError(format!("no token in slot {}", slot_index)))?;
+
+        let session = context.open_rw_session(slot).map_err(hsm_error)?;
+        session
+            .login(UserType::User, Some(&AuthPin::new(pin.to_string())))
+            .map_err(hsm_error)?;
+
+        Ok(Self {
+            session,
+            ed25519_label: None,
+            secp256k1_label: None,
+        })
+    }
+
+    /// Use the Ed25519 key pair with this label for Solana signing
+    pub fn with_ed25519_key(mut self, label: &str) -> Self {
+        self.ed25519_label = Some(label.to_string());
+        self
+    }
+
+    /// Use the secp256k1 key pair with this label for EVM signing
+    pub fn with_secp256k1_key(mut self, label: &str) -> Self {
+        self.secp256k1_label = Some(label.to_string());
+        self
+    }
+
+    /// Generate an Ed25519 key pair inside the token, returning the public key
+    pub fn generate_ed25519_key(&self, label: &str) -> Result<[u8; 32], SignerError> {
+        self.generate_key_pair(Mechanism::EccEdwardsKeyPairGen, &ED25519_PARAMS, label)?;
+        self.ed25519_public_key_for(label)
+    }
+
+    /// Generate a secp256k1 key pair inside the token, returning its EVM address
+    pub fn generate_secp256k1_key(&self, label: &str) -> Result<String, SignerError> {
+        self.generate_key_pair(Mechanism::EccKeyPairGen, &SECP256K1_PARAMS, label)?;
+        Ok(evm_address_from_pubkey(&self.secp256k1_public_key_for(label)?))
+    }
+
+    /// Public key of the configured Ed25519 key
+    pub fn solana_public_key(&self) -> Result<[u8; 32], SignerError> {
+        self.ed25519_public_key_for(required(&self.ed25519_label, "Ed25519")?)
+    }
+
+    /// Public key and EVM address of the configured secp256k1 key
+    pub fn evm_public_key(&self) -> Result<(K256VerifyingKey, String), SignerError> {
+        let key = self.secp256k1_public_key_for(required(&self.secp256k1_label, "secp256k1")?)?;
+        let address = evm_address_from_pubkey(&key);
+        Ok((key, address))
+    }
+
+    fn generate_key_pair(&self, mechanism: Mechanism, params: &[u8], label: &str) -> Result<(), SignerError> {
+        let public_template = [
+            Attribute::Token(true),
+            Attribute::Verify(true),
+            Attribute::EcParams(params.to_vec()),
+            Attribute::Label(label.as_bytes().to_vec()),
+        ];
+        let private_template = [
+            Attribute::Token(true),
+            Attribute::Private(true),
+            Attribute::Sensitive(true),
+            Attribute::Extractable(false),
+            Attribute::Sign(true),
+            Attribute::Label(label.as_bytes().to_vec()),
+        ];
+        self.session
+            .generate_key_pair(&mechanism, &public_template, &private_template)
+            .map_err(hsm_error)?;
+        Ok(())
+    }
+
+    fn find_key(&self, class: ObjectClass, key_type: KeyType, label: &str) -> Result<ObjectHandle, SignerError> {
+        let template = [
+            Attribute::Class(class),
+            Attribute::KeyType(key_type),
+            Attribute::Label(label.as_bytes().to_vec()),
+        ];
+        let mut handles = self.session.find_objects(&template).map_err(hsm_error)?;
+        match handles.len() {
+            1 => Ok(handles.remove(0)),
+            0 => Err(SignerError::BackendError(format!("no key labelled '{}'", label))),
+            n => Err(SignerError::BackendError(format!("{} keys labelled '{}'", n, label))),
+        }
+    }
+
+    fn ec_point(&self, key_type: KeyType, label: &str) -> Result<Vec<u8>, SignerError> {
+        let handle = self.find_key(ObjectClass::PUBLIC_KEY, key_type, label)?;
+        let attributes = self
+            .session
+            .get_attributes(handle, &[AttributeType::EcPoint])
+            .map_err(hsm_error)?;
+        attributes
+            .into_iter()
+            .find_map(|a| match a {
+                Attribute::EcPoint(point) => Some(point),
+                _ => None,
+            })
+            .ok_or_else(|| SignerError::BackendError("public key has no CKA_EC_POINT".to_string()))
+    }
+
+    fn ed25519_public_key_for(&self, label: &str) -> Result<[u8; 32], SignerError> {
+        let point = self.ec_point(KeyType::EC_EDWARDS, label)?;
+        parse_ec_point(&point, 32)?
+            .try_into()
+            .map_err(|_| SignerError::BackendError("invalid Ed25519 public key".to_string()))
+    }
+
+    fn secp256k1_public_key_for(&self, label: &str) -> Result<K256VerifyingKey, SignerError> {
+        let point = self.ec_point(KeyType::EC, label)?;
+        K256VerifyingKey::from_sec1_bytes(parse_ec_point(&point, 65)?)
+            .map_err(|_| SignerError::BackendError("invalid secp256k1 public key".to_string()))
+    }
+}
+
+impl SignerBackend for Pkcs11Backend {
+    fn sign_solana(&self, message: &[u8]) -> Result<SigningResult, SignerError> {
+        let label = required(&self.ed25519_label, "Ed25519")?;
+        let public_key = self.ed25519_public_key_for(label)?;
+        check_solana_message(message, &public_key)?;
+        let key = self.find_key(ObjectClass::PRIVATE_KEY, KeyType::EC_EDWARDS, label)?;
+
+        let mechanism = Mechanism::Eddsa(EddsaParams::new(EddsaSignatureScheme::Pure));
+        let signature: [u8; 64] = self
+            .session
+            .sign(&mechanism, key, message)
+            .map_err(hsm_error)?
+            .try_into()
+            .map_err(|_| SignerError::BackendError("unexpected signature length".to_string()))?;
+
+        // Check the token signed what we sent with the key we expect
+        VerifyingKey::from_bytes(&public_key)
+            .and_then(|key| key.verify(message, &Signature::from_bytes(&signature)))
+            .map_err(|_| SignerError::BackendError("token returned an invalid signature".to_string()))?;
+
+        Ok(SigningResult {
+            signature: bs58::encode(signature).into_string(),
+            signed_transaction: assemble_signed_transaction(&signature, message),
+            public_key: bs58::encode(public_key).into_string(),
+            counter: None,
+        })
+    }
+
+    fn sign_evm_hash(&self, message_hash: &[u8]) -> Result<EVMSigningResult, SignerError> {
+        if message_hash.len() != 32 {
+            return Err(SignerError::InvalidTransaction(format!(
+                "EVM message hash must be 32 bytes, got {}",
+                message_hash.len()
+            )));
+        }
+        let label = required(&self.secp256k1_label, "secp256k1")?;
+        let (public_key, address) = self.evm_public_key()?;
+        let key = self.find_key(ObjectClass::PRIVATE_KEY, KeyType::EC, label)?;
+
+        // CKM_ECDSA signs the hash as given and returns raw r || s
+        let raw = self.session.sign(&Mechanism::Ecdsa, key, message_hash).map_err(hsm_error)?;
+        let (signature, recovery_id) = normalize_and_recover(&raw, message_hash, &public_key)?;
+
+        let v = recovery_id.to_byte() + 27;
+        let mut sig_bytes = signature.to_bytes().to_vec();
+        sig_bytes.push(v);
+
+        Ok(EVMSigningResult {
+            signature: format!("0x{}", hex::encode(&sig_bytes)),
+            address,
+            v,
+            counter: None,
+        })
+    }
+}
+
+fn hsm_error(e: cryptoki::error::Error) -> SignerError {
+    SignerError::BackendError(format!("PKCS#11: {}", e))
+}
+
+fn required<'a>(label: &'a Option<String>, curve: &str) -> Result<&'a str, SignerError> {
+    label
+        .as_deref()
+        .ok_or_else(|| SignerError::BackendError(format!("no {} key configured", curve)))
+}
+
+/// Extract a public key from `CKA_EC_POINT`
+///
+/// The spec requires a DER OCTET STRING, but some tokens return the raw
+/// point; both are accepted.
+fn parse_ec_point(point: &[u8], len: usize) -> Result<&[u8], SignerError> {
+    match point {
+        [0x04, l, rest @ ..] if *l as usize == len && rest.len() == len => Ok(rest),
+        raw if raw.len() == len => Ok(raw),
+        _ => Err(SignerError::BackendError("unexpected CKA_EC_POINT encoding".to_string())),
+    }
+}
+
+/// Normalize an HSM signature to low-s and find its recovery id
+///
+/// HSMs do not enforce low-s (required by Ethereum) and return no recovery
+/// id, so it is found by recovering against the token's public key.
+fn normalize_and_recover(
+    raw: &[u8],
+    hash: &[u8],
+    public_key: &K256VerifyingKey,
+) -> Result<(K256Signature, RecoveryId), SignerError> {
+    let signature = K256Signature::from_slice(raw)
+        .map_err(|e| SignerError::BackendError(format!("invalid signature: {}", e)))?;
+    let signature = signature.normalize_s().unwrap_or(signature);
+
+    let recovery_id = (0..2u8)
+        .filter_map(RecoveryId::from_byte)
+        .find(|id| {
+            K256VerifyingKey::recover_from_prehash(hash, &signature, *id)
+                .map(|key| key == *public_key)
+                .unwrap_or(false)
+        })
+        .ok_or_else(|| SignerError::BackendError("token returned an invalid signature".to_string()))?;
+
+    Ok((signature, recovery_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k256::ecdsa::SigningKey;
+    use k256::elliptic_curve::scalar::IsHigh;
+
+    #[test]
+    fn test_parse_ec_point_encodings() {
+        let raw = [7u8; 32];
+        let mut der = vec![0x04, 32];
+        der.extend_from_slice(&raw);
+
+        assert_eq!(parse_ec_point(&der, 32).unwrap(), raw);
+        assert_eq!(parse_ec_point(&raw, 32).unwrap(), raw);
+        assert!(parse_ec_point(&der[..20], 32).is_err());
+
+        // An uncompressed secp256k1 point starts with 0x04 too
+        let key = SigningKey::from_slice(&[1u8; 32]).unwrap();
+        let point = key.verifying_key().to_encoded_point(false);
+        assert_eq!(parse_ec_point(point.as_bytes(), 65).unwrap(), point.as_bytes());
+    }
+
+    #[test]
+    fn test_high_s_signature_is_normalized_and_recovered() {
+        let key = SigningKey::from_slice(&[3u8; 32]).unwrap();
+        let hash = [0x5au8; 32];
+        let (signature, _) = key.sign_prehash_recoverable(&hash).unwrap();
+
+        // Flip to the high-s form an HSM might return
+        let high = K256Signature::from_scalars(signature.r(), -*signature.s()).unwrap();
+        assert!(bool::from(high.s().is_high()));
+
+        let (normalized, id) = normalize_and_recover(&high.to_bytes(), &hash, key.verifying_key()).unwrap();
+        assert_eq!(normalized, signature);
+        assert_eq!(
+            K256VerifyingKey::recover_from_prehash(&hash, &normalized, id).unwrap(),
+            *key.verifying_key()
+        );
+
+        let other = SigningKey::from_slice(&[4u8; 32]).unwrap();
+        assert!(normalize_and_recover(&high.to_bytes(), &hash, other.verifying_key()).is_err());
+    }
+}
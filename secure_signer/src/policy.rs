@@ -0,0 +1,294 @@
+//! Signing policy rules
+//!
+//! A [`Policy`] is a list of rules checked before a payload is signed. Any
+//! violated rule rejects the request with [`SignerError::PolicyViolation`].
+//! Policies are plain JSON so they can live in configuration, and may be
+//! scoped to specific containers by container ID:
+//!
+//! ```json
+//! {"containers": ["<container id>"], "rules": [
+//!   {"rule": "forbid_approval_for_all", "allowed_operators": ["<marketplace operator address>"]},
+//!   {"rule": "forbid_unlimited_approvals"}
+//! ]}
+//! ```
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::SignerError;
+use crate::metrics::{self, Counter};
+use crate::evm::nft::{decode_nft_call, NftCall};
+use crate::solana::builder::Transfer;
+
+/// A single policy rule
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(tag = "rule", rename_all = "snake_case")]
+pub enum PolicyRule {
+    /// Reject collection-wide `setApprovalForAll(operator, true)` unless the
+    /// operator is listed (e.g. a trusted marketplace)
+    ForbidApprovalForAll {
+        /// Operators that may still be approved
+        #[serde(default)]
+        allowed_operators: Vec<String>,
+    },
+    /// NFT transfers (ERC-721/1155) may only go to these addresses
+    NftRecipientAllowlist {
+        /// Allowed recipients
+        recipients: Vec<String>,
+    },
+    /// Reject `approve` calls with an allowance of 2^255 or more
+    ForbidUnlimitedApprovals,
+    /// Built Solana transfers (SOL or tokens) may only go to these wallets
+    SolanaRecipientAllowlist {
+        /// Allowed recipients (base58)
+        recipients: Vec<String>,
+    },
+    /// Built Solana transactions may send at most this many lamports in
+    /// total
+    MaxLamports {
+        /// Largest total in lamports
+        lamports: u64,
+    },
+}
+
+/// A set of rules applied to signing requests
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Policy {
+    /// Rules, all of which must pass
+    pub rules: Vec<PolicyRule>,
+    /// Container IDs this policy applies to; empty means every container
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub containers: Vec<String>,
+}
+
+impl Policy {
+    /// Create a policy from rules
+    pub fn new(rules: Vec<PolicyRule>) -> Self {
+        Self {
+            rules,
+            containers: Vec::new(),
+        }
+    }
+
+    /// Restrict the policy to the given container IDs
+    pub fn for_containers(mut self, container_ids: Vec<String>) -> Self {
+        self.containers = container_ids;
+        self
+    }
+
+    /// Whether the policy governs the container with this ID
+    pub fn applies_to(&self, container_id: &str) -> bool {
+        self.containers.is_empty() || self.containers.iter().any(|id| id == container_id)
+    }
+
+    /// Parse a policy from JSON
+    pub fn from_json(json: &str) -> Result<Self, SignerError> {
+        serde_json::from_str(json).map_err(|e| SignerError::SerializationError(e.to_string()))
+    }
+
+    /// Check EVM calldata against the rules
+    ///
+    /// Calldata that is not a recognized NFT/approval call passes the
+    /// NFT rules.
+    pub fn check_evm_call(&self, calldata: &[u8]) -> Result<(), SignerError> {
+        counted("evm_call", self.evm_call_violation(calldata))
+    }
+
+    fn evm_call_violation(&self, calldata: &[u8]) -> Result<(), SignerError> {
+        let call = match decode_nft_call(calldata)? {
+            Some(call) => call,
+            None => return Ok(()),
+        };
+
+        for rule in &self.rules {
+            match (rule, &call) {
+                (
+                    PolicyRule::ForbidApprovalForAll { allowed_operators },
+                    NftCall::SetApprovalForAll { operator, approved: true },
+                ) if !contains_address(allowed_operators, operator) => {
+                    return Err(SignerError::PolicyViolation(format!(
+                        "collection-wide approval for {} is forbidden",
+                        operator
+                    )));
+                }
+                (PolicyRule::NftRecipientAllowlist { recipients }, call) => {
+                    if let Some(to) = call.recipient() {
+                        if !contains_address(recipients, to) {
+                            return Err(SignerError::PolicyViolation(format!(
+                                "recipient {} is not on the allowlist",
+                                to
+                            )));
+                        }
+                    }
+                }
+                (PolicyRule::ForbidUnlimitedApprovals, NftCall::Approve { spender, value })
+                    if is_unlimited(value) =>
+                {
+                    return Err(SignerError::PolicyViolation(format!(
+                        "unlimited approval for {} is forbidden",
+                        spender
+                    )));
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether any rule applies to Solana transfers
+    pub fn has_solana_rules(&self) -> bool {
+        self.rules.iter().any(|rule| {
+            matches!(
+                rule,
+                PolicyRule::SolanaRecipientAllowlist { .. } | PolicyRule::MaxLamports { .. }
+            )
+        })
+    }
+
+    /// Check transfers from a
+    /// [`TransferBuilder`](crate::solana::TransferBuilder) against the
+    /// rules, before it builds and signs them
+    pub fn check_solana_transfers(&self, transfers: &[Transfer]) -> Result<(), SignerError> {
+        counted("solana_transfers", self.solana_transfers_violation(transfers))
+    }
+
+    fn solana_transfers_violation(&self, transfers: &[Transfer]) -> Result<(), SignerError> {
+        for rule in &self.rules {
+            match rule {
+                PolicyRule::SolanaRecipientAllowlist { recipients } => {
+                    for transfer in transfers {
+                        let recipient = bs58::encode(transfer.recipient()).into_string();
+                        if !recipients.contains(&recipient) {
+                            return Err(SignerError::PolicyViolation(format!(
+                                "recipient {} is not on the allowlist",
+                                recipient
+                            )));
+                        }
+                    }
+                }
+                PolicyRule::MaxLamports { lamports } => {
+                    let total = transfers
+                        .iter()
+                        .map(|transfer| match transfer {
+                            Transfer::Sol { lamports, .. } => u128::from(*lamports),
+                            Transfer::Token(_) => 0,
+                        })
+                        .sum::<u128>();
+                    if total > u128::from(*lamports) {
+                        return Err(SignerError::PolicyViolation(format!(
+                            "transfers total {} lamports, above the limit of {}",
+                            total, lamports
+                        )));
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Count a rejection in the [`metrics`](crate::metrics)
+fn counted(check: &str, result: Result<(), SignerError>) -> Result<(), SignerError> {
+    if let Err(SignerError::PolicyViolation(_)) = result {
+        metrics::increment(Counter::PolicyRejections, &[("check", check)]);
+    }
+    result
+}
+
+fn contains_address(list: &[String], address: &str) -> bool {
+    list.iter().any(|a| a.eq_ignore_ascii_case(address))
+}
+
+/// `value` (minimal 0x-hex) is at least 2^255
+fn is_unlimited(value: &str) -> bool {
+    value.len() == 66 && value.as_bytes()[2] >= b'8'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evm::nft::{APPROVE, SAFE_TRANSFER_FROM_721, SET_APPROVAL_FOR_ALL};
+
+    fn word(byte: u8, fill: usize) -> Vec<u8> {
+        let mut w = vec![0u8; 32 - fill];
+        w.extend(std::iter::repeat_n(byte, fill));
+        w
+    }
+
+    fn approval_for_all(operator: u8) -> Vec<u8> {
+        let mut calldata = SET_APPROVAL_FOR_ALL.to_vec();
+        calldata.extend(word(operator, 20));
+        calldata.extend(word(1, 1));
+        calldata
+    }
+
+    #[test]
+    fn test_solana_transfer_rules() {
+        use crate::solana::{TokenTransfer, Transfer};
+
+        let policy = Policy::from_json(&format!(
+            r#"{{"rules":[{{"rule":"solana_recipient_allowlist","recipients":["{}"]}},{{"rule":"max_lamports","lamports":1000}}]}}"#,
+            bs58::encode([1u8; 32]).into_string()
+        ))
+        .unwrap();
+        let sol = |to: u8, lamports| Transfer::Sol { to: [to; 32], lamports };
+        let token = Transfer::Token(TokenTransfer::new([9u8; 32], [1u8; 32], u64::MAX, 0));
+
+        assert!(policy.check_solana_transfers(&[sol(1, 600), token]).is_ok());
+        assert!(policy.check_solana_transfers(&[sol(2, 1)]).is_err());
+        assert!(matches!(
+            policy.check_solana_transfers(&[sol(1, 600), sol(1, 600)]),
+            Err(SignerError::PolicyViolation(_))
+        ));
+    }
+
+    #[test]
+    fn test_forbid_approval_for_all() {
+        let policy = Policy::from_json(&format!(
+            r#"{{"rules":[{{"rule":"forbid_approval_for_all","allowed_operators":["0x{}"]}}]}}"#,
+            "AA".repeat(20)
+        ))
+        .unwrap();
+
+        assert!(policy.check_evm_call(&approval_for_all(0xaa)).is_ok());
+        assert!(matches!(
+            policy.check_evm_call(&approval_for_all(0xbb)),
+            Err(SignerError::PolicyViolation(_))
+        ));
+        // Unrelated calldata passes
+        assert!(policy.check_evm_call(&[1, 2, 3, 4]).is_ok());
+    }
+
+    #[test]
+    fn test_recipient_allowlist_and_unlimited_approvals() {
+        let policy = Policy::new(vec![
+            PolicyRule::NftRecipientAllowlist {
+                recipients: vec![format!("0x{}", "11".repeat(20))],
+            },
+            PolicyRule::ForbidUnlimitedApprovals,
+        ]);
+
+        let transfer_to = |to: u8| {
+            let mut calldata = SAFE_TRANSFER_FROM_721.to_vec();
+            calldata.extend(word(0x22, 20));
+            calldata.extend(word(to, 20));
+            calldata.extend(word(5, 1));
+            calldata
+        };
+        assert!(policy.check_evm_call(&transfer_to(0x11)).is_ok());
+        assert!(policy.check_evm_call(&transfer_to(0x33)).is_err());
+
+        let mut approve = APPROVE.to_vec();
+        approve.extend(word(0x44, 20));
+        approve.extend(word(0xff, 32));
+        assert!(policy.check_evm_call(&approve).is_err());
+    }
+
+    #[test]
+    fn test_container_scope() {
+        let policy = Policy::from_json(r#"{"rules":[],"containers":["abc"]}"#).unwrap();
+        assert!(policy.applies_to("abc"));
+        assert!(!policy.applies_to("def"));
+        assert!(Policy::new(vec![]).applies_to("def"));
+    }
+}
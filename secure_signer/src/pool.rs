@@ -0,0 +1,356 @@
+//! Concurrent signing across pre-unlocked sessions
+//!
+//! A [`SigningSession`] serializes its signatures on the key's lock, so a
+//! service signing hundreds of times a second wants several copies of each
+//! key. A [`SignerPool`] holds a fixed number of sessions per key ID; a
+//! signer checks one out, waiting (up to `acquire_timeout`) when all are
+//! busy, and returns it when done. The free list is the semaphore: there
+//! are never more concurrent signers per key than sessions.
+//!
+//! Every session locks at least one page, and `RLIMIT_MEMLOCK` is often
+//! only a few hundred KiB. [`SignerPool::add_key`] checks the remaining
+//! budget before decrypting and fails with
+//! [`SignerError::MemoryLockFailed`] rather than unlocking some sessions
+//! and swapping the rest.
+//!
+//! [`SignerPool::shutdown`] stops new checkouts, waits for signers in
+//! flight, and zeroizes every session.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, RwLock};
+use std::time::{Duration, Instant};
+
+use crate::backend::SignerBackend;
+use crate::crypto::{get_locking_mode, EVMSigningResult, EncryptedKeyContainer, SigningResult};
+use crate::error::SignerError;
+use crate::secure_buffer::{LockingMode, SecureBuffer};
+use crate::session::{SessionConfig, SigningSession};
+
+/// Sizing of a [`SignerPool`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PoolConfig {
+    /// Sessions (and so concurrent signers) per key
+    pub sessions_per_key: usize,
+    /// Lifetime limits of each session
+    pub session: SessionConfig,
+    /// How long a signer waits for a free session; `None` waits forever
+    pub acquire_timeout: Option<Duration>,
+}
+
+impl Default for PoolConfig {
+    /// Four sessions per key, a 30 second wait
+    fn default() -> Self {
+        Self {
+            sessions_per_key: 4,
+            session: SessionConfig::default(),
+            acquire_timeout: Some(Duration::from_secs(30)),
+        }
+    }
+}
+
+/// The sessions of one key and which of them are free
+struct KeySlots {
+    sessions: Vec<SigningSession>,
+    free: Mutex<Vec<usize>>,
+    returned: Condvar,
+}
+
+impl KeySlots {
+    fn free(&self) -> MutexGuard<'_, Vec<usize>> {
+        self.free.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// A checked-out session, returned to the pool on drop
+struct Lease {
+    slots: Arc<KeySlots>,
+    index: usize,
+}
+
+impl Lease {
+    fn session(&self) -> &SigningSession {
+        &self.slots.sessions[self.index]
+    }
+}
+
+impl Drop for Lease {
+    fn drop(&mut self) {
+        self.slots.free().push(self.index);
+        self.slots.returned.notify_all();
+    }
+}
+
+/// Pre-unlocked sessions for several keys, shared between threads
+pub struct SignerPool {
+    config: PoolConfig,
+    keys: RwLock<HashMap<String, Arc<KeySlots>>>,
+    draining: AtomicBool,
+}
+
+impl SignerPool {
+    /// An empty pool
+    pub fn new(config: PoolConfig) -> Self {
+        Self {
+            config,
+            keys: RwLock::new(HashMap::new()),
+            draining: AtomicBool::new(false),
+        }
+    }
+
+    /// Decrypt `container` once and add `sessions_per_key` sessions for it
+    /// under `key_id`
+    pub fn add_key(&self, key_id: &str, container: &EncryptedKeyContainer, passphrase: &str) -> Result<(), SignerError> {
+        self.check_open()?;
+        let count = self.config.sessions_per_key;
+        if count == 0 {
+            return Err(SignerError::BackendError("a pool needs at least one session per key".to_string()));
+        }
+        if self.keys.read().unwrap_or_else(|p| p.into_inner()).contains_key(key_id) {
+            return Err(SignerError::BackendError(format!("key '{}' is already in the pool", key_id)));
+        }
+        let mode = get_locking_mode();
+        if mode != LockingMode::Permissive {
+            check_memlock_budget(count)?;
+        }
+
+        let mut secure_key = container.decrypt_key(passphrase)?;
+        let copies: Result<Vec<SecureBuffer>, SignerError> =
+            (0..count).map(|_| SecureBuffer::from_slice_with_mode(secure_key.as_slice(), mode)).collect();
+        secure_key.zeroize();
+        let sessions = copies?
+            .into_iter()
+            .map(|key| SigningSession::from_key(key, container.public_key.clone(), self.config.session))
+            .collect();
+
+        let slots = Arc::new(KeySlots {
+            sessions,
+            free: Mutex::new((0..count).collect()),
+            returned: Condvar::new(),
+        });
+        let mut keys = self.keys.write().unwrap_or_else(|p| p.into_inner());
+        // Re-checked under the write lock; the loser's sessions zeroize on drop
+        if keys.contains_key(key_id) {
+            return Err(SignerError::BackendError(format!("key '{}' is already in the pool", key_id)));
+        }
+        keys.insert(key_id.to_string(), slots);
+        Ok(())
+    }
+
+    /// IDs of the keys in the pool
+    pub fn key_ids(&self) -> Vec<String> {
+        let mut ids: Vec<String> = self.keys.read().unwrap_or_else(|p| p.into_inner()).keys().cloned().collect();
+        ids.sort();
+        ids
+    }
+
+    /// Sessions of `key_id` not checked out right now
+    pub fn available(&self, key_id: &str) -> Option<usize> {
+        self.slots(key_id).ok().map(|slots| slots.free().len())
+    }
+
+    /// Run `f` with a session of `key_id`, waiting for one if all are busy
+    pub fn with_session<R>(
+        &self,
+        key_id: &str,
+        f: impl FnOnce(&SigningSession) -> Result<R, SignerError>,
+    ) -> Result<R, SignerError> {
+        let lease = self.acquire(key_id)?;
+        f(lease.session())
+    }
+
+    /// Sign a Solana transaction message with `key_id`
+    pub fn sign(&self, key_id: &str, message: &[u8]) -> Result<SigningResult, SignerError> {
+        self.with_session(key_id, |session| session.sign(message))
+    }
+
+    /// Sign a 32-byte EVM hash with `key_id`
+    pub fn sign_evm_hash(&self, key_id: &str, message_hash: &[u8]) -> Result<EVMSigningResult, SignerError> {
+        self.with_session(key_id, |session| session.sign_evm_hash(message_hash))
+    }
+
+    /// Stop checkouts, wait up to `timeout` for signers in flight, and
+    /// zeroize every session
+    ///
+    /// Returns `false` if some signer was still running at the deadline;
+    /// its session is zeroized once that signature completes. Signers
+    /// waiting for a session fail with [`SignerError::SessionLocked`].
+    pub fn shutdown(&self, timeout: Duration) -> bool {
+        self.draining.store(true, Ordering::SeqCst);
+        let drained: Vec<Arc<KeySlots>> =
+            self.keys.write().unwrap_or_else(|p| p.into_inner()).drain().map(|(_, slots)| slots).collect();
+
+        let deadline = Instant::now() + timeout;
+        let mut clean = true;
+        for slots in &drained {
+            let mut free = slots.free();
+            // Wake waiters so they see the drain
+            slots.returned.notify_all();
+            while free.len() < slots.sessions.len() {
+                let now = Instant::now();
+                if now >= deadline {
+                    clean = false;
+                    break;
+                }
+                free = slots
+                    .returned
+                    .wait_timeout(free, deadline - now)
+                    .unwrap_or_else(|p| p.into_inner())
+                    .0;
+            }
+            drop(free);
+            for session in &slots.sessions {
+                session.close();
+            }
+        }
+        clean
+    }
+
+    fn check_open(&self) -> Result<(), SignerError> {
+        if self.draining.load(Ordering::SeqCst) {
+            return Err(SignerError::SessionLocked("signer pool is shutting down".to_string()));
+        }
+        Ok(())
+    }
+
+    fn slots(&self, key_id: &str) -> Result<Arc<KeySlots>, SignerError> {
+        self.keys
+            .read()
+            .unwrap_or_else(|p| p.into_inner())
+            .get(key_id)
+            .cloned()
+            .ok_or_else(|| SignerError::BackendError(format!("no key '{}' in the pool", key_id)))
+    }
+
+    fn acquire(&self, key_id: &str) -> Result<Lease, SignerError> {
+        self.check_open()?;
+        let slots = self.slots(key_id)?;
+        let deadline = self.config.acquire_timeout.map(|timeout| Instant::now() + timeout);
+        let mut free = slots.free();
+        loop {
+            self.check_open()?;
+            if let Some(index) = free.pop() {
+                drop(free);
+                return Ok(Lease { slots, index });
+            }
+            free = match deadline {
+                None => slots.returned.wait(free).unwrap_or_else(|p| p.into_inner()),
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return Err(SignerError::BackendError(format!(
+                            "timed out waiting for a session of key '{}'",
+                            key_id
+                        )));
+                    }
+                    slots.returned.wait_timeout(free, deadline - now).unwrap_or_else(|p| p.into_inner()).0
+                }
+            };
+        }
+    }
+}
+
+impl Drop for SignerPool {
+    fn drop(&mut self) {
+        self.shutdown(Duration::ZERO);
+    }
+}
+
+/// Fail if `sessions` more locked pages would exceed `RLIMIT_MEMLOCK`
+#[cfg(unix)]
+fn check_memlock_budget(sessions: usize) -> Result<(), SignerError> {
+    let mut limit = libc::rlimit { rlim_cur: 0, rlim_max: 0 };
+    // SAFETY: getrlimit only writes the struct it is given
+    if unsafe { libc::getrlimit(libc::RLIMIT_MEMLOCK, &mut limit) } != 0 || limit.rlim_cur == libc::RLIM_INFINITY {
+        return Ok(());
+    }
+    // SAFETY: sysconf has no preconditions
+    let page_size = match unsafe { libc::sysconf(libc::_SC_PAGESIZE) } {
+        size if size > 0 => size as u64,
+        _ => 4096,
+    };
+    let needed = sessions as u64 * page_size;
+    let in_use = locked_bytes().unwrap_or(0);
+    let limit = limit.rlim_cur;
+    if in_use.saturating_add(needed) > limit {
+        return Err(SignerError::MemoryLockFailed(format!(
+            "{} sessions need {} KiB of locked memory; RLIMIT_MEMLOCK is {} KiB with {} KiB in use. \
+             Use fewer sessions, raise ulimit -l, or grant CAP_IPC_LOCK.",
+            sessions,
+            needed / 1024,
+            limit / 1024,
+            in_use / 1024
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn check_memlock_budget(_sessions: usize) -> Result<(), SignerError> {
+    Ok(())
+}
+
+/// Memory this process has locked already (Linux `VmLck`)
+#[cfg(unix)]
+fn locked_bytes() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmLck:"))?;
+    let kib: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kib * 1024)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::Cipher;
+    use crate::kdf::KdfParams;
+
+    #[test]
+    fn test_pool_routes_bounds_and_drains() {
+        std::env::set_var("SIGNER_ALLOW_INSECURE_MEMORY", "1");
+        let first =
+            EncryptedKeyContainer::encrypt_with_kdf(&[7u8; 32], "pw", Cipher::Aes256Gcm, KdfParams::MINIMUM).unwrap();
+        let second =
+            EncryptedKeyContainer::encrypt_with_kdf(&[8u8; 32], "pw", Cipher::Aes256Gcm, KdfParams::MINIMUM).unwrap();
+        let pool = Arc::new(SignerPool::new(PoolConfig {
+            sessions_per_key: 2,
+            acquire_timeout: Some(Duration::from_millis(100)),
+            ..PoolConfig::default()
+        }));
+        pool.add_key("treasury", &first, "pw").unwrap();
+        pool.add_key("hot", &second, "pw").unwrap();
+        assert!(pool.add_key("hot", &second, "pw").is_err());
+        assert!(pool.add_key("cold", &second, "wrong").is_err());
+        assert_eq!(pool.key_ids(), ["hot", "treasury"]);
+
+        // Routed by key ID
+        let result = pool.sign("hot", &crate::crypto::test_transaction(&[8u8; 32])).unwrap();
+        assert_eq!(Some(result.public_key.as_str()), second.public_key.as_deref());
+        assert!(pool.sign("missing", b"message").is_err());
+
+        // Concurrent signers share the sessions
+        let threads: Vec<_> = (0..8)
+            .map(|_| {
+                let pool = Arc::clone(&pool);
+                std::thread::spawn(move || pool.sign("treasury", &crate::crypto::test_transaction(&[7u8; 32])).unwrap().signature)
+            })
+            .collect();
+        let signatures: Vec<String> = threads.into_iter().map(|t| t.join().unwrap()).collect();
+        assert!(signatures.windows(2).all(|w| w[0] == w[1]));
+        assert_eq!(pool.available("treasury"), Some(2));
+
+        // With every session checked out, the next signer times out
+        pool.with_session("treasury", |_| {
+            pool.with_session("treasury", |_| {
+                let err = pool.sign("treasury", &crate::crypto::test_transaction(&[7u8; 32])).err().unwrap();
+                assert!(err.to_string().contains("timed out"), "{}", err);
+                Ok(())
+            })
+        })
+        .unwrap();
+
+        assert!(pool.shutdown(Duration::from_secs(1)));
+        assert!(matches!(pool.sign("hot", &crate::crypto::test_transaction(&[8u8; 32])), Err(SignerError::SessionLocked(_))));
+        assert!(pool.key_ids().is_empty());
+    }
+}
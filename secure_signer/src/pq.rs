@@ -0,0 +1,237 @@
+//! Post-quantum hybrid signing (ML-DSA-65)
+//!
+//! Chains do not accept post-quantum signatures yet, but an ML-DSA-65
+//! (FIPS 204) signature made today alongside the chain signature is an
+//! attestation that survives the classical one being broken.
+//!
+//! A [`HybridKeyContainer`] is an ordinary container with a second,
+//! independent 32-byte ML-DSA seed encrypted under the same
+//! passphrase-derived key, so one KDF run unlocks both. The ML-DSA seed is
+//! generated rather than derived from the classical seed: that seed doubles
+//! as the secp256k1 private key, which a quantum adversary could recover
+//! from any EVM public key. Unknown fields are ignored when a container is
+//! parsed, so a hybrid container still works everywhere a classical one does.
+
+use ml_dsa::{KeyGen, MlDsa65, Signature, VerifyingKey, B32};
+use serde::{Deserialize, Serialize};
+
+use crate::crypto::{get_locking_mode, sign_with_secure_key, EncryptedKeyContainer};
+use crate::entropy::{fill_random, health_check, EntropyRng};
+use crate::error::SignerError;
+use crate::keys::SecureKdfKey;
+use crate::secure_buffer::SecureBuffer;
+
+/// Algorithm name recorded in containers and results
+pub const ML_DSA_65: &str = "ml-dsa-65";
+
+/// An encrypted ML-DSA seed and its public key
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PqKey {
+    /// Signature algorithm (`"ml-dsa-65"`)
+    pub algorithm: String,
+    /// Nonce for the container's cipher (base64)
+    pub nonce: String,
+    /// Encrypted 32-byte seed with auth tag (base64)
+    pub ciphertext: String,
+    /// Encoded verifying key (base64, 1952 bytes)
+    pub public_key: String,
+}
+
+/// A classical container with an ML-DSA key alongside
+#[derive(Serialize, Deserialize, Clone)]
+pub struct HybridKeyContainer {
+    /// The classical (Ed25519 / secp256k1) key
+    #[serde(flatten)]
+    pub classical: EncryptedKeyContainer,
+    /// The post-quantum key
+    pub pq: PqKey,
+}
+
+/// Result of a hybrid signing operation
+#[derive(Serialize, Deserialize)]
+pub struct HybridSigningResult {
+    /// Ed25519 signature (base58)
+    pub signature: String,
+    /// Ed25519 public key (base58)
+    pub public_key: String,
+    /// Post-quantum algorithm (`"ml-dsa-65"`)
+    pub pq_algorithm: String,
+    /// ML-DSA signature (base64, 3309 bytes)
+    pub pq_signature: String,
+    /// ML-DSA verifying key (base64)
+    pub pq_public_key: String,
+}
+
+impl HybridKeyContainer {
+    /// Generate a new classical key and ML-DSA key under one passphrase
+    pub fn generate(passphrase: &str) -> Result<Self, SignerError> {
+        let mut seed = SecureBuffer::with_mode(32, get_locking_mode())?;
+        fill_random(seed.as_mut_slice())?;
+        let container = EncryptedKeyContainer::encrypt(seed.as_slice(), passphrase);
+        seed.zeroize();
+        Self::from_container(container?, passphrase)
+    }
+
+    /// Add a freshly generated ML-DSA key to an existing container
+    ///
+    /// The passphrase must be the container's; the new key is encrypted with
+    /// the same KDF output and cipher.
+    pub fn from_container(classical: EncryptedKeyContainer, passphrase: &str) -> Result<Self, SignerError> {
+        let mut unlock_key = classical.derive_unlock_key(passphrase)?;
+        // Proves the passphrase before anything is sealed under it
+        let check = classical.decrypt_key_with_unlock_key(&unlock_key);
+        let pq = check.and_then(|mut classical_key| {
+            classical_key.zeroize();
+            seal_pq_key(&classical, &unlock_key)
+        });
+        unlock_key.zeroize();
+
+        Ok(Self { classical, pq: pq? })
+    }
+
+    /// Parse a hybrid container from JSON
+    pub fn from_json(json: &str) -> Result<Self, SignerError> {
+        serde_json::from_str(json).map_err(|e| SignerError::ContainerError(e.to_string()))
+    }
+
+    /// Serialize to JSON
+    pub fn to_json(&self) -> Result<String, SignerError> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// The encoded ML-DSA verifying key
+    pub fn pq_public_key(&self) -> Result<Vec<u8>, SignerError> {
+        Ok(base64::Engine::decode(&base64::engine::general_purpose::STANDARD, &self.pq.public_key)?)
+    }
+
+    /// Decrypt both keys with one KDF run
+    fn decrypt_keys(&self, passphrase: &str) -> Result<(SecureBuffer, SecureBuffer), SignerError> {
+        if self.pq.algorithm != ML_DSA_65 {
+            return Err(SignerError::ContainerError(format!(
+                "unsupported post-quantum algorithm '{}'",
+                self.pq.algorithm
+            )));
+        }
+
+        let mut unlock_key = self.classical.derive_unlock_key(passphrase)?;
+        let keys = self.classical.decrypt_key_with_unlock_key(&unlock_key).and_then(|classical| {
+            let base64 = base64::engine::general_purpose::STANDARD;
+            let nonce = base64::Engine::decode(&base64, &self.pq.nonce)?;
+            let ciphertext = base64::Engine::decode(&base64, &self.pq.ciphertext)?;
+            let pq = self.classical.cipher.decrypt_to_secure(unlock_key.as_slice(), &nonce, &ciphertext)?;
+            Ok((classical, pq))
+        });
+        unlock_key.zeroize();
+        keys
+    }
+}
+
+fn seal_pq_key(classical: &EncryptedKeyContainer, unlock_key: &SecureKdfKey) -> Result<PqKey, SignerError> {
+    let mut seed = SecureBuffer::with_mode(32, get_locking_mode())?;
+    fill_random(seed.as_mut_slice())?;
+    let public_key = ml_dsa_key(&seed)?.verifying_key().encode();
+
+    let mut nonce = vec![0u8; classical.cipher.nonce_size()];
+    fill_random(&mut nonce)?;
+    let ciphertext = classical.cipher.encrypt(unlock_key.as_slice(), &nonce, seed.as_slice());
+    seed.zeroize();
+
+    let base64 = base64::engine::general_purpose::STANDARD;
+    Ok(PqKey {
+        algorithm: ML_DSA_65.to_string(),
+        nonce: base64::Engine::encode(&base64, nonce),
+        ciphertext: base64::Engine::encode(&base64, ciphertext?),
+        public_key: base64::Engine::encode(&base64, public_key),
+    })
+}
+
+/// Expand a 32-byte seed into an ML-DSA-65 key pair
+///
+/// The key pair zeroizes its signing key on drop.
+fn ml_dsa_key(seed: &SecureBuffer) -> Result<ml_dsa::KeyPair<MlDsa65>, SignerError> {
+    let mut xi = B32::try_from(seed.as_slice()).map_err(|_| SignerError::InvalidKeyFormat(seed.len()))?;
+    let key_pair = MlDsa65::key_gen_internal(&xi);
+    zeroize::Zeroize::zeroize(xi.as_mut_slice());
+    Ok(key_pair)
+}
+
+/// Sign `payload` with both keys of a hybrid container
+///
+/// The Ed25519 signature is the same one `decrypt_and_sign` produces. The
+/// ML-DSA signature is randomized (hedged) with an empty context string.
+pub fn decrypt_and_sign_hybrid(
+    container_json: &str,
+    passphrase: &str,
+    payload: &[u8],
+) -> Result<HybridSigningResult, SignerError> {
+    let container = HybridKeyContainer::from_json(container_json)?;
+    let (mut classical_key, mut pq_seed) = container.decrypt_keys(passphrase)?;
+
+    let classical = sign_with_secure_key(&classical_key, payload);
+    classical_key.zeroize();
+    let pq = health_check().and_then(|()| {
+        let key_pair = ml_dsa_key(&pq_seed)?;
+        let signature = key_pair
+            .signing_key()
+            .sign_randomized(payload, &[], &mut EntropyRng)
+            .map_err(|e| SignerError::SigningFailed(format!("ML-DSA signing failed: {}", e)))?;
+        Ok((signature.encode(), key_pair.verifying_key().encode()))
+    });
+    pq_seed.zeroize();
+
+    let classical = classical?;
+    let (pq_signature, pq_public_key) = pq?;
+    let base64 = base64::engine::general_purpose::STANDARD;
+    Ok(HybridSigningResult {
+        signature: classical.signature,
+        public_key: classical.public_key,
+        pq_algorithm: ML_DSA_65.to_string(),
+        pq_signature: base64::Engine::encode(&base64, pq_signature),
+        pq_public_key: base64::Engine::encode(&base64, pq_public_key),
+    })
+}
+
+/// Verify an ML-DSA-65 signature (empty context) over `payload`
+pub fn verify_pq(public_key: &[u8], payload: &[u8], signature: &[u8]) -> bool {
+    let Ok(encoded_key) = public_key.try_into() else {
+        return false;
+    };
+    let Ok(signature) = Signature::<MlDsa65>::try_from(signature) else {
+        return false;
+    };
+    VerifyingKey::<MlDsa65>::decode(encoded_key).verify_with_context(payload, &[], &signature)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::Cipher;
+    use crate::kdf::KdfParams;
+
+    #[test]
+    fn test_hybrid_container_signs_with_both_keys() {
+        std::env::set_var("SIGNER_ALLOW_INSECURE_MEMORY", "1");
+        let classical =
+            EncryptedKeyContainer::encrypt_with_kdf(&[7u8; 32], "pw", Cipher::Aes256Gcm, KdfParams::MINIMUM).unwrap();
+        assert!(HybridKeyContainer::from_container(classical.clone(), "wrong").is_err());
+
+        let hybrid = HybridKeyContainer::from_container(classical, "pw").unwrap();
+        let json = hybrid.to_json().unwrap();
+        // Still an ordinary container for classical signing
+        let payload = crate::crypto::test_transaction(&[7u8; 32]);
+        let plain = crate::decrypt_and_sign(&json, "pw", &payload).unwrap();
+
+        let result = decrypt_and_sign_hybrid(&json, "pw", &payload).unwrap();
+        assert_eq!(result.signature, plain.signature);
+        assert_eq!(result.pq_public_key, hybrid.pq.public_key);
+
+        let base64 = base64::engine::general_purpose::STANDARD;
+        let pq_signature = base64::Engine::decode(&base64, &result.pq_signature).unwrap();
+        let pq_public_key = hybrid.pq_public_key().unwrap();
+        assert_eq!((pq_public_key.len(), pq_signature.len()), (1952, 3309));
+        assert!(verify_pq(&pq_public_key, &payload, &pq_signature));
+        assert!(!verify_pq(&pq_public_key, b"other", &pq_signature));
+
+        assert!(decrypt_and_sign_hybrid(&json, "wrong", b"payload").is_err());
+    }
+}
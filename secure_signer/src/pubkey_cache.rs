@@ -0,0 +1,229 @@
+//! Cache of public keys and addresses per container
+//!
+//! A container stores its Solana public key in the clear, but the EVM
+//! address and secp256k1 public key can only be computed from the private
+//! key. [`PublicKeyCache`] remembers them by container ID after the first
+//! decryption, so address displays and `inspect` do not need the passphrase
+//! again. Nothing secret is cached.
+//!
+//! # Storage
+//!
+//! Like [`SignatureCounter`](crate::counter::SignatureCounter), the file is
+//! authenticated with HMAC-SHA256 under a caller-supplied key and rewritten
+//! with write-to-temp-then-rename, so an attacker with write access to the
+//! file cannot substitute their own address for a container's.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use hmac::{Hmac, Mac};
+use k256::ecdsa::SigningKey as K256SigningKey;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use crate::crypto::{evm_address_from_pubkey, get_locking_mode, EncryptedKeyContainer};
+use crate::error::SignerError;
+use crate::secure_buffer::SecureBuffer;
+
+/// Domain separator for the file MAC
+const MAC_DOMAIN: &[u8] = b"coldstar-pubkey-cache-v1";
+
+/// Shortest accepted MAC key
+const MIN_MAC_KEY_SIZE: usize = 16;
+
+/// Public information derived from one container's key
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct CachedKeys {
+    /// Ed25519 public key (base58)
+    pub solana_public_key: String,
+    /// EVM address (0x-prefixed)
+    pub evm_address: String,
+    /// Compressed secp256k1 public key (hex), as used by MuSig2
+    pub secp256k1_public_key: String,
+    /// Unix timestamp (seconds) when the entry was computed
+    pub cached_at: u64,
+}
+
+/// On-disk representation of the cache
+#[derive(Serialize, Deserialize)]
+struct CacheFile {
+    version: u8,
+    entries: BTreeMap<String, CachedKeys>,
+    /// HMAC-SHA256 over the entries (hex)
+    mac: String,
+}
+
+/// Public keys and addresses keyed by container ID
+pub struct PublicKeyCache {
+    path: Option<PathBuf>,
+    mac_key: Option<SecureBuffer>,
+    entries: BTreeMap<String, CachedKeys>,
+}
+
+impl PublicKeyCache {
+    /// Create a cache that lives only in memory
+    pub fn in_memory() -> Self {
+        Self {
+            path: None,
+            mac_key: None,
+            entries: BTreeMap::new(),
+        }
+    }
+
+    /// Open (or create) a cache persisted at `path`
+    ///
+    /// Fails with [`SignerError::IntegrityError`] if the file was modified
+    /// or was written under a different key.
+    pub fn open(path: impl AsRef<Path>, mac_key: &SecureBuffer) -> Result<Self, SignerError> {
+        if mac_key.len() < MIN_MAC_KEY_SIZE {
+            return Err(SignerError::IntegrityError(format!(
+                "cache MAC key must be at least {} bytes",
+                MIN_MAC_KEY_SIZE
+            )));
+        }
+        let path = path.as_ref().to_path_buf();
+        let mut cache = Self {
+            path: None,
+            mac_key: Some(SecureBuffer::from_slice_with_mode(mac_key.as_slice(), get_locking_mode())?),
+            entries: BTreeMap::new(),
+        };
+
+        if path.exists() {
+            let file: CacheFile = serde_json::from_str(&std::fs::read_to_string(&path)?)?;
+            let expected = hex::decode(&file.mac).unwrap_or_default();
+            cache
+                .mac(&file.entries)?
+                .verify_slice(&expected)
+                .map_err(|_| SignerError::IntegrityError("public key cache file was modified".to_string()))?;
+            cache.entries = file.entries;
+        }
+
+        cache.path = Some(path);
+        Ok(cache)
+    }
+
+    /// Cached keys for a container, without decrypting it
+    ///
+    /// Returns `None` if the container was never cached or its cached
+    /// Solana public key no longer matches the container.
+    pub fn get(&self, container: &EncryptedKeyContainer) -> Option<&CachedKeys> {
+        let entry = self.entries.get(&container.container_id().ok()?)?;
+        (container.public_key.as_deref() == Some(entry.solana_public_key.as_str())).then_some(entry)
+    }
+
+    /// Cached keys for a container, decrypting it once if not yet cached
+    pub fn get_or_compute(
+        &mut self,
+        container: &EncryptedKeyContainer,
+        passphrase: &str,
+    ) -> Result<&CachedKeys, SignerError> {
+        let id = container.container_id()?;
+        if self.get(container).is_none() {
+            let keys = compute_keys(container, passphrase)?;
+            self.entries.insert(id.clone(), keys);
+            if let Err(e) = self.persist() {
+                self.entries.remove(&id);
+                return Err(e);
+            }
+        }
+        Ok(&self.entries[&id])
+    }
+
+    /// Forget a container, returning whether it was cached
+    pub fn remove(&mut self, container_id: &str) -> Result<bool, SignerError> {
+        let removed = self.entries.remove(container_id).is_some();
+        if removed {
+            self.persist()?;
+        }
+        Ok(removed)
+    }
+
+    fn mac(&self, entries: &BTreeMap<String, CachedKeys>) -> Result<Hmac<Sha256>, SignerError> {
+        let key = self
+            .mac_key
+            .as_ref()
+            .ok_or_else(|| SignerError::IntegrityError("no cache MAC key".to_string()))?;
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(key.as_slice())
+            .map_err(|e| SignerError::IntegrityError(e.to_string()))?;
+        mac.update(MAC_DOMAIN);
+        mac.update(&serde_json::to_vec(entries)?);
+        Ok(mac)
+    }
+
+    fn persist(&self) -> Result<(), SignerError> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        let file = CacheFile {
+            version: 1,
+            entries: self.entries.clone(),
+            mac: hex::encode(self.mac(&self.entries)?.finalize().into_bytes()),
+        };
+        let json = serde_json::to_string(&file)?;
+
+        let tmp_path = path.with_extension("tmp");
+        std::fs::write(&tmp_path, json)?;
+        std::fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+}
+
+fn compute_keys(container: &EncryptedKeyContainer, passphrase: &str) -> Result<CachedKeys, SignerError> {
+    let mut secure_key = container.decrypt_key(passphrase)?;
+    let secp256k1 = K256SigningKey::from_slice(secure_key.as_slice());
+    secure_key.zeroize();
+
+    let secp256k1 = secp256k1.map_err(|e| SignerError::SigningFailed(format!("Invalid secp256k1 key: {}", e)))?;
+    let verifying_key = secp256k1.verifying_key();
+
+    Ok(CachedKeys {
+        solana_public_key: container
+            .public_key
+            .clone()
+            .ok_or_else(|| SignerError::ContainerError("container has no public key".to_string()))?,
+        evm_address: evm_address_from_pubkey(verifying_key),
+        secp256k1_public_key: hex::encode(verifying_key.to_encoded_point(true).as_bytes()),
+        cached_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::Cipher;
+    use crate::kdf::KdfParams;
+
+    #[test]
+    fn test_cache_avoids_decryption_and_detects_edits() {
+        std::env::set_var("SIGNER_ALLOW_INSECURE_MEMORY", "1");
+        let path = std::env::temp_dir().join(format!("coldstar-pubkey-cache-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let key = SecureBuffer::from_slice_with_mode(&[6u8; 32], get_locking_mode()).unwrap();
+        let container =
+            EncryptedKeyContainer::encrypt_with_kdf(&[8u8; 32], "pw", Cipher::Aes256Gcm, KdfParams::MINIMUM).unwrap();
+
+        let address = {
+            let mut cache = PublicKeyCache::open(&path, &key).unwrap();
+            assert!(cache.get(&container).is_none());
+            assert!(cache.get_or_compute(&container, "wrong").is_err());
+            cache.get_or_compute(&container, "pw").unwrap().evm_address.clone()
+        };
+        let expected = crate::crypto::sign_evm_transaction(&[8u8; 32], &[0u8; 32]).unwrap().address;
+        assert_eq!(address, expected);
+
+        // No passphrase needed once cached
+        let cache = PublicKeyCache::open(&path, &key).unwrap();
+        assert_eq!(cache.get(&container).unwrap().evm_address, expected);
+
+        let edited = std::fs::read_to_string(&path).unwrap().replace(&expected, "0x0000000000000000000000000000000000000000");
+        std::fs::write(&path, edited).unwrap();
+        assert!(matches!(PublicKeyCache::open(&path, &key), Err(SignerError::IntegrityError(_))));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
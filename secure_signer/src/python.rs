@@ -0,0 +1,211 @@
+//! Python bindings (pyo3)
+//!
+//! Built with the `python` feature, the `cdylib` is an extension module
+//! named `coldstar_secure_signer` (`maturin build --features python`, or
+//! copy the shared library to `coldstar_secure_signer.so` / `.pyd`):
+//!
+//! ```python
+//! import coldstar_secure_signer as signer
+//!
+//! container = signer.create_encrypted_key_container(seed, passphrase)
+//! result = signer.decrypt_and_sign(container, passphrase, message)
+//!
+//! with signer.SigningSession(container, passphrase, idle_timeout=120) as session:
+//!     session.sign(message)["signature"]
+//! ```
+//!
+//! The GIL is released while the KDF, decryption and signing run, so other
+//! Python threads keep going. Results are dicts with the same keys as the
+//! CLI's JSON; failures raise `coldstar_secure_signer.SignerError`.
+
+use std::time::Duration;
+
+use pyo3::create_exception;
+use pyo3::exceptions::PyException;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+use crate::backend::SignerBackend;
+use crate::crypto::{self, EVMSigningResult, EncryptedKeyContainer, SigningResult};
+use crate::error;
+use crate::evm;
+use crate::session::{self, SessionConfig};
+
+create_exception!(coldstar_secure_signer, SignerError, PyException, "A signing operation failed");
+
+fn py_error(error: error::SignerError) -> PyErr {
+    SignerError::new_err(error.to_string())
+}
+
+fn solana_dict(py: Python<'_>, result: SigningResult) -> PyResult<Bound<'_, PyDict>> {
+    let dict = PyDict::new(py);
+    dict.set_item("signature", result.signature)?;
+    dict.set_item("signed_transaction", result.signed_transaction)?;
+    dict.set_item("public_key", result.public_key)?;
+    Ok(dict)
+}
+
+fn evm_dict(py: Python<'_>, result: EVMSigningResult) -> PyResult<Bound<'_, PyDict>> {
+    let dict = PyDict::new(py);
+    dict.set_item("signature", result.signature)?;
+    dict.set_item("address", result.address)?;
+    dict.set_item("v", result.v)?;
+    Ok(dict)
+}
+
+/// Encrypt a 32-byte seed (or 64-byte keypair) into container JSON
+#[pyfunction]
+fn create_encrypted_key_container(py: Python<'_>, private_key: &[u8], passphrase: &str) -> PyResult<String> {
+    py.allow_threads(|| crypto::create_encrypted_key_container(private_key, passphrase))
+        .map_err(py_error)
+}
+
+/// Decrypt a container and sign a Solana transaction or message
+#[pyfunction]
+fn decrypt_and_sign<'py>(
+    py: Python<'py>,
+    container_json: &str,
+    passphrase: &str,
+    message: &[u8],
+) -> PyResult<Bound<'py, PyDict>> {
+    let result = py
+        .allow_threads(|| crypto::decrypt_and_sign(container_json, passphrase, message))
+        .map_err(py_error)?;
+    solana_dict(py, result)
+}
+
+/// Decrypt a container and sign an off-chain message (no transaction checks)
+#[pyfunction]
+fn decrypt_and_sign_message<'py>(
+    py: Python<'py>,
+    container_json: &str,
+    passphrase: &str,
+    message: &[u8],
+) -> PyResult<Bound<'py, PyDict>> {
+    let result = py
+        .allow_threads(|| crypto::decrypt_and_sign_message(container_json, passphrase, message))
+        .map_err(py_error)?;
+    solana_dict(py, result)
+}
+
+/// Decrypt a container and sign a 32-byte EVM hash
+#[pyfunction]
+fn decrypt_and_sign_evm<'py>(
+    py: Python<'py>,
+    container_json: &str,
+    passphrase: &str,
+    message_hash: &[u8],
+) -> PyResult<Bound<'py, PyDict>> {
+    let result = py
+        .allow_threads(|| crypto::decrypt_and_sign_evm(container_json, passphrase, message_hash))
+        .map_err(py_error)?;
+    evm_dict(py, result)
+}
+
+/// Address (0x-prefixed lowercase hex) that signed a 32-byte EVM hash,
+/// from a 65-byte `r || s || v` signature
+#[pyfunction]
+fn recover_evm_address(message_hash: &[u8], signature: &[u8]) -> PyResult<String> {
+    evm_signature(signature)
+        .and_then(|signature| evm::recover_evm_address(message_hash, &signature))
+        .map_err(py_error)
+}
+
+fn evm_signature(signature: &[u8]) -> Result<[u8; 65], error::SignerError> {
+    signature.try_into().map_err(|_| {
+        error::SignerError::InvalidTransaction(format!("EVM signature must be 65 bytes, got {}", signature.len()))
+    })
+}
+
+/// An unlocked container; see [`session::SigningSession`]
+///
+/// Used as a context manager, the session is closed on exit.
+#[pyclass(module = "coldstar_secure_signer")]
+struct SigningSession {
+    inner: session::SigningSession,
+}
+
+#[pymethods]
+impl SigningSession {
+    /// Decrypt the container (with the GIL released) and start a session
+    ///
+    /// `idle_timeout` and `ttl` are in seconds; omitted, they default to 5
+    /// minutes and 1 hour.
+    #[new]
+    #[pyo3(signature = (container_json, passphrase, idle_timeout=None, ttl=None))]
+    fn new(
+        py: Python<'_>,
+        container_json: &str,
+        passphrase: &str,
+        idle_timeout: Option<f64>,
+        ttl: Option<f64>,
+    ) -> PyResult<Self> {
+        let mut config = SessionConfig::default();
+        let seconds = |value: f64| Duration::try_from_secs_f64(value).map_err(|e| SignerError::new_err(e.to_string()));
+        if let Some(idle_timeout) = idle_timeout {
+            config.idle_timeout = seconds(idle_timeout)?;
+        }
+        if let Some(ttl) = ttl {
+            config.ttl = Some(seconds(ttl)?);
+        }
+        let inner = py
+            .allow_threads(|| {
+                let container = EncryptedKeyContainer::from_json(container_json)?;
+                session::SigningSession::unlock(&container, passphrase, config)
+            })
+            .map_err(py_error)?;
+        Ok(Self { inner })
+    }
+
+    /// Sign a Solana transaction or message
+    fn sign<'py>(&self, py: Python<'py>, message: &[u8]) -> PyResult<Bound<'py, PyDict>> {
+        let result = py.allow_threads(|| self.inner.sign(message)).map_err(py_error)?;
+        solana_dict(py, result)
+    }
+
+    /// Sign a 32-byte EVM hash
+    fn sign_evm_hash<'py>(&self, py: Python<'py>, message_hash: &[u8]) -> PyResult<Bound<'py, PyDict>> {
+        let result = py.allow_threads(|| self.inner.sign_evm_hash(message_hash)).map_err(py_error)?;
+        evm_dict(py, result)
+    }
+
+    /// The container's base58 public key, if it records one
+    #[getter]
+    fn public_key(&self) -> Option<&str> {
+        self.inner.public_key()
+    }
+
+    /// Whether the key has been zeroized (closed, idle, or expired)
+    #[getter]
+    fn is_locked(&self) -> bool {
+        self.inner.is_locked()
+    }
+
+    /// Zeroize the key now
+    fn close(&self) {
+        self.inner.close();
+    }
+
+    fn __enter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    #[pyo3(signature = (*_args))]
+    fn __exit__(&self, _args: &Bound<'_, pyo3::types::PyTuple>) -> bool {
+        self.inner.close();
+        false
+    }
+}
+
+#[pymodule]
+fn coldstar_secure_signer(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add("SignerError", m.py().get_type::<SignerError>())?;
+    m.add_function(wrap_pyfunction!(create_encrypted_key_container, m)?)?;
+    m.add_function(wrap_pyfunction!(decrypt_and_sign, m)?)?;
+    m.add_function(wrap_pyfunction!(decrypt_and_sign_message, m)?)?;
+    m.add_function(wrap_pyfunction!(decrypt_and_sign_evm, m)?)?;
+    m.add_function(wrap_pyfunction!(recover_evm_address, m)?)?;
+    m.add_class::<SigningSession>()?;
+    m.add("__version__", env!("CARGO_PKG_VERSION"))?;
+    Ok(())
+}
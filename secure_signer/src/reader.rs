@@ -0,0 +1,320 @@
+//! Bounded reading of untrusted bytes
+//!
+//! Transactions, envelopes, and bags of cells arrive from FFI callers and
+//! the stdin interface, so every parser treats its input as hostile. They
+//! all read through a [`ByteReader`], which:
+//!
+//! - computes every offset with checked arithmetic and never indexes past
+//!   the end of the input
+//! - checks element counts against the bytes left before anything is
+//!   allocated, so a forged length cannot request gigabytes
+//! - reports failures as a [`ParseError`] naming what was being parsed,
+//!   the byte offset, and the reason
+//!
+//! Chain-specific encodings (Solana shortvec, Bitcoin CompactSize, XDR
+//! padding, ...) are built on top of it in their own modules.
+
+use alloc::format;
+use alloc::string::String;
+use core::fmt;
+
+/// Why a parse failed
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    /// The input ended early
+    Truncated {
+        /// Bytes the field needed
+        needed: usize,
+        /// Bytes that were left
+        available: usize,
+    },
+    /// The input continued after the structure ended
+    TrailingBytes(usize),
+    /// A length or count does not fit the input (or a `usize`)
+    LengthOverflow,
+    /// A field holds a value the format does not allow
+    Invalid(String),
+}
+
+/// A structured parse failure
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    /// What was being parsed (e.g. "Solana message")
+    pub context: &'static str,
+    /// Byte offset of the failing field
+    pub offset: usize,
+    /// The reason
+    pub kind: ParseErrorKind,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at byte {}: ", self.context, self.offset)?;
+        match &self.kind {
+            ParseErrorKind::Truncated { needed, available } => {
+                write!(f, "truncated (needed {} bytes, {} available)", needed, available)
+            }
+            ParseErrorKind::TrailingBytes(n) => write!(f, "{} trailing bytes", n),
+            ParseErrorKind::LengthOverflow => write!(f, "length exceeds the input"),
+            ParseErrorKind::Invalid(reason) => write!(f, "{}", reason),
+        }
+    }
+}
+
+impl core::error::Error for ParseError {}
+
+/// Cursor over untrusted bytes
+pub(crate) struct ByteReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    context: &'static str,
+}
+
+impl<'a> ByteReader<'a> {
+    /// Read `bytes`; `context` names the structure in errors
+    pub(crate) fn new(bytes: &'a [u8], context: &'static str) -> Self {
+        Self { bytes, pos: 0, context }
+    }
+
+    /// Offset of the next byte
+    pub(crate) fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// Bytes not yet read
+    pub(crate) fn remaining(&self) -> usize {
+        self.bytes.len() - self.pos
+    }
+
+    /// An error at the current offset
+    pub(crate) fn error(&self, kind: ParseErrorKind) -> ParseError {
+        ParseError {
+            context: self.context,
+            offset: self.pos,
+            kind,
+        }
+    }
+
+    /// An [`ParseErrorKind::Invalid`] error at the current offset
+    pub(crate) fn invalid(&self, reason: impl Into<String>) -> ParseError {
+        self.error(ParseErrorKind::Invalid(reason.into()))
+    }
+
+    /// Fail unless every byte has been read
+    pub(crate) fn finish(&self) -> Result<(), ParseError> {
+        match self.remaining() {
+            0 => Ok(()),
+            n => Err(self.error(ParseErrorKind::TrailingBytes(n))),
+        }
+    }
+
+    pub(crate) fn take(&mut self, n: usize) -> Result<&'a [u8], ParseError> {
+        if n > self.remaining() {
+            return Err(self.error(ParseErrorKind::Truncated {
+                needed: n,
+                available: self.remaining(),
+            }));
+        }
+        let slice = &self.bytes[self.pos..self.pos + n];
+        self.pos += n;
+        Ok(slice)
+    }
+
+    /// The unread bytes, consuming them
+    pub(crate) fn rest(&mut self) -> &'a [u8] {
+        let slice = &self.bytes[self.pos..];
+        self.pos = self.bytes.len();
+        slice
+    }
+
+    /// The byte `offset` positions ahead, without consuming it
+    pub(crate) fn peek(&self, offset: usize) -> Option<u8> {
+        self.pos.checked_add(offset).and_then(|i| self.bytes.get(i)).copied()
+    }
+
+    pub(crate) fn array<const N: usize>(&mut self) -> Result<[u8; N], ParseError> {
+        Ok(self.take(N)?.try_into().expect("took N bytes"))
+    }
+
+    pub(crate) fn u8(&mut self) -> Result<u8, ParseError> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub(crate) fn u16_le(&mut self) -> Result<u16, ParseError> {
+        Ok(u16::from_le_bytes(self.array()?))
+    }
+
+    pub(crate) fn u32_le(&mut self) -> Result<u32, ParseError> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    pub(crate) fn u64_le(&mut self) -> Result<u64, ParseError> {
+        Ok(u64::from_le_bytes(self.array()?))
+    }
+
+    pub(crate) fn u32_be(&mut self) -> Result<u32, ParseError> {
+        Ok(u32::from_be_bytes(self.array()?))
+    }
+
+    pub(crate) fn i64_be(&mut self) -> Result<i64, ParseError> {
+        Ok(i64::from_be_bytes(self.array()?))
+    }
+
+    /// Big-endian unsigned integer of `size` bytes (at most 8)
+    pub(crate) fn uint_be(&mut self, size: usize) -> Result<u64, ParseError> {
+        if size > 8 {
+            return Err(self.invalid(format!("{}-byte integer", size)));
+        }
+        Ok(self.take(size)?.iter().fold(0u64, |acc, &b| acc << 8 | u64::from(b)))
+    }
+
+    /// Validate an element count read from the input
+    ///
+    /// Each element takes at least `min_item_size` bytes, so a count whose
+    /// elements cannot fit in what is left is rejected before the caller
+    /// allocates for it.
+    pub(crate) fn count(&self, count: u64, min_item_size: usize) -> Result<usize, ParseError> {
+        let needed = count.checked_mul(min_item_size as u64);
+        match needed {
+            Some(needed) if needed <= self.remaining() as u64 => Ok(count as usize),
+            _ => Err(self.error(ParseErrorKind::LengthOverflow)),
+        }
+    }
+
+    /// Bytes prefixed by a length read from the input
+    pub(crate) fn take_len(&mut self, len: u64) -> Result<&'a [u8], ParseError> {
+        let len = self.count(len, 1)?;
+        self.take(len)
+    }
+
+    /// A Solana "shortvec" length (1-3 bytes, 7 bits each)
+    pub(crate) fn compact_u16(&mut self) -> Result<usize, ParseError> {
+        let mut value = 0usize;
+        for i in 0..3 {
+            let byte = self.u8()?;
+            value |= ((byte & 0x7f) as usize) << (7 * i);
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(self.invalid("invalid compact-u16"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reader_bounds() {
+        let mut r = ByteReader::new(&[1, 2, 0, 0, 0, 0xff], "test data");
+        assert_eq!(r.u8().unwrap(), 1);
+        assert_eq!(r.u32_le().unwrap(), 2);
+        assert_eq!(r.peek(0), Some(0xff));
+        assert_eq!(r.peek(usize::MAX), None);
+
+        let err = r.u16_le().unwrap_err();
+        assert_eq!(
+            err.kind,
+            ParseErrorKind::Truncated {
+                needed: 2,
+                available: 1
+            }
+        );
+        assert_eq!(err.to_string(), "test data at byte 5: truncated (needed 2 bytes, 1 available)");
+
+        // A failed read consumes nothing
+        assert_eq!(r.finish().unwrap_err().kind, ParseErrorKind::TrailingBytes(1));
+        assert_eq!(r.count(u64::MAX, 32).unwrap_err().kind, ParseErrorKind::LengthOverflow);
+        assert!(r.take_len(2).is_err());
+        assert_eq!(r.take_len(1).unwrap(), &[0xff]);
+        assert!(r.finish().is_ok());
+    }
+
+    /// Deterministic stand-in for the `fuzz/` targets: every parser gets
+    /// truncated and bit-flipped versions of well-formed inputs and must
+    /// return (Ok or Err) rather than panic
+    #[test]
+    fn test_parsers_survive_malformed_input() {
+        use crate::bitcoin::{OutPoint, Psbt, Transaction, TxIn, TxOut};
+        use crate::solana::squads::{proposal_approve_transaction, VaultTransactionMessage};
+        use crate::solana::{decode_transaction, SolanaMessage, SolanaTransaction};
+        use crate::stellar::StellarEnvelope;
+        use crate::ton::{Cell, CellBuilder};
+
+        let parsers: [fn(&[u8]); 10] = [
+            |b| drop(SolanaMessage::parse(b)),
+            |b| drop(SolanaTransaction::parse(b)),
+            |b| drop(decode_transaction(b)),
+            |b| drop(VaultTransactionMessage::parse(b)),
+            |b| drop(VaultTransactionMessage::from_account_data(b)),
+            |b| drop(StellarEnvelope::from_xdr(b)),
+            |b| drop(Transaction::parse(b)),
+            |b| drop(Psbt::parse(b)),
+            |b| drop(Cell::from_boc(b)),
+            |b| drop(crate::evm::decode_nft_call(b)),
+        ];
+
+        let solana = proposal_approve_transaction(&[1; 32], &[2; 32], 7, Some("memo"), &[3; 32]).unwrap();
+        let bitcoin = Transaction {
+            version: 2,
+            inputs: vec![TxIn {
+                previous_output: OutPoint { txid: [4; 32], vout: 1 },
+                script_sig: Vec::new(),
+                sequence: 0xffff_fffd,
+                witness: vec![vec![5; 72], vec![6; 33]],
+            }],
+            outputs: vec![TxOut {
+                value: 50_000,
+                script_pubkey: vec![0x00, 0x14, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7],
+            }],
+            lock_time: 0,
+        };
+        let mut psbt = b"psbt\xff\x01\x00".to_vec();
+        let unsigned = Transaction {
+            inputs: vec![TxIn { witness: Vec::new(), ..bitcoin.inputs[0].clone() }],
+            ..bitcoin.clone()
+        }
+        .serialize();
+        psbt.push(unsigned.len() as u8);
+        psbt.extend_from_slice(&unsigned);
+        psbt.extend_from_slice(&[0, 0, 0]);
+        let leaf = CellBuilder::new().uint(32, 0xdead_beef).build().unwrap();
+        let ton = CellBuilder::new().uint(8, 1).reference(leaf.into()).build().unwrap().to_boc();
+        // v1 envelope: no preconditions, no memo, no operations, no signatures
+        let mut stellar = [2u32.to_be_bytes(), 0u32.to_be_bytes()].concat();
+        stellar.extend_from_slice(&[8; 32]);
+        stellar.extend_from_slice(&[0, 0, 0, 100, 0, 0, 0, 0, 0, 0, 0, 1]);
+        stellar.extend_from_slice(&[0; 20]);
+
+        let seeds = [solana.serialize(), solana.message_bytes().to_vec(), bitcoin.serialize(), psbt, ton, stellar];
+        assert!(SolanaTransaction::parse(&seeds[0]).is_ok() && SolanaMessage::parse(&seeds[1]).is_ok());
+        assert!(Transaction::parse(&seeds[2]).is_ok() && Psbt::parse(&seeds[3]).is_ok());
+        assert!(Cell::from_boc(&seeds[4]).is_ok() && StellarEnvelope::from_xdr(&seeds[5]).is_ok());
+
+        // xorshift64, so failures reproduce
+        let mut state = 0x9e37_79b9_7f4a_7c15u64;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        for seed in &seeds {
+            let mut inputs: Vec<Vec<u8>> = (0..=seed.len()).map(|n| seed[..n].to_vec()).collect();
+            for _ in 0..300 {
+                let mut mutated = seed.clone();
+                for _ in 0..=next() % 4 {
+                    let i = (next() % mutated.len() as u64) as usize;
+                    mutated[i] = if next() % 2 == 0 { 0xff } else { next() as u8 };
+                }
+                inputs.push(mutated);
+            }
+            for input in &inputs {
+                for parse in &parsers {
+                    parse(input);
+                }
+            }
+        }
+    }
+}
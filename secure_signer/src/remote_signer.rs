@@ -0,0 +1,573 @@
+//! Remote signer for Solana validators
+//!
+//! Lets a validator keep its identity key (and its authorized voter) on a
+//! separate hardened host. The validator connects over TCP. Both ends
+//! authenticate with Ed25519 transport keys, and every request and
+//! response is encrypted.
+//!
+//! # Handshake
+//!
+//! ```text
+//! client -> server   client ephemeral X25519 || client transport key
+//! server -> client   server ephemeral X25519 || server transport key || Ed25519ctx(transcript || "server")
+//! client -> server   Ed25519ctx(transcript || "client")
+//! ```
+//!
+//! - `transcript` is SHA-256 over the protocol context and all four public
+//!   keys.
+//! - The server only continues with client keys on its allowlist. The
+//!   client only continues with the server key it has pinned.
+//! - Signatures use Ed25519ctx under the [`PROTOCOL_CONTEXT`], so they
+//!   cannot be replayed as transaction signatures.
+//! - Each direction then has its own ChaCha20-Poly1305 key, derived with
+//!   HKDF-SHA256 from the X25519 shared secret.
+//! - Frames carry a 4-byte length and use a counter nonce, so replayed,
+//!   reordered or dropped frames fail to decrypt.
+//!
+//! # Keys
+//!
+//! Each signing key has a [`KeyRole`]:
+//!
+//! - **`Identity`** signs any message: gossip, repair and QUIC
+//!   certificates all need the identity key.
+//! - **`Vote`** (an authorized voter) signs only messages whose every
+//!   instruction calls the Vote program. A compromised validator host
+//!   therefore cannot spend from it.
+//!
+//! Requests inside the channel are the JSON of [`serve`](crate::serve):
+//! `{"action":"sign","key":"vote","message":"<base64>"}` and
+//! `{"action":"get_pubkey","key":"identity"}`.
+//! [`RemoteSignerClient`] speaks them for Rust validators and sidecars.
+
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use hkdf::Hkdf;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use x25519_dalek::{EphemeralSecret, PublicKey};
+use zeroize::Zeroizing;
+
+use crate::eddsa::verify_with_context;
+use crate::encoding::Encoding;
+use crate::entropy::EntropyRng;
+use crate::error::SignerError;
+use crate::keys::SecureEd25519Seed;
+use crate::serve::Response;
+use crate::solana::SolanaMessage;
+
+/// Ed25519ctx context of handshake signatures, also mixed into the transcript
+pub const PROTOCOL_CONTEXT: &[u8] = b"coldstar-remote-signer-v1";
+
+/// The Solana Vote program
+pub const VOTE_PROGRAM_ID: &str = "Vote111111111111111111111111111111111111111";
+
+/// Most connections served at once
+pub const MAX_CONNECTIONS: usize = 16;
+
+/// Largest frame either side accepts
+const MAX_FRAME: usize = 64 * 1024;
+
+/// What a key may sign
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyRole {
+    /// Validator identity: any message
+    Identity,
+    /// Authorized voter: Vote program instructions only
+    Vote,
+}
+
+impl KeyRole {
+    /// Refuse a message this role may not sign
+    pub fn check(&self, message: &[u8]) -> Result<(), SignerError> {
+        match self {
+            KeyRole::Identity => Ok(()),
+            KeyRole::Vote => {
+                let message = SolanaMessage::parse(message)?;
+                let only_votes = !message.instructions.is_empty()
+                    && message.instructions.iter().all(|ix| {
+                        message
+                            .static_key(ix.program_id_index)
+                            .is_some_and(|program| bs58::encode(program).into_string() == VOTE_PROGRAM_ID)
+                    });
+                if only_votes {
+                    Ok(())
+                } else {
+                    Err(SignerError::PolicyViolation(
+                        "vote keys only sign Vote program instructions".to_string(),
+                    ))
+                }
+            }
+        }
+    }
+}
+
+fn auth_error(message: &str) -> SignerError {
+    SignerError::IntegrityError(format!("remote signer handshake: {}", message))
+}
+
+/// A mutually authenticated, encrypted connection
+pub struct SecureChannel<S> {
+    stream: S,
+    peer: [u8; 32],
+    send: ChaCha20Poly1305,
+    receive: ChaCha20Poly1305,
+    sent: u64,
+    received: u64,
+}
+
+impl<S: Read + Write> SecureChannel<S> {
+    /// Handshake as the client, requiring the server to hold `server_key`
+    pub fn connect(mut stream: S, identity: &SecureEd25519Seed, server_key: &[u8; 32]) -> Result<Self, SignerError> {
+        let ephemeral = EphemeralSecret::random_from_rng(EntropyRng);
+        let client_ephemeral = PublicKey::from(&ephemeral).to_bytes();
+        let client_static = identity.public_key();
+        stream.write_all(&[client_ephemeral, client_static].concat())?;
+        stream.flush()?;
+
+        let mut reply = [0u8; 128];
+        stream.read_exact(&mut reply)?;
+        let server_ephemeral: [u8; 32] = reply[..32].try_into().expect("32 bytes");
+        let server_static: [u8; 32] = reply[32..64].try_into().expect("32 bytes");
+        let server_signature: [u8; 64] = reply[64..].try_into().expect("64 bytes");
+        if server_static != *server_key {
+            return Err(auth_error("server key does not match the pinned key"));
+        }
+
+        let transcript = transcript(&client_ephemeral, &client_static, &server_ephemeral, &server_static);
+        if !verify_with_context(&server_static, &role_message(&transcript, b"server"), PROTOCOL_CONTEXT, &server_signature)
+        {
+            return Err(auth_error("server signature is invalid"));
+        }
+        let signature = raw_signature(identity, &role_message(&transcript, b"client"))?;
+        stream.write_all(&signature)?;
+        stream.flush()?;
+
+        let shared = ephemeral.diffie_hellman(&PublicKey::from(server_ephemeral));
+        if !shared.was_contributory() {
+            return Err(auth_error("degenerate key exchange"));
+        }
+        let (client_to_server, server_to_client) = session_keys(shared.as_bytes(), &transcript)?;
+        Ok(Self::new(stream, server_static, client_to_server, server_to_client))
+    }
+
+    /// Handshake as the server, admitting only `authorized` client keys
+    pub fn accept(mut stream: S, identity: &SecureEd25519Seed, authorized: &[[u8; 32]]) -> Result<Self, SignerError> {
+        let mut hello = [0u8; 64];
+        stream.read_exact(&mut hello)?;
+        let client_ephemeral: [u8; 32] = hello[..32].try_into().expect("32 bytes");
+        let client_static: [u8; 32] = hello[32..].try_into().expect("32 bytes");
+        if !authorized.contains(&client_static) {
+            return Err(SignerError::PolicyViolation(format!(
+                "client {} is not authorized",
+                bs58::encode(client_static).into_string()
+            )));
+        }
+
+        let ephemeral = EphemeralSecret::random_from_rng(EntropyRng);
+        let server_ephemeral = PublicKey::from(&ephemeral).to_bytes();
+        let server_static = identity.public_key();
+        let transcript = transcript(&client_ephemeral, &client_static, &server_ephemeral, &server_static);
+        let signature = raw_signature(identity, &role_message(&transcript, b"server"))?;
+        stream.write_all(&[&server_ephemeral[..], &server_static, &signature].concat())?;
+        stream.flush()?;
+
+        let mut client_signature = [0u8; 64];
+        stream.read_exact(&mut client_signature)?;
+        if !verify_with_context(&client_static, &role_message(&transcript, b"client"), PROTOCOL_CONTEXT, &client_signature)
+        {
+            return Err(auth_error("client signature is invalid"));
+        }
+
+        let shared = ephemeral.diffie_hellman(&PublicKey::from(client_ephemeral));
+        if !shared.was_contributory() {
+            return Err(auth_error("degenerate key exchange"));
+        }
+        let (client_to_server, server_to_client) = session_keys(shared.as_bytes(), &transcript)?;
+        Ok(Self::new(stream, client_static, server_to_client, client_to_server))
+    }
+
+    fn new(stream: S, peer: [u8; 32], send: Zeroizing<[u8; 32]>, receive: Zeroizing<[u8; 32]>) -> Self {
+        Self {
+            stream,
+            peer,
+            send: ChaCha20Poly1305::new(Key::from_slice(&send[..])),
+            receive: ChaCha20Poly1305::new(Key::from_slice(&receive[..])),
+            sent: 0,
+            received: 0,
+        }
+    }
+
+    /// The authenticated transport key of the other end
+    pub fn peer(&self) -> &[u8; 32] {
+        &self.peer
+    }
+
+    /// Encrypt and send one frame
+    pub fn send(&mut self, plaintext: &[u8]) -> Result<(), SignerError> {
+        let ciphertext = self
+            .send
+            .encrypt(&counter_nonce(self.sent), plaintext)
+            .map_err(|_| SignerError::IntegrityError("frame encryption failed".to_string()))?;
+        if ciphertext.len() > MAX_FRAME {
+            return Err(SignerError::IoError("frame too large".to_string()));
+        }
+        self.sent += 1;
+        self.stream.write_all(&(ciphertext.len() as u32).to_be_bytes())?;
+        self.stream.write_all(&ciphertext)?;
+        self.stream.flush()?;
+        Ok(())
+    }
+
+    /// Receive and decrypt one frame; `None` once the peer has closed
+    pub fn receive(&mut self) -> Result<Option<Zeroizing<Vec<u8>>>, SignerError> {
+        let mut length = [0u8; 4];
+        match self.stream.read_exact(&mut length) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e.into()),
+        }
+        let length = u32::from_be_bytes(length) as usize;
+        if length > MAX_FRAME {
+            return Err(SignerError::IoError("frame too large".to_string()));
+        }
+        let mut ciphertext = vec![0u8; length];
+        self.stream.read_exact(&mut ciphertext)?;
+        let plaintext = self
+            .receive
+            .decrypt(&counter_nonce(self.received), ciphertext.as_slice())
+            .map_err(|_| SignerError::IntegrityError("frame failed authentication".to_string()))?;
+        self.received += 1;
+        Ok(Some(Zeroizing::new(plaintext)))
+    }
+}
+
+fn transcript(client_ephemeral: &[u8; 32], client_static: &[u8; 32], server_ephemeral: &[u8; 32], server_static: &[u8; 32]) -> [u8; 32] {
+    Sha256::new()
+        .chain_update(PROTOCOL_CONTEXT)
+        .chain_update(client_ephemeral)
+        .chain_update(client_static)
+        .chain_update(server_ephemeral)
+        .chain_update(server_static)
+        .finalize()
+        .into()
+}
+
+fn role_message(transcript: &[u8; 32], role: &[u8]) -> Vec<u8> {
+    [&transcript[..], role].concat()
+}
+
+fn raw_signature(identity: &SecureEd25519Seed, message: &[u8]) -> Result<[u8; 64], SignerError> {
+    let signature = bs58::decode(identity.sign_with_context(message, PROTOCOL_CONTEXT)?.signature).into_vec()?;
+    signature.try_into().map_err(|_| SignerError::SigningFailed("unexpected signature length".to_string()))
+}
+
+/// Client-to-server and server-to-client keys
+#[allow(clippy::type_complexity)]
+fn session_keys(shared: &[u8; 32], transcript: &[u8; 32]) -> Result<(Zeroizing<[u8; 32]>, Zeroizing<[u8; 32]>), SignerError> {
+    let hkdf = Hkdf::<Sha256>::new(Some(transcript), shared);
+    let mut client_to_server = Zeroizing::new([0u8; 32]);
+    let mut server_to_client = Zeroizing::new([0u8; 32]);
+    hkdf.expand(b"client to server", client_to_server.as_mut())
+        .and_then(|()| hkdf.expand(b"server to client", server_to_client.as_mut()))
+        .map_err(|_| SignerError::KeyDerivationFailed("HKDF output too long".to_string()))?;
+    Ok((client_to_server, server_to_client))
+}
+
+fn counter_nonce(counter: u64) -> Nonce {
+    let mut nonce = [0u8; 12];
+    nonce[4..].copy_from_slice(&counter.to_be_bytes());
+    nonce.into()
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+enum Request {
+    Sign { key: String, message: String },
+    GetPubkey { key: String },
+}
+
+#[derive(Serialize, Deserialize)]
+struct KeyReply {
+    public_key: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    signature: Option<String>,
+}
+
+struct RemoteKey {
+    name: String,
+    role: KeyRole,
+    seed: SecureEd25519Seed,
+}
+
+/// The server: validator keys behind an authenticated listener
+pub struct RemoteSigner {
+    transport: SecureEd25519Seed,
+    authorized_clients: Vec<[u8; 32]>,
+    keys: Vec<RemoteKey>,
+    connections: AtomicUsize,
+}
+
+impl RemoteSigner {
+    /// A server authenticating as `transport`, open to the given client
+    /// transport keys
+    pub fn new(transport: SecureEd25519Seed, authorized_clients: Vec<[u8; 32]>) -> Self {
+        Self {
+            transport,
+            authorized_clients,
+            keys: Vec::new(),
+            connections: AtomicUsize::new(0),
+        }
+    }
+
+    /// The key clients pin
+    pub fn transport_public_key(&self) -> [u8; 32] {
+        self.transport.public_key()
+    }
+
+    /// Serve `seed` under `name`, limited to what `role` may sign
+    pub fn add_key(&mut self, name: &str, role: KeyRole, seed: SecureEd25519Seed) -> [u8; 32] {
+        let public_key = seed.public_key();
+        self.keys.retain(|key| key.name != name);
+        self.keys.push(RemoteKey {
+            name: name.to_string(),
+            role,
+            seed,
+        });
+        public_key
+    }
+
+    /// Accept connections until `listener` fails
+    pub fn serve(&self, listener: TcpListener) -> Result<(), SignerError> {
+        std::thread::scope(|scope| {
+            for stream in listener.incoming() {
+                let stream = match stream {
+                    Ok(stream) => stream,
+                    Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                    Err(e) => return Err(SignerError::from(e)),
+                };
+                if self.connections.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+                    self.connections.fetch_sub(1, Ordering::SeqCst);
+                    continue;
+                }
+                scope.spawn(move || {
+                    let _ = stream.set_nodelay(true);
+                    let _ = self.handle_connection(stream);
+                    self.connections.fetch_sub(1, Ordering::SeqCst);
+                });
+            }
+            Ok(())
+        })
+    }
+
+    /// Authenticate a client and answer its requests until it disconnects
+    pub fn handle_connection<S: Read + Write>(&self, stream: S) -> Result<(), SignerError> {
+        let mut channel = SecureChannel::accept(stream, &self.transport, &self.authorized_clients)?;
+        while let Some(request) = channel.receive()? {
+            let response = match self.execute(&request) {
+                Ok(data) => Response {
+                    id: None,
+                    success: true,
+                    data: Some(data),
+                    error: None,
+                },
+                Err(e) => Response {
+                    id: None,
+                    success: false,
+                    data: None,
+                    error: Some(e.to_string()),
+                },
+            };
+            channel.send(&serde_json::to_vec(&response)?)?;
+        }
+        Ok(())
+    }
+
+    fn execute(&self, request: &[u8]) -> Result<serde_json::Value, SignerError> {
+        let request: Request =
+            serde_json::from_slice(request).map_err(|e| SignerError::SerializationError(e.to_string()))?;
+        let (name, message) = match &request {
+            Request::Sign { key, message } => (key, Some(Encoding::Base64.decode(message)?)),
+            Request::GetPubkey { key } => (key, None),
+        };
+        let key = self
+            .keys
+            .iter()
+            .find(|key| key.name == *name)
+            .ok_or_else(|| SignerError::ContainerError(format!("no key named '{}'", name)))?;
+
+        let signature = match message {
+            Some(message) => {
+                key.role.check(&message)?;
+                Some(key.seed.sign(&message)?.signature)
+            }
+            None => None,
+        };
+        Ok(serde_json::to_value(KeyReply {
+            public_key: bs58::encode(key.seed.public_key()).into_string(),
+            signature,
+        })?)
+    }
+}
+
+/// A validator-side connection to a [`RemoteSigner`]
+pub struct RemoteSignerClient<S = TcpStream> {
+    channel: SecureChannel<S>,
+}
+
+impl RemoteSignerClient<TcpStream> {
+    /// Connect over TCP and authenticate both ends
+    pub fn connect(
+        address: impl ToSocketAddrs,
+        identity: &SecureEd25519Seed,
+        server_key: &[u8; 32],
+    ) -> Result<Self, SignerError> {
+        let stream = TcpStream::connect(address)?;
+        stream.set_nodelay(true)?;
+        Self::over(stream, identity, server_key)
+    }
+}
+
+impl<S: Read + Write> RemoteSignerClient<S> {
+    /// Authenticate over an existing stream
+    pub fn over(stream: S, identity: &SecureEd25519Seed, server_key: &[u8; 32]) -> Result<Self, SignerError> {
+        Ok(Self {
+            channel: SecureChannel::connect(stream, identity, server_key)?,
+        })
+    }
+
+    /// The public key of the server's key named `key`
+    pub fn public_key(&mut self, key: &str) -> Result<[u8; 32], SignerError> {
+        let reply = self.request(&serde_json::json!({ "action": "get_pubkey", "key": key }))?;
+        decode_public_key(&reply.public_key)
+    }
+
+    /// Sign `message` with the server's key named `key`
+    ///
+    /// The signature is checked against the key's public key before it is
+    /// returned.
+    pub fn sign(&mut self, key: &str, message: &[u8]) -> Result<[u8; 64], SignerError> {
+        let request = serde_json::json!({
+            "action": "sign",
+            "key": key,
+            "message": Encoding::Base64.encode(message),
+        });
+        let reply = self.request(&request)?;
+        let public_key = decode_public_key(&reply.public_key)?;
+        let signature: [u8; 64] = bs58::decode(reply.signature.unwrap_or_default())
+            .into_vec()?
+            .try_into()
+            .map_err(|_| SignerError::SigningFailed("remote signer returned no signature".to_string()))?;
+
+        VerifyingKey::from_bytes(&public_key)
+            .and_then(|verifying_key| verifying_key.verify(message, &Signature::from_bytes(&signature)))
+            .map_err(|_| SignerError::SigningFailed("remote signature does not verify".to_string()))?;
+        Ok(signature)
+    }
+
+    fn request(&mut self, request: &serde_json::Value) -> Result<KeyReply, SignerError> {
+        self.channel.send(&serde_json::to_vec(request)?)?;
+        let frame = self
+            .channel
+            .receive()?
+            .ok_or_else(|| SignerError::IoError("remote signer closed the connection".to_string()))?;
+
+        #[derive(Deserialize)]
+        struct Reply {
+            success: bool,
+            data: Option<KeyReply>,
+            error: Option<String>,
+        }
+        let reply: Reply = serde_json::from_slice(&frame)?;
+        match (reply.success, reply.data) {
+            (true, Some(data)) => Ok(data),
+            _ => Err(SignerError::BackendError(reply.error.unwrap_or_else(|| "remote signer failed".to_string()))),
+        }
+    }
+}
+
+fn decode_public_key(public_key: &str) -> Result<[u8; 32], SignerError> {
+    bs58::decode(public_key)
+        .into_vec()?
+        .try_into()
+        .map_err(|_| SignerError::BackendError("remote signer returned a malformed public key".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seed(byte: u8) -> SecureEd25519Seed {
+        SecureEd25519Seed::from_bytes(&[byte; 32]).unwrap()
+    }
+
+    /// A legacy message with one instruction for `program`
+    fn message_calling(program: &[u8; 32]) -> Vec<u8> {
+        let mut message = vec![1, 0, 1, 2];
+        message.extend(seed(3).public_key());
+        message.extend(program);
+        message.extend([0u8; 32]);
+        message.extend([1, 1, 1, 0, 2, 0xaa, 0xbb]);
+        message
+    }
+
+    fn start_server(signer: RemoteSigner) -> (std::net::SocketAddr, std::thread::JoinHandle<()>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let handle = std::thread::spawn(move || {
+            for stream in listener.incoming().take(2) {
+                let _ = signer.handle_connection(stream.unwrap());
+            }
+        });
+        (address, handle)
+    }
+
+    #[test]
+    fn test_vote_key_signs_only_votes_over_authenticated_channel() {
+        std::env::set_var("SIGNER_ALLOW_INSECURE_MEMORY", "1");
+        let client_identity = seed(1);
+        let mut signer = RemoteSigner::new(seed(2), vec![client_identity.public_key()]);
+        let server_key = signer.transport_public_key();
+        let vote_key = signer.add_key("vote", KeyRole::Vote, seed(3));
+        let (address, server) = start_server(signer);
+
+        let mut client = RemoteSignerClient::connect(address, &client_identity, &server_key).unwrap();
+        assert_eq!(client.public_key("vote").unwrap(), vote_key);
+
+        let vote_program: [u8; 32] = bs58::decode(VOTE_PROGRAM_ID).into_vec().unwrap().try_into().unwrap();
+        let vote = message_calling(&vote_program);
+        let signature = client.sign("vote", &vote).unwrap();
+        assert!(VerifyingKey::from_bytes(&vote_key)
+            .unwrap()
+            .verify(&vote, &Signature::from_bytes(&signature))
+            .is_ok());
+
+        let transfer = message_calling(&[0u8; 32]);
+        let refused = client.sign("vote", &transfer).unwrap_err();
+        assert!(refused.to_string().contains("Vote program"), "{}", refused);
+        drop(client);
+
+        // A client the server does not know never gets a channel
+        assert!(RemoteSignerClient::connect(address, &seed(9), &server_key).is_err());
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_client_rejects_unpinned_server() {
+        std::env::set_var("SIGNER_ALLOW_INSECURE_MEMORY", "1");
+        let client_identity = seed(1);
+        let signer = RemoteSigner::new(seed(2), vec![client_identity.public_key()]);
+        let (address, server) = start_server(signer);
+
+        let wrong_pin = seed(4).public_key();
+        let error = RemoteSignerClient::connect(address, &client_identity, &wrong_pin).err().unwrap();
+        assert!(error.to_string().contains("pinned"), "{}", error);
+        drop(TcpStream::connect(address));
+        server.join().unwrap();
+    }
+}
@@ -0,0 +1,262 @@
+//! Key rotation attestations
+//!
+//! When a key is replaced, anyone who trusted the old key needs proof that
+//! the new one is its legitimate successor. [`rotate_key`] generates the
+//! new key into a container and returns a [`RotationAttestation`]: a JSON
+//! document naming both keys, when and why, signed by each of them.
+//!
+//! - The old key's signature shows its holder authorized the rotation.
+//! - The new key's signature shows the new key's holder took part, so a
+//!   stolen old key cannot hand trust to a key nobody controls.
+//!
+//! Both sign the SHA-256 digest of the attestation body, each under its own
+//! domain separator, so neither signature can stand in for the other.
+//! [`verify_rotation_chain`] follows a sequence of rotations from a known
+//! key to the current one.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::crypto::{get_locking_mode, EncryptedKeyContainer};
+use crate::entropy::fill_random;
+use crate::error::SignerError;
+use crate::secure_buffer::SecureBuffer;
+
+/// Attestation format version
+const ATTESTATION_VERSION: u8 = 1;
+
+/// Domain separators for the old and new key's signatures
+const OLD_KEY_DOMAIN: &[u8] = b"coldstar-rotation-v1/old";
+const NEW_KEY_DOMAIN: &[u8] = b"coldstar-rotation-v1/new";
+
+/// Cross-signed proof that `new_public_key` replaces `old_public_key`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct RotationAttestation {
+    /// Attestation format version
+    pub version: u8,
+    /// Key being retired (base58)
+    pub old_public_key: String,
+    /// Key replacing it (base58)
+    pub new_public_key: String,
+    /// Why the key was rotated ("scheduled", "suspected compromise", ...)
+    pub reason: String,
+    /// Unix timestamp the attestation was signed
+    pub issued_at: u64,
+    /// Old key's signature over the digest (base58)
+    pub old_key_signature: String,
+    /// New key's signature over the digest (base58)
+    pub new_key_signature: String,
+}
+
+#[derive(Serialize)]
+struct AttestationBody<'a> {
+    version: u8,
+    old_public_key: &'a str,
+    new_public_key: &'a str,
+    reason: &'a str,
+    issued_at: u64,
+}
+
+impl RotationAttestation {
+    /// SHA-256 of the attestation body (everything except the signatures)
+    pub fn digest(&self) -> Result<[u8; 32], SignerError> {
+        let body = serde_json::to_vec(&AttestationBody {
+            version: self.version,
+            old_public_key: &self.old_public_key,
+            new_public_key: &self.new_public_key,
+            reason: &self.reason,
+            issued_at: self.issued_at,
+        })?;
+        Ok(Sha256::digest(body).into())
+    }
+
+    /// Verify both signatures
+    pub fn verify(&self) -> Result<(), SignerError> {
+        if self.version != ATTESTATION_VERSION {
+            return Err(SignerError::RotationError(format!(
+                "unsupported attestation version {}",
+                self.version
+            )));
+        }
+        if self.old_public_key == self.new_public_key {
+            return Err(SignerError::RotationError("old and new keys are the same".to_string()));
+        }
+        let digest = self.digest()?;
+        verify_ed25519(&self.old_public_key, &signed_message(OLD_KEY_DOMAIN, &digest), &self.old_key_signature)?;
+        verify_ed25519(&self.new_public_key, &signed_message(NEW_KEY_DOMAIN, &digest), &self.new_key_signature)
+    }
+
+    /// Serialize the attestation to JSON
+    pub fn to_json(&self) -> Result<String, SignerError> {
+        serde_json::to_string_pretty(self).map_err(|e| SignerError::SerializationError(e.to_string()))
+    }
+
+    /// Parse an attestation from JSON (without verifying it)
+    pub fn from_json(json: &str) -> Result<Self, SignerError> {
+        serde_json::from_str(json).map_err(|e| SignerError::SerializationError(e.to_string()))
+    }
+}
+
+/// Generate a new key, encrypted with `new_passphrase`, and attest that it
+/// replaces the key in `old`
+pub fn rotate_key(
+    old: &EncryptedKeyContainer,
+    old_passphrase: &str,
+    new_passphrase: &str,
+    reason: &str,
+) -> Result<(EncryptedKeyContainer, RotationAttestation), SignerError> {
+    let mut seed = SecureBuffer::with_mode(32, get_locking_mode())?;
+    fill_random(seed.as_mut_slice())?;
+    let new = EncryptedKeyContainer::encrypt_with_cipher(seed.as_slice(), new_passphrase, old.cipher);
+    seed.zeroize();
+    let new = new?;
+
+    let attestation = attest_rotation(old, old_passphrase, &new, new_passphrase, reason)?;
+    Ok((new, attestation))
+}
+
+/// Attest that the key in `new` replaces the key in `old`, for a new key
+/// generated elsewhere (a [`KeyCeremony`](crate::ceremony::KeyCeremony),
+/// another machine)
+pub fn attest_rotation(
+    old: &EncryptedKeyContainer,
+    old_passphrase: &str,
+    new: &EncryptedKeyContainer,
+    new_passphrase: &str,
+    reason: &str,
+) -> Result<RotationAttestation, SignerError> {
+    let old_key = signing_key(old, old_passphrase)?;
+    let new_key = signing_key(new, new_passphrase)?;
+
+    let mut attestation = RotationAttestation {
+        version: ATTESTATION_VERSION,
+        old_public_key: bs58::encode(old_key.verifying_key().as_bytes()).into_string(),
+        new_public_key: bs58::encode(new_key.verifying_key().as_bytes()).into_string(),
+        reason: reason.to_string(),
+        issued_at: unix_now(),
+        old_key_signature: String::new(),
+        new_key_signature: String::new(),
+    };
+    if attestation.old_public_key == attestation.new_public_key {
+        return Err(SignerError::RotationError("old and new keys are the same".to_string()));
+    }
+    let digest = attestation.digest()?;
+    let old_signature: Signature = old_key.sign(&signed_message(OLD_KEY_DOMAIN, &digest));
+    let new_signature: Signature = new_key.sign(&signed_message(NEW_KEY_DOMAIN, &digest));
+    attestation.old_key_signature = bs58::encode(old_signature.to_bytes()).into_string();
+    attestation.new_key_signature = bs58::encode(new_signature.to_bytes()).into_string();
+
+    Ok(attestation)
+}
+
+/// Verify a sequence of rotations starting at `trusted_key` (base58),
+/// returning the key it ends at
+///
+/// Each attestation must retire the key the previous one introduced, and
+/// be issued no earlier than it.
+pub fn verify_rotation_chain(trusted_key: &str, attestations: &[RotationAttestation]) -> Result<String, SignerError> {
+    let mut current = trusted_key;
+    let mut issued_at = 0;
+    for attestation in attestations {
+        if attestation.old_public_key != current {
+            return Err(SignerError::RotationError(format!(
+                "attestation retires {}, expected {}",
+                attestation.old_public_key, current
+            )));
+        }
+        if attestation.issued_at < issued_at {
+            return Err(SignerError::RotationError("attestations are out of order".to_string()));
+        }
+        attestation.verify()?;
+        current = &attestation.new_public_key;
+        issued_at = attestation.issued_at;
+    }
+    Ok(current.to_string())
+}
+
+fn signing_key(container: &EncryptedKeyContainer, passphrase: &str) -> Result<SigningKey, SignerError> {
+    let mut secret = container.decrypt_key(passphrase)?;
+    let signing_key = secret
+        .as_slice()
+        .try_into()
+        .map(SigningKey::from_bytes)
+        .map_err(|_| SignerError::InvalidKeyFormat(secret.len()));
+    secret.zeroize();
+    signing_key
+}
+
+fn signed_message(domain: &[u8], digest: &[u8; 32]) -> Vec<u8> {
+    [domain, digest].concat()
+}
+
+fn verify_ed25519(public_key: &str, message: &[u8], signature: &str) -> Result<(), SignerError> {
+    let public_key: [u8; 32] = bs58::decode(public_key)
+        .into_vec()?
+        .try_into()
+        .map_err(|_| SignerError::RotationError(format!("invalid public key {}", public_key)))?;
+    let signature: [u8; 64] = bs58::decode(signature)
+        .into_vec()?
+        .try_into()
+        .map_err(|_| SignerError::RotationError("invalid signature encoding".to_string()))?;
+    VerifyingKey::from_bytes(&public_key)
+        .map_err(|e| SignerError::RotationError(e.to_string()))?
+        .verify(message, &Signature::from_bytes(&signature))
+        .map_err(|_| SignerError::RotationError("signature verification failed".to_string()))
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::Cipher;
+    use crate::kdf::KdfParams;
+
+    fn container(seed: u8) -> EncryptedKeyContainer {
+        EncryptedKeyContainer::encrypt_with_kdf(&[seed; 32], "pw", Cipher::Aes256Gcm, KdfParams::MINIMUM).unwrap()
+    }
+
+    #[test]
+    fn test_rotation_is_cross_signed() {
+        std::env::set_var("SIGNER_ALLOW_INSECURE_MEMORY", "1");
+        let old = container(1);
+        let (new, attestation) = rotate_key(&old, "pw", "new", "scheduled").unwrap();
+        assert_eq!(attestation.old_public_key, old.public_key.clone().unwrap());
+        assert_eq!(attestation.new_public_key, new.public_key.clone().unwrap());
+        assert!(new.decrypt_key("new").is_ok());
+
+        let parsed = RotationAttestation::from_json(&attestation.to_json().unwrap()).unwrap();
+        parsed.verify().unwrap();
+
+        // The reason is signed, and the two signatures are not interchangeable
+        let mut tampered = parsed.clone();
+        tampered.reason = "compromise".to_string();
+        assert!(matches!(tampered.verify(), Err(SignerError::RotationError(_))));
+        let mut swapped = parsed.clone();
+        core::mem::swap(&mut swapped.old_key_signature, &mut swapped.new_key_signature);
+        assert!(swapped.verify().is_err());
+    }
+
+    #[test]
+    fn test_rotation_chain() {
+        std::env::set_var("SIGNER_ALLOW_INSECURE_MEMORY", "1");
+        let (a, b, c) = (container(1), container(2), container(3));
+        let first = attest_rotation(&a, "pw", &b, "pw", "scheduled").unwrap();
+        let second = attest_rotation(&b, "pw", &c, "pw", "scheduled").unwrap();
+        let root = a.public_key.clone().unwrap();
+
+        let head = verify_rotation_chain(&root, &[first.clone(), second.clone()]).unwrap();
+        assert_eq!(head, c.public_key.clone().unwrap());
+        assert!(verify_rotation_chain(&root, std::slice::from_ref(&second)).is_err());
+        assert!(verify_rotation_chain(&root, &[second, first]).is_err());
+        assert!(attest_rotation(&a, "pw", &a, "pw", "none").is_err());
+    }
+}
@@ -2,15 +2,185 @@
 //!
 //! This module provides a memory-locked buffer that:
 //! - Locks memory to prevent swapping (mlock)
+//! - Keeps its pages out of core dumps (`MADV_DONTDUMP` on Linux)
 //! - Automatically zeroizes on drop
 //! - Handles panic-safe cleanup
 //! - Prevents copies of sensitive data
+//! - Optionally ([`LockingMode::Hardened`]) sits between `PROT_NONE` guard
+//!   pages behind a canary, so overflows fault instead of corrupting or
+//!   leaking the key, on pages forked children receive zeroed
+//!   (`MADV_WIPEONFORK`)
+//!
+//! Locking goes through the process-wide [`PageLocker`], [`OsPageLocker`]
+//! unless replaced with [`set_page_locker`]. Targets without virtual
+//! memory, where nothing can be swapped out, install [`NoopPageLocker`].
+//!
+//! [`SecureBuffer::lock_status`] tells whether one buffer is locked, and
+//! [`locking_report`] how locking is going for the whole process, so a
+//! permissive fallback does not go unnoticed.
 
-use std::ops::{Deref, DerefMut};
-use std::ptr;
+use alloc::string::ToString;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ops::{Deref, DerefMut};
+use core::ptr;
+use core::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+use serde::Serialize;
 use zeroize::Zeroize;
 
+use crate::crypto::get_locking_mode;
 use crate::error::SignerError;
+use crate::metrics::{self, Counter};
+use crate::sync::Mutex;
+
+/// Keeps memory holding secrets from being paged out
+pub trait PageLocker: Sync {
+    /// Lock the pages holding `data`; `false` if they could not be locked
+    fn lock(&self, data: &[u8]) -> bool;
+
+    /// Undo a successful [`lock`](PageLocker::lock)
+    fn unlock(&self, data: &[u8]);
+}
+
+/// `mlock` on Unix, `VirtualLock` on Windows, and a failure elsewhere
+pub struct OsPageLocker;
+
+impl PageLocker for OsPageLocker {
+    fn lock(&self, data: &[u8]) -> bool {
+        os_lock_memory(data)
+    }
+
+    fn unlock(&self, data: &[u8]) {
+        os_unlock_memory(data)
+    }
+}
+
+/// Reports every lock as successful, for targets without swap (secure
+/// elements, microcontrollers), so [`LockingMode::Strict`] can be used there
+pub struct NoopPageLocker;
+
+impl PageLocker for NoopPageLocker {
+    fn lock(&self, _data: &[u8]) -> bool {
+        true
+    }
+
+    fn unlock(&self, _data: &[u8]) {}
+}
+
+static PAGE_LOCKER: Mutex<Option<&'static dyn PageLocker>> = Mutex::new(None);
+
+/// Replace the process-wide page locker
+///
+/// Buffers unlock with the locker current when they are dropped, so set it
+/// once at startup, before any buffer is created.
+pub fn set_page_locker(locker: &'static dyn PageLocker) {
+    *PAGE_LOCKER.lock() = Some(locker);
+}
+
+fn page_locker() -> &'static dyn PageLocker {
+    PAGE_LOCKER.lock().unwrap_or(&OsPageLocker)
+}
+
+/// Bytes locked by live buffers
+static LOCKED_BYTES: AtomicU64 = AtomicU64::new(0);
+static LOCK_FAILURES: AtomicU64 = AtomicU64::new(0);
+/// Outcome of the last lock: 0 none yet, 1 locked, 2 failed
+static LAST_LOCK: AtomicU8 = AtomicU8::new(0);
+
+fn lock_memory(data: &[u8]) -> bool {
+    let locked = page_locker().lock(data);
+    if locked {
+        LOCKED_BYTES.fetch_add(data.len() as u64, Ordering::Relaxed);
+    } else {
+        LOCK_FAILURES.fetch_add(1, Ordering::Relaxed);
+    }
+    LAST_LOCK.store(if locked { 1 } else { 2 }, Ordering::Relaxed);
+    locked
+}
+
+fn unlock_memory(data: &[u8]) {
+    page_locker().unlock(data);
+    LOCKED_BYTES.fetch_sub(data.len() as u64, Ordering::Relaxed);
+}
+
+/// The first `locked_len` bytes of `data`'s allocation, which stay locked
+/// when the buffer shrinks
+fn locked_prefix(data: &Vec<u8>, locked_len: usize) -> &[u8] {
+    debug_assert!(locked_len <= data.capacity());
+    // SAFETY: shrinking never reallocates, so the allocation still holds
+    // the `locked_len` initialized bytes that were locked
+    unsafe { core::slice::from_raw_parts(data.as_ptr(), locked_len) }
+}
+
+/// Whether one buffer is locked ([`SecureBuffer::lock_status`])
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct LockStatus {
+    /// Whether `mlock`/`VirtualLock` succeeded for the buffer
+    pub locked: bool,
+    /// Whether it has guard pages ([`LockingMode::Hardened`])
+    pub hardened: bool,
+    /// Bytes the buffer holds locked, including a hardened buffer's canary
+    /// and padding
+    pub locked_bytes: u64,
+}
+
+/// How memory locking is going for the process ([`locking_report`])
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct LockingReport {
+    /// Whether the most recent lock succeeded (`None` before the first)
+    pub last_lock_succeeded: Option<bool>,
+    /// Locks that have failed since the process started, whether they
+    /// were fatal ([`LockingMode::Strict`]) or fell back
+    /// ([`LockingMode::Permissive`])
+    pub lock_failures: u64,
+    /// Bytes currently locked by this crate's buffers
+    pub locked_bytes: u64,
+    /// Soft `RLIMIT_MEMLOCK` in bytes (`u64::MAX`: unlimited; `None`: no
+    /// such limit on this platform)
+    pub memlock_limit: Option<u64>,
+    /// Hard `RLIMIT_MEMLOCK`, the most the soft limit can be raised to
+    pub memlock_max: Option<u64>,
+    /// Whether buffers fall back to unlocked memory when locking fails
+    pub permissive: bool,
+}
+
+/// Report on memory locking across every buffer of this crate
+pub fn locking_report() -> LockingReport {
+    let (memlock_limit, memlock_max) = match memlock_rlimit() {
+        Some((current, max)) => (Some(current), Some(max)),
+        None => (None, None),
+    };
+    LockingReport {
+        last_lock_succeeded: match LAST_LOCK.load(Ordering::Relaxed) {
+            0 => None,
+            last => Some(last == 1),
+        },
+        lock_failures: LOCK_FAILURES.load(Ordering::Relaxed),
+        locked_bytes: LOCKED_BYTES.load(Ordering::Relaxed),
+        memlock_limit,
+        memlock_max,
+        permissive: get_locking_mode() == LockingMode::Permissive,
+    }
+}
+
+/// Soft and hard `RLIMIT_MEMLOCK`
+#[cfg(unix)]
+fn memlock_rlimit() -> Option<(u64, u64)> {
+    let mut limit = libc::rlimit { rlim_cur: 0, rlim_max: 0 };
+    // SAFETY: getrlimit only writes the struct it is given
+    if unsafe { libc::getrlimit(libc::RLIMIT_MEMLOCK, &mut limit) } != 0 {
+        return None;
+    }
+    // rlim_t is narrower than u64 on some targets
+    #[allow(clippy::unnecessary_cast)]
+    let bytes = |value: libc::rlim_t| if value == libc::RLIM_INFINITY { u64::MAX } else { value as u64 };
+    Some((bytes(limit.rlim_cur), bytes(limit.rlim_max)))
+}
+
+#[cfg(not(unix))]
+fn memlock_rlimit() -> Option<(u64, u64)> {
+    None
+}
 
 /// A secure buffer that locks its memory and zeroizes on drop
 ///
@@ -27,23 +197,56 @@ use crate::error::SignerError;
 /// # Security Properties
 ///
 /// - Memory is never swapped to disk
+/// - Memory is left out of core dumps where the platform allows
 /// - Contents are zeroized even on panic (via Drop)
 /// - No implicit copies are made
 /// - Debug output does not reveal contents
 pub struct SecureBuffer {
     /// The underlying data buffer
-    data: Vec<u8>,
+    data: Storage,
     /// Whether memory is currently locked
     is_locked: bool,
+    /// Bytes of a heap buffer's allocation that are locked
+    locked_len: usize,
+}
+
+/// Where a buffer's bytes live
+enum Storage {
+    Heap(Vec<u8>),
+    Guarded(GuardedRegion),
+}
+
+impl Storage {
+    fn as_slice(&self) -> &[u8] {
+        match self {
+            Storage::Heap(data) => data,
+            Storage::Guarded(region) => region.as_slice(),
+        }
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [u8] {
+        match self {
+            Storage::Heap(data) => data,
+            Storage::Guarded(region) => region.as_mut_slice(),
+        }
+    }
 }
 
 /// Configuration for memory locking behavior
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum LockingMode {
     /// Require memory locking - fail if mlock is not available
     Strict,
     /// Allow fallback if mlock fails (less secure, logs warning)
     Permissive,
+    /// Strict locking, plus guard pages and a canary
+    ///
+    /// The buffer gets its own pages: a `PROT_NONE` page on each side, the
+    /// data ending exactly at the trailing one, and a random canary just
+    /// before the data. Running off the end faults at once; running into
+    /// the canary from the front aborts the process on the next access or
+    /// on drop. Costs two extra mappings per buffer.
+    Hardened,
 }
 
 impl SecureBuffer {
@@ -75,10 +278,26 @@ impl SecureBuffer {
     /// * `Ok(SecureBuffer)` - A buffer (locked if possible)
     /// * `Err(SignerError)` - If strict mode and locking fails
     pub fn with_mode(capacity: usize, mode: LockingMode) -> Result<Self, SignerError> {
+        if mode == LockingMode::Hardened {
+            let region = GuardedRegion::new(capacity)?;
+            if !region.locked {
+                return Err(SignerError::MemoryLockFailed(
+                    "mlock failed on hardened buffer. \
+                     Check ulimit -l or run with CAP_IPC_LOCK capability.".to_string()
+                ));
+            }
+            return Ok(Self {
+                data: Storage::Guarded(region),
+                is_locked: true,
+                locked_len: 0,
+            });
+        }
+
         let data = vec![0u8; capacity];
 
         // Lock the memory to prevent swapping
         let locked = lock_memory(&data);
+        exclude_from_dumps(&data);
 
         if mode == LockingMode::Strict && !locked {
             return Err(SignerError::MemoryLockFailed(
@@ -88,6 +307,8 @@ impl SecureBuffer {
         }
 
         if !locked {
+            metrics::increment(Counter::MlockFallbacks, &[]);
+            #[cfg(feature = "std")]
             eprintln!(
                 "Warning: Memory locking failed. Private keys may be swapped to disk. \
                  Consider running with elevated privileges or increasing ulimit -l."
@@ -95,7 +316,8 @@ impl SecureBuffer {
         }
 
         Ok(Self {
-            data,
+            locked_len: if locked { data.len() } else { 0 },
+            data: Storage::Heap(data),
             is_locked: locked,
         })
     }
@@ -122,7 +344,7 @@ impl SecureBuffer {
     /// Create a secure buffer from existing data with configurable locking.
     pub fn from_slice_with_mode(source: &[u8], mode: LockingMode) -> Result<Self, SignerError> {
         let mut buffer = Self::with_mode(source.len(), mode)?;
-        buffer.data.copy_from_slice(source);
+        buffer.as_mut_slice().copy_from_slice(source);
         Ok(buffer)
     }
 
@@ -133,12 +355,12 @@ impl SecureBuffer {
 
     /// Get the length of the buffer
     pub fn len(&self) -> usize {
-        self.data.len()
+        self.data.as_slice().len()
     }
 
     /// Check if the buffer is empty
     pub fn is_empty(&self) -> bool {
-        self.data.is_empty()
+        self.len() == 0
     }
 
     /// Check if memory is locked
@@ -146,13 +368,32 @@ impl SecureBuffer {
         self.is_locked
     }
 
+    /// Check if the buffer has guard pages ([`LockingMode::Hardened`])
+    pub fn is_hardened(&self) -> bool {
+        matches!(self.data, Storage::Guarded(_))
+    }
+
+    /// Whether, and how much of, the buffer is locked
+    pub fn lock_status(&self) -> LockStatus {
+        let locked_bytes = match &self.data {
+            Storage::Heap(_) => self.locked_len,
+            Storage::Guarded(region) if region.locked => region.total - 2 * region.page_size,
+            Storage::Guarded(_) => 0,
+        };
+        LockStatus {
+            locked: self.is_locked,
+            hardened: self.is_hardened(),
+            locked_bytes: locked_bytes as u64,
+        }
+    }
+
     /// Get a reference to the underlying data
     ///
     /// # Security Note
     /// The returned reference is only valid within the current scope.
     /// Do not store or copy the referenced data.
     pub fn as_slice(&self) -> &[u8] {
-        &self.data
+        self.data.as_slice()
     }
 
     /// Get a mutable reference to the underlying data
@@ -161,14 +402,17 @@ impl SecureBuffer {
     /// Modifications should be done carefully. After use,
     /// call zeroize() explicitly if needed before the natural drop.
     pub fn as_mut_slice(&mut self) -> &mut [u8] {
-        &mut self.data
+        self.data.as_mut_slice()
     }
 
     /// Explicitly zeroize the buffer contents
     ///
     /// This is also called automatically on drop.
     pub fn zeroize(&mut self) {
-        self.data.zeroize();
+        match &mut self.data {
+            Storage::Heap(data) => data.zeroize(),
+            Storage::Guarded(region) => region.clear(),
+        }
     }
 
     /// Resize the buffer (maintains strict locking requirement)
@@ -182,12 +426,25 @@ impl SecureBuffer {
 
     /// Resize the buffer with configurable locking mode
     pub fn resize_with_mode(&mut self, new_len: usize, mode: LockingMode) -> Result<(), SignerError> {
-        if new_len > self.data.len() {
+        // Guarded data must end at its trailing guard page, so it always moves
+        if let Storage::Guarded(region) = &self.data {
+            let mut resized = Self::with_mode(new_len, LockingMode::Hardened)?;
+            let keep = new_len.min(region.len);
+            resized.as_mut_slice()[..keep].copy_from_slice(&region.as_slice()[..keep]);
+            *self = resized;
+            return Ok(());
+        }
+        let Storage::Heap(data) = &mut self.data else {
+            unreachable!("guarded storage handled above")
+        };
+
+        if new_len > data.len() {
             // Create new buffer first
             let mut new_data = vec![0u8; new_len];
             
             // Lock new memory before proceeding
             let new_locked = lock_memory(&new_data);
+            exclude_from_dumps(&new_data);
             
             if mode == LockingMode::Strict && !new_locked {
                 // Don't proceed - original buffer is preserved
@@ -195,24 +452,28 @@ impl SecureBuffer {
                     "mlock failed on resized buffer".to_string()
                 ));
             }
+            if !new_locked {
+                metrics::increment(Counter::MlockFallbacks, &[]);
+            }
 
             // Unlock old memory
             if self.is_locked {
-                unlock_memory(&self.data);
+                unlock_memory(locked_prefix(data, self.locked_len));
             }
 
             // Copy data and zeroize old
-            new_data[..self.data.len()].copy_from_slice(&self.data);
-            self.data.zeroize();
+            new_data[..data.len()].copy_from_slice(data);
+            data.zeroize();
 
             self.is_locked = new_locked;
-            self.data = new_data;
+            self.locked_len = if new_locked { new_len } else { 0 };
+            *data = new_data;
         } else {
             // Shrinking: just truncate and zeroize the rest
-            for byte in &mut self.data[new_len..] {
+            for byte in &mut data[new_len..] {
                 *byte = 0;
             }
-            self.data.truncate(new_len);
+            data.truncate(new_len);
         }
 
         Ok(())
@@ -221,16 +482,19 @@ impl SecureBuffer {
 
 impl Drop for SecureBuffer {
     fn drop(&mut self) {
-        // CRITICAL: Zeroize memory before releasing
-        // This happens even on panic due to Drop semantics
-        self.data.zeroize();
+        // Guarded regions zeroize, unlock and unmap themselves
+        if let Storage::Heap(data) = &mut self.data {
+            // CRITICAL: Zeroize memory before releasing
+            // This happens even on panic due to Drop semantics
+            data.as_mut_slice().zeroize();
 
-        // Unlock the memory
-        if self.is_locked {
-            unlock_memory(&self.data);
-        }
+            // Unlock the memory, all of it if the buffer shrank
+            if self.is_locked {
+                unlock_memory(locked_prefix(data, self.locked_len));
+            }
 
-        // Memory will be freed by Vec's Drop
+            // Memory will be freed by Vec's Drop
+        }
     }
 }
 
@@ -238,31 +502,96 @@ impl Deref for SecureBuffer {
     type Target = [u8];
 
     fn deref(&self) -> &Self::Target {
-        &self.data
+        self.data.as_slice()
     }
 }
 
 impl DerefMut for SecureBuffer {
     fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.data
+        self.data.as_mut_slice()
     }
 }
 
 // Prevent accidental debug printing of sensitive data
-impl std::fmt::Debug for SecureBuffer {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl core::fmt::Debug for SecureBuffer {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         f.debug_struct("SecureBuffer")
-            .field("len", &self.data.len())
+            .field("len", &self.len())
             .field("is_locked", &self.is_locked)
+            .field("is_hardened", &self.is_hardened())
             .field("data", &"[REDACTED]")
             .finish()
     }
 }
 
+/// Keep the pages holding `data` out of core dumps (best effort)
+///
+/// Heap buffers share pages with other allocations, which are excluded
+/// along with them; that only ever removes data from a dump. The advice is
+/// not undone when the buffer is freed, since a neighbour may still be live.
+#[cfg(any(target_os = "linux", target_os = "android", target_os = "freebsd"))]
+fn exclude_from_dumps(data: &[u8]) -> bool {
+    #[cfg(target_os = "freebsd")]
+    const ADVICE: libc::c_int = libc::MADV_NOCORE;
+    #[cfg(not(target_os = "freebsd"))]
+    const ADVICE: libc::c_int = libc::MADV_DONTDUMP;
+
+    if data.is_empty() {
+        return true;
+    }
+    let page = page_size();
+    let start = data.as_ptr() as usize / page * page;
+    let end = (data.as_ptr() as usize + data.len()).div_ceil(page) * page;
+    // SAFETY: the range covers mapped pages of this buffer; the advice
+    // changes what is dumped, not what the process sees
+    unsafe { libc::madvise(start as *mut libc::c_void, end - start, ADVICE) == 0 }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android", target_os = "freebsd")))]
+fn exclude_from_dumps(_data: &[u8]) -> bool {
+    // No per-range dump exclusion on this platform
+    false
+}
+
+/// Keep `pages` from reaching forked children: Linux and FreeBSD hand the
+/// child zeroed pages, macOS leaves them unmapped in the child
+///
+/// Only for page-aligned memory the buffer owns outright; applied to a
+/// shared heap page it would wipe unrelated allocations in the child.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn exclude_from_fork(pages: &mut [u8]) -> bool {
+    // SAFETY: `pages` is a whole-page region owned by the caller.
+    // Fails (EINVAL) before Linux 4.14.
+    unsafe { libc::madvise(pages.as_mut_ptr() as *mut libc::c_void, pages.len(), libc::MADV_WIPEONFORK) == 0 }
+}
+
+#[cfg(any(target_os = "freebsd", target_os = "macos", target_os = "ios"))]
+fn exclude_from_fork(pages: &mut [u8]) -> bool {
+    #[cfg(target_os = "freebsd")]
+    const INHERIT: libc::c_int = libc::INHERIT_ZERO;
+    #[cfg(not(target_os = "freebsd"))]
+    const INHERIT: libc::c_int = libc::VM_INHERIT_NONE;
+
+    // SAFETY: `pages` is a whole-page region owned by the caller
+    unsafe { libc::minherit(pages.as_mut_ptr() as *mut libc::c_void, pages.len(), INHERIT) == 0 }
+}
+
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "freebsd",
+    target_os = "macos",
+    target_os = "ios"
+)))]
+fn exclude_from_fork(_pages: &mut [u8]) -> bool {
+    // Nothing to do without fork (Windows), or no way to do it
+    cfg!(windows)
+}
+
 /// Lock memory to prevent swapping (platform-specific)
 #[cfg(unix)]
-fn lock_memory(data: &[u8]) -> bool {
-    use std::ffi::c_void;
+fn os_lock_memory(data: &[u8]) -> bool {
+    use core::ffi::c_void;
 
     if data.is_empty() {
         return true;
@@ -278,8 +607,8 @@ fn lock_memory(data: &[u8]) -> bool {
 }
 
 #[cfg(unix)]
-fn unlock_memory(data: &[u8]) {
-    use std::ffi::c_void;
+fn os_unlock_memory(data: &[u8]) {
+    use core::ffi::c_void;
 
     if data.is_empty() {
         return;
@@ -293,13 +622,13 @@ fn unlock_memory(data: &[u8]) {
 }
 
 #[cfg(windows)]
-fn lock_memory(data: &[u8]) -> bool {
+fn os_lock_memory(data: &[u8]) -> bool {
     if data.is_empty() {
         return true;
     }
 
     unsafe {
-        use std::ffi::c_void;
+        use core::ffi::c_void;
         extern "system" {
             fn VirtualLock(lpAddress: *const c_void, dwSize: usize) -> i32;
         }
@@ -309,13 +638,13 @@ fn lock_memory(data: &[u8]) -> bool {
 }
 
 #[cfg(windows)]
-fn unlock_memory(data: &[u8]) {
+fn os_unlock_memory(data: &[u8]) {
     if data.is_empty() {
         return;
     }
 
     unsafe {
-        use std::ffi::c_void;
+        use core::ffi::c_void;
         extern "system" {
             fn VirtualUnlock(lpAddress: *const c_void, dwSize: usize) -> i32;
         }
@@ -325,18 +654,245 @@ fn unlock_memory(data: &[u8]) {
 }
 
 #[cfg(not(any(unix, windows)))]
-fn lock_memory(_data: &[u8]) -> bool {
+fn os_lock_memory(_data: &[u8]) -> bool {
     // Platform doesn't support memory locking
     // Continue anyway but log a warning
+    #[cfg(feature = "std")]
     eprintln!("Warning: Memory locking not supported on this platform");
     false
 }
 
 #[cfg(not(any(unix, windows)))]
-fn unlock_memory(_data: &[u8]) {
+fn os_unlock_memory(_data: &[u8]) {
     // No-op on unsupported platforms
 }
 
+/// Size of the canary in front of guarded data
+const CANARY_SIZE: usize = 16;
+
+/// Per-process canary value, random so an overflow cannot forge it
+fn canary() -> [u8; CANARY_SIZE] {
+    static CANARY: Mutex<Option<[u8; CANARY_SIZE]>> = Mutex::new(None);
+    *CANARY.lock().get_or_insert_with(|| {
+        let mut value = [0u8; CANARY_SIZE];
+        rand::RngCore::fill_bytes(&mut crate::entropy::EntropyRng, &mut value);
+        value
+    })
+}
+
+/// Pages of its own for a hardened buffer
+///
+/// ```text
+/// | guard | ... canary | data | guard |
+/// ```
+struct GuardedRegion {
+    base: *mut u8,
+    page_size: usize,
+    total: usize,
+    /// Offset of the data from `base`
+    offset: usize,
+    /// Data length; the data ends at the trailing guard page until cleared
+    len: usize,
+    locked: bool,
+}
+
+// SAFETY: the region is owned exclusively, like a Vec's allocation
+unsafe impl Send for GuardedRegion {}
+unsafe impl Sync for GuardedRegion {}
+
+impl GuardedRegion {
+    fn new(len: usize) -> Result<Self, SignerError> {
+        let page_size = page_size();
+        let inner = (len + CANARY_SIZE).div_ceil(page_size) * page_size;
+        let total = inner + 2 * page_size;
+        let base = map_pages(total)
+            .ok_or_else(|| SignerError::MemoryLockFailed("could not map a guarded buffer".to_string()))?;
+
+        // SAFETY: both guards lie within the mapping just made
+        let guarded = unsafe { protect_none(base, page_size) && protect_none(base.add(total - page_size), page_size) };
+        if !guarded {
+            // SAFETY: `base` came from map_pages(total)
+            unsafe { unmap_pages(base, total) };
+            return Err(SignerError::MemoryLockFailed("could not protect guard pages".to_string()));
+        }
+
+        let offset = total - page_size - len;
+        let mut region = Self {
+            base,
+            page_size,
+            total,
+            offset,
+            len,
+            locked: false,
+        };
+        region.locked = lock_memory(region.inner());
+        // The region owns these pages, so forked children can lose them too
+        exclude_from_dumps(region.inner());
+        exclude_from_fork(region.inner());
+        region.canary_mut().copy_from_slice(&canary());
+        Ok(region)
+    }
+
+    /// Everything between the guards
+    fn inner(&mut self) -> &mut [u8] {
+        // SAFETY: the pages between the guards are mapped read-write
+        unsafe { core::slice::from_raw_parts_mut(self.base.add(self.page_size), self.total - 2 * self.page_size) }
+    }
+
+    fn canary_mut(&mut self) -> &mut [u8] {
+        let start = self.offset - CANARY_SIZE - self.page_size;
+        &mut self.inner()[start..start + CANARY_SIZE]
+    }
+
+    fn canary_intact(&self) -> bool {
+        // SAFETY: the canary lies between the guards, just before the data
+        let current = unsafe { core::slice::from_raw_parts(self.base.add(self.offset - CANARY_SIZE), CANARY_SIZE) };
+        current == canary()
+    }
+
+    /// Abort on a corrupted canary; the key may already have leaked or
+    /// been overwritten, and unwinding would run more code over it
+    ///
+    /// Without `std` this panics, and the target's panic handler decides.
+    fn check_canary(&self) {
+        if !self.canary_intact() {
+            #[cfg(feature = "std")]
+            {
+                eprintln!("SecureBuffer canary overwritten: memory corruption detected, aborting");
+                std::process::abort();
+            }
+            #[cfg(not(feature = "std"))]
+            panic!("SecureBuffer canary overwritten: memory corruption detected");
+        }
+    }
+
+    fn as_slice(&self) -> &[u8] {
+        self.check_canary();
+        // SAFETY: offset..offset + len lies between the guards
+        unsafe { core::slice::from_raw_parts(self.base.add(self.offset), self.len) }
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [u8] {
+        self.check_canary();
+        // SAFETY: as in as_slice, and `self` is borrowed mutably
+        unsafe { core::slice::from_raw_parts_mut(self.base.add(self.offset), self.len) }
+    }
+
+    /// Zero the data and empty the buffer, as `Vec::zeroize` does
+    fn clear(&mut self) {
+        self.as_mut_slice().zeroize();
+        self.len = 0;
+    }
+}
+
+impl Drop for GuardedRegion {
+    fn drop(&mut self) {
+        self.check_canary();
+        let locked = self.locked;
+        let inner = self.inner();
+        inner.zeroize();
+        if locked {
+            unlock_memory(inner);
+        }
+        // SAFETY: `base` came from map_pages(total) and is not used again
+        unsafe { unmap_pages(self.base, self.total) };
+    }
+}
+
+#[cfg(unix)]
+fn page_size() -> usize {
+    // SAFETY: sysconf has no preconditions
+    match unsafe { libc::sysconf(libc::_SC_PAGESIZE) } {
+        size if size > 0 => size as usize,
+        _ => 4096,
+    }
+}
+
+#[cfg(unix)]
+fn map_pages(len: usize) -> Option<*mut u8> {
+    // SAFETY: an anonymous private mapping has no preconditions
+    let ptr = unsafe {
+        libc::mmap(
+            ptr::null_mut(),
+            len,
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_PRIVATE | libc::MAP_ANON,
+            -1,
+            0,
+        )
+    };
+    (ptr != libc::MAP_FAILED).then_some(ptr as *mut u8)
+}
+
+#[cfg(unix)]
+unsafe fn protect_none(ptr: *mut u8, len: usize) -> bool {
+    libc::mprotect(ptr as *mut libc::c_void, len, libc::PROT_NONE) == 0
+}
+
+#[cfg(unix)]
+unsafe fn unmap_pages(ptr: *mut u8, len: usize) {
+    libc::munmap(ptr as *mut libc::c_void, len);
+}
+
+#[cfg(windows)]
+mod win {
+    use core::ffi::c_void;
+
+    pub const MEM_COMMIT_RESERVE: u32 = 0x3000;
+    pub const MEM_RELEASE: u32 = 0x8000;
+    pub const PAGE_READWRITE: u32 = 0x04;
+    pub const PAGE_NOACCESS: u32 = 0x01;
+
+    extern "system" {
+        pub fn VirtualAlloc(lpAddress: *mut c_void, dwSize: usize, flAllocationType: u32, flProtect: u32) -> *mut c_void;
+        pub fn VirtualProtect(lpAddress: *mut c_void, dwSize: usize, flNewProtect: u32, lpflOldProtect: *mut u32) -> i32;
+        pub fn VirtualFree(lpAddress: *mut c_void, dwSize: usize, dwFreeType: u32) -> i32;
+    }
+}
+
+#[cfg(windows)]
+fn page_size() -> usize {
+    // Every Windows target Rust supports uses 4 KiB pages
+    4096
+}
+
+#[cfg(windows)]
+fn map_pages(len: usize) -> Option<*mut u8> {
+    // SAFETY: a fresh allocation has no preconditions
+    let ptr = unsafe { win::VirtualAlloc(ptr::null_mut(), len, win::MEM_COMMIT_RESERVE, win::PAGE_READWRITE) };
+    (!ptr.is_null()).then_some(ptr as *mut u8)
+}
+
+#[cfg(windows)]
+unsafe fn protect_none(ptr: *mut u8, len: usize) -> bool {
+    let mut old = 0u32;
+    win::VirtualProtect(ptr as *mut _, len, win::PAGE_NOACCESS, &mut old) != 0
+}
+
+#[cfg(windows)]
+unsafe fn unmap_pages(ptr: *mut u8, _len: usize) {
+    win::VirtualFree(ptr as *mut _, 0, win::MEM_RELEASE);
+}
+
+#[cfg(not(any(unix, windows)))]
+fn page_size() -> usize {
+    4096
+}
+
+#[cfg(not(any(unix, windows)))]
+fn map_pages(_len: usize) -> Option<*mut u8> {
+    // No page protection on this platform; Hardened buffers are unavailable
+    None
+}
+
+#[cfg(not(any(unix, windows)))]
+unsafe fn protect_none(_ptr: *mut u8, _len: usize) -> bool {
+    false
+}
+
+#[cfg(not(any(unix, windows)))]
+unsafe fn unmap_pages(_ptr: *mut u8, _len: usize) {}
+
 /// A guard that holds a secure reference and zeroizes on drop
 ///
 /// Useful for temporary access to sensitive data within a scope.
@@ -373,7 +929,7 @@ impl<'a> Drop for SecureGuard<'a> {
                 ptr::write_volatile(byte, 0);
             }
         }
-        std::sync::atomic::compiler_fence(std::sync::atomic::Ordering::SeqCst);
+        core::sync::atomic::compiler_fence(core::sync::atomic::Ordering::SeqCst);
     }
 }
 
@@ -403,6 +959,28 @@ mod tests {
         assert!(buffer.as_slice().iter().all(|&b| b == 0));
     }
 
+    #[test]
+    fn test_lock_status_and_report() {
+        let mut buffer = SecureBuffer::new_permissive(100).unwrap();
+        let status = buffer.lock_status();
+        assert!(!status.hardened);
+        assert_eq!(status.locked, buffer.is_locked());
+        let report = locking_report();
+        assert!(report.last_lock_succeeded.is_some());
+        if status.locked {
+            assert_eq!(status.locked_bytes, 100);
+            assert!(report.locked_bytes >= 100);
+            // Shrinking keeps the whole allocation locked until drop
+            buffer.resize(10).unwrap();
+            assert_eq!(buffer.lock_status().locked_bytes, 100);
+        } else {
+            assert_eq!(status.locked_bytes, 0);
+            assert!(report.lock_failures > 0);
+        }
+        #[cfg(unix)]
+        assert!(report.memlock_max >= report.memlock_limit && report.memlock_limit.is_some());
+    }
+
     #[test]
     fn test_debug_redacts_data() {
         let buffer = SecureBuffer::from_slice_permissive(&[0xDE, 0xAD, 0xBE, 0xEF]).unwrap();
@@ -426,4 +1004,88 @@ mod tests {
             Err(e) => panic!("Unexpected error: {}", e),
         }
     }
+
+    /// `VmFlags` of the mapping containing `address`, from /proc/self/smaps
+    #[cfg(target_os = "linux")]
+    fn vm_flags(address: usize) -> Vec<String> {
+        let smaps = std::fs::read_to_string("/proc/self/smaps").unwrap();
+        let mut inside = false;
+        for line in smaps.lines() {
+            let range = line.split_whitespace().next().and_then(|field| field.split_once('-'));
+            if let Some((Ok(start), Ok(end))) =
+                range.map(|(start, end)| (usize::from_str_radix(start, 16), usize::from_str_radix(end, 16)))
+            {
+                inside = (start..end).contains(&address);
+            } else if inside && line.starts_with("VmFlags:") {
+                return line.split_whitespace().skip(1).map(str::to_string).collect();
+            }
+        }
+        panic!("no mapping contains {:#x}", address);
+    }
+
+    #[test]
+    fn test_hardened_buffer_guards_and_canary() {
+        let mut buffer = match SecureBuffer::from_slice_with_mode(&[1, 2, 3], LockingMode::Hardened) {
+            Ok(buffer) => buffer,
+            // Hardened implies strict locking
+            Err(SignerError::MemoryLockFailed(_)) => return,
+            Err(e) => panic!("Unexpected error: {}", e),
+        };
+        assert!(buffer.is_hardened() && buffer.is_locked());
+        assert_eq!(buffer.as_slice(), &[1, 2, 3]);
+
+        let Storage::Guarded(region) = &buffer.data else { unreachable!() };
+        // The data ends where the trailing guard page begins
+        let end = buffer.as_ptr() as usize + buffer.len();
+        assert_eq!(end, region.base as usize + region.total - region.page_size);
+        assert_eq!(end % region.page_size, 0);
+
+        // An underflow into the canary is detected
+        let canary_byte = region.base.wrapping_add(region.offset - 1);
+        unsafe { *canary_byte ^= 0xff };
+        assert!(!region.canary_intact());
+        unsafe { *canary_byte ^= 0xff };
+        assert!(region.canary_intact());
+
+        #[cfg(target_os = "linux")]
+        {
+            let flags = vm_flags(buffer.as_ptr() as usize);
+            // dd: MADV_DONTDUMP, wf: MADV_WIPEONFORK (Linux 4.14+)
+            assert!(flags.contains(&"dd".to_string()) && flags.contains(&"wf".to_string()), "{:?}", flags);
+            let heap = SecureBuffer::from_slice_permissive(&[1, 2, 3]).unwrap();
+            assert!(vm_flags(heap.as_ptr() as usize).contains(&"dd".to_string()));
+        }
+
+        buffer.resize_with_mode(5000, LockingMode::Hardened).unwrap();
+        assert!(buffer.is_hardened());
+        assert_eq!(&buffer[..3], &[1, 2, 3]);
+        assert_eq!(buffer.len(), 5000);
+        buffer.zeroize();
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn test_page_locker_is_pluggable() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        // Delegates to the OS, so other tests see no difference
+        struct Counting(AtomicUsize);
+        impl PageLocker for Counting {
+            fn lock(&self, data: &[u8]) -> bool {
+                self.0.fetch_add(1, Ordering::SeqCst);
+                OsPageLocker.lock(data)
+            }
+            fn unlock(&self, data: &[u8]) {
+                OsPageLocker.unlock(data)
+            }
+        }
+        static COUNTING: Counting = Counting(AtomicUsize::new(0));
+
+        set_page_locker(&COUNTING);
+        let _buffer = SecureBuffer::new_permissive(32).unwrap();
+        assert!(COUNTING.0.load(Ordering::SeqCst) >= 1);
+
+        // Without swap, every lock succeeds
+        assert!(NoopPageLocker.lock(&[0u8; 32]));
+    }
 }
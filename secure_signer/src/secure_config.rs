@@ -0,0 +1,438 @@
+//! Encrypted configuration files
+//!
+//! Services embedding the signer need credentials of their own (backend API
+//! tokens, webhook HMAC secrets, ...). This module defines a JSON config
+//! format where ordinary settings stay readable but every sensitive field is
+//! sealed individually with XChaCha20-Poly1305 under a key-encryption key
+//! (KEK). Secrets are decrypted straight into SecureBuffers on load and are
+//! never written to disk in plaintext.
+//!
+//! # Key Sources
+//!
+//! - `Passphrase`: the KEK is derived with Argon2id from a boot passphrase
+//!   and a per-file random salt.
+//! - `Tpm2`: the KEK is a 32-byte object sealed to the machine's TPM at a
+//!   persistent handle, unsealed with `tpm2_unseal` from tpm2-tools.
+//!
+//! # File Format
+//!
+//! ```json
+//! {
+//!   "version": 2,
+//!   "seal": { "method": "passphrase", "salt": "<base64>", "kdf": { ... } },
+//!   "settings": { "listen": "/run/coldstar.sock" },
+//!   "secrets": { "webhook_secret": { "nonce": "<base64>", "ciphertext": "<base64>" } },
+//!   "integrity": { "nonce": "<base64>", "ciphertext": "<base64>" }
+//! }
+//! ```
+//!
+//! Each secret's name is bound as associated data, so sealed values cannot
+//! be swapped between fields without failing authentication. `integrity`
+//! seals nothing under the same KEK, with the seal, the settings, and the
+//! secret names as associated data: editing a setting or dropping a secret
+//! fails to load, and so does a wrong passphrase, even with no secrets.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, Payload},
+    XChaCha20Poly1305, XNonce,
+};
+use serde::{Deserialize, Serialize};
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::crypto::{get_locking_mode, SALT_SIZE};
+use crate::entropy::fill_random;
+use crate::error::SignerError;
+use crate::kdf::{derive_key, KdfParams, KEY_SIZE};
+use crate::secure_buffer::SecureBuffer;
+
+/// Current config file format version (2 adds `integrity`)
+const CONFIG_VERSION: u8 = 2;
+
+/// Prefix of the integrity tag's associated data
+const INTEGRITY_DOMAIN: &[u8] = b"coldstar-config-v2";
+
+/// XChaCha20-Poly1305 nonce size
+const SECRET_NONCE_SIZE: usize = 24;
+
+/// Where the key-encryption key for a config file comes from
+pub enum ConfigKeySource<'a> {
+    /// Derive the KEK from a boot passphrase (Argon2id)
+    Passphrase(&'a str),
+    /// Unseal the KEK from a TPM 2.0 persistent handle (e.g. "0x81000001")
+    Tpm2 { handle: &'a str },
+}
+
+/// How the KEK of a stored config was sealed
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "method", rename_all = "lowercase")]
+enum SealInfo {
+    Passphrase {
+        salt: String,
+        #[serde(default)]
+        kdf: KdfParams,
+    },
+    Tpm2 { handle: String },
+}
+
+/// A single sealed secret
+#[derive(Serialize, Deserialize, Clone)]
+struct SealedSecret {
+    nonce: String,
+    ciphertext: String,
+}
+
+/// On-disk representation
+#[derive(Serialize, Deserialize)]
+struct ConfigFile {
+    version: u8,
+    seal: SealInfo,
+    #[serde(default)]
+    settings: serde_json::Map<String, serde_json::Value>,
+    #[serde(default)]
+    secrets: BTreeMap<String, SealedSecret>,
+    integrity: SealedSecret,
+}
+
+/// A loaded configuration with its secrets held in locked memory
+#[derive(Default)]
+pub struct EncryptedConfig {
+    settings: serde_json::Map<String, serde_json::Value>,
+    secrets: BTreeMap<String, SecureBuffer>,
+}
+
+impl EncryptedConfig {
+    /// Create an empty configuration
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get a non-sensitive setting
+    pub fn setting(&self, name: &str) -> Option<&serde_json::Value> {
+        self.settings.get(name)
+    }
+
+    /// All non-sensitive settings
+    pub fn settings(&self) -> &serde_json::Map<String, serde_json::Value> {
+        &self.settings
+    }
+
+    /// Set a non-sensitive setting (stored in plaintext)
+    pub fn set_setting(&mut self, name: &str, value: serde_json::Value) {
+        self.settings.insert(name.to_string(), value);
+    }
+
+    /// Get a secret
+    ///
+    /// # Security Note
+    /// The returned buffer is locked memory; do not copy its contents into
+    /// ordinary heap allocations.
+    pub fn secret(&self, name: &str) -> Option<&SecureBuffer> {
+        self.secrets.get(name)
+    }
+
+    /// Set a secret (stored sealed)
+    ///
+    /// The value is copied into a SecureBuffer; the caller should zeroize
+    /// its own copy.
+    pub fn set_secret(&mut self, name: &str, value: &[u8]) -> Result<(), SignerError> {
+        let buffer = SecureBuffer::from_slice_with_mode(value, get_locking_mode())?;
+        self.secrets.insert(name.to_string(), buffer);
+        Ok(())
+    }
+
+    /// Names of all secrets
+    pub fn secret_names(&self) -> impl Iterator<Item = &str> {
+        self.secrets.keys().map(String::as_str)
+    }
+
+    /// Load and unseal a config file
+    pub fn load(path: impl AsRef<Path>, source: &ConfigKeySource) -> Result<Self, SignerError> {
+        let json = std::fs::read_to_string(path)?;
+        Self::from_json(&json, source)
+    }
+
+    /// Seal and write a config file
+    ///
+    /// The file is written to a temporary path and renamed into place.
+    pub fn save(&self, path: impl AsRef<Path>, source: &ConfigKeySource) -> Result<(), SignerError> {
+        let path = path.as_ref();
+        let json = self.to_json(source)?;
+        let tmp_path = path.with_extension("tmp");
+        std::fs::write(&tmp_path, json)?;
+        std::fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+
+    /// Parse and unseal a config from JSON
+    pub fn from_json(json: &str, source: &ConfigKeySource) -> Result<Self, SignerError> {
+        let file: ConfigFile =
+            serde_json::from_str(json).map_err(|e| SignerError::ContainerError(e.to_string()))?;
+
+        if file.version != CONFIG_VERSION {
+            return Err(SignerError::ContainerError(format!(
+                "unsupported config version {}",
+                file.version
+            )));
+        }
+
+        let mut kek = match (&file.seal, source) {
+            (SealInfo::Passphrase { salt, kdf }, ConfigKeySource::Passphrase(passphrase)) => {
+                let salt = base64::Engine::decode(&base64::engine::general_purpose::STANDARD, salt)?;
+                derive_key(passphrase.as_bytes(), &salt, kdf)?
+            }
+            (SealInfo::Tpm2 { handle }, ConfigKeySource::Tpm2 { handle: requested })
+                if handle == requested =>
+            {
+                tpm2_unseal(handle)?
+            }
+            _ => {
+                return Err(SignerError::ContainerError(
+                    "config was sealed with a different key source".to_string(),
+                ));
+            }
+        };
+
+        // The KEK is zeroized on drop if anything fails to open
+        let aad = integrity_data(&file.seal, &file.settings, file.secrets.keys())?;
+        open_secret(kek.as_slice(), "integrity tag", &aad, &file.integrity)?;
+        let mut secrets = BTreeMap::new();
+        for (name, sealed) in &file.secrets {
+            let value = open_secret(kek.as_slice(), &format!("secret '{}'", name), name.as_bytes(), sealed)?;
+            secrets.insert(name.clone(), SecureBuffer::from_slice_with_mode(&value, get_locking_mode())?);
+        }
+        kek.zeroize();
+
+        Ok(Self {
+            settings: file.settings,
+            secrets,
+        })
+    }
+
+    /// Seal the config to JSON
+    ///
+    /// A fresh salt (passphrase mode) and fresh nonces are generated on
+    /// every call.
+    pub fn to_json(&self, source: &ConfigKeySource) -> Result<String, SignerError> {
+        let (seal, mut kek) = match source {
+            ConfigKeySource::Passphrase(passphrase) => {
+                let mut salt = [0u8; SALT_SIZE];
+                fill_random(&mut salt)?;
+                let kdf = KdfParams::default();
+                let kek = derive_key(passphrase.as_bytes(), &salt, &kdf)?;
+                let salt = base64::Engine::encode(&base64::engine::general_purpose::STANDARD, salt);
+                (SealInfo::Passphrase { salt, kdf }, kek)
+            }
+            ConfigKeySource::Tpm2 { handle } => (
+                SealInfo::Tpm2 {
+                    handle: handle.to_string(),
+                },
+                tpm2_unseal(handle)?,
+            ),
+        };
+
+        let mut secrets = BTreeMap::new();
+        for (name, value) in &self.secrets {
+            secrets.insert(name.clone(), seal_secret(kek.as_slice(), name.as_bytes(), value.as_slice())?);
+        }
+        let aad = integrity_data(&seal, &self.settings, secrets.keys())?;
+        let integrity = seal_secret(kek.as_slice(), &aad, &[]);
+        kek.zeroize();
+
+        let file = ConfigFile {
+            version: CONFIG_VERSION,
+            seal,
+            settings: self.settings.clone(),
+            secrets,
+            integrity: integrity?,
+        };
+        serde_json::to_string_pretty(&file).map_err(|e| SignerError::SerializationError(e.to_string()))
+    }
+}
+
+impl std::fmt::Debug for EncryptedConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EncryptedConfig")
+            .field("settings", &self.settings)
+            .field("secrets", &self.secrets.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+/// domain || JSON of (seal, settings, secret names)
+fn integrity_data<'a>(
+    seal: &SealInfo,
+    settings: &serde_json::Map<String, serde_json::Value>,
+    secret_names: impl Iterator<Item = &'a String>,
+) -> Result<Vec<u8>, SignerError> {
+    let mut aad = INTEGRITY_DOMAIN.to_vec();
+    serde_json::to_writer(&mut aad, &(seal, settings, secret_names.collect::<Vec<_>>()))
+        .map_err(|e| SignerError::SerializationError(e.to_string()))?;
+    Ok(aad)
+}
+
+fn seal_secret(kek: &[u8], aad: &[u8], value: &[u8]) -> Result<SealedSecret, SignerError> {
+    let mut nonce = [0u8; SECRET_NONCE_SIZE];
+    fill_random(&mut nonce)?;
+
+    let cipher = XChaCha20Poly1305::new_from_slice(kek)
+        .map_err(|e| SignerError::KeyDerivationFailed(e.to_string()))?;
+    let ciphertext = cipher
+        .encrypt(
+            XNonce::from_slice(&nonce),
+            Payload {
+                msg: value,
+                aad,
+            },
+        )
+        .map_err(|_| SignerError::SigningFailed("Encryption failed".to_string()))?;
+
+    Ok(SealedSecret {
+        nonce: base64::Engine::encode(&base64::engine::general_purpose::STANDARD, nonce),
+        ciphertext: base64::Engine::encode(&base64::engine::general_purpose::STANDARD, ciphertext),
+    })
+}
+
+/// Open `sealed`, which `what` names in errors
+fn open_secret(kek: &[u8], what: &str, aad: &[u8], sealed: &SealedSecret) -> Result<Zeroizing<Vec<u8>>, SignerError> {
+    let nonce = base64::Engine::decode(&base64::engine::general_purpose::STANDARD, &sealed.nonce)?;
+    let ciphertext =
+        base64::Engine::decode(&base64::engine::general_purpose::STANDARD, &sealed.ciphertext)?;
+
+    if nonce.len() != SECRET_NONCE_SIZE {
+        return Err(SignerError::ContainerError(format!(
+            "{} has a {}-byte nonce, expected {}",
+            what,
+            nonce.len(),
+            SECRET_NONCE_SIZE
+        )));
+    }
+
+    let cipher = XChaCha20Poly1305::new_from_slice(kek)
+        .map_err(|e| SignerError::KeyDerivationFailed(e.to_string()))?;
+    cipher
+        .decrypt(
+            XNonce::from_slice(&nonce),
+            Payload {
+                msg: &ciphertext,
+                aad,
+            },
+        )
+        .map(Zeroizing::new)
+        .map_err(|_| SignerError::DecryptionFailed)
+}
+
+/// Unseal a 32-byte KEK from a TPM persistent handle using tpm2-tools
+fn tpm2_unseal(handle: &str) -> Result<SecureBuffer, SignerError> {
+    let output = std::process::Command::new("tpm2_unseal")
+        .args(["-c", handle])
+        .output()
+        .map_err(|e| SignerError::KeyDerivationFailed(format!("tpm2_unseal unavailable: {}", e)))?;
+
+    let mut stdout = output.stdout;
+    if !output.status.success() || stdout.len() != KEY_SIZE {
+        stdout.zeroize();
+        return Err(SignerError::KeyDerivationFailed(format!(
+            "tpm2_unseal failed for handle {}",
+            handle
+        )));
+    }
+
+    let kek = SecureBuffer::from_slice_with_mode(&stdout, get_locking_mode());
+    stdout.zeroize();
+    kek
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn enable_permissive_mode() {
+        std::env::set_var("SIGNER_ALLOW_INSECURE_MEMORY", "1");
+    }
+
+    fn sample_config() -> EncryptedConfig {
+        let mut config = EncryptedConfig::new();
+        config.set_setting("listen", serde_json::json!("/run/coldstar.sock"));
+        config.set_secret("webhook_secret", b"hunter2-webhook").unwrap();
+        config.set_secret("backend_token", b"tok_live_abc").unwrap();
+        config
+    }
+
+    #[test]
+    fn test_config_roundtrip() {
+        enable_permissive_mode();
+
+        let json = sample_config()
+            .to_json(&ConfigKeySource::Passphrase("boot-pass"))
+            .unwrap();
+        assert!(!json.contains("hunter2"));
+        assert!(!json.contains("tok_live"));
+
+        let loaded =
+            EncryptedConfig::from_json(&json, &ConfigKeySource::Passphrase("boot-pass")).unwrap();
+        assert_eq!(loaded.setting("listen").unwrap(), "/run/coldstar.sock");
+        assert_eq!(loaded.secret("webhook_secret").unwrap().as_slice(), b"hunter2-webhook");
+        assert_eq!(loaded.secret("backend_token").unwrap().as_slice(), b"tok_live_abc");
+    }
+
+    #[test]
+    fn test_wrong_passphrase_fails() {
+        enable_permissive_mode();
+
+        let json = sample_config()
+            .to_json(&ConfigKeySource::Passphrase("boot-pass"))
+            .unwrap();
+        let result = EncryptedConfig::from_json(&json, &ConfigKeySource::Passphrase("nope"));
+        assert!(matches!(result, Err(SignerError::DecryptionFailed)));
+
+        // Even when there are no secrets to fail on
+        let mut settings_only = EncryptedConfig::new();
+        settings_only.set_setting("listen", serde_json::json!("/run/coldstar.sock"));
+        let json = settings_only.to_json(&ConfigKeySource::Passphrase("boot-pass")).unwrap();
+        let result = EncryptedConfig::from_json(&json, &ConfigKeySource::Passphrase("nope"));
+        assert!(matches!(result, Err(SignerError::DecryptionFailed)));
+    }
+
+    #[test]
+    fn test_swapped_secrets_fail_authentication() {
+        enable_permissive_mode();
+
+        let json = sample_config()
+            .to_json(&ConfigKeySource::Passphrase("boot-pass"))
+            .unwrap();
+        let mut value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        let secrets = value["secrets"].as_object_mut().unwrap();
+        let a = secrets["webhook_secret"].clone();
+        let b = secrets["backend_token"].clone();
+        secrets.insert("webhook_secret".into(), b);
+        secrets.insert("backend_token".into(), a);
+
+        let result = EncryptedConfig::from_json(
+            &value.to_string(),
+            &ConfigKeySource::Passphrase("boot-pass"),
+        );
+        assert!(matches!(result, Err(SignerError::DecryptionFailed)));
+
+        // Settings and the set of secrets are authenticated too
+        let mut value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        value["settings"]["listen"] = serde_json::json!("/tmp/evil.sock");
+        let result = EncryptedConfig::from_json(&value.to_string(), &ConfigKeySource::Passphrase("boot-pass"));
+        assert!(matches!(result, Err(SignerError::DecryptionFailed)));
+        let mut value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        value["secrets"].as_object_mut().unwrap().remove("backend_token");
+        let result = EncryptedConfig::from_json(&value.to_string(), &ConfigKeySource::Passphrase("boot-pass"));
+        assert!(matches!(result, Err(SignerError::DecryptionFailed)));
+    }
+
+    #[test]
+    fn test_debug_hides_secrets() {
+        enable_permissive_mode();
+
+        let debug = format!("{:?}", sample_config());
+        assert!(debug.contains("webhook_secret"));
+        assert!(!debug.contains("hunter2"));
+    }
+}
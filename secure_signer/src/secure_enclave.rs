@@ -0,0 +1,315 @@
+//! macOS Secure Enclave and Keychain storage
+//!
+//! Hardware-bound at-rest protection for desktop wallets (feature
+//! `secure-enclave`, macOS only):
+//!
+//! - [`EnclaveKey`]: a P-256 wrapping key generated inside the Secure
+//!   Enclave. Its private half never leaves the enclave and can be gated on
+//!   Touch ID or the login password ([`AccessGate`]).
+//! - [`EnclaveWrappedContainer`]: an [`EncryptedKeyContainer`] sealed to an
+//!   enclave key (ECIES, X9.63-SHA256 / AES-GCM). Opening it needs both the
+//!   enclave (on this Mac) and, as before, the container passphrase.
+//! - [`store_container`] / [`load_container`]: keep containers in the
+//!   user's Keychain instead of loose files.
+//!
+//! Enclave keys live in the data protection keychain, which requires the
+//! calling binary to be code-signed with a keychain access group
+//! entitlement.
+//!
+//! # Memory
+//!
+//! Unwrapped bytes are returned by Security.framework in a `CFData` that
+//! this crate cannot zeroize; they are copied into a [`SecureBuffer`] and
+//! the `CFData` is released immediately.
+
+use std::ptr;
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use core_foundation::base::{CFType, TCFType};
+use core_foundation::boolean::CFBoolean;
+use core_foundation::data::CFData;
+use core_foundation::dictionary::CFDictionary;
+use core_foundation::error::{CFError, CFErrorRef};
+use core_foundation::string::CFString;
+use security_framework::access_control::{ProtectionMode, SecAccessControl};
+use security_framework::item::Location;
+use security_framework::key::{Algorithm, GenerateKeyOptions, KeyType, SecKey, Token};
+use security_framework::passwords::{delete_generic_password, get_generic_password, set_generic_password};
+use security_framework_sys::access_control::{
+    kSecAccessControlBiometryCurrentSet, kSecAccessControlPrivateKeyUsage, kSecAccessControlUserPresence,
+};
+use security_framework_sys::base::{errSecItemNotFound, errSecSuccess};
+use security_framework_sys::item::{
+    kSecAttrKeyClass, kSecAttrKeyClassPrivate, kSecAttrLabel, kSecAttrTokenID, kSecAttrTokenIDSecureEnclave,
+    kSecClass, kSecClassKey, kSecReturnRef, kSecUseDataProtectionKeychain,
+};
+use security_framework_sys::key::{SecKeyCreateDecryptedData, SecKeyCreateEncryptedData};
+use security_framework_sys::keychain_item::{SecItemCopyMatching, SecItemDelete};
+use serde::{Deserialize, Serialize};
+
+use crate::crypto::{get_locking_mode, EncryptedKeyContainer};
+use crate::error::SignerError;
+use crate::secure_buffer::SecureBuffer;
+
+/// Envelope format version
+const ENVELOPE_VERSION: u8 = 1;
+
+/// ECIES variant used for wrapping (the one CryptoKit/Apple recommend for
+/// enclave keys)
+const WRAP_ALGORITHM: Algorithm = Algorithm::ECIESEncryptionCofactorVariableIVX963SHA256AESGCM;
+
+/// What the user must do before the enclave will use a wrapping key
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AccessGate {
+    /// No prompt while the Mac is unlocked
+    None,
+    /// Touch ID, or the login password as a fallback
+    UserPresence,
+    /// Touch ID with the currently enrolled fingers only; the key becomes
+    /// unusable if fingerprints are added or removed
+    BiometryCurrentSet,
+}
+
+/// A P-256 wrapping key held in the Secure Enclave
+pub struct EnclaveKey {
+    label: String,
+    private_key: SecKey,
+}
+
+impl EnclaveKey {
+    /// Generate a new enclave key and store it under `label`
+    pub fn generate(label: &str, gate: AccessGate) -> Result<Self, SignerError> {
+        let flags = kSecAccessControlPrivateKeyUsage
+            | match gate {
+                AccessGate::None => 0,
+                AccessGate::UserPresence => kSecAccessControlUserPresence,
+                AccessGate::BiometryCurrentSet => kSecAccessControlBiometryCurrentSet,
+            };
+        let access = SecAccessControl::create_with_protection(
+            Some(ProtectionMode::AccessibleWhenUnlockedThisDeviceOnly),
+            flags,
+        )
+        .map_err(|e| SignerError::PlatformError(format!("access control: {}", e)))?;
+
+        let mut options = GenerateKeyOptions::default();
+        options
+            .set_key_type(KeyType::ec())
+            .set_size_in_bits(256)
+            .set_label(label)
+            .set_token(Token::SecureEnclave)
+            .set_location(Location::DataProtectionKeychain)
+            .set_access_control(access);
+
+        let private_key = SecKey::generate(options.to_dictionary()).map_err(cf_error)?;
+        Ok(Self {
+            label: label.to_string(),
+            private_key,
+        })
+    }
+
+    /// Load an existing enclave key by label
+    pub fn load(label: &str) -> Result<Self, SignerError> {
+        let query = key_query(label, true);
+        let mut result = ptr::null();
+        let status = unsafe { SecItemCopyMatching(query.as_concrete_TypeRef(), &mut result) };
+        match status {
+            s if s == errSecSuccess => {
+                let private_key = unsafe { SecKey::wrap_under_create_rule(result as _) };
+                Ok(Self {
+                    label: label.to_string(),
+                    private_key,
+                })
+            }
+            s if s == errSecItemNotFound => {
+                Err(SignerError::PlatformError(format!("no enclave key labelled '{}'", label)))
+            }
+            s => Err(SignerError::PlatformError(format!("keychain lookup failed (OSStatus {})", s))),
+        }
+    }
+
+    /// Permanently delete the enclave key labelled `label`
+    ///
+    /// Anything wrapped to it can no longer be opened.
+    pub fn delete(label: &str) -> Result<(), SignerError> {
+        let status = unsafe { SecItemDelete(key_query(label, false).as_concrete_TypeRef()) };
+        if status == errSecSuccess {
+            Ok(())
+        } else {
+            Err(SignerError::PlatformError(format!("keychain delete failed (OSStatus {})", status)))
+        }
+    }
+
+    /// Label the key is stored under
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    /// Encrypt to this key (public-key operation, no prompt)
+    pub fn wrap(&self, plaintext: &[u8]) -> Result<Vec<u8>, SignerError> {
+        let public_key = self
+            .private_key
+            .public_key()
+            .ok_or_else(|| SignerError::PlatformError("enclave key has no public key".to_string()))?;
+        ecies(&public_key, plaintext, false)
+    }
+
+    /// Decrypt with this key inside the enclave
+    ///
+    /// Shows the Touch ID / password prompt if the key was generated with an
+    /// [`AccessGate`] other than `None`.
+    pub fn unwrap(&self, wrapped: &[u8]) -> Result<SecureBuffer, SignerError> {
+        let plaintext = ecies(&self.private_key, wrapped, true)?;
+        SecureBuffer::from_slice_with_mode(&plaintext, get_locking_mode())
+    }
+}
+
+/// An [`EncryptedKeyContainer`] sealed to an [`EnclaveKey`]
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct EnclaveWrappedContainer {
+    /// Envelope format version
+    pub version: u8,
+    /// Label of the enclave key that can open this envelope
+    pub key_label: String,
+    /// ECIES ciphertext of the container JSON (base64)
+    pub ciphertext: String,
+}
+
+impl EnclaveWrappedContainer {
+    /// Seal a container to an enclave key
+    pub fn wrap(container: &EncryptedKeyContainer, key: &EnclaveKey) -> Result<Self, SignerError> {
+        let json = container.to_json()?;
+        Ok(Self {
+            version: ENVELOPE_VERSION,
+            key_label: key.label().to_string(),
+            ciphertext: BASE64.encode(key.wrap(json.as_bytes())?),
+        })
+    }
+
+    /// Open the envelope with the enclave key named in it
+    pub fn unwrap(&self) -> Result<EncryptedKeyContainer, SignerError> {
+        if self.version != ENVELOPE_VERSION {
+            return Err(SignerError::ContainerError(format!(
+                "unsupported enclave envelope version {}",
+                self.version
+            )));
+        }
+        let ciphertext = BASE64.decode(&self.ciphertext)?;
+        let key = EnclaveKey::load(&self.key_label)?;
+        let json = key.unwrap(&ciphertext)?;
+        let json = std::str::from_utf8(json.as_slice())
+            .map_err(|_| SignerError::ContainerError("envelope does not hold a container".to_string()))?;
+        EncryptedKeyContainer::from_json(json)
+    }
+
+    /// Serialize to JSON
+    pub fn to_json(&self) -> Result<String, SignerError> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Parse from JSON
+    pub fn from_json(json: &str) -> Result<Self, SignerError> {
+        Ok(serde_json::from_str(json)?)
+    }
+}
+
+/// Store a container in the login Keychain as a generic password item
+pub fn store_container(service: &str, account: &str, container: &EncryptedKeyContainer) -> Result<(), SignerError> {
+    set_generic_password(service, account, container.to_json()?.as_bytes())
+        .map_err(|e| SignerError::PlatformError(format!("keychain store failed: {}", e)))
+}
+
+/// Load a container stored with [`store_container`]
+pub fn load_container(service: &str, account: &str) -> Result<EncryptedKeyContainer, SignerError> {
+    let bytes = get_generic_password(service, account)
+        .map_err(|e| SignerError::PlatformError(format!("keychain load failed: {}", e)))?;
+    let json = String::from_utf8(bytes)
+        .map_err(|_| SignerError::ContainerError("keychain item is not a container".to_string()))?;
+    EncryptedKeyContainer::from_json(&json)
+}
+
+/// Remove a container stored with [`store_container`]
+pub fn delete_container(service: &str, account: &str) -> Result<(), SignerError> {
+    delete_generic_password(service, account)
+        .map_err(|e| SignerError::PlatformError(format!("keychain delete failed: {}", e)))
+}
+
+/// Keychain query for an enclave private key
+fn key_query(label: &str, return_ref: bool) -> CFDictionary<CFType, CFType> {
+    let mut pairs = unsafe {
+        vec![
+            (CFString::wrap_under_get_rule(kSecClass), CFString::wrap_under_get_rule(kSecClassKey).as_CFType()),
+            (
+                CFString::wrap_under_get_rule(kSecAttrKeyClass),
+                CFString::wrap_under_get_rule(kSecAttrKeyClassPrivate).as_CFType(),
+            ),
+            (
+                CFString::wrap_under_get_rule(kSecAttrTokenID),
+                CFString::wrap_under_get_rule(kSecAttrTokenIDSecureEnclave).as_CFType(),
+            ),
+            (CFString::wrap_under_get_rule(kSecAttrLabel), CFString::new(label).as_CFType()),
+            (
+                CFString::wrap_under_get_rule(kSecUseDataProtectionKeychain),
+                CFBoolean::true_value().as_CFType(),
+            ),
+        ]
+    };
+    if return_ref {
+        pairs.push((
+            unsafe { CFString::wrap_under_get_rule(kSecReturnRef) },
+            CFBoolean::true_value().as_CFType(),
+        ));
+    }
+    let pairs: Vec<(CFType, CFType)> = pairs.into_iter().map(|(k, v)| (k.as_CFType(), v)).collect();
+    CFDictionary::from_CFType_pairs(&pairs)
+}
+
+fn ecies(key: &SecKey, input: &[u8], decrypt: bool) -> Result<Vec<u8>, SignerError> {
+    let input = CFData::from_buffer(input);
+    let mut error: CFErrorRef = ptr::null_mut();
+    let output = unsafe {
+        let operation = if decrypt {
+            SecKeyCreateDecryptedData
+        } else {
+            SecKeyCreateEncryptedData
+        };
+        operation(
+            key.as_concrete_TypeRef(),
+            WRAP_ALGORITHM.into(),
+            input.as_concrete_TypeRef(),
+            &mut error,
+        )
+    };
+
+    if output.is_null() {
+        return Err(if error.is_null() {
+            SignerError::PlatformError("Secure Enclave operation failed".to_string())
+        } else {
+            cf_error(unsafe { CFError::wrap_under_create_rule(error) })
+        });
+    }
+    let output = unsafe { CFData::wrap_under_create_rule(output) };
+    Ok(output.bytes().to_vec())
+}
+
+fn cf_error(error: CFError) -> SignerError {
+    SignerError::PlatformError(format!("Secure Enclave: {}", error.description()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_envelope_json_roundtrip() {
+        let envelope = EnclaveWrappedContainer {
+            version: ENVELOPE_VERSION,
+            key_label: "wallet".to_string(),
+            ciphertext: BASE64.encode([1u8, 2, 3]),
+        };
+        let parsed = EnclaveWrappedContainer::from_json(&envelope.to_json().unwrap()).unwrap();
+        assert_eq!(parsed.key_label, "wallet");
+
+        let future = EnclaveWrappedContainer { version: 9, ..parsed };
+        assert!(matches!(future.unwrap(), Err(SignerError::ContainerError(_))));
+    }
+}
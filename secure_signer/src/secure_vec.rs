@@ -0,0 +1,221 @@
+//! Growable secure memory
+//!
+//! [`SecureBuffer`] has a fixed size, which suits keys but not data whose
+//! length is only known as it arrives (decrypted plaintext, derived
+//! material, stream chunks). A [`SecureVec`] grows like a `Vec<u8>`, but
+//! every allocation is locked with the vector's [`LockingMode`], and on
+//! growth the old allocation is zeroized before it is freed, so no copy of
+//! the contents is left behind in unlocked or freed memory.
+//!
+//! It implements [`aead::Buffer`], so AEAD ciphers can decrypt into it in
+//! place.
+
+use alloc::string::ToString;
+use core::ops::{Deref, DerefMut};
+
+use aes_gcm::aead;
+use zeroize::Zeroize;
+
+use crate::error::SignerError;
+use crate::secure_buffer::{LockingMode, SecureBuffer};
+
+/// Smallest capacity allocated on first growth
+const MIN_CAPACITY: usize = 32;
+
+/// A locked, zeroizing, growable byte vector
+pub struct SecureVec {
+    /// Backing storage; bytes past `len` are always zero
+    buffer: SecureBuffer,
+    len: usize,
+    mode: LockingMode,
+}
+
+impl SecureVec {
+    /// An empty vector with strict locking
+    pub fn new() -> Self {
+        Self::with_mode(LockingMode::Strict)
+    }
+
+    /// An empty vector whose allocations use `mode`
+    pub fn with_mode(mode: LockingMode) -> Self {
+        Self {
+            // Nothing to lock yet, so this cannot fail
+            buffer: SecureBuffer::with_mode(0, LockingMode::Permissive).expect("empty buffer"),
+            len: 0,
+            mode,
+        }
+    }
+
+    /// An empty vector with room for `capacity` bytes
+    pub fn with_capacity_and_mode(capacity: usize, mode: LockingMode) -> Result<Self, SignerError> {
+        Ok(Self {
+            buffer: SecureBuffer::with_mode(capacity, mode)?,
+            len: 0,
+            mode,
+        })
+    }
+
+    /// Copy `source` into a new vector
+    pub fn from_slice_with_mode(source: &[u8], mode: LockingMode) -> Result<Self, SignerError> {
+        let mut vec = Self::with_capacity_and_mode(source.len(), mode)?;
+        vec.extend_from_slice(source)?;
+        Ok(vec)
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Bytes the vector can hold before it reallocates
+    pub fn capacity(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Whether the current allocation is locked
+    pub fn is_locked(&self) -> bool {
+        self.buffer.is_locked()
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        &self.buffer.as_slice()[..self.len]
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [u8] {
+        &mut self.buffer.as_mut_slice()[..self.len]
+    }
+
+    /// Make room for `additional` more bytes
+    ///
+    /// On failure (a strict allocation that cannot be locked) the vector
+    /// is unchanged.
+    pub fn reserve(&mut self, additional: usize) -> Result<(), SignerError> {
+        let needed = self
+            .len
+            .checked_add(additional)
+            .ok_or_else(|| SignerError::MemoryLockFailed("SecureVec capacity overflow".to_string()))?;
+        if needed <= self.capacity() {
+            return Ok(());
+        }
+        let capacity = needed.max(self.capacity().saturating_mul(2)).max(MIN_CAPACITY);
+        let mut grown = SecureBuffer::with_mode(capacity, self.mode)?;
+        grown.as_mut_slice()[..self.len].copy_from_slice(self.as_slice());
+        // The old buffer zeroizes and unlocks itself as it drops
+        self.buffer = grown;
+        Ok(())
+    }
+
+    pub fn push(&mut self, byte: u8) -> Result<(), SignerError> {
+        self.extend_from_slice(&[byte])
+    }
+
+    pub fn extend_from_slice(&mut self, data: &[u8]) -> Result<(), SignerError> {
+        self.reserve(data.len())?;
+        self.buffer.as_mut_slice()[self.len..self.len + data.len()].copy_from_slice(data);
+        self.len += data.len();
+        Ok(())
+    }
+
+    /// Shorten to `len` bytes, zeroizing the rest
+    pub fn truncate(&mut self, len: usize) {
+        if len < self.len {
+            self.buffer.as_mut_slice()[len..self.len].zeroize();
+            self.len = len;
+        }
+    }
+
+    /// Zeroize the contents and empty the vector, keeping its allocation
+    pub fn clear(&mut self) {
+        self.truncate(0);
+    }
+
+    /// The contents as an exactly sized [`SecureBuffer`]
+    pub fn into_secure_buffer(mut self) -> Result<SecureBuffer, SignerError> {
+        self.buffer.resize_with_mode(self.len, self.mode)?;
+        Ok(self.buffer)
+    }
+}
+
+impl Default for SecureVec {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Deref for SecureVec {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        self.as_slice()
+    }
+}
+
+impl DerefMut for SecureVec {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.as_mut_slice()
+    }
+}
+
+impl AsRef<[u8]> for SecureVec {
+    fn as_ref(&self) -> &[u8] {
+        self.as_slice()
+    }
+}
+
+impl AsMut<[u8]> for SecureVec {
+    fn as_mut(&mut self) -> &mut [u8] {
+        self.as_mut_slice()
+    }
+}
+
+impl aead::Buffer for SecureVec {
+    fn extend_from_slice(&mut self, other: &[u8]) -> aead::Result<()> {
+        SecureVec::extend_from_slice(self, other).map_err(|_| aead::Error)
+    }
+
+    fn truncate(&mut self, len: usize) {
+        SecureVec::truncate(self, len)
+    }
+}
+
+// Prevent accidental debug printing of sensitive data
+impl core::fmt::Debug for SecureVec {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("SecureVec")
+            .field("len", &self.len)
+            .field("capacity", &self.capacity())
+            .field("data", &"[REDACTED]")
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secure_vec_grows_and_shrinks() {
+        let mut vec = SecureVec::with_mode(LockingMode::Permissive);
+        assert!(vec.is_empty());
+        for i in 0..100u8 {
+            vec.push(i).unwrap();
+        }
+        vec.extend_from_slice(&[0xaa; 1000]).unwrap();
+        assert_eq!(vec.len(), 1100);
+        assert!(vec.capacity() >= 1100);
+        assert_eq!(&vec[..3], &[0, 1, 2]);
+
+        vec.truncate(2);
+        assert_eq!(vec.as_slice(), &[0, 1]);
+        // Bytes past the end were zeroized, not just hidden
+        assert!(vec.buffer.as_slice()[2..].iter().all(|&b| b == 0));
+
+        let buffer = vec.into_secure_buffer().unwrap();
+        assert_eq!(buffer.as_slice(), &[0, 1]);
+        assert!(!format!("{:?}", SecureVec::from_slice_with_mode(&[0xde, 0xad], LockingMode::Permissive).unwrap())
+            .contains("de"));
+    }
+}
@@ -0,0 +1,243 @@
+//! Known-answer self-test
+//!
+//! [`selftest`] runs published test vectors through the primitives this
+//! crate signs and encrypts with, and reports each one:
+//!
+//! | Test | Vector |
+//! |------|--------|
+//! | `ed25519` | RFC 8032 section 7.1, tests 1 and 2 |
+//! | `secp256k1` | EIP-155 example transaction (Keccak-256, RFC 6979 ECDSA) |
+//! | `aes-256-gcm` | GCM specification, test case 14 |
+//! | `argon2id` | RFC 9106 section 5.3 |
+//!
+//! A miscompiled or fault-injected build, or a dependency bumped to a
+//! broken release, fails here instead of producing signatures nobody can
+//! verify. Run it at startup:
+//!
+//! ```ignore
+//! let report = selftest(OnFailure::RefuseSigning);
+//! if !report.passed {
+//!     eprintln!("{}", serde_json::to_string(&report)?);
+//! }
+//! ```
+//!
+//! With [`OnFailure::RefuseSigning`], a failure puts the process in an
+//! error state: every later signature and every draw of randomness fails
+//! with [`SignerError::IntegrityError`], until the process restarts.
+//! [`OnFailure::Report`] only reports.
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce};
+use argon2::{Algorithm, Argon2, AssociatedData, ParamsBuilder, Version};
+use ed25519_dalek::{Signer, SigningKey};
+use k256::ecdsa::hazmat::{self, SignPrimitive};
+use k256::ecdsa::SigningKey as K256SigningKey;
+use k256::Secp256k1;
+use serde::Serialize;
+use sha3::{Digest, Keccak256};
+
+use crate::error::SignerError;
+
+/// What [`selftest`] does when a test fails
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OnFailure {
+    /// Only report the failure
+    Report,
+    /// Report it, and refuse to sign for the rest of the process
+    RefuseSigning,
+}
+
+/// Outcome of one known-answer test
+#[derive(Clone, Debug, Serialize, PartialEq, Eq)]
+pub struct KatResult {
+    pub name: &'static str,
+    pub passed: bool,
+    /// What differed, for a failed test
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Outcome of [`selftest`]
+#[derive(Clone, Debug, Serialize, PartialEq, Eq)]
+pub struct SelfTestReport {
+    /// Whether every test passed
+    pub passed: bool,
+    pub results: Vec<KatResult>,
+}
+
+type Kat = (&'static str, fn() -> Result<(), String>);
+
+const KATS: &[Kat] = &[
+    ("ed25519", ed25519),
+    ("secp256k1", secp256k1),
+    ("aes-256-gcm", aes_256_gcm),
+    ("argon2id", argon2id),
+];
+
+static FAILED: AtomicBool = AtomicBool::new(false);
+
+/// Run every known-answer test
+pub fn selftest(on_failure: OnFailure) -> SelfTestReport {
+    let report = run(KATS);
+    if !report.passed && on_failure == OnFailure::RefuseSigning {
+        FAILED.store(true, Ordering::SeqCst);
+    }
+    report
+}
+
+/// Whether a failed [`selftest`] has disabled signing
+pub fn signing_refused() -> bool {
+    FAILED.load(Ordering::SeqCst)
+}
+
+/// Fail if a self-test has disabled signing
+pub(crate) fn check() -> Result<(), SignerError> {
+    if signing_refused() {
+        return Err(SignerError::IntegrityError(
+            "known-answer self-test failed; signing is disabled".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+fn run(kats: &[Kat]) -> SelfTestReport {
+    let results: Vec<KatResult> = kats
+        .iter()
+        .map(|&(name, kat)| {
+            let error = kat().err();
+            KatResult {
+                name,
+                passed: error.is_none(),
+                error,
+            }
+        })
+        .collect();
+    SelfTestReport {
+        passed: results.iter().all(|result| result.passed),
+        results,
+    }
+}
+
+fn expect(what: &str, actual: &[u8], expected: &str) -> Result<(), String> {
+    let actual = hex::encode(actual);
+    if actual != expected {
+        return Err(format!("{}: expected {}, got {}", what, expected, actual));
+    }
+    Ok(())
+}
+
+fn unhex<const N: usize>(value: &str) -> [u8; N] {
+    let mut bytes = [0u8; N];
+    hex::decode_to_slice(value, &mut bytes).expect("test vectors are valid hex");
+    bytes
+}
+
+fn ed25519() -> Result<(), String> {
+    const VECTORS: &[(&str, &str, &[u8], &str)] = &[
+        (
+            "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60",
+            "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a",
+            b"",
+            "e5564300c360ac729086e2cc806e828a84877f1eb8e5d974d873e065224901555fb8821590a33bacc61e39701cf9b46bd25bf5f0595bbe24655141438e7a100b",
+        ),
+        (
+            "4ccd089b28ff96da9db6c346ec114e0f5b8a319f35aba624da8cf6ed4fb8a6fb",
+            "3d4017c3e843895a92b70aa74d1b7ebc9c982ccf2ec4968cc0cd55f12af4660c",
+            &[0x72],
+            "92a009a9f0d4cab8720e820b5f642540a2b27b5416503f8fb3762223ebdb69da085ac1e43e15996e458f3613d0f11d8c387b2eaeb4302aeeb00d291612bb0c00",
+        ),
+    ];
+    for &(secret, public, message, signature) in VECTORS {
+        let signing_key = SigningKey::from_bytes(&unhex(secret));
+        expect("public key", signing_key.verifying_key().as_bytes(), public)?;
+        let signed = signing_key.sign(message);
+        expect("signature", &signed.to_bytes(), signature)?;
+        signing_key
+            .verifying_key()
+            .verify_strict(message, &signed)
+            .map_err(|e| format!("verification: {}", e))?;
+    }
+    Ok(())
+}
+
+/// The EIP-155 example: nonce 9, 20 gwei, 21000 gas, 1 ether to
+/// 0x3535...35 on chain 1
+fn secp256k1() -> Result<(), String> {
+    const PREIMAGE: &str =
+        "ec098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a764000080018080";
+    let hash = Keccak256::digest(unhex::<45>(PREIMAGE));
+    expect("keccak-256", &hash, "daf5a779ae972f972197303d7b574746c7ef83eadac0f2791ad23db92e4c8e53")?;
+
+    let signing_key = K256SigningKey::from_bytes(&[0x46; 32].into()).map_err(|e| e.to_string())?;
+    let (signature, recovery_id) = hazmat::bits2field::<Secp256k1>(&hash)
+        .and_then(|z| signing_key.as_nonzero_scalar().try_sign_prehashed_rfc6979::<sha2::Sha256>(&z, &[]))
+        .map_err(|e| e.to_string())?;
+    expect("r", &signature.r().to_bytes(), "28ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276")?;
+    expect("s", &signature.s().to_bytes(), "67cbe9d8997f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83")?;
+    // v = 37 on chain 1
+    match recovery_id.map(|id| id.to_byte()) {
+        Some(0) => Ok(()),
+        other => Err(format!("recovery id: expected 0, got {:?}", other)),
+    }
+}
+
+fn aes_256_gcm() -> Result<(), String> {
+    let cipher = Aes256Gcm::new(&[0u8; 32].into());
+    let sealed = cipher
+        .encrypt(Nonce::from_slice(&[0u8; 12]), [0u8; 16].as_slice())
+        .map_err(|e| e.to_string())?;
+    expect("ciphertext and tag", &sealed, "cea7403d4d606b6e074ec5d3baf39d18d0d1c8a799996bf0265b98b5d48ab919")
+}
+
+fn argon2id() -> Result<(), String> {
+    let params = AssociatedData::new(&[0x04; 12])
+        .and_then(|data| {
+            ParamsBuilder::new()
+                .m_cost(32)
+                .t_cost(3)
+                .p_cost(4)
+                .output_len(32)
+                .data(data)
+                .build()
+        })
+        .map_err(|e| e.to_string())?;
+    let argon2 = Argon2::new_with_secret(&[0x03; 8], Algorithm::Argon2id, Version::V0x13, params)
+        .map_err(|e| e.to_string())?;
+    let mut tag = [0u8; 32];
+    argon2
+        .hash_password_into(&[0x01; 32], &[0x02; 16], &mut tag)
+        .map_err(|e| e.to_string())?;
+    expect("tag", &tag, "0d640df58d78766c08c037a34a8b53c9d01ef0452d75b65eb52520e96b01e659")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_selftest_passes() {
+        let report = selftest(OnFailure::RefuseSigning);
+        assert!(report.passed, "{:?}", report);
+        assert_eq!(report.results.len(), KATS.len());
+        assert!(!signing_refused());
+        assert!(check().is_ok());
+    }
+
+    #[test]
+    fn test_failing_kat_is_reported() {
+        fn broken() -> Result<(), String> {
+            expect("tag", &[0u8], "01")
+        }
+        let report = run(&[("ed25519", ed25519), ("broken", broken)]);
+        assert!(!report.passed);
+        assert!(report.results[0].passed);
+        assert_eq!(report.results[1].error.as_deref(), Some("tag: expected 01, got 00"));
+        let json = serde_json::to_string(&report).unwrap();
+        assert!(json.contains(r#"{"name":"ed25519","passed":true}"#), "{}", json);
+    }
+}
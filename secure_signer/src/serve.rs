@@ -0,0 +1,313 @@
+//! Long-lived JSON-lines protocol for a signer child process
+//!
+//! `coldstar-signer serve` reads one JSON request per line on stdin and
+//! writes one JSON response per line on stdout. Hosts that would rather
+//! not load a native library (Electron, Go) spawn it as a sandboxed child:
+//! keys are decrypted and held only in the child, and the host sees public
+//! keys and signatures.
+//!
+//! ```text
+//! {"id":1,"action":"unlock_session","container":"{...}","passphrase":"...","idle_timeout_secs":120}
+//! {"id":1,"success":true,"data":{"session":"9f2c...","public_key":"...","expires_in_secs":3600}}
+//! {"id":2,"action":"sign","session":"9f2c...","transaction":"<base64>"}
+//! {"id":3,"action":"sign_evm","session":"9f2c...","hash":"0x..."}
+//! {"id":4,"action":"get_pubkey","session":"9f2c..."}
+//! {"id":5,"action":"lock","session":"9f2c..."}
+//! ```
+//!
+//! `id` is optional and echoed back so a host can pipeline requests. A
+//! request with an `idempotency_key` is answered from the
+//! [`IdempotencyStore`] when retried.
+//! Sessions are [`SigningSession`]s: they lock on their own after the idle
+//! timeout or TTL, and a locked session's ID is forgotten. When stdin
+//! closes (including when the host dies) every session is zeroized and the
+//! process exits.
+
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+use crate::backend::SignerBackend;
+use crate::crypto::EncryptedKeyContainer;
+use crate::encoding::{Encoding, OutputEncoding};
+use crate::entropy::fill_random;
+use crate::error::SignerError;
+use crate::idempotency::{self, IdempotencyStore};
+use crate::session::{SessionConfig, SigningSession};
+
+/// Most sessions held at once; each locks at least a page
+pub const MAX_SESSIONS: usize = 64;
+
+/// A request line
+#[derive(Deserialize)]
+struct Request {
+    #[serde(default)]
+    id: Option<serde_json::Value>,
+    #[serde(default)]
+    idempotency_key: Option<String>,
+    #[serde(flatten)]
+    action: Action,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+enum Action {
+    UnlockSession {
+        container: String,
+        passphrase: String,
+        #[serde(default)]
+        idle_timeout_secs: Option<u64>,
+        #[serde(default)]
+        ttl_secs: Option<u64>,
+    },
+    Sign {
+        session: String,
+        transaction: String,
+        #[serde(default)]
+        encoding: OutputEncoding,
+    },
+    SignEvm {
+        session: String,
+        hash: String,
+    },
+    GetPubkey {
+        session: String,
+    },
+    Lock {
+        session: String,
+    },
+}
+
+/// A response line
+#[derive(Serialize, Debug)]
+pub struct Response {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<serde_json::Value>,
+    pub success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Unlocked sessions, keyed by random IDs
+pub struct Server {
+    sessions: HashMap<String, SigningSession>,
+    idempotency: IdempotencyStore,
+}
+
+impl Default for Server {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Server {
+    /// A server with an in-memory idempotency store
+    pub fn new() -> Self {
+        Self::with_idempotency_store(IdempotencyStore::in_memory(idempotency::DEFAULT_CAPACITY))
+    }
+
+    /// A server that answers retried requests from `store`
+    pub fn with_idempotency_store(store: IdempotencyStore) -> Self {
+        Self {
+            sessions: HashMap::new(),
+            idempotency: store,
+        }
+    }
+
+    /// Answer requests until `input` reaches end of file, then lock every
+    /// session
+    pub fn run(&mut self, input: impl BufRead, mut output: impl Write) -> Result<(), SignerError> {
+        for line in input.lines() {
+            let line = Zeroizing::new(line?);
+            if line.trim().is_empty() {
+                continue;
+            }
+            let response = self.handle_line(&line);
+            writeln!(output, "{}", serde_json::to_string(&response)?)?;
+            output.flush()?;
+        }
+        self.lock_all();
+        Ok(())
+    }
+
+    /// Answer one request line
+    pub fn handle_line(&mut self, line: &str) -> Response {
+        let request: Request = match serde_json::from_str(line) {
+            Ok(request) => request,
+            Err(e) => {
+                return Response {
+                    id: None,
+                    success: false,
+                    data: None,
+                    error: Some(format!("Invalid request: {}", e)),
+                }
+            }
+        };
+
+        self.sessions.retain(|_, session| !session.is_locked());
+        let result = match &request.idempotency_key {
+            Some(key) => self.execute_once(key, line, request.action),
+            None => self.execute(request.action),
+        };
+        match result {
+            Ok(data) => Response {
+                id: request.id,
+                success: true,
+                data: Some(data),
+                error: None,
+            },
+            Err(e) => Response {
+                id: request.id,
+                success: false,
+                data: None,
+                error: Some(e.to_string()),
+            },
+        }
+    }
+
+    /// Zeroize and forget every session
+    pub fn lock_all(&mut self) {
+        for session in self.sessions.values() {
+            session.close();
+        }
+        self.sessions.clear();
+    }
+
+    /// Run `action` unless a request with this key already ran
+    fn execute_once(&mut self, key: &str, line: &str, action: Action) -> Result<serde_json::Value, SignerError> {
+        let request = self.idempotency.canonical_request(line)?;
+        if let Some(cached) = self.idempotency.lookup(key, &request)? {
+            return Ok(cached);
+        }
+        let data = self.execute(action)?;
+        self.idempotency.record(key, &request, &data)?;
+        Ok(data)
+    }
+
+    fn execute(&mut self, action: Action) -> Result<serde_json::Value, SignerError> {
+        match action {
+            Action::UnlockSession {
+                container,
+                passphrase,
+                idle_timeout_secs,
+                ttl_secs,
+            } => {
+                if self.sessions.len() >= MAX_SESSIONS {
+                    return Err(SignerError::SessionLocked(format!(
+                        "{} sessions already open; lock one first",
+                        MAX_SESSIONS
+                    )));
+                }
+                let mut config = SessionConfig::default();
+                if let Some(secs) = idle_timeout_secs {
+                    config.idle_timeout = Duration::from_secs(secs);
+                }
+                if let Some(secs) = ttl_secs {
+                    config.ttl = Some(Duration::from_secs(secs));
+                }
+
+                let passphrase = Zeroizing::new(passphrase);
+                let container = EncryptedKeyContainer::from_json(&container)?;
+                let session = SigningSession::unlock(&container, &passphrase, config)?;
+
+                let mut id = [0u8; 16];
+                fill_random(&mut id)?;
+                let id = hex::encode(id);
+                let data = serde_json::json!({
+                    "session": id,
+                    "public_key": session.public_key(),
+                    "expires_in_secs": session.expires_in().map(|ttl| ttl.as_secs()),
+                });
+                self.sessions.insert(id, session);
+                Ok(data)
+            }
+            Action::Sign {
+                session,
+                transaction,
+                encoding,
+            } => {
+                let transaction = Encoding::Base64.decode(&transaction)?;
+                let result = self.session(&session)?.sign(&transaction)?.with_encoding(&encoding)?;
+                Ok(serde_json::to_value(result)?)
+            }
+            Action::SignEvm { session, hash } => {
+                let hash = Encoding::Hex.decode(&hash)?;
+                Ok(serde_json::to_value(self.session(&session)?.sign_evm_hash(&hash)?)?)
+            }
+            Action::GetPubkey { session } => {
+                let session = self.session(&session)?;
+                Ok(serde_json::json!({
+                    "public_key": session.public_key(),
+                    "expires_in_secs": session.expires_in().map(|ttl| ttl.as_secs()),
+                }))
+            }
+            Action::Lock { session } => {
+                let locked = self.sessions.remove(&session).inspect(SigningSession::close).is_some();
+                Ok(serde_json::json!({ "locked": locked }))
+            }
+        }
+    }
+
+    fn session(&self, id: &str) -> Result<&SigningSession, SignerError> {
+        self.sessions
+            .get(id)
+            .ok_or_else(|| SignerError::SessionLocked(format!("no open session '{}'", id)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kdf::KdfParams;
+    use crate::Cipher;
+
+    #[test]
+    fn test_session_lifecycle_over_json_lines() {
+        std::env::set_var("SIGNER_ALLOW_INSECURE_MEMORY", "1");
+        let container =
+            EncryptedKeyContainer::encrypt_with_kdf(&[7u8; 32], "pw", Cipher::Aes256Gcm, KdfParams::MINIMUM).unwrap();
+        let unlock = serde_json::json!({
+            "id": 1,
+            "action": "unlock_session",
+            "container": container.to_json().unwrap(),
+            "passphrase": "pw",
+        });
+        let input = format!(
+            "{}\n{}\n",
+            unlock,
+            r#"{"id":2,"action":"lock","session":"nope"}"#
+        );
+
+        let mut server = Server::new();
+        let mut output = Vec::new();
+        server.run(input.as_bytes(), &mut output).unwrap();
+        let responses: Vec<serde_json::Value> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(responses[0]["id"], 1);
+        assert_eq!(responses[0]["data"]["public_key"].as_str(), container.public_key.as_deref());
+        assert_eq!(responses[1]["data"]["locked"], false);
+        assert!(server.sessions.is_empty(), "end of input locks every session");
+
+        let unlocked = server.handle_line(&unlock.to_string());
+        let session = unlocked.data.unwrap()["session"].as_str().unwrap().to_string();
+        let transaction = base64::Engine::encode(
+            &base64::engine::general_purpose::STANDARD,
+            crate::crypto::test_transaction(&[7u8; 32]),
+        );
+        let sign = serde_json::json!({"action": "sign", "session": session, "transaction": transaction});
+        assert!(server.handle_line(&sign.to_string()).success);
+
+        let lock = serde_json::json!({"action": "lock", "session": session});
+        assert_eq!(server.handle_line(&lock.to_string()).data.unwrap()["locked"], true);
+        let refused = server.handle_line(&sign.to_string());
+        assert!(refused.error.unwrap().contains("no open session"));
+    }
+}
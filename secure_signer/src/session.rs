@@ -0,0 +1,313 @@
+//! Unlock once, sign many times
+//!
+//! Interactive wallets cannot ask for the passphrase (and pay the KDF) on
+//! every signature. A [`SigningSession`] decrypts the container once and
+//! keeps the key in a [`SecureBuffer`] until the first of:
+//!
+//! - [`SigningSession::close`], or the session being dropped
+//! - the idle timeout passing without a signature
+//! - the TTL passing, however active the session is
+//! - the container's `not_after`, if it comes before the TTL
+//! - the system suspending, when a [`SuspendMonitor`](crate::suspend::SuspendMonitor) is running
+//!
+//! The buffer is zeroized at that moment, by a watchdog thread if need be;
+//! later calls fail with [`SignerError::SessionLocked`]. A session is a
+//! [`SignerBackend`], so every chain module can sign through it.
+//!
+//! With [`count_with`](SigningSession::count_with), each signature is
+//! numbered by a [`SignatureCounter`] before it is returned, and the number
+//! is set in the result's `counter`. With [`audit_to`](SigningSession::audit_to),
+//! each is also logged as an [`AuditEvent::Signed`] carrying that number.
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::audit::{payload_hash, AuditEvent, AuditLog};
+use crate::backend::SignerBackend;
+use crate::crypto::{
+    sign_evm_with_nonce_mode, sign_message_with_secure_key, sign_schnorr_with_secure_key, sign_with_secure_key,
+    EVMSigningResult, EncryptedKeyContainer, NonceMode, SchnorrSigningResult, SigningResult,
+};
+use crate::counter::SignatureCounter;
+use crate::error::SignerError;
+use crate::metrics;
+use crate::secure_buffer::SecureBuffer;
+use crate::suspend::{on_suspend, SuspendHook};
+use crate::validity::{unix_now, Validity};
+use crate::watchdog::IdleWatchdog;
+
+/// Lifetime limits of a session
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SessionConfig {
+    /// Lock after this long without a signature
+    pub idle_timeout: Duration,
+    /// Lock this long after unlocking, if set
+    pub ttl: Option<Duration>,
+}
+
+impl Default for SessionConfig {
+    /// Five idle minutes, one hour at most
+    fn default() -> Self {
+        Self {
+            idle_timeout: Duration::from_secs(5 * 60),
+            ttl: Some(Duration::from_secs(60 * 60)),
+        }
+    }
+}
+
+impl SessionConfig {
+    /// This config with the TTL cut short to end at `validity.not_after`
+    pub(crate) fn within(mut self, validity: Validity) -> Self {
+        if let Some(not_after) = validity.not_after {
+            let left = Duration::from_secs(not_after.saturating_sub(unix_now()).saturating_add(1));
+            self.ttl = Some(self.ttl.map_or(left, |ttl| ttl.min(left)));
+        }
+        self
+    }
+}
+
+/// A container's key, unlocked until closed or expired
+pub struct SigningSession {
+    key: Arc<IdleWatchdog<SecureBuffer>>,
+    public_key: Option<String>,
+    container_id: Option<String>,
+    counter: Option<Arc<Mutex<SignatureCounter>>>,
+    audit: Option<Arc<Mutex<AuditLog>>>,
+    _suspend: SuspendHook,
+}
+
+impl SigningSession {
+    /// Decrypt `container` and hold its key for the session
+    pub fn unlock(
+        container: &EncryptedKeyContainer,
+        passphrase: &str,
+        config: SessionConfig,
+    ) -> Result<Self, SignerError> {
+        let secure_key = container.decrypt_key(passphrase)?;
+        let mut session = Self::from_key(
+            secure_key,
+            container.public_key.clone(),
+            config.within(container.validity()),
+        );
+        session.container_id = container.container_id().ok();
+        Ok(session)
+    }
+
+    /// A session over an already decrypted key
+    pub(crate) fn from_key(secure_key: SecureBuffer, public_key: Option<String>, config: SessionConfig) -> Self {
+        let mut key = IdleWatchdog::new(secure_key, config.idle_timeout);
+        if let Some(ttl) = config.ttl {
+            key = key.expire_after(ttl);
+        }
+        let key = Arc::new(key);
+        let weak = Arc::downgrade(&key);
+        let suspend = on_suspend(move || {
+            if let Some(key) = weak.upgrade() {
+                key.lock();
+            }
+        });
+
+        Self {
+            key,
+            public_key,
+            container_id: None,
+            counter: None,
+            audit: None,
+            _suspend: suspend,
+        }
+    }
+
+    /// [`unlock`](Self::unlock) on tokio's blocking pool, so the KDF does
+    /// not stall the async executor
+    #[cfg(feature = "tokio")]
+    pub async fn unlock_async(
+        container: &EncryptedKeyContainer,
+        passphrase: &str,
+        config: SessionConfig,
+    ) -> Result<Self, SignerError> {
+        let container = container.clone();
+        let passphrase = zeroize::Zeroizing::new(passphrase.to_string());
+        crate::nonblocking::spawn_blocking(move || Self::unlock(&container, &passphrase, config)).await
+    }
+
+    /// Log every signature to `log`, and the session timing out under
+    /// `session`
+    pub fn audit_to(mut self, log: Arc<Mutex<AuditLog>>, session: &str) -> Self {
+        self.key.set_audit(log.clone(), session);
+        self.audit = Some(log);
+        self
+    }
+
+    /// Number every signature with `counter`, under the session's public key
+    ///
+    /// The new value is persisted before the signature is returned; if it
+    /// cannot be, the signature is withheld.
+    pub fn count_with(mut self, counter: Arc<Mutex<SignatureCounter>>) -> Self {
+        self.counter = Some(counter);
+        self
+    }
+
+    /// The container's Solana public key (base58), if it records one
+    pub fn public_key(&self) -> Option<&str> {
+        self.public_key.as_deref()
+    }
+
+    /// Sign a Solana transaction message (Ed25519)
+    pub fn sign(&self, message: &[u8]) -> Result<SigningResult, SignerError> {
+        let mut result = metrics::sign("solana", || self.with_key(|key| sign_with_secure_key(key, message)))?;
+        result.counter = self.record("solana", message, &result.signature)?;
+        Ok(result)
+    }
+
+    /// Whether the key has been zeroized
+    pub fn is_locked(&self) -> bool {
+        self.key.is_locked()
+    }
+
+    /// Time left before the TTL expires, if one is set
+    pub fn expires_in(&self) -> Option<Duration> {
+        self.key.expires_in()
+    }
+
+    /// Zeroize the key now
+    ///
+    /// Dropping the session does the same; this makes the point explicit
+    /// and works through a shared reference.
+    pub fn close(&self) {
+        self.key.lock();
+    }
+
+    fn with_key<R>(&self, f: impl FnOnce(&mut SecureBuffer) -> Result<R, SignerError>) -> Result<R, SignerError> {
+        self.key.with(f)?
+    }
+
+    /// Count and log a signature over `payload`, returning its number
+    fn record(&self, chain: &str, payload: &[u8], signature: &str) -> Result<Option<u64>, SignerError> {
+        let public_key = self.public_key.clone().unwrap_or_default();
+        let counter = match &self.counter {
+            Some(counter) => Some(
+                counter
+                    .lock()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner())
+                    .next(&public_key)?,
+            ),
+            None => None,
+        };
+        if let Some(log) = &self.audit {
+            log.lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .append(AuditEvent::Signed {
+                    chain: chain.to_string(),
+                    public_key,
+                    container_id: self.container_id.clone(),
+                    payload_hash: payload_hash(payload),
+                    signature: signature.to_string(),
+                    counter,
+                })?;
+        }
+        Ok(counter)
+    }
+}
+
+impl SignerBackend for SigningSession {
+    fn sign_solana(&self, message: &[u8]) -> Result<SigningResult, SignerError> {
+        self.sign(message)
+    }
+
+    fn sign_solana_message(&self, message: &[u8]) -> Result<SigningResult, SignerError> {
+        let mut result = metrics::sign("solana", || self.with_key(|key| sign_message_with_secure_key(key, message)))?;
+        result.counter = self.record("solana", message, &result.signature)?;
+        Ok(result)
+    }
+
+    fn sign_evm_hash(&self, message_hash: &[u8]) -> Result<EVMSigningResult, SignerError> {
+        self.sign_evm_hash_with_nonce(message_hash, NonceMode::Deterministic)
+    }
+
+    fn sign_evm_hash_with_nonce(&self, message_hash: &[u8], nonce: NonceMode) -> Result<EVMSigningResult, SignerError> {
+        if message_hash.len() != 32 {
+            return Err(SignerError::InvalidTransaction(format!(
+                "EVM message hash must be 32 bytes, got {}",
+                message_hash.len()
+            )));
+        }
+        let mut result =
+            metrics::sign("evm", || self.with_key(|key| sign_evm_with_nonce_mode(key, message_hash, nonce)))?;
+        result.counter = self.record("evm", message_hash, &result.signature)?;
+        Ok(result)
+    }
+
+    fn sign_schnorr(&self, message: &[u8]) -> Result<SchnorrSigningResult, SignerError> {
+        let mut result =
+            metrics::sign("schnorr", || self.with_key(|key| sign_schnorr_with_secure_key(key, message)))?;
+        result.counter = self.record("schnorr", message, &result.signature)?;
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::Cipher;
+    use crate::kdf::KdfParams;
+
+    #[test]
+    fn test_session_signs_until_closed_or_expired() {
+        std::env::set_var("SIGNER_ALLOW_INSECURE_MEMORY", "1");
+        let container =
+            EncryptedKeyContainer::encrypt_with_kdf(&[7u8; 32], "pw", Cipher::Aes256Gcm, KdfParams::MINIMUM).unwrap();
+        assert!(SigningSession::unlock(&container, "wrong", SessionConfig::default()).is_err());
+
+        let session = SigningSession::unlock(&container, "pw", SessionConfig::default()).unwrap();
+        let message = crate::crypto::test_transaction(&[7u8; 32]);
+        let expected = crate::backend::ContainerBackend::new(&container, "pw").sign_solana(&message);
+        assert_eq!(session.sign(&message).unwrap().signature, expected.unwrap().signature);
+        assert_eq!(session.public_key(), container.public_key.as_deref());
+        assert!(session.sign_evm_hash(&[1u8; 32]).is_ok());
+
+        session.close();
+        assert!(session.is_locked());
+        assert!(matches!(session.sign(&message), Err(SignerError::SessionLocked(_))));
+
+        // The TTL locks even an active session
+        let config = SessionConfig {
+            idle_timeout: Duration::from_secs(60),
+            ttl: Some(Duration::from_millis(300)),
+        };
+        let session = SigningSession::unlock(&container, "pw", config).unwrap();
+        assert!(session.sign(&message).is_ok());
+        std::thread::sleep(Duration::from_millis(600));
+        assert!(session.is_locked());
+        assert!(matches!(session.sign(&message), Err(SignerError::SessionLocked(_))));
+    }
+
+    #[test]
+    fn test_session_counts_and_logs_signatures() {
+        std::env::set_var("SIGNER_ALLOW_INSECURE_MEMORY", "1");
+        let container =
+            EncryptedKeyContainer::encrypt_with_kdf(&[7u8; 32], "pw", Cipher::Aes256Gcm, KdfParams::MINIMUM).unwrap();
+        let counter = Arc::new(Mutex::new(SignatureCounter::in_memory()));
+        let log = Arc::new(Mutex::new(AuditLog::in_memory()));
+        let session = SigningSession::unlock(&container, "pw", SessionConfig::default())
+            .unwrap()
+            .count_with(counter.clone())
+            .audit_to(log.clone(), "test");
+
+        let message = crate::crypto::test_transaction(&[7u8; 32]);
+        assert_eq!(session.sign(&message).unwrap().counter, Some(1));
+        assert_eq!(session.sign_evm_hash(&[1u8; 32]).unwrap().counter, Some(2));
+        assert_eq!(session.sign_schnorr(&[2u8; 32]).unwrap().counter, Some(3));
+        assert_eq!(counter.lock().unwrap().current(container.public_key.as_deref().unwrap()), 3);
+
+        let log = log.lock().unwrap();
+        assert!(crate::audit::verify_counters(log.entries()).is_ok());
+        let AuditEvent::Signed {
+            counter, container_id, ..
+        } = &log.entries()[1].event
+        else {
+            panic!("expected a Signed event");
+        };
+        assert_eq!(*counter, Some(2));
+        assert_eq!(container_id.as_deref(), Some(container.container_id().unwrap().as_str()));
+    }
+}
@@ -0,0 +1,427 @@
+//! Shamir secret sharing of container seeds
+//!
+//! [`split_container`] splits a container's 32-byte seed into `n` shares so
+//! that any `threshold` of them recover it and fewer reveal nothing. Each
+//! share is encrypted under its holder's own passphrase, so a stolen share
+//! file is useless on its own and no holder can open another's share;
+//! [`combine_shares`] decrypts `threshold` shares, recombines the seed in
+//! locked memory, and returns a fresh container.
+//!
+//! # Scheme
+//!
+//! Byte-wise Shamir over GF(2^8) with the AES reduction polynomial
+//! `x^8 + x^4 + x^3 + x + 1` (0x11b). For every seed byte a random
+//! polynomial of degree `threshold - 1` is chosen with the byte as its
+//! constant term; share `i` (1-based) holds the polynomial evaluated at
+//! `x = i`. Recombination is Lagrange interpolation at `x = 0`.
+//!
+//! Shares from one split carry the same random `group_id` and the public
+//! key of the split key, so mixing shares from different splits is
+//! rejected and a corrupted share is detected when the recombined seed
+//! does not match the public key. A share's metadata (group, public key,
+//! threshold, count, index, and the split container's validity window) is
+//! authenticated as associated data of its encryption, so editing any of it
+//! makes the share fail to decrypt. The recombined container keeps the
+//! window.
+
+use ed25519_dalek::SigningKey;
+use serde::{Deserialize, Serialize};
+
+use crate::crypto::{get_locking_mode, Cipher, EncryptedKeyContainer, Sealing};
+use crate::entropy::fill_random;
+use crate::error::SignerError;
+use crate::kdf::{Kdf, KdfParams};
+use crate::secure_buffer::SecureBuffer;
+use crate::validity::Validity;
+
+/// Share format version
+const SHARE_VERSION: u8 = 2;
+
+/// Domain separator for a share's associated data
+const SHARE_AAD_DOMAIN: &[u8] = b"coldstar-shamir-share-v2";
+
+/// Size of the shared secret (an Ed25519 seed)
+const SECRET_SIZE: usize = 32;
+
+/// One encrypted share of a split container
+#[derive(Serialize, Deserialize, Clone)]
+pub struct KeyShare {
+    /// Share format version
+    pub version: u8,
+    /// Random identifier shared by all shares of one split (hex)
+    pub group_id: String,
+    /// Public key of the split key (base58)
+    pub public_key: String,
+    /// Number of shares needed to recover the key
+    pub threshold: u8,
+    /// Number of shares created
+    pub shares: u8,
+    /// Share index (the x coordinate), 1-based
+    pub index: u8,
+    /// Start of the split container's validity window (Unix seconds)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub not_before: Option<u64>,
+    /// End of the split container's validity window (Unix seconds)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub not_after: Option<u64>,
+    /// The share value, encrypted under the share passphrase
+    pub share: EncryptedKeyContainer,
+}
+
+impl KeyShare {
+    /// Decrypt the share value
+    fn decrypt(&self, passphrase: &str) -> Result<SecureBuffer, SignerError> {
+        if self.version != SHARE_VERSION {
+            return Err(SignerError::ContainerError(format!("unsupported share version {}", self.version)));
+        }
+        let aad = associated_data(
+            &self.group_id,
+            &self.public_key,
+            self.threshold,
+            self.shares,
+            self.index,
+            self.validity(),
+        )?;
+        self.share.decrypt_key_in_context(passphrase, &aad)
+    }
+
+    /// The split container's validity window
+    pub fn validity(&self) -> Validity {
+        Validity {
+            not_before: self.not_before,
+            not_after: self.not_after,
+        }
+    }
+
+    /// Serialize to JSON
+    pub fn to_json(&self) -> Result<String, SignerError> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Parse from JSON
+    pub fn from_json(json: &str) -> Result<Self, SignerError> {
+        serde_json::from_str(json).map_err(|e| SignerError::ContainerError(e.to_string()))
+    }
+}
+
+/// Split a container (opened with `passphrase`) into one share per entry
+/// of `share_passphrases`, any `threshold` of which recover it
+///
+/// Share `i` is encrypted under `share_passphrases[i]` with the default KDF
+/// parameters.
+pub fn split_container(
+    container: &EncryptedKeyContainer,
+    passphrase: &str,
+    threshold: u8,
+    share_passphrases: &[&str],
+) -> Result<Vec<KeyShare>, SignerError> {
+    split_container_with_kdf(container, passphrase, threshold, share_passphrases, KdfParams::default())
+}
+
+/// Split a container, encrypting each share with explicit KDF parameters
+pub fn split_container_with_kdf(
+    container: &EncryptedKeyContainer,
+    passphrase: &str,
+    threshold: u8,
+    share_passphrases: &[&str],
+    kdf: KdfParams,
+) -> Result<Vec<KeyShare>, SignerError> {
+    let shares = u8::try_from(share_passphrases.len())
+        .map_err(|_| SignerError::ContainerError(format!("at most 255 shares, got {}", share_passphrases.len())))?;
+    if threshold < 2 || shares < threshold {
+        return Err(SignerError::ContainerError(format!(
+            "invalid {}-of-{} split: need 2 <= threshold <= shares",
+            threshold, shares
+        )));
+    }
+    let public_key = container
+        .public_key
+        .clone()
+        .ok_or_else(|| SignerError::ContainerError("container has no public key".to_string()))?;
+    let validity = container.validity();
+
+    let mut seed = container.decrypt_key(passphrase)?;
+    let result = split_seed(&seed, threshold, shares).and_then(|points| {
+        let mut group_id = [0u8; 8];
+        fill_random(&mut group_id)?;
+
+        points
+            .iter()
+            .zip(share_passphrases)
+            .enumerate()
+            .map(|(i, (point, share_passphrase))| {
+                let group_id = hex::encode(group_id);
+                let index = i as u8 + 1;
+                let share = EncryptedKeyContainer::encrypt_without_public_key(
+                    point.as_slice(),
+                    share_passphrase,
+                    Cipher::default(),
+                    kdf,
+                    &associated_data(&group_id, &public_key, threshold, shares, index, validity)?,
+                )?;
+
+                Ok(KeyShare {
+                    version: SHARE_VERSION,
+                    group_id,
+                    public_key: public_key.clone(),
+                    threshold,
+                    shares,
+                    index,
+                    not_before: validity.not_before,
+                    not_after: validity.not_after,
+                    share,
+                })
+            })
+            .collect()
+    });
+    seed.zeroize();
+    result
+}
+
+/// Recombine shares into a new container encrypted under `passphrase`
+///
+/// `share_passphrases[i]` opens `shares[i]`. The new container reuses the
+/// shares' Argon2id parameters and the split container's validity window.
+pub fn combine_shares(
+    shares: &[KeyShare],
+    share_passphrases: &[&str],
+    passphrase: &str,
+) -> Result<EncryptedKeyContainer, SignerError> {
+    let first = shares
+        .first()
+        .ok_or_else(|| SignerError::ContainerError("no shares given".to_string()))?;
+    if share_passphrases.len() != shares.len() {
+        return Err(SignerError::ContainerError(format!(
+            "{} shares but {} passphrases",
+            shares.len(),
+            share_passphrases.len()
+        )));
+    }
+
+    for (i, share) in shares.iter().enumerate() {
+        if share.version != first.version {
+            return Err(SignerError::ContainerError("shares come from different splits".to_string()));
+        }
+        if share.group_id != first.group_id
+            || share.public_key != first.public_key
+            || share.threshold != first.threshold
+            || share.validity() != first.validity()
+        {
+            return Err(SignerError::ContainerError("shares come from different splits".to_string()));
+        }
+        if share.index == 0 || shares[..i].iter().any(|s| s.index == share.index) {
+            return Err(SignerError::ContainerError(format!("duplicate or invalid share index {}", share.index)));
+        }
+    }
+    let threshold = usize::from(first.threshold);
+    if shares.len() < threshold {
+        return Err(SignerError::ContainerError(format!(
+            "need {} shares, got {}",
+            threshold,
+            shares.len()
+        )));
+    }
+
+    let mut points = Vec::with_capacity(threshold);
+    for (share, share_passphrase) in shares.iter().zip(share_passphrases).take(threshold) {
+        points.push((share.index, share.decrypt(share_passphrase)?));
+    }
+    let seed = interpolate_at_zero(&points);
+    for (_, point) in points.iter_mut() {
+        point.zeroize();
+    }
+    let mut seed = seed?;
+
+    let signing_key = SigningKey::from_bytes(
+        seed.as_slice()
+            .try_into()
+            .map_err(|_| SignerError::InvalidKeyFormat(seed.len()))?,
+    );
+    let result = if bs58::encode(signing_key.verifying_key().as_bytes()).into_string() != first.public_key {
+        Err(SignerError::ContainerError(
+            "recombined key does not match the shares' public key".to_string(),
+        ))
+    } else {
+        let kdf = match &first.share.kdf {
+            Kdf::Argon2id(params) => *params,
+            _ => KdfParams::default(),
+        };
+        let sealing = Sealing {
+            validity: first.validity(),
+            ..Sealing::new(Cipher::default(), kdf)
+        };
+        EncryptedKeyContainer::seal(seed.as_slice(), passphrase, &sealing)
+    };
+    seed.zeroize();
+    result
+}
+
+/// The metadata a share authenticates with its value
+fn associated_data(
+    group_id: &str,
+    public_key: &str,
+    threshold: u8,
+    shares: u8,
+    index: u8,
+    validity: Validity,
+) -> Result<Vec<u8>, SignerError> {
+    let group_id = hex::decode(group_id).map_err(|e| SignerError::ContainerError(format!("invalid group_id: {}", e)))?;
+    let public_key = bs58::decode(public_key).into_vec()?;
+    let mut aad = SHARE_AAD_DOMAIN.to_vec();
+    aad.extend_from_slice(&(group_id.len() as u32).to_le_bytes());
+    aad.extend_from_slice(&group_id);
+    aad.extend_from_slice(&(public_key.len() as u32).to_le_bytes());
+    aad.extend_from_slice(&public_key);
+    aad.extend_from_slice(&[threshold, shares, index]);
+    aad.extend_from_slice(&validity.not_before.unwrap_or(0).to_le_bytes());
+    aad.extend_from_slice(&validity.not_after.unwrap_or(u64::MAX).to_le_bytes());
+    Ok(aad)
+}
+
+/// Evaluate random polynomials with the seed bytes as constant terms
+fn split_seed(seed: &SecureBuffer, threshold: u8, shares: u8) -> Result<Vec<SecureBuffer>, SignerError> {
+    if seed.len() != SECRET_SIZE {
+        return Err(SignerError::InvalidKeyFormat(seed.len()));
+    }
+
+    let mode = get_locking_mode();
+    let mut coefficients = SecureBuffer::with_mode(SECRET_SIZE * usize::from(threshold - 1), mode)?;
+    fill_random(coefficients.as_mut_slice())?;
+
+    let mut points = Vec::with_capacity(usize::from(shares));
+    for x in 1..=shares {
+        let mut point = SecureBuffer::with_mode(SECRET_SIZE, mode)?;
+        for (byte, out) in point.as_mut_slice().iter_mut().enumerate() {
+            // Horner's rule, highest coefficient first
+            let mut y = 0u8;
+            for k in (0..usize::from(threshold - 1)).rev() {
+                y = gf_mul(y, x) ^ coefficients.as_slice()[k * SECRET_SIZE + byte];
+            }
+            *out = gf_mul(y, x) ^ seed.as_slice()[byte];
+        }
+        points.push(point);
+    }
+
+    coefficients.zeroize();
+    Ok(points)
+}
+
+/// Lagrange interpolation at x = 0
+fn interpolate_at_zero(points: &[(u8, SecureBuffer)]) -> Result<SecureBuffer, SignerError> {
+    let mut secret = SecureBuffer::with_mode(SECRET_SIZE, get_locking_mode())?;
+    for (i, (xi, yi)) in points.iter().enumerate() {
+        if yi.len() != SECRET_SIZE {
+            secret.zeroize();
+            return Err(SignerError::InvalidKeyFormat(yi.len()));
+        }
+
+        // basis_i(0) = prod_{j != i} x_j / (x_j - x_i); subtraction is XOR
+        let mut basis = 1u8;
+        for (j, (xj, _)) in points.iter().enumerate() {
+            if i != j {
+                basis = gf_mul(basis, gf_mul(*xj, gf_inv(xj ^ xi)));
+            }
+        }
+        for (out, y) in secret.as_mut_slice().iter_mut().zip(yi.as_slice()) {
+            *out ^= gf_mul(*y, basis);
+        }
+    }
+    Ok(secret)
+}
+
+/// Multiply in GF(2^8) without secret-dependent branches
+fn gf_mul(mut a: u8, mut b: u8) -> u8 {
+    let mut product = 0u8;
+    for _ in 0..8 {
+        product ^= a & 0u8.wrapping_sub(b & 1);
+        let carry = 0u8.wrapping_sub(a >> 7);
+        a = (a << 1) ^ (carry & 0x1b);
+        b >>= 1;
+    }
+    product
+}
+
+/// Multiplicative inverse (a^254); only called on public x coordinates
+fn gf_inv(a: u8) -> u8 {
+    let mut result = 1u8;
+    let mut base = a;
+    let mut exponent = 254u8;
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = gf_mul(result, base);
+        }
+        base = gf_mul(base, base);
+        exponent >>= 1;
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gf_arithmetic() {
+        // FIPS-197 section 4.2: {57} * {83} = {c1}
+        assert_eq!(gf_mul(0x57, 0x83), 0xc1);
+        for a in 1..=255u8 {
+            assert_eq!(gf_mul(a, gf_inv(a)), 1);
+        }
+    }
+
+    #[test]
+    fn test_two_of_three_recovery() {
+        let container =
+            EncryptedKeyContainer::encrypt_with_kdf(&[7u8; 32], "pw", Cipher::Aes256Gcm, KdfParams::MINIMUM).unwrap();
+        let holders = ["alice", "bob", "carol"];
+        let shares = split_container_with_kdf(&container, "pw", 2, &holders, KdfParams::MINIMUM).unwrap();
+        assert_eq!(shares.len(), 3);
+        assert!(shares.iter().all(|s| s.share.public_key.is_none()));
+
+        let pair = [shares[2].clone(), KeyShare::from_json(&shares[0].to_json().unwrap()).unwrap()];
+        let recovered = combine_shares(&pair, &["carol", "alice"], "new").unwrap();
+        assert_eq!(recovered.public_key, container.public_key);
+        assert_eq!(recovered.decrypt_key("new").unwrap().as_slice(), &[7u8; 32]);
+
+        // Each share opens only under its holder's passphrase
+        assert!(combine_shares(&pair, &["alice", "carol"], "new").is_err());
+        assert!(combine_shares(&shares[..1], &["alice"], "new").is_err());
+
+        // Edited metadata no longer decrypts
+        let mut tampered = pair.clone();
+        tampered[0].index = 2;
+        assert!(matches!(combine_shares(&tampered, &["carol", "alice"], "new"), Err(SignerError::DecryptionFailed)));
+        let mut raised = shares.clone();
+        for share in raised.iter_mut() {
+            share.threshold = 3;
+        }
+        assert!(matches!(combine_shares(&raised, &holders, "new"), Err(SignerError::DecryptionFailed)));
+
+        let other = split_container_with_kdf(&container, "pw", 2, &holders, KdfParams::MINIMUM).unwrap();
+        assert!(combine_shares(&[shares[0].clone(), other[1].clone()], &["alice", "bob"], "new").is_err());
+    }
+
+    #[test]
+    fn test_validity_window_survives_recombination() {
+        let not_after = crate::validity::unix_now() + 3600;
+        let container =
+            EncryptedKeyContainer::encrypt_with_kdf(&[7u8; 32], "pw", Cipher::Aes256Gcm, KdfParams::MINIMUM)
+                .unwrap()
+                .with_validity("pw", Validity::until(not_after))
+                .unwrap();
+        let holders = ["alice", "bob"];
+        let shares = split_container_with_kdf(&container, "pw", 2, &holders, KdfParams::MINIMUM).unwrap();
+        assert!(shares.iter().all(|s| s.not_after == Some(not_after)));
+
+        let recovered = combine_shares(&shares, &holders, "new").unwrap();
+        assert_eq!(recovered.validity(), container.validity());
+        assert_eq!(recovered.decrypt_key("new").unwrap().as_slice(), &[7u8; 32]);
+
+        // Dropping the window from the shares no longer decrypts
+        let mut unbounded = shares.clone();
+        for share in unbounded.iter_mut() {
+            share.not_after = None;
+        }
+        assert!(matches!(combine_shares(&unbounded, &holders, "new"), Err(SignerError::DecryptionFailed)));
+    }
+}
@@ -0,0 +1,176 @@
+//! Explicit signer configuration
+//!
+//! `SIGNER_ALLOW_INSECURE_MEMORY`, `SIGNER_HARDENED_MEMORY`, and
+//! `SIGNER_VERIFY_SIGNATURES` configure the whole process, and anything in
+//! it can change them. A [`Signer`] carries its own [`SignerConfig`]
+//! instead, so two tenants of one process can sign under different
+//! settings:
+//!
+//! ```ignore
+//! let signer = Signer::new(
+//!     SignerConfig::default()
+//!         .with_locking_mode(LockingMode::Hardened)
+//!         .with_cipher(Cipher::XChaCha20Poly1305),
+//! );
+//! let container = signer.create_container(&seed, passphrase)?;
+//! let result = signer.decrypt_and_sign(&container.to_json()?, passphrase, &message)?;
+//! ```
+//!
+//! While one of its methods runs, a signer's locking mode and signature
+//! verification replace the environment and [`set_locking_mode`] on the
+//! calling thread; other threads are unaffected. [`Signer::run`] applies
+//! them to any other call, such as signing through a
+//! [`SigningSession`](crate::session::SigningSession). Work the call hands
+//! to other threads (a [`SignerPool`](crate::pool::SignerPool), a batch)
+//! uses the process-wide settings.
+//!
+//! [`set_locking_mode`]: crate::crypto::set_locking_mode
+
+use crate::crypto::{
+    decrypt_and_sign, decrypt_and_sign_evm, with_thread_settings, Cipher, EVMSigningResult, EncryptedKeyContainer,
+    SigningResult, ThreadSettings,
+};
+use crate::error::SignerError;
+use crate::kdf::KdfParams;
+use crate::secure_buffer::LockingMode;
+
+/// How a [`Signer`] protects keys
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SignerConfig {
+    /// How decrypted keys are held in memory
+    pub locking_mode: LockingMode,
+    /// Argon2id parameters for new containers
+    pub kdf: KdfParams,
+    /// Cipher for new containers
+    pub cipher: Cipher,
+    /// Verify each signature before returning it; `None` verifies only in
+    /// [`LockingMode::Hardened`]
+    pub verify_signatures: Option<bool>,
+}
+
+impl Default for SignerConfig {
+    /// Strict locking, default Argon2id parameters, AES-256-GCM
+    fn default() -> Self {
+        Self {
+            locking_mode: LockingMode::Strict,
+            kdf: KdfParams::default(),
+            cipher: Cipher::default(),
+            verify_signatures: None,
+        }
+    }
+}
+
+impl SignerConfig {
+    pub fn with_locking_mode(mut self, locking_mode: LockingMode) -> Self {
+        self.locking_mode = locking_mode;
+        self
+    }
+
+    pub fn with_kdf(mut self, kdf: KdfParams) -> Self {
+        self.kdf = kdf;
+        self
+    }
+
+    pub fn with_cipher(mut self, cipher: Cipher) -> Self {
+        self.cipher = cipher;
+        self
+    }
+
+    pub fn with_verify_signatures(mut self, verify: bool) -> Self {
+        self.verify_signatures = Some(verify);
+        self
+    }
+}
+
+/// Signing operations under one [`SignerConfig`]
+#[derive(Clone, Debug)]
+pub struct Signer {
+    config: SignerConfig,
+}
+
+impl Signer {
+    pub fn new(config: SignerConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn config(&self) -> &SignerConfig {
+        &self.config
+    }
+
+    /// Run `f` under this signer's locking mode and signature verification
+    pub fn run<T>(&self, f: impl FnOnce() -> T) -> T {
+        let settings = ThreadSettings {
+            locking_mode: self.config.locking_mode,
+            verify_signatures: self.config.verify_signatures,
+        };
+        with_thread_settings(settings, f)
+    }
+
+    /// Encrypt `private_key` with the configured cipher and KDF
+    pub fn create_container(&self, private_key: &[u8], passphrase: &str) -> Result<EncryptedKeyContainer, SignerError> {
+        self.run(|| EncryptedKeyContainer::encrypt_with_kdf(private_key, passphrase, self.config.cipher, self.config.kdf))
+    }
+
+    /// [`decrypt_and_sign`](crate::decrypt_and_sign) under this signer's
+    /// settings
+    pub fn decrypt_and_sign(
+        &self,
+        container_json: &str,
+        passphrase: &str,
+        transaction_bytes: &[u8],
+    ) -> Result<SigningResult, SignerError> {
+        self.run(|| decrypt_and_sign(container_json, passphrase, transaction_bytes))
+    }
+
+    /// [`decrypt_and_sign_evm`](crate::decrypt_and_sign_evm) under this
+    /// signer's settings
+    pub fn decrypt_and_sign_evm(
+        &self,
+        container_json: &str,
+        passphrase: &str,
+        message_hash: &[u8],
+    ) -> Result<EVMSigningResult, SignerError> {
+        self.run(|| decrypt_and_sign_evm(container_json, passphrase, message_hash))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::{get_locking_mode, verify_after_signing};
+
+    #[test]
+    fn test_signer_applies_its_config_on_its_thread() {
+        let signer = Signer::new(
+            SignerConfig::default()
+                .with_locking_mode(LockingMode::Permissive)
+                .with_kdf(KdfParams::MINIMUM)
+                .with_cipher(Cipher::XChaCha20Poly1305)
+                .with_verify_signatures(true),
+        );
+        let outside = (get_locking_mode(), verify_after_signing());
+
+        let container = signer.create_container(&[4u8; 32], "pw").unwrap();
+        assert_eq!(container.cipher, Cipher::XChaCha20Poly1305);
+        assert_eq!(container.kdf, crate::kdf::Kdf::Argon2id(KdfParams::MINIMUM));
+        let json = container.to_json().unwrap();
+        let message = crate::crypto::test_transaction(&[4u8; 32]);
+        let expected = crate::crypto::sign_transaction(&[4u8; 32], &message).unwrap();
+        assert_eq!(signer.decrypt_and_sign(&json, "pw", &message).unwrap().signature, expected.signature);
+        assert!(signer.decrypt_and_sign_evm(&json, "pw", &[1u8; 32]).is_ok());
+
+        signer.run(|| {
+            assert_eq!(get_locking_mode(), LockingMode::Permissive);
+            assert!(verify_after_signing());
+            // An inner signer takes over, and hands back when done
+            Signer::new(SignerConfig::default().with_locking_mode(LockingMode::Hardened))
+                .run(|| assert_eq!(get_locking_mode(), LockingMode::Hardened));
+            assert_eq!(get_locking_mode(), LockingMode::Permissive);
+        });
+        assert!(std::panic::catch_unwind(|| signer.run(|| panic!("inside"))).is_err());
+        assert_eq!((get_locking_mode(), verify_after_signing()), outside);
+
+        let other = std::thread::spawn(move || signer.run(|| std::thread::spawn(get_locking_mode).join().unwrap()));
+        assert_eq!(other.join().unwrap(), outside.0);
+    }
+}
@@ -0,0 +1,500 @@
+//! Transfer transaction building
+//!
+//! [`TransferBuilder`] turns high-level transfers into a signed legacy
+//! transaction in one call:
+//!
+//! - [`Transfer::Sol`]: a System program transfer
+//! - [`Transfer::Token`]: an SPL Token (or Token-2022) `TransferChecked`
+//!   between the owners' associated token accounts, optionally preceded by
+//!   an idempotent create of the recipient's account
+//!
+//! A priority fee, set directly or taken from a [`FeeEstimator`], adds
+//! `SetComputeUnitLimit` and `SetComputeUnitPrice` instructions in front.
+//!
+//! The builder keeps the [`Transfer`]s it was given, so the [`Policy`] its
+//! signing path takes checks recipients and amounts instead of decoding
+//! message bytes.
+
+use super::decode::{SYSTEM_PROGRAM_ID, TOKEN_2022_PROGRAM_ID, TOKEN_PROGRAM_ID};
+use super::message::{CompiledInstruction, MessageHeader, MessageVersion, SolanaMessage};
+use super::squads::find_program_address;
+use super::transaction::SolanaTransaction;
+use crate::backend::{ContainerBackend, SignerBackend};
+use crate::crypto::EncryptedKeyContainer;
+use crate::error::SignerError;
+use crate::fees::{FeeEstimator, SolanaFee, COMPUTE_BUDGET_PROGRAM_ID};
+use crate::policy::Policy;
+
+/// Associated Token Account program id
+pub const ASSOCIATED_TOKEN_PROGRAM_ID: &str = "ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL";
+
+/// System program `Transfer` instruction index
+const SYSTEM_TRANSFER: u32 = 2;
+/// SPL Token `TransferChecked` instruction index
+const TOKEN_TRANSFER_CHECKED: u8 = 12;
+/// Associated Token Account `CreateIdempotent` instruction index
+const ATA_CREATE_IDEMPOTENT: u8 = 1;
+
+fn program_id(id: &str) -> [u8; 32] {
+    bs58::decode(id)
+        .into_vec()
+        .expect("valid program id")
+        .try_into()
+        .expect("program id is 32 bytes")
+}
+
+/// An account an instruction uses
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AccountMeta {
+    pub pubkey: [u8; 32],
+    pub is_signer: bool,
+    pub is_writable: bool,
+}
+
+impl AccountMeta {
+    fn writable(pubkey: [u8; 32], is_signer: bool) -> Self {
+        Self {
+            pubkey,
+            is_signer,
+            is_writable: true,
+        }
+    }
+
+    fn readonly(pubkey: [u8; 32], is_signer: bool) -> Self {
+        Self {
+            pubkey,
+            is_signer,
+            is_writable: false,
+        }
+    }
+}
+
+/// An instruction before its accounts are compiled into indexes
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Instruction {
+    pub program_id: [u8; 32],
+    pub accounts: Vec<AccountMeta>,
+    pub data: Vec<u8>,
+}
+
+/// An SPL token transfer between two owners' associated token accounts
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TokenTransfer {
+    /// Token mint
+    pub mint: [u8; 32],
+    /// Recipient wallet (not its token account)
+    pub recipient: [u8; 32],
+    /// Amount in base units
+    pub amount: u64,
+    /// The mint's decimals, which the token program checks
+    pub decimals: u8,
+    /// SPL Token or Token-2022
+    pub token_program: [u8; 32],
+    /// Create the recipient's associated token account if it is missing
+    pub create_recipient_account: bool,
+}
+
+impl TokenTransfer {
+    /// An SPL Token transfer to an existing account
+    pub fn new(mint: [u8; 32], recipient: [u8; 32], amount: u64, decimals: u8) -> Self {
+        Self {
+            mint,
+            recipient,
+            amount,
+            decimals,
+            token_program: program_id(TOKEN_PROGRAM_ID),
+            create_recipient_account: false,
+        }
+    }
+
+    /// Use the Token-2022 program
+    pub fn token_2022(mut self) -> Self {
+        self.token_program = program_id(TOKEN_2022_PROGRAM_ID);
+        self
+    }
+
+    /// Create the recipient's associated token account first (idempotent,
+    /// paid by the sender)
+    pub fn create_recipient_account(mut self) -> Self {
+        self.create_recipient_account = true;
+        self
+    }
+}
+
+/// A transfer from the builder's owner
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Transfer {
+    /// Native SOL
+    Sol {
+        /// Recipient
+        to: [u8; 32],
+        /// Amount in lamports
+        lamports: u64,
+    },
+    /// SPL tokens
+    Token(TokenTransfer),
+}
+
+impl Transfer {
+    /// The recipient wallet
+    pub fn recipient(&self) -> &[u8; 32] {
+        match self {
+            Transfer::Sol { to, .. } => to,
+            Transfer::Token(token) => &token.recipient,
+        }
+    }
+}
+
+/// Associated token account of `owner` for `mint` under `token_program`
+pub fn associated_token_address(owner: &[u8; 32], mint: &[u8; 32], token_program: &[u8; 32]) -> [u8; 32] {
+    find_program_address(&[owner, token_program, mint], &program_id(ASSOCIATED_TOKEN_PROGRAM_ID))
+        .expect("a bump seed exists for all but a negligible fraction of seeds")
+        .0
+}
+
+/// System program transfer of `lamports` from `from` to `to`
+pub fn system_transfer(from: &[u8; 32], to: &[u8; 32], lamports: u64) -> Instruction {
+    let mut data = SYSTEM_TRANSFER.to_le_bytes().to_vec();
+    data.extend_from_slice(&lamports.to_le_bytes());
+    Instruction {
+        program_id: program_id(SYSTEM_PROGRAM_ID),
+        accounts: vec![AccountMeta::writable(*from, true), AccountMeta::writable(*to, false)],
+        data,
+    }
+}
+
+/// Create `owner`'s associated token account for `mint` unless it exists
+pub fn create_associated_token_account_idempotent(
+    payer: &[u8; 32],
+    owner: &[u8; 32],
+    mint: &[u8; 32],
+    token_program: &[u8; 32],
+) -> Instruction {
+    Instruction {
+        program_id: program_id(ASSOCIATED_TOKEN_PROGRAM_ID),
+        accounts: vec![
+            AccountMeta::writable(*payer, true),
+            AccountMeta::writable(associated_token_address(owner, mint, token_program), false),
+            AccountMeta::readonly(*owner, false),
+            AccountMeta::readonly(*mint, false),
+            AccountMeta::readonly(program_id(SYSTEM_PROGRAM_ID), false),
+            AccountMeta::readonly(*token_program, false),
+        ],
+        data: vec![ATA_CREATE_IDEMPOTENT],
+    }
+}
+
+/// `TransferChecked` of `amount` from `owner`'s token account for the
+/// transfer's mint to the recipient's
+pub fn token_transfer_checked(owner: &[u8; 32], transfer: &TokenTransfer) -> Instruction {
+    let mut data = vec![TOKEN_TRANSFER_CHECKED];
+    data.extend_from_slice(&transfer.amount.to_le_bytes());
+    data.push(transfer.decimals);
+    Instruction {
+        program_id: transfer.token_program,
+        accounts: vec![
+            AccountMeta::writable(associated_token_address(owner, &transfer.mint, &transfer.token_program), false),
+            AccountMeta::readonly(transfer.mint, false),
+            AccountMeta::writable(
+                associated_token_address(&transfer.recipient, &transfer.mint, &transfer.token_program),
+                false,
+            ),
+            AccountMeta::readonly(*owner, true),
+        ],
+        data,
+    }
+}
+
+/// `SetComputeUnitLimit` and `SetComputeUnitPrice` instructions for `fee`
+pub fn compute_budget_instructions(fee: &SolanaFee) -> [Instruction; 2] {
+    let compute_budget = program_id(COMPUTE_BUDGET_PROGRAM_ID);
+    [
+        Instruction {
+            program_id: compute_budget,
+            accounts: Vec::new(),
+            data: fee.set_compute_unit_limit_data(),
+        },
+        Instruction {
+            program_id: compute_budget,
+            accounts: Vec::new(),
+            data: fee.set_compute_unit_price_data(),
+        },
+    ]
+}
+
+/// Compile instructions into a legacy message paid for by `fee_payer`
+///
+/// Accounts are merged (signer and writable if any use is) and ordered as
+/// the runtime requires: writable signers (fee payer first), read-only
+/// signers, writable non-signers, then read-only non-signers.
+pub fn compile_message(
+    fee_payer: &[u8; 32],
+    instructions: &[Instruction],
+    recent_blockhash: &[u8; 32],
+) -> Result<SolanaMessage, SignerError> {
+    let mut accounts = vec![AccountMeta::writable(*fee_payer, true)];
+    let uses = instructions.iter().flat_map(|ix| {
+        ix.accounts
+            .iter()
+            .copied()
+            .chain(std::iter::once(AccountMeta::readonly(ix.program_id, false)))
+    });
+    for meta in uses {
+        match accounts.iter_mut().find(|known| known.pubkey == meta.pubkey) {
+            Some(known) => {
+                known.is_signer |= meta.is_signer;
+                known.is_writable |= meta.is_writable;
+            }
+            None => accounts.push(meta),
+        }
+    }
+    if accounts.len() > 256 {
+        return Err(SignerError::InvalidTransaction(format!(
+            "{} accounts do not fit in a legacy message",
+            accounts.len()
+        )));
+    }
+    // Stable, so the fee payer stays first among the writable signers
+    accounts.sort_by_key(|meta| (!meta.is_signer, !meta.is_writable));
+
+    let count = |signer: bool, writable: bool| {
+        accounts
+            .iter()
+            .filter(|meta| meta.is_signer == signer && meta.is_writable == writable)
+            .count() as u8
+    };
+    let header = MessageHeader {
+        num_required_signatures: count(true, true) + count(true, false),
+        num_readonly_signed_accounts: count(true, false),
+        num_readonly_unsigned_accounts: count(false, false),
+    };
+    let index = |pubkey: &[u8; 32]| accounts.iter().position(|meta| meta.pubkey == *pubkey).expect("collected") as u8;
+    let compiled = instructions
+        .iter()
+        .map(|ix| CompiledInstruction {
+            program_id_index: index(&ix.program_id),
+            accounts: ix.accounts.iter().map(|meta| index(&meta.pubkey)).collect(),
+            data: ix.data.clone(),
+        })
+        .collect();
+
+    Ok(SolanaMessage {
+        version: MessageVersion::Legacy,
+        header,
+        account_keys: accounts.iter().map(|meta| meta.pubkey).collect(),
+        recent_blockhash: *recent_blockhash,
+        instructions: compiled,
+        address_table_lookups: Vec::new(),
+    })
+}
+
+/// Builds transfers from one owner, who also pays the fees
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TransferBuilder {
+    owner: [u8; 32],
+    recent_blockhash: [u8; 32],
+    transfers: Vec<Transfer>,
+    fee: Option<SolanaFee>,
+}
+
+impl TransferBuilder {
+    /// Start a transaction from `owner`
+    pub fn new(owner: [u8; 32], recent_blockhash: [u8; 32]) -> Self {
+        Self {
+            owner,
+            recent_blockhash,
+            transfers: Vec::new(),
+            fee: None,
+        }
+    }
+
+    /// Pay a priority fee of `fee`
+    pub fn fee(mut self, fee: SolanaFee) -> Self {
+        self.fee = Some(fee);
+        self
+    }
+
+    /// Pay the priority fee `estimator` gives for the accounts the
+    /// transfers added so far write to
+    pub fn estimate_fee(self, estimator: &dyn FeeEstimator) -> Result<Self, SignerError> {
+        let mut writable = vec![self.owner];
+        for meta in self.instructions().iter().flat_map(|ix| &ix.accounts) {
+            if meta.is_writable && !writable.contains(&meta.pubkey) {
+                writable.push(meta.pubkey);
+            }
+        }
+        let fee = estimator.solana_fee(&writable)?;
+        Ok(self.fee(fee))
+    }
+
+    /// Add a SOL transfer
+    pub fn sol(mut self, to: [u8; 32], lamports: u64) -> Self {
+        self.transfers.push(Transfer::Sol { to, lamports });
+        self
+    }
+
+    /// Add a token transfer
+    pub fn token(mut self, transfer: TokenTransfer) -> Self {
+        self.transfers.push(Transfer::Token(transfer));
+        self
+    }
+
+    /// The transfers added so far, in order
+    pub fn transfers(&self) -> &[Transfer] {
+        &self.transfers
+    }
+
+    /// The instructions the fee and transfers compile to
+    pub fn instructions(&self) -> Vec<Instruction> {
+        let budget = self.fee.as_ref().map(compute_budget_instructions).into_iter().flatten();
+        let transfers = self.transfers.iter().flat_map(|transfer| match transfer {
+            Transfer::Sol { to, lamports } => vec![system_transfer(&self.owner, to, *lamports)],
+            Transfer::Token(token) => {
+                let create = token.create_recipient_account.then(|| {
+                    create_associated_token_account_idempotent(
+                        &self.owner,
+                        &token.recipient,
+                        &token.mint,
+                        &token.token_program,
+                    )
+                });
+                create
+                    .into_iter()
+                    .chain(std::iter::once(token_transfer_checked(&self.owner, token)))
+                    .collect()
+            }
+        });
+        budget.chain(transfers).collect()
+    }
+
+    /// The unsigned message
+    pub fn build(&self) -> Result<SolanaMessage, SignerError> {
+        if self.transfers.is_empty() {
+            return Err(SignerError::InvalidTransaction("no transfers to build".to_string()));
+        }
+        compile_message(&self.owner, &self.instructions(), &self.recent_blockhash)
+    }
+
+    /// Check the transfers against `policy`, then build the transaction and
+    /// sign it with `backend`, whose key must be the owner's
+    pub fn sign_with(&self, backend: &dyn SignerBackend, policy: &Policy) -> Result<SolanaTransaction, SignerError> {
+        policy.check_solana_transfers(&self.transfers)?;
+        let mut transaction = SolanaTransaction::from_message(&self.build()?.serialize())?;
+        transaction.sign_with(backend)?;
+        Ok(transaction)
+    }
+}
+
+/// Decrypt a key container and sign `transfers` from its key
+///
+/// The container's Solana public key is the owner and fee payer. `policy`
+/// is checked first when it applies to the container.
+pub fn decrypt_and_sign_transfers(
+    container_json: &str,
+    passphrase: &str,
+    transfers: &[Transfer],
+    recent_blockhash: &[u8; 32],
+    policy: &Policy,
+) -> Result<SolanaTransaction, SignerError> {
+    let container = EncryptedKeyContainer::from_json(container_json)?;
+    let unrestricted = Policy::default();
+    let policy = if policy.applies_to(&container.container_id()?) {
+        policy
+    } else {
+        &unrestricted
+    };
+    let owner: [u8; 32] = container
+        .public_key
+        .as_deref()
+        .and_then(|key| bs58::decode(key).into_vec().ok())
+        .and_then(|key| key.try_into().ok())
+        .ok_or_else(|| SignerError::ContainerError("container has no Solana public key".to_string()))?;
+
+    let builder = TransferBuilder {
+        owner,
+        recent_blockhash: *recent_blockhash,
+        transfers: transfers.to_vec(),
+        fee: None,
+    };
+    builder.sign_with(&ContainerBackend::new(&container, passphrase), policy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::Cipher;
+    use crate::fees::FixedFeeEstimator;
+    use crate::kdf::KdfParams;
+    use crate::solana::decode::{decode_transaction, DecodedInstruction};
+
+    #[test]
+    fn test_sol_and_token_transfers_sign_in_one_call() {
+        std::env::set_var("SIGNER_ALLOW_INSECURE_MEMORY", "1");
+        let container =
+            EncryptedKeyContainer::encrypt_with_kdf(&[7u8; 32], "pw", Cipher::Aes256Gcm, KdfParams::MINIMUM).unwrap();
+        let owner: [u8; 32] = bs58::decode(container.public_key.as_ref().unwrap())
+            .into_vec()
+            .unwrap()
+            .try_into()
+            .unwrap();
+        let (recipient, mint) = ([0x22; 32], [0x33; 32]);
+        let transfers = [
+            Transfer::Sol { to: recipient, lamports: 5_000 },
+            Transfer::Token(TokenTransfer::new(mint, recipient, 1_500_000, 6).create_recipient_account()),
+        ];
+
+        let json = container.to_json().unwrap();
+        let signed = decrypt_and_sign_transfers(&json, "pw", &transfers, &[9u8; 32], &Policy::default()).unwrap();
+        assert!(signed.is_fully_signed());
+        assert_eq!(signed.required_signers(), [owner]);
+
+        let decoded = decode_transaction(signed.message_bytes()).unwrap();
+        assert_eq!(decoded.len(), 3);
+        assert!(matches!(decoded[0], DecodedInstruction::SystemTransfer { lamports: 5_000, .. }));
+        let destination = associated_token_address(&recipient, &mint, &program_id(TOKEN_PROGRAM_ID));
+        assert!(matches!(
+            &decoded[2],
+            DecodedInstruction::TokenTransfer { amount: 1_500_000, destination: d, decimals: Some(6), .. }
+                if *d == bs58::encode(destination).into_string()
+        ));
+
+        // An associated token account is off the curve, and depends on the program
+        assert!(ed25519_dalek::VerifyingKey::from_bytes(&destination).is_err());
+        assert_ne!(destination, associated_token_address(&recipient, &mint, &program_id(TOKEN_2022_PROGRAM_ID)));
+
+        // Another key cannot sign for the owner
+        let other =
+            EncryptedKeyContainer::encrypt_with_kdf(&[8u8; 32], "pw", Cipher::Aes256Gcm, KdfParams::MINIMUM).unwrap();
+        let builder = TransferBuilder::new(owner, [9u8; 32]).sol(recipient, 1);
+        assert!(builder.sign_with(&ContainerBackend::new(&other, "pw"), &Policy::default()).is_err());
+        assert!(TransferBuilder::new(owner, [9u8; 32]).build().is_err());
+
+        // The policy is checked before the key is used
+        let capped = Policy::new(vec![crate::policy::PolicyRule::MaxLamports { lamports: 4_999 }]);
+        let refused = decrypt_and_sign_transfers(&json, "pw", &transfers, &[9u8; 32], &capped);
+        assert!(matches!(refused, Err(SignerError::PolicyViolation(_))));
+        let elsewhere = capped.for_containers(vec!["another container".to_string()]);
+        assert!(decrypt_and_sign_transfers(&json, "pw", &transfers, &[9u8; 32], &elsewhere).is_ok());
+    }
+
+    #[test]
+    fn test_estimated_fee_comes_first() {
+        let (owner, recipient) = ([0x11; 32], [0x22; 32]);
+        let estimator = FixedFeeEstimator {
+            solana: Some(SolanaFee {
+                compute_unit_limit: 200_000,
+                compute_unit_price: 5_000,
+            }),
+            evm: None,
+        };
+        let builder = TransferBuilder::new(owner, [9u8; 32]).sol(recipient, 1_000);
+        assert!(builder.clone().estimate_fee(&FixedFeeEstimator::default()).is_err());
+
+        let message = builder.estimate_fee(&estimator).unwrap().build().unwrap();
+        let decoded = decode_transaction(&message.serialize()).unwrap();
+        assert_eq!(decoded[0], DecodedInstruction::SetComputeUnitLimit { units: 200_000 });
+        assert_eq!(decoded[1], DecodedInstruction::SetComputeUnitPrice { micro_lamports: 5_000 });
+        assert!(matches!(decoded[2], DecodedInstruction::SystemTransfer { lamports: 1_000, .. }));
+        assert_eq!(message.account_keys[0], owner);
+    }
+}
@@ -0,0 +1,728 @@
+//! Instruction decoding for transaction previews
+//!
+//! Turns compiled instructions into [`DecodedInstruction`]s with the
+//! accounts and amounts a human needs to approve a transaction. Recognized
+//! programs:
+//!
+//! - System program (transfers)
+//! - SPL Token / Token-2022 (transfers, approvals, authority changes)
+//! - Compute Budget
+//! - Metaplex Bubblegum (compressed NFTs)
+//! - Metaplex Token Metadata (NFT transfers, delegates, burns)
+//! - Squads v4 multisig (vault transactions, including the instructions
+//!   they will run, proposals, and votes)
+//!
+//! Anything else decodes to [`DecodedInstruction::Unknown`]. Accounts loaded
+//! from address lookup tables cannot be resolved offline and are shown as
+//! `lookup:<index>`.
+
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use super::message::{CompiledInstruction, SolanaMessage};
+use super::squads::{VaultTransactionMessage, SQUADS_V4_PROGRAM_ID};
+use crate::error::SignerError;
+use crate::fees::COMPUTE_BUDGET_PROGRAM_ID;
+
+/// System program id
+pub const SYSTEM_PROGRAM_ID: &str = "11111111111111111111111111111111";
+/// SPL Token program id
+pub const TOKEN_PROGRAM_ID: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+/// Token-2022 program id
+pub const TOKEN_2022_PROGRAM_ID: &str = "TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb";
+/// Metaplex Bubblegum (compressed NFT) program id
+pub const BUBBLEGUM_PROGRAM_ID: &str = "BGUMAp9Gq7iTEuizy4pqaxsTyUCBK68MDfK752saRPUY";
+/// Metaplex Token Metadata program id
+pub const TOKEN_METADATA_PROGRAM_ID: &str = "metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s";
+
+/// Bubblegum instructions that are decoded, by Anchor method name
+const BUBBLEGUM_INSTRUCTIONS: &[&str] = &[
+    "transfer",
+    "delegate",
+    "burn",
+    "mint_v1",
+    "mint_to_collection_v1",
+    "redeem",
+    "cancel_redeem",
+    "decompress_v1",
+    "verify_creator",
+    "unverify_creator",
+    "verify_collection",
+    "unverify_collection",
+    "set_and_verify_collection",
+    "update_metadata",
+    "set_tree_delegate",
+    "create_tree",
+];
+
+/// Token Metadata `DelegateArgs` variant names, by index
+const METADATA_DELEGATE_ROLES: &[&str] = &[
+    "collection",
+    "sale",
+    "transfer",
+    "data",
+    "utility",
+    "staking",
+    "standard",
+    "locked_transfer",
+    "programmable_config",
+    "authority_item",
+    "data_item",
+    "collection_item",
+    "programmable_config_item",
+    "print",
+];
+
+/// A decoded instruction
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DecodedInstruction {
+    /// Native SOL transfer
+    SystemTransfer {
+        /// Sender
+        from: String,
+        /// Recipient
+        to: String,
+        /// Amount in lamports
+        lamports: u64,
+    },
+    /// SPL token transfer (`Transfer` or `TransferChecked`)
+    TokenTransfer {
+        /// Source token account
+        source: String,
+        /// Destination token account
+        destination: String,
+        /// Owner or delegate authorizing the transfer
+        authority: String,
+        /// Amount in base units
+        amount: u64,
+        /// Mint (TransferChecked only)
+        #[serde(skip_serializing_if = "Option::is_none")]
+        mint: Option<String>,
+        /// Decimals (TransferChecked only)
+        #[serde(skip_serializing_if = "Option::is_none")]
+        decimals: Option<u8>,
+    },
+    /// SPL token delegate approval (`Approve` or `ApproveChecked`)
+    TokenApprove {
+        /// Token account
+        source: String,
+        /// Delegate being approved
+        delegate: String,
+        /// Token account owner
+        owner: String,
+        /// Amount the delegate may move
+        amount: u64,
+    },
+    /// SPL token authority change
+    TokenSetAuthority {
+        /// Account or mint whose authority changes
+        account: String,
+        /// New authority (`None` removes it)
+        new_authority: Option<String>,
+    },
+    /// Compute unit limit
+    SetComputeUnitLimit {
+        /// Requested compute units
+        units: u32,
+    },
+    /// Priority fee
+    SetComputeUnitPrice {
+        /// Micro-lamports per compute unit
+        micro_lamports: u64,
+    },
+    /// Compressed NFT transfer
+    BubblegumTransfer {
+        /// Merkle tree holding the asset
+        merkle_tree: String,
+        /// Current owner
+        leaf_owner: String,
+        /// New owner
+        new_leaf_owner: String,
+        /// Leaf index in the tree
+        leaf_index: u32,
+    },
+    /// Compressed NFT delegation (e.g. marketplace listing)
+    BubblegumDelegate {
+        /// Merkle tree holding the asset
+        merkle_tree: String,
+        /// Current owner
+        leaf_owner: String,
+        /// New delegate
+        new_leaf_delegate: String,
+        /// Leaf index in the tree
+        leaf_index: u32,
+    },
+    /// Compressed NFT burn
+    BubblegumBurn {
+        /// Merkle tree holding the asset
+        merkle_tree: String,
+        /// Owner
+        leaf_owner: String,
+        /// Leaf index in the tree
+        leaf_index: u32,
+    },
+    /// Compressed NFT mint
+    BubblegumMint {
+        /// Merkle tree receiving the asset
+        merkle_tree: String,
+        /// Owner of the new asset
+        leaf_owner: String,
+    },
+    /// Other Bubblegum instruction
+    BubblegumOther {
+        /// Anchor method name, if known
+        name: String,
+    },
+    /// Token Metadata transfer (including programmable NFTs)
+    MetadataTransfer {
+        /// NFT mint
+        mint: String,
+        /// Current token owner
+        owner: String,
+        /// New owner
+        destination_owner: String,
+        /// Amount (1 for NFTs)
+        amount: u64,
+    },
+    /// Token Metadata delegate (sale/transfer delegates are used for listings)
+    MetadataDelegate {
+        /// NFT mint
+        mint: String,
+        /// Delegate being approved
+        delegate: String,
+        /// Delegate role ("sale", "transfer", "collection", ...)
+        role: String,
+    },
+    /// Token Metadata delegate revocation
+    MetadataRevoke {
+        /// NFT mint
+        mint: String,
+        /// Delegate being revoked
+        delegate: String,
+    },
+    /// Token Metadata burn
+    MetadataBurn {
+        /// NFT mint
+        mint: String,
+    },
+    /// Other Token Metadata instruction
+    MetadataOther {
+        /// Instruction name, if known
+        name: String,
+    },
+    /// Squads vault transaction creation: what the vault will do once approved
+    SquadsVaultTransactionCreate {
+        /// Multisig account
+        multisig: String,
+        /// Vault that will sign the transaction
+        vault: String,
+        /// Member creating the transaction
+        creator: String,
+        /// The vault transaction's instructions
+        instructions: Vec<DecodedInstruction>,
+    },
+    /// Squads proposal creation
+    SquadsProposalCreate {
+        /// Multisig account
+        multisig: String,
+        /// Proposal account
+        proposal: String,
+        /// Index of the transaction being proposed
+        transaction_index: u64,
+    },
+    /// Squads member vote on a proposal
+    SquadsProposalVote {
+        /// "approve", "reject", or "cancel"
+        vote: String,
+        /// Multisig account
+        multisig: String,
+        /// Proposal account
+        proposal: String,
+        /// Voting member
+        member: String,
+    },
+    /// Squads execution of an approved vault transaction
+    SquadsVaultTransactionExecute {
+        /// Multisig account
+        multisig: String,
+        /// Proposal account
+        proposal: String,
+        /// Transaction account
+        transaction: String,
+    },
+    /// Other Squads instruction
+    SquadsOther {
+        /// Anchor method name, if known
+        name: String,
+    },
+    /// Instruction of an unrecognized program (or undecodable data)
+    Unknown {
+        /// Program id
+        program_id: String,
+        /// Number of accounts
+        accounts: usize,
+        /// Length of instruction data
+        data_len: usize,
+    },
+}
+
+impl DecodedInstruction {
+    /// One-line human-readable preview
+    pub fn summary(&self) -> String {
+        match self {
+            Self::SystemTransfer { from, to, lamports } => {
+                format!("Transfer {} SOL from {} to {}", format_sol(*lamports), from, to)
+            }
+            Self::TokenTransfer { source, destination, amount, mint, .. } => match mint {
+                Some(mint) => format!("Transfer {} of token {} from {} to {}", amount, mint, source, destination),
+                None => format!("Transfer {} tokens from {} to {}", amount, source, destination),
+            },
+            Self::TokenApprove { source, delegate, amount, .. } => {
+                format!("Approve {} to move {} tokens from {}", delegate, amount, source)
+            }
+            Self::TokenSetAuthority { account, new_authority } => match new_authority {
+                Some(a) => format!("Change authority of {} to {}", account, a),
+                None => format!("Remove authority of {}", account),
+            },
+            Self::SetComputeUnitLimit { units } => format!("Set compute unit limit to {}", units),
+            Self::SetComputeUnitPrice { micro_lamports } => {
+                format!("Set priority fee to {} micro-lamports per CU", micro_lamports)
+            }
+            Self::BubblegumTransfer { merkle_tree, leaf_owner, new_leaf_owner, leaf_index } => format!(
+                "Transfer compressed NFT #{} in tree {} from {} to {}",
+                leaf_index, merkle_tree, leaf_owner, new_leaf_owner
+            ),
+            Self::BubblegumDelegate { merkle_tree, new_leaf_delegate, leaf_index, .. } => format!(
+                "Delegate compressed NFT #{} in tree {} to {}",
+                leaf_index, merkle_tree, new_leaf_delegate
+            ),
+            Self::BubblegumBurn { merkle_tree, leaf_index, .. } => {
+                format!("Burn compressed NFT #{} in tree {}", leaf_index, merkle_tree)
+            }
+            Self::BubblegumMint { merkle_tree, leaf_owner } => {
+                format!("Mint compressed NFT in tree {} to {}", merkle_tree, leaf_owner)
+            }
+            Self::BubblegumOther { name } => format!("Bubblegum: {}", name),
+            Self::MetadataTransfer { mint, owner, destination_owner, .. } => {
+                format!("Transfer NFT {} from {} to {}", mint, owner, destination_owner)
+            }
+            Self::MetadataDelegate { mint, delegate, role } => {
+                format!("Approve {} as {} delegate of NFT {}", delegate, role, mint)
+            }
+            Self::MetadataRevoke { mint, delegate } => format!("Revoke delegate {} of NFT {}", delegate, mint),
+            Self::MetadataBurn { mint } => format!("Burn NFT {}", mint),
+            Self::MetadataOther { name } => format!("Token Metadata: {}", name),
+            Self::SquadsVaultTransactionCreate { vault, instructions, .. } => format!(
+                "Propose vault {} transaction: {}",
+                vault,
+                instructions.iter().map(Self::summary).collect::<Vec<_>>().join("; ")
+            ),
+            Self::SquadsProposalCreate { multisig, transaction_index, .. } => {
+                format!("Create proposal #{} on multisig {}", transaction_index, multisig)
+            }
+            Self::SquadsProposalVote { vote, proposal, member, .. } => {
+                format!("{} votes to {} proposal {}", member, vote, proposal)
+            }
+            Self::SquadsVaultTransactionExecute { proposal, .. } => {
+                format!("Execute approved proposal {}", proposal)
+            }
+            Self::SquadsOther { name } => format!("Squads: {}", name),
+            Self::Unknown { program_id, accounts, data_len } => format!(
+                "Call program {} ({} accounts, {} bytes of data)",
+                program_id, accounts, data_len
+            ),
+        }
+    }
+}
+
+/// Decode every instruction in a serialized message
+pub fn decode_transaction(message_bytes: &[u8]) -> Result<Vec<DecodedInstruction>, SignerError> {
+    Ok(decode_message(&SolanaMessage::parse(message_bytes)?))
+}
+
+/// Decode every instruction in a parsed message
+pub fn decode_message(message: &SolanaMessage) -> Vec<DecodedInstruction> {
+    message
+        .instructions
+        .iter()
+        .map(|ix| decode_instruction(message, ix))
+        .collect()
+}
+
+/// Decode a single instruction; falls back to `Unknown` on malformed data
+pub fn decode_instruction(message: &SolanaMessage, ix: &CompiledInstruction) -> DecodedInstruction {
+    let program_id = account_name(message, ix.program_id_index);
+    let view = InstructionView { message, ix };
+
+    let decoded = match program_id.as_str() {
+        SYSTEM_PROGRAM_ID => decode_system(&view),
+        TOKEN_PROGRAM_ID | TOKEN_2022_PROGRAM_ID => decode_token(&view),
+        COMPUTE_BUDGET_PROGRAM_ID => decode_compute_budget(&view),
+        BUBBLEGUM_PROGRAM_ID => decode_bubblegum(&view),
+        TOKEN_METADATA_PROGRAM_ID => decode_token_metadata(&view),
+        SQUADS_V4_PROGRAM_ID => decode_squads(&view),
+        _ => None,
+    };
+
+    decoded.unwrap_or(DecodedInstruction::Unknown {
+        program_id,
+        accounts: ix.accounts.len(),
+        data_len: ix.data.len(),
+    })
+}
+
+/// Anchor instruction discriminator: `sha256("global:<name>")[..8]`
+pub fn anchor_discriminator(name: &str) -> [u8; 8] {
+    let hash = Sha256::digest(format!("global:{}", name).as_bytes());
+    hash[..8].try_into().expect("sha256 output is 32 bytes")
+}
+
+/// Accessors over one instruction's accounts and data
+struct InstructionView<'a> {
+    message: &'a SolanaMessage,
+    ix: &'a CompiledInstruction,
+}
+
+impl InstructionView<'_> {
+    fn account(&self, position: usize) -> Option<String> {
+        self.ix.accounts.get(position).map(|&i| account_name(self.message, i))
+    }
+
+    fn data(&self) -> &[u8] {
+        &self.ix.data
+    }
+
+    fn u32_at(&self, offset: usize) -> Option<u32> {
+        self.ix.data.get(offset..offset + 4).map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+    }
+
+    fn u64_at(&self, offset: usize) -> Option<u64> {
+        self.ix.data.get(offset..offset + 8).map(|b| u64::from_le_bytes(b.try_into().unwrap()))
+    }
+}
+
+fn account_name(message: &SolanaMessage, index: u8) -> String {
+    match message.static_key(index) {
+        Some(key) => bs58::encode(key).into_string(),
+        None => format!("lookup:{}", index),
+    }
+}
+
+fn decode_system(view: &InstructionView) -> Option<DecodedInstruction> {
+    // Transfer = 2 (u32 discriminator) followed by lamports
+    if view.u32_at(0)? != 2 || view.data().len() != 12 {
+        return None;
+    }
+    Some(DecodedInstruction::SystemTransfer {
+        from: view.account(0)?,
+        to: view.account(1)?,
+        lamports: view.u64_at(4)?,
+    })
+}
+
+fn decode_token(view: &InstructionView) -> Option<DecodedInstruction> {
+    match *view.data().first()? {
+        3 => Some(DecodedInstruction::TokenTransfer {
+            source: view.account(0)?,
+            destination: view.account(1)?,
+            authority: view.account(2)?,
+            amount: view.u64_at(1)?,
+            mint: None,
+            decimals: None,
+        }),
+        4 | 13 => Some(DecodedInstruction::TokenApprove {
+            source: view.account(0)?,
+            // ApproveChecked inserts the mint at position 1
+            delegate: view.account(if view.data()[0] == 13 { 2 } else { 1 })?,
+            owner: view.account(if view.data()[0] == 13 { 3 } else { 2 })?,
+            amount: view.u64_at(1)?,
+        }),
+        6 => {
+            // authority_type u8, then COption<Pubkey>
+            let new_authority = match *view.data().get(2)? {
+                0 => None,
+                1 => Some(bs58::encode(view.data().get(3..35)?).into_string()),
+                _ => return None,
+            };
+            Some(DecodedInstruction::TokenSetAuthority {
+                account: view.account(0)?,
+                new_authority,
+            })
+        }
+        12 => Some(DecodedInstruction::TokenTransfer {
+            source: view.account(0)?,
+            destination: view.account(2)?,
+            authority: view.account(3)?,
+            amount: view.u64_at(1)?,
+            mint: Some(view.account(1)?),
+            decimals: Some(*view.data().get(9)?),
+        }),
+        _ => None,
+    }
+}
+
+fn decode_compute_budget(view: &InstructionView) -> Option<DecodedInstruction> {
+    match *view.data().first()? {
+        2 => Some(DecodedInstruction::SetComputeUnitLimit { units: view.u32_at(1)? }),
+        3 => Some(DecodedInstruction::SetComputeUnitPrice {
+            micro_lamports: view.u64_at(1)?,
+        }),
+        _ => None,
+    }
+}
+
+fn decode_bubblegum(view: &InstructionView) -> Option<DecodedInstruction> {
+    let discriminator = view.data().get(..8)?;
+    let name = BUBBLEGUM_INSTRUCTIONS
+        .iter()
+        .find(|name| anchor_discriminator(name) == discriminator)?;
+
+    // transfer/delegate/burn args: root, data_hash, creator_hash (3 x 32),
+    // nonce u64, index u32
+    let leaf_index = || view.u32_at(8 + 96 + 8);
+
+    match *name {
+        // tree_authority, leaf_owner, leaf_delegate, new_leaf_owner, merkle_tree, ...
+        "transfer" => Some(DecodedInstruction::BubblegumTransfer {
+            merkle_tree: view.account(4)?,
+            leaf_owner: view.account(1)?,
+            new_leaf_owner: view.account(3)?,
+            leaf_index: leaf_index()?,
+        }),
+        // tree_authority, leaf_owner, previous_leaf_delegate, new_leaf_delegate, merkle_tree, ...
+        "delegate" => Some(DecodedInstruction::BubblegumDelegate {
+            merkle_tree: view.account(4)?,
+            leaf_owner: view.account(1)?,
+            new_leaf_delegate: view.account(3)?,
+            leaf_index: leaf_index()?,
+        }),
+        // tree_authority, leaf_owner, leaf_delegate, merkle_tree, ...
+        "burn" => Some(DecodedInstruction::BubblegumBurn {
+            merkle_tree: view.account(3)?,
+            leaf_owner: view.account(1)?,
+            leaf_index: leaf_index()?,
+        }),
+        // tree_authority, leaf_owner, leaf_delegate, merkle_tree, ...
+        "mint_v1" | "mint_to_collection_v1" => Some(DecodedInstruction::BubblegumMint {
+            merkle_tree: view.account(3)?,
+            leaf_owner: view.account(1)?,
+        }),
+        other => Some(DecodedInstruction::BubblegumOther {
+            name: other.to_string(),
+        }),
+    }
+}
+
+fn decode_token_metadata(view: &InstructionView) -> Option<DecodedInstruction> {
+    let name = match *view.data().first()? {
+        // Transfer: token, token_owner, destination, destination_owner, mint, ...
+        // args: TransferArgs::V1 { amount, .. }
+        49 => {
+            return Some(DecodedInstruction::MetadataTransfer {
+                mint: view.account(4)?,
+                owner: view.account(1)?,
+                destination_owner: view.account(3)?,
+                amount: view.u64_at(2)?,
+            })
+        }
+        // Delegate: delegate_record, delegate, metadata, master_edition,
+        // token_record, mint, ...
+        44 => {
+            let role = METADATA_DELEGATE_ROLES.get(*view.data().get(1)? as usize)?;
+            return Some(DecodedInstruction::MetadataDelegate {
+                mint: view.account(5)?,
+                delegate: view.account(1)?,
+                role: role.to_string(),
+            });
+        }
+        // Revoke: same account layout as Delegate
+        45 => {
+            return Some(DecodedInstruction::MetadataRevoke {
+                mint: view.account(5)?,
+                delegate: view.account(1)?,
+            })
+        }
+        // Burn: authority, collection_metadata, metadata, edition, mint, ...
+        41 => return Some(DecodedInstruction::MetadataBurn { mint: view.account(4)? }),
+        1 | 15 => "update_metadata_account",
+        33 => "create_metadata_account_v3",
+        42 => "create",
+        43 => "mint",
+        46 => "lock",
+        47 => "unlock",
+        48 => "migrate",
+        50 => "update",
+        51 => "use",
+        52 => "verify",
+        53 => "unverify",
+        _ => return None,
+    };
+    Some(DecodedInstruction::MetadataOther { name: name.to_string() })
+}
+
+/// Squads instructions that are decoded, by Anchor method name
+const SQUADS_INSTRUCTIONS: &[&str] = &[
+    "vault_transaction_create",
+    "proposal_create",
+    "proposal_approve",
+    "proposal_reject",
+    "proposal_cancel",
+    "proposal_cancel_v2",
+    "proposal_activate",
+    "vault_transaction_execute",
+    "multisig_create_v2",
+    "config_transaction_create",
+    "config_transaction_execute",
+    "batch_create",
+    "batch_add_transaction",
+    "batch_execute_transaction",
+    "spending_limit_use",
+];
+
+fn decode_squads(view: &InstructionView) -> Option<DecodedInstruction> {
+    let discriminator = view.data().get(..8)?;
+    let name = SQUADS_INSTRUCTIONS
+        .iter()
+        .find(|name| anchor_discriminator(name) == discriminator)?;
+
+    match *name {
+        // multisig, transaction, creator, rent_payer, system_program
+        // args: vault_index u8, ephemeral_signers u8, transaction_message bytes, memo
+        "vault_transaction_create" => {
+            let len = view.u32_at(10)? as usize;
+            let bytes = view.data().get(14..14usize.checked_add(len)?)?;
+            let message = VaultTransactionMessage::parse(bytes).ok()?.to_message().ok()?;
+            Some(DecodedInstruction::SquadsVaultTransactionCreate {
+                multisig: view.account(0)?,
+                // The vault is the inner transaction's first signer
+                vault: bs58::encode(message.fee_payer()?).into_string(),
+                creator: view.account(2)?,
+                instructions: decode_message(&message),
+            })
+        }
+        // multisig, proposal, creator, rent_payer, system_program
+        // args: transaction_index u64, draft bool
+        "proposal_create" => Some(DecodedInstruction::SquadsProposalCreate {
+            multisig: view.account(0)?,
+            proposal: view.account(1)?,
+            transaction_index: view.u64_at(8)?,
+        }),
+        // multisig, member, proposal
+        "proposal_approve" | "proposal_reject" | "proposal_cancel" | "proposal_cancel_v2" => {
+            let vote = name.trim_start_matches("proposal_").trim_end_matches("_v2");
+            Some(DecodedInstruction::SquadsProposalVote {
+                vote: vote.to_string(),
+                multisig: view.account(0)?,
+                proposal: view.account(2)?,
+                member: view.account(1)?,
+            })
+        }
+        // multisig, proposal, transaction, member
+        "vault_transaction_execute" => Some(DecodedInstruction::SquadsVaultTransactionExecute {
+            multisig: view.account(0)?,
+            proposal: view.account(1)?,
+            transaction: view.account(2)?,
+        }),
+        other => Some(DecodedInstruction::SquadsOther {
+            name: other.to_string(),
+        }),
+    }
+}
+
+fn format_sol(lamports: u64) -> String {
+    format!("{}.{:09}", lamports / 1_000_000_000, lamports % 1_000_000_000)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solana::message::encode_compact_u16;
+
+    fn key(name: &str) -> [u8; 32] {
+        bs58::decode(name).into_vec().unwrap().try_into().unwrap()
+    }
+
+    /// Legacy message with one signer and the given instructions
+    fn message(keys: &[[u8; 32]], instructions: &[(u8, Vec<u8>, Vec<u8>)]) -> Vec<u8> {
+        let mut bytes = vec![1, 0, 0];
+        encode_compact_u16(keys.len(), &mut bytes);
+        keys.iter().for_each(|k| bytes.extend_from_slice(k));
+        bytes.extend_from_slice(&[0u8; 32]);
+        encode_compact_u16(instructions.len(), &mut bytes);
+        for (program, accounts, data) in instructions {
+            bytes.push(*program);
+            encode_compact_u16(accounts.len(), &mut bytes);
+            bytes.extend_from_slice(accounts);
+            encode_compact_u16(data.len(), &mut bytes);
+            bytes.extend_from_slice(data);
+        }
+        bytes
+    }
+
+    #[test]
+    fn test_decode_system_and_compute_budget() {
+        let keys = [[1u8; 32], [2u8; 32], key(SYSTEM_PROGRAM_ID), key(COMPUTE_BUDGET_PROGRAM_ID)];
+        let mut transfer = 2u32.to_le_bytes().to_vec();
+        transfer.extend_from_slice(&1_500_000_000u64.to_le_bytes());
+        let bytes = message(
+            &keys,
+            &[(3, vec![], vec![3, 10, 0, 0, 0, 0, 0, 0, 0]), (2, vec![0, 1], transfer)],
+        );
+
+        let decoded = decode_transaction(&bytes).unwrap();
+        assert_eq!(decoded[0], DecodedInstruction::SetComputeUnitPrice { micro_lamports: 10 });
+        assert!(decoded[1].summary().starts_with("Transfer 1.500000000 SOL"));
+    }
+
+    #[test]
+    fn test_decode_bubblegum_transfer() {
+        let keys = [
+            [1u8; 32], // leaf owner / payer
+            [2u8; 32], // tree authority
+            [3u8; 32], // new owner
+            [4u8; 32], // merkle tree
+            key(BUBBLEGUM_PROGRAM_ID),
+        ];
+        let mut data = anchor_discriminator("transfer").to_vec();
+        data.extend_from_slice(&[0u8; 96]);
+        data.extend_from_slice(&7u64.to_le_bytes());
+        data.extend_from_slice(&42u32.to_le_bytes());
+        let bytes = message(&keys, &[(4, vec![1, 0, 0, 2, 3], data)]);
+
+        let decoded = decode_transaction(&bytes).unwrap();
+        assert_eq!(
+            decoded[0],
+            DecodedInstruction::BubblegumTransfer {
+                merkle_tree: bs58::encode([4u8; 32]).into_string(),
+                leaf_owner: bs58::encode([1u8; 32]).into_string(),
+                new_leaf_owner: bs58::encode([3u8; 32]).into_string(),
+                leaf_index: 42,
+            }
+        );
+    }
+
+    #[test]
+    fn test_decode_token_metadata_delegate_and_unknown() {
+        let keys = [[1u8; 32], [2u8; 32], [3u8; 32], key(TOKEN_METADATA_PROGRAM_ID), [9u8; 32]];
+        let bytes = message(
+            &keys,
+            &[
+                // Delegate SaleV1 to account 2 for mint at account 1
+                (3, vec![0, 2, 0, 0, 0, 1, 0, 0], vec![44, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0]),
+                (4, vec![0], vec![1, 2, 3]),
+            ],
+        );
+
+        let decoded = decode_transaction(&bytes).unwrap();
+        assert_eq!(
+            decoded[0],
+            DecodedInstruction::MetadataDelegate {
+                mint: bs58::encode([2u8; 32]).into_string(),
+                delegate: bs58::encode([3u8; 32]).into_string(),
+                role: "sale".to_string(),
+            }
+        );
+        assert!(matches!(decoded[1], DecodedInstruction::Unknown { data_len: 3, .. }));
+    }
+}
@@ -0,0 +1,310 @@
+//! Solana message parsing (legacy and v0)
+//!
+//! Parses the bytes that are signed (the message, not the full transaction
+//! with its signature section) into account keys and compiled instructions.
+
+use crate::error::SignerError;
+use crate::reader::{ByteReader, ParseError};
+
+/// Message format version
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MessageVersion {
+    /// Legacy message
+    Legacy,
+    /// Versioned message, v0 (address lookup tables)
+    V0,
+}
+
+/// Message header
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MessageHeader {
+    /// Number of signatures required
+    pub num_required_signatures: u8,
+    /// Signed accounts that are read-only
+    pub num_readonly_signed_accounts: u8,
+    /// Unsigned accounts that are read-only
+    pub num_readonly_unsigned_accounts: u8,
+}
+
+/// An instruction referencing accounts by index
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CompiledInstruction {
+    /// Index of the program account
+    pub program_id_index: u8,
+    /// Indexes of the instruction's accounts
+    pub accounts: Vec<u8>,
+    /// Instruction data
+    pub data: Vec<u8>,
+}
+
+/// Accounts loaded from an address lookup table (v0 only)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AddressTableLookup {
+    /// Lookup table account
+    pub account_key: [u8; 32],
+    /// Indexes of writable accounts in the table
+    pub writable_indexes: Vec<u8>,
+    /// Indexes of read-only accounts in the table
+    pub readonly_indexes: Vec<u8>,
+}
+
+/// A parsed Solana message
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SolanaMessage {
+    /// Format version
+    pub version: MessageVersion,
+    /// Header
+    pub header: MessageHeader,
+    /// Static account keys
+    pub account_keys: Vec<[u8; 32]>,
+    /// Recent blockhash (or durable nonce)
+    pub recent_blockhash: [u8; 32],
+    /// Instructions
+    pub instructions: Vec<CompiledInstruction>,
+    /// Address table lookups (empty for legacy messages)
+    pub address_table_lookups: Vec<AddressTableLookup>,
+}
+
+impl SolanaMessage {
+    /// Parse a serialized message
+    pub fn parse(bytes: &[u8]) -> Result<Self, SignerError> {
+        let mut reader = ByteReader::new(bytes, "Solana message");
+
+        let version = match reader.peek(0) {
+            Some(prefix) if prefix & 0x80 != 0 => {
+                reader.u8()?;
+                match prefix & 0x7f {
+                    0 => MessageVersion::V0,
+                    v => {
+                        return Err(SignerError::InvalidTransaction(format!(
+                            "unsupported message version {}",
+                            v
+                        )))
+                    }
+                }
+            }
+            _ => MessageVersion::Legacy,
+        };
+
+        let header = MessageHeader {
+            num_required_signatures: reader.u8()?,
+            num_readonly_signed_accounts: reader.u8()?,
+            num_readonly_unsigned_accounts: reader.u8()?,
+        };
+
+        let num_keys = read_count(&mut reader, 32)?;
+        let account_keys = (0..num_keys).map(|_| reader.array()).collect::<Result<Vec<_>, _>>()?;
+        let recent_blockhash = reader.array()?;
+
+        // program index and two empty shortvecs
+        let num_instructions = read_count(&mut reader, 3)?;
+        let instructions = (0..num_instructions)
+            .map(|_| {
+                let program_id_index = reader.u8()?;
+                let accounts = read_vec(&mut reader)?;
+                let data = read_vec(&mut reader)?;
+                Ok(CompiledInstruction {
+                    program_id_index,
+                    accounts,
+                    data,
+                })
+            })
+            .collect::<Result<Vec<_>, SignerError>>()?;
+
+        let address_table_lookups = match version {
+            MessageVersion::Legacy => Vec::new(),
+            MessageVersion::V0 => {
+                let count = read_count(&mut reader, 34)?;
+                (0..count)
+                    .map(|_| {
+                        Ok(AddressTableLookup {
+                            account_key: reader.array()?,
+                            writable_indexes: read_vec(&mut reader)?,
+                            readonly_indexes: read_vec(&mut reader)?,
+                        })
+                    })
+                    .collect::<Result<Vec<_>, SignerError>>()?
+            }
+        };
+
+        reader.finish()?;
+
+        let message = Self {
+            version,
+            header,
+            account_keys,
+            recent_blockhash,
+            instructions,
+            address_table_lookups,
+        };
+        message.validate()?;
+        Ok(message)
+    }
+
+    /// Serialize back to wire format (the bytes that are signed)
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        if self.version == MessageVersion::V0 {
+            out.push(0x80);
+        }
+        out.extend_from_slice(&[
+            self.header.num_required_signatures,
+            self.header.num_readonly_signed_accounts,
+            self.header.num_readonly_unsigned_accounts,
+        ]);
+
+        encode_compact_u16(self.account_keys.len(), &mut out);
+        self.account_keys.iter().for_each(|k| out.extend_from_slice(k));
+        out.extend_from_slice(&self.recent_blockhash);
+
+        encode_compact_u16(self.instructions.len(), &mut out);
+        for ix in &self.instructions {
+            out.push(ix.program_id_index);
+            encode_compact_u16(ix.accounts.len(), &mut out);
+            out.extend_from_slice(&ix.accounts);
+            encode_compact_u16(ix.data.len(), &mut out);
+            out.extend_from_slice(&ix.data);
+        }
+
+        if self.version == MessageVersion::V0 {
+            encode_compact_u16(self.address_table_lookups.len(), &mut out);
+            for lookup in &self.address_table_lookups {
+                out.extend_from_slice(&lookup.account_key);
+                encode_compact_u16(lookup.writable_indexes.len(), &mut out);
+                out.extend_from_slice(&lookup.writable_indexes);
+                encode_compact_u16(lookup.readonly_indexes.len(), &mut out);
+                out.extend_from_slice(&lookup.readonly_indexes);
+            }
+        }
+        out
+    }
+
+    /// Total number of accounts, including those loaded from lookup tables
+    pub fn total_accounts(&self) -> usize {
+        self.account_keys.len()
+            + self
+                .address_table_lookups
+                .iter()
+                .map(|l| l.writable_indexes.len() + l.readonly_indexes.len())
+                .sum::<usize>()
+    }
+
+    /// Static key at `index`, or `None` for lookup-table accounts
+    pub fn static_key(&self, index: u8) -> Option<&[u8; 32]> {
+        self.account_keys.get(index as usize)
+    }
+
+    /// Whether the account at `index` is a signer
+    pub fn is_signer(&self, index: usize) -> bool {
+        index < self.header.num_required_signatures as usize
+    }
+
+    /// Whether the account at `index` is writable
+    pub fn is_writable(&self, index: usize) -> bool {
+        let num_static = self.account_keys.len();
+        let signers = self.header.num_required_signatures as usize;
+
+        if index < signers {
+            index < signers - self.header.num_readonly_signed_accounts as usize
+        } else if index < num_static {
+            index < num_static - self.header.num_readonly_unsigned_accounts as usize
+        } else {
+            // Lookup accounts: all writable ones come before read-only ones
+            let writable: usize = self.address_table_lookups.iter().map(|l| l.writable_indexes.len()).sum();
+            index - num_static < writable
+        }
+    }
+
+    /// The fee payer (first signer)
+    pub fn fee_payer(&self) -> Option<&[u8; 32]> {
+        self.account_keys.first()
+    }
+
+    fn validate(&self) -> Result<(), SignerError> {
+        let header = &self.header;
+        if header.num_required_signatures == 0
+            || header.num_readonly_signed_accounts >= header.num_required_signatures
+            || header.num_required_signatures as usize + header.num_readonly_unsigned_accounts as usize
+                > self.account_keys.len()
+        {
+            return Err(SignerError::InvalidTransaction("invalid message header".to_string()));
+        }
+
+        let total = self.total_accounts();
+        for ix in &self.instructions {
+            if ix.program_id_index as usize >= self.account_keys.len()
+                || ix.accounts.iter().any(|&a| a as usize >= total)
+            {
+                return Err(SignerError::InvalidTransaction(
+                    "instruction references a missing account".to_string(),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Read a shortvec element count, each element taking at least `min_item_size` bytes
+pub(crate) fn read_count(reader: &mut ByteReader, min_item_size: usize) -> Result<usize, ParseError> {
+    let count = reader.compact_u16()?;
+    reader.count(count as u64, min_item_size)
+}
+
+fn read_vec(reader: &mut ByteReader) -> Result<Vec<u8>, ParseError> {
+    let len = reader.compact_u16()?;
+    Ok(reader.take_len(len as u64)?.to_vec())
+}
+
+/// Encode a Solana "shortvec" length
+pub(crate) fn encode_compact_u16(mut value: usize, out: &mut Vec<u8>) {
+    loop {
+        let mut byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value != 0 {
+            byte |= 0x80;
+        }
+        out.push(byte);
+        if value == 0 {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compact_u16_roundtrip() {
+        for value in [0usize, 1, 127, 128, 300, 16383, 16384] {
+            let mut bytes = Vec::new();
+            encode_compact_u16(value, &mut bytes);
+            let mut reader = ByteReader::new(&bytes, "test");
+            assert_eq!(reader.compact_u16().unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn test_parse_v0_message() {
+        // v0 prefix, header, 2 keys, blockhash, 1 instruction, 1 lookup table
+        let mut bytes = vec![0x80, 1, 0, 1, 2];
+        bytes.extend_from_slice(&[1u8; 32]);
+        bytes.extend_from_slice(&[2u8; 32]);
+        bytes.extend_from_slice(&[9u8; 32]);
+        bytes.extend_from_slice(&[1, 1, 2, 0, 2, 1, 0xff]);
+        bytes.push(1);
+        bytes.extend_from_slice(&[3u8; 32]);
+        bytes.extend_from_slice(&[1, 5, 0]);
+
+        let message = SolanaMessage::parse(&bytes).unwrap();
+        assert_eq!(message.version, MessageVersion::V0);
+        assert_eq!(message.total_accounts(), 3);
+        assert!(message.is_writable(0) && !message.is_writable(1) && message.is_writable(2));
+        assert_eq!(message.instructions[0].accounts, vec![0, 2]);
+        assert!(message.static_key(2).is_none());
+        assert_eq!(message.serialize(), bytes);
+
+        // Truncated input is rejected
+        assert!(SolanaMessage::parse(&bytes[..bytes.len() - 1]).is_err());
+    }
+}
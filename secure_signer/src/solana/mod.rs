@@ -0,0 +1,22 @@
+//! Solana transaction support
+//!
+//! - [`builder`]: SOL and SPL token transfer transactions from high-level
+//!   parameters
+//! - [`message`]: parsing of legacy and v0 messages
+//! - [`decode`]: instruction decoding for human-readable previews
+//! - [`transaction`]: partially-signed transactions for multi-party signing
+//! - [`squads`]: Squads v4 multisig proposals and member approvals
+//! - [`siws`]: Sign-In with Solana messages
+
+pub mod builder;
+pub mod decode;
+pub mod message;
+pub mod siws;
+pub mod squads;
+pub mod transaction;
+
+pub use builder::{TokenTransfer, Transfer, TransferBuilder};
+pub use decode::{decode_transaction, DecodedInstruction};
+pub use message::SolanaMessage;
+pub use siws::{SiwsMessage, SiwsSignature};
+pub use transaction::{decrypt_and_sign_all, SignatureStatus, SolanaTransaction};
@@ -0,0 +1,422 @@
+//! Sign-In with Solana
+//!
+//! [`SiwsMessage`] is the sign-in message of the Wallet Standard
+//! `solana:signIn` feature, the Solana counterpart of
+//! [SIWE](crate::evm::siwe). Only the domain and address are required; the
+//! statement and each field block are separated by a blank line only when
+//! present, which differs from EIP-4361. Wallets sign the UTF-8 text itself
+//! with Ed25519, so the signature verifies against the message bytes
+//! directly.
+//!
+//! [`SiwsMessage::sign`] refuses a message for another key, an expired one,
+//! and any text that also parses as a Solana transaction message, so a
+//! sign-in can never double as a transaction signature.
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::Serialize;
+
+use crate::backend::SignerBackend;
+use crate::error::SignerError;
+use crate::solana::message::SolanaMessage;
+use crate::validity::{format_rfc3339, parse_rfc3339, unix_now};
+
+const HEADER_SUFFIX: &str = " wants you to sign in with your Solana account:";
+
+/// Chain ids the Wallet Standard defines, besides `solana:` CAIP-2 ids
+const CLUSTERS: &[&str] = &["mainnet", "testnet", "devnet", "localnet"];
+
+/// A Sign-In with Solana message
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SiwsMessage {
+    /// RFC 3986 authority requesting the sign-in
+    pub domain: String,
+    /// Base58 public key
+    pub address: String,
+    /// Human-readable assertion, on a single line
+    pub statement: Option<String>,
+    pub uri: Option<String>,
+    /// "1" when present
+    pub version: Option<String>,
+    /// `mainnet`, `devnet`, ... or a `solana:` CAIP-2 id
+    pub chain_id: Option<String>,
+    /// At least eight alphanumeric characters, chosen by the dApp
+    pub nonce: Option<String>,
+    /// RFC 3339
+    pub issued_at: Option<String>,
+    /// RFC 3339
+    pub expiration_time: Option<String>,
+    /// RFC 3339
+    pub not_before: Option<String>,
+    pub request_id: Option<String>,
+    pub resources: Vec<String>,
+}
+
+/// A signed sign-in message
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct SiwsSignature {
+    /// The exact text that was signed
+    pub message: String,
+    /// Ed25519 signature (base58)
+    pub signature: String,
+    /// Base58 public key
+    pub address: String,
+}
+
+fn invalid(reason: impl Into<String>) -> SignerError {
+    SignerError::InvalidTransaction(format!("SIWS message: {}", reason.into()))
+}
+
+impl SiwsMessage {
+    /// A message from `domain` for `address`, issued now, version 1
+    pub fn new(domain: &str, address: &str) -> Result<Self, SignerError> {
+        let message = SiwsMessage {
+            domain: domain.to_string(),
+            address: address.to_string(),
+            statement: None,
+            uri: None,
+            version: Some("1".to_string()),
+            chain_id: None,
+            nonce: None,
+            issued_at: Some(format_rfc3339(unix_now())),
+            expiration_time: None,
+            not_before: None,
+            request_id: None,
+            resources: Vec::new(),
+        };
+        message.validate()?;
+        Ok(message)
+    }
+
+    pub fn with_statement(mut self, statement: &str) -> Self {
+        self.statement = Some(statement.to_string());
+        self
+    }
+
+    pub fn with_uri(mut self, uri: &str) -> Self {
+        self.uri = Some(uri.to_string());
+        self
+    }
+
+    pub fn with_chain_id(mut self, chain_id: &str) -> Self {
+        self.chain_id = Some(chain_id.to_string());
+        self
+    }
+
+    pub fn with_nonce(mut self, nonce: &str) -> Self {
+        self.nonce = Some(nonce.to_string());
+        self
+    }
+
+    /// Expire at `unix_secs`
+    pub fn with_expiration_time(mut self, unix_secs: u64) -> Self {
+        self.expiration_time = Some(format_rfc3339(unix_secs));
+        self
+    }
+
+    /// Not valid before `unix_secs`
+    pub fn with_not_before(mut self, unix_secs: u64) -> Self {
+        self.not_before = Some(format_rfc3339(unix_secs));
+        self
+    }
+
+    pub fn with_request_id(mut self, request_id: &str) -> Self {
+        self.request_id = Some(request_id.to_string());
+        self
+    }
+
+    pub fn with_resources(mut self, resources: Vec<String>) -> Self {
+        self.resources = resources;
+        self
+    }
+
+    /// Check every field that is present
+    pub fn validate(&self) -> Result<(), SignerError> {
+        let single_line = |value: &str| !value.is_empty() && !value.contains(['\n', '\r']);
+        if !single_line(&self.domain) || self.domain.contains(char::is_whitespace) {
+            return Err(invalid("domain must be a non-empty authority"));
+        }
+        if bs58::decode(&self.address).into_vec().map(|key| key.len()) != Ok(32) {
+            return Err(invalid(format!("'{}' is not a Solana address", self.address)));
+        }
+        if self.statement.as_deref().is_some_and(|statement| !single_line(statement)) {
+            return Err(invalid("statement must be a single non-empty line"));
+        }
+        if self
+            .uri
+            .as_deref()
+            .is_some_and(|uri| !single_line(uri) || uri.contains(char::is_whitespace) || !uri.contains(':'))
+        {
+            return Err(invalid("uri must be an absolute URI"));
+        }
+        if self.version.as_deref().is_some_and(|version| version != "1") {
+            return Err(invalid(format!("unsupported version '{}'", self.version.as_deref().unwrap_or(""))));
+        }
+        if let Some(chain_id) = &self.chain_id {
+            let caip2 = chain_id
+                .strip_prefix("solana:")
+                .is_some_and(|reference| !reference.is_empty() && reference.bytes().all(|b| b.is_ascii_alphanumeric()));
+            if !caip2 && !CLUSTERS.contains(&chain_id.as_str()) {
+                return Err(invalid(format!("unknown chain id '{}'", chain_id)));
+            }
+        }
+        if self
+            .nonce
+            .as_deref()
+            .is_some_and(|nonce| nonce.len() < 8 || !nonce.bytes().all(|b| b.is_ascii_alphanumeric()))
+        {
+            return Err(invalid("nonce must be at least 8 alphanumeric characters"));
+        }
+        for (name, value) in [
+            ("issued-at", &self.issued_at),
+            ("expiration-time", &self.expiration_time),
+            ("not-before", &self.not_before),
+        ] {
+            if value.as_deref().is_some_and(|value| parse_rfc3339(value).is_none()) {
+                return Err(invalid(format!("{} is not an RFC 3339 timestamp", name)));
+            }
+        }
+        if self.request_id.as_deref().is_some_and(|id| id.contains(['\n', '\r'])) {
+            return Err(invalid("request id must be a single line"));
+        }
+        if self.resources.iter().any(|resource| !single_line(resource)) {
+            return Err(invalid("resources must be single-line URIs"));
+        }
+        Ok(())
+    }
+
+    /// Fail unless the message is valid at `now` (Unix seconds)
+    pub fn check_time(&self, now: u64) -> Result<(), SignerError> {
+        let now = now as i64;
+        if let Some(expiration) = self.expiration_time.as_deref().and_then(parse_rfc3339) {
+            if now >= expiration {
+                return Err(invalid(format!("expired at {}", self.expiration_time.as_deref().unwrap_or(""))));
+            }
+        }
+        if let Some(not_before) = self.not_before.as_deref().and_then(parse_rfc3339) {
+            if now < not_before {
+                return Err(invalid(format!("not valid before {}", self.not_before.as_deref().unwrap_or(""))));
+            }
+        }
+        Ok(())
+    }
+
+    /// Sign the message text with the backend's Ed25519 key
+    ///
+    /// Fails if the message is malformed or expired, or if the backend's
+    /// key is not the message's address. A `not-before` in the future is
+    /// allowed: the dApp checks it when the signature is presented.
+    pub fn sign(&self, backend: &dyn SignerBackend) -> Result<SiwsSignature, SignerError> {
+        self.validate()?;
+        if let Some(expiration) = self.expiration_time.as_deref().and_then(parse_rfc3339) {
+            if unix_now() as i64 >= expiration {
+                return Err(invalid("message has expired"));
+            }
+        }
+        let message = self.to_string();
+        if SolanaMessage::parse(message.as_bytes()).is_ok() {
+            return Err(invalid("text also parses as a transaction message"));
+        }
+        let result = backend.sign_solana_message(message.as_bytes())?;
+        if result.public_key != self.address {
+            return Err(invalid(format!(
+                "message is for {}, but the key is {}",
+                self.address, result.public_key
+            )));
+        }
+        Ok(SiwsSignature {
+            message,
+            signature: result.signature,
+            address: result.public_key,
+        })
+    }
+
+    /// The `Name: value` lines after the statement
+    fn fields(&self) -> Vec<String> {
+        let mut fields: Vec<String> = [
+            ("URI", &self.uri),
+            ("Version", &self.version),
+            ("Chain ID", &self.chain_id),
+            ("Nonce", &self.nonce),
+            ("Issued At", &self.issued_at),
+            ("Expiration Time", &self.expiration_time),
+            ("Not Before", &self.not_before),
+            ("Request ID", &self.request_id),
+        ]
+        .into_iter()
+        .filter_map(|(name, value)| value.as_ref().map(|value| format!("{}: {}", name, value)))
+        .collect();
+        if !self.resources.is_empty() {
+            fields.push("Resources:".to_string());
+            fields.extend(self.resources.iter().map(|resource| format!("- {}", resource)));
+        }
+        fields
+    }
+}
+
+impl fmt::Display for SiwsMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}\n{}", self.domain, HEADER_SUFFIX, self.address)?;
+        if let Some(statement) = &self.statement {
+            write!(f, "\n\n{}", statement)?;
+        }
+        let fields = self.fields();
+        if !fields.is_empty() {
+            write!(f, "\n\n{}", fields.join("\n"))?;
+        }
+        Ok(())
+    }
+}
+
+/// Parse the text of a sign-in message
+impl FromStr for SiwsMessage {
+    type Err = SignerError;
+
+    fn from_str(text: &str) -> Result<Self, SignerError> {
+        let mut blocks = text.split("\n\n");
+        let mut header = blocks.next().unwrap_or_default().split('\n');
+        let domain = header
+            .next()
+            .and_then(|line| line.strip_suffix(HEADER_SUFFIX))
+            .ok_or_else(|| invalid("first line is not a sign-in request"))?;
+        let address = header.next().ok_or_else(|| invalid("missing address"))?;
+        if header.next().is_some() {
+            return Err(invalid("expected a blank line after the address"));
+        }
+
+        let mut message = SiwsMessage {
+            domain: domain.to_string(),
+            address: address.to_string(),
+            statement: None,
+            uri: None,
+            version: None,
+            chain_id: None,
+            nonce: None,
+            issued_at: None,
+            expiration_time: None,
+            not_before: None,
+            request_id: None,
+            resources: Vec::new(),
+        };
+        let mut block = blocks.next();
+        // A statement is a single line that does not start a field block
+        if let Some(statement) = block.filter(|block| !block.contains('\n') && !is_field(block)) {
+            message.statement = Some(statement.to_string());
+            block = blocks.next();
+        }
+        if let Some(fields) = block {
+            let mut lines = fields.split('\n').peekable();
+            let slots = [
+                ("URI", &mut message.uri),
+                ("Version", &mut message.version),
+                ("Chain ID", &mut message.chain_id),
+                ("Nonce", &mut message.nonce),
+                ("Issued At", &mut message.issued_at),
+                ("Expiration Time", &mut message.expiration_time),
+                ("Not Before", &mut message.not_before),
+                ("Request ID", &mut message.request_id),
+            ];
+            for (name, slot) in slots {
+                let value = lines
+                    .peek()
+                    .and_then(|line| line.strip_prefix(name))
+                    .and_then(|rest| rest.strip_prefix(": "));
+                if let Some(value) = value {
+                    *slot = Some(value.to_string());
+                    lines.next();
+                }
+            }
+            if lines.next_if_eq(&"Resources:").is_some() {
+                while let Some(resource) = lines.next_if(|line| line.starts_with("- ")) {
+                    message.resources.push(resource[2..].to_string());
+                }
+            }
+            if let Some(line) = lines.next() {
+                return Err(invalid(format!("unexpected line '{}'", line)));
+            }
+        }
+        if let Some(extra) = blocks.next() {
+            return Err(invalid(format!("unexpected text '{}'", extra)));
+        }
+        message.validate()?;
+        Ok(message)
+    }
+}
+
+fn is_field(line: &str) -> bool {
+    line == "Resources:"
+        || ["URI", "Version", "Chain ID", "Nonce", "Issued At", "Expiration Time", "Not Before", "Request ID"]
+            .iter()
+            .any(|name| line.strip_prefix(name).is_some_and(|rest| rest.starts_with(": ")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::ContainerBackend;
+    use crate::crypto::{Cipher, EncryptedKeyContainer};
+    use crate::KdfParams;
+    use ed25519_dalek::{Signature, VerifyingKey};
+
+    #[test]
+    fn test_layout_and_round_trip() {
+        let address = bs58::encode([3u8; 32]).into_string();
+        let bare = SiwsMessage {
+            version: None,
+            issued_at: None,
+            ..SiwsMessage::new("example.com", &address).unwrap()
+        };
+        assert_eq!(
+            bare.to_string(),
+            format!("example.com wants you to sign in with your Solana account:\n{}", address)
+        );
+        assert_eq!(bare.to_string().parse::<SiwsMessage>().unwrap(), bare);
+
+        let full = SiwsMessage::new("example.com", &address)
+            .unwrap()
+            .with_statement("Clicking Sign or Approve only means you have proved this wallet is owned by you.")
+            .with_uri("https://example.com")
+            .with_chain_id("devnet")
+            .with_nonce("oBbLoEldZs")
+            .with_expiration_time(2_000_000_000)
+            .with_resources(vec!["https://example.com/terms".to_string()]);
+        let text = full.to_string();
+        assert!(text.contains(&format!("{}\n\nClicking", address)));
+        assert!(text.contains("owned by you.\n\nURI: https://example.com\nVersion: 1\nChain ID: devnet\nNonce: oBbLoEldZs\n"));
+        assert!(text.ends_with("Expiration Time: 2033-05-18T03:33:20Z\nResources:\n- https://example.com/terms"));
+        assert_eq!(text.parse::<SiwsMessage>().unwrap(), full);
+
+        // Fields without a statement
+        let no_statement = SiwsMessage { statement: None, ..full.clone() };
+        assert_eq!(no_statement.to_string().parse::<SiwsMessage>().unwrap(), no_statement);
+
+        assert!(text.replace("devnet", "ropsten").parse::<SiwsMessage>().is_err());
+        assert!(text.replace(&address, "0x1234").parse::<SiwsMessage>().is_err());
+        assert!(full.check_time(2_000_000_000).is_err());
+    }
+
+    #[test]
+    fn test_sign_in() {
+        std::env::set_var("SIGNER_ALLOW_INSECURE_MEMORY", "1");
+        let container =
+            EncryptedKeyContainer::encrypt_with_kdf(&[5u8; 32], "pw", Cipher::Aes256Gcm, KdfParams::MINIMUM).unwrap();
+        let backend = ContainerBackend::new(&container, "pw");
+        let address = backend.sign_solana_message(b"probe").unwrap().public_key;
+
+        let message = SiwsMessage::new("example.com", &address)
+            .unwrap()
+            .with_nonce("nonce12345");
+        let signed = message.sign(&backend).unwrap();
+        let key: [u8; 32] = bs58::decode(&signed.address).into_vec().unwrap().try_into().unwrap();
+        let signature: [u8; 64] = bs58::decode(&signed.signature).into_vec().unwrap().try_into().unwrap();
+        VerifyingKey::from_bytes(&key)
+            .unwrap()
+            .verify_strict(signed.message.as_bytes(), &Signature::from_bytes(&signature))
+            .unwrap();
+
+        let other = SiwsMessage::new("example.com", &bs58::encode([3u8; 32]).into_string()).unwrap();
+        assert!(other.sign(&backend).is_err());
+        assert!(message.with_expiration_time(1_000).sign(&backend).is_err());
+    }
+}
@@ -0,0 +1,470 @@
+//! Squads v4 multisig helpers
+//!
+//! A Squads multisig holds funds in vault PDAs. Spending from a vault takes
+//! three steps, each an ordinary Solana transaction signed by a member:
+//!
+//! 1. `vault_transaction_create` stores the vault's transaction message
+//!    (see [`VaultTransactionMessage`]) in a transaction account,
+//! 2. `proposal_create` opens a proposal for it, and members vote with
+//!    `proposal_approve` (see [`approve_proposal`]),
+//! 3. once the threshold is met, `vault_transaction_execute` runs it.
+//!
+//! Previews of all three decode through [`decode`](super::decode), including
+//! the instructions inside a vault transaction.
+
+use sha2::{Digest, Sha256};
+
+use super::decode::anchor_discriminator;
+use super::message::{
+    AddressTableLookup, CompiledInstruction, MessageHeader, MessageVersion, SolanaMessage,
+};
+use super::transaction::SolanaTransaction;
+use crate::backend::{ContainerBackend, SignerBackend};
+use crate::crypto::EncryptedKeyContainer;
+use crate::error::SignerError;
+use crate::reader::{ByteReader, ParseError};
+
+/// Squads v4 program id
+pub const SQUADS_V4_PROGRAM_ID: &str = "SQDS4ep65T869zMMBKyuUq6aD6EgTu8psMjkvj52pCf";
+
+/// Anchor discriminator of the `VaultTransaction` account
+fn vault_transaction_account_discriminator() -> [u8; 8] {
+    let hash = Sha256::digest(b"account:VaultTransaction");
+    hash[..8].try_into().expect("sha256 output is 32 bytes")
+}
+
+fn program_id() -> [u8; 32] {
+    bs58::decode(SQUADS_V4_PROGRAM_ID)
+        .into_vec()
+        .expect("valid program id")
+        .try_into()
+        .expect("program id is 32 bytes")
+}
+
+/// Find a program-derived address and its bump seed
+pub fn find_program_address(seeds: &[&[u8]], program_id: &[u8; 32]) -> Option<([u8; 32], u8)> {
+    (0..=255u8).rev().find_map(|bump| {
+        let mut hasher = Sha256::new();
+        seeds.iter().for_each(|seed| hasher.update(seed));
+        hasher.update([bump]);
+        hasher.update(program_id);
+        hasher.update(b"ProgramDerivedAddress");
+        let address: [u8; 32] = hasher.finalize().into();
+        // A PDA must not be a valid Ed25519 public key
+        ed25519_dalek::VerifyingKey::from_bytes(&address)
+            .is_err()
+            .then_some((address, bump))
+    })
+}
+
+fn squads_pda(seeds: &[&[u8]]) -> [u8; 32] {
+    find_program_address(seeds, &program_id())
+        .expect("a bump seed exists for all but a negligible fraction of seeds")
+        .0
+}
+
+/// Multisig account created with `create_key`
+pub fn multisig_pda(create_key: &[u8; 32]) -> [u8; 32] {
+    squads_pda(&[b"multisig", b"multisig", create_key])
+}
+
+/// Vault `index` of a multisig (the default vault is 0)
+pub fn vault_pda(multisig: &[u8; 32], index: u8) -> [u8; 32] {
+    squads_pda(&[b"multisig", multisig, b"vault", &[index]])
+}
+
+/// Transaction account for `transaction_index`
+pub fn transaction_pda(multisig: &[u8; 32], transaction_index: u64) -> [u8; 32] {
+    squads_pda(&[b"multisig", multisig, b"transaction", &transaction_index.to_le_bytes()])
+}
+
+/// Proposal account for `transaction_index`
+pub fn proposal_pda(multisig: &[u8; 32], transaction_index: u64) -> [u8; 32] {
+    squads_pda(&[
+        b"multisig",
+        multisig,
+        b"transaction",
+        &transaction_index.to_le_bytes(),
+        b"proposal",
+    ])
+}
+
+/// The message a vault signs, in Squads' compact format
+///
+/// Account keys are ordered like a Solana message: writable signers,
+/// read-only signers, writable non-signers, read-only non-signers. The vault
+/// is the first signer.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VaultTransactionMessage {
+    /// Number of signer accounts
+    pub num_signers: u8,
+    /// Signers that are writable
+    pub num_writable_signers: u8,
+    /// Non-signers that are writable
+    pub num_writable_non_signers: u8,
+    /// Static account keys
+    pub account_keys: Vec<[u8; 32]>,
+    /// Instructions
+    pub instructions: Vec<CompiledInstruction>,
+    /// Address table lookups
+    pub address_table_lookups: Vec<AddressTableLookup>,
+}
+
+impl VaultTransactionMessage {
+    /// Convert a Solana message whose fee payer is the vault
+    ///
+    /// The blockhash is dropped: the vault transaction executes inside the
+    /// member's `vault_transaction_execute` transaction.
+    pub fn from_message(message: &SolanaMessage) -> Result<Self, SignerError> {
+        let header = &message.header;
+        let num_non_signers = message.account_keys.len() - header.num_required_signatures as usize;
+        let too_large = || SignerError::InvalidTransaction("vault transaction is too large".to_string());
+        Ok(Self {
+            num_signers: header.num_required_signatures,
+            num_writable_signers: header.num_required_signatures - header.num_readonly_signed_accounts,
+            num_writable_non_signers: u8::try_from(num_non_signers - header.num_readonly_unsigned_accounts as usize)
+                .map_err(|_| too_large())?,
+            account_keys: message.account_keys.clone(),
+            instructions: message.instructions.clone(),
+            address_table_lookups: message.address_table_lookups.clone(),
+        })
+    }
+
+    /// Rebuild a Solana message, for previews
+    pub fn to_message(&self) -> Result<SolanaMessage, SignerError> {
+        let num_non_signers = self.account_keys.len().saturating_sub(self.num_signers as usize);
+        let num_readonly_unsigned = num_non_signers
+            .checked_sub(self.num_writable_non_signers as usize)
+            .and_then(|n| u8::try_from(n).ok());
+        let num_readonly_signed = self.num_signers.checked_sub(self.num_writable_signers);
+        let (Some(num_readonly_unsigned), Some(num_readonly_signed)) = (num_readonly_unsigned, num_readonly_signed)
+        else {
+            return Err(SignerError::InvalidTransaction("invalid vault transaction header".to_string()));
+        };
+
+        let message = SolanaMessage {
+            version: if self.address_table_lookups.is_empty() { MessageVersion::Legacy } else { MessageVersion::V0 },
+            header: MessageHeader {
+                num_required_signatures: self.num_signers,
+                num_readonly_signed_accounts: num_readonly_signed,
+                num_readonly_unsigned_accounts: num_readonly_unsigned,
+            },
+            account_keys: self.account_keys.clone(),
+            recent_blockhash: [0u8; 32],
+            instructions: self.instructions.clone(),
+            address_table_lookups: self.address_table_lookups.clone(),
+        };
+        // Round-trip through the wire format to run the message checks
+        SolanaMessage::parse(&message.serialize())
+    }
+
+    /// Serialize as the `transaction_message` argument of
+    /// `vault_transaction_create` (u8 lengths, u16 for instruction data)
+    pub fn serialize(&self) -> Result<Vec<u8>, SignerError> {
+        let mut out = vec![self.num_signers, self.num_writable_signers, self.num_writable_non_signers];
+        small_vec_u8(&mut out, self.account_keys.len())?;
+        self.account_keys.iter().for_each(|k| out.extend_from_slice(k));
+
+        small_vec_u8(&mut out, self.instructions.len())?;
+        for ix in &self.instructions {
+            out.push(ix.program_id_index);
+            small_vec_u8(&mut out, ix.accounts.len())?;
+            out.extend_from_slice(&ix.accounts);
+            let len = u16::try_from(ix.data.len())
+                .map_err(|_| SignerError::InvalidTransaction("instruction data is too large".to_string()))?;
+            out.extend_from_slice(&len.to_le_bytes());
+            out.extend_from_slice(&ix.data);
+        }
+
+        small_vec_u8(&mut out, self.address_table_lookups.len())?;
+        for lookup in &self.address_table_lookups {
+            out.extend_from_slice(&lookup.account_key);
+            small_vec_u8(&mut out, lookup.writable_indexes.len())?;
+            out.extend_from_slice(&lookup.writable_indexes);
+            small_vec_u8(&mut out, lookup.readonly_indexes.len())?;
+            out.extend_from_slice(&lookup.readonly_indexes);
+        }
+        Ok(out)
+    }
+
+    /// Parse the `transaction_message` argument of `vault_transaction_create`
+    pub fn parse(bytes: &[u8]) -> Result<Self, SignerError> {
+        let mut r = ByteReader::new(bytes, "Squads vault transaction");
+        let message = Self::read(&mut r, Length::U8)?;
+        r.finish()?;
+        Ok(message)
+    }
+
+    /// Parse the message stored in a `VaultTransaction` account's data
+    pub fn from_account_data(data: &[u8]) -> Result<Self, SignerError> {
+        let mut r = ByteReader::new(data, "Squads vault transaction account");
+        if r.take(8)? != vault_transaction_account_discriminator() {
+            return Err(SignerError::InvalidTransaction(
+                "not a Squads vault transaction account".to_string(),
+            ));
+        }
+        // multisig, creator, index, bump, vault_index, vault_bump
+        r.take(32 + 32 + 8 + 3)?;
+        Length::U32.vec(&mut r)?;
+        // Accounts are allocated with room to spare, so trailing bytes are fine
+        Ok(Self::read(&mut r, Length::U32)?)
+    }
+
+    fn read(r: &mut ByteReader, length: Length) -> Result<Self, ParseError> {
+        let [num_signers, num_writable_signers, num_writable_non_signers] = [r.u8()?, r.u8()?, r.u8()?];
+        let num_keys = length.read(r)?;
+        let account_keys = (0..r.count(num_keys as u64, 32)?)
+            .map(|_| r.array())
+            .collect::<Result<Vec<_>, _>>()?;
+        let num_instructions = length.read(r)?;
+        let instructions = (0..num_instructions)
+            .map(|_| {
+                let program_id_index = r.u8()?;
+                let accounts = length.vec(r)?;
+                let data = if length == Length::U8 { Length::U16 } else { length }.vec(r)?;
+                Ok(CompiledInstruction {
+                    program_id_index,
+                    accounts,
+                    data,
+                })
+            })
+            .collect::<Result<Vec<_>, ParseError>>()?;
+        let num_lookups = length.read(r)?;
+        let address_table_lookups = (0..r.count(num_lookups as u64, 32)?)
+            .map(|_| {
+                Ok(AddressTableLookup {
+                    account_key: r.array()?,
+                    writable_indexes: length.vec(r)?,
+                    readonly_indexes: length.vec(r)?,
+                })
+            })
+            .collect::<Result<Vec<_>, ParseError>>()?;
+        Ok(Self {
+            num_signers,
+            num_writable_signers,
+            num_writable_non_signers,
+            account_keys,
+            instructions,
+            address_table_lookups,
+        })
+    }
+}
+
+fn small_vec_u8(out: &mut Vec<u8>, len: usize) -> Result<(), SignerError> {
+    out.push(u8::try_from(len).map_err(|_| SignerError::InvalidTransaction("vault transaction is too large".to_string()))?);
+    Ok(())
+}
+
+/// Instruction data of a proposal vote (`proposal_approve`, `proposal_reject`,
+/// `proposal_cancel`) with an optional memo
+pub fn proposal_vote_data(instruction: &str, memo: Option<&str>) -> Vec<u8> {
+    let mut data = anchor_discriminator(instruction).to_vec();
+    match memo {
+        None => data.push(0),
+        Some(memo) => {
+            data.push(1);
+            data.extend_from_slice(&(memo.len() as u32).to_le_bytes());
+            data.extend_from_slice(memo.as_bytes());
+        }
+    }
+    data
+}
+
+/// Build the transaction in which `member` approves a proposal
+///
+/// The member pays the fee; the message has one signature slot.
+pub fn proposal_approve_transaction(
+    multisig: &[u8; 32],
+    member: &[u8; 32],
+    transaction_index: u64,
+    memo: Option<&str>,
+    recent_blockhash: &[u8; 32],
+) -> Result<SolanaTransaction, SignerError> {
+    let message = SolanaMessage {
+        version: MessageVersion::Legacy,
+        header: MessageHeader {
+            num_required_signatures: 1,
+            num_readonly_signed_accounts: 0,
+            num_readonly_unsigned_accounts: 2,
+        },
+        account_keys: vec![*member, proposal_pda(multisig, transaction_index), *multisig, program_id()],
+        recent_blockhash: *recent_blockhash,
+        // Accounts: multisig, member (signer), proposal (writable)
+        instructions: vec![CompiledInstruction {
+            program_id_index: 3,
+            accounts: vec![2, 0, 1],
+            data: proposal_vote_data("proposal_approve", memo),
+        }],
+        address_table_lookups: Vec::new(),
+    };
+    SolanaTransaction::from_message(&message.serialize())
+}
+
+/// Approve a proposal as `member`, signing with `backend`
+///
+/// Returns the signed transaction, ready to broadcast.
+pub fn approve_proposal(
+    backend: &dyn SignerBackend,
+    member: &[u8; 32],
+    multisig: &[u8; 32],
+    transaction_index: u64,
+    memo: Option<&str>,
+    recent_blockhash: &[u8; 32],
+) -> Result<SolanaTransaction, SignerError> {
+    let mut transaction = proposal_approve_transaction(multisig, member, transaction_index, memo, recent_blockhash)?;
+    transaction.sign_with(backend)?;
+    Ok(transaction)
+}
+
+/// Decrypt a key container and approve a proposal with its key
+///
+/// The container's Solana public key is the voting member.
+pub fn decrypt_and_approve_proposal(
+    container_json: &str,
+    passphrase: &str,
+    multisig: &[u8; 32],
+    transaction_index: u64,
+    memo: Option<&str>,
+    recent_blockhash: &[u8; 32],
+) -> Result<SolanaTransaction, SignerError> {
+    let container = EncryptedKeyContainer::from_json(container_json)?;
+    let member: [u8; 32] = container
+        .public_key
+        .as_deref()
+        .and_then(|key| bs58::decode(key).into_vec().ok())
+        .and_then(|key| key.try_into().ok())
+        .ok_or_else(|| SignerError::ContainerError("container has no Solana public key".to_string()))?;
+
+    let backend = ContainerBackend::new(&container, passphrase);
+    approve_proposal(&backend, &member, multisig, transaction_index, memo, recent_blockhash)
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Length {
+    U8,
+    U16,
+    U32,
+}
+
+impl Length {
+    /// Read a length prefix, checked against the bytes left
+    fn read(self, r: &mut ByteReader) -> Result<usize, ParseError> {
+        let len = match self {
+            Length::U8 => u64::from(r.u8()?),
+            Length::U16 => u64::from(r.u16_le()?),
+            Length::U32 => u64::from(r.u32_le()?),
+        };
+        r.count(len, 1)
+    }
+
+    /// Read a length-prefixed byte vector
+    fn vec(self, r: &mut ByteReader) -> Result<Vec<u8>, ParseError> {
+        let len = self.read(r)?;
+        Ok(r.take(len)?.to_vec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::Cipher;
+    use crate::kdf::KdfParams;
+    use crate::solana::decode::{decode_transaction, DecodedInstruction, SYSTEM_PROGRAM_ID};
+
+    fn key(name: &str) -> [u8; 32] {
+        bs58::decode(name).into_vec().unwrap().try_into().unwrap()
+    }
+
+    #[test]
+    fn test_vault_transaction_message_round_trip() {
+        let multisig = multisig_pda(&[5u8; 32]);
+        let vault = vault_pda(&multisig, 0);
+        assert_ne!(vault, vault_pda(&multisig, 1));
+        assert_ne!(transaction_pda(&multisig, 1), proposal_pda(&multisig, 1));
+
+        // The vault transfers 1 SOL to [9; 32]
+        let mut transfer = 2u32.to_le_bytes().to_vec();
+        transfer.extend_from_slice(&1_000_000_000u64.to_le_bytes());
+        let inner = SolanaMessage {
+            version: MessageVersion::Legacy,
+            header: MessageHeader {
+                num_required_signatures: 1,
+                num_readonly_signed_accounts: 0,
+                num_readonly_unsigned_accounts: 1,
+            },
+            account_keys: vec![vault, [9u8; 32], key(SYSTEM_PROGRAM_ID)],
+            recent_blockhash: [0u8; 32],
+            instructions: vec![CompiledInstruction {
+                program_id_index: 2,
+                accounts: vec![0, 1],
+                data: transfer,
+            }],
+            address_table_lookups: Vec::new(),
+        };
+
+        let vault_message = VaultTransactionMessage::from_message(&inner).unwrap();
+        assert_eq!((vault_message.num_writable_signers, vault_message.num_writable_non_signers), (1, 1));
+        let bytes = vault_message.serialize().unwrap();
+        assert_eq!(VaultTransactionMessage::parse(&bytes).unwrap(), vault_message);
+        assert_eq!(vault_message.to_message().unwrap(), inner);
+
+        // vault_transaction_create: multisig, transaction, creator, rent_payer, system_program
+        let mut data = anchor_discriminator("vault_transaction_create").to_vec();
+        data.extend_from_slice(&[0, 0]);
+        data.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+        data.extend_from_slice(&bytes);
+        data.push(0);
+        let create = SolanaMessage {
+            version: MessageVersion::Legacy,
+            header: MessageHeader {
+                num_required_signatures: 1,
+                num_readonly_signed_accounts: 0,
+                num_readonly_unsigned_accounts: 2,
+            },
+            account_keys: vec![[1u8; 32], multisig, transaction_pda(&multisig, 1), key(SYSTEM_PROGRAM_ID), program_id()],
+            recent_blockhash: [0u8; 32],
+            instructions: vec![CompiledInstruction {
+                program_id_index: 4,
+                accounts: vec![1, 2, 0, 0, 3],
+                data,
+            }],
+            address_table_lookups: Vec::new(),
+        };
+        let decoded = decode_transaction(&create.serialize()).unwrap();
+        let DecodedInstruction::SquadsVaultTransactionCreate { vault: decoded_vault, instructions, .. } = &decoded[0]
+        else {
+            panic!("not decoded as a vault transaction: {:?}", decoded[0]);
+        };
+        assert_eq!(*decoded_vault, bs58::encode(vault).into_string());
+        assert!(instructions[0].summary().starts_with("Transfer 1.000000000 SOL"));
+    }
+
+    #[test]
+    fn test_approve_proposal_signs_as_member() {
+        std::env::set_var("SIGNER_ALLOW_INSECURE_MEMORY", "1");
+        let container =
+            EncryptedKeyContainer::encrypt_with_kdf(&[3u8; 32], "pw", Cipher::Aes256Gcm, KdfParams::MINIMUM).unwrap();
+        let json = container.to_json().unwrap();
+        let multisig = multisig_pda(&[5u8; 32]);
+
+        let transaction = decrypt_and_approve_proposal(&json, "pw", &multisig, 7, Some("lgtm"), &[4u8; 32]).unwrap();
+        assert!(transaction.is_fully_signed());
+        assert_eq!(
+            bs58::encode(transaction.required_signers()[0]).into_string(),
+            container.public_key.clone().unwrap()
+        );
+
+        let decoded = decode_transaction(transaction.message_bytes()).unwrap();
+        assert_eq!(
+            decoded[0],
+            DecodedInstruction::SquadsProposalVote {
+                vote: "approve".to_string(),
+                multisig: bs58::encode(multisig).into_string(),
+                proposal: bs58::encode(proposal_pda(&multisig, 7)).into_string(),
+                member: container.public_key.clone().unwrap(),
+            }
+        );
+
+        assert!(decrypt_and_approve_proposal(&json, "wrong", &multisig, 7, None, &[4u8; 32]).is_err());
+    }
+}
@@ -0,0 +1,322 @@
+//! Partially-signed Solana transactions
+//!
+//! A transaction is `compact-u16 count || count × 64-byte signature ||
+//! message`, with one signature slot per required signer in account-key
+//! order; the first slot belongs to the fee payer. Unfilled slots are all
+//! zeros. [`SolanaTransaction`] lets several parties fill their slots in
+//! turn (for example a fee payer co-signing a user's transaction offline)
+//! and reports which signers are still missing.
+
+use ed25519_dalek::{Signature, VerifyingKey};
+
+use crate::backend::SignerBackend;
+use crate::crypto::{sign_with_secure_key, EncryptedKeyContainer, SigningResult};
+use crate::error::SignerError;
+use crate::reader::ByteReader;
+use crate::solana::message::{encode_compact_u16, read_count, SolanaMessage};
+
+/// An empty signature slot
+const EMPTY_SIGNATURE: [u8; 64] = [0u8; 64];
+
+/// State of one required signature
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SignatureStatus {
+    /// Slot is empty
+    Missing,
+    /// Slot holds a valid signature over the message
+    Valid,
+    /// Slot holds a signature that does not verify
+    Invalid,
+}
+
+impl SignatureStatus {
+    /// Lowercase label for display and JSON output
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SignatureStatus::Missing => "missing",
+            SignatureStatus::Valid => "valid",
+            SignatureStatus::Invalid => "invalid",
+        }
+    }
+}
+
+/// A Solana transaction whose signature slots may be partly filled
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SolanaTransaction {
+    /// One slot per required signer
+    pub signatures: Vec<[u8; 64]>,
+    /// The parsed message
+    pub message: SolanaMessage,
+    message_bytes: Vec<u8>,
+}
+
+impl SolanaTransaction {
+    /// Start an unsigned transaction from message bytes
+    pub fn from_message(message_bytes: &[u8]) -> Result<Self, SignerError> {
+        let message = SolanaMessage::parse(message_bytes)?;
+        Ok(Self {
+            signatures: vec![EMPTY_SIGNATURE; message.header.num_required_signatures as usize],
+            message,
+            message_bytes: message_bytes.to_vec(),
+        })
+    }
+
+    /// Parse a serialized (possibly partially-signed) transaction
+    pub fn parse(bytes: &[u8]) -> Result<Self, SignerError> {
+        let mut reader = ByteReader::new(bytes, "Solana transaction");
+        let count = read_count(&mut reader, 64)?;
+        let signatures = (0..count).map(|_| reader.array()).collect::<Result<Vec<[u8; 64]>, _>>()?;
+
+        let message_bytes = reader.rest();
+        let message = SolanaMessage::parse(message_bytes)?;
+        if signatures.len() != message.header.num_required_signatures as usize {
+            return Err(SignerError::InvalidTransaction(format!(
+                "transaction has {} signature slots, message requires {}",
+                signatures.len(),
+                message.header.num_required_signatures
+            )));
+        }
+
+        Ok(Self {
+            signatures,
+            message,
+            message_bytes: message_bytes.to_vec(),
+        })
+    }
+
+    /// Parse either a transaction or a bare message
+    pub fn parse_or_from_message(bytes: &[u8]) -> Result<Self, SignerError> {
+        Self::parse(bytes).or_else(|_| Self::from_message(bytes))
+    }
+
+    /// Serialize to wire format
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(3 + 64 * self.signatures.len() + self.message_bytes.len());
+        encode_compact_u16(self.signatures.len(), &mut out);
+        self.signatures.iter().for_each(|s| out.extend_from_slice(s));
+        out.extend_from_slice(&self.message_bytes);
+        out
+    }
+
+    /// The bytes each signer signs
+    pub fn message_bytes(&self) -> &[u8] {
+        &self.message_bytes
+    }
+
+    /// Public keys of the required signers, fee payer first
+    pub fn required_signers(&self) -> &[[u8; 32]] {
+        &self.message.account_keys[..self.signatures.len()]
+    }
+
+    /// Status of every required signature, in signer order
+    pub fn signature_statuses(&self) -> Vec<([u8; 32], SignatureStatus)> {
+        self.required_signers()
+            .iter()
+            .zip(&self.signatures)
+            .map(|(key, signature)| (*key, self.status_of(key, signature)))
+            .collect()
+    }
+
+    /// Signers whose slot is empty or invalid
+    pub fn missing_signers(&self) -> Vec<[u8; 32]> {
+        self.signature_statuses()
+            .into_iter()
+            .filter(|(_, status)| *status != SignatureStatus::Valid)
+            .map(|(key, _)| key)
+            .collect()
+    }
+
+    /// Whether every required signature is present and valid
+    pub fn is_fully_signed(&self) -> bool {
+        self.missing_signers().is_empty()
+    }
+
+    /// Place a signature in the slot of `public_key`
+    ///
+    /// The signature must verify over the message.
+    pub fn add_signature(&mut self, public_key: &[u8; 32], signature: &[u8; 64]) -> Result<(), SignerError> {
+        let slot = self
+            .required_signers()
+            .iter()
+            .position(|key| key == public_key)
+            .ok_or_else(|| {
+                SignerError::InvalidTransaction(format!(
+                    "{} is not a required signer",
+                    bs58::encode(public_key).into_string()
+                ))
+            })?;
+
+        if self.status_of(public_key, signature) != SignatureStatus::Valid {
+            return Err(SignerError::SigningFailed(
+                "signature does not verify over the message".to_string(),
+            ));
+        }
+        self.signatures[slot] = *signature;
+        Ok(())
+    }
+
+    /// Sign the message with `backend` and fill its slot
+    ///
+    /// Returns the base58 public key that signed.
+    pub fn sign_with(&mut self, backend: &dyn SignerBackend) -> Result<String, SignerError> {
+        let result = backend.sign_solana(&self.message_bytes)?;
+        self.add_signing_result(&result)?;
+        Ok(result.public_key)
+    }
+
+    /// Place the signature of a [`SigningResult`] over this message
+    fn add_signing_result(&mut self, result: &SigningResult) -> Result<(), SignerError> {
+        let public_key: [u8; 32] = bs58::decode(&result.public_key)
+            .into_vec()?
+            .try_into()
+            .map_err(|_| SignerError::SigningFailed("backend returned a malformed public key".to_string()))?;
+        let signature: [u8; 64] = bs58::decode(&result.signature)
+            .into_vec()?
+            .try_into()
+            .map_err(|_| SignerError::SigningFailed("backend returned a malformed signature".to_string()))?;
+
+        self.add_signature(&public_key, &signature)
+    }
+
+    fn status_of(&self, public_key: &[u8; 32], signature: &[u8; 64]) -> SignatureStatus {
+        if *signature == EMPTY_SIGNATURE {
+            return SignatureStatus::Missing;
+        }
+        let valid = VerifyingKey::from_bytes(public_key)
+            .map(|key| key.verify_strict(&self.message_bytes, &Signature::from_bytes(signature)).is_ok())
+            .unwrap_or(false);
+        if valid {
+            SignatureStatus::Valid
+        } else {
+            SignatureStatus::Invalid
+        }
+    }
+}
+
+/// Sign several transactions with one unlock, as wallet-adapter's
+/// `signAllTransactions`
+///
+/// Each entry is a serialized (possibly partially-signed) transaction or a
+/// bare message. All of them are parsed and must list the container's key
+/// as a required signer before the key is decrypted; it is then decrypted
+/// once, signs every message into its own slot, and is zeroized. Either all
+/// transactions are returned signed, in input order, or an error naming the
+/// first bad one.
+pub fn decrypt_and_sign_all(
+    container_json: &str,
+    passphrase: &str,
+    transactions: &[Vec<u8>],
+) -> Result<Vec<SolanaTransaction>, SignerError> {
+    let container = EncryptedKeyContainer::from_json(container_json)?;
+    let signer: Option<[u8; 32]> = container
+        .public_key
+        .as_deref()
+        .and_then(|key| bs58::decode(key).into_vec().ok())
+        .and_then(|key| key.try_into().ok());
+
+    let mut parsed = transactions
+        .iter()
+        .enumerate()
+        .map(|(index, bytes)| {
+            let tx = SolanaTransaction::parse_or_from_message(bytes)
+                .map_err(|e| SignerError::InvalidTransaction(format!("transaction {}: {}", index, e)))?;
+            if let Some(signer) = signer.filter(|key| !tx.required_signers().contains(key)) {
+                return Err(SignerError::InvalidTransaction(format!(
+                    "transaction {}: {} is not a required signer",
+                    index,
+                    bs58::encode(signer).into_string()
+                )));
+            }
+            Ok(tx)
+        })
+        .collect::<Result<Vec<_>, SignerError>>()?;
+
+    let mut secure_key = container.decrypt_key(passphrase)?;
+    let result = parsed.iter_mut().enumerate().try_for_each(|(index, tx)| {
+        sign_with_secure_key(&secure_key, &tx.message_bytes)
+            .and_then(|result| tx.add_signing_result(&result))
+            .map_err(|e| SignerError::SigningFailed(format!("transaction {}: {}", index, e)))
+    });
+    secure_key.zeroize();
+
+    result.map(|()| parsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::ContainerBackend;
+    use crate::crypto::Cipher;
+    use crate::kdf::KdfParams;
+
+    #[test]
+    fn test_two_signers_fill_slots_in_turn() {
+        std::env::set_var("SIGNER_ALLOW_INSECURE_MEMORY", "1");
+        let payer =
+            EncryptedKeyContainer::encrypt_with_kdf(&[1u8; 32], "a", Cipher::Aes256Gcm, KdfParams::MINIMUM).unwrap();
+        let user =
+            EncryptedKeyContainer::encrypt_with_kdf(&[2u8; 32], "b", Cipher::Aes256Gcm, KdfParams::MINIMUM).unwrap();
+        let key = |c: &EncryptedKeyContainer| -> [u8; 32] {
+            bs58::decode(c.public_key.as_ref().unwrap()).into_vec().unwrap().try_into().unwrap()
+        };
+
+        // Legacy message: 2 signers (payer writable, user read-only), 1 program
+        let mut message = vec![2, 1, 1, 3];
+        message.extend_from_slice(&key(&payer));
+        message.extend_from_slice(&key(&user));
+        message.extend_from_slice(&[7u8; 32]);
+        message.extend_from_slice(&[4u8; 32]);
+        message.extend_from_slice(&[1, 2, 2, 0, 1, 0]);
+
+        let mut tx = SolanaTransaction::parse_or_from_message(&message).unwrap();
+        assert_eq!(tx.missing_signers(), vec![key(&payer), key(&user)]);
+
+        tx.sign_with(&ContainerBackend::new(&user, "b")).unwrap();
+        let mut tx = SolanaTransaction::parse_or_from_message(&tx.serialize()).unwrap();
+        assert_eq!(tx.signature_statuses()[1].1, SignatureStatus::Valid);
+        assert_eq!(tx.missing_signers(), vec![key(&payer)]);
+
+        tx.sign_with(&ContainerBackend::new(&payer, "a")).unwrap();
+        assert!(tx.is_fully_signed());
+
+        // Keys outside the signer set and corrupted signatures are rejected
+        let outsider =
+            EncryptedKeyContainer::encrypt_with_kdf(&[3u8; 32], "c", Cipher::Aes256Gcm, KdfParams::MINIMUM).unwrap();
+        assert!(tx.sign_with(&ContainerBackend::new(&outsider, "c")).is_err());
+        tx.signatures[0][0] ^= 1;
+        assert_eq!(tx.signature_statuses()[0].1, SignatureStatus::Invalid);
+        assert!(!tx.is_fully_signed());
+    }
+
+    #[test]
+    fn test_sign_all_fills_each_slot_in_order() {
+        std::env::set_var("SIGNER_ALLOW_INSECURE_MEMORY", "1");
+        let payer =
+            EncryptedKeyContainer::encrypt_with_kdf(&[1u8; 32], "a", Cipher::Aes256Gcm, KdfParams::MINIMUM).unwrap();
+        let json = payer.to_json().unwrap();
+        let key: [u8; 32] = bs58::decode(payer.public_key.as_ref().unwrap()).into_vec().unwrap().try_into().unwrap();
+
+        // Legacy messages with the payer as sole signer, differing in program
+        let message = |program: u8| {
+            let mut message = vec![1, 0, 1, 2];
+            message.extend_from_slice(&key);
+            message.extend_from_slice(&[program; 32]);
+            message.extend_from_slice(&[4u8; 32]);
+            message.extend_from_slice(&[1, 1, 1, 0, 0]);
+            message
+        };
+        let batch = vec![message(7), SolanaTransaction::from_message(&message(8)).unwrap().serialize()];
+
+        let signed = decrypt_and_sign_all(&json, "a", &batch).unwrap();
+        assert_eq!(signed.len(), 2);
+        assert!(signed.iter().all(SolanaTransaction::is_fully_signed));
+        assert_eq!(signed[0].message_bytes(), &message(7)[..]);
+        assert_eq!(signed[1].message_bytes(), &message(8)[..]);
+
+        // A transaction the key does not sign fails the whole batch
+        let mut foreign = message(9);
+        foreign[4..36].copy_from_slice(&[5u8; 32]);
+        let err = decrypt_and_sign_all(&json, "a", &[message(7), foreign]).unwrap_err();
+        assert!(err.to_string().contains("transaction 1"));
+    }
+}
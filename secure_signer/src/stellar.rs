@@ -0,0 +1,510 @@
+//! Stellar transaction signing
+//!
+//! Stellar uses Ed25519 with the same 32-byte seed as Solana. A signature
+//! covers the SHA-256 of the transaction's signature payload:
+//!
+//! ```text
+//! SHA-256(network passphrase) || envelope type || transaction XDR
+//! ```
+//!
+//! so a signature for one network is useless on another. Signatures are
+//! appended to the envelope as `DecoratedSignature`s, tagged with the last
+//! four bytes of the public key as a hint.
+//!
+//! The envelope header (source account, fee, sequence number, memo, and
+//! operation count) is decoded for previews; operations are carried through
+//! as opaque bytes. The signature list is located from the end of the
+//! envelope, where XDR places it.
+
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::crypto::{get_locking_mode, EncryptedKeyContainer};
+use crate::encoding::crc16_xmodem;
+use crate::entropy::health_check;
+use crate::error::SignerError;
+use crate::reader::{ByteReader, ParseError};
+use crate::secure_buffer::SecureBuffer;
+
+/// Network passphrase of the Stellar public network
+pub const PUBLIC_NETWORK_PASSPHRASE: &str = "Public Global Stellar Network ; September 2015";
+
+/// Network passphrase of the Stellar test network
+pub const TESTNET_PASSPHRASE: &str = "Test SDF Network ; September 2015";
+
+const ENVELOPE_TYPE_TX_V0: u32 = 0;
+const ENVELOPE_TYPE_TX: u32 = 2;
+const ENVELOPE_TYPE_TX_FEE_BUMP: u32 = 5;
+
+const KEY_TYPE_ED25519: u32 = 0;
+const KEY_TYPE_MUXED_ED25519: u32 = 0x100;
+
+/// Maximum number of signatures on an envelope
+const MAX_SIGNATURES: usize = 20;
+
+/// Size of an XDR `DecoratedSignature`: hint, length, 64-byte signature
+const DECORATED_SIGNATURE_SIZE: usize = 4 + 4 + 64;
+
+/// strkey version byte of an Ed25519 account (`G...`)
+const STRKEY_ACCOUNT: u8 = 6 << 3;
+
+/// strkey version byte of a muxed account (`M...`)
+const STRKEY_MUXED_ACCOUNT: u8 = 12 << 3;
+
+fn xdr_error(msg: impl Into<String>) -> SignerError {
+    SignerError::InvalidTransaction(format!("Invalid Stellar envelope: {}", msg.into()))
+}
+
+/// Kind of transaction envelope
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EnvelopeKind {
+    /// Pre-protocol 13 transaction with an Ed25519 source account
+    TxV0,
+    /// Transaction
+    Tx,
+    /// Fee bump wrapping a transaction
+    FeeBump,
+}
+
+/// A transaction envelope, decoded far enough to preview and sign it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StellarEnvelope {
+    /// Kind of envelope
+    pub kind: EnvelopeKind,
+    /// Source account (`G...` or muxed `M...`); the fee source for fee bumps
+    pub source_account: String,
+    /// Maximum fee in stroops
+    pub fee: u64,
+    /// Sequence number (of the inner transaction, for fee bumps)
+    pub sequence: i64,
+    /// Memo, if any: text, an ID, or hex of a hash
+    pub memo: Option<String>,
+    /// Number of operations
+    pub operations: u32,
+    /// The signed transaction bytes, between envelope type and signatures
+    transaction: Vec<u8>,
+    /// Existing signatures
+    signatures: Vec<DecoratedSignature>,
+}
+
+impl StellarEnvelope {
+    /// Parse a base64 XDR `TransactionEnvelope`
+    pub fn from_xdr_base64(envelope: &str) -> Result<Self, SignerError> {
+        let bytes = base64::Engine::decode(&base64::engine::general_purpose::STANDARD, envelope.trim())
+            .map_err(|e| xdr_error(format!("invalid base64: {}", e)))?;
+        Self::from_xdr(&bytes)
+    }
+
+    /// Parse an XDR `TransactionEnvelope`
+    pub fn from_xdr(bytes: &[u8]) -> Result<Self, SignerError> {
+        let mut r = ByteReader::new(bytes, "Stellar envelope");
+        let (kind, source_account, fee, header) = match r.u32_be()? {
+            ENVELOPE_TYPE_TX_V0 => {
+                let source = strkey(STRKEY_ACCOUNT, &r.array::<32>()?);
+                let fee = r.u32_be()? as u64;
+                (EnvelopeKind::TxV0, source, fee, read_header(&mut r, true)?)
+            }
+            ENVELOPE_TYPE_TX => {
+                let source = read_muxed_account(&mut r)?;
+                let fee = r.u32_be()? as u64;
+                (EnvelopeKind::Tx, source, fee, read_header(&mut r, false)?)
+            }
+            ENVELOPE_TYPE_TX_FEE_BUMP => {
+                let fee_source = read_muxed_account(&mut r)?;
+                let fee = u64::try_from(r.i64_be()?).map_err(|_| r.invalid("negative fee"))?;
+                if r.u32_be()? != ENVELOPE_TYPE_TX {
+                    return Err(r.invalid("fee bump must wrap a transaction").into());
+                }
+                read_muxed_account(&mut r)?;
+                r.u32_be()?;
+                (EnvelopeKind::FeeBump, fee_source, fee, read_header(&mut r, false)?)
+            }
+            other => return Err(r.invalid(format!("unsupported envelope type {}", other)).into()),
+        };
+
+        let (transaction_end, signatures) = find_signatures(bytes, r.position())?;
+        let (sequence, memo, operations) = header;
+        Ok(Self {
+            kind,
+            source_account,
+            fee,
+            sequence,
+            memo,
+            operations,
+            transaction: bytes[4..transaction_end].to_vec(),
+            signatures,
+        })
+    }
+
+    /// Serialize back to XDR, with any added signatures
+    pub fn to_xdr(&self) -> Vec<u8> {
+        let envelope_type = match self.kind {
+            EnvelopeKind::TxV0 => ENVELOPE_TYPE_TX_V0,
+            EnvelopeKind::Tx => ENVELOPE_TYPE_TX,
+            EnvelopeKind::FeeBump => ENVELOPE_TYPE_TX_FEE_BUMP,
+        };
+        let mut out = Vec::with_capacity(8 + self.transaction.len() + self.signatures.len() * DECORATED_SIGNATURE_SIZE);
+        out.extend_from_slice(&envelope_type.to_be_bytes());
+        out.extend_from_slice(&self.transaction);
+        out.extend_from_slice(&(self.signatures.len() as u32).to_be_bytes());
+        for (hint, signature) in &self.signatures {
+            out.extend_from_slice(hint);
+            out.extend_from_slice(&64u32.to_be_bytes());
+            out.extend_from_slice(signature);
+        }
+        out
+    }
+
+    /// Serialize to base64 XDR
+    pub fn to_xdr_base64(&self) -> String {
+        base64::Engine::encode(&base64::engine::general_purpose::STANDARD, self.to_xdr())
+    }
+
+    /// Number of signatures on the envelope
+    pub fn signature_count(&self) -> usize {
+        self.signatures.len()
+    }
+
+    /// Transaction hash on the network identified by `network_passphrase`
+    ///
+    /// This is the value signers sign and explorers show.
+    pub fn hash(&self, network_passphrase: &str) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(Sha256::digest(network_passphrase.as_bytes()));
+        match self.kind {
+            // v0 transactions are signed as the equivalent v1 transaction,
+            // whose source account adds only the key type
+            EnvelopeKind::TxV0 => {
+                hasher.update(ENVELOPE_TYPE_TX.to_be_bytes());
+                hasher.update(KEY_TYPE_ED25519.to_be_bytes());
+            }
+            EnvelopeKind::Tx => hasher.update(ENVELOPE_TYPE_TX.to_be_bytes()),
+            EnvelopeKind::FeeBump => hasher.update(ENVELOPE_TYPE_TX_FEE_BUMP.to_be_bytes()),
+        }
+        hasher.update(&self.transaction);
+        hasher.finalize().into()
+    }
+
+    /// Add a signature by `public_key`, replacing an earlier one by the same key
+    pub fn add_signature(&mut self, public_key: &[u8; 32], signature: &[u8; 64], network_passphrase: &str) -> Result<(), SignerError> {
+        let hash = self.hash(network_passphrase);
+        let verifying_key = VerifyingKey::from_bytes(public_key)
+            .map_err(|e| SignerError::SigningFailed(format!("Invalid Ed25519 public key: {}", e)))?;
+        if verifying_key.verify(&hash, &Signature::from_bytes(signature)).is_err() {
+            return Err(SignerError::SigningFailed(
+                "signature does not verify over the transaction hash".to_string(),
+            ));
+        }
+
+        let hint: [u8; 4] = public_key[28..].try_into().expect("4 bytes");
+        self.signatures.retain(|(existing_hint, existing)| {
+            *existing_hint != hint || verifying_key.verify(&hash, &Signature::from_bytes(existing)).is_err()
+        });
+        if self.signatures.len() == MAX_SIGNATURES {
+            return Err(xdr_error(format!("envelope already has {} signatures", MAX_SIGNATURES)));
+        }
+        self.signatures.push((hint, *signature));
+        Ok(())
+    }
+}
+
+/// Signature hint (last four bytes of the public key) and signature
+type DecoratedSignature = ([u8; 4], [u8; 64]);
+
+/// Sequence number, memo, and operation count of a transaction
+type Header = (i64, Option<String>, u32);
+
+/// Read a transaction from its sequence number to its operation count
+fn read_header(r: &mut ByteReader, v0: bool) -> Result<Header, ParseError> {
+    let sequence = r.i64_be()?;
+    if v0 {
+        // Optional time bounds
+        if read_bool(r)? {
+            r.take(16)?;
+        }
+    } else {
+        match r.u32_be()? {
+            0 => {}
+            1 => {
+                r.take(16)?;
+            }
+            2 => {
+                // time bounds, ledger bounds, min sequence number (all optional)
+                for size in [16, 8, 8] {
+                    if read_bool(r)? {
+                        r.take(size)?;
+                    }
+                }
+                // min sequence age, min ledger gap
+                r.take(12)?;
+                let extra_signers = r.u32_be()?;
+                if extra_signers > 2 {
+                    return Err(r.invalid("too many extra signers"));
+                }
+                for _ in 0..extra_signers {
+                    match r.u32_be()? {
+                        0..=2 => {
+                            r.take(32)?;
+                        }
+                        3 => {
+                            r.take(32)?;
+                            read_opaque(r)?;
+                        }
+                        other => return Err(r.invalid(format!("unknown signer key type {}", other))),
+                    }
+                }
+            }
+            other => return Err(r.invalid(format!("unknown precondition type {}", other))),
+        }
+    }
+
+    let memo = match r.u32_be()? {
+        0 => None,
+        1 => Some(String::from_utf8_lossy(&read_opaque(r)?).into_owned()),
+        2 => Some((r.i64_be()? as u64).to_string()),
+        3 | 4 => Some(hex::encode(r.array::<32>()?)),
+        other => return Err(r.invalid(format!("unknown memo type {}", other))),
+    };
+    let operations = r.u32_be()?;
+    Ok((sequence, memo, operations))
+}
+
+/// Locate the trailing `DecoratedSignature` list
+///
+/// Returns where the transaction ends and the parsed signatures. The
+/// shortest list that fits is taken, so an unsigned envelope is never read
+/// as a signed one.
+fn find_signatures(bytes: &[u8], header_end: usize) -> Result<(usize, Vec<DecoratedSignature>), SignerError> {
+    for count in 0..=MAX_SIGNATURES {
+        let Some(start) = bytes.len().checked_sub(4 + count * DECORATED_SIGNATURE_SIZE) else {
+            break;
+        };
+        if start < header_end || bytes[start..start + 4] != (count as u32).to_be_bytes() {
+            continue;
+        }
+        let entries = bytes[start + 4..].chunks_exact(DECORATED_SIGNATURE_SIZE);
+        if entries.clone().all(|entry| entry[4..8] == 64u32.to_be_bytes()) {
+            let signatures = entries
+                .map(|entry| {
+                    (
+                        entry[..4].try_into().expect("4 bytes"),
+                        entry[8..].try_into().expect("64 bytes"),
+                    )
+                })
+                .collect();
+            return Ok((start, signatures));
+        }
+    }
+    Err(xdr_error("signature list not found"))
+}
+
+/// Encode a strkey: base32 of version byte, payload, and CRC-16 (little-endian)
+fn strkey(version: u8, payload: &[u8]) -> String {
+    let mut data = Vec::with_capacity(payload.len() + 3);
+    data.push(version);
+    data.extend_from_slice(payload);
+    let crc = crc16_xmodem(&data);
+    data.extend_from_slice(&crc.to_le_bytes());
+    base32(&data)
+}
+
+/// RFC 4648 base32 without padding
+fn base32(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+    let mut out = String::with_capacity(data.len().div_ceil(5) * 8);
+    let (mut buffer, mut bits) = (0u32, 0);
+    for &byte in data {
+        buffer = buffer << 8 | byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            out.push(ALPHABET[(buffer >> bits) as usize & 31] as char);
+        }
+    }
+    if bits > 0 {
+        out.push(ALPHABET[(buffer << (5 - bits)) as usize & 31] as char);
+    }
+    out
+}
+
+/// `G...` account address of an Ed25519 public key
+pub fn stellar_address(public_key: &[u8; 32]) -> String {
+    strkey(STRKEY_ACCOUNT, public_key)
+}
+
+/// Result of a Stellar signing operation
+#[derive(Serialize, Deserialize)]
+pub struct StellarSigningResult {
+    /// The signature (base64, 64 bytes)
+    pub signature: String,
+    /// The envelope with the signature added (base64 XDR)
+    pub signed_envelope: String,
+    /// Transaction hash (hex)
+    pub hash: String,
+    /// Signing account (`G...`)
+    pub public_key: String,
+}
+
+/// Sign an envelope with a key in a secure buffer
+pub(crate) fn sign_stellar_with_secure_key(
+    secure_key: &mut SecureBuffer,
+    envelope: &mut StellarEnvelope,
+    network_passphrase: &str,
+) -> Result<StellarSigningResult, SignerError> {
+    if secure_key.len() != 32 {
+        return Err(SignerError::InvalidKeyFormat(secure_key.len()));
+    }
+    health_check()?;
+
+    let signing_key = SigningKey::from_bytes(
+        secure_key
+            .as_slice()
+            .try_into()
+            .map_err(|_| SignerError::InvalidKeyFormat(secure_key.len()))?,
+    );
+    let public_key = signing_key.verifying_key().to_bytes();
+    let hash = envelope.hash(network_passphrase);
+    let signature = signing_key.sign(&hash).to_bytes();
+    envelope.add_signature(&public_key, &signature, network_passphrase)?;
+
+    Ok(StellarSigningResult {
+        signature: base64::Engine::encode(&base64::engine::general_purpose::STANDARD, signature),
+        signed_envelope: envelope.to_xdr_base64(),
+        hash: hex::encode(hash),
+        public_key: stellar_address(&public_key),
+    })
+}
+
+/// Decrypt a key container and sign a Stellar transaction envelope
+///
+/// Same security model as `decrypt_and_sign`, with the same Ed25519 key as
+/// Solana.
+///
+/// # Arguments
+/// * `container_json` - JSON-serialized EncryptedKeyContainer
+/// * `passphrase` - The passphrase for decryption
+/// * `envelope_xdr` - Base64 XDR `TransactionEnvelope`, possibly already
+///   signed by others
+/// * `network_passphrase` - e.g. [`PUBLIC_NETWORK_PASSPHRASE`]
+pub fn decrypt_and_sign_stellar(
+    container_json: &str,
+    passphrase: &str,
+    envelope_xdr: &str,
+    network_passphrase: &str,
+) -> Result<StellarSigningResult, SignerError> {
+    let mut envelope = StellarEnvelope::from_xdr_base64(envelope_xdr)?;
+    let container = EncryptedKeyContainer::from_json(container_json)?;
+
+    let mut secure_key = container.decrypt_key(passphrase)?;
+    let result = sign_stellar_with_secure_key(&mut secure_key, &mut envelope, network_passphrase);
+    secure_key.zeroize();
+
+    result
+}
+
+/// Sign a Stellar transaction envelope with a raw private key
+///
+/// # Security Warning
+/// Prefer using decrypt_and_sign_stellar() for the full secure workflow.
+pub fn sign_stellar(
+    private_key: &[u8],
+    envelope_xdr: &str,
+    network_passphrase: &str,
+) -> Result<StellarSigningResult, SignerError> {
+    let mut envelope = StellarEnvelope::from_xdr_base64(envelope_xdr)?;
+    let mut secure_key = SecureBuffer::from_slice_with_mode(private_key, get_locking_mode())?;
+    let result = sign_stellar_with_secure_key(&mut secure_key, &mut envelope, network_passphrase);
+    secure_key.zeroize();
+    result
+}
+
+fn read_bool(r: &mut ByteReader) -> Result<bool, ParseError> {
+    match r.u32_be()? {
+        0 => Ok(false),
+        1 => Ok(true),
+        other => Err(r.invalid(format!("invalid bool {}", other))),
+    }
+}
+
+/// Variable-length opaque data, padded to a multiple of 4 bytes
+fn read_opaque(r: &mut ByteReader) -> Result<Vec<u8>, ParseError> {
+    let len = r.u32_be()?;
+    let data = r.take_len(u64::from(len))?.to_vec();
+    r.take((4 - data.len() % 4) % 4)?;
+    Ok(data)
+}
+
+fn read_muxed_account(r: &mut ByteReader) -> Result<String, ParseError> {
+    match r.u32_be()? {
+        KEY_TYPE_ED25519 => Ok(strkey(STRKEY_ACCOUNT, &r.array::<32>()?)),
+        KEY_TYPE_MUXED_ED25519 => {
+            let id = r.take(8)?;
+            let mut payload = r.array::<32>()?.to_vec();
+            payload.extend_from_slice(id);
+            Ok(strkey(STRKEY_MUXED_ACCOUNT, &payload))
+        }
+        other => Err(r.invalid(format!("unknown account type {}", other))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::Cipher;
+    use crate::kdf::KdfParams;
+
+    /// Payment of 1 XLM from the [7; 32] seed's account, with memo "hi"
+    const UNSIGNED: &str = "AAAAAgAAAADqSmxj4pxSCr71UHsTLsX5lUd2rr6+e5JCHuppFEbSLAAAAGQAAAAAAAAwOQAAAAEAAAAAAAAAAAAAAABlU/EAAAAAAQAAAAJoaQAAAAAAAQAAAAAAAAABAAAAAAkJCQkJCQkJCQkJCQkJCQkJCQkJCQkJCQkJCQkJCQkJAAAAAAAAAAAAmJaAAAAAAAAAAAA=";
+    const SIGNED: &str = "AAAAAgAAAADqSmxj4pxSCr71UHsTLsX5lUd2rr6+e5JCHuppFEbSLAAAAGQAAAAAAAAwOQAAAAEAAAAAAAAAAAAAAABlU/EAAAAAAQAAAAJoaQAAAAAAAQAAAAAAAAABAAAAAAkJCQkJCQkJCQkJCQkJCQkJCQkJCQkJCQkJCQkJCQkJAAAAAAAAAAAAmJaAAAAAAAAAAAEURtIsAAAAQG8or5fxEaDPX/P7FPCPEmxAuwBofL30v8VyEltMkfXCVBA7oFMvxKLGN1FjQW6phsUR5TPat7Ds3Rl6WDnx9A4=";
+    /// The same transaction in a v0 envelope
+    const UNSIGNED_V0: &str = "AAAAAOpKbGPinFIKvvVQexMuxfmVR3auvr57kkIe6mkURtIsAAAAZAAAAAAAADA5AAAAAQAAAAAAAAAAAAAAAGVT8QAAAAABAAAAAmhpAAAAAAABAAAAAAAAAAEAAAAACQkJCQkJCQkJCQkJCQkJCQkJCQkJCQkJCQkJCQkJCQkAAAAAAAAAAACYloAAAAAAAAAAAA==";
+    const HASH: &str = "ca78286ba81713f9ae26b1db34713f0ad996e72c6c0561bf03a3b0c45c18f74a";
+
+    #[test]
+    fn test_envelope_header_and_hash() {
+        assert_eq!(stellar_address(&[0u8; 32]), "GAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAWHF");
+
+        let envelope = StellarEnvelope::from_xdr_base64(UNSIGNED).unwrap();
+        assert_eq!(envelope.kind, EnvelopeKind::Tx);
+        assert_eq!(envelope.source_account, "GDVEU3DD4KOFECV66VIHWEZOYX4ZKR3WV27L464SIIPOU2IUI3JCZA57");
+        assert_eq!((envelope.fee, envelope.sequence, envelope.operations), (100, 12345, 1));
+        assert_eq!(envelope.memo.as_deref(), Some("hi"));
+        assert_eq!(envelope.signature_count(), 0);
+        assert_eq!(envelope.to_xdr_base64(), UNSIGNED);
+        assert_eq!(hex::encode(envelope.hash(PUBLIC_NETWORK_PASSPHRASE)), HASH);
+        assert_ne!(hex::encode(envelope.hash(TESTNET_PASSPHRASE)), HASH);
+
+        let v0 = StellarEnvelope::from_xdr_base64(UNSIGNED_V0).unwrap();
+        assert_eq!(v0.kind, EnvelopeKind::TxV0);
+        assert_eq!(v0.source_account, envelope.source_account);
+        assert_eq!(hex::encode(v0.hash(PUBLIC_NETWORK_PASSPHRASE)), HASH);
+
+        assert_eq!(StellarEnvelope::from_xdr_base64(SIGNED).unwrap().signature_count(), 1);
+        assert!(StellarEnvelope::from_xdr_base64("AAAAAQ==").is_err());
+    }
+
+    #[test]
+    fn test_sign_matches_reference_envelope() {
+        std::env::set_var("SIGNER_ALLOW_INSECURE_MEMORY", "1");
+        let container =
+            EncryptedKeyContainer::encrypt_with_kdf(&[7u8; 32], "pw", Cipher::Aes256Gcm, KdfParams::MINIMUM).unwrap();
+        let json = container.to_json().unwrap();
+
+        let result = decrypt_and_sign_stellar(&json, "pw", UNSIGNED, PUBLIC_NETWORK_PASSPHRASE).unwrap();
+        assert_eq!(result.signed_envelope, SIGNED);
+        assert_eq!(result.hash, HASH);
+        assert_eq!(result.public_key, "GDVEU3DD4KOFECV66VIHWEZOYX4ZKR3WV27L464SIIPOU2IUI3JCZA57");
+
+        // Signing again replaces the signature instead of duplicating it
+        let again = sign_stellar(&[7u8; 32], SIGNED, PUBLIC_NETWORK_PASSPHRASE).unwrap();
+        assert_eq!(again.signed_envelope, SIGNED);
+
+        // A second signer is appended
+        let cosigned = sign_stellar(&[8u8; 32], SIGNED, PUBLIC_NETWORK_PASSPHRASE).unwrap();
+        assert_eq!(StellarEnvelope::from_xdr_base64(&cosigned.signed_envelope).unwrap().signature_count(), 2);
+
+        assert!(decrypt_and_sign_stellar(&json, "wrong", UNSIGNED, PUBLIC_NETWORK_PASSPHRASE).is_err());
+    }
+}
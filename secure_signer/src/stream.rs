@@ -0,0 +1,381 @@
+//! Streaming encryption of large payloads
+//!
+//! Containers encrypt their key in one AEAD call, which is fine for 32
+//! bytes but not for a wallet backup or a state snapshot: the whole
+//! plaintext would have to sit in (locked) memory at once. The stream
+//! format here splits the payload into fixed-size chunks, each sealed
+//! separately, so only one chunk of plaintext is held at a time.
+//!
+//! # Format
+//!
+//! ```text
+//! header: "CSTREAM1" | cipher (u8) | chunk size (u32 BE) | salt (32 bytes)
+//! chunks: ciphertext || tag, chunk-size bytes of plaintext each, the last shorter (or empty)
+//! ```
+//!
+//! The stream key is HKDF-SHA256 over the container's seed with the whole
+//! header as info, so every stream gets its own key and a modified header
+//! fails authentication. Chunk nonces follow the STREAM construction
+//! (Hoang et al.): a big-endian chunk counter and a final-chunk flag, so
+//! reordered, dropped, or duplicated chunks and truncation at a chunk
+//! boundary are all detected.
+
+use std::io::{self, Read, Write};
+
+use hkdf::Hkdf;
+use sha2::Sha256;
+use zeroize::Zeroize;
+
+use crate::crypto::{get_locking_mode, Cipher, EncryptedKeyContainer};
+use crate::entropy::fill_random;
+use crate::error::SignerError;
+use crate::secure_buffer::SecureBuffer;
+use crate::secure_vec::SecureVec;
+
+const MAGIC: &[u8; 8] = b"CSTREAM1";
+const HEADER_SIZE: usize = 8 + 1 + 4 + 32;
+const STREAM_SALT: &[u8] = b"coldstar-stream-v1";
+const TAG_SIZE: usize = 16;
+const KEY_SIZE: usize = 32;
+
+/// Plaintext bytes per chunk unless configured otherwise (64 KiB)
+pub const DEFAULT_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Largest chunk size a stream may declare (16 MiB)
+pub const MAX_CHUNK_SIZE: usize = 16 * 1024 * 1024;
+
+fn cipher_id(cipher: Cipher) -> u8 {
+    match cipher {
+        Cipher::Aes256Gcm => 1,
+        Cipher::XChaCha20Poly1305 => 2,
+        Cipher::Aes256GcmSiv => 3,
+    }
+}
+
+fn cipher_from_id(id: u8) -> Option<Cipher> {
+    match id {
+        1 => Some(Cipher::Aes256Gcm),
+        2 => Some(Cipher::XChaCha20Poly1305),
+        3 => Some(Cipher::Aes256GcmSiv),
+        _ => None,
+    }
+}
+
+/// The stream key for `header`
+fn stream_key(container: &EncryptedKeyContainer, passphrase: &str, header: &[u8]) -> Result<SecureBuffer, SignerError> {
+    let mut seed = container.decrypt_key(passphrase)?;
+    let mut key = SecureBuffer::with_mode(KEY_SIZE, get_locking_mode())?;
+    let result = Hkdf::<Sha256>::new(Some(STREAM_SALT), seed.as_slice())
+        .expand(header, key.as_mut_slice())
+        .map_err(|e| SignerError::KeyDerivationFailed(e.to_string()));
+    seed.zeroize();
+    result.map(|_| key)
+}
+
+/// Nonce of chunk `counter`: zeros, the counter (u32 BE), the final flag
+fn chunk_nonce(cipher: Cipher, counter: u32, last: bool) -> Vec<u8> {
+    let mut nonce = vec![0u8; cipher.nonce_size()];
+    let len = nonce.len();
+    nonce[len - 5..len - 1].copy_from_slice(&counter.to_be_bytes());
+    nonce[len - 1] = u8::from(last);
+    nonce
+}
+
+/// Encrypts everything written to it into `writer`
+///
+/// Call [`finish`](Self::finish) at the end: it seals the final chunk.
+/// A stream dropped without it is rejected as truncated when decrypted.
+pub struct SecureStreamEncryptor<W: Write> {
+    writer: W,
+    cipher: Cipher,
+    key: SecureBuffer,
+    buffer: SecureBuffer,
+    filled: usize,
+    counter: u32,
+}
+
+impl<W: Write> SecureStreamEncryptor<W> {
+    /// Start a stream under `container`'s key with the default chunk size
+    pub fn new(container: &EncryptedKeyContainer, passphrase: &str, writer: W) -> Result<Self, SignerError> {
+        Self::with_options(container, passphrase, writer, container.cipher, DEFAULT_CHUNK_SIZE)
+    }
+
+    /// Start a stream with an explicit cipher and chunk size
+    pub fn with_options(
+        container: &EncryptedKeyContainer,
+        passphrase: &str,
+        mut writer: W,
+        cipher: Cipher,
+        chunk_size: usize,
+    ) -> Result<Self, SignerError> {
+        if chunk_size == 0 || chunk_size > MAX_CHUNK_SIZE {
+            return Err(SignerError::ContainerError(format!(
+                "chunk size must be 1 to {} bytes, got {}",
+                MAX_CHUNK_SIZE, chunk_size
+            )));
+        }
+        let mut header = Vec::with_capacity(HEADER_SIZE);
+        header.extend_from_slice(MAGIC);
+        header.push(cipher_id(cipher));
+        header.extend_from_slice(&(chunk_size as u32).to_be_bytes());
+        let mut salt = [0u8; 32];
+        fill_random(&mut salt)?;
+        header.extend_from_slice(&salt);
+
+        let key = stream_key(container, passphrase, &header)?;
+        let buffer = SecureBuffer::with_mode(chunk_size, get_locking_mode())?;
+        writer.write_all(&header)?;
+        Ok(Self {
+            writer,
+            cipher,
+            key,
+            buffer,
+            filled: 0,
+            counter: 0,
+        })
+    }
+
+    fn seal_chunk(&mut self, last: bool) -> Result<(), SignerError> {
+        let nonce = chunk_nonce(self.cipher, self.counter, last);
+        let sealed = self.cipher.encrypt(self.key.as_slice(), &nonce, &self.buffer.as_slice()[..self.filled])?;
+        self.writer.write_all(&sealed)?;
+        // Slice-wise, as zeroizing the buffer itself would empty it
+        self.buffer.as_mut_slice().zeroize();
+        self.filled = 0;
+        self.counter = self
+            .counter
+            .checked_add(1)
+            .ok_or_else(|| SignerError::ContainerError("stream exceeds 2^32 chunks".to_string()))?;
+        Ok(())
+    }
+
+    /// Seal the final chunk and return the writer
+    pub fn finish(mut self) -> Result<W, SignerError> {
+        self.seal_chunk(true)?;
+        self.writer.flush()?;
+        self.key.zeroize();
+        Ok(self.writer)
+    }
+}
+
+impl<W: Write> Write for SecureStreamEncryptor<W> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        if data.is_empty() {
+            return Ok(0);
+        }
+        // A full buffer is sealed only once more data arrives, since the
+        // last chunk must carry the final flag
+        if self.filled == self.buffer.len() {
+            self.seal_chunk(false).map_err(io::Error::other)?;
+        }
+        let n = data.len().min(self.buffer.len() - self.filled);
+        self.buffer.as_mut_slice()[self.filled..self.filled + n].copy_from_slice(&data[..n]);
+        self.filled += n;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Decrypts a stream from `reader`, one chunk at a time
+///
+/// Reading fails with [`io::ErrorKind::InvalidData`] on a chunk that does
+/// not authenticate and on a stream that ends without its final chunk.
+/// Data already returned came from authenticated chunks, but the stream as
+/// a whole is only known to be complete once a read returns 0.
+pub struct SecureStreamDecryptor<R: Read> {
+    reader: R,
+    cipher: Cipher,
+    key: SecureBuffer,
+    chunk_size: usize,
+    /// The current chunk, decrypted in place
+    plaintext: SecureVec,
+    /// Bytes of `plaintext` already returned
+    start: usize,
+    lookahead: Option<u8>,
+    counter: u32,
+    done: bool,
+}
+
+impl<R: Read> SecureStreamDecryptor<R> {
+    /// Read the stream header and derive the stream key
+    pub fn new(container: &EncryptedKeyContainer, passphrase: &str, mut reader: R) -> Result<Self, SignerError> {
+        let mut header = [0u8; HEADER_SIZE];
+        reader.read_exact(&mut header)?;
+        if &header[..8] != MAGIC {
+            return Err(SignerError::ContainerError("not an encrypted stream".to_string()));
+        }
+        let cipher = cipher_from_id(header[8])
+            .ok_or_else(|| SignerError::ContainerError(format!("unknown stream cipher {}", header[8])))?;
+        let chunk_size = u32::from_be_bytes(header[9..13].try_into().expect("4 bytes")) as usize;
+        if chunk_size == 0 || chunk_size > MAX_CHUNK_SIZE {
+            return Err(SignerError::ContainerError(format!("invalid stream chunk size {}", chunk_size)));
+        }
+
+        let key = stream_key(container, passphrase, &header)?;
+        let plaintext = SecureVec::with_capacity_and_mode(chunk_size + TAG_SIZE, get_locking_mode())?;
+        Ok(Self {
+            reader,
+            cipher,
+            key,
+            chunk_size,
+            plaintext,
+            start: 0,
+            lookahead: None,
+            counter: 0,
+            done: false,
+        })
+    }
+
+    /// Read the next sealed chunk; whether it is the last is known by
+    /// trying to read one byte past it
+    fn open_chunk(&mut self) -> io::Result<()> {
+        let sealed_size = self.chunk_size + TAG_SIZE;
+        let mut sealed = Vec::with_capacity(sealed_size);
+        sealed.extend(self.lookahead.take());
+        while sealed.len() < sealed_size {
+            let n = (&mut self.reader).take((sealed_size - sealed.len()) as u64).read_to_end(&mut sealed)?;
+            if n == 0 {
+                break;
+            }
+        }
+        let mut next = [0u8; 1];
+        let last = sealed.len() < sealed_size || loop {
+            match self.reader.read(&mut next) {
+                Ok(0) => break true,
+                Ok(_) => {
+                    self.lookahead = Some(next[0]);
+                    break false;
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        };
+        let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+        if sealed.len() < TAG_SIZE {
+            return Err(invalid("stream is truncated".to_string()));
+        }
+
+        let nonce = chunk_nonce(self.cipher, self.counter, last);
+        self.plaintext.clear();
+        self.plaintext.extend_from_slice(&sealed).map_err(io::Error::other)?;
+        self.cipher
+            .decrypt_in_place(self.key.as_slice(), &nonce, &mut self.plaintext)
+            .map_err(|_| match last {
+                // A cut at a chunk boundary leaves a full chunk sealed as non-final
+                true => invalid(format!("stream chunk {} failed authentication or the stream is truncated", self.counter)),
+                false => invalid(format!("stream chunk {} failed authentication", self.counter)),
+            })?;
+        self.start = 0;
+        self.done = last;
+        self.counter = self
+            .counter
+            .checked_add(1)
+            .ok_or_else(|| invalid("stream exceeds 2^32 chunks".to_string()))?;
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for SecureStreamDecryptor<R> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        while self.start == self.plaintext.len() {
+            if self.done || out.is_empty() {
+                return Ok(0);
+            }
+            self.open_chunk()?;
+        }
+        let n = out.len().min(self.plaintext.len() - self.start);
+        out[..n].copy_from_slice(&self.plaintext[self.start..self.start + n]);
+        self.start += n;
+        if self.start == self.plaintext.len() {
+            self.plaintext.clear();
+            self.start = 0;
+        }
+        Ok(n)
+    }
+}
+
+/// Encrypt everything from `reader` into `writer`, returning the
+/// plaintext length
+pub fn encrypt_stream<R: Read, W: Write>(
+    container: &EncryptedKeyContainer,
+    passphrase: &str,
+    reader: &mut R,
+    writer: W,
+) -> Result<u64, SignerError> {
+    let mut encryptor = SecureStreamEncryptor::new(container, passphrase, writer)?;
+    let copied = io::copy(reader, &mut encryptor)?;
+    encryptor.finish()?;
+    Ok(copied)
+}
+
+/// Decrypt a stream from `reader` into `writer`, returning the plaintext
+/// length
+///
+/// On error, `writer` may already hold a prefix of the plaintext; callers
+/// writing to a file should discard it.
+pub fn decrypt_stream<R: Read, W: Write>(
+    container: &EncryptedKeyContainer,
+    passphrase: &str,
+    reader: R,
+    writer: &mut W,
+) -> Result<u64, SignerError> {
+    let mut decryptor = SecureStreamDecryptor::new(container, passphrase, reader)?;
+    io::copy(&mut decryptor, writer).map_err(|e| match e.kind() {
+        io::ErrorKind::InvalidData => SignerError::ContainerError(e.to_string()),
+        _ => SignerError::from(e),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kdf::KdfParams;
+
+    #[test]
+    fn test_stream_round_trip_and_tampering() {
+        std::env::set_var("SIGNER_ALLOW_INSECURE_MEMORY", "1");
+        let container =
+            EncryptedKeyContainer::encrypt_with_kdf(&[7u8; 32], "pw", Cipher::Aes256Gcm, KdfParams::MINIMUM).unwrap();
+        let payload: Vec<u8> = (0..10_000u32).map(|i| (i * 7 % 251) as u8).collect();
+
+        for cipher in [Cipher::Aes256Gcm, Cipher::XChaCha20Poly1305, Cipher::Aes256GcmSiv] {
+            // Lengths around chunk boundaries, including empty
+            for len in [0, 100, 101] {
+                let mut encryptor =
+                    SecureStreamEncryptor::with_options(&container, "pw", Vec::new(), cipher, 100).unwrap();
+                encryptor.write_all(&payload[..len]).unwrap();
+                let sealed = encryptor.finish().unwrap();
+                let mut opened = Vec::new();
+                decrypt_stream(&container, "pw", sealed.as_slice(), &mut opened).unwrap();
+                assert_eq!(opened, &payload[..len]);
+            }
+        }
+
+        let mut sealed = Vec::new();
+        assert_eq!(encrypt_stream(&container, "pw", &mut payload.as_slice(), &mut sealed).unwrap(), 10_000);
+        let mut opened = Vec::new();
+        assert!(decrypt_stream(&container, "wrong", sealed.as_slice(), &mut opened).is_err());
+        decrypt_stream(&container, "pw", sealed.as_slice(), &mut opened).unwrap();
+        assert_eq!(opened, payload);
+
+        // Small chunks: truncation at a boundary, a flipped bit, a header edit
+        let mut encryptor =
+            SecureStreamEncryptor::with_options(&container, "pw", Vec::new(), Cipher::Aes256Gcm, 100).unwrap();
+        encryptor.write_all(&payload[..1000]).unwrap();
+        let sealed = encryptor.finish().unwrap();
+        let chunk = 100 + TAG_SIZE;
+        let truncated = &sealed[..HEADER_SIZE + 5 * chunk];
+        let mut flipped = sealed.clone();
+        flipped[HEADER_SIZE + 3 * chunk + 10] ^= 1;
+        let mut header = sealed.clone();
+        header[20] ^= 1;
+        let mut swapped = sealed.clone();
+        swapped[HEADER_SIZE..HEADER_SIZE + 2 * chunk].rotate_left(chunk);
+        for bad in [truncated, &flipped[..], &header[..], &swapped[..]] {
+            assert!(decrypt_stream(&container, "pw", bad, &mut Vec::new()).is_err());
+        }
+    }
+}
@@ -0,0 +1,187 @@
+//! sr25519 signing for Substrate chains (Polkadot, Kusama, parachains)
+//!
+//! Uses the same 32-byte seed as the Ed25519 and secp256k1 keys, expanded
+//! into an sr25519 key pair the way Substrate does (`MiniSecretKey` with
+//! Ed25519 expansion), so a container imported from a Substrate seed
+//! reproduces its account.
+//!
+//! # Signing Payloads
+//!
+//! Signatures use the `substrate` signing context. As in Substrate's
+//! extrinsic format, payloads longer than 256 bytes are replaced by their
+//! BLAKE2b-256 hash before signing.
+
+use blake2::digest::consts::U32;
+use blake2::{Blake2b, Blake2b512, Digest};
+use schnorrkel::{signing_context, ExpansionMode, MiniSecretKey};
+use serde::{Deserialize, Serialize};
+
+use crate::crypto::{get_locking_mode, EncryptedKeyContainer};
+use crate::entropy::health_check;
+use crate::error::SignerError;
+use crate::secure_buffer::SecureBuffer;
+
+/// Signing context used by Substrate
+const SIGNING_CONTEXT: &[u8] = b"substrate";
+
+/// Payloads longer than this are hashed before signing
+const MAX_UNHASHED_PAYLOAD: usize = 256;
+
+/// SS58 address format of the generic Substrate network
+pub const GENERIC_SS58_FORMAT: u16 = 42;
+
+/// `MultiSignature` variant index for sr25519
+const MULTI_SIGNATURE_SR25519: u8 = 0x01;
+
+/// Result of an sr25519 signing operation
+#[derive(Serialize, Deserialize)]
+pub struct SubstrateSigningResult {
+    /// The signature (0x-prefixed hex, 64 bytes)
+    pub signature: String,
+    /// The signature as a SCALE-encoded `MultiSignature` (0x-prefixed hex,
+    /// 65 bytes), ready to place in an extrinsic
+    pub multi_signature: String,
+    /// The sr25519 public key (0x-prefixed hex, 32 bytes)
+    pub public_key: String,
+    /// SS58 address with the generic Substrate prefix (42)
+    pub address: String,
+}
+
+/// The bytes actually signed for an extrinsic payload
+pub fn signing_payload(payload: &[u8]) -> Vec<u8> {
+    if payload.len() > MAX_UNHASHED_PAYLOAD {
+        Blake2b::<U32>::digest(payload).to_vec()
+    } else {
+        payload.to_vec()
+    }
+}
+
+/// Encode an sr25519 public key as an SS58 address
+pub fn ss58_address(public_key: &[u8; 32], format: u16) -> String {
+    let mut data = match format {
+        0..=63 => vec![format as u8],
+        // Two-byte prefix encoding for formats 64..16383
+        _ => vec![
+            ((format & 0b1111_1100) as u8 >> 2) | 0b0100_0000,
+            (format >> 8) as u8 | ((format & 0b11) as u8) << 6,
+        ],
+    };
+    data.extend_from_slice(public_key);
+
+    let checksum = Blake2b512::new().chain_update(b"SS58PRE").chain_update(&data).finalize();
+    data.extend_from_slice(&checksum[..2]);
+    bs58::encode(data).into_string()
+}
+
+/// Sign a Substrate payload with a seed in a secure buffer
+pub(crate) fn sign_substrate_with_secure_key(
+    secure_key: &mut SecureBuffer,
+    payload: &[u8],
+) -> Result<SubstrateSigningResult, SignerError> {
+    if secure_key.len() != 32 {
+        return Err(SignerError::InvalidKeyFormat(secure_key.len()));
+    }
+    health_check()?;
+
+    // MiniSecretKey and the expanded SecretKey zeroize on drop
+    let keypair = MiniSecretKey::from_bytes(secure_key.as_slice())
+        .map_err(|e| SignerError::SigningFailed(format!("Invalid sr25519 seed: {}", e)))?
+        .expand_to_keypair(ExpansionMode::Ed25519);
+
+    let signature = keypair
+        .sign(signing_context(SIGNING_CONTEXT).bytes(&signing_payload(payload)))
+        .to_bytes();
+    let public_key = keypair.public.to_bytes();
+
+    let mut multi_signature = Vec::with_capacity(65);
+    multi_signature.push(MULTI_SIGNATURE_SR25519);
+    multi_signature.extend_from_slice(&signature);
+
+    Ok(SubstrateSigningResult {
+        signature: format!("0x{}", hex::encode(signature)),
+        multi_signature: format!("0x{}", hex::encode(multi_signature)),
+        public_key: format!("0x{}", hex::encode(public_key)),
+        address: ss58_address(&public_key, GENERIC_SS58_FORMAT),
+    })
+}
+
+/// Decrypt a key container and sign a Substrate extrinsic payload
+///
+/// Same security model as `decrypt_and_sign` but uses sr25519.
+///
+/// # Arguments
+/// * `container_json` - JSON-serialized EncryptedKeyContainer
+/// * `passphrase` - The passphrase for decryption
+/// * `payload` - The SCALE-encoded signing payload
+pub fn decrypt_and_sign_substrate(
+    container_json: &str,
+    passphrase: &str,
+    payload: &[u8],
+) -> Result<SubstrateSigningResult, SignerError> {
+    let container = EncryptedKeyContainer::from_json(container_json)?;
+
+    let mut secure_key = container.decrypt_key(passphrase)?;
+    let result = sign_substrate_with_secure_key(&mut secure_key, payload);
+    secure_key.zeroize();
+
+    result
+}
+
+/// Sign a Substrate payload with a raw seed
+///
+/// # Security Warning
+/// Prefer using decrypt_and_sign_substrate() for the full secure workflow.
+pub fn sign_substrate(private_key: &[u8], payload: &[u8]) -> Result<SubstrateSigningResult, SignerError> {
+    let mut secure_key = SecureBuffer::from_slice_with_mode(private_key, get_locking_mode())?;
+    let result = sign_substrate_with_secure_key(&mut secure_key, payload);
+    secure_key.zeroize();
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::Cipher;
+    use crate::kdf::KdfParams;
+
+    fn verify(result: &SubstrateSigningResult, payload: &[u8]) -> bool {
+        let public_key = schnorrkel::PublicKey::from_bytes(&hex::decode(&result.public_key[2..]).unwrap()).unwrap();
+        let signature = schnorrkel::Signature::from_bytes(&hex::decode(&result.signature[2..]).unwrap()).unwrap();
+        public_key
+            .verify_simple(SIGNING_CONTEXT, &signing_payload(payload), &signature)
+            .is_ok()
+    }
+
+    #[test]
+    fn test_substrate_signature_verifies() {
+        std::env::set_var("SIGNER_ALLOW_INSECURE_MEMORY", "1");
+        let container =
+            EncryptedKeyContainer::encrypt_with_kdf(&[4u8; 32], "pw", Cipher::Aes256Gcm, KdfParams::MINIMUM).unwrap();
+        let json = container.to_json().unwrap();
+
+        let short = decrypt_and_sign_substrate(&json, "pw", b"short payload").unwrap();
+        assert!(verify(&short, b"short payload"));
+        assert!(!verify(&short, b"other payload"));
+        assert_eq!(&short.multi_signature[..4], "0x01");
+        assert_eq!(short.address, sign_substrate(&[4u8; 32], b"x").unwrap().address);
+
+        // Long payloads are signed by hash
+        let long = [7u8; 300];
+        assert!(verify(&decrypt_and_sign_substrate(&json, "pw", &long).unwrap(), &long));
+        assert!(decrypt_and_sign_substrate(&json, "wrong", &long).is_err());
+    }
+
+    #[test]
+    fn test_ss58_address_of_known_key() {
+        // Alice (//Alice) on the generic network
+        let alice: [u8; 32] = hex::decode("d43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d")
+            .unwrap()
+            .try_into()
+            .unwrap();
+        assert_eq!(
+            ss58_address(&alice, GENERIC_SS58_FORMAT),
+            "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY"
+        );
+        assert_eq!(ss58_address(&alice, 0), "15oF4uVJwmo4TdGW7VfQxNLavjCXviqxT9S1MgbjMNHr6Sp5");
+    }
+}
@@ -0,0 +1,203 @@
+//! Sui Ed25519 signing
+//!
+//! Sui signs the BLAKE2b-256 digest of an intent message: a three-byte
+//! intent (scope, version, app ID) followed by the BCS-encoded
+//! `TransactionData`. The scope keeps a transaction signature from being
+//! valid as a personal message signature and vice versa. The container's
+//! 32-byte key is the Ed25519 seed, as for Solana.
+//!
+//! An account's address is `BLAKE2b-256(0x00 || public key)`, the leading
+//! byte being the Ed25519 signature scheme flag. Signatures are submitted
+//! in the serialized form `flag || signature || public key`.
+
+use blake2::digest::consts::U32;
+use blake2::{Blake2b, Digest};
+use ed25519_dalek::{Signer, SigningKey};
+use serde::{Deserialize, Serialize};
+
+use crate::crypto::{get_locking_mode, EncryptedKeyContainer};
+use crate::entropy::health_check;
+use crate::error::SignerError;
+use crate::secure_buffer::SecureBuffer;
+
+/// Signature scheme flag of Ed25519
+const ED25519_FLAG: u8 = 0x00;
+
+/// Intent scope of the signed data
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum IntentScope {
+    /// BCS `TransactionData`
+    TransactionData = 0,
+    /// Arbitrary bytes shown to the user (BCS `vector<u8>`)
+    PersonalMessage = 3,
+}
+
+/// Result of a Sui signing operation
+#[derive(Serialize, Deserialize)]
+pub struct SuiSigningResult {
+    /// Serialized signature `flag || signature || public key` (base64), as
+    /// passed to `sui_executeTransactionBlock`
+    pub signature: String,
+    /// The public key (base64, 32 bytes)
+    pub public_key: String,
+    /// Account address (0x-prefixed hex, 32 bytes)
+    pub address: String,
+    /// Transaction digest (base58), for transactions
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub digest: Option<String>,
+}
+
+/// Digest that is signed for `bytes` under `scope`
+///
+/// For personal messages `bytes` is the raw message; its BCS length prefix
+/// is added here.
+pub fn intent_digest(scope: IntentScope, bytes: &[u8]) -> [u8; 32] {
+    let mut hasher = Blake2b::<U32>::new();
+    // scope, version 0, app ID Sui
+    hasher.update([scope as u8, 0, 0]);
+    if scope == IntentScope::PersonalMessage {
+        hasher.update(uleb128(bytes.len()));
+    }
+    hasher.update(bytes);
+    hasher.finalize().into()
+}
+
+/// Digest explorers and RPCs identify a transaction by (base58)
+pub fn transaction_digest(transaction_data: &[u8]) -> String {
+    let mut hasher = Blake2b::<U32>::new();
+    hasher.update(b"TransactionData::");
+    hasher.update(transaction_data);
+    bs58::encode(hasher.finalize()).into_string()
+}
+
+fn uleb128(mut value: usize) -> Vec<u8> {
+    let mut out = Vec::new();
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+    out
+}
+
+/// Address of an Ed25519 public key
+pub fn sui_address(public_key: &[u8; 32]) -> String {
+    let mut hasher = Blake2b::<U32>::new();
+    hasher.update([ED25519_FLAG]);
+    hasher.update(public_key);
+    format!("0x{}", hex::encode(hasher.finalize()))
+}
+
+/// Sign intent-scoped bytes with a key in a secure buffer
+pub(crate) fn sign_sui_with_secure_key(
+    secure_key: &mut SecureBuffer,
+    scope: IntentScope,
+    bytes: &[u8],
+) -> Result<SuiSigningResult, SignerError> {
+    if secure_key.len() != 32 {
+        return Err(SignerError::InvalidKeyFormat(secure_key.len()));
+    }
+    health_check()?;
+
+    let signing_key = SigningKey::from_bytes(
+        secure_key
+            .as_slice()
+            .try_into()
+            .map_err(|_| SignerError::InvalidKeyFormat(secure_key.len()))?,
+    );
+    let public_key = signing_key.verifying_key().to_bytes();
+    let signature = signing_key.sign(&intent_digest(scope, bytes)).to_bytes();
+
+    let base64 = base64::engine::general_purpose::STANDARD;
+    let serialized = [&[ED25519_FLAG][..], &signature, &public_key].concat();
+    Ok(SuiSigningResult {
+        signature: base64::Engine::encode(&base64, serialized),
+        public_key: base64::Engine::encode(&base64, public_key),
+        address: sui_address(&public_key),
+        digest: (scope == IntentScope::TransactionData).then(|| transaction_digest(bytes)),
+    })
+}
+
+/// Decrypt a key container and sign a Sui transaction
+///
+/// Same security model as `decrypt_and_sign`, with the same Ed25519 key as
+/// Solana.
+///
+/// # Arguments
+/// * `container_json` - JSON-serialized EncryptedKeyContainer
+/// * `passphrase` - The passphrase for decryption
+/// * `transaction_data` - BCS-encoded `TransactionData` (the `txBytes` of
+///   the RPC), without an intent
+pub fn decrypt_and_sign_sui(
+    container_json: &str,
+    passphrase: &str,
+    transaction_data: &[u8],
+) -> Result<SuiSigningResult, SignerError> {
+    decrypt_and_sign_sui_intent(container_json, passphrase, IntentScope::TransactionData, transaction_data)
+}
+
+/// Decrypt a key container and sign bytes under an explicit intent scope
+pub fn decrypt_and_sign_sui_intent(
+    container_json: &str,
+    passphrase: &str,
+    scope: IntentScope,
+    bytes: &[u8],
+) -> Result<SuiSigningResult, SignerError> {
+    let container = EncryptedKeyContainer::from_json(container_json)?;
+
+    let mut secure_key = container.decrypt_key(passphrase)?;
+    let result = sign_sui_with_secure_key(&mut secure_key, scope, bytes);
+    secure_key.zeroize();
+
+    result
+}
+
+/// Sign a Sui transaction with a raw private key
+///
+/// # Security Warning
+/// Prefer using decrypt_and_sign_sui() for the full secure workflow.
+pub fn sign_sui(private_key: &[u8], transaction_data: &[u8]) -> Result<SuiSigningResult, SignerError> {
+    let mut secure_key = SecureBuffer::from_slice_with_mode(private_key, get_locking_mode())?;
+    let result = sign_sui_with_secure_key(&mut secure_key, IntentScope::TransactionData, transaction_data);
+    secure_key.zeroize();
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::Cipher;
+    use crate::kdf::KdfParams;
+    use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+
+    #[test]
+    fn test_sui_signature_and_address() {
+        std::env::set_var("SIGNER_ALLOW_INSECURE_MEMORY", "1");
+        let container =
+            EncryptedKeyContainer::encrypt_with_kdf(&[7u8; 32], "pw", Cipher::Aes256Gcm, KdfParams::MINIMUM).unwrap();
+        let json = container.to_json().unwrap();
+        let transaction_data = [0x22u8; 60];
+
+        let result = decrypt_and_sign_sui(&json, "pw", &transaction_data).unwrap();
+        assert_eq!(result.address, "0xa0ccc8bcc83f6c628340134f8546a21e0618fd1aaa02432bba454c4a2c2233da");
+        assert_eq!(result.digest, Some(transaction_digest(&transaction_data)));
+
+        let decode = |s: &str| base64::Engine::decode(&base64::engine::general_purpose::STANDARD, s).unwrap();
+        let serialized = decode(&result.signature);
+        assert_eq!((serialized.len(), serialized[0]), (97, ED25519_FLAG));
+        assert_eq!(serialized[65..], decode(&result.public_key));
+
+        let public_key = VerifyingKey::from_bytes(&serialized[65..].try_into().unwrap()).unwrap();
+        let signature = Signature::from_slice(&serialized[1..65]).unwrap();
+        let digest = intent_digest(IntentScope::TransactionData, &transaction_data);
+        assert!(public_key.verify(&digest, &signature).is_ok());
+
+        // The same bytes as a personal message sign differently
+        let message = decrypt_and_sign_sui_intent(&json, "pw", IntentScope::PersonalMessage, &transaction_data).unwrap();
+        assert_ne!(message.signature, result.signature);
+        assert!(message.digest.is_none());
+
+        assert!(decrypt_and_sign_sui(&json, "wrong", &transaction_data).is_err());
+    }
+}
@@ -0,0 +1,448 @@
+//! Zeroize unlocked sessions before suspend and hibernate
+//!
+//! `mlock` keeps decrypted keys out of swap, but not out of a
+//! suspend-to-disk image: hibernation writes all of RAM, locked pages
+//! included, to disk. Anything that keeps key material unlocked between
+//! signatures should register a hook here so it is wiped before the
+//! machine sleeps.
+//!
+//! - [`on_suspend`] registers a hook; dropping the returned [`SuspendHook`]
+//!   unregisters it.
+//! - [`SuspendLocked`] wraps a value (an unlock key, a decrypted seed, ...)
+//!   that is dropped, and so zeroized, on suspend. Later use fails with
+//!   [`SignerError::SessionLocked`].
+//! - [`SuspendMonitor`] listens for the platform's sleep notification and
+//!   runs the hooks before sleep proceeds:
+//!   - **Linux**: takes a systemd-logind *delay* inhibitor lock
+//!     (`systemd-inhibit --mode=delay`) and watches `PrepareForSleep`
+//!     (`dbus-monitor`); the lock is released once the hooks have run and
+//!     taken again on resume.
+//!   - **macOS**: IOKit system power notifications
+//!     (`IORegisterForSystemPower`); sleep is acknowledged with
+//!     `IOAllowPowerChange` after the hooks have run.
+//!
+//! [`run_suspend_hooks`] runs the hooks directly, for applications that
+//! receive power events some other way.
+
+use std::panic::AssertUnwindSafe;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::error::SignerError;
+
+type Hook = Arc<dyn Fn() + Send + Sync>;
+
+static HOOKS: Mutex<Vec<(u64, Hook)>> = Mutex::new(Vec::new());
+static NEXT_HOOK_ID: AtomicU64 = AtomicU64::new(0);
+static MONITOR_RUNNING: AtomicBool = AtomicBool::new(false);
+
+fn hooks() -> MutexGuard<'static, Vec<(u64, Hook)>> {
+    HOOKS.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Registration of a suspend hook; unregisters it when dropped
+#[must_use = "the hook is unregistered when this handle is dropped"]
+pub struct SuspendHook {
+    id: u64,
+}
+
+impl Drop for SuspendHook {
+    fn drop(&mut self) {
+        hooks().retain(|(id, _)| *id != self.id);
+    }
+}
+
+/// Run `hook` before the system suspends or hibernates
+pub fn on_suspend(hook: impl Fn() + Send + Sync + 'static) -> SuspendHook {
+    let id = NEXT_HOOK_ID.fetch_add(1, Ordering::Relaxed);
+    hooks().push((id, Arc::new(hook)));
+    SuspendHook { id }
+}
+
+/// Run every registered hook now
+///
+/// A panicking hook does not stop the others. Returns the number of hooks
+/// run.
+pub fn run_suspend_hooks() -> usize {
+    // Hooks may drop their own registration, so run them unlocked
+    let snapshot: Vec<Hook> = hooks().iter().map(|(_, hook)| Arc::clone(hook)).collect();
+    for hook in &snapshot {
+        let _ = std::panic::catch_unwind(AssertUnwindSafe(|| hook()));
+    }
+    snapshot.len()
+}
+
+/// A value that is dropped when the system suspends
+///
+/// Wrap types that zeroize on drop, such as [`SecureBuffer`](crate::SecureBuffer).
+pub struct SuspendLocked<T: Send + 'static> {
+    value: Arc<Mutex<Option<T>>>,
+    _hook: SuspendHook,
+}
+
+impl<T: Send + 'static> SuspendLocked<T> {
+    /// Hold `value` until the next suspend
+    pub fn new(value: T) -> Self {
+        let value = Arc::new(Mutex::new(Some(value)));
+        let weak = Arc::downgrade(&value);
+        let hook = on_suspend(move || {
+            if let Some(value) = weak.upgrade() {
+                lock_ignoring_poison(&value).take();
+            }
+        });
+        Self { value, _hook: hook }
+    }
+
+    /// Use the value, unless a suspend has dropped it
+    pub fn with<R>(&self, f: impl FnOnce(&mut T) -> R) -> Result<R, SignerError> {
+        lock_ignoring_poison(&self.value)
+            .as_mut()
+            .map(f)
+            .ok_or_else(|| SignerError::SessionLocked("locked by system suspend".to_string()))
+    }
+
+    /// Whether the value has been dropped
+    pub fn is_locked(&self) -> bool {
+        lock_ignoring_poison(&self.value).is_none()
+    }
+
+    /// Drop the value now
+    pub fn lock(&self) {
+        lock_ignoring_poison(&self.value).take();
+    }
+}
+
+fn lock_ignoring_poison<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Runs the suspend hooks on the platform's sleep notification
+///
+/// Only one monitor can run per process. It stops when dropped.
+pub struct SuspendMonitor {
+    _platform: platform::Monitor,
+}
+
+impl SuspendMonitor {
+    /// Start listening for sleep notifications on a background thread
+    pub fn start() -> Result<Self, SignerError> {
+        if MONITOR_RUNNING.swap(true, Ordering::SeqCst) {
+            return Err(SignerError::PlatformError("a suspend monitor is already running".to_string()));
+        }
+        match platform::Monitor::start() {
+            Ok(monitor) => Ok(Self { _platform: monitor }),
+            Err(e) => {
+                MONITOR_RUNNING.store(false, Ordering::SeqCst);
+                Err(e)
+            }
+        }
+    }
+}
+
+impl Drop for SuspendMonitor {
+    fn drop(&mut self) {
+        // The platform monitor is dropped (and stopped) after this runs
+        MONITOR_RUNNING.store(false, Ordering::SeqCst);
+    }
+}
+
+/// Track `dbus-monitor` output: returns the `PrepareForSleep` argument
+/// (true before sleep, false after resume) once its line is seen
+#[cfg(target_os = "linux")]
+fn prepare_for_sleep(line: &str, pending: &mut bool) -> Option<bool> {
+    if line.starts_with("signal ") {
+        *pending = line.contains("member=PrepareForSleep");
+        return None;
+    }
+    if !std::mem::take(pending) {
+        return None;
+    }
+    match line.trim() {
+        "boolean true" => Some(true),
+        "boolean false" => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::io::{BufRead, BufReader};
+    use std::process::{Child, Command, Stdio};
+    use std::thread::JoinHandle;
+
+    use crate::error::SignerError;
+
+    const MATCH_RULE: &str = "type='signal',sender='org.freedesktop.login1',\
+                              interface='org.freedesktop.login1.Manager',member='PrepareForSleep'";
+
+    pub(super) struct Monitor {
+        dbus_monitor: Child,
+        thread: Option<JoinHandle<()>>,
+    }
+
+    impl Monitor {
+        pub(super) fn start() -> Result<Self, SignerError> {
+            let inhibitor = take_inhibitor()?;
+            let mut dbus_monitor = match Command::new("dbus-monitor")
+                .args(["--system", MATCH_RULE])
+                .stdin(Stdio::null())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::null())
+                .spawn()
+            {
+                Ok(child) => child,
+                Err(e) => {
+                    release_inhibitor(inhibitor);
+                    return Err(SignerError::PlatformError(format!("failed to start dbus-monitor: {}", e)));
+                }
+            };
+            let stdout = dbus_monitor.stdout.take().expect("stdout is piped");
+
+            let thread = std::thread::spawn(move || {
+                let mut inhibitor = Some(inhibitor);
+                let mut pending = false;
+                for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                    match super::prepare_for_sleep(&line, &mut pending) {
+                        Some(true) => {
+                            super::run_suspend_hooks();
+                            if let Some(inhibitor) = inhibitor.take() {
+                                release_inhibitor(inhibitor);
+                            }
+                        }
+                        Some(false) if inhibitor.is_none() => inhibitor = take_inhibitor().ok(),
+                        _ => {}
+                    }
+                }
+                if let Some(inhibitor) = inhibitor {
+                    release_inhibitor(inhibitor);
+                }
+            });
+
+            Ok(Self {
+                dbus_monitor,
+                thread: Some(thread),
+            })
+        }
+    }
+
+    impl Drop for Monitor {
+        fn drop(&mut self) {
+            let _ = self.dbus_monitor.kill();
+            let _ = self.dbus_monitor.wait();
+            if let Some(thread) = self.thread.take() {
+                let _ = thread.join();
+            }
+        }
+    }
+
+    /// Hold a delay lock for as long as the child's stdin stays open
+    fn take_inhibitor() -> Result<Child, SignerError> {
+        Command::new("systemd-inhibit")
+            .args([
+                "--what=sleep",
+                "--mode=delay",
+                "--who=coldstar-signer",
+                "--why=Zeroize unlocked keys before sleep",
+                "cat",
+            ])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| SignerError::PlatformError(format!("failed to start systemd-inhibit: {}", e)))
+    }
+
+    fn release_inhibitor(mut inhibitor: Child) {
+        // Closing stdin ends `cat`, which ends systemd-inhibit and its lock
+        drop(inhibitor.stdin.take());
+        let _ = inhibitor.wait();
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use std::ffi::c_void;
+    use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+    use std::sync::{mpsc, Arc};
+    use std::thread::JoinHandle;
+
+    use crate::error::SignerError;
+
+    type IoConnect = u32;
+    type IoObject = u32;
+    type NotificationPort = *mut c_void;
+    type PowerCallback = extern "C" fn(*mut c_void, IoObject, u32, *mut c_void);
+
+    // iokit_common_msg(0x270) and iokit_common_msg(0x280)
+    const IO_MESSAGE_CAN_SYSTEM_SLEEP: u32 = 0xe000_0270;
+    const IO_MESSAGE_SYSTEM_WILL_SLEEP: u32 = 0xe000_0280;
+
+    #[link(name = "IOKit", kind = "framework")]
+    extern "C" {
+        fn IORegisterForSystemPower(
+            refcon: *mut c_void,
+            port: *mut NotificationPort,
+            callback: PowerCallback,
+            notifier: *mut IoObject,
+        ) -> IoConnect;
+        fn IODeregisterForSystemPower(notifier: *mut IoObject) -> i32;
+        fn IOAllowPowerChange(kernel_port: IoConnect, notification_id: isize) -> i32;
+        fn IOServiceClose(connect: IoConnect) -> i32;
+        fn IONotificationPortGetRunLoopSource(port: NotificationPort) -> *mut c_void;
+        fn IONotificationPortDestroy(port: NotificationPort);
+    }
+
+    #[link(name = "CoreFoundation", kind = "framework")]
+    extern "C" {
+        static kCFRunLoopDefaultMode: *const c_void;
+        fn CFRunLoopGetCurrent() -> *mut c_void;
+        fn CFRunLoopAddSource(run_loop: *mut c_void, source: *mut c_void, mode: *const c_void);
+        fn CFRunLoopRunInMode(mode: *const c_void, seconds: f64, return_after_source_handled: u8) -> i32;
+    }
+
+    /// Connection used to acknowledge power messages
+    static ROOT_PORT: AtomicU32 = AtomicU32::new(0);
+
+    extern "C" fn power_callback(_refcon: *mut c_void, _service: IoObject, message: u32, argument: *mut c_void) {
+        match message {
+            IO_MESSAGE_SYSTEM_WILL_SLEEP => {
+                super::run_suspend_hooks();
+                unsafe { IOAllowPowerChange(ROOT_PORT.load(Ordering::SeqCst), argument as isize) };
+            }
+            IO_MESSAGE_CAN_SYSTEM_SLEEP => unsafe {
+                IOAllowPowerChange(ROOT_PORT.load(Ordering::SeqCst), argument as isize);
+            },
+            _ => {}
+        }
+    }
+
+    pub(super) struct Monitor {
+        stop: Arc<AtomicBool>,
+        thread: Option<JoinHandle<()>>,
+    }
+
+    impl Monitor {
+        pub(super) fn start() -> Result<Self, SignerError> {
+            let stop = Arc::new(AtomicBool::new(false));
+            let (ready_tx, ready_rx) = mpsc::channel();
+
+            let thread_stop = Arc::clone(&stop);
+            let thread = std::thread::spawn(move || unsafe {
+                let mut port: NotificationPort = std::ptr::null_mut();
+                let mut notifier: IoObject = 0;
+                let root_port =
+                    IORegisterForSystemPower(std::ptr::null_mut(), &mut port, power_callback, &mut notifier);
+                if root_port == 0 {
+                    let _ = ready_tx.send(false);
+                    return;
+                }
+                ROOT_PORT.store(root_port, Ordering::SeqCst);
+                CFRunLoopAddSource(
+                    CFRunLoopGetCurrent(),
+                    IONotificationPortGetRunLoopSource(port),
+                    kCFRunLoopDefaultMode,
+                );
+                let _ = ready_tx.send(true);
+
+                // Poll the stop flag between one-second run loop slices
+                while !thread_stop.load(Ordering::SeqCst) {
+                    CFRunLoopRunInMode(kCFRunLoopDefaultMode, 1.0, 0);
+                }
+
+                IODeregisterForSystemPower(&mut notifier);
+                IOServiceClose(root_port);
+                IONotificationPortDestroy(port);
+                ROOT_PORT.store(0, Ordering::SeqCst);
+            });
+
+            if ready_rx.recv().unwrap_or(false) {
+                Ok(Self {
+                    stop,
+                    thread: Some(thread),
+                })
+            } else {
+                let _ = thread.join();
+                Err(SignerError::PlatformError(
+                    "IORegisterForSystemPower failed".to_string(),
+                ))
+            }
+        }
+    }
+
+    impl Drop for Monitor {
+        fn drop(&mut self) {
+            self.stop.store(true, Ordering::SeqCst);
+            if let Some(thread) = self.thread.take() {
+                let _ = thread.join();
+            }
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+mod platform {
+    use crate::error::SignerError;
+
+    pub(super) struct Monitor;
+
+    impl Monitor {
+        pub(super) fn start() -> Result<Self, SignerError> {
+            Err(SignerError::PlatformError(
+                "suspend notifications are not supported on this platform".to_string(),
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::get_locking_mode;
+    use crate::secure_buffer::SecureBuffer;
+
+    #[test]
+    fn test_suspend_hooks_drop_locked_values() {
+        std::env::set_var("SIGNER_ALLOW_INSECURE_MEMORY", "1");
+        let secret =
+            SuspendLocked::new(SecureBuffer::from_slice_with_mode(&[9u8; 32], get_locking_mode()).unwrap());
+        assert_eq!(secret.with(|key| key.as_slice()[0]).unwrap(), 9);
+
+        let calls = Arc::new(AtomicU64::new(0));
+        let counter = Arc::clone(&calls);
+        let hook = on_suspend(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+        let _panicking = on_suspend(|| panic!("hook failure"));
+
+        assert!(run_suspend_hooks() >= 3);
+        assert!(secret.is_locked());
+        assert!(matches!(secret.with(|_| ()), Err(SignerError::SessionLocked(_))));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // Unregistered hooks no longer run
+        drop(hook);
+        run_suspend_hooks();
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_prepare_for_sleep_parsing() {
+        let output = "signal time=1.0 sender=org.freedesktop.DBus -> destination=:1.9 serial=2 \
+                      path=/org/freedesktop/DBus; interface=org.freedesktop.DBus; member=NameAcquired\n   \
+                      string \":1.9\"\n\
+                      signal time=2.0 sender=:1.1 -> destination=(null destination) serial=7 \
+                      path=/org/freedesktop/login1; interface=org.freedesktop.login1.Manager; member=PrepareForSleep\n   \
+                      boolean true\n\
+                      signal time=3.0 sender=:1.1 -> destination=(null destination) serial=8 \
+                      path=/org/freedesktop/login1; interface=org.freedesktop.login1.Manager; member=PrepareForSleep\n   \
+                      boolean false\n   \
+                      boolean true\n";
+        let mut pending = false;
+        let events: Vec<bool> = output
+            .lines()
+            .filter_map(|line| prepare_for_sleep(line, &mut pending))
+            .collect();
+        assert_eq!(events, vec![true, false]);
+    }
+}
@@ -0,0 +1,111 @@
+//! A mutex for the process-wide state of the signing core
+//!
+//! With `std` this wraps `std::sync::Mutex`, ignoring poisoning: the state
+//! it guards (entropy source, page locker, hooks) stays consistent even if
+//! a holder panicked. Without `std` it is a spinlock, which is adequate for
+//! the short critical sections here on single-core secure elements and
+//! microcontrollers.
+
+use core::ops::{Deref, DerefMut};
+
+#[cfg(feature = "std")]
+pub(crate) struct Mutex<T: ?Sized>(std::sync::Mutex<T>);
+
+#[cfg(feature = "std")]
+pub(crate) struct MutexGuard<'a, T: ?Sized>(std::sync::MutexGuard<'a, T>);
+
+#[cfg(feature = "std")]
+impl<T> Mutex<T> {
+    pub(crate) const fn new(value: T) -> Self {
+        Self(std::sync::Mutex::new(value))
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T: ?Sized> Mutex<T> {
+    pub(crate) fn lock(&self) -> MutexGuard<'_, T> {
+        MutexGuard(self.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner()))
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T: ?Sized> Deref for MutexGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T: ?Sized> DerefMut for MutexGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+#[cfg(not(feature = "std"))]
+pub(crate) struct Mutex<T: ?Sized> {
+    locked: core::sync::atomic::AtomicBool,
+    value: core::cell::UnsafeCell<T>,
+}
+
+// SAFETY: `value` is only reached through a guard, and `locked` admits one
+// guard at a time
+#[cfg(not(feature = "std"))]
+unsafe impl<T: ?Sized + Send> Sync for Mutex<T> {}
+
+#[cfg(not(feature = "std"))]
+pub(crate) struct MutexGuard<'a, T: ?Sized> {
+    mutex: &'a Mutex<T>,
+}
+
+#[cfg(not(feature = "std"))]
+impl<T> Mutex<T> {
+    pub(crate) const fn new(value: T) -> Self {
+        Self {
+            locked: core::sync::atomic::AtomicBool::new(false),
+            value: core::cell::UnsafeCell::new(value),
+        }
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl<T: ?Sized> Mutex<T> {
+    pub(crate) fn lock(&self) -> MutexGuard<'_, T> {
+        use core::sync::atomic::Ordering;
+        while self
+            .locked
+            .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            core::hint::spin_loop();
+        }
+        MutexGuard { mutex: self }
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl<T: ?Sized> Deref for MutexGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // SAFETY: this guard holds the lock
+        unsafe { &*self.mutex.value.get() }
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl<T: ?Sized> DerefMut for MutexGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        // SAFETY: this guard holds the lock, and is borrowed mutably
+        unsafe { &mut *self.mutex.value.get() }
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl<T: ?Sized> Drop for MutexGuard<'_, T> {
+    fn drop(&mut self) {
+        self.mutex.locked.store(false, core::sync::atomic::Ordering::Release);
+    }
+}
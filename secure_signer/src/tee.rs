@@ -0,0 +1,335 @@
+//! Trusted execution environments (SGX, SEV-SNP) and remote attestation
+//!
+//! With feature `tee` (Linux) the signer can keep its keys inside a
+//! hardware-isolated enclave and prove it to a counterparty:
+//!
+//! - **SGX**: the signer binary runs unmodified inside an enclave under the
+//!   Gramine library OS, which exposes quote generation at
+//!   `/dev/attestation`.
+//! - **SEV-SNP**: the signer runs in a confidential VM, and the guest
+//!   kernel produces attestation reports through configfs
+//!   (`/sys/kernel/config/tsm/report`, Linux 6.7+).
+//!
+//! [`TeeBackend`] refuses to unlock a container anywhere else, so
+//! decryption and signing only happen in enclave memory.
+//! [`TeeBackend::attest`] returns an [`AttestationQuote`] whose report data
+//! is `SHA-512("coldstar-tee-v1" || ed25519_public_key || evm_address)`: the
+//! hardware vendor's signature over the quote then covers both the enclave
+//! measurement and the keys it holds.
+//!
+//! [`AttestationQuote::verify`] checks that binding and the measurement. It
+//! does not check the quote's signature; do that first with Intel's DCAP
+//! quote verification library or `snpguest verify`, against the vendor's
+//! certificate chain.
+
+use std::fs;
+use std::path::Path;
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha512};
+
+use crate::backend::{ContainerBackend, SignerBackend};
+use crate::crypto::{EVMSigningResult, EncryptedKeyContainer, NonceMode, SchnorrSigningResult, SigningResult};
+use crate::entropy::fill_random;
+use crate::error::SignerError;
+use crate::pubkey_cache::PublicKeyCache;
+
+/// Quote format version
+const QUOTE_VERSION: u8 = 1;
+
+/// Domain separator for the report data
+const REPORT_DATA_DOMAIN: &[u8] = b"coldstar-tee-v1";
+
+/// Gramine's attestation pseudo-filesystem
+const GRAMINE_ATTESTATION: &str = "/dev/attestation";
+
+/// The kernel's configfs-tsm report directory
+const TSM_REPORT: &str = "/sys/kernel/config/tsm/report";
+
+/// SGX quote: 48-byte header, then the report body
+const SGX_MRENCLAVE: core::ops::Range<usize> = 112..144;
+const SGX_REPORT_DATA: core::ops::Range<usize> = 368..432;
+
+/// SEV-SNP attestation report fields
+const SNP_REPORT_DATA: core::ops::Range<usize> = 0x50..0x90;
+const SNP_MEASUREMENT: core::ops::Range<usize> = 0x90..0xc0;
+const SNP_REPORT_SIZE: usize = 0x4a0;
+
+/// The kind of trusted execution environment
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum TeeKind {
+    /// An Intel SGX enclave (DCAP quote; the measurement is MRENCLAVE)
+    Sgx,
+    /// An AMD SEV-SNP guest (attestation report; the measurement is the
+    /// launch digest)
+    SevSnp,
+}
+
+/// The environment this process runs in, if it is an enclave
+pub fn detect() -> Option<TeeKind> {
+    let attestation_type = fs::read_to_string(Path::new(GRAMINE_ATTESTATION).join("attestation_type"));
+    if attestation_type.is_ok_and(|kind| kind.trim() == "dcap") {
+        return Some(TeeKind::Sgx);
+    }
+    if Path::new("/dev/sev-guest").exists() && Path::new(TSM_REPORT).is_dir() {
+        return Some(TeeKind::SevSnp);
+    }
+    None
+}
+
+/// Cross-checkable proof that a signer's keys live in an enclave
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct AttestationQuote {
+    /// Quote format version
+    pub version: u8,
+    /// Where the quote came from
+    pub tee: TeeKind,
+    /// The signer's Ed25519 public key (base58)
+    pub public_key: String,
+    /// The signer's EVM address
+    pub evm_address: String,
+    /// MRENCLAVE (SGX) or launch measurement (SEV-SNP), hex
+    pub measurement: String,
+    /// The SGX quote or SEV-SNP report (base64)
+    pub quote: String,
+}
+
+impl AttestationQuote {
+    /// Check that the quote binds `public_key` and `evm_address`, and that
+    /// its measurement is `expected_measurement` (hex) if given
+    ///
+    /// The quote's own signature is not checked here.
+    pub fn verify(&self, expected_measurement: Option<&str>) -> Result<(), SignerError> {
+        if self.version != QUOTE_VERSION {
+            return Err(SignerError::AttestationError(format!(
+                "unsupported quote version {}",
+                self.version
+            )));
+        }
+        let quote = BASE64.decode(&self.quote)?;
+        let (measurement, report_data) = parse_quote(self.tee, &quote)?;
+        if report_data != report_data_for(&self.public_key, &self.evm_address)? {
+            return Err(SignerError::AttestationError(
+                "quote does not bind this public key and address".to_string(),
+            ));
+        }
+        let measurement = hex::encode(measurement);
+        if measurement != self.measurement.to_ascii_lowercase() {
+            return Err(SignerError::AttestationError("measurement does not match the quote".to_string()));
+        }
+        match expected_measurement {
+            Some(expected) if !expected.eq_ignore_ascii_case(&measurement) => Err(SignerError::AttestationError(
+                format!("enclave measurement is {}, expected {}", measurement, expected),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Serialize the quote to JSON
+    pub fn to_json(&self) -> Result<String, SignerError> {
+        serde_json::to_string_pretty(self).map_err(|e| SignerError::SerializationError(e.to_string()))
+    }
+
+    /// Parse a quote from JSON (without verifying it)
+    pub fn from_json(json: &str) -> Result<Self, SignerError> {
+        serde_json::from_str(json).map_err(|e| SignerError::SerializationError(e.to_string()))
+    }
+}
+
+/// A [`ContainerBackend`] that only runs inside an enclave
+pub struct TeeBackend<'a> {
+    inner: ContainerBackend<'a>,
+    container: &'a EncryptedKeyContainer,
+    passphrase: &'a str,
+    tee: TeeKind,
+}
+
+impl<'a> TeeBackend<'a> {
+    /// Create a backend for a container and its passphrase, failing unless
+    /// this process is in an SGX enclave or SEV-SNP guest
+    pub fn new(container: &'a EncryptedKeyContainer, passphrase: &'a str) -> Result<Self, SignerError> {
+        let tee = detect().ok_or_else(|| {
+            SignerError::AttestationError("not running inside an SGX enclave or SEV-SNP guest".to_string())
+        })?;
+        Ok(Self {
+            inner: ContainerBackend::new(container, passphrase),
+            container,
+            passphrase,
+            tee,
+        })
+    }
+
+    /// The environment the backend runs in
+    pub fn tee(&self) -> TeeKind {
+        self.tee
+    }
+
+    /// Produce a quote binding this enclave's measurement to the
+    /// container's keys
+    pub fn attest(&self) -> Result<AttestationQuote, SignerError> {
+        let keys = PublicKeyCache::in_memory()
+            .get_or_compute(self.container, self.passphrase)?
+            .clone();
+        let report_data = report_data_for(&keys.solana_public_key, &keys.evm_address)?;
+        let quote = match self.tee {
+            TeeKind::Sgx => sgx_quote(&report_data)?,
+            TeeKind::SevSnp => snp_report(&report_data)?,
+        };
+        let (measurement, bound) = parse_quote(self.tee, &quote)?;
+        if bound != report_data {
+            return Err(SignerError::AttestationError(
+                "the enclave returned a quote for different report data".to_string(),
+            ));
+        }
+        Ok(AttestationQuote {
+            version: QUOTE_VERSION,
+            tee: self.tee,
+            public_key: keys.solana_public_key,
+            evm_address: keys.evm_address,
+            measurement: hex::encode(measurement),
+            quote: BASE64.encode(&quote),
+        })
+    }
+}
+
+impl SignerBackend for TeeBackend<'_> {
+    fn sign_solana(&self, message: &[u8]) -> Result<SigningResult, SignerError> {
+        self.inner.sign_solana(message)
+    }
+
+    fn sign_solana_message(&self, message: &[u8]) -> Result<SigningResult, SignerError> {
+        self.inner.sign_solana_message(message)
+    }
+
+    fn sign_evm_hash(&self, message_hash: &[u8]) -> Result<EVMSigningResult, SignerError> {
+        self.inner.sign_evm_hash(message_hash)
+    }
+
+    fn sign_evm_hash_with_nonce(&self, message_hash: &[u8], nonce: NonceMode) -> Result<EVMSigningResult, SignerError> {
+        self.inner.sign_evm_hash_with_nonce(message_hash, nonce)
+    }
+
+    fn sign_schnorr(&self, message: &[u8]) -> Result<SchnorrSigningResult, SignerError> {
+        self.inner.sign_schnorr(message)
+    }
+}
+
+/// `SHA-512(domain || ed25519 public key || EVM address)`
+fn report_data_for(public_key: &str, evm_address: &str) -> Result<[u8; 64], SignerError> {
+    let public_key: [u8; 32] = bs58::decode(public_key)
+        .into_vec()?
+        .try_into()
+        .map_err(|_| SignerError::AttestationError(format!("invalid public key {}", public_key)))?;
+    let mut address = [0u8; 20];
+    hex::decode_to_slice(evm_address.trim_start_matches("0x"), &mut address)
+        .map_err(|_| SignerError::AttestationError(format!("invalid EVM address {}", evm_address)))?;
+    Ok(Sha512::new()
+        .chain_update(REPORT_DATA_DOMAIN)
+        .chain_update(public_key)
+        .chain_update(address)
+        .finalize()
+        .into())
+}
+
+/// The measurement and report data of an SGX quote or SEV-SNP report
+fn parse_quote(tee: TeeKind, quote: &[u8]) -> Result<(&[u8], &[u8]), SignerError> {
+    let (measurement, report_data, min_len) = match tee {
+        TeeKind::Sgx => (SGX_MRENCLAVE, SGX_REPORT_DATA, SGX_REPORT_DATA.end),
+        TeeKind::SevSnp => (SNP_MEASUREMENT, SNP_REPORT_DATA, SNP_REPORT_SIZE),
+    };
+    if quote.len() < min_len {
+        return Err(SignerError::AttestationError(format!(
+            "quote is {} bytes, expected at least {}",
+            quote.len(),
+            min_len
+        )));
+    }
+    Ok((&quote[measurement], &quote[report_data]))
+}
+
+/// Ask Gramine for a DCAP quote over `report_data`
+fn sgx_quote(report_data: &[u8; 64]) -> Result<Vec<u8>, SignerError> {
+    let dir = Path::new(GRAMINE_ATTESTATION);
+    fs::write(dir.join("user_report_data"), report_data).map_err(attestation_error)?;
+    fs::read(dir.join("quote")).map_err(attestation_error)
+}
+
+/// Ask the guest kernel for an SEV-SNP report over `report_data`
+fn snp_report(report_data: &[u8; 64]) -> Result<Vec<u8>, SignerError> {
+    let mut name = [0u8; 8];
+    fill_random(&mut name)?;
+    let entry = Path::new(TSM_REPORT).join(format!("coldstar-{}", hex::encode(name)));
+    fs::create_dir(&entry).map_err(attestation_error)?;
+
+    let report = (|| {
+        fs::write(entry.join("inblob"), report_data)?;
+        let provider = fs::read_to_string(entry.join("provider"))?;
+        if provider.trim() != "sev_guest" {
+            return Err(std::io::Error::other(format!("unexpected report provider {}", provider.trim())));
+        }
+        fs::read(entry.join("outblob"))
+    })();
+    let _ = fs::remove_dir(&entry);
+    report.map_err(attestation_error)
+}
+
+fn attestation_error(e: std::io::Error) -> SignerError {
+    SignerError::AttestationError(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::Cipher;
+    use crate::kdf::KdfParams;
+
+    #[test]
+    fn test_quote_binds_keys_and_measurement() {
+        std::env::set_var("SIGNER_ALLOW_INSECURE_MEMORY", "1");
+        let container =
+            EncryptedKeyContainer::encrypt_with_kdf(&[4u8; 32], "pw", Cipher::Aes256Gcm, KdfParams::MINIMUM).unwrap();
+        let keys = PublicKeyCache::in_memory().get_or_compute(&container, "pw").unwrap().clone();
+        let report_data = report_data_for(&keys.solana_public_key, &keys.evm_address).unwrap();
+
+        // Synthetic reports with the fields at their documented offsets
+        for (tee, size, measurement, data) in [
+            (TeeKind::Sgx, 1024, SGX_MRENCLAVE, SGX_REPORT_DATA),
+            (TeeKind::SevSnp, SNP_REPORT_SIZE, SNP_MEASUREMENT, SNP_REPORT_DATA),
+        ] {
+            let mut report = vec![0u8; size];
+            report[measurement.clone()].fill(0xab);
+            report[data].copy_from_slice(&report_data);
+            let quote = AttestationQuote {
+                version: QUOTE_VERSION,
+                tee,
+                public_key: keys.solana_public_key.clone(),
+                evm_address: keys.evm_address.clone(),
+                measurement: hex::encode(&report[measurement]),
+                quote: BASE64.encode(&report),
+            };
+            let quote = AttestationQuote::from_json(&quote.to_json().unwrap()).unwrap();
+            quote.verify(None).unwrap();
+            quote.verify(Some(&quote.measurement.to_ascii_uppercase())).unwrap();
+            assert!(quote.verify(Some(&"00".repeat(quote.measurement.len() / 2))).is_err());
+
+            let mut other_key = quote.clone();
+            other_key.public_key = bs58::encode([9u8; 32]).into_string();
+            assert!(matches!(other_key.verify(None), Err(SignerError::AttestationError(_))));
+            let mut truncated = quote.clone();
+            truncated.quote = BASE64.encode(&report[..size / 4]);
+            assert!(truncated.verify(None).is_err());
+        }
+    }
+
+    #[test]
+    fn test_backend_refuses_outside_an_enclave() {
+        if detect().is_some() {
+            return;
+        }
+        let container =
+            EncryptedKeyContainer::encrypt_with_kdf(&[4u8; 32], "pw", Cipher::Aes256Gcm, KdfParams::MINIMUM).unwrap();
+        assert!(matches!(TeeBackend::new(&container, "pw"), Err(SignerError::AttestationError(_))));
+    }
+}
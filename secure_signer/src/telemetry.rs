@@ -0,0 +1,284 @@
+//! Structured tracing of unlock and signing (feature `tracing`)
+//!
+//! The signer is deliberately opaque, which leaves operators blind to
+//! slow unlocks and failing signatures. With this feature every container
+//! unlock and every signature through a [`ContainerBackend`] is a
+//! [`tracing`] span at `INFO` level:
+//!
+//! | Span | Fields |
+//! |------|--------|
+//! | `coldstar.unlock` | `container`, `kdf`, `cached`, `duration_ms`, `outcome` |
+//! | `coldstar.sign` | `container`, `chain`, `duration_ms`, `outcome` |
+//!
+//! `container` is the container's public fingerprint
+//! ([`container_id`](EncryptedKeyContainer::container_id)), `chain` is
+//! `solana`, `evm`, or `schnorr`, and `outcome` is `ok` or the error's
+//! [`kind`](SignerError::kind), never its message.
+//!
+//! # Redaction
+//!
+//! These spans carry nothing secret, but other code in the process may log
+//! carelessly. Wrap the subscriber in [`Redacted`] and any span or event
+//! with a field whose name suggests a secret or a signing preimage (see
+//! [`is_sensitive_field`]) is disabled at its callsite, so its values are
+//! never recorded or formatted:
+//!
+//! ```ignore
+//! let subscriber = Redacted::new(tracing_subscriber::fmt().json().finish());
+//! tracing::subscriber::set_global_default(subscriber)?;
+//! ```
+//!
+//! Redaction works on field names. Free text that interpolates a secret
+//! into an event's message cannot be recognized; this crate never does
+//! that.
+//!
+//! [`ContainerBackend`]: crate::backend::ContainerBackend
+
+use std::time::Instant;
+
+use tracing::field::Empty;
+use tracing::span::{Attributes, Id, Record};
+use tracing::subscriber::Interest;
+use tracing::{Event, Metadata, Span, Subscriber};
+
+use crate::crypto::EncryptedKeyContainer;
+use crate::error::SignerError;
+
+/// Word prefixes that make a field sensitive when they start a word of its
+/// name (words are separated by `_`, `.`, or `-`)
+pub const SENSITIVE_WORDS: &[&str] = &[
+    "passphrase",
+    "password",
+    "pin",
+    "secret",
+    "seed",
+    "mnemonic",
+    "private",
+    "key",
+    "plaintext",
+    "preimage",
+    "payload",
+    "transaction",
+    "tx",
+    "hash",
+    "digest",
+];
+
+/// Whether a field named `name` could carry key material, a passphrase,
+/// or a signature preimage
+///
+/// A name is split into words and lowercased, and any word starting with
+/// one of [`SENSITIVE_WORDS`] makes it sensitive: `unlock_key` and
+/// `txBytes` are not let through, and `public_key` neither, since a rule
+/// that only blocks some keys is one typo away from leaking one.
+pub fn is_sensitive_field(name: &str) -> bool {
+    let lower = name.to_ascii_lowercase();
+    lower
+        .split(['_', '.', '-'])
+        .any(|word| SENSITIVE_WORDS.iter().any(|sensitive| word.starts_with(sensitive)))
+}
+
+fn is_sensitive(metadata: &Metadata<'_>) -> bool {
+    metadata.fields().iter().any(|field| is_sensitive_field(field.name()))
+}
+
+/// A subscriber that drops every span and event with a sensitive field
+/// before the wrapped subscriber sees it
+#[derive(Debug)]
+pub struct Redacted<S> {
+    inner: S,
+}
+
+impl<S: Subscriber> Redacted<S> {
+    pub fn new(inner: S) -> Self {
+        Self { inner }
+    }
+
+    /// The wrapped subscriber
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+}
+
+impl<S: Subscriber> Subscriber for Redacted<S> {
+    fn register_callsite(&self, metadata: &'static Metadata<'static>) -> Interest {
+        if is_sensitive(metadata) {
+            return Interest::never();
+        }
+        self.inner.register_callsite(metadata)
+    }
+
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        !is_sensitive(metadata) && self.inner.enabled(metadata)
+    }
+
+    fn max_level_hint(&self) -> Option<tracing::level_filters::LevelFilter> {
+        self.inner.max_level_hint()
+    }
+
+    fn new_span(&self, span: &Attributes<'_>) -> Id {
+        self.inner.new_span(span)
+    }
+
+    fn record(&self, span: &Id, values: &Record<'_>) {
+        self.inner.record(span, values)
+    }
+
+    fn record_follows_from(&self, span: &Id, follows: &Id) {
+        self.inner.record_follows_from(span, follows)
+    }
+
+    fn event_enabled(&self, event: &Event<'_>) -> bool {
+        !is_sensitive(event.metadata()) && self.inner.event_enabled(event)
+    }
+
+    fn event(&self, event: &Event<'_>) {
+        self.inner.event(event)
+    }
+
+    fn enter(&self, span: &Id) {
+        self.inner.enter(span)
+    }
+
+    fn exit(&self, span: &Id) {
+        self.inner.exit(span)
+    }
+
+    fn clone_span(&self, id: &Id) -> Id {
+        self.inner.clone_span(id)
+    }
+
+    fn try_close(&self, id: Id) -> bool {
+        self.inner.try_close(id)
+    }
+
+    fn current_span(&self) -> tracing_core::span::Current {
+        self.inner.current_span()
+    }
+}
+
+/// The container's public fingerprint, or `unknown` without a public key
+fn fingerprint(container: &EncryptedKeyContainer) -> String {
+    container.container_id().unwrap_or_else(|_| "unknown".to_string())
+}
+
+/// Run `f` inside `span`, recording its duration and outcome
+fn timed<T>(span: Span, f: impl FnOnce() -> Result<T, SignerError>) -> Result<T, SignerError> {
+    let started = Instant::now();
+    let result = span.in_scope(f);
+    span.record("duration_ms", started.elapsed().as_millis() as u64);
+    match &result {
+        Ok(_) => span.record("outcome", "ok"),
+        Err(e) => span.record("outcome", e.kind()),
+    };
+    result
+}
+
+/// Trace an unlock of `container` (`cached`: the KDF was skipped)
+pub(crate) fn unlock<T>(
+    container: &EncryptedKeyContainer,
+    cached: bool,
+    f: impl FnOnce() -> Result<T, SignerError>,
+) -> Result<T, SignerError> {
+    let span = tracing::info_span!(
+        "coldstar.unlock",
+        container = %fingerprint(container),
+        kdf = container.kdf.name(),
+        cached,
+        duration_ms = Empty,
+        outcome = Empty,
+    );
+    timed(span, f)
+}
+
+/// Trace a signature by `container`'s key on `chain`
+pub(crate) fn sign<T>(
+    container: &EncryptedKeyContainer,
+    chain: &'static str,
+    f: impl FnOnce() -> Result<T, SignerError>,
+) -> Result<T, SignerError> {
+    let span = tracing::info_span!(
+        "coldstar.sign",
+        container = %fingerprint(container),
+        chain,
+        duration_ms = Empty,
+        outcome = Empty,
+    );
+    timed(span, f)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    use tracing::field::{Field, Visit};
+
+    use crate::backend::{ContainerBackend, SignerBackend};
+    use crate::crypto::Cipher;
+    use crate::kdf::KdfParams;
+
+    /// Records every span name and field it is shown
+    #[derive(Clone, Default)]
+    struct Recorder {
+        seen: Arc<Mutex<Vec<String>>>,
+        next_id: Arc<AtomicU64>,
+    }
+
+    impl Visit for Recorder {
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            self.seen.lock().unwrap().push(format!("{}={:?}", field.name(), value));
+        }
+    }
+
+    impl Subscriber for Recorder {
+        fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+            true
+        }
+        fn new_span(&self, span: &Attributes<'_>) -> Id {
+            self.seen.lock().unwrap().push(span.metadata().name().to_string());
+            span.record(&mut self.clone());
+            Id::from_u64(self.next_id.fetch_add(1, Ordering::Relaxed) + 1)
+        }
+        fn record(&self, _span: &Id, values: &Record<'_>) {
+            values.record(&mut self.clone());
+        }
+        fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+        fn event(&self, event: &Event<'_>) {
+            event.record(&mut self.clone());
+        }
+        fn enter(&self, _span: &Id) {}
+        fn exit(&self, _span: &Id) {}
+    }
+
+    #[test]
+    fn test_signing_is_traced_and_secrets_are_redacted() {
+        std::env::set_var("SIGNER_ALLOW_INSECURE_MEMORY", "1");
+        let container =
+            EncryptedKeyContainer::encrypt_with_kdf(&[9u8; 32], "pw", Cipher::Aes256Gcm, KdfParams::MINIMUM).unwrap();
+        let recorder = Recorder::default();
+        let subscriber = Redacted::new(recorder.clone());
+
+        tracing::subscriber::with_default(subscriber, || {
+            ContainerBackend::new(&container, "pw").sign_solana(&crate::crypto::test_transaction(&[9u8; 32])).unwrap();
+            assert!(ContainerBackend::new(&container, "wrong").sign_evm_hash(&[1u8; 32]).is_err());
+            tracing::info!(passphrase = "hunter2", "careless");
+            tracing::info!(unlock_key = ?[1u8; 32]);
+            tracing::info!(chain = "solana", "harmless");
+        });
+
+        let seen = recorder.seen.lock().unwrap().join("\n");
+        let id = container.container_id().unwrap();
+        assert!(seen.contains("coldstar.unlock") && seen.contains("coldstar.sign"), "{}", seen);
+        assert!(seen.contains(&format!("container={}", id)));
+        assert!(seen.contains("kdf=\"argon2id\"") && seen.contains("chain=\"solana\""));
+        assert!(seen.contains("outcome=\"ok\"") && seen.contains("outcome=\"DecryptionFailed\""));
+        assert!(seen.contains("duration_ms="));
+        assert!(!seen.contains("hunter2") && !seen.contains("careless") && !seen.contains("unlock_key"));
+        assert!(seen.contains("harmless"));
+
+        assert!(is_sensitive_field("txBytes") && is_sensitive_field("message_hash"));
+        assert!(!is_sensitive_field("container") && !is_sensitive_field("message"));
+    }
+}
@@ -0,0 +1,202 @@
+//! Tezos Ed25519 (tz1) signing
+//!
+//! Tezos signs the BLAKE2b-256 digest of the forged operation bytes behind
+//! a one-byte watermark that says what is being signed (`0x03` for manager
+//! operations such as transfers and contract calls), so a signature for
+//! one purpose cannot be replayed as another. The container's 32-byte key
+//! is the Ed25519 seed, as for Solana.
+//!
+//! Keys, addresses, and signatures are base58check-encoded with a prefix
+//! that yields the familiar `edpk`, `tz1`, and `edsig` strings.
+
+use blake2::digest::consts::{U20, U32};
+use blake2::{Blake2b, Digest};
+use ed25519_dalek::{Signer, SigningKey};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use crate::crypto::{get_locking_mode, EncryptedKeyContainer};
+use crate::entropy::health_check;
+use crate::error::SignerError;
+use crate::secure_buffer::SecureBuffer;
+
+/// Watermark of manager operations (transactions, originations, delegations, ...)
+pub const GENERIC_OPERATION_WATERMARK: u8 = 0x03;
+
+/// Watermark of packed Michelson data, used for off-chain signed messages
+pub const MICHELSON_DATA_WATERMARK: u8 = 0x05;
+
+/// base58check prefix of `tz1` addresses
+const TZ1_PREFIX: [u8; 3] = [6, 161, 159];
+
+/// base58check prefix of `edpk` public keys
+const EDPK_PREFIX: [u8; 4] = [13, 15, 37, 217];
+
+/// base58check prefix of `edsig` signatures
+const EDSIG_PREFIX: [u8; 5] = [9, 245, 205, 134, 18];
+
+/// Result of a Tezos signing operation
+#[derive(Serialize, Deserialize)]
+pub struct TezosSigningResult {
+    /// The signature (`edsig...`)
+    pub signature: String,
+    /// Forged operation followed by the raw signature (hex), ready to inject
+    pub signed_operation: String,
+    /// The public key (`edpk...`), needed to reveal a new account
+    pub public_key: String,
+    /// Account address (`tz1...`)
+    pub address: String,
+}
+
+/// Digest that is signed: BLAKE2b-256 of the watermark and the bytes
+pub fn operation_digest(watermark: u8, bytes: &[u8]) -> [u8; 32] {
+    let mut hasher = Blake2b::<U32>::new();
+    hasher.update([watermark]);
+    hasher.update(bytes);
+    hasher.finalize().into()
+}
+
+/// Encode with a prefix and a 4-byte double-SHA-256 checksum
+fn base58check(prefix: &[u8], payload: &[u8]) -> String {
+    let mut data = [prefix, payload].concat();
+    let checksum = Sha256::digest(Sha256::digest(&data));
+    data.extend_from_slice(&checksum[..4]);
+    bs58::encode(data).into_string()
+}
+
+/// `tz1` address of an Ed25519 public key
+pub fn tz1_address(public_key: &[u8; 32]) -> String {
+    base58check(&TZ1_PREFIX, &Blake2b::<U20>::digest(public_key))
+}
+
+/// `edpk` encoding of an Ed25519 public key
+pub fn edpk(public_key: &[u8; 32]) -> String {
+    base58check(&EDPK_PREFIX, public_key)
+}
+
+/// Sign watermarked bytes with a key in a secure buffer
+pub(crate) fn sign_tezos_with_secure_key(
+    secure_key: &mut SecureBuffer,
+    watermark: u8,
+    bytes: &[u8],
+) -> Result<TezosSigningResult, SignerError> {
+    if secure_key.len() != 32 {
+        return Err(SignerError::InvalidKeyFormat(secure_key.len()));
+    }
+    health_check()?;
+
+    let signing_key = SigningKey::from_bytes(
+        secure_key
+            .as_slice()
+            .try_into()
+            .map_err(|_| SignerError::InvalidKeyFormat(secure_key.len()))?,
+    );
+    let public_key = signing_key.verifying_key().to_bytes();
+    let signature = signing_key.sign(&operation_digest(watermark, bytes)).to_bytes();
+
+    Ok(TezosSigningResult {
+        signature: base58check(&EDSIG_PREFIX, &signature),
+        signed_operation: hex::encode([bytes, &signature].concat()),
+        public_key: edpk(&public_key),
+        address: tz1_address(&public_key),
+    })
+}
+
+/// Decrypt a key container and sign a forged Tezos operation
+///
+/// Same security model as `decrypt_and_sign`, with the same Ed25519 key as
+/// Solana. The operation is signed under [`GENERIC_OPERATION_WATERMARK`].
+///
+/// # Arguments
+/// * `container_json` - JSON-serialized EncryptedKeyContainer
+/// * `passphrase` - The passphrase for decryption
+/// * `forged_operation` - Forged operation bytes (branch and contents),
+///   without a watermark
+pub fn decrypt_and_sign_tezos(
+    container_json: &str,
+    passphrase: &str,
+    forged_operation: &[u8],
+) -> Result<TezosSigningResult, SignerError> {
+    decrypt_and_sign_tezos_with_watermark(container_json, passphrase, GENERIC_OPERATION_WATERMARK, forged_operation)
+}
+
+/// Decrypt a key container and sign bytes under an explicit watermark
+///
+/// Use [`MICHELSON_DATA_WATERMARK`] for packed Michelson data such as
+/// off-chain permits.
+pub fn decrypt_and_sign_tezos_with_watermark(
+    container_json: &str,
+    passphrase: &str,
+    watermark: u8,
+    bytes: &[u8],
+) -> Result<TezosSigningResult, SignerError> {
+    let container = EncryptedKeyContainer::from_json(container_json)?;
+
+    let mut secure_key = container.decrypt_key(passphrase)?;
+    let result = sign_tezos_with_secure_key(&mut secure_key, watermark, bytes);
+    secure_key.zeroize();
+
+    result
+}
+
+/// Sign a forged Tezos operation with a raw private key
+///
+/// # Security Warning
+/// Prefer using decrypt_and_sign_tezos() for the full secure workflow.
+pub fn sign_tezos(private_key: &[u8], forged_operation: &[u8]) -> Result<TezosSigningResult, SignerError> {
+    let mut secure_key = SecureBuffer::from_slice_with_mode(private_key, get_locking_mode())?;
+    let result = sign_tezos_with_secure_key(&mut secure_key, GENERIC_OPERATION_WATERMARK, forged_operation);
+    secure_key.zeroize();
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::Cipher;
+    use crate::kdf::KdfParams;
+    use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+
+    /// Seed of the sandbox `bootstrap1` account
+    const BOOTSTRAP1_EDSK: &str = "edsk3gUfUPyBSfrS9CCgmCiQsTCHGkviBDusMxDJstFtojtc1zcpsh";
+
+    fn decode(value: &str, prefix_len: usize) -> Vec<u8> {
+        let data = bs58::decode(value).into_vec().unwrap();
+        let (body, checksum) = data.split_at(data.len() - 4);
+        assert_eq!(&Sha256::digest(Sha256::digest(body))[..4], checksum);
+        body[prefix_len..].to_vec()
+    }
+
+    #[test]
+    fn test_sandbox_account_encodings() {
+        let seed = decode(BOOTSTRAP1_EDSK, 4);
+        let public_key = SigningKey::from_bytes(&seed.try_into().unwrap()).verifying_key().to_bytes();
+        assert_eq!(edpk(&public_key), "edpkuBknW28nW72KG6RoHtYW7p12T6GKc7nAbwYX5m8Wd9sDVC9yav");
+        assert_eq!(tz1_address(&public_key), "tz1KqTpEZ7Yob7QbPE4Hy4Wo8fHG8LhKxZSx");
+    }
+
+    #[test]
+    fn test_tezos_signature_verifies() {
+        std::env::set_var("SIGNER_ALLOW_INSECURE_MEMORY", "1");
+        let container =
+            EncryptedKeyContainer::encrypt_with_kdf(&[7u8; 32], "pw", Cipher::Aes256Gcm, KdfParams::MINIMUM).unwrap();
+        let json = container.to_json().unwrap();
+        let operation = [0xaau8; 48];
+
+        let result = decrypt_and_sign_tezos(&json, "pw", &operation).unwrap();
+        assert!(result.signature.starts_with("edsig"));
+        assert!(result.address.starts_with("tz1"));
+
+        let public_key = VerifyingKey::from_bytes(&decode(&result.public_key, 4).try_into().unwrap()).unwrap();
+        let signature = Signature::from_slice(&decode(&result.signature, 5)).unwrap();
+        let digest = operation_digest(GENERIC_OPERATION_WATERMARK, &operation);
+        assert!(public_key.verify(&digest, &signature).is_ok());
+        assert_eq!(result.signed_operation, hex::encode([&operation[..], &signature.to_bytes()].concat()));
+
+        // The watermark is part of what is signed
+        let data = decrypt_and_sign_tezos_with_watermark(&json, "pw", MICHELSON_DATA_WATERMARK, &operation).unwrap();
+        assert_ne!(data.signature, result.signature);
+
+        assert!(decrypt_and_sign_tezos(&json, "wrong", &operation).is_err());
+    }
+}
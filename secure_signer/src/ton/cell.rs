@@ -0,0 +1,355 @@
+//! TON cells and bag-of-cells serialization
+//!
+//! Only ordinary (non-exotic) cells are supported: wallet code, state init,
+//! and external message bodies never contain pruned branches or library
+//! references.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use sha2::{Digest, Sha256};
+
+use crate::error::SignerError;
+use crate::reader::ByteReader;
+
+/// Maximum number of data bits in a cell
+pub const MAX_BITS: usize = 1023;
+
+/// Maximum number of references from a cell
+pub const MAX_REFS: usize = 4;
+
+const BOC_MAGIC: [u8; 4] = [0xb5, 0xee, 0x9c, 0x72];
+
+fn cell_error(msg: impl Into<String>) -> SignerError {
+    SignerError::InvalidTransaction(format!("Invalid TON cell: {}", msg.into()))
+}
+
+/// An ordinary TON cell: up to 1023 bits and up to 4 references
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cell {
+    data: Vec<u8>,
+    bit_len: usize,
+    refs: Vec<Arc<Cell>>,
+    hash: [u8; 32],
+    depth: u16,
+}
+
+impl Cell {
+    /// Create a cell from its first `bit_len` bits of `data` and its references
+    pub fn new(data: &[u8], bit_len: usize, refs: Vec<Arc<Cell>>) -> Result<Self, SignerError> {
+        if bit_len > MAX_BITS {
+            return Err(cell_error(format!("{} bits exceeds {}", bit_len, MAX_BITS)));
+        }
+        if refs.len() > MAX_REFS {
+            return Err(cell_error(format!("{} references exceeds {}", refs.len(), MAX_REFS)));
+        }
+        if data.len() * 8 < bit_len {
+            return Err(cell_error(format!("{} bytes cannot hold {} bits", data.len(), bit_len)));
+        }
+
+        let mut data = data[..bit_len.div_ceil(8)].to_vec();
+        if !bit_len.is_multiple_of(8) {
+            // Clear bits past the end, so equal cells compare equal
+            let last = data.len() - 1;
+            data[last] &= 0xffu8 << (8 - bit_len % 8);
+        }
+
+        let depth = refs.iter().map(|r| r.depth + 1).max().unwrap_or(0);
+        let mut cell = Self {
+            data,
+            bit_len,
+            refs,
+            hash: [0; 32],
+            depth,
+        };
+        let mut hasher = Sha256::new();
+        hasher.update(cell.descriptors());
+        hasher.update(cell.padded_data());
+        for r in &cell.refs {
+            hasher.update(r.depth.to_be_bytes());
+        }
+        for r in &cell.refs {
+            hasher.update(r.hash);
+        }
+        cell.hash = hasher.finalize().into();
+        Ok(cell)
+    }
+
+    /// The cell's data, with unused bits of the last byte cleared
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Number of data bits
+    pub fn bit_len(&self) -> usize {
+        self.bit_len
+    }
+
+    /// Referenced cells
+    pub fn refs(&self) -> &[Arc<Cell>] {
+        &self.refs
+    }
+
+    /// Representation hash, which is what TON signs and addresses derive from
+    pub fn hash(&self) -> [u8; 32] {
+        self.hash
+    }
+
+    /// Maximum depth of the cell's reference tree
+    pub fn depth(&self) -> u16 {
+        self.depth
+    }
+
+    fn descriptors(&self) -> [u8; 2] {
+        [self.refs.len() as u8, (self.bit_len / 8 + self.bit_len.div_ceil(8)) as u8]
+    }
+
+    /// Data with the completion tag: a 1 bit after the last data bit
+    fn padded_data(&self) -> Vec<u8> {
+        let mut data = self.data.clone();
+        if !self.bit_len.is_multiple_of(8) {
+            let last = data.len() - 1;
+            data[last] |= 0x80 >> (self.bit_len % 8);
+        }
+        data
+    }
+
+    /// Parse a bag of cells with a single root
+    pub fn from_boc(boc: &[u8]) -> Result<Arc<Cell>, SignerError> {
+        let mut r = ByteReader::new(boc, "TON bag of cells");
+        if r.take(4)? != BOC_MAGIC {
+            return Err(r.invalid("bad bag-of-cells magic").into());
+        }
+        let flags = r.u8()?;
+        let has_index = flags & 0x80 != 0;
+        let has_crc = flags & 0x40 != 0;
+        let ref_size = (flags & 0x07) as usize;
+        let offset_size = r.u8()? as usize;
+        if ref_size == 0 || ref_size > 4 || offset_size == 0 || offset_size > 8 {
+            return Err(r.invalid("bad bag-of-cells header").into());
+        }
+
+        let cell_count = r.uint_be(ref_size)?;
+        let root_count = r.uint_be(ref_size)?;
+        let _absent = r.uint_be(ref_size)?;
+        let _total_size = r.uint_be(offset_size)?;
+        if root_count != 1 {
+            return Err(r.invalid(format!("expected one root, found {}", root_count)).into());
+        }
+        let root = r.uint_be(ref_size)?;
+        if has_index {
+            let index_size = r.count(cell_count, offset_size)? * offset_size;
+            r.take(index_size)?;
+        }
+
+        // Cells only reference later cells, so build them back to front
+        let cell_count = r.count(cell_count, 2)?;
+        let mut raw = Vec::with_capacity(cell_count);
+        for _ in 0..cell_count {
+            let [d1, d2] = [r.u8()?, r.u8()?];
+            if d1 & 0x08 != 0 {
+                return Err(r.invalid("exotic cells are not supported").into());
+            }
+            let ref_count = (d1 & 0x07) as usize;
+            let data = r.take(d2.div_ceil(2) as usize)?;
+            let bit_len = if d2.is_multiple_of(2) {
+                data.len() * 8
+            } else {
+                let last = *data.last().ok_or_else(|| cell_error("missing data"))?;
+                if last == 0 {
+                    return Err(r.invalid("missing completion tag").into());
+                }
+                data.len() * 8 - 1 - last.trailing_zeros() as usize
+            };
+            let refs = (0..ref_count).map(|_| r.uint_be(ref_size)).collect::<Result<Vec<_>, _>>()?;
+            raw.push((data, bit_len, refs));
+        }
+
+        if has_crc {
+            let end = r.position();
+            let crc = r.u32_le()?;
+            if crc32c(&boc[..end]) != crc {
+                return Err(cell_error("bag-of-cells checksum mismatch"));
+            }
+        }
+
+        let mut cells: Vec<Option<Arc<Cell>>> = vec![None; raw.len()];
+        for (i, (data, bit_len, refs)) in raw.into_iter().enumerate().rev() {
+            let refs = refs
+                .into_iter()
+                .map(|j| {
+                    let j = usize::try_from(j).unwrap_or(usize::MAX);
+                    if j <= i {
+                        return Err(cell_error("reference to an earlier cell"));
+                    }
+                    cells.get(j).cloned().flatten().ok_or_else(|| cell_error("reference out of range"))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            cells[i] = Some(Arc::new(Cell::new(data, bit_len, refs)?));
+        }
+        cells.get(usize::try_from(root).unwrap_or(usize::MAX)).cloned().flatten().ok_or_else(|| cell_error("root out of range"))
+    }
+
+    /// Parse a base64 bag of cells
+    pub fn from_boc_base64(boc: &str) -> Result<Arc<Cell>, SignerError> {
+        let bytes = base64::Engine::decode(&base64::engine::general_purpose::STANDARD, boc.trim())
+            .map_err(|e| cell_error(format!("invalid base64: {}", e)))?;
+        Self::from_boc(&bytes)
+    }
+
+    /// Serialize as a bag of cells with a CRC32C, as wallets and APIs expect
+    pub fn to_boc(&self) -> Vec<u8> {
+        // Parents before children, each distinct cell once
+        let mut order: Vec<&Cell> = Vec::new();
+        let mut visited = HashSet::new();
+        fn visit<'a>(cell: &'a Cell, visited: &mut HashSet<[u8; 32]>, order: &mut Vec<&'a Cell>) {
+            if !visited.insert(cell.hash) {
+                return;
+            }
+            for r in &cell.refs {
+                visit(r, visited, order);
+            }
+            order.push(cell);
+        }
+        visit(self, &mut visited, &mut order);
+        order.reverse();
+        let index: HashMap<[u8; 32], usize> = order.iter().enumerate().map(|(i, c)| (c.hash, i)).collect();
+
+        let ref_size = byte_len(order.len() as u64);
+        let mut cells = Vec::new();
+        for cell in &order {
+            cells.extend_from_slice(&cell.descriptors());
+            cells.extend_from_slice(&cell.padded_data());
+            for r in &cell.refs {
+                cells.extend_from_slice(&index[&r.hash].to_be_bytes()[8 - ref_size..]);
+            }
+        }
+        let offset_size = byte_len(cells.len() as u64);
+
+        let mut out = BOC_MAGIC.to_vec();
+        out.push(0x40 | ref_size as u8);
+        out.push(offset_size as u8);
+        for value in [order.len(), 1, 0] {
+            out.extend_from_slice(&value.to_be_bytes()[8 - ref_size..]);
+        }
+        out.extend_from_slice(&cells.len().to_be_bytes()[8 - offset_size..]);
+        out.extend_from_slice(&0usize.to_be_bytes()[8 - ref_size..]);
+        out.extend_from_slice(&cells);
+        let crc = crc32c(&out);
+        out.extend_from_slice(&crc.to_le_bytes());
+        out
+    }
+
+    /// Serialize as a base64 bag of cells
+    pub fn to_boc_base64(&self) -> String {
+        base64::Engine::encode(&base64::engine::general_purpose::STANDARD, self.to_boc())
+    }
+}
+
+fn byte_len(value: u64) -> usize {
+    (8 - value.leading_zeros() as usize / 8).max(1)
+}
+
+/// Bit-level cell builder
+#[derive(Default)]
+pub struct CellBuilder {
+    data: Vec<u8>,
+    bit_len: usize,
+    refs: Vec<Arc<Cell>>,
+}
+
+impl CellBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append one bit
+    pub fn bit(&mut self, bit: bool) -> &mut Self {
+        if self.bit_len.is_multiple_of(8) {
+            self.data.push(0);
+        }
+        if bit {
+            let last = self.data.len() - 1;
+            self.data[last] |= 0x80 >> (self.bit_len % 8);
+        }
+        self.bit_len += 1;
+        self
+    }
+
+    /// Append the low `bits` bits of `value`, most significant first
+    pub fn uint(&mut self, bits: usize, value: u64) -> &mut Self {
+        for i in (0..bits).rev() {
+            self.bit(i < 64 && (value >> i) & 1 == 1);
+        }
+        self
+    }
+
+    /// Append the first `bit_len` bits of `data`
+    pub fn bits(&mut self, data: &[u8], bit_len: usize) -> &mut Self {
+        for i in 0..bit_len {
+            self.bit(data[i / 8] & (0x80 >> (i % 8)) != 0);
+        }
+        self
+    }
+
+    /// Append whole bytes
+    pub fn bytes(&mut self, data: &[u8]) -> &mut Self {
+        self.bits(data, data.len() * 8)
+    }
+
+    /// Append a cell's bits and references
+    pub fn cell(&mut self, cell: &Cell) -> &mut Self {
+        self.bits(&cell.data, cell.bit_len);
+        self.refs.extend(cell.refs.iter().cloned());
+        self
+    }
+
+    /// Add a reference
+    pub fn reference(&mut self, cell: Arc<Cell>) -> &mut Self {
+        self.refs.push(cell);
+        self
+    }
+
+    pub fn build(&self) -> Result<Cell, SignerError> {
+        Cell::new(&self.data, self.bit_len, self.refs.clone())
+    }
+}
+
+/// CRC-32C (Castagnoli), as used by bags of cells
+fn crc32c(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0x82f6_3b78 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cell_hash_and_boc_round_trip() {
+        let mut builder = CellBuilder::new();
+        builder.uint(32, 0xdead_beef).uint(3, 5);
+        let cell = builder.build().unwrap();
+        assert_eq!(cell.bit_len(), 35);
+        assert_eq!(
+            hex::encode(cell.hash()),
+            "ec1def1dc4e6d1982ca42f1c04f5d61889700e4c409356d63f252dbe3db0ebcb"
+        );
+
+        // Byte-identical to other TON implementations
+        let boc = cell.to_boc();
+        assert_eq!(hex::encode(&boc), "b5ee9c72410101010007000009deadbeefb06b157932");
+        assert_eq!(*Cell::from_boc(&boc).unwrap(), cell);
+
+        let mut corrupted = boc.clone();
+        corrupted[12] ^= 1;
+        assert!(Cell::from_boc(&corrupted).is_err());
+        assert!(Cell::from_boc(&boc[..boc.len() - 1]).is_err());
+    }
+}
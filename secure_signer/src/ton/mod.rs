@@ -0,0 +1,285 @@
+//! TON signing and wallet addresses
+//!
+//! TON uses Ed25519 like Solana, with the same 32-byte seed, but signs the
+//! representation hash of a cell instead of raw message bytes. A wallet's
+//! address is not derived from the public key directly: it is the hash of
+//! the wallet contract's initial state (code plus a data cell holding the
+//! key), so the same key has a different address for each wallet version.
+//!
+//! Supported wallet contracts are v4r2 and v5r1 (W5), with their standard
+//! mainnet wallet IDs.
+
+pub mod cell;
+
+use std::sync::Arc;
+
+use ed25519_dalek::{Signer, SigningKey};
+use serde::{Deserialize, Serialize};
+
+use crate::crypto::{get_locking_mode, EncryptedKeyContainer};
+use crate::encoding::crc16_xmodem;
+use crate::entropy::health_check;
+use crate::error::SignerError;
+use crate::secure_buffer::SecureBuffer;
+
+pub use cell::{Cell, CellBuilder};
+
+/// Code of the v4r2 wallet contract (base64 bag of cells)
+const WALLET_V4R2_CODE: &str = include_str!("wallet_v4r2.code");
+
+/// Code of the v5r1 (W5) wallet contract (base64 bag of cells)
+const WALLET_V5R1_CODE: &str = include_str!("wallet_v5r1.code");
+
+/// Default subwallet ID of v3 and v4 wallets
+pub const DEFAULT_WALLET_ID_V4: u32 = 698_983_191;
+
+/// Wallet ID of a v5r1 wallet on the mainnet basechain
+pub const DEFAULT_WALLET_ID_V5R1: u32 = 0x7fff_ff11;
+
+/// Workchain of ordinary accounts
+pub const BASECHAIN: i8 = 0;
+
+/// Wallet contract a key is used with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WalletVersion {
+    V4R2,
+    V5R1,
+}
+
+impl WalletVersion {
+    fn code(self) -> Result<Arc<Cell>, SignerError> {
+        Cell::from_boc_base64(match self {
+            WalletVersion::V4R2 => WALLET_V4R2_CODE,
+            WalletVersion::V5R1 => WALLET_V5R1_CODE,
+        })
+    }
+
+    /// Initial data cell of the wallet contract for `public_key`
+    fn data(self, public_key: &[u8; 32]) -> Result<Cell, SignerError> {
+        let mut data = CellBuilder::new();
+        match self {
+            WalletVersion::V4R2 => {
+                // seqno, subwallet ID, public key, empty plugin dictionary
+                data.uint(32, 0).uint(32, DEFAULT_WALLET_ID_V4 as u64).bytes(public_key).bit(false);
+            }
+            WalletVersion::V5R1 => {
+                // signature allowed, seqno, wallet ID, public key, empty extension dictionary
+                data.bit(true)
+                    .uint(32, 0)
+                    .uint(32, DEFAULT_WALLET_ID_V5R1 as u64)
+                    .bytes(public_key)
+                    .bit(false);
+            }
+        }
+        data.build()
+    }
+
+    /// Wrap a signature around an external message body
+    ///
+    /// v4 puts the signature first, v5 after the body.
+    pub fn signed_body(self, body: &Cell, signature: &[u8; 64]) -> Result<Cell, SignerError> {
+        let mut signed = CellBuilder::new();
+        match self {
+            WalletVersion::V4R2 => signed.bytes(signature).cell(body),
+            WalletVersion::V5R1 => signed.cell(body).bytes(signature),
+        };
+        signed.build()
+    }
+}
+
+/// An account address: workchain and account ID
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TonAddress {
+    pub workchain: i8,
+    pub hash: [u8; 32],
+}
+
+impl TonAddress {
+    /// Raw form, `0:<hex>`
+    pub fn to_raw(&self) -> String {
+        format!("{}:{}", self.workchain, hex::encode(self.hash))
+    }
+
+    /// User-friendly base64url form
+    ///
+    /// Wallets show their own addresses non-bounceable (`UQ...` on
+    /// mainnet), since an undeployed wallet cannot bounce a transfer.
+    pub fn to_user_friendly(&self, bounceable: bool, testnet: bool) -> String {
+        let mut tag = if bounceable { 0x11 } else { 0x51 };
+        if testnet {
+            tag |= 0x80;
+        }
+        let mut bytes = Vec::with_capacity(36);
+        bytes.push(tag);
+        bytes.push(self.workchain as u8);
+        bytes.extend_from_slice(&self.hash);
+        let crc = crc16_xmodem(&bytes);
+        bytes.extend_from_slice(&crc.to_be_bytes());
+        base64::Engine::encode(&base64::engine::general_purpose::URL_SAFE_NO_PAD, bytes)
+    }
+}
+
+/// Address of the `version` wallet contract owned by `public_key`
+pub fn wallet_address(public_key: &[u8; 32], version: WalletVersion, workchain: i8) -> Result<TonAddress, SignerError> {
+    // StateInit: no split depth, not special, code, data, no library
+    let mut state_init = CellBuilder::new();
+    state_init
+        .uint(5, 0b00110)
+        .reference(version.code()?)
+        .reference(Arc::new(version.data(public_key)?));
+    Ok(TonAddress {
+        workchain,
+        hash: state_init.build()?.hash(),
+    })
+}
+
+/// Result of a TON signing operation
+#[derive(Serialize, Deserialize)]
+pub struct TonSigningResult {
+    /// The signature over the body's cell hash (hex, 64 bytes)
+    pub signature: String,
+    /// The signed external message body (base64 bag of cells)
+    pub signed_body: String,
+    /// The Ed25519 public key (hex, 32 bytes)
+    pub public_key: String,
+    /// Non-bounceable mainnet address of the wallet contract
+    pub address: String,
+    /// Raw address of the wallet contract, `0:<hex>`
+    pub raw_address: String,
+}
+
+/// Sign an external message body with a key in a secure buffer
+pub(crate) fn sign_ton_with_secure_key(
+    secure_key: &mut SecureBuffer,
+    body: &Cell,
+    version: WalletVersion,
+) -> Result<TonSigningResult, SignerError> {
+    if secure_key.len() != 32 {
+        return Err(SignerError::InvalidKeyFormat(secure_key.len()));
+    }
+    health_check()?;
+
+    let signing_key = SigningKey::from_bytes(
+        secure_key
+            .as_slice()
+            .try_into()
+            .map_err(|_| SignerError::InvalidKeyFormat(secure_key.len()))?,
+    );
+    let public_key = signing_key.verifying_key().to_bytes();
+    let signature = signing_key.sign(&body.hash()).to_bytes();
+
+    let signed_body = version.signed_body(body, &signature)?;
+    let address = wallet_address(&public_key, version, BASECHAIN)?;
+    Ok(TonSigningResult {
+        signature: hex::encode(signature),
+        signed_body: signed_body.to_boc_base64(),
+        public_key: hex::encode(public_key),
+        address: address.to_user_friendly(false, false),
+        raw_address: address.to_raw(),
+    })
+}
+
+/// Decrypt a key container and sign a TON wallet message
+///
+/// Same security model as `decrypt_and_sign`, with the same Ed25519 key as
+/// Solana.
+///
+/// # Arguments
+/// * `container_json` - JSON-serialized EncryptedKeyContainer
+/// * `passphrase` - The passphrase for decryption
+/// * `body_boc` - The unsigned external message body as a bag of cells
+///   (seqno, valid-until, and actions, in the wallet version's layout)
+/// * `version` - The wallet contract the key is used with
+pub fn decrypt_and_sign_ton(
+    container_json: &str,
+    passphrase: &str,
+    body_boc: &[u8],
+    version: WalletVersion,
+) -> Result<TonSigningResult, SignerError> {
+    let body = Cell::from_boc(body_boc)?;
+    let container = EncryptedKeyContainer::from_json(container_json)?;
+
+    let mut secure_key = container.decrypt_key(passphrase)?;
+    let result = sign_ton_with_secure_key(&mut secure_key, &body, version);
+    secure_key.zeroize();
+
+    result
+}
+
+/// Sign a TON wallet message with a raw private key
+///
+/// # Security Warning
+/// Prefer using decrypt_and_sign_ton() for the full secure workflow.
+pub fn sign_ton(private_key: &[u8], body_boc: &[u8], version: WalletVersion) -> Result<TonSigningResult, SignerError> {
+    let body = Cell::from_boc(body_boc)?;
+    let mut secure_key = SecureBuffer::from_slice_with_mode(private_key, get_locking_mode())?;
+    let result = sign_ton_with_secure_key(&mut secure_key, &body, version);
+    secure_key.zeroize();
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::Cipher;
+    use crate::kdf::KdfParams;
+    use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+
+    const PUBLIC_KEY: &str = "ea4a6c63e29c520abef5507b132ec5f9954776aebebe7b92421eea691446d22c";
+
+    #[test]
+    fn test_wallet_addresses() {
+        assert_eq!(
+            hex::encode(WalletVersion::V4R2.code().unwrap().hash()),
+            "feb5ff6820e2ff0d9483e7e0d62c817d846789fb4ae580c878866d959dabd5c0"
+        );
+        assert_eq!(
+            hex::encode(WalletVersion::V5R1.code().unwrap().hash()),
+            "20834b7b72b112147e1b2fb457b84e74d1a30f04f737d4f62a668e9552d2b72f"
+        );
+
+        let public_key: [u8; 32] = hex::decode(PUBLIC_KEY).unwrap().try_into().unwrap();
+        let v4 = wallet_address(&public_key, WalletVersion::V4R2, BASECHAIN).unwrap();
+        assert_eq!(v4.to_raw(), "0:2a6ee6b7ff41bfecafe383386325c7a895f4fe4ce346b18eb9c14a0152d6629c");
+        assert_eq!(v4.to_user_friendly(true, false), "EQAqbua3_0G_7K_jgzhjJceolfT-TONGsY65wUoBUtZinKC1");
+        assert_eq!(v4.to_user_friendly(false, false), "UQAqbua3_0G_7K_jgzhjJceolfT-TONGsY65wUoBUtZinP1w");
+
+        let v5 = wallet_address(&public_key, WalletVersion::V5R1, BASECHAIN).unwrap();
+        assert_eq!(v5.to_user_friendly(false, false), "UQCUm8ivArnrArRJxOEdWU9L79QzyMa2ZZZ7YKn52__z0N1Q");
+    }
+
+    #[test]
+    fn test_ton_signature_and_body_layout() {
+        std::env::set_var("SIGNER_ALLOW_INSECURE_MEMORY", "1");
+        let container =
+            EncryptedKeyContainer::encrypt_with_kdf(&[7u8; 32], "pw", Cipher::Aes256Gcm, KdfParams::MINIMUM).unwrap();
+        let json = container.to_json().unwrap();
+
+        let mut body = CellBuilder::new();
+        body.uint(32, 0x7369_676e).uint(32, 1).uint(32, 42);
+        let body = body.build().unwrap();
+
+        for version in [WalletVersion::V4R2, WalletVersion::V5R1] {
+            let result = decrypt_and_sign_ton(&json, "pw", &body.to_boc(), version).unwrap();
+            assert_eq!(result.public_key, PUBLIC_KEY);
+            let expected = match version {
+                WalletVersion::V4R2 => "UQAqbua3_0G_7K_jgzhjJceolfT-TONGsY65wUoBUtZinP1w",
+                WalletVersion::V5R1 => "UQCUm8ivArnrArRJxOEdWU9L79QzyMa2ZZZ7YKn52__z0N1Q",
+            };
+            assert_eq!(result.address, expected);
+
+            let signature: [u8; 64] = hex::decode(&result.signature).unwrap().try_into().unwrap();
+            let public_key = VerifyingKey::from_bytes(&hex::decode(PUBLIC_KEY).unwrap().try_into().unwrap()).unwrap();
+            assert!(public_key.verify(&body.hash(), &Signature::from_bytes(&signature)).is_ok());
+
+            let signed = Cell::from_boc_base64(&result.signed_body).unwrap();
+            assert_eq!(signed.bit_len(), body.bit_len() + 512);
+            let signature_at = if version == WalletVersion::V4R2 { 0 } else { body.bit_len() / 8 };
+            assert_eq!(signed.data()[signature_at..signature_at + 64], signature);
+        }
+
+        assert!(decrypt_and_sign_ton(&json, "wrong", &body.to_boc(), WalletVersion::V5R1).is_err());
+        assert!(decrypt_and_sign_ton(&json, "pw", b"not a boc", WalletVersion::V5R1).is_err());
+    }
+}
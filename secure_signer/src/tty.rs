@@ -0,0 +1,195 @@
+//! Passphrase prompts on the controlling terminal
+//!
+//! Prompts go to, and answers come from, the terminal itself (`/dev/tty`,
+//! or `CONIN$`/`CONOUT$` on Windows) rather than stdin and stdout, so they
+//! work while stdin carries data and stdout carries JSON. Echo is turned
+//! off while the answer is typed and restored afterwards, even on error.
+//! Answers are read a byte at a time straight into zeroized memory, so no
+//! buffered copy outlives the call.
+
+use std::io::{Read, Write};
+
+use zeroize::Zeroizing;
+
+use crate::error::SignerError;
+
+/// Prompt for a passphrase without echoing it
+pub fn read_passphrase(prompt: &str) -> Result<Zeroizing<String>, SignerError> {
+    let (mut input, mut output) = imp::open()?;
+    output.write_all(prompt.as_bytes())?;
+    output.flush()?;
+
+    let answer = {
+        let _echo = imp::EchoOff::new(&input)?;
+        read_line(&mut input)
+    };
+    imp::finish_line(&mut output)?;
+    answer
+}
+
+/// Prompt for a new passphrase twice, rejecting an empty or mismatched one
+pub fn read_new_passphrase(prompt: &str) -> Result<Zeroizing<String>, SignerError> {
+    let passphrase = read_passphrase(prompt)?;
+    if passphrase.is_empty() {
+        return Err(SignerError::IoError("passphrase must not be empty".to_string()));
+    }
+    let confirmation = read_passphrase("Repeat passphrase: ")?;
+    if *passphrase != *confirmation {
+        return Err(SignerError::IoError("passphrases do not match".to_string()));
+    }
+    Ok(passphrase)
+}
+
+/// Read up to a newline (dropped, along with a trailing `\r`)
+fn read_line(input: &mut impl Read) -> Result<Zeroizing<String>, SignerError> {
+    let mut line = Zeroizing::new(Vec::new());
+    let mut byte = Zeroizing::new([0u8; 1]);
+    loop {
+        match input.read(&mut *byte)? {
+            0 if line.is_empty() => {
+                return Err(SignerError::IoError("terminal closed before a passphrase was entered".to_string()))
+            }
+            0 => break,
+            _ if byte[0] == b'\n' => break,
+            _ => line.push(byte[0]),
+        }
+    }
+    if line.last() == Some(&b'\r') {
+        line.pop();
+    }
+
+    let text = std::str::from_utf8(&line).map_err(|_| SignerError::IoError("passphrase is not UTF-8".to_string()))?;
+    Ok(Zeroizing::new(text.to_string()))
+}
+
+#[cfg(unix)]
+mod imp {
+    use std::fs::{File, OpenOptions};
+    use std::os::unix::io::AsRawFd;
+
+    use crate::error::SignerError;
+
+    pub(super) fn open() -> Result<(File, File), SignerError> {
+        let tty = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open("/dev/tty")
+            .map_err(|e| SignerError::IoError(format!("no terminal to prompt on: {}", e)))?;
+        let output = tty.try_clone()?;
+        Ok((tty, output))
+    }
+
+    /// Restores the terminal's original settings on drop
+    pub(super) struct EchoOff {
+        fd: i32,
+        original: libc::termios,
+    }
+
+    impl EchoOff {
+        pub(super) fn new(tty: &File) -> Result<Self, SignerError> {
+            let fd = tty.as_raw_fd();
+            // SAFETY: fd is an open terminal and termios is plain data
+            unsafe {
+                let mut original = std::mem::zeroed::<libc::termios>();
+                if libc::tcgetattr(fd, &mut original) != 0 {
+                    return Err(std::io::Error::last_os_error().into());
+                }
+                let mut silent = original;
+                silent.c_lflag &= !libc::ECHO;
+                silent.c_lflag |= libc::ECHONL;
+                if libc::tcsetattr(fd, libc::TCSAFLUSH, &silent) != 0 {
+                    return Err(std::io::Error::last_os_error().into());
+                }
+                Ok(Self { fd, original })
+            }
+        }
+    }
+
+    impl Drop for EchoOff {
+        fn drop(&mut self) {
+            // SAFETY: restores the settings read in `new` on the same fd
+            unsafe {
+                libc::tcsetattr(self.fd, libc::TCSAFLUSH, &self.original);
+            }
+        }
+    }
+
+    /// ECHONL already echoed the newline
+    pub(super) fn finish_line(_output: &mut File) -> Result<(), SignerError> {
+        Ok(())
+    }
+}
+
+#[cfg(windows)]
+mod imp {
+    use std::ffi::c_void;
+    use std::fs::{File, OpenOptions};
+    use std::io::Write;
+    use std::os::windows::io::AsRawHandle;
+
+    use crate::error::SignerError;
+
+    const ENABLE_ECHO_INPUT: u32 = 0x4;
+
+    extern "system" {
+        fn GetConsoleMode(handle: *mut c_void, mode: *mut u32) -> i32;
+        fn SetConsoleMode(handle: *mut c_void, mode: u32) -> i32;
+    }
+
+    pub(super) fn open() -> Result<(File, File), SignerError> {
+        let no_console = |e: std::io::Error| SignerError::IoError(format!("no console to prompt on: {}", e));
+        let input = OpenOptions::new().read(true).write(true).open("CONIN$").map_err(no_console)?;
+        let output = OpenOptions::new().write(true).open("CONOUT$").map_err(no_console)?;
+        Ok((input, output))
+    }
+
+    /// Restores the console's original mode on drop
+    pub(super) struct EchoOff {
+        handle: *mut c_void,
+        original: u32,
+    }
+
+    impl EchoOff {
+        pub(super) fn new(console: &File) -> Result<Self, SignerError> {
+            let handle = console.as_raw_handle();
+            let mut original = 0u32;
+            // SAFETY: handle is an open console input handle
+            unsafe {
+                if GetConsoleMode(handle, &mut original) == 0
+                    || SetConsoleMode(handle, original & !ENABLE_ECHO_INPUT) == 0
+                {
+                    return Err(std::io::Error::last_os_error().into());
+                }
+            }
+            Ok(Self { handle, original })
+        }
+    }
+
+    impl Drop for EchoOff {
+        fn drop(&mut self) {
+            // SAFETY: restores the mode read in `new` on the same handle
+            unsafe {
+                SetConsoleMode(self.handle, self.original);
+            }
+        }
+    }
+
+    /// The console does not echo the Enter key with echo off
+    pub(super) fn finish_line(output: &mut File) -> Result<(), SignerError> {
+        output.write_all(b"\r\n")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_line_strips_line_ending() {
+        let mut input: &[u8] = b"correct horse\r\nnext line\n";
+        assert_eq!(read_line(&mut input).unwrap().as_str(), "correct horse");
+        assert_eq!(read_line(&mut input).unwrap().as_str(), "next line");
+        assert!(read_line(&mut input).is_err());
+    }
+}
@@ -0,0 +1,200 @@
+//! secp256k1 key tweaking
+//!
+//! Additive tweaks (`k' = k + t`, `P' = P + t·G`) as used by BIP-32
+//! non-hardened derivation, taproot output keys, payment-code schemes and
+//! some L2 account derivations. Private keys stay in [`SecureBuffer`]s, so
+//! callers never need to extract them to apply a tweak.
+//!
+//! Tweaks are 32-byte big-endian scalars; values `>= n` and tweaks that
+//! produce the zero key / point at infinity are rejected.
+
+use k256::elliptic_curve::ff::PrimeField;
+use k256::elliptic_curve::sec1::ToEncodedPoint;
+use k256::{FieldBytes, NonZeroScalar, ProjectivePoint, PublicKey, Scalar, SecretKey};
+use sha2::{Digest, Sha256};
+use zeroize::Zeroize;
+
+use crate::crypto::get_locking_mode;
+use crate::error::SignerError;
+use crate::secure_buffer::SecureBuffer;
+
+/// Compressed (33-byte) public key of a secp256k1 private key
+pub fn secp256k1_public_key(key: &SecureBuffer) -> Result<[u8; 33], SignerError> {
+    let secret = parse_secret(key)?;
+    Ok(compressed(&secret.public_key()))
+}
+
+/// Add a tweak to a private key: `k + t (mod n)`
+pub fn secp256k1_add_tweak_private(key: &SecureBuffer, tweak: &[u8; 32]) -> Result<SecureBuffer, SignerError> {
+    let secret = parse_secret(key)?;
+    add_scalar(*secret.to_nonzero_scalar(), parse_tweak(tweak)?)
+}
+
+/// Add a tweak to a public key (33- or 65-byte SEC1): `P + t·G`
+///
+/// Returns the compressed tweaked key.
+pub fn secp256k1_add_tweak_public(public_key: &[u8], tweak: &[u8; 32]) -> Result<[u8; 33], SignerError> {
+    let point = PublicKey::from_sec1_bytes(public_key)
+        .map_err(|_| SignerError::DerivationError("invalid secp256k1 public key".to_string()))?;
+    add_point(&point, parse_tweak(tweak)?).map(|p| compressed(&p))
+}
+
+/// BIP-341 taproot output key for an internal private key
+///
+/// The key is negated first if its public key has odd y, then tweaked with
+/// `tagged_hash("TapTweak", x(P) || merkle_root)`. Use `None` for key-path
+/// only outputs.
+pub fn taproot_tweak_private(
+    key: &SecureBuffer,
+    merkle_root: Option<&[u8; 32]>,
+) -> Result<SecureBuffer, SignerError> {
+    let secret = parse_secret(key)?;
+    let public = compressed(&secret.public_key());
+    let scalar = *secret.to_nonzero_scalar();
+    let scalar = if public[0] == 0x03 { -scalar } else { scalar };
+
+    let x_only: [u8; 32] = public[1..].try_into().expect("33-byte key");
+    add_scalar(scalar, taproot_tweak(&x_only, merkle_root)?)
+}
+
+/// BIP-341 taproot output key for an x-only internal public key
+///
+/// Returns the x-only output key and whether its y coordinate is odd (the
+/// parity bit needed for script-path control blocks).
+pub fn taproot_tweak_public(
+    internal_key: &[u8; 32],
+    merkle_root: Option<&[u8; 32]>,
+) -> Result<([u8; 32], bool), SignerError> {
+    let mut sec1 = [0x02u8; 33];
+    sec1[1..].copy_from_slice(internal_key);
+    let point = PublicKey::from_sec1_bytes(&sec1)
+        .map_err(|_| SignerError::DerivationError("invalid x-only public key".to_string()))?;
+
+    let output = compressed(&add_point(&point, taproot_tweak(internal_key, merkle_root)?)?);
+    Ok((output[1..].try_into().expect("33-byte key"), output[0] == 0x03))
+}
+
+/// BIP-340 tagged hash
+fn tagged_hash(tag: &str, data: &[&[u8]]) -> [u8; 32] {
+    let tag_hash = Sha256::digest(tag.as_bytes());
+    let mut hasher = Sha256::new();
+    hasher.update(tag_hash);
+    hasher.update(tag_hash);
+    for part in data {
+        hasher.update(part);
+    }
+    hasher.finalize().into()
+}
+
+fn taproot_tweak(x_only: &[u8; 32], merkle_root: Option<&[u8; 32]>) -> Result<Scalar, SignerError> {
+    let hash = match merkle_root {
+        Some(root) => tagged_hash("TapTweak", &[x_only, root]),
+        None => tagged_hash("TapTweak", &[x_only]),
+    };
+    parse_tweak(&hash)
+}
+
+fn parse_secret(key: &SecureBuffer) -> Result<SecretKey, SignerError> {
+    if key.len() != 32 {
+        return Err(SignerError::InvalidKeyFormat(key.len()));
+    }
+    SecretKey::from_slice(key.as_slice())
+        .map_err(|_| SignerError::DerivationError("invalid secp256k1 private key".to_string()))
+}
+
+fn parse_tweak(tweak: &[u8; 32]) -> Result<Scalar, SignerError> {
+    Option::<Scalar>::from(Scalar::from_repr(FieldBytes::from(*tweak)))
+        .ok_or_else(|| SignerError::DerivationError("tweak is not less than the curve order".to_string()))
+}
+
+fn add_scalar(key: Scalar, tweak: Scalar) -> Result<SecureBuffer, SignerError> {
+    let sum = Option::<NonZeroScalar>::from(NonZeroScalar::new(key + tweak))
+        .ok_or_else(|| SignerError::DerivationError("tweaked key is zero".to_string()))?;
+
+    let mut bytes = sum.to_repr();
+    let result = SecureBuffer::from_slice_with_mode(&bytes, get_locking_mode());
+    bytes.zeroize();
+    result
+}
+
+fn add_point(point: &PublicKey, tweak: Scalar) -> Result<PublicKey, SignerError> {
+    let sum = point.to_projective() + ProjectivePoint::GENERATOR * tweak;
+    PublicKey::from_affine(sum.to_affine())
+        .map_err(|_| SignerError::DerivationError("tweaked key is the point at infinity".to_string()))
+}
+
+fn compressed(point: &PublicKey) -> [u8; 33] {
+    point
+        .to_encoded_point(true)
+        .as_bytes()
+        .try_into()
+        .expect("compressed point is 33 bytes")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(byte: u8) -> SecureBuffer {
+        SecureBuffer::from_slice(&[byte; 32]).unwrap()
+    }
+
+    #[test]
+    fn test_private_and_public_tweaks_agree() {
+        let private = key(7);
+        let tweak = [0x11u8; 32];
+
+        let tweaked_private = secp256k1_add_tweak_private(&private, &tweak).unwrap();
+        let public = secp256k1_public_key(&private).unwrap();
+        assert_eq!(
+            secp256k1_public_key(&tweaked_private).unwrap(),
+            secp256k1_add_tweak_public(&public, &tweak).unwrap()
+        );
+
+        // Tweaks at or above the curve order are rejected
+        assert!(secp256k1_add_tweak_private(&private, &[0xff; 32]).is_err());
+    }
+
+    #[test]
+    fn test_tweak_to_zero_is_rejected() {
+        // n - 1 is a valid key; adding 1 wraps to zero
+        let n_minus_1 = hex::decode("fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364140").unwrap();
+        let private = SecureBuffer::from_slice(&n_minus_1).unwrap();
+        let mut one = [0u8; 32];
+        one[31] = 1;
+
+        assert!(secp256k1_add_tweak_private(&private, &one).is_err());
+        let public = secp256k1_public_key(&private).unwrap();
+        assert!(secp256k1_add_tweak_public(&public, &one).is_err());
+    }
+
+    #[test]
+    fn test_taproot_key_path_vector() {
+        // BIP-341 wallet test vector (scriptPubKey, key-path only)
+        let internal: [u8; 32] =
+            hex::decode("d6889cb081036e0faefa3a35157ad71086b123b2b144b649798b494c300a961d")
+                .unwrap()
+                .try_into()
+                .unwrap();
+        let (output, _) = taproot_tweak_public(&internal, None).unwrap();
+        assert_eq!(
+            hex::encode(output),
+            "53a1f6e454df1aa2776a2814a721372d6258050de330b3c6d10ee8f4e0dda343"
+        );
+    }
+
+    #[test]
+    fn test_taproot_private_matches_public() {
+        let root = [0x42u8; 32];
+        for byte in [1u8, 2, 3, 4] {
+            let private = key(byte);
+            let internal: [u8; 32] = secp256k1_public_key(&private).unwrap()[1..].try_into().unwrap();
+
+            let tweaked = taproot_tweak_private(&private, Some(&root)).unwrap();
+            let (output, odd) = taproot_tweak_public(&internal, Some(&root)).unwrap();
+            let public = secp256k1_public_key(&tweaked).unwrap();
+            assert_eq!(public[1..], output);
+            assert_eq!(public[0] == 0x03, odd);
+        }
+    }
+}
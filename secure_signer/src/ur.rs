@@ -0,0 +1,675 @@
+//! Air-gapped transfer as animated QR codes (BC-UR)
+//!
+//! Containers, messages to sign, and signing results can cross the air gap
+//! through a camera alone, encoded as [Uniform Resources]: text of the form
+//! `ur:<type>/<seq>-<count>/<bytewords>`, one string per QR frame.
+//!
+//! Payloads too big for one QR code are split with a fountain code. The
+//! first frames carry the fragments in order; every frame after that mixes
+//! a pseudo-random subset of them, so the sender can loop frames forever
+//! and the receiver finishes after roughly as many frames as there are
+//! fragments, whichever ones it misses. The encoding follows BCR-2020-005
+//! and the reference implementation, so other UR-aware wallets can read
+//! `bytes` frames.
+//!
+//! A UR is lowercase, but scanners read frames more easily as QR
+//! alphanumeric codes: uppercase a frame before rendering it.
+//! [`UrDecoder`] accepts either case.
+//!
+//! [Uniform Resources]: https://github.com/BlockchainCommons/Research/blob/master/papers/bcr-2020-005-ur.md
+
+use sha2::{Digest, Sha256};
+
+use crate::crypto::{EVMSigningResult, EncryptedKeyContainer, SigningResult};
+use crate::error::SignerError;
+
+/// Fragment length that keeps each frame readable on a phone screen
+pub const DEFAULT_MAX_FRAGMENT_LEN: usize = 200;
+
+/// Smallest fragment the encoder will cut
+pub const MIN_FRAGMENT_LEN: usize = 10;
+
+/// Largest message a [`UrDecoder`] will reassemble
+pub const MAX_MESSAGE_LEN: usize = 1024 * 1024;
+
+/// First and last letter of each of the 256 bytewords, in byte order
+const BYTEWORDS: &[u8; 512] = b"aeadaoaxaaahamatayasbkbdbnbtbabsbebybgbwbbbzcmchcscfcycwcecackctcxclcpcndkdadsdidedtdrdndwdpdmdldyeheyeoeeecenemetesftfrfnfsfmfhfzfpfwfxfyfefgflfdgagegrgsgtglgwgdgygmgughgohfhghdhkhthphhhlhyhehnhsidiaieihiyioisinimjejzjnjtjljojsjpjkjykpkoktkskkknkgkekikblblalylflslrlplnltloldlelulklgmnmymhmemomumwmdmtmsmknlnyndnsntnnnenboyoeotoxonolospdptpkpypspmplpepfpaprqdqzrerprlrorhrdrkrfryrnrsrtsesasrssskswstspsosgsbsfsntotktitttdtetytltbtstptatnuyuoutueurvtvyvovlvevwvavdvswlwdwmwpwewywswtwnwzwfwkykynylyaytzszoztzczezm";
+
+fn malformed(reason: &str) -> SignerError {
+    SignerError::SerializationError(format!("malformed UR: {}", reason))
+}
+
+/// CRC-32 (IEEE), as bytewords and fountain parts use it
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xedb8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+/// Minimal bytewords: two letters per byte, then a CRC-32
+fn bytewords_encode(data: &[u8]) -> String {
+    let checksum = crc32(data).to_be_bytes();
+    data.iter()
+        .chain(&checksum)
+        .flat_map(|&byte| BYTEWORDS[2 * byte as usize..2 * byte as usize + 2].iter())
+        .map(|&letter| letter as char)
+        .collect()
+}
+
+fn bytewords_decode(text: &str) -> Result<Vec<u8>, SignerError> {
+    let text = text.as_bytes();
+    if !text.len().is_multiple_of(2) || text.len() < 10 {
+        return Err(malformed("bad bytewords length"));
+    }
+    let mut bytes = text
+        .chunks(2)
+        .map(|pair| BYTEWORDS.chunks(2).position(|word| word == pair).map(|byte| byte as u8))
+        .collect::<Option<Vec<u8>>>()
+        .ok_or_else(|| malformed("not a byteword"))?;
+    let checksum = bytes.split_off(bytes.len() - 4);
+    if crc32(&bytes).to_be_bytes() != checksum[..] {
+        return Err(malformed("bytewords checksum mismatch"));
+    }
+    Ok(bytes)
+}
+
+fn cbor_head(out: &mut Vec<u8>, major: u8, value: u64) {
+    let major = major << 5;
+    match value {
+        0..=23 => out.push(major | value as u8),
+        24..=0xff => out.extend([major | 24, value as u8]),
+        0x100..=0xffff => {
+            out.push(major | 25);
+            out.extend((value as u16).to_be_bytes());
+        }
+        0x1_0000..=0xffff_ffff => {
+            out.push(major | 26);
+            out.extend((value as u32).to_be_bytes());
+        }
+        _ => {
+            out.push(major | 27);
+            out.extend(value.to_be_bytes());
+        }
+    }
+}
+
+/// A CBOR byte string, the body of every UR type defined here
+fn cbor_bytes(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len() + 9);
+    cbor_head(&mut out, 2, data.len() as u64);
+    out.extend_from_slice(data);
+    out
+}
+
+/// Reads the few CBOR items URs need
+struct CborReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> CborReader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], SignerError> {
+        if len > self.bytes.len() {
+            return Err(malformed("truncated CBOR"));
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn head(&mut self, major: u8) -> Result<u64, SignerError> {
+        let initial = self.take(1)?[0];
+        if initial >> 5 != major {
+            return Err(malformed("unexpected CBOR type"));
+        }
+        let width = match initial & 0x1f {
+            info @ 0..=23 => return Ok(info as u64),
+            24 => 1,
+            25 => 2,
+            26 => 4,
+            27 => 8,
+            _ => return Err(malformed("unsupported CBOR length")),
+        };
+        Ok(self.take(width)?.iter().fold(0u64, |value, &byte| value << 8 | byte as u64))
+    }
+
+    fn uint(&mut self) -> Result<u64, SignerError> {
+        self.head(0)
+    }
+
+    fn bytes(&mut self) -> Result<&'a [u8], SignerError> {
+        let len = usize::try_from(self.head(2)?).map_err(|_| malformed("CBOR byte string too long"))?;
+        self.take(len)
+    }
+
+    fn finish(self) -> Result<(), SignerError> {
+        if self.bytes.is_empty() {
+            Ok(())
+        } else {
+            Err(malformed("trailing CBOR"))
+        }
+    }
+}
+
+/// One fountain-coded frame: `[seqNum, seqLen, messageLen, checksum, data]`
+struct Part {
+    seq_num: u32,
+    seq_len: usize,
+    message_len: usize,
+    checksum: u32,
+    data: Vec<u8>,
+}
+
+impl Part {
+    fn to_cbor(&self) -> Vec<u8> {
+        let mut out = vec![0x85];
+        cbor_head(&mut out, 0, self.seq_num as u64);
+        cbor_head(&mut out, 0, self.seq_len as u64);
+        cbor_head(&mut out, 0, self.message_len as u64);
+        cbor_head(&mut out, 0, self.checksum as u64);
+        out.extend(cbor_bytes(&self.data));
+        out
+    }
+
+    fn from_cbor(cbor: &[u8]) -> Result<Self, SignerError> {
+        let mut reader = CborReader { bytes: cbor };
+        if reader.head(4)? != 5 {
+            return Err(malformed("a fountain part has five fields"));
+        }
+        let mut field = || reader.uint().map_err(|_| malformed("bad fountain part field"));
+        let (seq_num, seq_len, message_len, checksum) = (field()?, field()?, field()?, field()?);
+        let data = reader.bytes()?.to_vec();
+        reader.finish()?;
+
+        let part = Self {
+            seq_num: u32::try_from(seq_num).map_err(|_| malformed("sequence number too large"))?,
+            seq_len: usize::try_from(seq_len).map_err(|_| malformed("too many fragments"))?,
+            message_len: usize::try_from(message_len)
+                .ok()
+                .filter(|&len| len <= MAX_MESSAGE_LEN)
+                .ok_or_else(|| malformed("message too long"))?,
+            checksum: u32::try_from(checksum).map_err(|_| malformed("checksum too large"))?,
+            data,
+        };
+        if part.seq_num == 0 || part.data.is_empty() || part.seq_len != part.message_len.div_ceil(part.data.len()) {
+            return Err(malformed("inconsistent fountain part"));
+        }
+        Ok(part)
+    }
+}
+
+/// Xoshiro256**, seeded from SHA-256 of the seed bytes as the reference
+/// encoder does
+struct Xoshiro256([u64; 4]);
+
+impl Xoshiro256 {
+    fn from_seed(seed: &[u8]) -> Self {
+        let digest = Sha256::digest(seed);
+        let mut state = [0u64; 4];
+        for (word, chunk) in state.iter_mut().zip(digest.chunks(8)) {
+            *word = chunk.iter().fold(0u64, |value, &byte| value << 8 | byte as u64);
+        }
+        Self(state)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let s = &mut self.0;
+        let result = s[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = s[1] << 17;
+        s[2] ^= s[0];
+        s[3] ^= s[1];
+        s[1] ^= s[2];
+        s[0] ^= s[3];
+        s[2] ^= t;
+        s[3] = s[3].rotate_left(45);
+        result
+    }
+
+    fn next_f64(&mut self) -> f64 {
+        self.next_u64() as f64 / (u64::MAX as f64 + 1.0)
+    }
+
+    fn next_int(&mut self, low: usize, high: usize) -> usize {
+        (self.next_f64() * (high - low + 1) as f64) as usize + low
+    }
+}
+
+/// How many fragments a mixed part combines: degree `d` with probability
+/// proportional to `1/d`, drawn with Walker's alias method
+fn choose_degree(seq_len: usize, rng: &mut Xoshiro256) -> usize {
+    let weights: Vec<f64> = (1..=seq_len).map(|degree| 1.0 / degree as f64).collect();
+    let total: f64 = weights.iter().sum();
+    let mut scaled: Vec<f64> = weights.iter().map(|w| w * seq_len as f64 / total).collect();
+    let (mut small, mut large): (Vec<usize>, Vec<usize>) = (0..seq_len).rev().partition(|&i| scaled[i] < 1.0);
+
+    let mut probabilities = vec![0.0; seq_len];
+    let mut aliases = vec![0; seq_len];
+    while let (Some(a), Some(g)) = (small.last().copied(), large.last().copied()) {
+        small.pop();
+        large.pop();
+        probabilities[a] = scaled[a];
+        aliases[a] = g;
+        scaled[g] += scaled[a] - 1.0;
+        if scaled[g] < 1.0 {
+            small.push(g);
+        } else {
+            large.push(g);
+        }
+    }
+    for i in large.into_iter().chain(small) {
+        probabilities[i] = 1.0;
+    }
+
+    let r1 = rng.next_f64();
+    let r2 = rng.next_f64();
+    let i = (seq_len as f64 * r1) as usize;
+    1 + if r2 < probabilities[i] { i } else { aliases[i] }
+}
+
+/// The fragment indexes part `seq_num` carries, sorted
+fn choose_fragments(seq_num: u32, seq_len: usize, checksum: u32) -> Vec<usize> {
+    if seq_num as usize <= seq_len {
+        return vec![seq_num as usize - 1];
+    }
+    let mut seed = [0u8; 8];
+    seed[..4].copy_from_slice(&seq_num.to_be_bytes());
+    seed[4..].copy_from_slice(&checksum.to_be_bytes());
+    let mut rng = Xoshiro256::from_seed(&seed);
+    let degree = choose_degree(seq_len, &mut rng);
+
+    let mut remaining: Vec<usize> = (0..seq_len).collect();
+    let mut chosen: Vec<usize> = (0..degree)
+        .map(|_| remaining.remove(rng.next_int(0, remaining.len() - 1)))
+        .collect();
+    chosen.sort_unstable();
+    chosen
+}
+
+fn xor_into(target: &mut [u8], source: &[u8]) {
+    for (t, s) in target.iter_mut().zip(source) {
+        *t ^= s;
+    }
+}
+
+/// The fragment length the reference encoder picks: the longest that
+/// still splits the message evenly into fragments of at most `max_len`
+fn fragment_len(message_len: usize, max_len: usize) -> usize {
+    let max_count = (message_len / MIN_FRAGMENT_LEN).max(1);
+    (1..=max_count)
+        .map(|count| message_len.div_ceil(count))
+        .find(|&len| len <= max_len)
+        .unwrap_or_else(|| message_len.div_ceil(max_count))
+}
+
+fn check_ur_type(ur_type: &str) -> Result<(), SignerError> {
+    if ur_type.is_empty() || !ur_type.bytes().all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-') {
+        return Err(malformed("type must be lowercase letters, digits, and hyphens"));
+    }
+    Ok(())
+}
+
+/// A decoded Uniform Resource: its type and CBOR body
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Ur {
+    pub ur_type: String,
+    pub cbor: Vec<u8>,
+}
+
+impl Ur {
+    /// The body of a byte-string UR, which every type here is
+    pub fn payload(&self) -> Result<Vec<u8>, SignerError> {
+        let mut reader = CborReader { bytes: &self.cbor };
+        let payload = reader.bytes()?.to_vec();
+        reader.finish()?;
+        Ok(payload)
+    }
+
+    /// Decode the UR as `T`, checking its type
+    pub fn decode<T: UrPayload>(&self) -> Result<T, SignerError> {
+        if self.ur_type != T::UR_TYPE {
+            return Err(SignerError::SerializationError(format!(
+                "expected a '{}' UR, got '{}'",
+                T::UR_TYPE,
+                self.ur_type
+            )));
+        }
+        T::from_ur_payload(&self.payload()?)
+    }
+}
+
+/// Produces the frames of one UR, in order and then without end
+///
+/// Show the frames as an animated QR code until the other side has read
+/// them all; [`fragment_count`](UrEncoder::fragment_count) frames are
+/// enough if none are missed.
+pub struct UrEncoder {
+    ur_type: String,
+    message: Vec<u8>,
+    fragments: Vec<Vec<u8>>,
+    checksum: u32,
+    seq_num: u32,
+}
+
+impl UrEncoder {
+    /// Split `cbor` into fragments of at most `max_fragment_len` bytes
+    pub fn new(ur_type: &str, cbor: &[u8], max_fragment_len: usize) -> Result<Self, SignerError> {
+        check_ur_type(ur_type)?;
+        if cbor.is_empty() || max_fragment_len < MIN_FRAGMENT_LEN {
+            return Err(SignerError::SerializationError(format!(
+                "cannot encode {} bytes in fragments of {}",
+                cbor.len(),
+                max_fragment_len
+            )));
+        }
+        let len = fragment_len(cbor.len(), max_fragment_len);
+        let fragments = cbor
+            .chunks(len)
+            .map(|chunk| {
+                let mut fragment = chunk.to_vec();
+                fragment.resize(len, 0);
+                fragment
+            })
+            .collect();
+        Ok(Self {
+            ur_type: ur_type.to_string(),
+            message: cbor.to_vec(),
+            fragments,
+            checksum: crc32(cbor),
+            seq_num: 0,
+        })
+    }
+
+    /// How many fragments the payload was cut into
+    pub fn fragment_count(&self) -> usize {
+        self.fragments.len()
+    }
+
+    /// The next frame; a single-fragment UR repeats one frame
+    pub fn next_part(&mut self) -> String {
+        if self.fragments.len() == 1 {
+            return format!("ur:{}/{}", self.ur_type, bytewords_encode(&self.message));
+        }
+        self.seq_num = self.seq_num.wrapping_add(1).max(1);
+        let indexes = choose_fragments(self.seq_num, self.fragments.len(), self.checksum);
+        let mut data = self.fragments[indexes[0]].clone();
+        for &index in &indexes[1..] {
+            xor_into(&mut data, &self.fragments[index]);
+        }
+        let part = Part {
+            seq_num: self.seq_num,
+            seq_len: self.fragments.len(),
+            message_len: self.message.len(),
+            checksum: self.checksum,
+            data,
+        };
+        format!(
+            "ur:{}/{}-{}/{}",
+            self.ur_type,
+            part.seq_num,
+            part.seq_len,
+            bytewords_encode(&part.to_cbor())
+        )
+    }
+}
+
+impl Iterator for UrEncoder {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        Some(self.next_part())
+    }
+}
+
+/// Reassembles a UR from frames read in any order
+///
+/// Feed it every frame the camera reads until
+/// [`result`](UrDecoder::result) is available. Frames of another UR are
+/// rejected, and repeated frames are harmless.
+#[derive(Default)]
+pub struct UrDecoder {
+    ur_type: Option<String>,
+    /// `(seq_len, message_len, checksum, fragment length)` of the first part
+    shape: Option<(usize, usize, u32, usize)>,
+    fragments: Vec<Option<Vec<u8>>>,
+    mixed: Vec<(Vec<usize>, Vec<u8>)>,
+    result: Option<Ur>,
+}
+
+impl UrDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Take in one scanned frame
+    pub fn receive(&mut self, frame: &str) -> Result<(), SignerError> {
+        if self.result.is_some() {
+            return Ok(());
+        }
+        let frame = frame.trim().to_ascii_lowercase();
+        let body = frame.strip_prefix("ur:").ok_or_else(|| malformed("missing 'ur:' prefix"))?;
+        let components: Vec<&str> = body.split('/').collect();
+        let (ur_type, sequence, bytewords) = match components[..] {
+            [ur_type, bytewords] => (ur_type, None, bytewords),
+            [ur_type, sequence, bytewords] => (ur_type, Some(sequence), bytewords),
+            _ => return Err(malformed("expected 'ur:<type>/[<seq>-<count>/]<bytewords>'")),
+        };
+        check_ur_type(ur_type)?;
+        if self.ur_type.as_deref().is_some_and(|expected| expected != ur_type) {
+            return Err(malformed("frame belongs to a different UR"));
+        }
+        let cbor = bytewords_decode(bytewords)?;
+
+        let Some(sequence) = sequence else {
+            self.result = Some(Ur {
+                ur_type: ur_type.to_string(),
+                cbor,
+            });
+            return Ok(());
+        };
+        let part = Part::from_cbor(&cbor)?;
+        if sequence != format!("{}-{}", part.seq_num, part.seq_len) {
+            return Err(malformed("sequence does not match the part"));
+        }
+        let shape = (part.seq_len, part.message_len, part.checksum, part.data.len());
+        match self.shape {
+            None => {
+                self.ur_type = Some(ur_type.to_string());
+                self.shape = Some(shape);
+                self.fragments = vec![None; part.seq_len];
+            }
+            Some(expected) if expected != shape => return Err(malformed("frame belongs to a different UR")),
+            Some(_) => {}
+        }
+
+        self.add(choose_fragments(part.seq_num, part.seq_len, part.checksum), part.data);
+        if self.fragments.iter().all(Option::is_some) {
+            self.finish(ur_type)?;
+        }
+        Ok(())
+    }
+
+    /// Peel known fragments off a part; keep it if more than one is left
+    fn add(&mut self, mut indexes: Vec<usize>, mut data: Vec<u8>) {
+        indexes.retain(|&index| match &self.fragments[index] {
+            Some(fragment) => {
+                xor_into(&mut data, fragment);
+                false
+            }
+            None => true,
+        });
+        match indexes[..] {
+            [] => {}
+            [index] => {
+                self.fragments[index] = Some(data);
+                for (indexes, data) in std::mem::take(&mut self.mixed) {
+                    self.add(indexes, data);
+                }
+            }
+            _ => {
+                if !self.mixed.iter().any(|(known, _)| *known == indexes) {
+                    self.mixed.push((indexes, data));
+                }
+            }
+        }
+    }
+
+    fn finish(&mut self, ur_type: &str) -> Result<(), SignerError> {
+        let Some((_, message_len, checksum, _)) = self.shape else {
+            return Ok(());
+        };
+        let mut message: Vec<u8> = self.fragments.iter().flatten().flatten().copied().collect();
+        message.truncate(message_len);
+        if crc32(&message) != checksum {
+            *self = Self::default();
+            return Err(malformed("message checksum mismatch"));
+        }
+        self.result = Some(Ur {
+            ur_type: ur_type.to_string(),
+            cbor: message,
+        });
+        Ok(())
+    }
+
+    /// Fraction of fragments recovered so far
+    pub fn progress(&self) -> f64 {
+        if self.result.is_some() {
+            return 1.0;
+        }
+        match self.fragments.len() {
+            0 => 0.0,
+            count => self.fragments.iter().filter(|f| f.is_some()).count() as f64 / count as f64,
+        }
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.result.is_some()
+    }
+
+    /// The reassembled UR, once every fragment is in
+    pub fn result(&self) -> Option<&Ur> {
+        self.result.as_ref()
+    }
+}
+
+/// Something that crosses the air gap as a UR byte string
+pub trait UrPayload: Sized {
+    /// The UR type frames are labelled with
+    const UR_TYPE: &'static str;
+
+    fn to_ur_payload(&self) -> Result<Vec<u8>, SignerError>;
+
+    fn from_ur_payload(payload: &[u8]) -> Result<Self, SignerError>;
+
+    /// Frames carrying `self`, each fragment at most `max_fragment_len`
+    /// bytes ([`DEFAULT_MAX_FRAGMENT_LEN`] suits most cameras)
+    fn to_ur_frames(&self, max_fragment_len: usize) -> Result<UrEncoder, SignerError> {
+        UrEncoder::new(Self::UR_TYPE, &cbor_bytes(&self.to_ur_payload()?), max_fragment_len)
+    }
+}
+
+/// Raw bytes (`ur:bytes`): unsigned transactions, messages, and hashes
+impl UrPayload for Vec<u8> {
+    const UR_TYPE: &'static str = "bytes";
+
+    fn to_ur_payload(&self) -> Result<Vec<u8>, SignerError> {
+        Ok(self.clone())
+    }
+
+    fn from_ur_payload(payload: &[u8]) -> Result<Self, SignerError> {
+        Ok(payload.to_vec())
+    }
+}
+
+/// The binary container form, which cannot carry a duress slot
+impl UrPayload for EncryptedKeyContainer {
+    const UR_TYPE: &'static str = "coldstar-container";
+
+    fn to_ur_payload(&self) -> Result<Vec<u8>, SignerError> {
+        self.to_bytes()
+    }
+
+    fn from_ur_payload(payload: &[u8]) -> Result<Self, SignerError> {
+        Self::from_bytes(payload)
+    }
+}
+
+impl UrPayload for SigningResult {
+    const UR_TYPE: &'static str = "coldstar-sign-result";
+
+    fn to_ur_payload(&self) -> Result<Vec<u8>, SignerError> {
+        serde_json::to_vec(self).map_err(|e| SignerError::SerializationError(e.to_string()))
+    }
+
+    fn from_ur_payload(payload: &[u8]) -> Result<Self, SignerError> {
+        serde_json::from_slice(payload).map_err(|e| SignerError::SerializationError(e.to_string()))
+    }
+}
+
+impl UrPayload for EVMSigningResult {
+    const UR_TYPE: &'static str = "coldstar-evm-sign-result";
+
+    fn to_ur_payload(&self) -> Result<Vec<u8>, SignerError> {
+        serde_json::to_vec(self).map_err(|e| SignerError::SerializationError(e.to_string()))
+    }
+
+    fn from_ur_payload(payload: &[u8]) -> Result<Self, SignerError> {
+        serde_json::from_slice(payload).map_err(|e| SignerError::SerializationError(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::Cipher;
+    use crate::kdf::KdfParams;
+
+    #[test]
+    fn test_bytewords_and_single_part() {
+        assert_eq!(bytewords_encode(&[0, 1, 2, 128, 255]), "aeadaolazmjendeoti");
+        assert_eq!(bytewords_decode("aeadaolazmjendeoti").unwrap(), [0, 1, 2, 128, 255]);
+        assert!(bytewords_decode("aeadaolazmjendeotj").is_err());
+
+        let mut frames = b"message".to_vec().to_ur_frames(DEFAULT_MAX_FRAGMENT_LEN).unwrap();
+        assert_eq!(frames.fragment_count(), 1);
+        let frame = frames.next_part();
+        assert!(frame.starts_with("ur:bytes/") && frame.matches('/').count() == 1);
+
+        let mut decoder = UrDecoder::new();
+        decoder.receive(&frame.to_uppercase()).unwrap();
+        assert_eq!(decoder.result().unwrap().decode::<Vec<u8>>().unwrap(), b"message");
+    }
+
+    #[test]
+    fn test_container_survives_lost_frames() {
+        std::env::set_var("SIGNER_ALLOW_INSECURE_MEMORY", "1");
+        let container =
+            EncryptedKeyContainer::encrypt_with_kdf(&[4u8; 32], "pw", Cipher::Aes256Gcm, KdfParams::MINIMUM).unwrap();
+        let mut frames = container.to_ur_frames(MIN_FRAGMENT_LEN * 2).unwrap();
+        let count = frames.fragment_count();
+        assert!(count > 3);
+
+        // Drop every third frame, including some of the in-order ones
+        let mut decoder = UrDecoder::new();
+        for (i, frame) in frames.by_ref().take(count * 10).enumerate() {
+            if i % 3 != 0 {
+                decoder.receive(&frame).unwrap();
+            }
+            if decoder.is_complete() {
+                break;
+            }
+        }
+        let received: EncryptedKeyContainer = decoder.result().unwrap().decode().unwrap();
+        assert_eq!(received.to_bytes().unwrap(), container.to_bytes().unwrap());
+        assert!(decoder.result().unwrap().decode::<Vec<u8>>().is_err());
+
+        // A frame of another UR is refused mid-transfer
+        let mut decoder = UrDecoder::new();
+        decoder.receive(&frames.next_part()).unwrap();
+        let other = b"x".repeat(100).to_ur_frames(MIN_FRAGMENT_LEN * 2).unwrap().next_part();
+        assert!(decoder.receive(&other).is_err());
+    }
+}
@@ -0,0 +1,279 @@
+//! Time-limited containers
+//!
+//! A container can carry a validity window, `not_before` and `not_after`
+//! in Unix seconds. Every decryption checks it against the system clock,
+//! so a key delegated for an engagement or a rotation period stops
+//! signing once the window closes. Version 3 authenticates both fields,
+//! so they cannot be edited or removed without failing decryption.
+//!
+//! The window is enforced by this library, not by the cryptography: anyone
+//! with the container, its passphrase, and other software can still
+//! decrypt the key, and so can a machine with its clock set back. Sessions
+//! (including handed-off ones) lock at `not_after`; other holders of an
+//! unlocked key check the window only when they unlock.
+//!
+//! [`EncryptedKeyContainer::with_validity`] re-encrypts a container with a
+//! new window, for example to extend it.
+
+use alloc::format;
+use alloc::string::String;
+
+use crate::crypto::{Cipher, EncryptedKeyContainer, Sealing};
+use crate::error::SignerError;
+use crate::kdf::{Kdf, KdfParams};
+
+/// When a container's key may be used, in Unix seconds (both inclusive)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Validity {
+    /// First second the key may be used (`None`: no lower bound)
+    pub not_before: Option<u64>,
+    /// Last second the key may be used (`None`: no upper bound)
+    pub not_after: Option<u64>,
+}
+
+impl Validity {
+    /// Valid from now until `not_after`
+    pub fn until(not_after: u64) -> Self {
+        Self {
+            not_before: None,
+            not_after: Some(not_after),
+        }
+    }
+
+    /// Fail unless `now` falls inside the window
+    pub fn check(&self, now: u64) -> Result<(), SignerError> {
+        if let Some(not_before) = self.not_before.filter(|&not_before| now < not_before) {
+            return Err(SignerError::ContainerNotValid(format!("not valid before {}", not_before)));
+        }
+        if let Some(not_after) = self.not_after.filter(|&not_after| now > not_after) {
+            return Err(SignerError::ContainerNotValid(format!("expired at {}", not_after)));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(feature = "std", not(all(target_arch = "wasm32", target_os = "unknown"))))]
+pub(crate) fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Browsers have no `SystemTime`
+#[cfg(all(feature = "std", target_arch = "wasm32", target_os = "unknown"))]
+pub(crate) fn unix_now() -> u64 {
+    (js_sys::Date::now() / 1000.0) as u64
+}
+
+/// Days since 1970-01-01 of a proleptic Gregorian date (H. Hinnant)
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// Unix seconds of an RFC 3339 timestamp such as `2021-09-30T16:25:24.000Z`
+pub(crate) fn parse_rfc3339(value: &str) -> Option<i64> {
+    let bytes = value.as_bytes();
+    let number = |range: core::ops::Range<usize>| -> Option<i64> {
+        let digits = value.get(range)?;
+        digits.bytes().all(|b| b.is_ascii_digit()).then(|| digits.parse().ok())?
+    };
+    if bytes.len() < 20
+        || bytes[4] != b'-'
+        || bytes[7] != b'-'
+        || !matches!(bytes[10], b'T' | b't')
+        || bytes[13] != b':'
+        || bytes[16] != b':'
+    {
+        return None;
+    }
+    let (year, month, day) = (number(0..4)?, number(5..7)?, number(8..10)?);
+    let (hour, minute, second) = (number(11..13)?, number(14..16)?, number(17..19)?);
+    let month_days = match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        1..=12 => 31,
+        _ => return None,
+    };
+    if day < 1 || day > month_days || hour > 23 || minute > 59 || second > 60 {
+        return None;
+    }
+
+    let mut rest = &value[19..];
+    if let Some(fraction) = rest.strip_prefix('.') {
+        let digits = fraction.bytes().take_while(u8::is_ascii_digit).count();
+        if digits == 0 {
+            return None;
+        }
+        rest = &fraction[digits..];
+    }
+    let offset = match rest.as_bytes() {
+        [b'Z' | b'z'] => 0,
+        [sign @ (b'+' | b'-'), _, _, b':', _, _] => {
+            let (hours, minutes) = (number(value.len() - 5..value.len() - 3)?, number(value.len() - 2..value.len())?);
+            if hours > 23 || minutes > 59 {
+                return None;
+            }
+            let offset = hours * 3_600 + minutes * 60;
+            if *sign == b'-' {
+                -offset
+            } else {
+                offset
+            }
+        }
+        _ => return None,
+    };
+    Some(days_from_civil(year, month, day) * 86_400 + hour * 3_600 + minute * 60 + second - offset)
+}
+
+/// RFC 3339 UTC timestamp of `unix_secs`, e.g. `2021-09-30T16:25:24Z`
+pub(crate) fn format_rfc3339(unix_secs: u64) -> String {
+    let days = (unix_secs / 86_400) as i64;
+    let secs = unix_secs % 86_400;
+    // Civil-from-days, the inverse of `days_from_civil`
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        secs / 3_600,
+        secs % 3_600 / 60,
+        secs % 60
+    )
+}
+
+impl EncryptedKeyContainer {
+    /// The container's validity window
+    ///
+    /// A `not_before` of 0 and a `not_after` of `u64::MAX` are the same as
+    /// no bound.
+    pub fn validity(&self) -> Validity {
+        Validity {
+            not_before: self.not_before.filter(|&t| t != 0),
+            not_after: self.not_after.filter(|&t| t != u64::MAX),
+        }
+    }
+
+    /// Fail outside the validity window (reading the clock only if there
+    /// is one)
+    #[cfg(feature = "std")]
+    pub(crate) fn check_validity(&self) -> Result<(), SignerError> {
+        match self.validity() {
+            validity if validity == Validity::default() => Ok(()),
+            validity => validity.check(unix_now()),
+        }
+    }
+
+    /// Without `std` there is no clock, so a windowed container fails closed
+    #[cfg(not(feature = "std"))]
+    pub(crate) fn check_validity(&self) -> Result<(), SignerError> {
+        match self.validity() {
+            validity if validity == Validity::default() => Ok(()),
+            _ => Err(SignerError::ContainerNotValid(
+                "validity window cannot be checked without a clock".into(),
+            )),
+        }
+    }
+
+    /// Create a container whose key can only be used inside `validity`
+    pub fn encrypt_with_validity(
+        private_key: &[u8],
+        passphrase: &str,
+        cipher: Cipher,
+        kdf: KdfParams,
+        validity: Validity,
+    ) -> Result<Self, SignerError> {
+        let sealing = Sealing {
+            validity,
+            ..Sealing::new(cipher, kdf)
+        };
+        Self::seal(private_key, passphrase, &sealing)
+    }
+
+    /// Re-encrypt the container with a new validity window
+    ///
+    /// Works on a container outside its window, so an expired key can be
+    /// extended by whoever holds the passphrase. The result is a version 3
+    /// container with the same cipher, Argon2id parameters (or the defaults
+    /// for an imported scrypt or PBKDF2 container), and passphrase, and a
+    /// new [`container_id`](EncryptedKeyContainer::container_id).
+    pub fn with_validity(&self, passphrase: &str, validity: Validity) -> Result<Self, SignerError> {
+        let kdf = match self.kdf {
+            Kdf::Argon2id(params) => params,
+            Kdf::Scrypt(_) | Kdf::Pbkdf2Sha256(_) => KdfParams::default(),
+        };
+        let sealing = Sealing {
+            with_public_key: self.public_key.is_some(),
+            validity,
+            ..Sealing::new(self.cipher, kdf)
+        };
+
+        let mut unlock_key = self.derive_unlock_key(passphrase)?;
+        let key = self.decrypt_key_at_any_time(&unlock_key);
+        unlock_key.zeroize();
+
+        let mut key = key?;
+        let container = Self::seal(key.as_slice(), passphrase, &sealing);
+        key.zeroize();
+        container
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_window_is_enforced_and_authenticated() {
+        std::env::set_var("SIGNER_ALLOW_INSECURE_MEMORY", "1");
+        let now = unix_now();
+        let expired = Validity::until(now - 60);
+        let container =
+            EncryptedKeyContainer::encrypt_with_validity(&[2u8; 32], "pw", Cipher::Aes256Gcm, KdfParams::MINIMUM, expired)
+                .unwrap();
+        let json = container.to_json().unwrap();
+        assert!(json.contains(&format!("\"not_after\":{}", now - 60)));
+        assert!(matches!(
+            crate::decrypt_and_sign(&json, "pw", b"message"),
+            Err(SignerError::ContainerNotValid(_))
+        ));
+
+        // Removing or moving the bound breaks authentication
+        let mut tampered = container.clone();
+        tampered.not_after = None;
+        assert!(matches!(
+            crate::decrypt_and_sign(&tampered.to_json().unwrap(), "pw", b"message"),
+            Err(SignerError::DecryptionFailed)
+        ));
+
+        // The passphrase holder can extend it, and the binary form keeps it
+        let extended = container.with_validity("pw", Validity::until(now + 3600)).unwrap();
+        assert!(container.with_validity("wrong", Validity::default()).is_err());
+        let extended = EncryptedKeyContainer::from_bytes(&extended.to_bytes().unwrap()).unwrap();
+        assert_eq!(extended.validity(), Validity::until(now + 3600));
+        assert!(crate::decrypt_and_sign(&extended.to_json().unwrap(), "pw", &crate::crypto::test_transaction(&[2u8; 32])).is_ok());
+
+        let later = Validity {
+            not_before: Some(now + 3600),
+            not_after: None,
+        };
+        assert!(later.check(now).is_err());
+        assert!(later.check(now + 3600).is_ok());
+    }
+}
@@ -0,0 +1,375 @@
+//! Vault of encrypted key containers
+//!
+//! A vault holds many [`EncryptedKeyContainer`]s indexed by
+//! [`EncryptedKeyContainer::container_id`]. Importing a container that is
+//! already present is a no-op, so re-running an import (or importing the
+//! same backup twice) never creates duplicate entries.
+//!
+//! # Storage
+//!
+//! # Derivation Registry
+//!
+//! For containers used as HD seeds, each entry records which derivation
+//! paths have been handed out and to whom. [`Vault::reserve_next_account`]
+//! returns the lowest account whose path no integration holds yet, so two
+//! integrations never end up sharing an address. Presets that map to the
+//! same path (e.g. the first MetaMask and Ledger Live Ethereum accounts)
+//! count as the same account.
+//!
+//! Like the idempotency store, a vault is either in-memory or a single JSON
+//! file rewritten with write-to-temp-then-rename. Containers are stored as
+//! they are (still encrypted); the vault itself holds no secrets.
+
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::crypto::EncryptedKeyContainer;
+use crate::error::SignerError;
+use crate::hd::DerivationPreset;
+
+/// A container stored in a vault
+#[derive(Serialize, Deserialize, Clone)]
+pub struct VaultEntry {
+    /// Container ID
+    pub id: String,
+    /// Optional human-readable label
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// Unix timestamp (seconds) when the container was imported
+    pub added_at: u64,
+    /// The encrypted container
+    pub container: EncryptedKeyContainer,
+    /// Derivation paths handed out from this container's seed
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub derivations: Vec<DerivationRecord>,
+}
+
+/// A derivation path reserved by an integration
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct DerivationRecord {
+    /// Wallet path convention
+    pub preset: DerivationPreset,
+    /// Account index within the preset
+    pub index: u32,
+    /// The full path, e.g. `m/44'/501'/3'/0'`
+    pub path: String,
+    /// Who the account was handed to
+    pub consumer: String,
+    /// Unix timestamp (seconds) of the reservation
+    pub reserved_at: u64,
+}
+
+/// Result of [`Vault::import`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ImportOutcome {
+    /// The container was added under this ID
+    Added(String),
+    /// A container with this ID was already present; nothing changed
+    Duplicate(String),
+}
+
+impl ImportOutcome {
+    /// ID of the imported (or already present) container
+    pub fn id(&self) -> &str {
+        match self {
+            ImportOutcome::Added(id) | ImportOutcome::Duplicate(id) => id,
+        }
+    }
+}
+
+/// On-disk representation of a vault
+#[derive(Serialize, Deserialize, Default)]
+struct VaultFile {
+    version: u8,
+    entries: Vec<VaultEntry>,
+}
+
+/// A collection of containers keyed by container ID
+pub struct Vault {
+    path: Option<PathBuf>,
+    entries: Vec<VaultEntry>,
+}
+
+impl Vault {
+    /// Create a vault that lives only in memory
+    pub fn in_memory() -> Self {
+        Self {
+            path: None,
+            entries: Vec::new(),
+        }
+    }
+
+    /// Open (or create) a vault persisted at `path`
+    ///
+    /// Every stored ID is recomputed from its container; a mismatch means
+    /// the file was edited and is rejected.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, SignerError> {
+        let path = path.as_ref().to_path_buf();
+        let mut vault = Self::in_memory();
+
+        if path.exists() {
+            let data = std::fs::read_to_string(&path)?;
+            let file: VaultFile = serde_json::from_str(&data)?;
+            for entry in &file.entries {
+                if entry.container.container_id()? != entry.id {
+                    return Err(SignerError::ContainerError(format!(
+                        "vault entry {} does not match its container",
+                        entry.id
+                    )));
+                }
+            }
+            vault.entries = file.entries;
+        }
+
+        vault.path = Some(path);
+        Ok(vault)
+    }
+
+    /// Number of containers
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Check if the vault holds no containers
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// All entries, in import order
+    pub fn entries(&self) -> &[VaultEntry] {
+        &self.entries
+    }
+
+    /// Look up a container by ID
+    pub fn get(&self, id: &str) -> Option<&EncryptedKeyContainer> {
+        self.entries.iter().find(|e| e.id == id).map(|e| &e.container)
+    }
+
+    /// IDs of all containers for a public key (base58 or address)
+    ///
+    /// The same key encrypted twice (different salts) has two IDs.
+    pub fn ids_for_public_key<'a>(&'a self, public_key: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.entries
+            .iter()
+            .filter(move |e| e.container.public_key.as_deref() == Some(public_key))
+            .map(|e| e.id.as_str())
+    }
+
+    /// Import a container, skipping it if its ID is already present
+    pub fn import(
+        &mut self,
+        container: EncryptedKeyContainer,
+        label: Option<&str>,
+    ) -> Result<ImportOutcome, SignerError> {
+        let id = container.container_id()?;
+        if self.get(&id).is_some() {
+            return Ok(ImportOutcome::Duplicate(id));
+        }
+
+        self.entries.push(VaultEntry {
+            id: id.clone(),
+            label: label.map(str::to_string),
+            added_at: unix_now(),
+            container,
+            derivations: Vec::new(),
+        });
+        self.persist()?;
+        Ok(ImportOutcome::Added(id))
+    }
+
+    /// Remove a container, returning whether it was present
+    pub fn remove(&mut self, id: &str) -> Result<bool, SignerError> {
+        let before = self.entries.len();
+        self.entries.retain(|e| e.id != id);
+        if self.entries.len() == before {
+            return Ok(false);
+        }
+        self.persist()?;
+        Ok(true)
+    }
+
+    /// Derivation paths reserved from a container, in reservation order
+    pub fn derivations(&self, id: &str) -> &[DerivationRecord] {
+        self.entries
+            .iter()
+            .find(|e| e.id == id)
+            .map_or(&[], |e| &e.derivations)
+    }
+
+    /// Reserve a specific account, failing if its path is already taken
+    pub fn reserve_account(
+        &mut self,
+        id: &str,
+        preset: DerivationPreset,
+        index: u32,
+        consumer: &str,
+    ) -> Result<DerivationRecord, SignerError> {
+        let path = preset.path(index)?.to_string();
+        if let Some(existing) = self.derivations(id).iter().find(|r| r.path == path) {
+            return Err(SignerError::DerivationError(format!(
+                "{} is already reserved by {}",
+                path, existing.consumer
+            )));
+        }
+        self.push_derivation(id, preset, index, path, consumer)
+    }
+
+    /// Reserve the lowest account of `preset` whose path is not taken
+    pub fn reserve_next_account(
+        &mut self,
+        id: &str,
+        preset: DerivationPreset,
+        consumer: &str,
+    ) -> Result<DerivationRecord, SignerError> {
+        let taken = self.derivations(id);
+        // Paths run out (with an error) at the hardened index limit
+        let mut index = 0;
+        let path = loop {
+            let path = preset.path(index)?.to_string();
+            if taken.iter().all(|r| r.path != path) {
+                break path;
+            }
+            index += 1;
+        };
+        self.push_derivation(id, preset, index, path, consumer)
+    }
+
+    fn push_derivation(
+        &mut self,
+        id: &str,
+        preset: DerivationPreset,
+        index: u32,
+        path: String,
+        consumer: &str,
+    ) -> Result<DerivationRecord, SignerError> {
+        let entry = self
+            .entries
+            .iter_mut()
+            .find(|e| e.id == id)
+            .ok_or_else(|| SignerError::ContainerError(format!("no container {} in vault", id)))?;
+
+        let record = DerivationRecord {
+            preset,
+            index,
+            path,
+            consumer: consumer.to_string(),
+            reserved_at: unix_now(),
+        };
+        entry.derivations.push(record.clone());
+        self.persist()?;
+        Ok(record)
+    }
+
+    fn persist(&self) -> Result<(), SignerError> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        let file = VaultFile {
+            version: 1,
+            entries: self.entries.clone(),
+        };
+        let json = serde_json::to_string(&file)?;
+
+        let tmp_path = path.with_extension("tmp");
+        std::fs::write(&tmp_path, json)?;
+        std::fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::Cipher;
+    use crate::kdf::KdfParams;
+
+    fn container(seed: u8) -> EncryptedKeyContainer {
+        EncryptedKeyContainer::encrypt_with_kdf(&[seed; 32], "pw", Cipher::Aes256Gcm, KdfParams::MINIMUM).unwrap()
+    }
+
+    #[test]
+    fn test_container_id_is_stable() {
+        let c = container(1);
+        let id = c.container_id().unwrap();
+        assert_eq!(id.len(), 32);
+
+        let reparsed = EncryptedKeyContainer::from_json(&c.to_json().unwrap()).unwrap();
+        assert_eq!(reparsed.container_id().unwrap(), id);
+
+        // Same key, new salt
+        assert_ne!(container(1).container_id().unwrap(), id);
+
+        let mut anonymous = c.clone();
+        anonymous.public_key = None;
+        assert!(anonymous.container_id().is_err());
+    }
+
+    #[test]
+    fn test_import_deduplicates_and_persists() {
+        let path = std::env::temp_dir().join(format!("coldstar-vault-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let a = container(2);
+        let b = container(3);
+        let mut vault = Vault::open(&path).unwrap();
+
+        let first = vault.import(a.clone(), Some("hot")).unwrap();
+        assert!(matches!(first, ImportOutcome::Added(_)));
+        assert_eq!(vault.import(a.clone(), None).unwrap(), ImportOutcome::Duplicate(first.id().to_string()));
+        vault.import(b, None).unwrap();
+
+        let reopened = Vault::open(&path).unwrap();
+        assert_eq!(reopened.len(), 2);
+        assert!(reopened.get(first.id()).is_some());
+        assert_eq!(
+            reopened.ids_for_public_key(a.public_key.as_deref().unwrap()).collect::<Vec<_>>(),
+            vec![first.id()]
+        );
+
+        let mut vault = reopened;
+        assert!(vault.remove(first.id()).unwrap());
+        assert!(!vault.remove(first.id()).unwrap());
+        assert_eq!(Vault::open(&path).unwrap().len(), 1);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_derivation_registry_hands_out_unused_accounts() {
+        let path = std::env::temp_dir().join(format!("coldstar-vault-hd-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let mut vault = Vault::open(&path).unwrap();
+        let id = vault.import(container(4), Some("seed")).unwrap().id().to_string();
+
+        let first = vault.reserve_next_account(&id, DerivationPreset::Solana, "payments").unwrap();
+        assert_eq!((first.index, first.path.as_str()), (0, "m/44'/501'/0'/0'"));
+        vault.reserve_account(&id, DerivationPreset::Solana, 2, "treasury").unwrap();
+        assert!(vault.reserve_account(&id, DerivationPreset::Solana, 2, "airdrop").is_err());
+        assert_eq!(vault.reserve_next_account(&id, DerivationPreset::Solana, "airdrop").unwrap().index, 1);
+        assert_eq!(vault.reserve_next_account(&id, DerivationPreset::Solana, "airdrop").unwrap().index, 3);
+
+        // The first Ledger Live Ethereum account is MetaMask's first account
+        vault.reserve_next_account(&id, DerivationPreset::Ethereum, "evm").unwrap();
+        let ledger = vault.reserve_next_account(&id, DerivationPreset::EthereumLedgerLive, "ledger").unwrap();
+        assert_eq!(ledger.path, "m/44'/60'/1'/0/0");
+
+        assert!(vault.reserve_next_account("missing", DerivationPreset::Solana, "x").is_err());
+
+        let reopened = Vault::open(&path).unwrap();
+        assert_eq!(reopened.derivations(&id).len(), 6);
+        assert_eq!(reopened.derivations(&id)[1].consumer, "treasury");
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
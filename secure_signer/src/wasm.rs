@@ -0,0 +1,67 @@
+//! JavaScript bindings for `wasm32-unknown-unknown`
+//!
+//! Browser-extension wallets load the same container format and signing
+//! code as the native signer:
+//!
+//! ```js
+//! import init, { encrypt, decryptAndSign, decryptAndSignEvm } from "./coldstar_secure_signer.js";
+//! await init();
+//! const container = encrypt(seed, passphrase);             // container JSON
+//! const result = JSON.parse(decryptAndSign(container, passphrase, message));
+//! const evm = JSON.parse(decryptAndSignEvm(container, passphrase, hash));
+//! ```
+//!
+//! Results are the same JSON documents the CLI and FFI return; errors are
+//! thrown as `Error`s carrying the [`SignerError`](crate::SignerError)
+//! message.
+//!
+//! # Memory
+//!
+//! WebAssembly linear memory cannot be locked or guarded, so keys here live
+//! in best-effort buffers: zeroized on drop, but never locked, and visible
+//! to anything that can read the module's memory (including the page's own
+//! scripts). Randomness comes from `crypto.getRandomValues`.
+
+use wasm_bindgen::prelude::*;
+
+use crate::crypto;
+use crate::error::SignerError;
+
+fn js_error(error: SignerError) -> JsError {
+    JsError::new(&error.to_string())
+}
+
+/// Encrypt a 32-byte seed (or 64-byte keypair) into a container, returned
+/// as JSON
+#[wasm_bindgen]
+pub fn encrypt(private_key: &[u8], passphrase: &str) -> Result<String, JsError> {
+    crypto::create_encrypted_key_container(private_key, passphrase).map_err(js_error)
+}
+
+/// Decrypt a container and sign a Solana transaction or message
+///
+/// Returns the signing result as JSON (`signature`, `public_key`, and
+/// `signed_transaction` when `message` is a transaction).
+#[wasm_bindgen(js_name = decryptAndSign)]
+pub fn decrypt_and_sign(container_json: &str, passphrase: &str, message: &[u8]) -> Result<String, JsError> {
+    let result = crypto::decrypt_and_sign(container_json, passphrase, message).map_err(js_error)?;
+    serde_json::to_string(&result).map_err(|e| js_error(e.into()))
+}
+
+/// Decrypt a container and sign an off-chain message (no transaction checks)
+///
+/// Returns the signing result as JSON (`signature`, `public_key`).
+#[wasm_bindgen(js_name = decryptAndSignMessage)]
+pub fn decrypt_and_sign_message(container_json: &str, passphrase: &str, message: &[u8]) -> Result<String, JsError> {
+    let result = crypto::decrypt_and_sign_message(container_json, passphrase, message).map_err(js_error)?;
+    serde_json::to_string(&result).map_err(|e| js_error(e.into()))
+}
+
+/// Decrypt a container and sign a 32-byte EVM hash
+///
+/// Returns the signing result as JSON (`signature`, `address`, `v`).
+#[wasm_bindgen(js_name = decryptAndSignEvm)]
+pub fn decrypt_and_sign_evm(container_json: &str, passphrase: &str, message_hash: &[u8]) -> Result<String, JsError> {
+    let result = crypto::decrypt_and_sign_evm(container_json, passphrase, message_hash).map_err(js_error)?;
+    serde_json::to_string(&result).map_err(|e| js_error(e.into()))
+}
@@ -0,0 +1,227 @@
+//! Auto-lock unlocked sessions after a period of inactivity
+//!
+//! An agent that keeps a key unlocked for convenience should not keep it
+//! unlocked forever because someone forgot about it. [`IdleWatchdog`] holds
+//! the session's secret state and drops it (zeroizing it, for types such as
+//! [`SecureBuffer`](crate::SecureBuffer)) once it has not been used for the
+//! configured idle period. Every use resets the timer.
+//!
+//! The idle period is independent of any absolute lifetime a session has:
+//! a session with a one-hour TTL ([`IdleWatchdog::expire_after`]) and a
+//! five-minute idle period locks after five minutes without activity, or
+//! after an hour at the latest.
+//!
+//! When given an [`AuditLog`], the watchdog records a
+//! [`AuditEvent::SessionLocked`] entry when it locks a session.
+
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use crate::audit::{AuditEvent, AuditLog};
+use crate::error::SignerError;
+
+struct State<T> {
+    value: Option<T>,
+    last_used: Instant,
+    expires_at: Option<Instant>,
+    stopped: bool,
+    audit: Option<(Arc<Mutex<AuditLog>>, String)>,
+}
+
+struct Shared<T> {
+    state: Mutex<State<T>>,
+    wake: Condvar,
+}
+
+impl<T> Shared<T> {
+    fn lock(&self) -> MutexGuard<'_, State<T>> {
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Session state that is dropped after `idle_timeout` without use
+pub struct IdleWatchdog<T: Send + 'static> {
+    shared: Arc<Shared<T>>,
+    idle_timeout: Duration,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl<T: Send + 'static> IdleWatchdog<T> {
+    /// Hold `value` until it has been idle for `idle_timeout`
+    pub fn new(value: T, idle_timeout: Duration) -> Self {
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State {
+                value: Some(value),
+                last_used: Instant::now(),
+                expires_at: None,
+                stopped: false,
+                audit: None,
+            }),
+            wake: Condvar::new(),
+        });
+
+        let thread_shared = Arc::clone(&shared);
+        let thread = std::thread::spawn(move || watch(&thread_shared, idle_timeout));
+
+        Self {
+            shared,
+            idle_timeout,
+            thread: Some(thread),
+        }
+    }
+
+    /// Record an audit event under `session` when the watchdog locks
+    pub fn audit_to(self, log: Arc<Mutex<AuditLog>>, session: &str) -> Self {
+        self.set_audit(log, session);
+        self
+    }
+
+    pub(crate) fn set_audit(&self, log: Arc<Mutex<AuditLog>>, session: &str) {
+        self.shared.lock().audit = Some((log, session.to_string()));
+    }
+
+    /// Also drop the value `ttl` from now, however recently it was used
+    pub fn expire_after(self, ttl: Duration) -> Self {
+        self.shared.lock().expires_at = Some(Instant::now() + ttl);
+        self.shared.wake.notify_all();
+        self
+    }
+
+    /// Use the value and reset the idle timer
+    ///
+    /// Fails with [`SignerError::SessionLocked`] once the session has locked.
+    pub fn with<R>(&self, f: impl FnOnce(&mut T) -> R) -> Result<R, SignerError> {
+        let mut state = self.shared.lock();
+        let state = &mut *state;
+        // Not waiting for the watchdog thread to notice
+        if state.expires_at.is_some_and(|expires_at| Instant::now() >= expires_at) {
+            return Err(SignerError::SessionLocked("session expired".to_string()));
+        }
+        let value = state
+            .value
+            .as_mut()
+            .ok_or_else(|| SignerError::SessionLocked("locked after inactivity".to_string()))?;
+        let result = f(value);
+        state.last_used = Instant::now();
+        Ok(result)
+    }
+
+    /// Reset the idle timer without using the value
+    pub fn touch(&self) {
+        let mut state = self.shared.lock();
+        if state.value.is_some() {
+            state.last_used = Instant::now();
+        }
+    }
+
+    /// Time since the value was last used
+    pub fn idle_for(&self) -> Duration {
+        self.shared.lock().last_used.elapsed()
+    }
+
+    /// The configured idle period
+    pub fn idle_timeout(&self) -> Duration {
+        self.idle_timeout
+    }
+
+    /// Time left before the TTL expires, if one is set
+    pub fn expires_in(&self) -> Option<Duration> {
+        self.shared
+            .lock()
+            .expires_at
+            .map(|expires_at| expires_at.saturating_duration_since(Instant::now()))
+    }
+
+    /// Whether the value has been dropped (or its TTL has passed)
+    pub fn is_locked(&self) -> bool {
+        let state = self.shared.lock();
+        state.value.is_none() || state.expires_at.is_some_and(|expires_at| Instant::now() >= expires_at)
+    }
+
+    /// Drop the value now, without an audit event
+    pub fn lock(&self) {
+        self.shared.lock().value.take();
+        self.shared.wake.notify_all();
+    }
+}
+
+impl<T: Send + 'static> Drop for IdleWatchdog<T> {
+    fn drop(&mut self) {
+        self.shared.lock().stopped = true;
+        self.shared.wake.notify_all();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn watch<T>(shared: &Shared<T>, idle_timeout: Duration) {
+    let mut state = shared.lock();
+    let reason = loop {
+        if state.stopped || state.value.is_none() {
+            return;
+        }
+        let idle = state.last_used.elapsed();
+        if idle >= idle_timeout {
+            break "idle";
+        }
+        let mut wait = idle_timeout - idle;
+        if let Some(expires_at) = state.expires_at {
+            let now = Instant::now();
+            if now >= expires_at {
+                break "ttl";
+            }
+            wait = wait.min(expires_at - now);
+        }
+        state = shared
+            .wake
+            .wait_timeout(state, wait)
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .0;
+    };
+
+    let value = state.value.take();
+    let audit = state.audit.clone();
+    let idle = state.last_used.elapsed();
+    drop(state);
+    drop(value);
+
+    if let Some((log, session)) = audit {
+        let event = AuditEvent::SessionLocked {
+            session,
+            reason: reason.to_string(),
+            idle_seconds: idle.as_secs(),
+        };
+        // The session is locked either way; a failed write cannot undo that
+        let _ = log.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).append(event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_idle_session_locks_and_is_audited() {
+        let log = Arc::new(Mutex::new(AuditLog::in_memory()));
+        let session = IdleWatchdog::new(vec![7u8; 32], Duration::from_secs(1)).audit_to(Arc::clone(&log), "agent");
+
+        // Activity keeps the session open past the idle period
+        for _ in 0..6 {
+            std::thread::sleep(Duration::from_millis(250));
+            assert_eq!(session.with(|key| key[0]).unwrap(), 7);
+        }
+
+        std::thread::sleep(Duration::from_millis(2500));
+        assert!(session.is_locked());
+        assert!(matches!(session.with(|_| ()), Err(SignerError::SessionLocked(_))));
+
+        let log = log.lock().unwrap();
+        assert_eq!(log.entries().len(), 1);
+        assert!(matches!(
+            &log.entries()[0].event,
+            AuditEvent::SessionLocked { session, reason, .. } if session == "agent" && reason == "idle"
+        ));
+    }
+}
@@ -0,0 +1,282 @@
+//! Web3Signer-compatible HTTP signing API
+//!
+//! Serves the eth1 endpoints of Consensys Web3Signer, so validators,
+//! relayers and other infrastructure already configured for it can sign
+//! with coldstar containers instead:
+//!
+//! - `GET /upcheck`: `OK`
+//! - `GET /api/v1/eth1/publicKeys`: JSON array of the loaded keys, each
+//!   the 64-byte uncompressed secp256k1 public key as 0x-prefixed hex
+//! - `POST /api/v1/eth1/sign/{publicKey}` with `{"data": "0x..."}`: the
+//!   recoverable signature of `keccak256(data)` as 0x-prefixed hex
+//!   (`r || s || v`, `v` 27 or 28), as `text/plain`
+//!
+//! Keys are decrypted once when loaded and held as
+//! [`SecureSecp256k1Scalar`]s until the server is dropped. The server
+//! speaks plain HTTP/1.1 with one request per connection; bind it to
+//! loopback or put it behind a TLS-terminating proxy, exactly as
+//! Web3Signer itself is deployed.
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use serde::Deserialize;
+use sha3::{Digest, Keccak256};
+
+use crate::crypto::EncryptedKeyContainer;
+use crate::encoding::Encoding;
+use crate::error::SignerError;
+use crate::keys::SecureSecp256k1Scalar;
+
+/// Most connections served at once
+pub const MAX_CONNECTIONS: usize = 64;
+
+/// Largest accepted request body
+const MAX_BODY: usize = 1024 * 1024;
+
+/// Largest accepted request line and headers
+const MAX_HEAD: usize = 64 * 1024;
+
+/// How long a client may take to send its request
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// An HTTP response
+#[derive(Debug, PartialEq, Eq)]
+struct Reply {
+    status: u16,
+    content_type: &'static str,
+    body: String,
+}
+
+impl Reply {
+    fn text(status: u16, body: impl Into<String>) -> Self {
+        Self {
+            status,
+            content_type: "text/plain; charset=utf-8",
+            body: body.into(),
+        }
+    }
+
+    fn json(body: String) -> Self {
+        Self {
+            status: 200,
+            content_type: "application/json",
+            body,
+        }
+    }
+
+    fn reason(&self) -> &'static str {
+        match self.status {
+            200 => "OK",
+            400 => "Bad Request",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            413 => "Payload Too Large",
+            _ => "Internal Server Error",
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct SignRequest {
+    data: String,
+}
+
+/// Loaded keys and the HTTP front end over them
+#[derive(Default)]
+pub struct Web3Signer {
+    /// Lowercase hex public key (64 bytes, no prefix byte) and its key
+    keys: Vec<(String, SecureSecp256k1Scalar)>,
+    connections: AtomicUsize,
+}
+
+impl Web3Signer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Decrypt a container's key for signing, returning its public key as
+    /// the API identifies it
+    pub fn add_key(&mut self, container: &EncryptedKeyContainer, passphrase: &str) -> Result<String, SignerError> {
+        let scalar = SecureSecp256k1Scalar::from_container(container, passphrase)?;
+        let public_key = hex::encode(&scalar.public_key()[1..]);
+        if !self.keys.iter().any(|(existing, _)| *existing == public_key) {
+            self.keys.push((public_key.clone(), scalar));
+        }
+        Ok(format!("0x{}", public_key))
+    }
+
+    /// Public keys of the loaded keys, as listed by the API
+    pub fn public_keys(&self) -> Vec<String> {
+        self.keys.iter().map(|(public_key, _)| format!("0x{}", public_key)).collect()
+    }
+
+    /// Serve requests until `listener` fails
+    pub fn serve(&self, listener: TcpListener) -> Result<(), SignerError> {
+        std::thread::scope(|scope| {
+            for stream in listener.incoming() {
+                let stream = match stream {
+                    Ok(stream) => stream,
+                    Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                    Err(e) => return Err(SignerError::from(e)),
+                };
+                if self.connections.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+                    self.connections.fetch_sub(1, Ordering::SeqCst);
+                    continue;
+                }
+                scope.spawn(move || {
+                    let _ = self.handle_connection(stream);
+                    self.connections.fetch_sub(1, Ordering::SeqCst);
+                });
+            }
+            Ok(())
+        })
+    }
+
+    fn handle_connection(&self, stream: TcpStream) -> Result<(), SignerError> {
+        stream.set_read_timeout(Some(READ_TIMEOUT))?;
+        let mut writer = stream.try_clone()?;
+        let limited = stream.take((MAX_HEAD + MAX_BODY) as u64);
+        let reply = match read_request(&mut BufReader::new(limited)) {
+            Ok((method, path, body)) => self.handle(&method, &path, &body),
+            Err(reply) => reply,
+        };
+        write!(
+            writer,
+            "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            reply.status,
+            reply.reason(),
+            reply.content_type,
+            reply.body.len(),
+            reply.body
+        )?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    fn handle(&self, method: &str, path: &str, body: &[u8]) -> Reply {
+        let path = path.split('?').next().unwrap_or(path);
+        match (method, path) {
+            ("GET", "/upcheck") => Reply::text(200, "OK"),
+            ("GET", "/api/v1/eth1/publicKeys") => match serde_json::to_string(&self.public_keys()) {
+                Ok(json) => Reply::json(json),
+                Err(e) => Reply::text(500, e.to_string()),
+            },
+            ("POST", path) if path.starts_with("/api/v1/eth1/sign/") => {
+                self.sign(&path["/api/v1/eth1/sign/".len()..], body)
+            }
+            (_, "/upcheck" | "/api/v1/eth1/publicKeys") => Reply::text(405, "Method not allowed"),
+            _ => Reply::text(404, "Not found"),
+        }
+    }
+
+    fn sign(&self, identifier: &str, body: &[u8]) -> Reply {
+        let identifier = identifier.trim_start_matches("0x").to_ascii_lowercase();
+        let identifier = identifier.strip_prefix("04").filter(|rest| rest.len() == 128).unwrap_or(&identifier);
+        let scalar = match self.keys.iter().find(|(public_key, _)| public_key == identifier) {
+            Some((_, scalar)) => scalar,
+            None => return Reply::text(404, "Public Key not found"),
+        };
+
+        let data = match serde_json::from_slice::<SignRequest>(body)
+            .map_err(|e| SignerError::SerializationError(e.to_string()))
+            .and_then(|request| Encoding::Hex.decode(&request.data))
+        {
+            Ok(data) => data,
+            Err(e) => return Reply::text(400, e.to_string()),
+        };
+
+        match scalar.sign_prehash(&Keccak256::digest(&data).into()) {
+            Ok(result) => Reply::text(200, result.signature),
+            Err(e) => Reply::text(500, e.to_string()),
+        }
+    }
+}
+
+/// Read the request line, headers and body, or the reply to refuse it with
+fn read_request(reader: &mut impl BufRead) -> Result<(String, String, Vec<u8>), Reply> {
+    let bad_request = |message: &str| Reply::text(400, message);
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).map_err(|_| bad_request("unreadable request"))?;
+    let mut parts = request_line.split_whitespace();
+    let (method, path) = match (parts.next(), parts.next()) {
+        (Some(method), Some(path)) => (method.to_string(), path.to_string()),
+        _ => return Err(bad_request("malformed request line")),
+    };
+
+    let mut content_length = 0usize;
+    loop {
+        let mut header = String::new();
+        reader.read_line(&mut header).map_err(|_| bad_request("unreadable headers"))?;
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().map_err(|_| bad_request("invalid Content-Length"))?;
+            }
+        }
+    }
+    if content_length > MAX_BODY {
+        return Err(Reply::text(413, "request body too large"));
+    }
+
+    let mut body = vec![0u8; content_length];
+    reader.read_exact(&mut body).map_err(|_| bad_request("truncated body"))?;
+    Ok((method, path, body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kdf::KdfParams;
+    use crate::Cipher;
+    use k256::ecdsa::{RecoveryId, Signature, VerifyingKey};
+
+    #[test]
+    fn test_eth1_sign_and_public_keys() {
+        std::env::set_var("SIGNER_ALLOW_INSECURE_MEMORY", "1");
+        let container =
+            EncryptedKeyContainer::encrypt_with_kdf(&[5u8; 32], "pw", Cipher::Aes256Gcm, KdfParams::MINIMUM).unwrap();
+        let mut signer = Web3Signer::new();
+        let public_key = signer.add_key(&container, "pw").unwrap();
+        assert_eq!(public_key.len(), 2 + 128);
+
+        let listed = signer.handle("GET", "/api/v1/eth1/publicKeys", b"");
+        assert_eq!(listed.body, format!("[\"{}\"]", public_key));
+
+        let reply = signer.handle(
+            "POST",
+            &format!("/api/v1/eth1/sign/{}", public_key.to_uppercase().replace("0X", "0x")),
+            br#"{"data":"0x48656c6c6f"}"#,
+        );
+        assert_eq!(reply.status, 200, "{}", reply.body);
+
+        // The signature recovers to the listed key over keccak256(data)
+        let signature = hex::decode(reply.body.trim_start_matches("0x")).unwrap();
+        let recovered = VerifyingKey::recover_from_prehash(
+            &Keccak256::digest(b"Hello"),
+            &Signature::from_slice(&signature[..64]).unwrap(),
+            RecoveryId::from_byte(signature[64] - 27).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(format!("0x{}", hex::encode(&recovered.to_encoded_point(false).as_bytes()[1..])), public_key);
+
+        assert_eq!(signer.handle("POST", "/api/v1/eth1/sign/0x1234", br#"{"data":"0x00"}"#).status, 404);
+        assert_eq!(signer.handle("GET", "/upcheck", b"").body, "OK");
+    }
+
+    #[test]
+    fn test_request_parsing() {
+        let raw = b"POST /api/v1/eth1/sign/0xab HTTP/1.1\r\nHost: x\r\ncontent-length: 4\r\n\r\nbodyextra";
+        let (method, path, body) = read_request(&mut &raw[..]).unwrap();
+        assert_eq!((method.as_str(), path.as_str(), body.as_slice()), ("POST", "/api/v1/eth1/sign/0xab", &b"body"[..]));
+
+        let huge = format!("POST / HTTP/1.1\r\nContent-Length: {}\r\n\r\n", MAX_BODY + 1);
+        assert_eq!(read_request(&mut huge.as_bytes()).unwrap_err().status, 413);
+    }
+}